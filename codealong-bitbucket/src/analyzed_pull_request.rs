@@ -0,0 +1,431 @@
+use chrono::prelude::*;
+use chrono::DateTime;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::iter::FromIterator;
+
+use codealong::{
+    metric_versions, AnalyzedDiff, Calendar, Config, Event, Hotspots, MetricVersions, Person,
+    PersonSnapshots, ReviewStats, Risk, SloSubject,
+};
+
+use crate::comment::Comment;
+use crate::error::Result;
+use crate::pull_request::{Endpoint, PullRequest};
+
+/// A Bitbucket pull request indexed as a `pull_request` event, with the same
+/// fields as those of Github pull requests, so that dashboards need not care
+/// which forge the data came from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnalyzedPullRequest {
+    timestamp: DateTime<Utc>,
+
+    normalized_author: Person,
+
+    /// Id of the normalized author, shared with their commits.
+    author_id: String,
+
+    #[serde(flatten)]
+    pr: PullRequestFields,
+
+    #[serde(flatten)]
+    pub diff: Option<AnalyzedDiff>,
+
+    pub time_to_resolve: Option<i64>,
+
+    /// Working seconds between creation and merge according to the
+    /// configured business calendar.
+    pub business_time_to_resolve: Option<i64>,
+
+    pub business_days_to_resolve: Option<f64>,
+
+    #[serde(flatten, default)]
+    pub review_stats: ReviewStats,
+
+    /// Risk of the changes when assessed against the hotspots of the repo.
+    #[serde(default)]
+    pub risk: Option<Risk>,
+
+    /// References to tickets and issues in the title and body, e.g.
+    /// `ABC-123`.
+    #[serde(default)]
+    pub issues: Vec<String>,
+
+    #[serde(default)]
+    pub metric_versions: MetricVersions,
+}
+
+/// How `pr` was reviewed. Bitbucket only keeps the latest review of each
+/// participant, so a participant counts as a single review. Reviews are
+/// those of the `participants` of `pr`, which are only listed when it is
+/// requested on its own, and comments of others than the author, of which
+/// the earliest may predate any approval.
+pub fn review_stats(pr: &PullRequest, comments: &[Comment], config: &Config) -> ReviewStats {
+    let author = pr.author.username();
+    let mut stats = ReviewStats::default();
+    let mut reviewed_at = vec![];
+    for participant in pr.participants.iter().flatten() {
+        let username = participant.user.username();
+        if username == author {
+            continue;
+        }
+        let changes_requested =
+            participant.state.as_ref().map(|s| &**s) == Some("changes_requested");
+        if !participant.approved && !changes_requested {
+            continue;
+        }
+        stats.reviews += 1;
+        if participant.approved {
+            stats.approvals += 1;
+        } else {
+            stats.change_requests += 1;
+        }
+        let at = participant.participated_on.unwrap_or(pr.updated_on);
+        reviewed_at.push(at);
+        let reviewer = config.person_for_bitbucket_username_at(username, &at).id;
+        if !stats.reviewers.contains(&reviewer) {
+            stats.reviewers.push(reviewer);
+        }
+    }
+    for comment in comments {
+        let is_author = comment
+            .user
+            .as_ref()
+            .map_or(false, |user| user.username() == author);
+        if comment.deleted || is_author {
+            continue;
+        }
+        if comment.inline.is_some() {
+            stats.review_comments += 1;
+        }
+        reviewed_at.push(comment.created_on);
+    }
+    stats.first_reviewed_at = reviewed_at.into_iter().min();
+    if let Some(first_reviewed_at) = stats.first_reviewed_at {
+        stats.time_to_first_review = Some((first_reviewed_at - pr.created_on).num_seconds());
+        stats.business_time_to_first_review = Some(
+            config
+                .calendar()
+                .business_seconds(&pr.created_on, &first_reviewed_at),
+        );
+    }
+    stats
+}
+
+/// The fields of a pull request named after their Github equivalents.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct PullRequestFields {
+    /// The number of the pull request, as Bitbucket has no id across
+    /// repositories.
+    id: u64,
+    number: u64,
+    forge: String,
+    base: RefFields,
+    head: RefFields,
+    html_url: Option<String>,
+    state: Option<String>,
+    title: Option<String>,
+    body: Option<String>,
+    user: UserFields,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    closed_at: Option<DateTime<Utc>>,
+    merged_at: Option<DateTime<Utc>>,
+    merge_commit_sha: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct RefFields {
+    sha: Option<String>,
+    #[serde(rename = "ref")]
+    reference: String,
+    repo: Option<RepoFields>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct RepoFields {
+    full_name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct UserFields {
+    /// The uuid of the user, as Bitbucket has no numeric ids.
+    id: String,
+    login: String,
+    name: Option<String>,
+}
+
+impl RefFields {
+    fn new(endpoint: &Endpoint) -> RefFields {
+        RefFields {
+            sha: endpoint.commit.as_ref().map(|commit| commit.hash.clone()),
+            reference: endpoint.branch.name.clone(),
+            repo: endpoint.repository.as_ref().map(|repo| RepoFields {
+                full_name: repo.full_name.clone(),
+            }),
+        }
+    }
+}
+
+impl PullRequestFields {
+    fn new(pr: &PullRequest) -> PullRequestFields {
+        PullRequestFields {
+            id: pr.id,
+            number: pr.id,
+            forge: "bitbucket".to_owned(),
+            base: RefFields::new(&pr.destination),
+            head: RefFields::new(&pr.source),
+            html_url: Some(pr.links.html.href.clone()),
+            // Github only distinguishes open and closed pull requests
+            state: Some(if pr.is_open() { "open" } else { "closed" }.to_owned()),
+            title: Some(pr.title.clone()),
+            body: pr.description.clone().filter(|body| !body.is_empty()),
+            user: UserFields {
+                id: pr.author.uuid.clone(),
+                login: pr.author.username().to_owned(),
+                name: pr.author.display_name.clone(),
+            },
+            created_at: pr.created_on,
+            updated_at: pr.updated_on,
+            closed_at: pr.closed_on(),
+            merged_at: pr.merged_on(),
+            merge_commit_sha: pr.merge_commit.as_ref().map(|commit| commit.hash.clone()),
+        }
+    }
+}
+
+impl AnalyzedPullRequest {
+    /// `repo` is the full name of the repository the pull request targets,
+    /// for when its destination does not list it.
+    pub fn new(
+        pr: &PullRequest,
+        repo: &str,
+        diff: Option<AnalyzedDiff>,
+        normalized_author: Person,
+        calendar: &Calendar,
+    ) -> AnalyzedPullRequest {
+        let merged_on = pr.merged_on();
+        let mut fields = PullRequestFields::new(pr);
+        if fields.base.repo.is_none() {
+            fields.base.repo = Some(RepoFields {
+                full_name: repo.to_owned(),
+            });
+        }
+        AnalyzedPullRequest {
+            timestamp: merged_on.unwrap_or(pr.updated_on),
+            author_id: normalized_author.id.clone(),
+            normalized_author,
+            diff,
+            time_to_resolve: merged_on.map(|merged_on| (merged_on - pr.created_on).num_seconds()),
+            business_time_to_resolve: merged_on
+                .map(|merged_on| calendar.business_seconds(&pr.created_on, &merged_on)),
+            business_days_to_resolve: merged_on
+                .map(|merged_on| calendar.business_days(&pr.created_on, &merged_on)),
+            review_stats: ReviewStats::default(),
+            risk: None,
+            issues: vec![],
+            metric_versions: metric_versions(&[
+                codealong::LINES_CHANGED,
+                codealong::TIME_TO_RESOLVE,
+                codealong::BUSINESS_TIME_TO_RESOLVE,
+                codealong::BUSINESS_DAYS_TO_RESOLVE,
+            ]),
+            pr: fields,
+        }
+    }
+
+    /// Assesses the risk of the changes of this pull request, if they were
+    /// analyzed.
+    pub fn assess_risk(&mut self, hotspots: &Hotspots) {
+        self.risk = self.diff.as_ref().map(|diff| Risk::assess(diff, hotspots));
+    }
+
+    /// Attaches how this pull request was reviewed.
+    pub fn set_review_stats(&mut self, review_stats: ReviewStats) {
+        self.review_stats = review_stats;
+        self.metric_versions.extend(metric_versions(&[
+            codealong::TIME_TO_FIRST_REVIEW,
+            codealong::BUSINESS_TIME_TO_FIRST_REVIEW,
+        ]));
+    }
+
+    pub fn updated_at(&self) -> &DateTime<Utc> {
+        &self.pr.updated_at
+    }
+
+    /// Names the repos of the base and head after following the
+    /// `repo_aliases` of `config`, so that the pull requests of a renamed repo are
+    /// not split across its names.
+    pub fn alias_repos(&mut self, config: &Config) {
+        let repos = self
+            .pr
+            .base
+            .repo
+            .iter_mut()
+            .chain(self.pr.head.repo.iter_mut());
+        for repo in repos {
+            repo.full_name = config.canonical_repo_name(&repo.full_name).to_owned();
+        }
+    }
+
+    /// Extracts the references to tickets and issues of the title and body,
+    /// before they are stored.
+    pub fn extract_issues(&mut self, config: &Config) -> Result<()> {
+        let text: Vec<&str> = self
+            .pr
+            .title
+            .iter()
+            .chain(self.pr.body.iter())
+            .map(|t| &**t)
+            .collect();
+        self.issues = config.issue_references.extract(&text.join("\n\n"))?;
+        Ok(())
+    }
+
+    /// Truncates, hashes or drops the title and body according to the
+    /// `summary_max_length` and `message_storage` of `config`.
+    pub fn store_messages(&mut self, config: &Config) {
+        self.pr.title = self.pr.title.take().and_then(|t| config.stored_summary(&t));
+        self.pr.body = self.pr.body.take().and_then(|b| config.stored_message(&b));
+    }
+
+    /// Records the author's work on this pull request in `snapshots`.
+    pub fn add_to_snapshots(&self, snapshots: &mut PersonSnapshots) {
+        if let Some(ref repo) = self.pr.base.repo {
+            snapshots.add_pull_request(
+                &self.normalized_author,
+                &self.timestamp,
+                &repo.full_name,
+                &self.pr.head.reference,
+                self.pr.number,
+                self.time_to_resolve,
+            );
+        }
+    }
+}
+
+impl SloSubject for AnalyzedPullRequest {
+    fn diff(&self) -> Option<&AnalyzedDiff> {
+        self.diff.as_ref()
+    }
+
+    fn time_to_resolve(&self) -> Option<i64> {
+        self.time_to_resolve
+    }
+
+    fn business_time_to_resolve(&self) -> Option<i64> {
+        self.business_time_to_resolve
+    }
+
+    fn review_stats(&self) -> &ReviewStats {
+        &self.review_stats
+    }
+
+    fn owner(&self) -> &Person {
+        &self.normalized_author
+    }
+
+    fn url(&self) -> Option<String> {
+        self.pr.html_url.clone()
+    }
+
+    fn repo(&self) -> Option<String> {
+        self.pr.base.repo.as_ref().map(|r| r.full_name.clone())
+    }
+}
+
+impl Event for AnalyzedPullRequest {
+    fn timestamp(&self) -> &DateTime<Utc> {
+        &self.timestamp
+    }
+
+    /// Prefixed with the forge and repository, as pull requests are only
+    /// numbered within their repository.
+    fn id(&self) -> Cow<str> {
+        let repo = self.pr.base.repo.as_ref().map_or("", |r| &r.full_name);
+        format!("bitbucket-{}-{}", repo, self.pr.number).into()
+    }
+
+    fn event_type(&self) -> &str {
+        "pull_request"
+    }
+
+    fn tags(&self) -> HashSet<String> {
+        if let Some(ref diff) = self.diff {
+            HashSet::from_iter(diff.tag_stats.keys().map(|s| s.to_owned()))
+        } else {
+            HashSet::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cursor::Page;
+
+    fn analyze(pr: &PullRequest, config: &Config) -> AnalyzedPullRequest {
+        AnalyzedPullRequest::new(
+            pr,
+            "acme/billing",
+            None,
+            config.person_for_bitbucket_username_at(pr.author.username(), &pr.created_on),
+            &config.calendar(),
+        )
+    }
+
+    #[test]
+    fn test_github_fields() {
+        let page: Page<PullRequest> =
+            serde_json::from_str(include_str!("../fixtures/pull_requests.json")).unwrap();
+        let config = Config::default();
+        let analyzed = analyze(&page.values[0], &config);
+        assert_eq!(analyzed.id(), "bitbucket-acme/billing-42");
+        assert_eq!(analyzed.event_type(), "pull_request");
+        assert_eq!(analyzed.time_to_resolve, Some(193764));
+
+        let json = serde_json::to_value(&analyzed).unwrap();
+        assert_eq!(json["number"], 42);
+        assert_eq!(json["forge"], "bitbucket");
+        assert_eq!(json["state"], "closed");
+        assert_eq!(json["user"]["login"], "jdoe");
+        assert_eq!(json["base"]["ref"], "master");
+        assert_eq!(json["base"]["repo"]["full_name"], "acme/billing");
+        assert_eq!(json["head"]["ref"], "retry-uploads");
+        assert_eq!(json["merge_commit_sha"], "8a3a7c0b4f1e");
+        assert_eq!(json["author_id"], "jdoe");
+
+        let open = analyze(&page.values[1], &config);
+        let json = serde_json::to_value(&open).unwrap();
+        assert_eq!(json["state"], "open");
+        assert_eq!(json["body"], serde_json::Value::Null);
+        assert_eq!(json["head"]["repo"], serde_json::Value::Null);
+        assert_eq!(open.time_to_resolve, None);
+    }
+
+    #[test]
+    fn test_review_stats() {
+        let pr: PullRequest =
+            serde_json::from_str(include_str!("../fixtures/pull_request.json")).unwrap();
+        let comments: Page<Comment> =
+            serde_json::from_str(include_str!("../fixtures/comments.json")).unwrap();
+        let config = Config::default();
+        let stats = review_stats(&pr, &comments.values, &config);
+        assert_eq!(stats.reviews, 2);
+        assert_eq!(stats.approvals, 1);
+        assert_eq!(stats.change_requests, 1);
+        assert_eq!(stats.review_comments, 1);
+        assert_eq!(stats.reviewers, vec!["asmith", "bwong"]);
+        assert_eq!(
+            stats.first_reviewed_at,
+            Some(Utc.ymd(2019, 3, 4).and_hms(18, 20, 0))
+        );
+        assert_eq!(stats.time_to_first_review, Some(8268));
+
+        let mut analyzed = analyze(&pr, &config);
+        analyzed.set_review_stats(stats);
+        let json = serde_json::to_value(&analyzed).unwrap();
+        assert_eq!(json["approvals"], 1);
+        assert_eq!(json["reviewers"][1], "bwong");
+    }
+}
@@ -0,0 +1,169 @@
+use reqwest::{RequestBuilder, Response};
+use slog::Logger;
+use std::env;
+
+use crate::comment::Comment;
+use crate::cursor::Cursor;
+use crate::error::{ErrorKind, ErrorPayload, Result};
+use crate::pull_request::PullRequest;
+
+const DEFAULT_URL: &str = "https://api.bitbucket.org/2.0";
+
+/// Pull requests are listed in pages of the largest size Bitbucket allows.
+const PAGE_LEN: usize = 50;
+
+#[derive(Debug, Clone)]
+enum Auth {
+    /// A username and app password.
+    Basic(String, String),
+    /// A repository, project or workspace access token.
+    Bearer(String),
+}
+
+/// Very basic wrapper around reqwest to interact with the 2.0 API of
+/// Bitbucket Cloud
+#[derive(Debug, Clone)]
+pub struct Client {
+    url: String,
+    auth: Option<Auth>,
+}
+
+impl Client {
+    /// Authenticates with the app password of `username`.
+    pub fn new(username: String, app_password: String) -> Client {
+        Client {
+            url: DEFAULT_URL.to_owned(),
+            auth: Some(Auth::Basic(username, app_password)),
+        }
+    }
+
+    /// Authenticates with an access token.
+    pub fn with_token(token: String) -> Client {
+        Client {
+            url: DEFAULT_URL.to_owned(),
+            auth: Some(Auth::Bearer(token)),
+        }
+    }
+
+    pub fn public() -> Client {
+        Client {
+            url: DEFAULT_URL.to_owned(),
+            auth: None,
+        }
+    }
+
+    /// Reads an app password from `BITBUCKET_USERNAME` and
+    /// `BITBUCKET_APP_PASSWORD`, or else an access token from
+    /// `BITBUCKET_TOKEN`. `BITBUCKET_API_URL` overrides the root of the API,
+    /// e.g. for a proxy.
+    pub fn from_env() -> Client {
+        let var = |name| env::var_os(name).and_then(|s| s.into_string().ok());
+        let auth = match (var("BITBUCKET_USERNAME"), var("BITBUCKET_APP_PASSWORD")) {
+            (Some(username), Some(app_password)) => Some(Auth::Basic(username, app_password)),
+            _ => var("BITBUCKET_TOKEN").map(Auth::Bearer),
+        };
+        Client {
+            url: var("BITBUCKET_API_URL")
+                .map(|url| url.trim_end_matches('/').to_owned())
+                .unwrap_or_else(|| DEFAULT_URL.to_owned()),
+            auth,
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn get(&self, url: &str) -> Result<Response> {
+        self.send(reqwest::Client::new().get(url))
+    }
+
+    fn send(&self, mut builder: RequestBuilder) -> Result<Response> {
+        codealong::ensure_online("requesting the Bitbucket api")?;
+        builder = match self.auth {
+            Some(Auth::Basic(ref username, ref app_password)) => {
+                builder.basic_auth(username, Some(app_password))
+            }
+            Some(Auth::Bearer(ref token)) => builder.bearer_auth(token),
+            None => builder,
+        };
+        let mut res = builder.send()?;
+        if res.status().is_success() {
+            Ok(res)
+        } else {
+            let message = res
+                .json::<ErrorPayload>()
+                .map(|payload| payload.error.message)
+                .unwrap_or_default();
+            Err(ErrorKind::Api(res.status().as_u16(), message).into())
+        }
+    }
+
+    /// All pull requests of the repository `repo`, e.g. `acme/billing`, most
+    /// recently updated first.
+    pub fn pull_requests(&self, repo: &str, logger: &Logger) -> Cursor<PullRequest> {
+        Cursor::new(
+            self,
+            &format!(
+                "{}/pullrequests?state=OPEN&state=MERGED&state=DECLINED&state=SUPERSEDED&sort=-updated_on&pagelen={}",
+                self.repo_url(repo),
+                PAGE_LEN
+            ),
+            logger,
+        )
+    }
+
+    /// A single pull request, which unlike those listed includes its
+    /// `participants`.
+    pub fn pull_request(&self, repo: &str, id: u64) -> Result<PullRequest> {
+        let mut res = self.get(&format!("{}/pullrequests/{}", self.repo_url(repo), id))?;
+        Ok(res.json()?)
+    }
+
+    /// The comments on the pull request `id`, oldest first.
+    pub fn comments(&self, repo: &str, id: u64, logger: &Logger) -> Cursor<Comment> {
+        Cursor::new(
+            self,
+            &format!(
+                "{}/pullrequests/{}/comments?sort=created_on&pagelen=100",
+                self.repo_url(repo),
+                id
+            ),
+            logger,
+        )
+    }
+
+    /// Comments `body`, in markdown, on the pull request `id`.
+    pub fn create_comment(&self, repo: &str, id: u64, body: &str) -> Result<()> {
+        let url = format!("{}/pullrequests/{}/comments", self.repo_url(repo), id);
+        self.send(
+            reqwest::Client::new()
+                .post(&url)
+                .json(&serde_json::json!({ "content": { "raw": body } })),
+        )?;
+        Ok(())
+    }
+
+    fn repo_url(&self, repo: &str) -> String {
+        format!("{}/repositories/{}", self.url, repo.trim_matches('/'))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codealong::test::build_test_logger;
+
+    #[test]
+    fn test_pull_requests_url() {
+        let client = Client::public();
+        assert_eq!(
+            client.pull_requests("acme/billing", &build_test_logger()).url(),
+            Some("https://api.bitbucket.org/2.0/repositories/acme/billing/pullrequests?state=OPEN&state=MERGED&state=DECLINED&state=SUPERSEDED&sort=-updated_on&pagelen=50")
+        );
+        assert_eq!(
+            client.comments("acme/billing", 42, &build_test_logger()).url(),
+            Some("https://api.bitbucket.org/2.0/repositories/acme/billing/pullrequests/42/comments?sort=created_on&pagelen=100")
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use chrono::prelude::*;
+use chrono::DateTime;
+
+use crate::user::User;
+
+/// A comment on a pull request, either on the pull request as a whole or
+/// inline on a line of its diff.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Comment {
+    pub id: u64,
+    pub content: Content,
+    /// Missing for users which were deleted.
+    #[serde(default)]
+    pub user: Option<User>,
+    pub created_on: DateTime<Utc>,
+    pub updated_on: DateTime<Utc>,
+    #[serde(default)]
+    pub inline: Option<Inline>,
+    #[serde(default)]
+    pub deleted: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Content {
+    pub raw: String,
+}
+
+/// Where an inline comment was made.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Inline {
+    pub path: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cursor::Page;
+
+    #[test]
+    fn test_deserialize() {
+        let page: Page<Comment> =
+            serde_json::from_str(include_str!("../fixtures/comments.json")).unwrap();
+        assert_eq!(page.values.len(), 3);
+        assert_eq!(page.values[0].user.as_ref().unwrap().username(), "asmith");
+        assert_eq!(
+            page.values[0].inline.as_ref().map(|inline| &*inline.path),
+            Some("src/upload.rs")
+        );
+        assert!(page.values[2].deleted);
+    }
+}
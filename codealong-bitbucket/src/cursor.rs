@@ -0,0 +1,101 @@
+use slog::Logger;
+
+use crate::client::Client;
+use crate::error::Result;
+
+/// A page of a paginated response of the 2.0 API.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub values: Vec<T>,
+    /// The url of the next page, which carries the cursor to continue from.
+    #[serde(default)]
+    pub next: Option<String>,
+    /// The number of values in all pages, which Bitbucket omits for some
+    /// collections.
+    #[serde(default)]
+    pub size: Option<usize>,
+}
+
+/// Provides an iterator on top of the Bitbucket pagination API, which links
+/// each page to the next rather than numbering them.
+pub struct Cursor<'client, T>
+where
+    for<'de> T: serde::Deserialize<'de>,
+{
+    client: &'client Client,
+    next_url: Option<String>,
+    total: Option<usize>,
+    current_page: Option<std::vec::IntoIter<T>>,
+    has_loaded_page: bool,
+    logger: Logger,
+}
+
+impl<'client, T> Cursor<'client, T>
+where
+    for<'de> T: serde::Deserialize<'de>,
+{
+    pub fn new(client: &'client Client, url: &str, logger: &Logger) -> Cursor<'client, T> {
+        Cursor {
+            client,
+            next_url: Some(url.to_owned()),
+            total: None,
+            current_page: None,
+            has_loaded_page: false,
+            logger: logger.clone(),
+        }
+    }
+
+    /// The url of the next page to be loaded.
+    pub fn url(&self) -> Option<&str> {
+        self.next_url.as_ref().map(|url| &**url)
+    }
+
+    pub fn guess_len(&mut self) -> Option<usize> {
+        self.ensure_page_loaded();
+        self.total
+    }
+
+    fn read_from_current_page(&mut self) -> Option<T> {
+        self.current_page.as_mut().and_then(|iter| iter.next())
+    }
+
+    fn ensure_page_loaded(&mut self) {
+        if !self.has_loaded_page {
+            self.load_next_page()
+        }
+    }
+
+    fn load_next_page(&mut self) {
+        match self.load_next_page_helper() {
+            Ok(_) => (),
+            Err(e) => error!(self.logger, "Error loading page: {}", e),
+        }
+    }
+
+    fn load_next_page_helper(&mut self) -> Result<()> {
+        if let Some(next_url) = self.next_url.take() {
+            let page: Page<T> = self.client.get(&next_url)?.json()?;
+            self.has_loaded_page = true;
+            self.next_url = page.next;
+            if self.total.is_none() {
+                self.total = page.size;
+            }
+            self.current_page = Some(page.values.into_iter());
+        }
+        Ok(())
+    }
+}
+
+impl<'client, T> Iterator for Cursor<'client, T>
+where
+    for<'de> T: serde::Deserialize<'de>,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.read_from_current_page().or_else(|| {
+            self.load_next_page();
+            self.read_from_current_page()
+        })
+    }
+}
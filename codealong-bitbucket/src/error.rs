@@ -0,0 +1,35 @@
+// error_chain 0.12 calls the deprecated `description` and `cause` of the
+// foreign errors
+#![allow(deprecated)]
+
+error_chain! {
+    errors {
+        Api(status: u16, message: String) {
+            description("Bitbucket API error")
+            display("Bitbucket API error {}: {}", status, message)
+        }
+    }
+
+    foreign_links {
+        Git2(git2::Error);
+        IO(std::io::Error);
+        Json(serde_json::Error);
+        Reqwest(reqwest::Error);
+    }
+
+    links {
+        Core(codealong::Error, codealong::ErrorKind);
+    }
+}
+
+/// Body of the error responses of the API, e.g.
+/// `{"type": "error", "error": {"message": "Repository not found"}}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorPayload {
+    pub error: ErrorDetail,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorDetail {
+    pub message: String,
+}
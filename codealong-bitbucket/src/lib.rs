@@ -0,0 +1,34 @@
+extern crate chrono;
+#[macro_use]
+extern crate error_chain;
+extern crate git2;
+extern crate reqwest;
+#[macro_use]
+extern crate serde_derive;
+extern crate codealong;
+extern crate serde;
+extern crate serde_json;
+#[macro_use]
+extern crate slog;
+
+mod analyzed_pull_request;
+mod client;
+mod comment;
+mod cursor;
+mod error;
+mod pull_request;
+mod pull_request_analyzer;
+mod pull_requests_analyzer;
+mod user;
+
+pub use crate::analyzed_pull_request::{review_stats, AnalyzedPullRequest};
+pub use crate::client::Client;
+pub use crate::comment::{Comment, Content, Inline};
+pub use crate::cursor::{Cursor, Page};
+pub use crate::error::{Error, ErrorKind};
+pub use crate::pull_request::{
+    Branch, CommitRef, Endpoint, Link, Links, Participant, PullRequest, RepositoryRef,
+};
+pub use crate::pull_request_analyzer::PullRequestAnalyzer;
+pub use crate::pull_requests_analyzer::PullRequestsAnalyzer;
+pub use crate::user::User;
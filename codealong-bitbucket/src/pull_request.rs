@@ -0,0 +1,138 @@
+use chrono::prelude::*;
+use chrono::DateTime;
+
+use crate::user::User;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PullRequest {
+    /// The number of the pull request within its repository.
+    pub id: u64,
+    pub title: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// One of `OPEN`, `MERGED`, `DECLINED` or `SUPERSEDED`.
+    pub state: String,
+    pub author: User,
+    pub source: Endpoint,
+    pub destination: Endpoint,
+    #[serde(default)]
+    pub merge_commit: Option<CommitRef>,
+    pub created_on: DateTime<Utc>,
+    pub updated_on: DateTime<Utc>,
+    #[serde(default)]
+    pub comment_count: u64,
+    /// Only included when a single pull request is requested.
+    #[serde(default)]
+    pub participants: Option<Vec<Participant>>,
+    pub links: Links,
+}
+
+impl PullRequest {
+    pub fn is_merged(&self) -> bool {
+        self.state == "MERGED"
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.state == "OPEN"
+    }
+
+    /// Bitbucket does not record when a pull request was merged or declined,
+    /// so it is approximated by when it was last updated.
+    pub fn closed_on(&self) -> Option<DateTime<Utc>> {
+        if self.is_open() {
+            None
+        } else {
+            Some(self.updated_on)
+        }
+    }
+
+    pub fn merged_on(&self) -> Option<DateTime<Utc>> {
+        if self.is_merged() {
+            Some(self.updated_on)
+        } else {
+            None
+        }
+    }
+}
+
+/// The branch, and its repository, a pull request is from or into.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Endpoint {
+    pub branch: Branch,
+    /// The head of the branch when the pull request was last updated,
+    /// abbreviated.
+    #[serde(default)]
+    pub commit: Option<CommitRef>,
+    /// Missing for forks which were deleted.
+    #[serde(default)]
+    pub repository: Option<RepositoryRef>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Branch {
+    pub name: String,
+}
+
+/// A commit, abbreviated to its first 12 characters in pull requests.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommitRef {
+    pub hash: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RepositoryRef {
+    pub full_name: String,
+}
+
+/// A reviewer or commenter of a pull request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Participant {
+    pub user: User,
+    /// `REVIEWER` or `PARTICIPANT`.
+    pub role: String,
+    pub approved: bool,
+    /// `approved` or `changes_requested`, if the participant reviewed the
+    /// pull request.
+    #[serde(default)]
+    pub state: Option<String>,
+    #[serde(default)]
+    pub participated_on: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Links {
+    pub html: Link,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Link {
+    pub href: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cursor::Page;
+
+    #[test]
+    fn test_deserialize() {
+        let page: Page<PullRequest> =
+            serde_json::from_str(include_str!("../fixtures/pull_requests.json")).unwrap();
+        assert_eq!(page.values.len(), 2);
+        let merged = &page.values[0];
+        assert_eq!(merged.id, 42);
+        assert_eq!(merged.author.username(), "jdoe");
+        assert!(merged.is_merged());
+        assert_eq!(merged.merged_on(), Some(merged.updated_on));
+        assert_eq!(merged.merge_commit.as_ref().unwrap().hash, "8a3a7c0b4f1e");
+        assert_eq!(merged.participants, None);
+        let open = &page.values[1];
+        assert!(open.is_open());
+        assert_eq!(open.closed_on(), None);
+        assert_eq!(open.source.repository, None);
+
+        let pr: PullRequest =
+            serde_json::from_str(include_str!("../fixtures/pull_request.json")).unwrap();
+        assert_eq!(pr.participants.unwrap().len(), 3);
+    }
+}
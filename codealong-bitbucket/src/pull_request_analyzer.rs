@@ -0,0 +1,129 @@
+use git2::{Oid, Repository};
+use slog::Logger;
+
+use codealong::{with_authentication, DiffAnalyzer, RepoConfig};
+
+use crate::analyzed_pull_request::{review_stats, AnalyzedPullRequest};
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::pull_request::PullRequest;
+
+pub struct PullRequestAnalyzer<'a> {
+    repo: &'a Repository,
+    config: &'a RepoConfig,
+    client: &'a Client,
+    pr: PullRequest,
+    logger: Logger,
+}
+
+impl<'a> PullRequestAnalyzer<'a> {
+    pub fn new(
+        repo: &'a Repository,
+        pr: PullRequest,
+        config: &'a RepoConfig,
+        client: &'a Client,
+        parent_logger: &Logger,
+    ) -> PullRequestAnalyzer<'a> {
+        let logger = parent_logger.new(o!("pull_request_id" => pr.id));
+        PullRequestAnalyzer {
+            repo,
+            pr,
+            config,
+            client,
+            logger,
+        }
+    }
+
+    pub fn analyze(mut self) -> Result<AnalyzedPullRequest> {
+        debug!(self.logger, "Analyzing pull_request"; "updated_at" => &self.pr.updated_on.to_rfc2822(), "user" => self.pr.author.username(), "title" => &self.pr.title);
+        let full_name = self.config.repo.bitbucket_name.as_ref().unwrap().to_owned();
+        if self.pr.participants.is_none() {
+            self.pr.participants = self
+                .client
+                .pull_request(&full_name, self.pr.id)?
+                .participants;
+        }
+        let comments: Vec<_> = self
+            .client
+            .comments(&full_name, self.pr.id, &self.logger)
+            .collect();
+        let diff = self
+            .analyze_diff()
+            .map_err(|e| warn!(self.logger, "Unable to analyze diff"; "error" => e.to_string()))
+            .ok();
+
+        let config = &self.config.config;
+        let normalized_author =
+            config.person_for_bitbucket_username_at(self.pr.author.username(), &self.pr.created_on);
+        debug!(self.logger, "Done analyzing");
+        let mut analyzed = AnalyzedPullRequest::new(
+            &self.pr,
+            &full_name,
+            diff,
+            normalized_author,
+            &config.calendar(),
+        );
+        analyzed.set_review_stats(review_stats(&self.pr, &comments, config));
+        analyzed.alias_repos(config);
+        analyzed.extract_issues(config)?;
+        analyzed.store_messages(config);
+        Ok(analyzed)
+    }
+
+    /// Diffs the head of the pull request against where it branched off its
+    /// destination, which Bitbucket does not list.
+    fn analyze_diff(&self) -> Result<codealong::AnalyzedDiff> {
+        let head = self.resolve(&self.pr.source.commit, &self.pr.source.branch.name)?;
+        let destination = self.resolve(
+            &self.pr.destination.commit,
+            &self.pr.destination.branch.name,
+        )?;
+        let base = self.repo.merge_base(head, destination)?;
+        let parent = self.repo.find_commit(base)?;
+        let commit = self.repo.find_commit(head)?;
+        Ok(
+            DiffAnalyzer::new(&self.repo, &commit, Some(&parent), &self.config.config)
+                .with_paths(&self.config.repo.paths)
+                .analyze()
+                .map_err(Error::from)?,
+        )
+    }
+
+    /// The commit an endpoint of the pull request points at, which is
+    /// abbreviated, fetching its branch if it is not in the repository yet.
+    /// The branches of forks are not fetched.
+    fn resolve(
+        &self,
+        commit: &Option<crate::pull_request::CommitRef>,
+        branch: &str,
+    ) -> Result<Oid> {
+        let hash = commit.as_ref().ok_or("pull request lists no commit")?;
+        if let Ok(object) = self.repo.revparse_single(&hash.hash) {
+            return Ok(object.peel_to_commit()?.id());
+        }
+        self.fetch_branch(branch)?;
+        Ok(self
+            .repo
+            .revparse_single(&hash.hash)?
+            .peel_to_commit()?
+            .id())
+    }
+
+    fn fetch_branch(&self, branch: &str) -> Result<()> {
+        let git_config = git2::Config::open_default()?;
+        let url = &self.config.repo.clone_url;
+        let refspec = format!("refs/heads/{}", branch);
+        with_authentication(url, &git_config, |f| {
+            let mut rcb = git2::RemoteCallbacks::new();
+            rcb.credentials(f);
+            let mut fo = git2::FetchOptions::new();
+            fo.remote_callbacks(rcb);
+
+            Ok(self
+                .repo
+                .remote_anonymous(url)
+                .and_then(|mut remote| remote.fetch(&[&refspec], Some(&mut fo), None))?)
+        })?;
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+use git2::Repository;
+use slog::Logger;
+
+use codealong::{AnalysisStream, AnalyzeOpts, Repo, RepoConfig};
+
+use crate::analyzed_pull_request::AnalyzedPullRequest;
+use crate::client::Client;
+use crate::cursor::Cursor;
+use crate::error::*;
+use crate::pull_request::PullRequest;
+use crate::pull_request_analyzer::PullRequestAnalyzer;
+
+pub struct PullRequestsAnalyzer<'client> {
+    repo: Repository,
+    config: RepoConfig,
+    client: &'client Client,
+    logger: Logger,
+}
+
+impl<'client> PullRequestsAnalyzer<'client> {
+    pub fn new(
+        repo: Repository,
+        config: RepoConfig,
+        client: &'client Client,
+        parent_logger: &Logger,
+    ) -> PullRequestsAnalyzer<'client> {
+        PullRequestsAnalyzer {
+            repo,
+            logger: parent_logger.new(o!("repo" => config.repo.name.to_owned())),
+            config,
+            client,
+        }
+    }
+
+    pub fn analyze(
+        &self,
+        opts: AnalyzeOpts,
+    ) -> Result<impl Iterator<Item = Result<PullRequestAnalyzer>>> {
+        Ok(PullRequestsCursor {
+            repo: &self.repo,
+            cursor: self.build_cursor(),
+            config: &self.config,
+            client: self.client,
+            opts,
+            logger: self.logger.clone(),
+        })
+    }
+
+    /// Like `analyze`, but fetches and analyzes the pull requests on a
+    /// thread of its own, with its own handle on the repository, and yields
+    /// them as a stream with at most `buffer` pull requests analyzed ahead
+    /// of the consumer.
+    pub fn stream(
+        &self,
+        opts: AnalyzeOpts,
+        buffer: usize,
+    ) -> AnalysisStream<AnalyzedPullRequest, Error> {
+        let path = self.repo.path().to_owned();
+        let config = self.config.clone();
+        let client = self.client.clone();
+        let logger = self.logger.clone();
+        AnalysisStream::spawn(buffer, move |emitter| {
+            let analyzer = match Repository::open(&path) {
+                Ok(repo) => PullRequestsAnalyzer {
+                    repo,
+                    config,
+                    client: &client,
+                    logger,
+                },
+                Err(e) => {
+                    emitter.emit(Err(e.into()));
+                    return;
+                }
+            };
+            let pull_requests = match analyzer.analyze(opts) {
+                Ok(pull_requests) => pull_requests,
+                Err(e) => {
+                    emitter.emit(Err(e));
+                    return;
+                }
+            };
+            for pr_analyzer in pull_requests {
+                if !emitter.emit(pr_analyzer.and_then(|a| a.analyze())) {
+                    return;
+                }
+            }
+        })
+    }
+
+    pub fn guess_len(&self, opts: AnalyzeOpts) -> Result<usize> {
+        if opts.since.is_some() {
+            Ok(self.analyze(opts)?.count())
+        } else {
+            Ok(self
+                .build_cursor()
+                .guess_len()
+                .ok_or("error estimating count of pull requests")?)
+        }
+    }
+
+    pub fn from_repo(repo: &Repo, client: &'client Client, logger: &Logger) -> Result<Self> {
+        Ok(Self::new(repo.repository()?, repo.config(), client, logger))
+    }
+
+    fn build_cursor(&self) -> Cursor<'client, PullRequest> {
+        self.client.pull_requests(
+            self.config.repo.bitbucket_name.as_ref().unwrap(),
+            &self.logger,
+        )
+    }
+}
+
+struct PullRequestsCursor<'client> {
+    repo: &'client Repository,
+    config: &'client RepoConfig,
+    client: &'client Client,
+    cursor: Cursor<'client, PullRequest>,
+    opts: AnalyzeOpts,
+    logger: Logger,
+}
+
+impl<'client> Iterator for PullRequestsCursor<'client> {
+    type Item = Result<PullRequestAnalyzer<'client>>;
+
+    fn next(&mut self) -> Option<Result<PullRequestAnalyzer<'client>>> {
+        loop {
+            let pr = self.cursor.next()?;
+            // listed most recently updated first
+            if let Some(ref since) = self.opts.since {
+                if since > &pr.updated_on {
+                    break None;
+                }
+            }
+
+            if !self.opts.ignore_unknown_authors
+                || self
+                    .config
+                    .config
+                    .is_bitbucket_username_known(pr.author.username())
+            {
+                break Some(Ok(PullRequestAnalyzer::new(
+                    &self.repo,
+                    pr,
+                    &self.config,
+                    self.client,
+                    &self.logger,
+                )));
+            }
+        }
+    }
+}
@@ -0,0 +1,20 @@
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct User {
+    pub uuid: String,
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub nickname: Option<String>,
+    #[serde(default)]
+    pub account_id: Option<String>,
+}
+
+impl User {
+    /// The name the user is mapped to authors by: their nickname, or their
+    /// account id or uuid for users which have none, e.g. deleted ones.
+    pub fn username(&self) -> &str {
+        self.nickname
+            .as_ref()
+            .or(self.account_id.as_ref())
+            .unwrap_or(&self.uuid)
+    }
+}
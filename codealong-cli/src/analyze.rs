@@ -1,35 +1,207 @@
+use std::fs::File;
+use std::sync::{Arc, Mutex};
+
+use chrono::{Duration, Utc};
+use rand::Rng;
 use slog::Logger;
 
-use codealong::{Repo, RepoInfo, Workspace};
+use codealong::{
+    AnalysisRun, AnalysisState, LockHolder, PipelineStats, Profiler, Repo, RepoInfo, RepoLock,
+    Workspace, STALE_LOCK_HOURS,
+};
 
 use crate::analyze_repos::analyze_repos;
 use crate::build_workspace::build_workspace;
-use crate::error::Result;
+use crate::error::{ErrorKind, Result};
 use crate::initialize_repos::initialize_repos;
-use crate::utils::build_es_client;
+use crate::utils::{build_es_client, configure_client, load_plugins, report_spool};
+
+/// How long to wait for an overlapping run to save the state of the
+/// workspace.
+const STATE_LOCK_TIMEOUT_SECS: u64 = 60;
 
 pub fn analyze(matches: &clap::ArgMatches, logger: &Logger) -> Result<()> {
-    validate_args(matches)?;
     let workspace = build_workspace(matches, logger)?;
+    let mut run = start_run(&workspace);
+    let client = configure_client(
+        build_es_client(matches)?.with_config_hash(&run.config_hash),
+        workspace.config(),
+    )?;
+    let client = load_plugins(client, matches, logger)?;
+    let client = if client.is_elasticsearch() && !client.has_spool() {
+        client.with_spool(&workspace.spool_dir())
+    } else {
+        client
+    };
+    check_health(&client, logger)?;
+    info!(logger, "Starting analysis run"; "run" => &run.id);
     let repos = build_repos(&workspace, matches);
+    let (repos, _locks) = lock_repos(&workspace, repos, &run, logger)?;
+    if codealong::is_offline() {
+        check_offline(matches, &client, &repos)?;
+    }
+    client.load_schema()?;
     initialize_repos(matches, repos.clone(), logger)?;
-    analyze_repos(matches, repos.clone(), logger)?;
+    let profiler = Profiler::new();
+    let stats = PipelineStats::new();
+    let state = Arc::new(Mutex::new(AnalysisState::from_path(
+        &workspace.state_path(),
+    )?));
+    run.repos = analyze_repos(
+        matches,
+        workspace.config(),
+        repos.clone(),
+        &client,
+        &state,
+        &profiler,
+        &stats,
+        logger,
+    )?;
+    // overlapping runs own the state of the other repos
+    let names: Vec<String> = repos
+        .iter()
+        .map(|repo| repo.config().repo_name().to_owned())
+        .collect();
+    {
+        // held so that overlapping runs do not save over each other
+        let _state_lock = RepoLock::acquire(
+            &workspace.state_lock_path(),
+            &LockHolder::current(run.host.clone(), Some(run.id.clone())),
+            Duration::hours(STALE_LOCK_HOURS),
+            std::time::Duration::from_secs(STATE_LOCK_TIMEOUT_SECS),
+        )?;
+        let mut saved = AnalysisState::from_path(&workspace.state_path())?;
+        saved.update_repos(&state.lock().unwrap(), &names);
+        saved.save(&workspace.state_path())?;
+    }
+    run.finish(Utc::now(), client.event_counts());
+    run.pipeline = stats.summary();
+    for stage in &run.pipeline {
+        info!(logger, "Pipeline stage"; "stage" => stage.stage.name(), "items" => stage.items, "busy_seconds" => stage.busy_seconds, "items_per_second" => stage.items_per_second, "mean_queue_depth" => stage.mean_queue_depth, "max_queue_depth" => stage.max_queue_depth);
+    }
+    client.index(run)?;
+    client.flush()?;
+    report_spool(&client, logger)?;
+    if let Some(path) = matches.value_of("profile_output") {
+        profiler.write_folded(File::create(path)?)?;
+        info!(logger, "Wrote profile"; "path" => path);
+    }
+    if let Some(path) = matches.value_of("metrics_output") {
+        stats.write_prometheus(File::create(path)?)?;
+        info!(logger, "Wrote pipeline metrics"; "path" => path);
+    }
     Ok(())
 }
 
-fn validate_args(matches: &clap::ArgMatches) -> Result<()> {
-    // Ensure ES is accessible
-    let client = build_es_client(matches);
-    client.health()?;
+/// Locks each repo for the duration of the run. Repos locked by an
+/// overlapping run are left to it rather than analyzed twice.
+fn lock_repos(
+    workspace: &Workspace,
+    repos: Vec<Repo>,
+    run: &AnalysisRun,
+    logger: &Logger,
+) -> Result<(Vec<Repo>, Vec<RepoLock>)> {
+    let holder = LockHolder::current(run.host.clone(), Some(run.id.clone()));
+    let stale_after = Duration::hours(STALE_LOCK_HOURS);
+    let mut locked = Vec::new();
+    let mut locks = Vec::new();
+    for repo in repos {
+        match RepoLock::try_acquire(&workspace.lock_path(&repo), &holder, stale_after)? {
+            Ok(lock) => {
+                locks.push(lock);
+                locked.push(repo);
+            }
+            Err(other) => {
+                warn!(logger, "Skipping repo locked by another run"; "repo" => &repo.repo_info().name, "pid" => other.pid, "host" => &other.host, "run" => &other.run, "acquired_at" => other.acquired_at.to_rfc3339())
+            }
+        }
+    }
+    Ok((locked, locks))
+}
+
+/// Records who started the run, from where and with which configuration, so
+/// that the indexed events can be traced back to it.
+fn start_run(workspace: &Workspace) -> AnalysisRun {
+    let started_at = Utc::now();
+    let id = format!(
+        "{}-{:08x}",
+        started_at.format("%Y%m%dT%H%M%SZ"),
+        rand::thread_rng().gen::<u32>()
+    );
+    let mut run = AnalysisRun::new(
+        &id,
+        started_at,
+        env!("CARGO_PKG_VERSION"),
+        workspace.config(),
+    );
+    run.user = std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .ok();
+    run.host = hostname::get_hostname();
+    run.working_dir = std::env::current_dir()
+        .ok()
+        .map(|dir| dir.display().to_string());
+    run.command = std::env::args().collect();
+    run
+}
+
+/// Ensures Elasticsearch is reachable, unless events can be spooled until it
+/// is, as they are to the spool of the workspace by default.
+fn check_health(client: &codealong_elk::Client, logger: &Logger) -> Result<()> {
+    if client.is_elasticsearch() && !codealong::is_offline() {
+        if let Err(e) = client.health() {
+            if !client.has_spool() {
+                return Err(e.into());
+            }
+            warn!(logger, "Elasticsearch is unreachable, spooling events"; "error" => e.to_string());
+        }
+    }
     Ok(())
 }
 
-fn build_repos(workspace: &Workspace, matches: &clap::ArgMatches) -> Vec<Repo> {
+/// Fails before anything is analyzed if the arguments or the workspace
+/// require network access, listing everything which does.
+fn check_offline(
+    matches: &clap::ArgMatches,
+    client: &codealong_elk::Client,
+    repos: &[Repo],
+) -> Result<()> {
+    let mut required = Vec::new();
+    if client.is_elasticsearch() {
+        required.push("writing to elasticsearch, pass --format ndjson or csv".to_owned());
+    }
+    if !matches.is_present("skip_pull_requests") && !matches.is_present("reconstruct_pull_requests")
+    {
+        required.push(
+            "analyzing pull requests, pass --skip-pull-requests or --reconstruct-pull-requests"
+                .to_owned(),
+        );
+    }
+    if matches.is_present("repo_metadata") {
+        required.push("--repo-metadata".to_owned());
+    }
+    if matches.is_present("direct_pushes") {
+        required.push("--direct-pushes".to_owned());
+    }
+    for repo in repos {
+        if repo.repository().is_err() {
+            required.push(format!("cloning {}", repo.repo_info().name));
+        }
+    }
+    if required.is_empty() {
+        Ok(())
+    } else {
+        Err(ErrorKind::RequiresNetwork(required.join("; ")).into())
+    }
+}
+
+pub fn build_repos(workspace: &Workspace, matches: &clap::ArgMatches) -> Vec<Repo> {
     let skip_forks = matches.is_present("skip_forks");
     let mut repos: Vec<Repo> = workspace
         .repos()
         .into_iter()
         .filter(|r| !skip_forks || !r.repo_info().fork)
+        .filter(|r| r.is_active())
         .collect();
 
     if let Some(repo_urls) = matches.values_of("repo") {
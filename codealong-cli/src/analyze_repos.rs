@@ -1,21 +1,48 @@
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
+use std::path::Path;
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::Instant;
 
 use chrono::offset::TimeZone;
-use chrono::Utc;
+use chrono::{DateTime, Duration, Utc};
 use error_chain::ChainedError;
 use slog::Logger;
 
-use codealong::{AnalyzeOpts, Repo, RepoAnalyzer};
-use codealong_github::PullRequestsAnalyzer;
+use codealong::{
+    detect_snapshot_shifts, rollup_org, rollup_teams, AnalysisState, AnalyzeOpts, AnalyzedCommit,
+    CommitAnalysis, CommitAnalyzer, CommitList, Config, DcoReports, Effort, Hotspots,
+    PairingSessions, Period, PersonSnapshots, PipelineStats, Profiler, Repo, RepoAnalyzer,
+    RepoMetadataSnapshot, RepoSnapshots, RunRepo, SloSubject, Span, Stage, SurveyScores, Survival,
+};
+use codealong_github::{PullRequestsAnalyzer, Traceability};
+use codealong_gitlab::MergeRequestsAnalyzer;
 
-use crate::error::Result;
+use crate::error::{ErrorKind, Result};
 use crate::ui::{NamedProgressBar, ProgressPool};
 
-/// Clone and/or fetch all repos
-pub fn analyze_repos(matches: &clap::ArgMatches, repos: Vec<Repo>, logger: &Logger) -> Result<()> {
+/// Window over which churn is counted for the dependency graph.
+const DEPENDENCY_CHURN_DAYS: i64 = 90;
+
+/// Window over which churn is counted to find the hotspots the risk of
+/// changes is assessed against.
+const HOTSPOT_DAYS: i64 = 90;
+
+/// Clone and/or fetch all repos, returning what was analyzed of each
+pub fn analyze_repos(
+    matches: &clap::ArgMatches,
+    workspace_config: &Config,
+    repos: Vec<Repo>,
+    client: &codealong_elk::Client,
+    state: &Arc<Mutex<AnalysisState>>,
+    profiler: &Profiler,
+    stats: &PipelineStats,
+    logger: &Logger,
+) -> Result<Vec<RunRepo>> {
     info!(logger, "Analyzing {} repos", repos.len());
+    if repos.is_empty() {
+        return Ok(vec![]);
+    }
     let num_threads = std::cmp::min(
         matches
             .value_of("concurrency")
@@ -23,15 +50,31 @@ pub fn analyze_repos(matches: &clap::ArgMatches, repos: Vec<Repo>, logger: &Logg
             .parse::<i32>()?,
         (repos.len() * 2) as i32,
     );
-    let tasks = expand_tasks(&matches, repos);
+    let repos = if matches.is_present("repo_metadata") {
+        let _span = profiler.span("repo_metadata");
+        fetch_repo_metadata(repos, client, logger)?
+    } else {
+        repos
+    };
+    // people work across repos, so their snapshots follow the period of the
+    // workspace
+    let period = workspace_config.rollup_period();
+    let tasks = expand_tasks(&matches, repos, client, &state.lock().unwrap())?;
     let m = Arc::new(ProgressPool::new(
         tasks.len() as u64,
         matches.is_present("progress"),
     ));
     let tasks = Arc::new(Mutex::new(tasks));
+    let snapshots = Arc::new(Mutex::new(PersonSnapshots::new(period)));
+    let run_repos = Arc::new(Mutex::new(Vec::new()));
     m.set_message("Data sources analyzed");
     for _ in 0..num_threads {
         let tasks = tasks.clone();
+        let snapshots = snapshots.clone();
+        let run_repos = run_repos.clone();
+        let state = state.clone();
+        let profiler = profiler.clone();
+        let stats = stats.clone();
         let m = m.clone();
         let mut pb = m.add();
         let root_logger = logger.clone();
@@ -42,10 +85,23 @@ pub fn analyze_repos(matches: &clap::ArgMatches, repos: Vec<Repo>, logger: &Logg
             };
             if let Some(task) = task {
                 let logger = root_logger.new(o!("repo" => task.repo.repo_info().name.to_owned()));
+                if task.opts.is_past_deadline() {
+                    warn!(
+                        logger,
+                        "Skipping analysis, the maximum duration was exceeded"
+                    );
+                    m.inc(1);
+                    continue;
+                }
                 pb.reset(task.display_name().to_owned());
-                task.analyze(&pb, &logger).unwrap_or_else(
-                    |e| error!(logger, "error analyzing"; "error" => e.display_chain().to_string()),
-                );
+                let span = profiler.span(&task.repo.repo_info().name);
+                match task.analyze(&pb, &snapshots, &state, &stats, &span, &logger) {
+                    Ok(Some(run_repo)) => run_repos.lock().unwrap().push(run_repo),
+                    Ok(None) => {}
+                    Err(e) => {
+                        error!(logger, "error analyzing"; "error" => e.display_chain().to_string())
+                    }
+                }
                 m.inc(1);
             } else {
                 pb.finish();
@@ -54,47 +110,296 @@ pub fn analyze_repos(matches: &clap::ArgMatches, repos: Vec<Repo>, logger: &Logg
         });
     }
     m.join_and_clear()?;
+    if !matches.is_present("since_last_run") {
+        let _span = profiler.span("snapshots");
+        index_snapshots(matches, snapshots, period, client)?;
+    }
+    let run_repos = std::mem::replace(&mut *run_repos.lock().unwrap(), Vec::new());
+    Ok(run_repos)
+}
+
+fn index_snapshots(
+    matches: &clap::ArgMatches,
+    snapshots: Arc<Mutex<PersonSnapshots>>,
+    period: Period,
+    client: &codealong_elk::Client,
+) -> Result<()> {
+    let mut snapshots = std::mem::replace(
+        &mut *snapshots.lock().unwrap(),
+        PersonSnapshots::new(period),
+    );
+    for heatmap in snapshots.heatmaps() {
+        client.index(heatmap)?;
+    }
+    let repo_snapshots = snapshots.finish();
+    let org_snapshots = rollup_org(&repo_snapshots);
+    for shift in detect_snapshot_shifts(&org_snapshots, period) {
+        client.index(shift)?;
+    }
+    let survey = match matches.value_of("survey") {
+        Some(path) => Some(SurveyScores::from_path(Path::new(path))?),
+        None => None,
+    };
+    for mut team_snapshot in rollup_teams(&org_snapshots) {
+        if let Some(ref survey) = survey {
+            team_snapshot.join_survey(survey);
+        }
+        client.index(team_snapshot)?;
+    }
+    for snapshot in repo_snapshots.into_iter().chain(org_snapshots) {
+        client.index(snapshot)?;
+    }
     Ok(())
 }
 
-fn expand_tasks(matches: &clap::ArgMatches, repos: Vec<Repo>) -> VecDeque<AnalyzeTask> {
+/// Stamps the Github metadata of each repo onto its events, and indexes it
+/// as a document of its own.
+fn fetch_repo_metadata(
+    mut repos: Vec<Repo>,
+    client: &codealong_elk::Client,
+    logger: &Logger,
+) -> Result<Vec<Repo>> {
+    let github_client = codealong_github::Client::from_env();
+    let now = Utc::now();
+    for repo in &mut repos {
+        let github_name = match repo.repo_info().github_name {
+            Some(ref github_name) => github_name.to_owned(),
+            None => continue,
+        };
+        let github_client = github_client.for_clone_url(&repo.repo_info().clone_url);
+        match github_client.repo_metadata(&github_name) {
+            Ok(metadata) => {
+                client.index(RepoMetadataSnapshot::new(
+                    repo.config().repo_name(),
+                    metadata.clone(),
+                    now,
+                ))?;
+                repo.set_metadata(metadata);
+            }
+            Err(e) => warn!(logger, "Unable to fetch repo metadata";
+                "repo" => github_name,
+                "error" => e.display_chain().to_string()),
+        }
+    }
+    Ok(repos)
+}
+
+fn expand_tasks(
+    matches: &clap::ArgMatches,
+    repos: Vec<Repo>,
+    client: &codealong_elk::Client,
+    state: &AnalysisState,
+) -> Result<VecDeque<AnalyzeTask>> {
+    // the budget covers the whole run rather than each task
+    let deadline = match matches.value_of("max_duration") {
+        Some(max_duration) => {
+            let max_duration = Duration::from_std(humantime::parse_duration(max_duration)?)
+                .map_err(|_| ErrorKind::InvalidDuration(max_duration.to_owned()))?;
+            Some(Utc::now() + max_duration)
+        }
+        None => None,
+    };
+    let commit_list = if matches.is_present("commits_from_stdin") {
+        Some(CommitList::read_from(std::io::stdin())?)
+    } else {
+        None
+    };
+    let jobs = matches.value_of("jobs").unwrap_or("1").parse::<usize>()?;
     let mut tasks: VecDeque<AnalyzeTask> = VecDeque::new();
     for repo in repos {
-        let opts = analyze_opts_from_args(&repo, matches).unwrap();
+        let opts = analyze_opts_from_args(&repo, matches, deadline)?;
+        let repo_state = state
+            .repo(repo.config().repo_name())
+            .filter(|_| matches.is_present("since_last_run"));
+        let commits = commit_list
+            .as_ref()
+            .map(|list| list.for_repo(&[repo.config().repo_name(), &repo.repo_info().name]));
+        if commits.as_ref().is_some_and(|commits| commits.is_empty()) {
+            continue;
+        }
         if !matches.is_present("skip_commits") {
+            let mut opts = opts.clone();
+            if let Some(repo_state) = repo_state {
+                opts.analyzed_heads = repo_state.refs.values().cloned().collect();
+            }
             tasks.push_back(AnalyzeTask {
                 repo: repo.clone(),
                 task_type: AnalyzeTaskType::Commit,
-                opts: opts.clone(),
+                opts,
+                client: client.clone(),
+                commits: commits.clone(),
+                dependency_graph: matches.is_present("dependency_graph"),
+                direct_pushes: matches.is_present("direct_pushes"),
+                survival: matches.is_present("survival"),
+                effort: matches.is_present("effort"),
+                lite: matches.is_present("lite"),
+                jobs,
             });
         }
-        if !matches.is_present("skip_pull_requests") {
+        // only the listed commits are analyzed
+        if !matches.is_present("skip_pull_requests") && commits.is_none() {
+            let info = repo.repo_info();
+            // without a forge, pull requests can only be inferred from git
+            let task_type = if matches.is_present("reconstruct_pull_requests")
+                || (info.github_name.is_none()
+                    && info.gitlab_name.is_none()
+                    && info.bitbucket_name.is_none())
+            {
+                AnalyzeTaskType::ReconstructedPullRequest
+            } else if info.bitbucket_name.is_some() {
+                AnalyzeTaskType::BitbucketPullRequest
+            } else if info.github_name.is_none() {
+                AnalyzeTaskType::MergeRequest
+            } else {
+                AnalyzeTaskType::PullRequest
+            };
+            let mut opts = opts.clone();
+            if let Some(updated_at) = repo_state.and_then(|s| s.pull_requests_updated_at) {
+                opts.since = Some(opts.since.map_or(updated_at, |since| since.max(updated_at)));
+            }
             tasks.push_back(AnalyzeTask {
                 repo: repo.clone(),
-                task_type: AnalyzeTaskType::PullRequest,
-                opts: opts.clone(),
+                task_type,
+                opts,
+                client: client.clone(),
+                commits: None,
+                dependency_graph: false,
+                direct_pushes: false,
+                survival: false,
+                effort: false,
+                lite: false,
+                jobs,
             });
         }
     }
-    tasks
+    Ok(tasks)
 }
 
 enum AnalyzeTaskType {
     Commit,
     PullRequest,
+    MergeRequest,
+    BitbucketPullRequest,
+    ReconstructedPullRequest,
 }
 
 struct AnalyzeTask {
     task_type: AnalyzeTaskType,
     repo: Repo,
     opts: AnalyzeOpts,
+    client: codealong_elk::Client,
+    commits: Option<Vec<String>>,
+    dependency_graph: bool,
+    direct_pushes: bool,
+    /// Compute the share of the lines of each commit which survive at the
+    /// head.
+    survival: bool,
+    /// Estimate the effort of each commit from the work sessions of its
+    /// author.
+    effort: bool,
+    /// Only analyze the metadata and numstat of commits.
+    lite: bool,
+    /// Number of threads the commits of the repo are analyzed on.
+    jobs: usize,
 }
 
 impl AnalyzeTask {
-    fn analyze(&self, pb: &NamedProgressBar, logger: &Logger) -> Result<()> {
+    fn analyze(
+        &self,
+        pb: &NamedProgressBar,
+        snapshots: &Mutex<PersonSnapshots>,
+        state: &Mutex<AnalysisState>,
+        stats: &PipelineStats,
+        span: &Span,
+        logger: &Logger,
+    ) -> Result<Option<RunRepo>> {
         match self.task_type {
-            AnalyzeTaskType::Commit => analyze_commits(pb, &self.repo, self.opts.clone(), logger),
-            AnalyzeTaskType::PullRequest => analyze_prs(pb, &self.repo, self.opts.clone(), logger),
+            AnalyzeTaskType::Commit if self.lite => analyze_lite_commits(
+                pb,
+                &self.repo,
+                self.commits.as_ref(),
+                self.opts.clone(),
+                &self.client,
+                state,
+                stats,
+                &span.child("lite_commits"),
+                logger,
+            )
+            .map(Some),
+            AnalyzeTaskType::Commit => match self.commits {
+                Some(ref commits) => analyze_listed_commits(
+                    pb,
+                    &self.repo,
+                    commits,
+                    self.opts.clone(),
+                    &self.client,
+                    snapshots,
+                    stats,
+                    &span.child("listed_commits"),
+                    logger,
+                )
+                .map(Some),
+                None => analyze_commits(
+                    pb,
+                    &self.repo,
+                    self.opts.clone(),
+                    self.dependency_graph,
+                    self.direct_pushes,
+                    self.survival,
+                    self.effort,
+                    self.jobs,
+                    &self.client,
+                    snapshots,
+                    state,
+                    stats,
+                    &span.child("commits"),
+                    logger,
+                )
+                .map(Some),
+            },
+            AnalyzeTaskType::PullRequest => analyze_prs(
+                pb,
+                &self.repo,
+                self.opts.clone(),
+                &self.client,
+                snapshots,
+                state,
+                &span.child("pull_requests"),
+                logger,
+            )
+            .map(|_| None),
+            AnalyzeTaskType::MergeRequest => analyze_mrs(
+                pb,
+                &self.repo,
+                self.opts.clone(),
+                &self.client,
+                snapshots,
+                state,
+                &span.child("merge_requests"),
+                logger,
+            )
+            .map(|_| None),
+            AnalyzeTaskType::BitbucketPullRequest => analyze_bitbucket_prs(
+                pb,
+                &self.repo,
+                self.opts.clone(),
+                &self.client,
+                snapshots,
+                state,
+                &span.child("pull_requests"),
+                logger,
+            )
+            .map(|_| None),
+            AnalyzeTaskType::ReconstructedPullRequest => reconstruct_prs(
+                pb,
+                &self.repo,
+                self.opts.clone(),
+                &self.client,
+                state,
+                &span.child("reconstructed_pull_requests"),
+                logger,
+            )
+            .map(|_| None),
         }
     }
 
@@ -107,44 +412,654 @@ fn analyze_commits(
     pb: &NamedProgressBar,
     repo: &Repo,
     opts: AnalyzeOpts,
+    dependency_graph: bool,
+    direct_pushes: bool,
+    survival: bool,
+    effort: bool,
+    jobs: usize,
+    client: &codealong_elk::Client,
+    snapshots: &Mutex<PersonSnapshots>,
+    state: &Mutex<AnalysisState>,
+    stats: &PipelineStats,
+    span: &Span,
     logger: &Logger,
-) -> Result<()> {
+) -> Result<RunRepo> {
     info!(logger, "Analyzing commits");
-    let analyzer = RepoAnalyzer::from_repo(repo, logger)?;
-    let client = codealong_elk::Client::default();
+    let analyzer =
+        RepoAnalyzer::from_repo(repo, logger)?.with_progress(pb.progress(span.profiler()));
+    let mut opts = opts;
+    let rewrite_span = span.child("history_rewrite");
+    if let Some(rewrite) = analyzer.history_rewrite()? {
+        warn!(logger, "History was rewritten since the last checkpoint"; "checkpoint" => &rewrite.checkpoint, "orphaned_commits" => rewrite.orphaned_commits.len());
+        pb.set_message("marking orphaned commits");
+        for commit_analyzer in analyzer.orphaned(&rewrite) {
+            let mut analyzed_commit = commit_analyzer?.analyze()?;
+            analyzed_commit.orphaned = true;
+            client.index(analyzed_commit)?;
+        }
+        client.index(rewrite)?;
+    }
+    drop(rewrite_span);
+    if opts.resume {
+        // resume from the start of the period so snapshots are complete
+        if let Some(resume_point) = analyzer.resume_point()? {
+            let (period_start, _) = repo.config().config.rollup_period().bounds(&resume_point);
+            opts.since = Some(
+                opts.since
+                    .map_or(period_start, |since| since.max(period_start)),
+            );
+        }
+    }
+    let github_client =
+        codealong_github::Client::from_env().for_clone_url(&repo.repo_info().clone_url);
+    let direct_pushes = match repo.repo_info().github_name {
+        Some(ref github_name) if direct_pushes => {
+            let _span = span.child("traceability");
+            pb.set_message("listing merged pull requests");
+            Some(DirectPushes {
+                mainline: analyzer.mainline()?,
+                traceability: Traceability::fetch(&github_client, github_name, logger)?,
+            })
+        }
+        _ => None,
+    };
     pb.set_message("calculating");
-    let count = analyzer.guess_len(opts.clone())?;
+    let count = {
+        let _span = span.child("guess_len");
+        analyzer.guess_len(opts.clone())?
+    };
     pb.set_length(count as u64);
+    let hotspots = {
+        let _span = span.child("hotspots");
+        pb.set_message("finding hotspots");
+        analyzer.hotspots(&opts, &(Utc::now() - Duration::days(HOTSPOT_DAYS)))?
+    };
+    let survival = if survival {
+        let _span = span.child("survival");
+        pb.set_message("blaming the head");
+        Some(analyzer.survival(&opts)?)
+    } else {
+        None
+    };
+    let effort = if effort {
+        let _span = span.child("effort");
+        pb.set_message("estimating effort");
+        Some(analyzer.effort(&opts)?)
+    } else {
+        None
+    };
+    pb.set_message("analyzing commits");
+    let head = analyzer.head(&opts)?;
+    let analyses: Box<dyn Iterator<Item = _>> = if jobs > 1 {
+        let mut revwalk = analyzer.analyze_parallel(opts.clone(), jobs, stats)?;
+        Box::new(std::iter::from_fn(move || {
+            let _span = span.child("analyze_parallel");
+            revwalk.next()
+        }))
+    } else {
+        Box::new(analyze_serially(
+            analyzer.analyze(opts.clone())?,
+            stats,
+            span,
+        ))
+    };
+    let (last_analyzed, commits) = index_commits(
+        pb,
+        repo,
+        analyses,
+        IndexContext {
+            direct_pushes: direct_pushes.as_ref(),
+            hotspots: Some(&hotspots),
+            survival: survival.as_ref(),
+            effort: effort.as_ref(),
+            // weekly rollups of only the new commits would replace complete
+            // ones
+            rollups: opts.analyzed_heads.is_empty(),
+            snapshots,
+            stats,
+        },
+        client,
+        span,
+    )?;
+    if dependency_graph && !opts.is_past_deadline() {
+        pb.set_message("building dependency graph");
+        let _span = span.child("dependency_graph");
+        let churn_since = Utc::now() - Duration::days(DEPENDENCY_CHURN_DAYS);
+        if let Some(graph) = analyzer.dependency_graph(&churn_since)? {
+            for directory in graph.directories(repo.config().repo_name()) {
+                client.index(directory)?;
+            }
+        }
+    }
+    if opts.is_past_deadline() {
+        // commits are analyzed oldest first when there is a deadline
+        warn!(logger, "Stopped analyzing commits, the maximum duration was exceeded"; "last_analyzed" => &last_analyzed);
+        if let Some(ref id) = last_analyzed {
+            analyzer.save_checkpoint_at(id)?;
+        }
+    } else {
+        analyzer.save_checkpoint()?;
+        state
+            .lock()
+            .unwrap()
+            .repo_mut(repo.config().repo_name())
+            .refs = analyzer.ref_heads(&opts)?;
+    }
+    pb.finish();
+    Ok(RunRepo {
+        name: repo.config().repo_name().to_owned(),
+        head,
+        since: opts.since,
+        commits,
+    })
+}
+
+/// Analyzes exactly the commits listed rather than walking history. No
+/// checkpoint is saved since the history in between was not analyzed.
+fn analyze_listed_commits(
+    pb: &NamedProgressBar,
+    repo: &Repo,
+    commits: &[String],
+    opts: AnalyzeOpts,
+    client: &codealong_elk::Client,
+    snapshots: &Mutex<PersonSnapshots>,
+    stats: &PipelineStats,
+    span: &Span,
+    logger: &Logger,
+) -> Result<RunRepo> {
+    info!(logger, "Analyzing listed commits"; "count" => commits.len());
+    let analyzer =
+        RepoAnalyzer::from_repo(repo, logger)?.with_progress(pb.progress(span.profiler()));
+    pb.set_length(commits.len() as u64);
     pb.set_message("analyzing commits");
-    for commit_analyzer in analyzer.analyze(opts)? {
-        client.index(commit_analyzer?.analyze()?)?;
+    // a bare sha is listed for every repo but only exists in some of them
+    let found = analyzer
+        .commits(commits)
+        .zip(commits)
+        .take_while(|_| !opts.is_past_deadline())
+        .filter_map(|(commit_analyzer, id)| match commit_analyzer {
+            Ok(commit_analyzer) => Some(Ok(commit_analyzer)),
+            Err(e) => {
+                warn!(logger, "Skipping listed commit"; "commit" => id, "error" => e.to_string());
+                pb.inc(1);
+                None
+            }
+        });
+    let (_, count) = index_commits(
+        pb,
+        repo,
+        analyze_serially(found, stats, span),
+        IndexContext {
+            direct_pushes: None,
+            hotspots: None,
+            survival: None,
+            effort: None,
+            rollups: true,
+            snapshots,
+            stats,
+        },
+        client,
+        span,
+    )?;
+    if opts.is_past_deadline() {
+        warn!(
+            logger,
+            "Stopped analyzing commits, the maximum duration was exceeded"
+        );
+    }
+    pb.finish();
+    Ok(RunRepo {
+        name: repo.config().repo_name().to_owned(),
+        head: None,
+        since: None,
+        commits: count,
+    })
+}
+
+/// Analyzes only the metadata and numstat of the commits of `repo`, or of
+/// the listed `commits`, which skips blaming their diffs.
+fn analyze_lite_commits(
+    pb: &NamedProgressBar,
+    repo: &Repo,
+    commits: Option<&Vec<String>>,
+    opts: AnalyzeOpts,
+    client: &codealong_elk::Client,
+    state: &Mutex<AnalysisState>,
+    stats: &PipelineStats,
+    span: &Span,
+    logger: &Logger,
+) -> Result<RunRepo> {
+    info!(logger, "Analyzing commit metadata");
+    let analyzer =
+        RepoAnalyzer::from_repo(repo, logger)?.with_progress(pb.progress(span.profiler()));
+    pb.set_message("calculating");
+    let commit_analyzers: Box<dyn Iterator<Item = _>> = match commits {
+        Some(commits) => {
+            pb.set_length(commits.len() as u64);
+            Box::new(analyzer.commits(commits))
+        }
+        None => {
+            pb.set_length(analyzer.guess_len(opts.clone())? as u64);
+            Box::new(analyzer.analyze(opts.clone())?)
+        }
+    };
+    pb.set_message("analyzing commits");
+    let head = analyzer.head(&opts)?;
+    let mut last_analyzed = None;
+    let mut count = 0;
+    for commit_analyzer in commit_analyzers.take_while(|_| !opts.is_past_deadline()) {
+        let commit = stats.time(Stage::Analyze, || -> Result<_> {
+            let _span = span.child("analyze_commit");
+            Ok(commit_analyzer?.analyze_lite()?)
+        })?;
+        last_analyzed = Some(commit.id.clone());
+        let _span = span.child("index");
+        stats.time(Stage::Sink, || client.index(commit))?;
+        count += 1;
         pb.inc(1);
     }
-    Ok(pb.finish())
+    // listed commits leave the history in between unanalyzed
+    if commits.is_none() {
+        if opts.is_past_deadline() {
+            warn!(logger, "Stopped analyzing commits, the maximum duration was exceeded"; "last_analyzed" => &last_analyzed);
+            if let Some(ref id) = last_analyzed {
+                analyzer.save_checkpoint_at(id)?;
+            }
+        } else {
+            analyzer.save_checkpoint()?;
+            state
+                .lock()
+                .unwrap()
+                .repo_mut(repo.config().repo_name())
+                .refs = analyzer.ref_heads(&opts)?;
+        }
+    }
+    pb.finish();
+    Ok(RunRepo {
+        name: repo.config().repo_name().to_owned(),
+        head: commits.map_or(head, |_| None),
+        since: commits.map_or(opts.since, |_| None),
+        commits: count,
+    })
+}
+
+/// Flags the commits which landed on the default branch without a merged
+/// pull request.
+struct DirectPushes<'client> {
+    mainline: HashSet<String>,
+    traceability: Traceability<'client>,
+}
+
+impl<'client> DirectPushes<'client> {
+    fn flag(&self, commit: &mut AnalyzedCommit) -> Result<()> {
+        if self.mainline.contains(&commit.id) {
+            commit.direct_push = Some(self.traceability.is_direct_push(&commit.id)?);
+        }
+        Ok(())
+    }
+}
+
+/// Analyzes each commit in turn on the current thread.
+fn analyze_serially<'a>(
+    mut commit_analyzers: impl Iterator<Item = std::result::Result<CommitAnalyzer<'a>, codealong::Error>>
+        + 'a,
+    stats: &'a PipelineStats,
+    span: &'a Span,
+) -> impl Iterator<Item = std::result::Result<CommitAnalysis, codealong::Error>> + 'a {
+    std::iter::from_fn(move || {
+        let commit_analyzer = {
+            let _span = span.child("revwalk");
+            let start = Instant::now();
+            let commit_analyzer = commit_analyzers.next()?;
+            stats.record(Stage::Walk, 1, start.elapsed());
+            commit_analyzer
+        };
+        Some(commit_analyzer.and_then(|commit_analyzer| {
+            stats.time(Stage::Analyze, || {
+                let commit = {
+                    let _span = span.child("analyze_commit");
+                    commit_analyzer.analyze()?
+                };
+                let _span = span.child("introduced_defects");
+                let introduced_defects = commit_analyzer.introduced_defects(&commit)?;
+                Ok(CommitAnalysis {
+                    commit,
+                    introduced_defects,
+                })
+            })
+        }))
+    })
+}
+
+/// What `index_commits` derives from the commits of a run and where it
+/// records them. The risk of the commits is assessed when `hotspots` are
+/// given, their survival rate recorded when `survival` is, their effort when
+/// `effort` is, and the weekly rollups of the repo are only indexed when
+/// `rollups` is set.
+struct IndexContext<'a> {
+    direct_pushes: Option<&'a DirectPushes<'a>>,
+    hotspots: Option<&'a Hotspots>,
+    survival: Option<&'a Survival>,
+    effort: Option<&'a Effort>,
+    rollups: bool,
+    snapshots: &'a Mutex<PersonSnapshots>,
+    stats: &'a PipelineStats,
+}
+
+/// Indexes the commits and the events derived from them, returning the id of
+/// the last commit indexed and the number of commits indexed.
+fn index_commits(
+    pb: &NamedProgressBar,
+    repo: &Repo,
+    analyses: impl Iterator<Item = std::result::Result<CommitAnalysis, codealong::Error>>,
+    context: IndexContext,
+    client: &codealong_elk::Client,
+    span: &Span,
+) -> Result<(Option<String>, u64)> {
+    let IndexContext {
+        direct_pushes,
+        hotspots,
+        survival,
+        effort,
+        rollups,
+        snapshots,
+        stats,
+    } = context;
+    let mut pairing_sessions = PairingSessions::new(Duration::minutes(
+        repo.config().config.pairing_window as i64,
+    ));
+    let repo_config = repo.config();
+    let mut repo_snapshots =
+        RepoSnapshots::new(repo_config.repo_name(), repo_config.config.rollup_period());
+    let mut dco_reports = DcoReports::new(repo_config.repo_name());
+    let mut last_analyzed = None;
+    let mut count = 0;
+    for analysis in analyses {
+        let CommitAnalysis {
+            commit: mut analyzed_commit,
+            introduced_defects,
+        } = analysis?;
+        if let Some(direct_pushes) = direct_pushes {
+            let _span = span.child("direct_push");
+            direct_pushes.flag(&mut analyzed_commit)?;
+        }
+        if let Some(hotspots) = hotspots {
+            analyzed_commit.assess_risk(hotspots);
+        }
+        if let Some(survival) = survival {
+            survival.apply(&mut analyzed_commit);
+        }
+        if let Some(effort) = effort {
+            effort.apply(&mut analyzed_commit);
+        }
+        pairing_sessions.add(&analyzed_commit);
+        repo_snapshots.add_commit(&analyzed_commit);
+        dco_reports.add_commit(&analyzed_commit);
+        snapshots.lock().unwrap().add_commit(&analyzed_commit);
+        last_analyzed = Some(analyzed_commit.id.clone());
+        {
+            let _span = span.child("index");
+            stats.time(Stage::Sink, || -> Result<()> {
+                for introduced_defect in introduced_defects {
+                    client.index(introduced_defect)?;
+                }
+                client.index(analyzed_commit)?;
+                Ok(())
+            })?;
+        }
+        count += 1;
+        pb.inc(1);
+    }
+    let _span = span.child("index");
+    for pairing_session in pairing_sessions.finish() {
+        client.index(pairing_session)?;
+    }
+    if rollups {
+        for repo_snapshot in repo_snapshots.finish() {
+            client.index(repo_snapshot)?;
+        }
+        for dco_report in dco_reports.finish() {
+            client.index(dco_report)?;
+        }
+    }
+    Ok((last_analyzed, count))
 }
 
 fn analyze_prs(
     pb: &NamedProgressBar,
     repo: &Repo,
     opts: AnalyzeOpts,
+    client: &codealong_elk::Client,
+    snapshots: &Mutex<PersonSnapshots>,
+    state: &Mutex<AnalysisState>,
+    span: &Span,
     logger: &Logger,
 ) -> Result<()> {
     info!(logger, "Analyzing pull requests");
-    let github_client = codealong_github::Client::from_env();
-    let analyzer = PullRequestsAnalyzer::from_repo(repo, &github_client, logger)?;
-    let client = codealong_elk::Client::default();
+    let github_client =
+        codealong_github::Client::from_env().for_clone_url(&repo.repo_info().clone_url);
+    let analyzer = PullRequestsAnalyzer::from_repo(repo, &github_client, logger)?
+        .with_progress(pb.progress(span.profiler()));
+    pb.set_message("finding hotspots");
+    // pull requests are assessed against the branch they usually target
+    let hotspots = RepoAnalyzer::from_repo(repo, logger)?.hotspots(
+        &AnalyzeOpts {
+            default_branch_only: true,
+            ..opts.clone()
+        },
+        &(Utc::now() - Duration::days(HOTSPOT_DAYS)),
+    )?;
     pb.set_message("calculating");
     let count = analyzer.guess_len(opts.clone())?;
     pb.set_length(count as u64);
     pb.set_message("analyzing pull requests");
-    for pull_request_analyzer in analyzer.analyze(opts)? {
-        client.index(pull_request_analyzer?.analyze()?)?;
+    let mut latest_updated_at = None;
+    for pull_request_analyzer in analyzer.analyze(opts.clone())? {
+        if opts.is_past_deadline() {
+            warn!(
+                logger,
+                "Stopped analyzing pull requests, the maximum duration was exceeded"
+            );
+            break;
+        }
+        let (mut analyzed_pr, reviews) = {
+            let _span = span.child("analyze_pull_request");
+            pull_request_analyzer?.analyze_with_reviews()?
+        };
+        analyzed_pr.assess_risk(&hotspots);
+        analyzed_pr.add_to_snapshots(&mut snapshots.lock().unwrap());
+        latest_updated_at = latest_updated_at.max(Some(*analyzed_pr.updated_at()));
+        let _span = span.child("index");
+        for breach in analyzed_pr.slo_breaches(&repo.config().config.slos) {
+            client.index(breach)?;
+        }
+        client.index(analyzed_pr)?;
+        for review in reviews {
+            review.add_to_snapshots(&mut snapshots.lock().unwrap());
+            client.index(review)?;
+        }
         pb.inc(1);
     }
+    // an interrupted analysis leaves older pull requests unanalyzed
+    if let Some(updated_at) = latest_updated_at.filter(|_| !opts.is_past_deadline()) {
+        state
+            .lock()
+            .unwrap()
+            .repo_mut(repo.config().repo_name())
+            .pull_requests_analyzed(updated_at);
+    }
     Ok(pb.finish())
 }
 
-fn analyze_opts_from_args(repo: &Repo, matches: &clap::ArgMatches) -> Result<AnalyzeOpts> {
+/// Analyzes the merge requests of a GitLab project as pull requests.
+fn analyze_mrs(
+    pb: &NamedProgressBar,
+    repo: &Repo,
+    opts: AnalyzeOpts,
+    client: &codealong_elk::Client,
+    snapshots: &Mutex<PersonSnapshots>,
+    state: &Mutex<AnalysisState>,
+    span: &Span,
+    logger: &Logger,
+) -> Result<()> {
+    info!(logger, "Analyzing merge requests");
+    let gitlab_client = codealong_gitlab::Client::from_env();
+    let analyzer = MergeRequestsAnalyzer::from_repo(repo, &gitlab_client, logger)?;
+    pb.set_message("finding hotspots");
+    // merge requests are assessed against the branch they usually target
+    let hotspots = RepoAnalyzer::from_repo(repo, logger)?.hotspots(
+        &AnalyzeOpts {
+            default_branch_only: true,
+            ..opts.clone()
+        },
+        &(Utc::now() - Duration::days(HOTSPOT_DAYS)),
+    )?;
+    pb.set_message("calculating");
+    // the count only sizes the progress bar, and GitLab omits it for large
+    // projects
+    let count = analyzer.guess_len(opts.clone()).unwrap_or(0);
+    pb.set_length(count as u64);
+    pb.set_message("analyzing merge requests");
+    let mut latest_updated_at = None;
+    for merge_request_analyzer in analyzer.analyze(opts.clone())? {
+        if opts.is_past_deadline() {
+            warn!(
+                logger,
+                "Stopped analyzing merge requests, the maximum duration was exceeded"
+            );
+            break;
+        }
+        let mut analyzed_mr = {
+            let _span = span.child("analyze_merge_request");
+            merge_request_analyzer?.analyze()?
+        };
+        analyzed_mr.assess_risk(&hotspots);
+        analyzed_mr.add_to_snapshots(&mut snapshots.lock().unwrap());
+        latest_updated_at = latest_updated_at.max(Some(*analyzed_mr.updated_at()));
+        let _span = span.child("index");
+        for breach in analyzed_mr.slo_breaches(&repo.config().config.slos) {
+            client.index(breach)?;
+        }
+        client.index(analyzed_mr)?;
+        pb.inc(1);
+    }
+    // an interrupted analysis leaves older merge requests unanalyzed
+    if let Some(updated_at) = latest_updated_at.filter(|_| !opts.is_past_deadline()) {
+        state
+            .lock()
+            .unwrap()
+            .repo_mut(repo.config().repo_name())
+            .pull_requests_analyzed(updated_at);
+    }
+    pb.finish();
+    Ok(())
+}
+
+/// Analyzes the pull requests of a Bitbucket Cloud repository.
+fn analyze_bitbucket_prs(
+    pb: &NamedProgressBar,
+    repo: &Repo,
+    opts: AnalyzeOpts,
+    client: &codealong_elk::Client,
+    snapshots: &Mutex<PersonSnapshots>,
+    state: &Mutex<AnalysisState>,
+    span: &Span,
+    logger: &Logger,
+) -> Result<()> {
+    info!(logger, "Analyzing Bitbucket pull requests");
+    let bitbucket_client = codealong_bitbucket::Client::from_env();
+    let analyzer =
+        codealong_bitbucket::PullRequestsAnalyzer::from_repo(repo, &bitbucket_client, logger)?;
+    pb.set_message("finding hotspots");
+    // pull requests are assessed against the branch they usually target
+    let hotspots = RepoAnalyzer::from_repo(repo, logger)?.hotspots(
+        &AnalyzeOpts {
+            default_branch_only: true,
+            ..opts.clone()
+        },
+        &(Utc::now() - Duration::days(HOTSPOT_DAYS)),
+    )?;
+    pb.set_message("calculating");
+    // the count only sizes the progress bar, and Bitbucket may omit it
+    let count = analyzer.guess_len(opts.clone()).unwrap_or(0);
+    pb.set_length(count as u64);
+    pb.set_message("analyzing pull requests");
+    let mut latest_updated_at = None;
+    for pull_request_analyzer in analyzer.analyze(opts.clone())? {
+        if opts.is_past_deadline() {
+            warn!(
+                logger,
+                "Stopped analyzing pull requests, the maximum duration was exceeded"
+            );
+            break;
+        }
+        let mut analyzed_pr = {
+            let _span = span.child("analyze_pull_request");
+            pull_request_analyzer?.analyze()?
+        };
+        analyzed_pr.assess_risk(&hotspots);
+        analyzed_pr.add_to_snapshots(&mut snapshots.lock().unwrap());
+        latest_updated_at = latest_updated_at.max(Some(*analyzed_pr.updated_at()));
+        let _span = span.child("index");
+        for breach in analyzed_pr.slo_breaches(&repo.config().config.slos) {
+            client.index(breach)?;
+        }
+        client.index(analyzed_pr)?;
+        pb.inc(1);
+    }
+    // an interrupted analysis leaves older pull requests unanalyzed
+    if let Some(updated_at) = latest_updated_at.filter(|_| !opts.is_past_deadline()) {
+        state
+            .lock()
+            .unwrap()
+            .repo_mut(repo.config().repo_name())
+            .pull_requests_analyzed(updated_at);
+    }
+    pb.finish();
+    Ok(())
+}
+
+/// Indexes the pull requests reconstructed from the merge commits of `repo`.
+fn reconstruct_prs(
+    pb: &NamedProgressBar,
+    repo: &Repo,
+    opts: AnalyzeOpts,
+    client: &codealong_elk::Client,
+    state: &Mutex<AnalysisState>,
+    span: &Span,
+    logger: &Logger,
+) -> Result<()> {
+    info!(logger, "Reconstructing pull requests from merge commits");
+    let analyzer =
+        RepoAnalyzer::from_repo(repo, logger)?.with_progress(pb.progress(span.profiler()));
+    pb.set_message("reconstructing pull requests");
+    let pull_requests = {
+        let _span = span.child("reconstruct");
+        analyzer.reconstructed_pull_requests(&opts)?
+    };
+    pb.set_length(pull_requests.len() as u64);
+    let latest_merged_at = pull_requests.iter().map(|pr| pr.merged_at).max();
+    let _span = span.child("index");
+    for pull_request in pull_requests {
+        client.index(pull_request)?;
+        pb.inc(1);
+    }
+    if let Some(merged_at) = latest_merged_at {
+        state
+            .lock()
+            .unwrap()
+            .repo_mut(repo.config().repo_name())
+            .pull_requests_analyzed(merged_at);
+    }
+    pb.finish();
+    Ok(())
+}
+
+fn analyze_opts_from_args(
+    repo: &Repo,
+    matches: &clap::ArgMatches,
+    deadline: Option<DateTime<Utc>>,
+) -> Result<AnalyzeOpts> {
     let since =
         if let Some(since) = matches.value_of("since") {
             Some(humantime::parse_duration(since).map(|duration| {
@@ -158,5 +1073,11 @@ fn analyze_opts_from_args(repo: &Repo, matches: &clap::ArgMatches) -> Result<Ana
         since,
         ignore_unknown_authors: matches.is_present("skip_unknown_authors")
             || repo.repo_info().fork && matches.is_present("skip_unknown_authors_in_forks"),
+        resume: matches.is_present("resume"),
+        deadline,
+        default_branch_only: matches.is_present("default_branch_only"),
+        branch: matches.value_of("branch").map(str::to_owned),
+        first_parent: matches.is_present("first_parent"),
+        analyzed_heads: vec![],
     })
 }
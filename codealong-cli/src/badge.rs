@@ -0,0 +1,31 @@
+use std::fs;
+
+use slog::Logger;
+
+use codealong::Badge;
+
+use crate::error::Result;
+use crate::utils::build_es_client;
+
+/// Renders an SVG badge of a metric of a repo from the analyzed events
+pub fn badge(matches: &clap::ArgMatches, logger: &Logger) -> Result<()> {
+    let repo = matches.value_of("repo").unwrap();
+    let metric = matches.value_of("metric").unwrap();
+    let days = matches.value_of("days").unwrap_or("90").parse::<u32>()?;
+
+    let client = build_es_client(matches)?;
+    let badge = match metric {
+        "bus-factor" => Badge::bus_factor(&client.commits_by_author(repo, days)?),
+        "active-contributors" => {
+            Badge::active_contributors(client.commits_by_author(repo, days)?.len() as u64)
+        }
+        _ => Badge::review_latency(client.median_time_to_resolve(repo, days)?),
+    };
+    info!(logger, "Rendered badge"; "repo" => repo, "metric" => metric, "value" => &badge.message);
+
+    match matches.value_of("out") {
+        Some(out) => fs::write(out, badge.to_svg())?,
+        None => print!("{}", badge.to_svg()),
+    }
+    Ok(())
+}
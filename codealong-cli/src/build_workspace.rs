@@ -1,7 +1,7 @@
 use slog::Logger;
 use std::path::Path;
 
-use codealong::{Config, RepoInfo, Workspace};
+use codealong::{discover_mirrors, Config, RepoInfo, Workspace};
 
 use crate::error::*;
 
@@ -25,11 +25,48 @@ pub fn build_workspace(matches: &clap::ArgMatches, logger: &Logger) -> Result<Wo
         }
     }
 
+    let values = |name| {
+        matches
+            .values_of(name)
+            .map(|values| values.map(|v| v.to_owned()).collect())
+            .unwrap_or_default()
+    };
+    res.add_config(Config {
+        only_paths: values("only_paths"),
+        exclude_paths: values("exclude_paths"),
+        ..Config::default()
+    });
+
     let repos = expand_repos(matches)?;
     for repo_info in repos {
         res.add(repo_info, None)?;
     }
 
+    let mut github_orgs = res.github_orgs().to_vec();
+    if let Some(orgs) = matches.values_of("github_org") {
+        github_orgs.extend(orgs.map(|org| org.to_owned()));
+    }
+    if !github_orgs.is_empty() {
+        codealong::ensure_online("discovering the repos of Github organizations")?;
+        let client = codealong_github::Client::from_env();
+        for github_org in github_orgs {
+            let entries = codealong_github::repos_for_org(&client, &github_org, logger)?;
+            let count = entries.len();
+            let added = res.add_discovered(entries);
+            info!(logger, "Discovered repos"; "github_org" => &github_org, "count" => count, "added" => added);
+        }
+    }
+
+    if let Some(dirs) = matches.values_of("mirrors") {
+        for dir in dirs {
+            let entries = discover_mirrors(Path::new(dir))?;
+            info!(logger, "Discovered mirrors"; "dir" => dir, "count" => entries.len());
+            for entry in entries {
+                res.add(entry.repo_info, entry.path)?;
+            }
+        }
+    }
+
     Ok(res)
 }
 
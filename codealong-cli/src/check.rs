@@ -0,0 +1,69 @@
+use std::path::Path;
+
+use chrono::{Duration, Utc};
+use slog::Logger;
+
+use codealong::{discover, CommitAnalyzer, Hotspots, Policy, RepoConfig};
+
+use crate::error::{ErrorKind, Result};
+
+/// Analyzes a range of commits of a local repository, printing the risk of
+/// each, and fails if any of them violate the policy, e.g. to gate pushes in
+/// CI
+pub fn check(matches: &clap::ArgMatches, logger: &Logger) -> Result<()> {
+    let path = Path::new(matches.value_of("path").unwrap_or("."));
+    let policy = Policy::from_path(Path::new(matches.value_of("policy").unwrap()))?;
+    let repo = discover(path)?;
+    let config = RepoConfig::from_repository(&repo)?;
+    let churn_days = matches
+        .value_of("churn_days")
+        .unwrap_or("90")
+        .parse::<i64>()?;
+    let hotspots = Hotspots::walk(
+        &repo,
+        repo.head()?.peel_to_commit()?.id(),
+        &config.config,
+        &(Utc::now() - Duration::days(churn_days)),
+    )?;
+    let mut revwalk = repo.revwalk()?;
+    match matches.value_of("range") {
+        Some(range) => revwalk.push_range(range)?,
+        None => revwalk.push_head()?,
+    }
+
+    let mut checker = policy.checker();
+    let mut checked = 0;
+    for oid in revwalk {
+        let commit = repo.find_commit(oid?)?;
+        let mut analyzed = CommitAnalyzer::new(&repo, commit.clone(), &config, logger).analyze()?;
+        analyzed.assess_risk(&hotspots);
+        if let Some(ref risk) = analyzed.risk {
+            println!(
+                "{} risk {} ({:.2}): hotspots {:.2}, unfamiliarity {:.2}, size {:.2}, untested {:.2}",
+                analyzed.id,
+                risk.level.as_str(),
+                risk.score,
+                risk.hotspot,
+                risk.unfamiliarity,
+                risk.size,
+                risk.untested
+            );
+        }
+        checker.check_commit(&repo, &commit, &analyzed)?;
+        checked += 1;
+    }
+    let violations = checker.finish();
+    info!(logger, "Checked policy"; "commits" => checked, "violations" => violations.len());
+
+    for violation in &violations {
+        match violation.commit {
+            Some(ref commit) => println!("{} ({}): {}", commit, violation.rule, violation.message),
+            None => println!("({}): {}", violation.rule, violation.message),
+        }
+    }
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(ErrorKind::PolicyViolations(violations.len()).into())
+    }
+}
@@ -0,0 +1,78 @@
+use std::path::Path;
+
+use chrono::{Duration, Utc};
+use git2::Repository;
+use slog::Logger;
+
+use codealong::{discover, CiReport, RepoConfig};
+
+use crate::error::{ErrorKind, Result};
+
+/// Reports on the changes of a pull request against its base branch, e.g.
+/// from its CI pipeline, printing the report or commenting it on the pull
+/// request
+pub fn ci_report(matches: &clap::ArgMatches, logger: &Logger) -> Result<()> {
+    let path = Path::new(matches.value_of("path").unwrap_or("."));
+    let repo = discover(path)?;
+    let config = RepoConfig::from_repository(&repo)?;
+    let churn_days = matches
+        .value_of("churn_days")
+        .unwrap_or("90")
+        .parse::<i64>()?;
+    let base = match matches.value_of("base") {
+        Some(base) => base.to_owned(),
+        None => remote_default_branch(&repo).ok_or("no base branch found, pass --base")?,
+    };
+    let head = matches.value_of("head").unwrap_or("HEAD");
+    let report = CiReport::build(
+        &repo,
+        &repo.revparse_single(&base)?.peel_to_commit()?,
+        &repo.revparse_single(head)?.peel_to_commit()?,
+        &config.config,
+        &(Utc::now() - Duration::days(churn_days)),
+    )?;
+    info!(logger, "Built CI report"; "base" => &base, "commits" => report.commits, "hotspots" => report.hotspots.len(), "owners" => report.owners.len());
+
+    let markdown = report.to_markdown();
+    match matches.value_of("pull_request") {
+        Some(number) => {
+            let number = number.parse::<u64>()?;
+            let info = &config.repo;
+            if let Some(ref github_name) = info.github_name {
+                codealong_github::Client::from_env()
+                    .for_clone_url(&info.clone_url)
+                    .comment(github_name, number, &markdown)?;
+            } else if let Some(ref gitlab_name) = info.gitlab_name {
+                codealong_gitlab::Client::from_env().create_note(gitlab_name, number, &markdown)?;
+            } else if let Some(ref bitbucket_name) = info.bitbucket_name {
+                codealong_bitbucket::Client::from_env().create_comment(
+                    bitbucket_name,
+                    number,
+                    &markdown,
+                )?;
+            } else {
+                return Err(ErrorKind::UnknownForge(info.name.clone()).into());
+            }
+            info!(logger, "Commented on pull request"; "number" => number);
+        }
+        None => match matches.value_of("format") {
+            Some("json") => println!("{}", serde_json::to_string_pretty(&report)?),
+            _ => print!("{}", markdown),
+        },
+    }
+    Ok(())
+}
+
+/// The default branch of `origin`. Unlike `codealong::default_branch`, the
+/// branch checked out is not considered, as in CI it is the pull request.
+fn remote_default_branch(repo: &Repository) -> Option<String> {
+    repo.find_reference("refs/remotes/origin/HEAD")
+        .ok()
+        .and_then(|reference| reference.symbolic_target().map(str::to_owned))
+        .into_iter()
+        .chain(vec![
+            "refs/remotes/origin/main".to_owned(),
+            "refs/remotes/origin/master".to_owned(),
+        ])
+        .find(|name| repo.refname_to_id(name).is_ok())
+}
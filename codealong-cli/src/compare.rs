@@ -0,0 +1,123 @@
+use std::fs::File;
+use std::path::Path;
+
+use chrono::offset::TimeZone;
+use chrono::Utc;
+use slog::Logger;
+
+use codealong::{
+    config_hash, AnalyzeOpts, Comparison, Config, MetricDelta, Repo, RepoAnalyzer, Side,
+};
+
+use crate::analyze::build_repos;
+use crate::build_workspace::build_workspace;
+use crate::error::Result;
+use crate::utils::{build_es_client, configure_client};
+
+/// Analyzes the same range of commits with the workspace config and with a
+/// candidate config, indexing each into indices of their own, and reports
+/// how the metrics of each repo differ between them. The candidate config is
+/// merged over the workspace config, so that what it sets overrides it.
+pub fn compare(matches: &clap::ArgMatches, logger: &Logger) -> Result<()> {
+    let workspace = build_workspace(matches, logger)?;
+    let mut candidate_config = Config::default();
+    for path in matches.values_of("candidate_config").unwrap() {
+        candidate_config.merge(Config::overlay_from_path(Path::new(path))?);
+    }
+    let mut candidate_workspace_config = workspace.config().clone();
+    candidate_workspace_config.merge(candidate_config.clone());
+
+    let baseline_client = configure_client(
+        build_es_client(matches)?
+            .with_index_prefix(matches.value_of("baseline_index").unwrap())
+            .with_config_hash(&config_hash(workspace.config())),
+        workspace.config(),
+    )?;
+    let candidate_client = configure_client(
+        build_es_client(matches)?
+            .with_index_prefix(matches.value_of("candidate_index").unwrap())
+            .with_config_hash(&config_hash(&candidate_workspace_config)),
+        &candidate_workspace_config,
+    )?;
+
+    let opts = AnalyzeOpts {
+        since: match matches.value_of("since") {
+            Some(since) => {
+                let duration = humantime::parse_duration(since)?;
+                Some(Utc.timestamp(Utc::now().timestamp() - duration.as_secs() as i64, 0))
+            }
+            None => None,
+        },
+        ..AnalyzeOpts::default()
+    };
+
+    let mut comparison = Comparison::new();
+    for repo in build_repos(&workspace, matches) {
+        let logger = logger.new(o!("repo" => repo.repo_info().name.to_owned()));
+        if repo.repository().is_err() {
+            warn!(logger, "Skipping repo which has not been cloned yet");
+            continue;
+        }
+        let candidate_repo = repo.with_config(candidate_config.clone());
+        for (side, repo, client) in vec![
+            (Side::Baseline, repo, &baseline_client),
+            (Side::Candidate, candidate_repo, &candidate_client),
+        ] {
+            info!(logger, "Analyzing commits"; "side" => format!("{:?}", side));
+            analyze_side(&mut comparison, side, &repo, opts.clone(), client, &logger)?;
+        }
+    }
+    baseline_client.flush()?;
+    candidate_client.flush()?;
+
+    let deltas = comparison.deltas();
+    if let Some(path) = matches.value_of("report") {
+        serde_json::to_writer_pretty(File::create(path)?, &deltas)?;
+    }
+    print_report(&comparison, &deltas);
+    Ok(())
+}
+
+fn analyze_side(
+    comparison: &mut Comparison,
+    side: Side,
+    repo: &Repo,
+    opts: AnalyzeOpts,
+    client: &codealong_elk::Client,
+    logger: &Logger,
+) -> Result<()> {
+    let analyzer = RepoAnalyzer::from_repo(repo, logger)?;
+    let repo_config = repo.config();
+    for commit_analyzer in analyzer.analyze(opts)? {
+        let analyzed_commit = commit_analyzer?.analyze()?;
+        comparison.add_commit(side, repo_config.repo_name(), &analyzed_commit);
+        client.index(analyzed_commit)?;
+    }
+    Ok(())
+}
+
+fn print_report(comparison: &Comparison, deltas: &[MetricDelta]) {
+    for repo in comparison.repos() {
+        println!(
+            "{}: {} commits changed",
+            repo,
+            comparison.changed_commits(repo)
+        );
+        for delta in deltas
+            .iter()
+            .filter(|delta| delta.repo == repo && delta.delta() != 0.0)
+        {
+            println!(
+                "  {:<18} {:>12.1} {:>12.1} {:>+12.1} {}",
+                delta.metric,
+                delta.baseline,
+                delta.candidate,
+                delta.delta(),
+                delta
+                    .relative_change()
+                    .map(|change| format!("({:+.1}%)", change * 100.0))
+                    .unwrap_or_default()
+            );
+        }
+    }
+}
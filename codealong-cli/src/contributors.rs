@@ -0,0 +1,36 @@
+use std::path::Path;
+
+use slog::Logger;
+
+use codealong::{discover, previous_release, CommitAnalyzer, ReleaseSummary, RepoConfig};
+
+use crate::error::Result;
+
+/// Prints the changes and contributors of a release of a local repository as
+/// markdown, for use in a changelog
+pub fn contributors(matches: &clap::ArgMatches, logger: &Logger) -> Result<()> {
+    let path = Path::new(matches.value_of("path").unwrap_or("."));
+    let repo = discover(path)?;
+    let config = RepoConfig::from_repository(&repo)?;
+    let release = matches.value_of("release").unwrap_or("HEAD");
+    let previous = match matches.value_of("previous") {
+        Some(previous) => Some(previous.to_owned()),
+        None => previous_release(&repo, release)?,
+    };
+
+    let mut revwalk = repo.revwalk()?;
+    match previous {
+        Some(ref previous) => revwalk.push_range(&format!("{}..{}", previous, release))?,
+        None => revwalk.push(repo.revparse_single(release)?.peel_to_commit()?.id())?,
+    }
+    let mut summary = ReleaseSummary::new();
+    for oid in revwalk {
+        let commit = repo.find_commit(oid?)?;
+        summary.add_commit(&CommitAnalyzer::new(&repo, commit, &config, logger).analyze()?);
+    }
+    let summary = summary.finish();
+    info!(logger, "Summarized release"; "release" => release, "previous" => &previous, "changes" => summary.changes.len());
+
+    print!("{}", summary.to_markdown());
+    Ok(())
+}
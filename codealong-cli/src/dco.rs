@@ -0,0 +1,57 @@
+use std::path::Path;
+
+use chrono::{TimeZone, Utc};
+use slog::Logger;
+
+use codealong::{discover, is_signed_off, DcoReports, Identity, RepoConfig};
+
+use crate::error::{ErrorKind, Result};
+
+/// Checks that every commit in a range of a local repository is signed off by
+/// its author and prints a DCO compliance report per contributor
+pub fn dco(matches: &clap::ArgMatches, logger: &Logger) -> Result<()> {
+    let path = Path::new(matches.value_of("path").unwrap_or("."));
+    let repo = discover(path)?;
+    let config = RepoConfig::from_repository(&repo)?;
+    let mut revwalk = repo.revwalk()?;
+    match matches.value_of("range") {
+        Some(range) => revwalk.push_range(range)?,
+        None => revwalk.push_head()?,
+    }
+
+    let mut reports = DcoReports::new(config.repo_name());
+    for oid in revwalk {
+        let commit = repo.find_commit(oid?)?;
+        if commit.parent_count() > 1 {
+            continue;
+        }
+        let author = Identity::from(commit.author());
+        let signed_off = is_signed_off(commit.message().unwrap_or(""), &author);
+        let committed_at = Utc.timestamp(commit.time().seconds(), 0);
+        reports.add(
+            &commit.id().to_string(),
+            &config.config.person_for_identity_at(&author, &committed_at),
+            signed_off,
+            &committed_at,
+        );
+    }
+    let reports = reports.finish();
+    info!(logger, "Checked DCO sign-offs"; "contributors" => reports.len());
+
+    let mut missing = 0;
+    for report in &reports {
+        println!(
+            "{}: {}/{} commits signed off",
+            report.contributor.id, report.signed_off, report.commits
+        );
+        for commit in &report.missing {
+            println!("  missing sign-off: {}", commit);
+        }
+        missing += report.missing.len();
+    }
+    if missing > 0 {
+        Err(ErrorKind::MissingSignOff(missing).into())
+    } else {
+        Ok(())
+    }
+}
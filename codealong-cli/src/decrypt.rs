@@ -0,0 +1,34 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+
+use slog::Logger;
+
+use codealong_elk::FieldDecryptor;
+
+use crate::error::Result;
+
+/// Decrypts the encrypted fields of newline delimited JSON events, e.g. as
+/// written by `analyze --stdout` or exported from elasticsearch, to stdout
+pub fn decrypt(matches: &clap::ArgMatches, logger: &Logger) -> Result<()> {
+    let private_key = fs::read(matches.value_of("private_key").unwrap())?;
+    let mut decryptor = FieldDecryptor::new(&private_key)?;
+    let input: Box<dyn BufRead> = match matches.value_of("path") {
+        Some(path) => Box::new(BufReader::new(File::open(path)?)),
+        None => Box::new(BufReader::new(io::stdin())),
+    };
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    let mut count = 0;
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut event = serde_json::from_str(&line)?;
+        decryptor.decrypt(&mut event)?;
+        writeln!(stdout, "{}", serde_json::to_string(&event)?)?;
+        count += 1;
+    }
+    info!(logger, "Decrypted events"; "count" => count);
+    Ok(())
+}
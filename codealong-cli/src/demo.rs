@@ -0,0 +1,102 @@
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+use chrono::Utc;
+use clap::App;
+use slog::Logger;
+
+use codealong::generate_sample_workspace;
+
+use crate::analyze::analyze;
+use crate::error::Result;
+use crate::report::report;
+
+/// Generates a workspace of synthetic repos, analyzes it as `analyze` would,
+/// writing the events as newline delimited JSON, and opens a report of it,
+/// so that codealong can be evaluated without configuring tokens or
+/// Elasticsearch
+pub fn demo(matches: &clap::ArgMatches, logger: &Logger) -> Result<()> {
+    let dir = env::current_dir()?.join(matches.value_of("dir").unwrap_or("codealong-demo"));
+    info!(logger, "Generating sample workspace"; "dir" => dir.display().to_string());
+    generate_sample_workspace(&dir, &Utc::now())?;
+    let workspace = dir.to_string_lossy().into_owned();
+    let events = dir.join("events.ndjson").to_string_lossy().into_owned();
+    let html = dir.join("report.html").to_string_lossy().into_owned();
+
+    info!(logger, "Analyzing sample workspace");
+    run(
+        &[
+            "analyze",
+            "--workspace",
+            &workspace,
+            "--format",
+            "ndjson",
+            "--output",
+            &events,
+            "--reconstruct-pull-requests",
+        ],
+        logger,
+    )?;
+    run(&["report", "--workspace", &workspace], logger)?;
+    run(
+        &[
+            "report",
+            "--workspace",
+            &workspace,
+            "--format",
+            "html",
+            "--out",
+            &html,
+        ],
+        logger,
+    )?;
+    println!("Events written to {}", events);
+    println!("Report written to {}", html);
+    println!(
+        "Explore further with e.g. `codealong contributors --workspace {}`",
+        workspace
+    );
+    if !matches.is_present("no_open") {
+        if let Err(e) = open(Path::new(&html)) {
+            warn!(logger, "Unable to open the report in a browser"; "error" => e.to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Runs the subcommand parsed from `args` as if codealong had been invoked
+/// with them.
+fn run(args: &[&str], logger: &Logger) -> Result<()> {
+    let yml = load_yaml!("cli.yml");
+    let matches = App::from_yaml(yml)
+        .get_matches_from_safe(std::iter::once("codealong").chain(args.iter().cloned()))
+        .map_err(|e| e.to_string())?;
+    match matches.subcommand() {
+        ("analyze", Some(matches)) => analyze(matches, logger),
+        ("report", Some(matches)) => report(matches, logger),
+        _ => unreachable!(),
+    }
+}
+
+/// Opens `path` with the default application of the platform.
+fn open(path: &Path) -> std::io::Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        Command::new("xdg-open")
+    };
+    let status = command.arg(path).status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!(
+            "opener exited with {}",
+            status
+        )))
+    }
+}
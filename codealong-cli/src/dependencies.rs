@@ -0,0 +1,30 @@
+use std::path::Path;
+
+use chrono::{Duration, Utc};
+use slog::Logger;
+
+use codealong::{discover, DependencyGraph, RepoConfig};
+
+use crate::error::Result;
+
+/// Prints the dependencies between the directories of a local repository at
+/// HEAD
+pub fn dependencies(matches: &clap::ArgMatches, logger: &Logger) -> Result<()> {
+    let path = Path::new(matches.value_of("path").unwrap_or("."));
+    let repo = discover(path)?;
+    let config = RepoConfig::from_repository(&repo)?;
+    let churn_days = matches
+        .value_of("churn_days")
+        .unwrap_or("90")
+        .parse::<i64>()?;
+    let head = repo.head()?.peel_to_commit()?;
+    let graph = DependencyGraph::build(&repo, &head, &(Utc::now() - Duration::days(churn_days)))?;
+    let directories = graph.directories(config.repo_name());
+    info!(logger, "Built dependency graph"; "directories" => directories.len(), "hotspots" => directories.iter().filter(|d| d.hotspot).count());
+
+    match matches.value_of("format") {
+        Some("json") => println!("{}", serde_json::to_string_pretty(&directories)?),
+        _ => print!("{}", graph.to_dot(config.repo_name())),
+    }
+    Ok(())
+}
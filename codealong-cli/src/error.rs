@@ -1,6 +1,8 @@
 use codealong;
+use codealong_bitbucket;
 use codealong_elk;
 use codealong_github;
+use codealong_gitlab;
 
 error_chain! {
     errors {
@@ -8,6 +10,41 @@ error_chain! {
             description("invalid repo")
             display("invalid repo: '{}'", repo)
         }
+
+        InvalidDuration(duration: String) {
+            description("invalid duration")
+            display("invalid duration: '{}'", duration)
+        }
+
+        MissingSignOff(count: usize) {
+            description("missing sign-off")
+            display("{} commits are missing a DCO sign-off", count)
+        }
+
+        PolicyViolations(count: usize) {
+            description("policy violations")
+            display("{} policy violations", count)
+        }
+
+        NoThroughputHistory(team: String) {
+            description("no throughput history")
+            display("no merged pull requests found for team: '{}'", team)
+        }
+
+        RunNotFound(id: String) {
+            description("run not found")
+            display("no analysis run found with id: '{}'", id)
+        }
+
+        UnknownForge(repo: String) {
+            description("unknown forge")
+            display("repo is not hosted on Github, GitLab or Bitbucket: '{}'", repo)
+        }
+
+        RequiresNetwork(reasons: String) {
+            description("network access required")
+            display("network access is disabled in offline mode but required for: {}", reasons)
+        }
     }
 
     foreign_links {
@@ -16,12 +53,16 @@ error_chain! {
         Url(url::ParseError);
         ArgParse(std::num::ParseIntError);
         Config(serde_yaml::Error);
+        Json(serde_json::Error);
         DurationParse(humantime::DurationError);
+        DateParse(chrono::ParseError);
     }
 
     links {
         Core(codealong::Error, codealong::ErrorKind);
+        Bitbucket(codealong_bitbucket::Error, codealong_bitbucket::ErrorKind);
         Elk(codealong_elk::Error, codealong_elk::ErrorKind);
         Github(codealong_github::Error, codealong_github::ErrorKind);
+        Gitlab(codealong_gitlab::Error, codealong_gitlab::ErrorKind);
     }
 }
@@ -0,0 +1,47 @@
+use chrono::Utc;
+use slog::Logger;
+
+use crate::error::{ErrorKind, Result};
+use crate::utils::build_es_client;
+
+/// Forecasts when a team will complete a number of items based on its
+/// historical pull request throughput
+pub fn forecast(matches: &clap::ArgMatches, logger: &Logger) -> Result<()> {
+    let team = matches.value_of("team").unwrap();
+    let items = matches.value_of("items").unwrap().parse::<u64>()?;
+    let weeks = matches.value_of("weeks").unwrap_or("12").parse::<u32>()?;
+    let trials = matches
+        .value_of("trials")
+        .unwrap_or("10000")
+        .parse::<usize>()?;
+
+    let client = build_es_client(matches)?;
+    let history = client.weekly_merged_pull_requests(team, weeks)?;
+    info!(logger, "Loaded throughput history"; "team" => team, "weeks" => history.len());
+
+    let forecast = codealong::forecast(
+        &history,
+        items,
+        trials,
+        &Utc::now(),
+        &mut rand::thread_rng(),
+    )
+    .ok_or_else(|| ErrorKind::NoThroughputHistory(team.to_owned()))?;
+
+    println!(
+        "Forecast for {} items by team {} ({} trials over {} weeks of history):",
+        items,
+        team,
+        trials,
+        history.len()
+    );
+    for percentile in forecast.percentiles {
+        println!(
+            "  {}%: {} ({} weeks)",
+            percentile.percentile,
+            percentile.completed_by.format("%Y-%m-%d"),
+            percentile.weeks
+        );
+    }
+    Ok(())
+}
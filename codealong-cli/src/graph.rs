@@ -0,0 +1,32 @@
+use std::path::Path;
+
+use slog::Logger;
+
+use codealong::{discover, CollaborationGraph, GraphKind, RepoConfig};
+
+use crate::error::Result;
+
+/// Prints a graph of how the contributors to a local repository collaborate
+pub fn graph(matches: &clap::ArgMatches, logger: &Logger) -> Result<()> {
+    let path = Path::new(matches.value_of("path").unwrap_or("."));
+    let repo = discover(path)?;
+    let config = RepoConfig::from_repository(&repo)?;
+    let kind = match matches.value_of("kind") {
+        Some("review-network") => GraphKind::ReviewNetwork,
+        _ => GraphKind::CoChange,
+    };
+    let graph = CollaborationGraph::from_repository(
+        &repo,
+        &config.config,
+        kind,
+        matches.value_of("range"),
+    )?;
+    info!(logger, "Built collaboration graph"; "kind" => kind.name(), "nodes" => graph.nodes.len(), "edges" => graph.edges.len());
+
+    match matches.value_of("format") {
+        Some("graphml") => print!("{}", graph.to_graphml()),
+        Some("json") => println!("{}", serde_json::to_string_pretty(&graph)?),
+        _ => print!("{}", graph.to_dot()),
+    }
+    Ok(())
+}
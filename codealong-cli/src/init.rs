@@ -23,6 +23,7 @@ fn build_config(matches: &clap::ArgMatches, logger: &Logger) -> Result<Workspace
     let mut config = WorkspaceConfig::default();
     let client = codealong_github::Client::from_env();
     if let Some(github_orgs) = matches.values_of("github_org") {
+        codealong::ensure_online("crawling Github organizations")?;
         for github_org in github_orgs {
             let org_config = config_from_org(&client, github_org, logger)?;
             config.merge(org_config);
@@ -18,6 +18,10 @@ pub fn initialize_repos(
     logger: &Logger,
 ) -> Result<()> {
     info!(logger, "Initializing {} repos", repos.len());
+    // the progress pool would wait forever on workers never started
+    if repos.is_empty() {
+        return Ok(());
+    }
     let num_threads = std::cmp::min(
         matches
             .value_of("concurrency")
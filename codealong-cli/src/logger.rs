@@ -2,7 +2,7 @@ use std::str::FromStr;
 
 use slog::{Drain, Duplicate, Logger};
 use sloggers::file::FileLoggerBuilder;
-use sloggers::terminal::TerminalLoggerBuilder;
+use sloggers::terminal::{Destination, TerminalLoggerBuilder};
 use sloggers::types::Severity;
 use sloggers::Build;
 
@@ -16,9 +16,36 @@ pub fn build_logger(matches: &clap::ArgMatches) -> slog::Logger {
         let verbosity = Severity::from_str(matches.value_of("verbosity").unwrap()).unwrap();
         let mut builder = TerminalLoggerBuilder::new();
         builder.level(verbosity);
+        // stdout is reserved for events when they are streamed, and for
+        // output meant to be redirected to a file
+        if matches
+            .subcommand_matches("analyze")
+            .or_else(|| matches.subcommand_matches("mbox"))
+            .map_or(false, writes_events_to_stdout)
+            || matches.subcommand_matches("badge").is_some()
+            || matches.subcommand_matches("ci-report").is_some()
+            || matches.subcommand_matches("compare").is_some()
+            || matches.subcommand_matches("contributors").is_some()
+            || matches.subcommand_matches("decrypt").is_some()
+            || matches.subcommand_matches("dependencies").is_some()
+            || matches.subcommand_matches("forecast").is_some()
+            || matches.subcommand_matches("graph").is_some()
+            || matches.subcommand_matches("read-log").is_some()
+            || matches.subcommand_matches("report").is_some()
+            || matches.subcommand_matches("runs").is_some()
+            || matches.subcommand_matches("search").is_some()
+            || matches.subcommand_matches("suggest-reviewers").is_some()
+        {
+            builder.destination(Destination::Stderr);
+        }
         let terminal_logger = builder.build().unwrap();
         Logger::root(Duplicate::new(file_logger, terminal_logger).fuse(), o!())
     } else {
         file_logger
     }
 }
+
+fn writes_events_to_stdout(matches: &clap::ArgMatches) -> bool {
+    matches.is_present("stdout")
+        || (matches.value_of("format") == Some("ndjson") && !matches.is_present("output"))
+}
@@ -1,33 +1,82 @@
 #[macro_use]
 extern crate clap;
 extern crate codealong;
+extern crate codealong_bitbucket;
 extern crate codealong_elk;
 extern crate codealong_github;
+extern crate codealong_gitlab;
 extern crate console;
 extern crate dirs;
 #[macro_use]
 extern crate error_chain;
+extern crate futures;
 extern crate git2;
+extern crate hostname;
+extern crate hyper;
 extern crate indicatif;
+extern crate rand;
 #[macro_use]
 extern crate slog;
 extern crate sloggers;
 
 mod analyze;
 mod analyze_repos;
+mod badge;
 mod build_workspace;
+mod check;
+mod ci_report;
+mod compare;
+mod contributors;
+mod dco;
+mod decrypt;
+mod demo;
+mod dependencies;
 mod error;
+mod forecast;
+mod graph;
 mod init;
 mod initialize_repos;
 mod logger;
+mod mbox;
+mod migrate_aliases;
+mod preview;
+mod read_log;
+mod report;
+mod runs;
+mod search;
+mod serve;
+mod suggest_reviewers;
 mod ui;
 mod utils;
 
+use std::process;
+
 use error_chain::ChainedError;
+use slog::Logger;
 
 use crate::analyze::analyze;
+use crate::badge::badge;
+use crate::check::check;
+use crate::ci_report::ci_report;
+use crate::compare::compare;
+use crate::contributors::contributors;
+use crate::dco::dco;
+use crate::decrypt::decrypt;
+use crate::demo::demo;
+use crate::dependencies::dependencies;
+use crate::forecast::forecast;
+use crate::graph::graph;
 use crate::init::init;
 use crate::logger::build_logger;
+use crate::mbox::mbox;
+use crate::migrate_aliases::migrate_aliases;
+use crate::preview::preview;
+use crate::read_log::read_log;
+use crate::report::report;
+use crate::runs::runs;
+use crate::search::search;
+use crate::serve::serve;
+use crate::suggest_reviewers::suggest_reviewers;
 
 fn main() {
     use clap::{App, AppSettings};
@@ -41,6 +90,14 @@ fn main() {
 
     let logger = build_logger(&matches);
 
+    // global flags are only propagated to the matches of subcommands
+    let offline = matches.is_present("offline")
+        || matches
+            .subcommand()
+            .1
+            .map_or(false, |matches| matches.is_present("offline"));
+    codealong::set_offline(offline);
+
     if let Some(matches) = matches.subcommand_matches("analyze") {
         analyze(matches, &logger).map_err(|e| {
             error!(logger, "error invoking analyze subcommand"; "error" => e.display_chain().to_string());
@@ -48,6 +105,144 @@ fn main() {
         }).unwrap();
     }
 
+    if let Some(matches) = matches.subcommand_matches("badge") {
+        badge(matches, &logger).map_err(|e| {
+            error!(logger, "error invoking badge subcommand"; "error" => e.display_chain().to_string());
+            e
+        }).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("check") {
+        if let Err(e) = check(matches, &logger) {
+            fail(logger, "check", &e);
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("ci-report") {
+        ci_report(matches, &logger).map_err(|e| {
+            error!(logger, "error invoking ci-report subcommand"; "error" => e.display_chain().to_string());
+            e
+        }).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("compare") {
+        compare(matches, &logger).map_err(|e| {
+            error!(logger, "error invoking compare subcommand"; "error" => e.display_chain().to_string());
+            e
+        }).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("contributors") {
+        contributors(matches, &logger).map_err(|e| {
+            error!(logger, "error invoking contributors subcommand"; "error" => e.display_chain().to_string());
+            e
+        }).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("dco") {
+        if let Err(e) = dco(matches, &logger) {
+            fail(logger, "dco", &e);
+        }
+    }
+
+    if let Some(matches) = matches.subcommand_matches("decrypt") {
+        decrypt(matches, &logger).map_err(|e| {
+            error!(logger, "error invoking decrypt subcommand"; "error" => e.display_chain().to_string());
+            e
+        }).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("demo") {
+        demo(matches, &logger).map_err(|e| {
+            error!(logger, "error invoking demo subcommand"; "error" => e.display_chain().to_string());
+            e
+        }).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("dependencies") {
+        dependencies(matches, &logger).map_err(|e| {
+            error!(logger, "error invoking dependencies subcommand"; "error" => e.display_chain().to_string());
+            e
+        }).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("forecast") {
+        forecast(matches, &logger).map_err(|e| {
+            error!(logger, "error invoking forecast subcommand"; "error" => e.display_chain().to_string());
+            e
+        }).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("graph") {
+        graph(matches, &logger).map_err(|e| {
+            error!(logger, "error invoking graph subcommand"; "error" => e.display_chain().to_string());
+            e
+        }).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("mbox") {
+        mbox(matches, &logger).map_err(|e| {
+            error!(logger, "error invoking mbox subcommand"; "error" => e.display_chain().to_string());
+            e
+        }).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("migrate-aliases") {
+        migrate_aliases(matches, &logger).map_err(|e| {
+            error!(logger, "error invoking migrate-aliases subcommand"; "error" => e.display_chain().to_string());
+            e
+        }).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("preview") {
+        preview(matches, &logger).map_err(|e| {
+            error!(logger, "error invoking preview subcommand"; "error" => e.display_chain().to_string());
+            e
+        }).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("read-log") {
+        read_log(matches, &logger).map_err(|e| {
+            error!(logger, "error invoking read-log subcommand"; "error" => e.display_chain().to_string());
+            e
+        }).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("report") {
+        report(matches, &logger).map_err(|e| {
+            error!(logger, "error invoking report subcommand"; "error" => e.display_chain().to_string());
+            e
+        }).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("runs") {
+        runs(matches, &logger).map_err(|e| {
+            error!(logger, "error invoking runs subcommand"; "error" => e.display_chain().to_string());
+            e
+        }).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("search") {
+        search(matches, &logger).map_err(|e| {
+            error!(logger, "error invoking search subcommand"; "error" => e.display_chain().to_string());
+            e
+        }).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("serve") {
+        serve(matches, &logger).map_err(|e| {
+            error!(logger, "error invoking serve subcommand"; "error" => e.display_chain().to_string());
+            e
+        }).unwrap();
+    }
+
+    if let Some(matches) = matches.subcommand_matches("suggest-reviewers") {
+        suggest_reviewers(matches, &logger).map_err(|e| {
+            error!(logger, "error invoking suggest-reviewers subcommand"; "error" => e.display_chain().to_string());
+            e
+        }).unwrap();
+    }
+
     if let Some(matches) = matches.subcommand_matches("init") {
         init(matches, &logger).map_err(|e| {
             error!(logger, "error invoking init subcommand"; "error" => e.display_chain().to_string());
@@ -55,3 +250,12 @@ fn main() {
         }).unwrap();
     }
 }
+
+/// Reports why a subcommand gating e.g. CI failed and exits with a nonzero
+/// status, rather than panicking, once the log is flushed.
+fn fail(logger: Logger, subcommand: &str, e: &error::Error) -> ! {
+    let causes: Vec<String> = e.iter().map(|cause| cause.to_string()).collect();
+    error!(logger, "error invoking {} subcommand", subcommand; "error" => causes.join(": "));
+    drop(logger);
+    process::exit(1);
+}
@@ -0,0 +1,36 @@
+use std::path::Path;
+
+use slog::Logger;
+
+use codealong::{patch_series, read_mbox, Config};
+
+use crate::error::Result;
+use crate::utils::{build_es_client, configure_client, load_plugins, report_spool};
+
+/// Indexes the patch series found in an mbox archive of patch emails
+pub fn mbox(matches: &clap::ArgMatches, logger: &Logger) -> Result<()> {
+    let config = match matches.value_of("config_path") {
+        Some(path) => Config::from_path(Path::new(path))?,
+        None => Config::base(),
+    };
+    let messages = read_mbox(Path::new(matches.value_of("path").unwrap()))?;
+    info!(logger, "Read mbox"; "messages" => messages.len());
+
+    let client = load_plugins(
+        configure_client(build_es_client(matches)?, &config)?,
+        matches,
+        logger,
+    )?;
+    client.load_schema()?;
+    let repo = matches
+        .value_of("repo")
+        .map(|repo| config.canonical_repo_name(repo));
+    let series = patch_series(&messages, repo, &config);
+    info!(logger, "Indexing patch series"; "series" => series.len());
+    for s in series {
+        client.index(s)?;
+    }
+    client.flush()?;
+    report_spool(&client, logger)?;
+    Ok(())
+}
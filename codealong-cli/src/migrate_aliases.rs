@@ -0,0 +1,28 @@
+use std::path::Path;
+
+use slog::Logger;
+
+use codealong::Config;
+
+use crate::error::Result;
+use crate::utils::{build_es_client, configure_client};
+
+/// Rewrites events indexed under the former names of repos to their current
+/// names, so that history is not split across a rename
+pub fn migrate_aliases(matches: &clap::ArgMatches, logger: &Logger) -> Result<()> {
+    let mut config = Config::default();
+    for path in matches.values_of("config_path").unwrap() {
+        config.merge(Config::from_path(Path::new(path))?);
+    }
+
+    let client = configure_client(build_es_client(matches)?, &config)?;
+    for from in config.repo_aliases.keys() {
+        let to = config.canonical_repo_name(from);
+        if to == from {
+            continue;
+        }
+        let updated = client.rename_repo(from, to)?;
+        info!(logger, "Migrated repo alias"; "from" => from, "to" => to, "events" => updated);
+    }
+    Ok(())
+}
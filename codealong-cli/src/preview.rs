@@ -0,0 +1,87 @@
+use std::path::Path;
+
+use slog::Logger;
+
+use codealong::{discover, preview as preview_changes, RepoConfig};
+
+use crate::error::Result;
+
+/// Analyzes the uncommitted changes of a local repository and prints how
+/// they would be categorized once committed
+pub fn preview(matches: &clap::ArgMatches, logger: &Logger) -> Result<()> {
+    let path = Path::new(matches.value_of("path").unwrap_or("."));
+    let repo = discover(path)?;
+    let config = RepoConfig::from_repository(&repo)?;
+    let max_lines = matches
+        .value_of("max_lines")
+        .unwrap_or("400")
+        .parse::<u64>()?;
+    let analyzed = preview_changes(
+        &repo,
+        &config,
+        matches.is_present("staged"),
+        matches.value_of("message").unwrap_or("Uncommitted changes"),
+        logger,
+    )?;
+
+    let diff = &analyzed.diff;
+    let mut tags: Vec<&String> = diff.tag_stats.keys().collect();
+    tags.sort();
+    if !tags.is_empty() {
+        println!(
+            "tags: {}",
+            tags.iter()
+                .map(|tag| tag.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    println!(
+        "new work: {}, legacy refactor: {}, churn: {}, help others: {}, other: {}",
+        diff.stats.new_work,
+        diff.stats.legacy_refactor,
+        diff.stats.churn,
+        diff.stats.help_others,
+        diff.stats.other
+    );
+    println!(
+        "lines: +{} -{} ({} moved)",
+        diff.line_stats.added, diff.line_stats.deleted, diff.line_stats.moved
+    );
+    if !diff.language_stats.is_empty() {
+        println!(
+            "languages: {}",
+            diff.language_stats
+                .iter()
+                .map(|(language, stats)| format!(
+                    "{} +{} -{}",
+                    language, stats.added, stats.deleted
+                ))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+    let more_complex: Vec<_> = diff
+        .files
+        .iter()
+        .filter(|file| file.complexity.map_or(false, |c| c.increases_complexity()))
+        .map(|file| file.path.as_str())
+        .collect();
+    if !more_complex.is_empty() {
+        println!("more complex: {}", more_complex.join(", "));
+    }
+    if analyzed.likely_refactor {
+        println!("likely refactor");
+    }
+    if analyzed.bug_fix {
+        println!("bug fix");
+    }
+    let lines_changed = diff.line_stats.added + diff.line_stats.deleted;
+    if lines_changed > max_lines {
+        println!(
+            "warning: {} lines changed, consider splitting the change (limit {})",
+            lines_changed, max_lines
+        );
+    }
+    Ok(())
+}
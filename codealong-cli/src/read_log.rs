@@ -0,0 +1,42 @@
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use slog::Logger;
+
+use codealong_elk::EventLog;
+
+use crate::error::Result;
+
+/// Reads the events of an event log from an offset, or from where a consumer
+/// left off, to stdout as newline delimited JSON records
+pub fn read_log(matches: &clap::ArgMatches, logger: &Logger) -> Result<()> {
+    let log = EventLog::new(PathBuf::from(matches.value_of("path").unwrap()));
+    let consumer = match matches.value_of("consumer") {
+        Some(name) => Some(log.consumer(name)?),
+        None => None,
+    };
+    let from = match consumer {
+        Some(ref consumer) => consumer.offset()?,
+        None => matches.value_of("from").unwrap_or("0").parse::<u64>()?,
+    };
+    let limit = match matches.value_of("limit") {
+        Some(limit) => limit.parse::<usize>()?,
+        None => usize::MAX,
+    };
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    let mut next_offset = from;
+    let mut count = 0;
+    for record in log.read_from(from)?.take(limit) {
+        let record = record?;
+        writeln!(stdout, "{}", serde_json::to_string(&record)?)?;
+        next_offset = record.offset + 1;
+        count += 1;
+    }
+    stdout.flush()?;
+    if let (Some(consumer), true) = (consumer, matches.is_present("commit")) {
+        consumer.commit(next_offset)?;
+    }
+    info!(logger, "Read events"; "count" => count, "from" => from, "next_offset" => next_offset);
+    Ok(())
+}
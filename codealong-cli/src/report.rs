@@ -0,0 +1,60 @@
+use std::fs;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use slog::Logger;
+
+use codealong::{AnalyzeOpts, RepoAnalyzer, Report};
+
+use crate::analyze::build_repos;
+use crate::build_workspace::build_workspace;
+use crate::error::Result;
+
+/// Summarizes the commits of the repos of the workspace by author, team and
+/// language as a terminal table or a static HTML page, without indexing
+/// them
+pub fn report(matches: &clap::ArgMatches, logger: &Logger) -> Result<()> {
+    let workspace = build_workspace(matches, logger)?;
+    let since = parse_date(matches.value_of("since"))?;
+    let until = parse_date(matches.value_of("until"))?;
+    let opts = AnalyzeOpts {
+        since,
+        ..AnalyzeOpts::default()
+    };
+
+    let mut report = Report::new(since, until);
+    for repo in build_repos(&workspace, matches) {
+        let logger = logger.new(o!("repo" => repo.repo_info().name.to_owned()));
+        if repo.repository().is_err() {
+            warn!(logger, "Skipping repo which has not been cloned yet");
+            continue;
+        }
+        info!(logger, "Analyzing commits");
+        let analyzer = RepoAnalyzer::from_repo(&repo, &logger)?;
+        for commit_analyzer in analyzer.analyze(opts.clone())? {
+            report.add_commit(&commit_analyzer?.analyze()?);
+        }
+    }
+    let report = report.finish();
+    info!(logger, "Summarized commits"; "commits" => report.commits);
+
+    let out = match matches.value_of("format").unwrap_or("table") {
+        "html" => report.to_html(),
+        "json" => serde_json::to_string_pretty(&report)?,
+        _ => report.to_table(),
+    };
+    match matches.value_of("out") {
+        Some(path) => fs::write(path, out)?,
+        None => print!("{}", out),
+    }
+    Ok(())
+}
+
+fn parse_date(date: Option<&str>) -> Result<Option<DateTime<Utc>>> {
+    Ok(match date {
+        Some(date) => Some(DateTime::from_utc(
+            NaiveDate::parse_from_str(date, "%Y-%m-%d")?.and_hms(0, 0, 0),
+            Utc,
+        )),
+        None => None,
+    })
+}
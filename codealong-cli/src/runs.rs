@@ -0,0 +1,52 @@
+use slog::Logger;
+
+use crate::error::{ErrorKind, Result};
+use crate::utils::build_es_client;
+
+/// Lists and shows the analysis runs recorded by `analyze`
+pub fn runs(matches: &clap::ArgMatches, logger: &Logger) -> Result<()> {
+    if let Some(matches) = matches.subcommand_matches("list") {
+        list(matches, logger)?;
+    }
+    if let Some(matches) = matches.subcommand_matches("show") {
+        show(matches, logger)?;
+    }
+    Ok(())
+}
+
+fn list(matches: &clap::ArgMatches, logger: &Logger) -> Result<()> {
+    let limit = matches.value_of("limit").unwrap_or("20").parse::<usize>()?;
+    let client = build_es_client(matches)?;
+    let runs = client.runs(limit)?;
+    info!(logger, "Listed analysis runs"; "runs" => runs.len());
+    for run in runs {
+        println!(
+            "{} {} {}@{} {} repos, {} events{}",
+            run.id,
+            run.started_at.format("%Y-%m-%d %H:%M:%S"),
+            run.user.unwrap_or_default(),
+            run.host.unwrap_or_default(),
+            run.repos.len(),
+            run.event_counts.values().sum::<u64>(),
+            if run.finished_at.is_none() {
+                " (unfinished)"
+            } else {
+                ""
+            }
+        );
+    }
+    Ok(())
+}
+
+fn show(matches: &clap::ArgMatches, logger: &Logger) -> Result<()> {
+    let id = matches.value_of("id").unwrap();
+    let client = build_es_client(matches)?;
+    match client.run(id)? {
+        Some(run) => {
+            info!(logger, "Found analysis run"; "run" => &run.id);
+            println!("{}", serde_json::to_string_pretty(&run)?);
+            Ok(())
+        }
+        None => Err(ErrorKind::RunNotFound(id.to_owned()).into()),
+    }
+}
@@ -0,0 +1,42 @@
+use chrono::offset::TimeZone;
+use chrono::Utc;
+use slog::Logger;
+
+use codealong_elk::SearchQuery;
+
+use crate::error::Result;
+use crate::utils::build_es_client;
+
+/// Searches the messages of analyzed commits and the titles and bodies of
+/// analyzed pull requests
+pub fn search(matches: &clap::ArgMatches, logger: &Logger) -> Result<()> {
+    let mut query = SearchQuery::new(matches.value_of("query").unwrap());
+    query.author = matches.value_of("author").map(|author| author.to_owned());
+    query.repo = matches.value_of("repo").map(|repo| repo.to_owned());
+    query.limit = matches.value_of("limit").unwrap_or("20").parse::<usize>()?;
+    if let Some(since) = matches.value_of("since") {
+        let duration = humantime::parse_duration(since)?;
+        query.since = Some(Utc.timestamp(Utc::now().timestamp() - duration.as_secs() as i64, 0));
+    }
+
+    let client = build_es_client(matches)?;
+    let hits = client.search(&query)?;
+    info!(logger, "Searched analyzed data"; "query" => &query.text, "hits" => hits.len());
+
+    for hit in hits {
+        println!(
+            "{} {:<12} {} {} {}{}",
+            hit.timestamp
+                .map(|timestamp| timestamp.format("%Y-%m-%d").to_string())
+                .unwrap_or_default(),
+            hit.event_type,
+            hit.repo.unwrap_or_default(),
+            &hit.id[..hit.id.len().min(10)],
+            hit.title,
+            hit.author
+                .map(|author| format!(" ({})", author))
+                .unwrap_or_default()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,376 @@
+use std::collections::HashMap;
+use std::env;
+use std::net::SocketAddr;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use error_chain::ChainedError;
+use futures::{future, Future, Stream};
+use hyper::header::CONTENT_LENGTH;
+use hyper::service::service_fn;
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use slog::Logger;
+
+use codealong::{Repo, RepoAnalyzer, SloSubject};
+use codealong_github::{
+    verify_signature, PullRequest, PullRequestAnalyzer, PushEvent, WebhookEvent,
+};
+
+use crate::build_workspace::build_workspace;
+use crate::error::Result;
+use crate::utils::{build_es_client, configure_client, load_plugins};
+
+/// Actions on a pull request which change what is analyzed of it.
+const PULL_REQUEST_ACTIONS: &[&str] = &[
+    "opened",
+    "reopened",
+    "synchronize",
+    "closed",
+    "edited",
+    "ready_for_review",
+];
+
+/// Largest delivery read, in bytes. Github caps the payloads of webhooks at
+/// 25 MB.
+const MAX_BODY_SIZE: usize = 25 * 1024 * 1024;
+
+/// A verified delivery, queued for the worker thread.
+struct Delivery {
+    id: String,
+    event: WebhookEvent,
+}
+
+/// Runs a server accepting Github webhook deliveries, which analyzes the
+/// commits and pull requests they reference as they happen, without walking
+/// the history of their repos
+pub fn serve(matches: &clap::ArgMatches, logger: &Logger) -> Result<()> {
+    let secret = match matches.value_of("webhook_secret") {
+        Some(secret) => secret.to_owned(),
+        None => env::var("GITHUB_WEBHOOK_SECRET")
+            .map_err(|_| "a webhook secret is required, set GITHUB_WEBHOOK_SECRET")?,
+    };
+    let addr: SocketAddr = matches
+        .value_of("listen")
+        .unwrap_or("0.0.0.0:8080")
+        .parse()
+        .map_err(|_| "invalid address to listen on")?;
+    let workspace = build_workspace(matches, logger)?;
+    let client = configure_client(build_es_client(matches)?, workspace.config())?;
+    let client = load_plugins(client, matches, logger)?;
+    client.load_schema()?;
+    let repos: HashMap<String, Repo> = workspace
+        .repos()
+        .into_iter()
+        .filter_map(|repo| Some((repo.repo_info().github_name.clone()?, repo)))
+        .collect();
+    info!(logger, "Serving webhooks"; "address" => addr.to_string(), "repos" => repos.len());
+
+    let (sender, receiver) = mpsc::channel();
+    let worker_logger = logger.clone();
+    thread::spawn(move || work(receiver, &repos, &client, &worker_logger));
+
+    let sender = Arc::new(Mutex::new(sender));
+    let secret = Arc::new(secret.into_bytes());
+    let service_logger = logger.clone();
+    let server = Server::bind(&addr)
+        .serve(move || {
+            let sender = sender.clone();
+            let secret = secret.clone();
+            let logger = service_logger.clone();
+            service_fn(move |req: Request<Body>| {
+                handle(req, secret.clone(), sender.clone(), logger.clone())
+            })
+        })
+        .map_err(|e| e.to_string());
+    let error_logger = logger.clone();
+    hyper::rt::run(server.map_err(move |e| error!(error_logger, "Server failed"; "error" => e)));
+    Ok(())
+}
+
+/// Routes a request to `accept`, rejecting those which are not deliveries
+/// before reading their body.
+fn handle(
+    req: Request<Body>,
+    secret: Arc<Vec<u8>>,
+    sender: Arc<Mutex<Sender<Delivery>>>,
+    logger: Logger,
+) -> Box<dyn Future<Item = Response<Body>, Error = hyper::Error> + Send> {
+    let (parts, body) = req.into_parts();
+    if parts.method != Method::POST {
+        return Box::new(future::ok(respond(StatusCode::METHOD_NOT_ALLOWED)));
+    }
+    let content_length = parts
+        .headers
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    if content_length.is_some_and(|len| len > MAX_BODY_SIZE) {
+        return Box::new(future::ok(respond(StatusCode::PAYLOAD_TOO_LARGE)));
+    }
+    Box::new(read_body(body, MAX_BODY_SIZE).map(move |body| {
+        respond(match body {
+            Some(body) => accept(&parts.headers, &body, &secret, &sender, &logger),
+            None => StatusCode::PAYLOAD_TOO_LARGE,
+        })
+    }))
+}
+
+/// Reads `body`, or stops reading it as soon as it turns out to be longer
+/// than `limit`, e.g. when it is sent chunked, and yields `None`.
+fn read_body(
+    body: Body,
+    limit: usize,
+) -> impl Future<Item = Option<Vec<u8>>, Error = hyper::Error> {
+    // errors of `None` stop the fold when the limit is exceeded
+    body.map_err(Some)
+        .fold(Vec::new(), move |mut read, chunk| {
+            if read.len() + chunk.len() > limit {
+                return Err(None);
+            }
+            read.extend_from_slice(&chunk);
+            Ok(read)
+        })
+        .then(|res| match res {
+            Ok(read) => Ok(Some(read)),
+            Err(None) => Ok(None),
+            Err(Some(e)) => Err(e),
+        })
+}
+
+fn respond(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::from(status.to_string()));
+    *response.status_mut() = status;
+    response
+}
+
+/// Verifies and parses a delivery, queuing it unless there is nothing to
+/// analyze for it. Github only waits a few seconds for the response, so the
+/// analysis itself is left to the worker thread.
+fn accept(
+    headers: &hyper::HeaderMap,
+    body: &[u8],
+    secret: &[u8],
+    sender: &Mutex<Sender<Delivery>>,
+    logger: &Logger,
+) -> StatusCode {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    let signature = header("X-Hub-Signature-256").or_else(|| header("X-Hub-Signature"));
+    if !signature.is_some_and(|signature| verify_signature(secret, body, signature)) {
+        warn!(
+            logger,
+            "Rejected webhook delivery with an invalid signature"
+        );
+        return StatusCode::UNAUTHORIZED;
+    }
+    let id = header("X-GitHub-Delivery").unwrap_or("").to_owned();
+    let event = match WebhookEvent::parse(header("X-GitHub-Event").unwrap_or(""), body) {
+        Ok(event) => event,
+        Err(e) => {
+            warn!(logger, "Unable to parse webhook delivery"; "delivery" => &id, "error" => e.to_string());
+            return StatusCode::BAD_REQUEST;
+        }
+    };
+    match event {
+        WebhookEvent::Push(_)
+        | WebhookEvent::PullRequest(_)
+        | WebhookEvent::PullRequestReview(_) => {
+            debug!(logger, "Queued webhook delivery"; "delivery" => &id);
+            if sender.lock().unwrap().send(Delivery { id, event }).is_err() {
+                return StatusCode::SERVICE_UNAVAILABLE;
+            }
+            StatusCode::ACCEPTED
+        }
+        _ => StatusCode::OK,
+    }
+}
+
+/// Analyzes the queued deliveries one at a time, so that concurrent
+/// deliveries for a repo do not fetch it concurrently.
+fn work(
+    receiver: Receiver<Delivery>,
+    repos: &HashMap<String, Repo>,
+    client: &codealong_elk::Client,
+    logger: &Logger,
+) {
+    let github_client = codealong_github::Client::from_env();
+    for Delivery { id, event } in receiver {
+        let logger = logger.new(o!("delivery" => id));
+        let result = match event {
+            WebhookEvent::Push(event) => match repos.get(&event.repository.full_name) {
+                Some(repo) => analyze_push(repo, &event, client, &logger),
+                None => Ok(()),
+            },
+            WebhookEvent::PullRequest(event) => match repos.get(&event.repository.full_name) {
+                Some(repo) if PULL_REQUEST_ACTIONS.contains(&&*event.action) => {
+                    analyze_pull_request(repo, event.pull_request, &github_client, client, &logger)
+                }
+                _ => Ok(()),
+            },
+            // the review events are derived from the pull request
+            WebhookEvent::PullRequestReview(event) => {
+                match repos.get(&event.repository.full_name) {
+                    Some(repo) => analyze_pull_request(
+                        repo,
+                        event.pull_request,
+                        &github_client,
+                        client,
+                        &logger,
+                    ),
+                    None => Ok(()),
+                }
+            }
+            _ => Ok(()),
+        };
+        if let Err(e) = result.and_then(|_| Ok(client.flush()?)) {
+            error!(logger, "error analyzing webhook delivery"; "error" => e.display_chain().to_string());
+        }
+    }
+}
+
+/// Analyzes the commits a push added to the repo, which Github lists up to
+/// 2048 of.
+fn analyze_push(
+    repo: &Repo,
+    event: &PushEvent,
+    client: &codealong_elk::Client,
+    logger: &Logger,
+) -> Result<()> {
+    let ids: Vec<String> = event
+        .commits
+        .iter()
+        .filter(|commit| commit.distinct)
+        .map(|commit| commit.id.clone())
+        .collect();
+    if event.deleted || ids.is_empty() {
+        return Ok(());
+    }
+    info!(logger, "Analyzing pushed commits"; "repo" => &repo.repo_info().name, "ref" => &event.reference, "count" => ids.len());
+    repo.init(None)?;
+    let analyzer = RepoAnalyzer::from_repo(repo, logger)?;
+    for commit_analyzer in analyzer.commits(&ids) {
+        let commit_analyzer = commit_analyzer?;
+        let commit = commit_analyzer.analyze()?;
+        for introduced_defect in commit_analyzer.introduced_defects(&commit)? {
+            client.index(introduced_defect)?;
+        }
+        client.index(commit)?;
+    }
+    Ok(())
+}
+
+/// Analyzes a pull request along with its reviews, from the pull request as
+/// of the delivery rather than as listed by the API.
+fn analyze_pull_request(
+    repo: &Repo,
+    pr: PullRequest,
+    github_client: &codealong_github::Client,
+    client: &codealong_elk::Client,
+    logger: &Logger,
+) -> Result<()> {
+    let github_name = repo.repo_info().github_name.clone().unwrap_or_default();
+    let github_client = github_client.for_clone_url(&repo.repo_info().clone_url);
+    info!(logger, "Analyzing pull request"; "repo" => &github_name, "number" => pr.number);
+    repo.init(None)?;
+    let reviews = github_client
+        .reviews(&github_name, pr.number)
+        .iter(logger)
+        .collect();
+    let comments = github_client
+        .review_comments(&github_name, pr.number)
+        .iter(logger)
+        .collect();
+    let repository = repo.repository()?;
+    let config = repo.config();
+    let (analyzed_pr, reviews) = PullRequestAnalyzer::new(&repository, pr, &config, logger)
+        .with_reviews(reviews, comments)
+        .analyze_with_reviews()?;
+    for breach in analyzed_pr.slo_breaches(&config.config.slos) {
+        client.index(breach)?;
+    }
+    client.index(analyzed_pr)?;
+    for review in reviews {
+        client.index(review)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codealong_github::sign_payload;
+
+    const PUSH: &[u8] = include_bytes!("../../codealong-github/fixtures/webhooks/push.json");
+
+    fn delivery(event: &str, body: &[u8], signature: &str) -> Request<Body> {
+        Request::post("/")
+            .header("X-GitHub-Event", event)
+            .header("X-GitHub-Delivery", "72d3162e")
+            .header("X-Hub-Signature-256", signature)
+            .body(Body::from(body.to_vec()))
+            .unwrap()
+    }
+
+    fn status(req: Request<Body>, sender: Sender<Delivery>) -> StatusCode {
+        let logger = Logger::root(slog::Discard, o!());
+        handle(
+            req,
+            Arc::new(b"s3cr3t".to_vec()),
+            Arc::new(Mutex::new(sender)),
+            logger,
+        )
+        .wait()
+        .unwrap()
+        .status()
+    }
+
+    #[test]
+    fn test_accept() {
+        let (sender, receiver) = mpsc::channel();
+        let signature = sign_payload(b"s3cr3t", PUSH).unwrap();
+        let req = delivery("push", PUSH, &signature);
+        assert_eq!(status(req, sender.clone()), StatusCode::ACCEPTED);
+        let queued = receiver.try_recv().unwrap();
+        assert_eq!(queued.id, "72d3162e");
+        assert!(matches!(queued.event, WebhookEvent::Push(_)));
+
+        let forged = sign_payload(b"guess", PUSH).unwrap();
+        let req = delivery("push", PUSH, &forged);
+        assert_eq!(status(req, sender.clone()), StatusCode::UNAUTHORIZED);
+
+        let body = br#"{"action": "opened"}"#;
+        let signature = sign_payload(b"s3cr3t", body).unwrap();
+        let req = delivery("pull_request", body, &signature);
+        assert_eq!(status(req, sender.clone()), StatusCode::BAD_REQUEST);
+
+        // nothing is analyzed of other events
+        let body = br#"{"action": "opened"}"#;
+        let req = delivery("issues", body, &signature);
+        assert_eq!(status(req, sender), StatusCode::OK);
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_handle() {
+        let (sender, receiver) = mpsc::channel();
+        let req = Request::get("/").body(Body::empty()).unwrap();
+        assert_eq!(status(req, sender.clone()), StatusCode::METHOD_NOT_ALLOWED);
+
+        let signature = sign_payload(b"s3cr3t", PUSH).unwrap();
+        let mut req = delivery("push", PUSH, &signature);
+        req.headers_mut()
+            .insert(CONTENT_LENGTH, (MAX_BODY_SIZE + 1).into());
+        assert_eq!(status(req, sender), StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_read_body() {
+        let body = || {
+            let chunks = (0..3).map(|_| Ok::<_, hyper::Error>(vec![0; 3]));
+            Body::wrap_stream(futures::stream::iter_result(chunks))
+        };
+        assert_eq!(read_body(body(), 9).wait().unwrap(), Some(vec![0; 9]));
+        assert_eq!(read_body(body(), 8).wait().unwrap(), None);
+    }
+}
@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use chrono::Utc;
+use git2::{Commit, Repository, RevparseMode};
+use slog::Logger;
+
+use codealong::{discover, Config, RepoConfig, ReviewerSuggestions};
+
+use crate::error::Result;
+
+/// Ranks the people best placed to review the changes of a range of
+/// commits, from who last changed the lines they touch, who owns the files
+/// they change per CODEOWNERS, and how many reviews each has pending
+pub fn suggest_reviewers(matches: &clap::ArgMatches, logger: &Logger) -> Result<()> {
+    let path = Path::new(matches.value_of("path").unwrap_or("."));
+    let repo = discover(path)?;
+    let config = RepoConfig::from_repository(&repo)?;
+    let range = matches.value_of("diff").unwrap_or("HEAD");
+    let (base, head) = parse_range(&repo, range)?;
+    let load = match config.repo.github_name {
+        Some(ref github_name) if !codealong::is_offline() && !matches.is_present("no_load") => {
+            review_load(github_name, &config.repo.clone_url, &config.config, logger)
+        }
+        _ => HashMap::new(),
+    };
+    let mut suggestions =
+        ReviewerSuggestions::build(&repo, &base, &head, &config.config, &load, &Utc::now())?;
+    info!(logger, "Suggested reviewers"; "range" => range, "candidates" => suggestions.candidates.len());
+    let limit = matches.value_of("limit").unwrap_or("5").parse::<usize>()?;
+    suggestions.candidates.truncate(limit);
+    match matches.value_of("format") {
+        Some("json") => println!("{}", serde_json::to_string_pretty(&suggestions)?),
+        _ => print!("{}", suggestions.to_text()),
+    }
+    Ok(())
+}
+
+/// The base and head of `range`, e.g. `main..feature`, or the parent of a
+/// single revision and the revision.
+fn parse_range<'repo>(
+    repo: &'repo Repository,
+    range: &str,
+) -> Result<(Commit<'repo>, Commit<'repo>)> {
+    let revspec = repo.revparse(range)?;
+    if revspec.mode().contains(RevparseMode::SINGLE) {
+        let head = revspec.from().ok_or("empty revision")?.peel_to_commit()?;
+        return Ok((head.parent(0)?, head));
+    }
+    let base = revspec
+        .from()
+        .ok_or("range without a base")?
+        .peel_to_commit()?;
+    let head = revspec
+        .to()
+        .ok_or("range without a head")?
+        .peel_to_commit()?;
+    Ok((base, head))
+}
+
+/// The number of open pull requests of the repo awaiting a review from each
+/// person, by person id. Failing to list them only leaves the suggestions
+/// unadjusted for load.
+fn review_load(
+    github_name: &str,
+    clone_url: &str,
+    config: &Config,
+    logger: &Logger,
+) -> HashMap<String, u64> {
+    let mut load = HashMap::new();
+    let client = codealong_github::Client::from_env().for_clone_url(clone_url);
+    for pr in client.open_pull_requests(github_name).iter(logger) {
+        for reviewer in pr.requested_reviewers {
+            *load
+                .entry(config.person_for_github_login(&reviewer.login).id)
+                .or_insert(0) += 1;
+        }
+    }
+    load
+}
@@ -1,6 +1,8 @@
+use codealong::{Profiler, Progress};
 use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
 use std::io;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 /// Encapsulates a MultiProgress with a progres bar to track an overall count
 pub struct ProgressPool {
@@ -88,6 +90,15 @@ impl NamedProgressBar {
         self.pb.finish_with_message("done")
     }
 
+    /// Renders the progress reported by an analysis on this bar, and records
+    /// the time the analysis spends in each stage with `profiler`.
+    pub fn progress(&self, profiler: &Profiler) -> Arc<dyn Progress> {
+        Arc::new(BarProgress {
+            pb: self.pb.clone(),
+            profiler: profiler.clone(),
+        })
+    }
+
     fn reset_style(&self) {
         self.pb.set_style(self.pb_style());
     }
@@ -107,3 +118,29 @@ impl NamedProgressBar {
         }
     }
 }
+
+/// See `NamedProgressBar::progress`. Commits are counted by the caller as
+/// they are indexed.
+struct BarProgress {
+    pb: ProgressBar,
+    profiler: Profiler,
+}
+
+impl Progress for BarProgress {
+    fn file_started(&self, path: &str) {
+        self.pb.set_message(&format!("analyzing {}", path));
+    }
+
+    fn page_fetched(&self, page: usize, pages: Option<usize>) {
+        match pages {
+            Some(pages) => self
+                .pb
+                .set_message(&format!("fetched page {} of {}", page, pages)),
+            None => self.pb.set_message(&format!("fetched page {}", page)),
+        }
+    }
+
+    fn timed(&self, stage: &str, elapsed: Duration) {
+        self.profiler.record_nested(stage, elapsed);
+    }
+}
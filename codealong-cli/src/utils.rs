@@ -1,6 +1,105 @@
-pub fn build_es_client(matches: &clap::ArgMatches) -> codealong_elk::Client {
-    let url = matches
-        .value_of("elasticsearch_url")
-        .unwrap_or("https://localhost:9200");
-    codealong_elk::Client::new(url)
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use slog::Logger;
+
+use codealong::{Config, Plugins};
+use codealong_elk::{CsvExporter, EventLog, FieldEncryptor, NdjsonExporter};
+
+use crate::error::Result;
+
+pub fn build_es_client(matches: &clap::ArgMatches) -> Result<codealong_elk::Client> {
+    let output = matches.value_of("output").map(PathBuf::from);
+    let client = match (matches.value_of("format"), output) {
+        _ if matches.is_present("stdout") => codealong_elk::Client::stdout(),
+        (Some("ndjson"), None) => codealong_elk::Client::stdout(),
+        (Some("ndjson"), Some(path)) => {
+            codealong_elk::Client::with_exporter(NdjsonExporter::file(path))
+        }
+        (Some("csv"), Some(dir)) => codealong_elk::Client::with_exporter(CsvExporter::new(dir)),
+        (Some("event-log"), Some(dir)) => codealong_elk::Client::with_exporter(EventLog::new(dir)),
+        _ => {
+            let url = matches
+                .value_of("elasticsearch_url")
+                .unwrap_or("https://localhost:9200");
+            let mut client = codealong_elk::Client::new(url);
+            if !matches.is_present("skip_schema_check") {
+                client = client.with_schema_check();
+            }
+            match matches.value_of("spool_dir") {
+                Some(dir) => client.with_spool(Path::new(dir)),
+                None => client,
+            }
+        }
+    };
+    let client = client.with_version(env!("CARGO_PKG_VERSION"));
+    Ok(match matches.value_of("tenant") {
+        Some(tenant) => client.with_tenant(tenant)?,
+        None => client,
+    })
+}
+
+/// Applies the tenant of `config` to `client`, if any, along with the
+/// batching and retries configured for its sink, and encrypts the fields of the events it
+/// indexes which `config` designates for encryption.
+pub fn configure_client(
+    client: codealong_elk::Client,
+    config: &Config,
+) -> Result<codealong_elk::Client> {
+    let client = match config.tenant {
+        Some(ref tenant) => client.with_tenant(tenant)?,
+        None => client,
+    };
+    let client = match config.sinks.get(client.sink()) {
+        Some(sink_config) => client.with_batching(sink_config).with_retries(sink_config),
+        None => client,
+    };
+    Ok(match config.field_encryption {
+        Some(ref encryption) => {
+            let public_key = fs::read(&encryption.public_key)?;
+            client.with_field_encryption(FieldEncryptor::new(&public_key, &encryption.fields)?)
+        }
+        None => client,
+    })
+}
+
+/// Has `client` run the events it indexes through the plugins in the
+/// plugins directory, unless plugins are disabled.
+pub fn load_plugins(
+    client: codealong_elk::Client,
+    matches: &clap::ArgMatches,
+    logger: &Logger,
+) -> Result<codealong_elk::Client> {
+    if matches.is_present("no_plugins") {
+        return Ok(client);
+    }
+    let dir = match matches.value_of("plugins_dir") {
+        Some(dir) => PathBuf::from(dir),
+        None => match Plugins::default_dir() {
+            Some(dir) => dir,
+            None => return Ok(client),
+        },
+    };
+    let plugins = Plugins::discover(&dir)?;
+    for (path, reason) in plugins.skipped() {
+        warn!(logger, "Skipping plugin"; "path" => path.display().to_string(), "reason" => reason);
+    }
+    if plugins.is_empty() {
+        return Ok(client);
+    }
+    for plugin in plugins.plugins() {
+        info!(logger, "Loaded plugin"; "name" => plugin.name(), "hooks" => format!("{:?}", plugin.manifest().hooks));
+    }
+    Ok(client.with_plugins(Arc::new(plugins)))
+}
+
+/// Warns about the events `client` spooled while elasticsearch was
+/// unreachable and which are still waiting to be replayed by a later run.
+pub fn report_spool(client: &codealong_elk::Client, logger: &Logger) -> Result<()> {
+    let pending = client.spool_len()?;
+    if client.spooled() > 0 || pending > 0 {
+        warn!(logger, "Spooled events while elasticsearch was unreachable"; "spooled" => client.spooled(), "pending" => pending);
+    }
+    Ok(())
 }
@@ -0,0 +1,108 @@
+use std::mem;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use codealong::SinkConfig;
+
+use crate::error::{Error, Result};
+use crate::spool::SpooledEvent;
+
+/// An event waiting to be written, along with the table it is exported to
+/// when not indexed.
+#[derive(Debug)]
+pub struct QueuedEvent {
+    pub event_type: String,
+    pub table: String,
+    pub event: SpooledEvent,
+}
+
+/// Collects events into batches and keeps track of the batches being
+/// written, which are written on threads of their own.
+#[derive(Debug)]
+pub struct Batcher {
+    batch_size: usize,
+    max_in_flight: usize,
+    flush_interval: Option<Duration>,
+    pending: Mutex<Pending>,
+    in_flight: Mutex<usize>,
+    changed: Condvar,
+    /// The first error writing a batch, returned by the next call.
+    error: Mutex<Option<Error>>,
+}
+
+#[derive(Debug, Default)]
+struct Pending {
+    events: Vec<QueuedEvent>,
+    since: Option<Instant>,
+}
+
+impl Batcher {
+    pub fn new(config: &SinkConfig) -> Batcher {
+        Batcher {
+            batch_size: config.batch_size.max(1),
+            max_in_flight: config.max_in_flight.max(1),
+            flush_interval: config.flush_interval_ms.map(Duration::from_millis),
+            pending: Mutex::new(Pending::default()),
+            in_flight: Mutex::new(0),
+            changed: Condvar::new(),
+            error: Mutex::new(None),
+        }
+    }
+
+    /// Adds `event` to the pending batch, returning the batch if it is due
+    /// to be written.
+    pub fn push(&self, event: QueuedEvent) -> Option<Vec<QueuedEvent>> {
+        let mut pending = self.pending.lock().unwrap();
+        pending.events.push(event);
+        let since = *pending.since.get_or_insert_with(Instant::now);
+        let expired = self
+            .flush_interval
+            .map_or(false, |interval| since.elapsed() >= interval);
+        if pending.events.len() >= self.batch_size || expired {
+            Some(mem::take(&mut *pending).events)
+        } else {
+            None
+        }
+    }
+
+    /// Takes the pending batch regardless of its size.
+    pub fn take(&self) -> Vec<QueuedEvent> {
+        mem::take(&mut *self.pending.lock().unwrap()).events
+    }
+
+    /// Waits until fewer than `max_in_flight` batches are being written and
+    /// counts one more.
+    pub fn acquire(&self) {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        while *in_flight >= self.max_in_flight {
+            in_flight = self.changed.wait(in_flight).unwrap();
+        }
+        *in_flight += 1;
+    }
+
+    /// Counts a batch as written, with the result `res`.
+    pub fn release(&self, res: Result<()>) {
+        if let Err(e) = res {
+            self.error.lock().unwrap().get_or_insert(e);
+        }
+        *self.in_flight.lock().unwrap() -= 1;
+        self.changed.notify_all();
+    }
+
+    /// Waits until no batch is being written.
+    pub fn wait(&self) -> Result<()> {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        while *in_flight > 0 {
+            in_flight = self.changed.wait(in_flight).unwrap();
+        }
+        self.check()
+    }
+
+    /// Returns the error writing a batch, if any since the last call.
+    pub fn check(&self) -> Result<()> {
+        match self.error.lock().unwrap().take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
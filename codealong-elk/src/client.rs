@@ -1,19 +1,81 @@
 use chrono::prelude::*;
 use chrono::DateTime;
 
-use crate::event::Event;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
 
-use crate::error::Result;
+use crate::batch::{Batcher, QueuedEvent};
+use crate::exporter::{Exporter, NdjsonExporter};
+use crate::field_encryption::FieldEncryptor;
+use crate::schema::{self, SchemaCheck, SchemaState};
+use crate::spool::{Spool, SpooledEvent};
+use crate::subscriber::{Subscriber, Subscriptions};
+
+use crate::error::{Error, ErrorKind, Result};
+use crate::search::{SearchHit, SearchQuery};
+use codealong::{AnalysisRun, Plugins, SinkConfig};
 use reqwest;
+use serde_json::Value;
+
+/// The position and status of the items of a bulk request to retry, and why
+/// the others were rejected.
+type BulkOutcome = (Vec<(usize, u16)>, Vec<String>);
 
+/// Clones share the counts of the events indexed, and the spool.
+#[derive(Debug, Clone)]
 pub struct Client {
     url: String,
+    /// Where events are written rather than Elasticsearch, if anywhere.
+    exporter: Option<Arc<dyn Exporter>>,
+    index_prefix: String,
+    codealong_version: Option<String>,
+    config_hash: Option<String>,
+    tenant: Option<String>,
+    field_encryption: Option<Arc<FieldEncryptor>>,
+    schema_check: Option<Arc<SchemaCheck>>,
+    plugins: Option<Arc<Plugins>>,
+    spool: Option<Spool>,
+    /// Whether the spool may hold events to replay.
+    spool_pending: Arc<AtomicBool>,
+    /// Held while replaying so that clones do not replay the same events.
+    replaying: Arc<Mutex<()>>,
+    spooled: Arc<AtomicUsize>,
+    /// Number of times a write rejected with a 429 or 503 is retried.
+    max_retries: u32,
+    /// Wait before the first retry, doubled for each retry after it.
+    retry_backoff: Duration,
+    /// Collects events into batches when they are not written one at a
+    /// time.
+    batcher: Option<Arc<Batcher>>,
+    event_counts: Arc<Mutex<BTreeMap<String, u64>>>,
+    subscriptions: Subscriptions,
 }
 
 impl Default for Client {
     fn default() -> Self {
         Client {
             url: "http://localhost:9200".to_owned(),
+            exporter: None,
+            index_prefix: "codealong".to_owned(),
+            codealong_version: None,
+            config_hash: None,
+            tenant: None,
+            field_encryption: None,
+            schema_check: None,
+            plugins: None,
+            spool: None,
+            spool_pending: Arc::new(AtomicBool::new(false)),
+            replaying: Arc::new(Mutex::new(())),
+            spooled: Arc::new(AtomicUsize::new(0)),
+            max_retries: SinkConfig::default_max_retries(),
+            retry_backoff: Duration::from_millis(SinkConfig::default_retry_backoff_ms()),
+            batcher: None,
+            event_counts: Arc::new(Mutex::new(BTreeMap::new())),
+            subscriptions: Subscriptions::default(),
         }
     }
 }
@@ -22,27 +84,812 @@ impl Client {
     pub fn new(url: &str) -> Client {
         Client {
             url: url.to_owned(),
+            ..Client::default()
+        }
+    }
+
+    /// A client which writes events to stdout as newline delimited JSON
+    /// rather than indexing them, e.g. to pipe them into other tools.
+    pub fn stdout() -> Client {
+        Client::with_exporter(NdjsonExporter::stdout())
+    }
+
+    /// A client which writes events with `exporter` rather than indexing
+    /// them.
+    pub fn with_exporter<E: Exporter + 'static>(exporter: E) -> Client {
+        Client {
+            exporter: Some(Arc::new(exporter)),
+            ..Client::default()
+        }
+    }
+
+    /// Indexes events into `<index_prefix>-<year>.<month>` rather than the
+    /// `codealong-` indices, e.g. to keep the output of a trial analysis
+    /// apart.
+    pub fn with_index_prefix(mut self, index_prefix: &str) -> Client {
+        self.index_prefix = index_prefix.to_owned();
+        self
+    }
+
+    /// Stamps every event indexed with the version of codealong.
+    pub fn with_version(mut self, codealong_version: &str) -> Client {
+        self.codealong_version = Some(codealong_version.to_owned());
+        self
+    }
+
+    /// Stamps every event indexed with the hash of the config it was
+    /// produced with, see `codealong::config_hash`.
+    pub fn with_config_hash(mut self, config_hash: &str) -> Client {
+        self.config_hash = Some(config_hash.to_owned());
+        self
+    }
+
+    /// Stamps every event indexed with `tenant` and keeps its events in
+    /// indices of their own, `<tenant>-<index_prefix>-<year>.<month>`, which
+    /// are also the only ones searched. Tenants are restricted to lowercase
+    /// letters, digits and underscores so that the indices of one tenant
+    /// never match the index pattern of another.
+    pub fn with_tenant(mut self, tenant: &str) -> Result<Client> {
+        if tenant.is_empty()
+            || !tenant
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        {
+            return Err(ErrorKind::InvalidTenant(tenant.to_owned()).into());
+        }
+        self.tenant = Some(tenant.to_owned());
+        Ok(self)
+    }
+
+    /// Encrypts the designated fields of every event before it is written.
+    pub fn with_field_encryption(mut self, field_encryption: FieldEncryptor) -> Client {
+        self.field_encryption = Some(Arc::new(field_encryption));
+        self
+    }
+
+    /// Checks the fields of every event against the mapping of the indices
+    /// it is indexed into before it is written, rather than leaving
+    /// Elasticsearch to reject it, e.g. halfway through a backfill, or to map
+    /// a field differently from one month to the next. The mappings of new
+    /// fields are added to the existing indices, while an event with a field
+    /// mapped as another type fails with the fields in conflict. Events
+    /// written by an exporter are not checked. Call `load_schema` once the
+    /// client is configured to read the mapping before anything is written.
+    pub fn with_schema_check(mut self) -> Client {
+        self.schema_check = Some(Arc::new(SchemaCheck::default()));
+        self
+    }
+
+    /// Has the taggers and enrichers among `plugins` process every event
+    /// before it is encrypted and written, and passes every event written to
+    /// the sinks among them.
+    pub fn with_plugins(mut self, plugins: Arc<Plugins>) -> Client {
+        self.plugins = Some(plugins);
+        self
+    }
+
+    /// Spools the events which cannot be indexed while Elasticsearch is
+    /// unreachable, or rejects them as overloaded for every retry, to `dir`
+    /// rather than failing, and replays them, along with those spooled by
+    /// earlier runs, once it is reachable again. The
+    /// spool is replayed before any new event is written, and new events are
+    /// spooled behind it while it cannot be, so that a spooled event never
+    /// overwrites a newer version of the same document.
+    pub fn with_spool(mut self, dir: &Path) -> Client {
+        self.spool = Some(Spool::new(dir));
+        self.spool_pending.store(true, Ordering::SeqCst);
+        self
+    }
+
+    /// Retries the writes Elasticsearch rejects as overloaded or
+    /// unavailable, with a 429 or 503, up to `config.max_retries` times,
+    /// waiting `config.retry_backoff_ms` before the first retry and twice as
+    /// long before each retry after it. Only the items of a bulk request
+    /// which were rejected are retried. Items rejected for any other reason,
+    /// e.g. a field which cannot be mapped, are not retried and fail the
+    /// write with the reasons they were rejected for.
+    pub fn with_retries(mut self, config: &SinkConfig) -> Client {
+        self.max_retries = config.max_retries;
+        self.retry_backoff = Duration::from_millis(config.retry_backoff_ms);
+        self
+    }
+
+    /// Writes events in batches of `config.batch_size`, e.g. with bulk
+    /// requests to Elasticsearch, with up to `config.max_in_flight` batches
+    /// being written at the same time in the background. Errors writing a
+    /// batch are returned by the next call to `index` or `flush`, which must
+    /// be called to write the last batch.
+    pub fn with_batching(mut self, config: &SinkConfig) -> Client {
+        self.batcher = Some(Arc::new(Batcher::new(config)));
+        self
+    }
+
+    /// Name of the sink events are written to, `elasticsearch` or the name
+    /// of the exporter, under which its batching is configured.
+    pub fn sink(&self) -> &'static str {
+        match self.exporter {
+            Some(ref exporter) => exporter.name(),
+            None => "elasticsearch",
         }
     }
 
-    pub fn index<T: codealong::Event + serde::Serialize>(
+    /// Number of events spooled by this client and its clones.
+    pub fn spooled(&self) -> usize {
+        self.spooled.load(Ordering::SeqCst)
+    }
+
+    /// Number of events in the spool waiting to be replayed.
+    pub fn spool_len(&self) -> Result<usize> {
+        match self.spool {
+            Some(ref spool) => Ok(spool.paths()?.len()),
+            None => Ok(0),
+        }
+    }
+
+    /// Whether events which cannot be indexed are spooled.
+    pub fn has_spool(&self) -> bool {
+        self.spool.is_some()
+    }
+
+    /// Whether events are indexed into Elasticsearch rather than exported.
+    pub fn is_elasticsearch(&self) -> bool {
+        self.exporter.is_none()
+    }
+
+    /// Writes out the pending batch and the events buffered by the
+    /// exporter, if any, or replays the spooled events if Elasticsearch is
+    /// reachable.
+    pub fn flush(&self) -> Result<()> {
+        if let Some(ref batcher) = self.batcher {
+            let batch = batcher.take();
+            if !batch.is_empty() {
+                self.dispatch(batcher, batch);
+            }
+            batcher.wait()?;
+        }
+        match self.exporter {
+            Some(ref exporter) => exporter.flush(),
+            None => self.replay_spool().map(|_| ()),
+        }
+    }
+
+    /// Number of events indexed by this client and its clones, by event
+    /// type.
+    pub fn event_counts(&self) -> BTreeMap<String, u64> {
+        self.event_counts.lock().unwrap().clone()
+    }
+
+    /// Subscribes to the events indexed from now on by this client and its
+    /// clones.
+    pub fn subscribe(&self) -> Subscriber {
+        self.subscriptions.subscribe(&[])
+    }
+
+    /// Subscribes to the events of the given types indexed from now on by
+    /// this client and its clones, e.g. `commit` or `pull_request`.
+    pub fn subscribe_to(&self, event_types: &[&str]) -> Subscriber {
+        self.subscriptions.subscribe(event_types)
+    }
+
+    pub fn index<T: codealong::Event + serde::Serialize>(&self, event: T) -> Result<()> {
+        let event_type = event.event_type().to_owned();
+        *self
+            .event_counts
+            .lock()
+            .unwrap()
+            .entry(event_type.clone())
+            .or_insert(0) += 1;
+        let event = codealong::Envelope::new(event)
+            .with_host(hostname::get_hostname())
+            .stamp(
+                self.codealong_version.as_ref().map(|version| &**version),
+                self.config_hash.as_ref().map(|hash| &**hash),
+            )
+            .with_tenant(self.tenant.as_ref().map(|tenant| &**tenant));
+        let id = event.id().into_owned();
+        let index = get_es_index(&self.qualify(&self.index_prefix), event.timestamp());
+        let mut event = serde_json::to_value(&event)?;
+        if let Some(ref plugins) = self.plugins {
+            plugins.process(&event_type, &id, &mut event)?;
+        }
+        if let Some(ref field_encryption) = self.field_encryption {
+            field_encryption.encrypt(&mut event)?;
+        }
+        if let (Some(ref schema_check), None) = (&self.schema_check, &self.exporter) {
+            match self.check_schema(schema_check, &event_type, &id, &event) {
+                // checked once Elasticsearch is reachable again
+                Err(ref e) if is_unreachable(e) && self.spool.is_some() => {}
+                res => res?,
+            }
+        }
+        if !self.subscriptions.is_empty() {
+            self.subscriptions.notify(&event_type, &id, &event);
+        }
+        let event = QueuedEvent {
+            table: self.qualify(&event_type),
+            event_type,
+            event: SpooledEvent {
+                index,
+                id,
+                document: event,
+            },
+        };
+        match self.batcher {
+            Some(ref batcher) => {
+                batcher.check()?;
+                if let Some(batch) = batcher.push(event) {
+                    self.dispatch(batcher, batch);
+                }
+                Ok(())
+            }
+            None => self.write(vec![event]),
+        }
+    }
+
+    /// Reads the mapping the events are checked against up front, so that a
+    /// run fails before it writes anything if the mapping cannot be read, and
+    /// the events are checked against the indices as they were before the
+    /// run. Otherwise it is read when the first event is indexed, as it is
+    /// when Elasticsearch is unreachable and events are spooled.
+    pub fn load_schema(&self) -> Result<()> {
+        let schema_check = match (&self.schema_check, &self.exporter) {
+            (Some(ref schema_check), None) => schema_check,
+            _ => return Ok(()),
+        };
+        let mut state = schema_check.state.lock().unwrap();
+        if state.is_none() {
+            match self.mapping() {
+                Ok(mapping) => *state = Some(SchemaState::new(&mapping)),
+                Err(ref e) if is_unreachable(e) && self.spool.is_some() => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the fields of `document` against the mapping of the indices of
+    /// the client, adding the mappings of the fields not mapped yet.
+    fn check_schema(
         &self,
-        event: T,
-    ) -> Result<reqwest::Response> {
-        let event = Event::new(event);
-        let client = reqwest::Client::new();
-        let index = get_es_index(event.timestamp());
-        let url = format!("{}/{}/_doc/{}", self.url, index, event.id());
-        Ok(client.put(&url).json(&event).send()?)
+        schema_check: &SchemaCheck,
+        event_type: &str,
+        id: &str,
+        document: &Value,
+    ) -> Result<()> {
+        let mut state = schema_check.state.lock().unwrap();
+        if state.is_none() {
+            *state = Some(SchemaState::new(&self.mapping()?));
+        }
+        let state = state.as_mut().unwrap();
+        let new_fields = state
+            .check(event_type, id, document)
+            .map_err(ErrorKind::SchemaDrift)?;
+        if !new_fields.is_empty() {
+            if state.has_indices {
+                self.put_mapping(&schema::mapping_for(&new_fields))?;
+            }
+            state.add(new_fields);
+        }
+        Ok(())
+    }
+
+    /// The mappings of the indices of the client, by index.
+    fn mapping(&self) -> Result<Value> {
+        let client = self.http()?;
+        let url = format!(
+            "{}/{}-*/_mapping",
+            self.url,
+            self.qualify(&self.index_prefix)
+        );
+        let response = client.get(&url).send()?;
+        let status = response.status();
+        if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(ErrorKind::Unavailable(status.as_u16()).into());
+        }
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Ok(json!({}));
+        }
+        Ok(response.error_for_status()?.json()?)
+    }
+
+    /// Adds `mapping` to the mappings of the indices of the client.
+    fn put_mapping(&self, mapping: &Value) -> Result<()> {
+        let client = self.http()?;
+        let url = format!(
+            "{}/{}-*/_mapping/_doc",
+            self.url,
+            self.qualify(&self.index_prefix)
+        );
+        let response = client.put(&url).json(mapping).send()?;
+        let status = response.status();
+        if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(ErrorKind::Unavailable(status.as_u16()).into());
+        }
+        response.error_for_status()?;
+        Ok(())
+    }
+
+    /// Writes `batch` on a thread of its own once fewer than
+    /// `max_in_flight` batches are being written.
+    fn dispatch(&self, batcher: &Arc<Batcher>, batch: Vec<QueuedEvent>) {
+        batcher.acquire();
+        let client = self.clone();
+        let batcher = batcher.clone();
+        thread::spawn(move || batcher.release(client.write(batch)));
+    }
+
+    fn write(&self, batch: Vec<QueuedEvent>) -> Result<()> {
+        self.write_to_sink(&batch)?;
+        if let Some(ref plugins) = self.plugins {
+            for queued in &batch {
+                plugins.sink(&queued.event_type, &queued.event.id, &queued.event.document)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_to_sink(&self, batch: &[QueuedEvent]) -> Result<()> {
+        if let Some(ref exporter) = self.exporter {
+            for queued in batch {
+                exporter.export(&queued.table, &queued.event.id, &queued.event.document)?;
+            }
+            if self.batcher.is_some() {
+                exporter.flush()?;
+            }
+            return Ok(());
+        }
+        if let Some(ref spool) = self.spool {
+            self.replay_spool()?;
+            if self.spool_pending.load(Ordering::SeqCst) {
+                // still unreachable, queued behind the events spooled before
+                return self.spool_events(spool, batch.iter().map(|queued| &queued.event));
+            }
+        }
+        let res = match batch.len() {
+            1 => self.put(&batch[0].event),
+            _ => self.bulk(batch),
+        };
+        match (res, &self.spool) {
+            (Err(ref e), Some(spool)) if is_unreachable(e) => {
+                self.spool_events(spool, batch.iter().map(|queued| &queued.event))
+            }
+            (res, _) => res,
+        }
+    }
+
+    fn spool_events<'a, I: Iterator<Item = &'a SpooledEvent>>(
+        &self,
+        spool: &Spool,
+        events: I,
+    ) -> Result<()> {
+        let mut count = 0;
+        for event in events {
+            spool.push(event)?;
+            count += 1;
+        }
+        self.spooled.fetch_add(count, Ordering::SeqCst);
+        self.spool_pending.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn put(&self, event: &SpooledEvent) -> Result<()> {
+        let mut attempt = 0;
+        loop {
+            match self.put_request(event) {
+                Err(ref e) if is_transient(e) && attempt < self.max_retries => {
+                    thread::sleep(self.backoff(attempt));
+                    attempt += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+
+    fn put_request(&self, event: &SpooledEvent) -> Result<()> {
+        let client = self.http()?;
+        let url = format!("{}/{}/_doc/{}", self.url, event.index, event.id);
+        let mut response = client.put(&url).json(&event.document).send()?;
+        let status = response.status();
+        if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(ErrorKind::Unavailable(status.as_u16()).into());
+        }
+        if !status.is_success() {
+            let body: Value = response.json().unwrap_or_default();
+            return Err(
+                ErrorKind::Rejected(vec![rejection(&event.id, status.as_u16(), &body)]).into(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Indexes the events of `batch` with bulk requests, retrying the
+    /// request if it is rejected as a whole, or else the items which were
+    /// rejected as overloaded. The items still rejected after every retry
+    /// are spooled if the client has a spool, while those rejected for any
+    /// other reason fail the write once the others are written.
+    fn bulk(&self, batch: &[QueuedEvent]) -> Result<()> {
+        let mut pending: Vec<&SpooledEvent> = batch.iter().map(|queued| &queued.event).collect();
+        let mut failures = vec![];
+        let mut attempt = 0;
+        loop {
+            let (retry, mut failed) = match self.bulk_request(&pending) {
+                Ok(res) => res,
+                Err(ref e) if is_transient(e) && attempt < self.max_retries => {
+                    thread::sleep(self.backoff(attempt));
+                    attempt += 1;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            failures.append(&mut failed);
+            match retry.first() {
+                None => break,
+                Some((_, status)) if attempt >= self.max_retries => match self.spool {
+                    Some(ref spool) => {
+                        self.spool_events(spool, retry.iter().map(|(i, _)| pending[*i]))?;
+                        break;
+                    }
+                    None => return Err(ErrorKind::Unavailable(*status).into()),
+                },
+                Some(_) => {}
+            }
+            thread::sleep(self.backoff(attempt));
+            attempt += 1;
+            pending = retry.into_iter().map(|(i, _)| pending[i]).collect();
+        }
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(ErrorKind::Rejected(failures).into())
+        }
+    }
+
+    /// Indexes `events` with a single bulk request, returning the position
+    /// and status of the items rejected with a 429 or 503, to be retried,
+    /// along with why the items rejected for any other reason were.
+    fn bulk_request(&self, events: &[&SpooledEvent]) -> Result<BulkOutcome> {
+        let mut body = String::new();
+        for event in events {
+            let action = json!({
+                "index": { "_index": event.index, "_type": "_doc", "_id": event.id }
+            });
+            body.push_str(&serde_json::to_string(&action)?);
+            body.push('\n');
+            body.push_str(&serde_json::to_string(&event.document)?);
+            body.push('\n');
+        }
+        let client = self.http()?;
+        let mut response = client
+            .post(&format!("{}/_bulk", self.url))
+            .header(reqwest::header::CONTENT_TYPE, "application/x-ndjson")
+            .body(body)
+            .send()?;
+        let status = response.status();
+        if status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(ErrorKind::Unavailable(status.as_u16()).into());
+        }
+        let response: Value = response.json()?;
+        let mut retry = vec![];
+        let mut failed = vec![];
+        if response["errors"].as_bool() != Some(true) {
+            return Ok((retry, failed));
+        }
+        let items = response["items"]
+            .as_array()
+            .map_or(&[][..], |items| &items[..]);
+        for (i, item) in items.iter().enumerate() {
+            let status = match item["index"]["status"].as_u64() {
+                Some(status) => status as u16,
+                None => continue,
+            };
+            if is_transient_status(status) {
+                retry.push((i, status));
+            } else if status >= 300 {
+                failed.push(rejection(&events[i].id, status, &item["index"]));
+            }
+        }
+        Ok((retry, failed))
+    }
+
+    /// Wait before retrying for the `attempt`th time, counting from 0.
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.retry_backoff * 2u32.saturating_pow(attempt)
+    }
+
+    /// Indexes the spooled events, oldest first, stopping at the first
+    /// which cannot be indexed while Elasticsearch is unreachable. Events it
+    /// rejects for good are dropped from the spool and fail the replay once
+    /// the others are indexed. Returns the number of events replayed.
+    pub fn replay_spool(&self) -> Result<usize> {
+        let spool = match self.spool {
+            Some(ref spool) => spool,
+            None => return Ok(0),
+        };
+        // waits for a clone replaying, so that nothing is written before the
+        // events spooled ahead of it
+        let _replaying = self.replaying.lock().unwrap();
+        if !self.spool_pending.swap(false, Ordering::SeqCst) {
+            return Ok(0);
+        }
+        let mut replayed = 0;
+        let mut failures = vec![];
+        for path in spool.paths()? {
+            match self.put(&spool.read(&path)?) {
+                Ok(()) => {
+                    spool.remove(&path)?;
+                    replayed += 1;
+                }
+                Err(ref e) if is_unreachable(e) => {
+                    self.spool_pending.store(true, Ordering::SeqCst);
+                    break;
+                }
+                Err(Error(ErrorKind::Rejected(mut rejected), _)) => {
+                    // replaying it again would only be rejected again
+                    spool.remove(&path)?;
+                    failures.append(&mut rejected);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        if failures.is_empty() {
+            Ok(replayed)
+        } else {
+            Err(ErrorKind::Rejected(failures).into())
+        }
+    }
+
+    /// Counts pull requests merged by members of `team` in each of the last
+    /// `weeks` full weeks.
+    pub fn weekly_merged_pull_requests(&self, team: &str, weeks: u32) -> Result<Vec<u64>> {
+        let since = format!("now-{}w/w", weeks);
+        let query = json!({
+            "size": 0,
+            "query": {
+                "bool": {
+                    "filter": [
+                        { "term": { "type.keyword": "pull_request" } },
+                        { "term": { "normalized_author.teams.keyword": team } },
+                        { "range": { "merged_at": { "gte": since, "lt": "now/w" } } }
+                    ]
+                }
+            },
+            "aggs": {
+                "weekly": {
+                    "date_histogram": {
+                        "field": "merged_at",
+                        "interval": "week",
+                        "min_doc_count": 0,
+                        "extended_bounds": { "min": since, "max": "now-1w/w" }
+                    }
+                }
+            }
+        });
+        let client = self.http()?;
+        let url = format!("{}/{}/_search", self.url, self.qualify("codealong-*"));
+        let response: Value = client.post(&url).json(&query).send()?.json()?;
+        Ok(response["aggregations"]["weekly"]["buckets"]
+            .as_array()
+            .map(|buckets| {
+                buckets
+                    .iter()
+                    .filter_map(|bucket| bucket["doc_count"].as_u64())
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// Counts the commits made to `repo` by each author over the last `days`.
+    pub fn commits_by_author(&self, repo: &str, days: u32) -> Result<Vec<u64>> {
+        let query = json!({
+            "size": 0,
+            "query": {
+                "bool": {
+                    "filter": [
+                        { "term": { "type.keyword": "commit" } },
+                        { "term": { "repo.name.keyword": repo } },
+                        { "range": { "authored_at": { "gte": format!("now-{}d/d", days) } } }
+                    ]
+                }
+            },
+            "aggs": {
+                "authors": {
+                    "terms": { "field": "normalized_author.id.keyword", "size": 10000 }
+                }
+            }
+        });
+        let client = self.http()?;
+        let url = format!("{}/{}/_search", self.url, self.qualify("codealong-*"));
+        let response: Value = client.post(&url).json(&query).send()?.json()?;
+        Ok(response["aggregations"]["authors"]["buckets"]
+            .as_array()
+            .map(|buckets| {
+                buckets
+                    .iter()
+                    .filter_map(|bucket| bucket["doc_count"].as_u64())
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    /// Median seconds from opening to merging the pull requests of `repo`
+    /// merged over the last `days`, if any were.
+    pub fn median_time_to_resolve(&self, repo: &str, days: u32) -> Result<Option<f64>> {
+        let query = json!({
+            "size": 0,
+            "query": {
+                "bool": {
+                    "filter": [
+                        { "term": { "type.keyword": "pull_request" } },
+                        { "term": { "base.repo.full_name.keyword": repo } },
+                        { "range": { "merged_at": { "gte": format!("now-{}d/d", days) } } }
+                    ]
+                }
+            },
+            "aggs": {
+                "time_to_resolve": {
+                    "percentiles": { "field": "time_to_resolve", "percents": [50] }
+                }
+            }
+        });
+        let client = self.http()?;
+        let url = format!("{}/{}/_search", self.url, self.qualify("codealong-*"));
+        let response: Value = client.post(&url).json(&query).send()?.json()?;
+        Ok(response["aggregations"]["time_to_resolve"]["values"]["50.0"].as_f64())
+    }
+
+    /// Searches the analyzed commits and pull requests, best matches first.
+    pub fn search(&self, query: &SearchQuery) -> Result<Vec<SearchHit>> {
+        let client = self.http()?;
+        let url = format!("{}/{}/_search", self.url, self.qualify("codealong-*"));
+        let response: Value = client.post(&url).json(&query.to_query()).send()?.json()?;
+        Ok(response["hits"]["hits"]
+            .as_array()
+            .map(|hits| hits.iter().map(SearchHit::from_hit).collect())
+            .unwrap_or_default())
+    }
+
+    /// The most recent analysis runs, latest first.
+    pub fn runs(&self, limit: usize) -> Result<Vec<AnalysisRun>> {
+        self.find_runs(json!({
+            "size": limit,
+            "query": { "term": { "type.keyword": "analysis_run" } },
+            "sort": [{ "started_at": "desc" }]
+        }))
+    }
+
+    /// The analysis run with an id starting with `id`.
+    pub fn run(&self, id: &str) -> Result<Option<AnalysisRun>> {
+        Ok(self
+            .find_runs(json!({
+                "size": 1,
+                "query": {
+                    "bool": {
+                        "filter": [
+                            { "term": { "type.keyword": "analysis_run" } },
+                            { "prefix": { "id.keyword": id } }
+                        ]
+                    }
+                },
+                "sort": [{ "started_at": "desc" }]
+            }))?
+            .pop())
+    }
+
+    fn find_runs(&self, query: Value) -> Result<Vec<AnalysisRun>> {
+        let client = self.http()?;
+        let url = format!("{}/{}/_search", self.url, self.qualify("codealong-*"));
+        let response: Value = client.post(&url).json(&query).send()?.json()?;
+        let hits = match response["hits"]["hits"].as_array() {
+            Some(hits) => hits.clone(),
+            None => return Ok(vec![]),
+        };
+        let mut runs = vec![];
+        for hit in hits {
+            runs.push(serde_json::from_value(hit["_source"].clone())?);
+        }
+        Ok(runs)
+    }
+
+    /// Rewrites the repo name of events indexed under `from` to `to`, e.g.
+    /// after a repo has been renamed. Returns the number of events updated.
+    pub fn rename_repo(&self, from: &str, to: &str) -> Result<u64> {
+        // events carry the repo either as an object with a name, as a plain
+        // string or, for snapshots, as a list of names
+        let query = json!({
+            "query": {
+                "bool": {
+                    "should": [
+                        { "term": { "repo.name.keyword": from } },
+                        { "term": { "repo.keyword": from } },
+                        { "term": { "repos.keyword": from } }
+                    ],
+                    "minimum_should_match": 1
+                }
+            },
+            "script": {
+                "lang": "painless",
+                "params": { "from": from, "to": to },
+                "source": "
+                    def repo = ctx._source.repo;
+                    if (repo instanceof Map) {
+                        if (repo.name == params.from) { repo.name = params.to; }
+                    } else if (repo == params.from) {
+                        ctx._source.repo = params.to;
+                    }
+                    def repos = ctx._source.repos;
+                    if (repos instanceof List) {
+                        for (int i = 0; i < repos.size(); i++) {
+                            if (repos[i] == params.from) { repos[i] = params.to; }
+                        }
+                    }
+                "
+            }
+        });
+        let client = self.http()?;
+        let url = format!(
+            "{}/{}/_update_by_query?conflicts=proceed&refresh=true",
+            self.url,
+            self.qualify("codealong-*")
+        );
+        let response: Value = client.post(&url).json(&query).send()?.json()?;
+        Ok(response["updated"].as_u64().unwrap_or(0))
+    }
+
+    /// Prefixes `name`, an index, index pattern or table, with the tenant.
+    fn qualify(&self, name: &str) -> String {
+        match self.tenant {
+            Some(ref tenant) => format!("{}-{}", tenant, name),
+            None => name.to_owned(),
+        }
     }
 
     pub fn health(&self) -> Result<reqwest::Response> {
-        let client = reqwest::Client::new();
+        let client = self.http()?;
         let url = format!("{}/{}", self.url, "_cluster/health");
         Ok(client.get(&url).send()?)
     }
+
+    /// A client for requests to Elasticsearch, unless network access is
+    /// disabled.
+    fn http(&self) -> Result<reqwest::Client> {
+        codealong::ensure_online(&format!("requesting {}", self.url))?;
+        Ok(reqwest::Client::new())
+    }
+}
+
+/// Whether `error` is one which Elasticsearch may recover from, e.g. it
+/// being down or overloaded, rather than one with the event itself.
+fn is_unreachable(error: &Error) -> bool {
+    match error.kind() {
+        ErrorKind::ES(_) | ErrorKind::Unavailable(_) => true,
+        _ => false,
+    }
+}
+
+/// Whether `error` is a rejection by Elasticsearch which is worth retrying.
+fn is_transient(error: &Error) -> bool {
+    match error.kind() {
+        ErrorKind::Unavailable(status) => is_transient_status(*status),
+        _ => false,
+    }
+}
+
+/// Whether `status` is 429, Elasticsearch being overloaded, or 503, it being
+/// unavailable, e.g. while a node restarts.
+fn is_transient_status(status: u16) -> bool {
+    status == 429 || status == 503
+}
+
+/// Why Elasticsearch rejected the event with `id` with `status`, from the
+/// `error` of `response`.
+fn rejection(id: &str, status: u16, response: &Value) -> String {
+    let error = &response["error"];
+    match (error["type"].as_str(), error["reason"].as_str()) {
+        (Some(kind), Some(reason)) => format!("{}: {}: {}", id, kind, reason),
+        _ => format!("{}: status {}", id, status),
+    }
 }
 
-fn get_es_index(date: &DateTime<Utc>) -> String {
-    date.format("codealong-%Y.%m").to_string()
+fn get_es_index(prefix: &str, date: &DateTime<Utc>) -> String {
+    format!("{}-{}", prefix, date.format("%Y.%m"))
 }
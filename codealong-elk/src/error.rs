@@ -1,7 +1,60 @@
 use reqwest;
 
+use crate::schema::FieldConflict;
+
 error_chain! {
+    errors {
+        InvalidEncryptedField(value: String) {
+            description("invalid encrypted field")
+            display("invalid encrypted field: '{}'", value)
+        }
+
+        Unavailable(status: u16) {
+            description("elasticsearch unavailable")
+            display("elasticsearch unavailable: status {}", status)
+        }
+
+        Rejected(reasons: Vec<String>) {
+            description("events rejected")
+            display(
+                "elasticsearch rejected {} events:\n{}",
+                reasons.len(),
+                reasons.iter().map(|r| format!("  {}", r)).collect::<Vec<_>>().join("\n")
+            )
+        }
+
+        InvalidTenant(tenant: String) {
+            description("invalid tenant")
+            display("invalid tenant: '{}', only lowercase letters, digits and underscores are allowed", tenant)
+        }
+
+        SchemaDrift(conflicts: Vec<FieldConflict>) {
+            description("schema drift")
+            display(
+                "events do not fit the mapping of their indices:\n{}",
+                conflicts.iter().map(|c| format!("  {}", c)).collect::<Vec<_>>().join("\n")
+            )
+        }
+
+        EventLogLocked(pid: u32) {
+            description("event log locked")
+            display("event log is being written by process {}", pid)
+        }
+
+        InvalidConsumer(name: String) {
+            description("invalid consumer")
+            display("invalid consumer: '{}', only letters, digits, dashes and underscores are allowed", name)
+        }
+    }
+
     foreign_links {
         ES(reqwest::Error);
+        IO(std::io::Error);
+        Json(serde_json::Error);
+        Openssl(openssl::error::ErrorStack);
+    }
+
+    links {
+        Core(codealong::Error, codealong::ErrorKind);
     }
 }
@@ -0,0 +1,341 @@
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::Duration;
+use serde_json::Value;
+
+use codealong::{LockHolder, RepoLock, STALE_LOCK_HOURS};
+
+use crate::error::{ErrorKind, Result};
+use crate::exporter::Exporter;
+
+/// Records written to a segment before the next one is started, so that
+/// reading from an offset only scans the segment holding it.
+const SEGMENT_RECORDS: u64 = 10_000;
+
+/// An event in an `EventLog`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogRecord {
+    pub offset: u64,
+    /// Event type of the event prefixed with the tenant, if any, e.g.
+    /// `acme-commit`.
+    pub table: String,
+    pub id: String,
+    pub document: Value,
+}
+
+/// An append-only log of events in a local directory, in which every event
+/// is given the next of a monotonically increasing offset. Several
+/// downstream consumers can read it from any offset, and track how far they
+/// got independently of each other, e.g. with `Consumer`, without running a
+/// message broker.
+///
+/// The log is kept as newline delimited JSON in segments named by their
+/// first offset, e.g. `00000000000000010000.ndjson`. Only one process
+/// writes to a log at a time, which is guarded by `writer.lock` in its
+/// directory, while any number may read it.
+pub struct EventLog {
+    dir: PathBuf,
+    writer: Mutex<Option<Writer>>,
+}
+
+struct Writer {
+    next_offset: u64,
+    /// First offset of the segment being written.
+    segment: u64,
+    file: BufWriter<File>,
+    _lock: RepoLock,
+}
+
+impl EventLog {
+    /// The directory is created, and the writer lock taken, when the first
+    /// event is appended.
+    pub fn new(dir: PathBuf) -> EventLog {
+        EventLog {
+            dir,
+            writer: Mutex::new(None),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Appends an event, returning its offset.
+    pub fn append(&self, table: &str, id: &str, document: &Value) -> Result<u64> {
+        let mut writer = self.writer.lock().unwrap();
+        if writer.is_none() {
+            *writer = Some(self.open_writer()?);
+        }
+        let writer = writer.as_mut().unwrap();
+        if writer.next_offset >= writer.segment + SEGMENT_RECORDS {
+            writer.file.flush()?;
+            writer.segment = writer.next_offset;
+            writer.file = BufWriter::new(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(segment_path(&self.dir, writer.segment))?,
+            );
+        }
+        let record = LogRecord {
+            offset: writer.next_offset,
+            table: table.to_owned(),
+            id: id.to_owned(),
+            document: document.clone(),
+        };
+        serde_json::to_writer(&mut writer.file, &record)?;
+        writer.file.write_all(b"\n")?;
+        writer.next_offset += 1;
+        Ok(record.offset)
+    }
+
+    /// The offset the next event appended will get.
+    pub fn next_offset(&self) -> Result<u64> {
+        if let Some(ref writer) = *self.writer.lock().unwrap() {
+            return Ok(writer.next_offset);
+        }
+        Ok(match segments(&self.dir)?.last() {
+            Some(&segment) => {
+                last_offset(&segment_path(&self.dir, segment))?.map_or(segment, |offset| offset + 1)
+            }
+            None => 0,
+        })
+    }
+
+    /// Reads the events from `offset` on, oldest first, up to the last one
+    /// written when each segment is reached. Events appended by a writer are
+    /// only seen once it flushed them.
+    pub fn read_from(&self, offset: u64) -> Result<LogReader> {
+        let segments = segments(&self.dir)?;
+        // the segment holding the offset, and all later ones
+        let start = segments
+            .iter()
+            .rposition(|&segment| segment <= offset)
+            .unwrap_or(0);
+        Ok(LogReader {
+            dir: self.dir.clone(),
+            segments: segments[start..].to_vec(),
+            current: None,
+            offset,
+        })
+    }
+
+    /// A consumer of the log named `name`, which keeps its offset in the
+    /// `consumers` directory of the log.
+    pub fn consumer(&self, name: &str) -> Result<Consumer> {
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            return Err(ErrorKind::InvalidConsumer(name.to_owned()).into());
+        }
+        Ok(Consumer {
+            log: EventLog::new(self.dir.clone()),
+            path: self.dir.join("consumers").join(format!("{}.offset", name)),
+        })
+    }
+
+    fn open_writer(&self) -> Result<Writer> {
+        fs::create_dir_all(&self.dir)?;
+        let holder = LockHolder::current(None, None);
+        let lock = match RepoLock::try_acquire(
+            &self.dir.join("writer.lock"),
+            &holder,
+            Duration::hours(STALE_LOCK_HOURS),
+        )? {
+            Ok(lock) => lock,
+            Err(holder) => return Err(ErrorKind::EventLogLocked(holder.pid).into()),
+        };
+        let segment = segments(&self.dir)?.last().cloned().unwrap_or(0);
+        let path = segment_path(&self.dir, segment);
+        truncate_partial_record(&path)?;
+        let next_offset = last_offset(&path)?.map_or(segment, |offset| offset + 1);
+        Ok(Writer {
+            next_offset,
+            segment,
+            file: BufWriter::new(OpenOptions::new().create(true).append(true).open(&path)?),
+            _lock: lock,
+        })
+    }
+}
+
+impl fmt::Debug for EventLog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EventLog").field("dir", &self.dir).finish()
+    }
+}
+
+impl Exporter for EventLog {
+    fn name(&self) -> &'static str {
+        "event_log"
+    }
+
+    fn export(&self, table: &str, id: &str, event: &Value) -> Result<()> {
+        self.append(table, id, event).map(|_| ())
+    }
+
+    fn flush(&self) -> Result<()> {
+        if let Some(ref mut writer) = *self.writer.lock().unwrap() {
+            writer.file.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads the events of an `EventLog` from an offset, see
+/// `EventLog::read_from`.
+pub struct LogReader {
+    dir: PathBuf,
+    /// First offsets of the segments left to read.
+    segments: Vec<u64>,
+    current: Option<BufReader<File>>,
+    /// Offset of the next event to yield.
+    offset: u64,
+}
+
+impl LogReader {
+    fn next_record(&mut self) -> Result<Option<LogRecord>> {
+        loop {
+            if self.current.is_none() {
+                if self.segments.is_empty() {
+                    return Ok(None);
+                }
+                let segment = self.segments.remove(0);
+                self.current = Some(BufReader::new(File::open(segment_path(
+                    &self.dir, segment,
+                ))?));
+            }
+            let mut line = String::new();
+            let read = self.current.as_mut().unwrap().read_line(&mut line)?;
+            // a record without its newline is still being written
+            if read == 0 || !line.ends_with('\n') {
+                self.current = None;
+                if read > 0 {
+                    return Ok(None);
+                }
+                continue;
+            }
+            let record: LogRecord = serde_json::from_str(&line)?;
+            if record.offset >= self.offset {
+                self.offset = record.offset + 1;
+                return Ok(Some(record));
+            }
+        }
+    }
+}
+
+impl Iterator for LogReader {
+    type Item = Result<LogRecord>;
+
+    fn next(&mut self) -> Option<Result<LogRecord>> {
+        self.next_record().transpose()
+    }
+}
+
+/// A downstream consumer of an `EventLog`, which tracks how far it got.
+/// Records are read from the committed offset, and the offset after the
+/// last record processed is committed once it is processed, so that a
+/// consumer which stops resumes where it left off.
+#[derive(Debug)]
+pub struct Consumer {
+    log: EventLog,
+    path: PathBuf,
+}
+
+impl Consumer {
+    /// The offset of the next record to process, 0 if nothing was
+    /// committed yet.
+    pub fn offset(&self) -> Result<u64> {
+        match fs::read_to_string(&self.path) {
+            Ok(offset) => Ok(offset
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid offset in {}", self.path.display()))?),
+            Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Reads the records from the committed offset on.
+    pub fn poll(&self) -> Result<LogReader> {
+        self.log.read_from(self.offset()?)
+    }
+
+    /// Commits that the records before `offset` were processed.
+    pub fn commit(&self, offset: u64) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // written aside and renamed so that the offset is never half written
+        let tmp_path = self.path.with_extension("offset.tmp");
+        fs::write(&tmp_path, format!("{}\n", offset))?;
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+fn segment_path(dir: &Path, segment: u64) -> PathBuf {
+    dir.join(format!("{:020}.ndjson", segment))
+}
+
+/// First offsets of the segments of the log in `dir`, in order.
+fn segments(dir: &Path) -> Result<Vec<u64>> {
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+    let mut segments = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().map_or(false, |ext| ext == "ndjson") {
+            if let Some(segment) = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| stem.parse().ok())
+            {
+                segments.push(segment);
+            }
+        }
+    }
+    segments.sort();
+    Ok(segments)
+}
+
+/// Offset of the last complete record of a segment, if any.
+fn last_offset(path: &Path) -> Result<Option<u64>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let mut last = None;
+    for line in BufReader::new(File::open(path)?).split(b'\n') {
+        let line = line?;
+        if let Ok(record) = serde_json::from_slice::<LogRecord>(&line) {
+            last = Some(record.offset);
+        }
+    }
+    Ok(last)
+}
+
+/// Drops the record a crashed writer left half written at the end of a
+/// segment.
+fn truncate_partial_record(path: &Path) -> Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    let mut content = vec![];
+    file.read_to_end(&mut content)?;
+    let complete = content
+        .iter()
+        .rposition(|&b| b == b'\n')
+        .map_or(0, |index| index + 1);
+    if complete < content.len() {
+        file.set_len(complete as u64)?;
+        file.seek(SeekFrom::End(0))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde_json::{Map, Value};
+
+use crate::error::Result;
+
+/// A destination other than Elasticsearch which events are written to, e.g.
+/// to load them into a data warehouse or process them with scripts.
+pub trait Exporter: Send + Sync + fmt::Debug {
+    /// Name of the format, under which the batching of the events exported
+    /// is configured, e.g. `ndjson`.
+    fn name(&self) -> &'static str;
+
+    /// Writes `event`, the JSON document of an event with the id `id`, to
+    /// `table`, which is its event type prefixed with the tenant, if any,
+    /// e.g. `acme-commit`.
+    fn export(&self, table: &str, id: &str, event: &Value) -> Result<()>;
+
+    /// Writes out anything buffered.
+    fn flush(&self) -> Result<()>;
+}
+
+/// Writes events as newline delimited JSON, to stdout or to a file.
+pub struct NdjsonExporter {
+    path: Option<PathBuf>,
+    writer: Mutex<Option<Box<dyn Write + Send>>>,
+}
+
+impl NdjsonExporter {
+    /// Events are flushed as they are written so that downstream tools see
+    /// them as they are produced.
+    pub fn stdout() -> NdjsonExporter {
+        NdjsonExporter {
+            path: None,
+            writer: Mutex::new(None),
+        }
+    }
+
+    /// The file is created when the first event is written.
+    pub fn file(path: PathBuf) -> NdjsonExporter {
+        NdjsonExporter {
+            path: Some(path),
+            writer: Mutex::new(None),
+        }
+    }
+}
+
+impl fmt::Debug for NdjsonExporter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NdjsonExporter")
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+impl Exporter for NdjsonExporter {
+    fn name(&self) -> &'static str {
+        "ndjson"
+    }
+
+    fn export(&self, _table: &str, _id: &str, event: &Value) -> Result<()> {
+        let line = serde_json::to_string(event)?;
+        let mut writer = self.writer.lock().unwrap();
+        if writer.is_none() {
+            *writer = Some(match self.path {
+                Some(ref path) => Box::new(BufWriter::new(File::create(path)?)),
+                None => Box::new(io::stdout()),
+            });
+        }
+        let writer = writer.as_mut().unwrap();
+        writeln!(writer, "{}", line)?;
+        if self.path.is_none() {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<()> {
+        if let Some(ref mut writer) = *self.writer.lock().unwrap() {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes the events of each table to `<dir>/<table>.csv`, with nested
+/// fields flattened into dotted columns, e.g. `author.email`, and arrays
+/// written as JSON. The columns of a file are those of the first event
+/// written to it; fields only present in later events are left out.
+#[derive(Debug)]
+pub struct CsvExporter {
+    dir: PathBuf,
+    files: Mutex<HashMap<String, CsvFile>>,
+}
+
+#[derive(Debug)]
+struct CsvFile {
+    writer: BufWriter<File>,
+    columns: Vec<String>,
+}
+
+impl CsvExporter {
+    /// The directory and files are created when the first event is written.
+    pub fn new(dir: PathBuf) -> CsvExporter {
+        CsvExporter {
+            dir,
+            files: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl Exporter for CsvExporter {
+    fn name(&self) -> &'static str {
+        "csv"
+    }
+
+    fn export(&self, table: &str, _id: &str, event: &Value) -> Result<()> {
+        let mut fields = Map::new();
+        flatten("", event, &mut fields);
+        let mut files = self.files.lock().unwrap();
+        if !files.contains_key(table) {
+            fs::create_dir_all(&self.dir)?;
+            let mut file = CsvFile {
+                writer: BufWriter::new(File::create(self.dir.join(format!("{}.csv", table)))?),
+                columns: fields.keys().cloned().collect(),
+            };
+            let header: Vec<_> = file.columns.iter().map(|c| csv_field(c)).collect();
+            writeln!(file.writer, "{}", header.join(","))?;
+            files.insert(table.to_owned(), file);
+        }
+        let file = files.get_mut(table).unwrap();
+        let row: Vec<_> = file
+            .columns
+            .iter()
+            .map(|column| match fields.get(column) {
+                None | Some(Value::Null) => String::new(),
+                Some(Value::String(s)) => csv_field(s),
+                Some(value) => csv_field(&value.to_string()),
+            })
+            .collect();
+        writeln!(file.writer, "{}", row.join(","))?;
+        Ok(())
+    }
+
+    fn flush(&self) -> Result<()> {
+        for file in self.files.lock().unwrap().values_mut() {
+            file.writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Collects the leaves of `value` keyed by their dotted paths.
+fn flatten(prefix: &str, value: &Value, res: &mut Map<String, Value>) {
+    match value {
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                let key = if prefix.is_empty() {
+                    key.to_owned()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten(&key, value, res);
+            }
+        }
+        value => {
+            res.insert(prefix.to_owned(), value.clone());
+        }
+    }
+}
+
+/// Quotes `field` if it contains a delimiter, quote or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+
+use openssl::pkey::{Private, Public};
+use openssl::rand::rand_bytes;
+use openssl::rsa::{Padding, Rsa};
+use openssl::symm::{decrypt_aead, encrypt_aead, Cipher};
+use serde_json::Value;
+
+use crate::error::{ErrorKind, Result};
+
+/// Prefix of encrypted field values, which also versions their format.
+const PREFIX: &str = "enc:v1:";
+const KEY_LEN: usize = 32;
+const IV_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+/// Encrypts designated fields of events with AES-256-GCM under a data key
+/// which is itself wrapped with an RSA public key. Each encrypted value
+/// carries the wrapped key, so that it can be decrypted on its own.
+#[derive(Debug)]
+pub struct FieldEncryptor {
+    fields: Vec<Vec<String>>,
+    key: Vec<u8>,
+    wrapped_key: Vec<u8>,
+}
+
+impl FieldEncryptor {
+    /// `public_key` is PEM encoded and `fields` are dotted paths, e.g.
+    /// `author.email`.
+    pub fn new(public_key: &[u8], fields: &[String]) -> Result<FieldEncryptor> {
+        let rsa = Rsa::<Public>::public_key_from_pem(public_key)?;
+        let mut key = vec![0; KEY_LEN];
+        rand_bytes(&mut key)?;
+        let mut wrapped_key = vec![0; rsa.size() as usize];
+        let len = rsa.public_encrypt(&key, &mut wrapped_key, Padding::PKCS1_OAEP)?;
+        wrapped_key.truncate(len);
+        Ok(FieldEncryptor {
+            fields: fields
+                .iter()
+                .map(|field| field.split('.').map(|s| s.to_owned()).collect())
+                .collect(),
+            key,
+            wrapped_key,
+        })
+    }
+
+    /// Replaces the designated fields of `event` with their encryption.
+    pub fn encrypt(&self, event: &mut Value) -> Result<()> {
+        for path in &self.fields {
+            let mut values = vec![];
+            find_fields(event, path, &mut values);
+            for value in values {
+                *value = Value::String(self.encrypt_value(value)?);
+            }
+        }
+        Ok(())
+    }
+
+    fn encrypt_value(&self, value: &Value) -> Result<String> {
+        let mut iv = [0; IV_LEN];
+        rand_bytes(&mut iv)?;
+        let mut tag = [0; TAG_LEN];
+        let ciphertext = encrypt_aead(
+            Cipher::aes_256_gcm(),
+            &self.key,
+            Some(&iv),
+            &[],
+            &serde_json::to_vec(value)?,
+            &mut tag,
+        )?;
+        let mut envelope =
+            Vec::with_capacity(2 + self.wrapped_key.len() + IV_LEN + TAG_LEN + ciphertext.len());
+        envelope.extend(&(self.wrapped_key.len() as u16).to_be_bytes());
+        envelope.extend(&self.wrapped_key);
+        envelope.extend(&iv);
+        envelope.extend(&tag);
+        envelope.extend(&ciphertext);
+        Ok(format!("{}{}", PREFIX, base64::encode(&envelope)))
+    }
+}
+
+/// Decrypts the fields encrypted by a `FieldEncryptor`, wherever they are in
+/// an event.
+pub struct FieldDecryptor {
+    rsa: Rsa<Private>,
+    /// Data keys by their wrapped form, since unwrapping is slow.
+    keys: HashMap<Vec<u8>, Vec<u8>>,
+}
+
+impl FieldDecryptor {
+    /// `private_key` is PEM encoded.
+    pub fn new(private_key: &[u8]) -> Result<FieldDecryptor> {
+        Ok(FieldDecryptor {
+            rsa: Rsa::private_key_from_pem(private_key)?,
+            keys: HashMap::new(),
+        })
+    }
+
+    /// Replaces the encrypted fields of `event` with their decryption.
+    pub fn decrypt(&mut self, event: &mut Value) -> Result<()> {
+        match event {
+            Value::String(s) if s.starts_with(PREFIX) => {
+                *event = self.decrypt_value(&s[PREFIX.len()..])?;
+            }
+            Value::Array(values) => {
+                for value in values {
+                    self.decrypt(value)?;
+                }
+            }
+            Value::Object(fields) => {
+                for (_, value) in fields.iter_mut() {
+                    self.decrypt(value)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn decrypt_value(&mut self, encoded: &str) -> Result<Value> {
+        let invalid = || ErrorKind::InvalidEncryptedField(encoded.to_owned());
+        let envelope = base64::decode(encoded).map_err(|_| invalid())?;
+        if envelope.len() < 2 {
+            return Err(invalid().into());
+        }
+        let key_len = u16::from_be_bytes([envelope[0], envelope[1]]) as usize;
+        if envelope.len() < 2 + key_len + IV_LEN + TAG_LEN {
+            return Err(invalid().into());
+        }
+        let (wrapped_key, rest) = envelope[2..].split_at(key_len);
+        let (iv, rest) = rest.split_at(IV_LEN);
+        let (tag, ciphertext) = rest.split_at(TAG_LEN);
+        if !self.keys.contains_key(wrapped_key) {
+            let mut key = vec![0; self.rsa.size() as usize];
+            let len = self
+                .rsa
+                .private_decrypt(wrapped_key, &mut key, Padding::PKCS1_OAEP)?;
+            key.truncate(len);
+            self.keys.insert(wrapped_key.to_vec(), key);
+        }
+        let plaintext = decrypt_aead(
+            Cipher::aes_256_gcm(),
+            &self.keys[wrapped_key],
+            Some(iv),
+            &[],
+            ciphertext,
+            tag,
+        )?;
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+}
+
+/// Collects the values at `path` in `value`, descending into every element
+/// of the arrays along the way.
+fn find_fields<'a>(value: &'a mut Value, path: &[String], res: &mut Vec<&'a mut Value>) {
+    match value {
+        Value::Array(values) => {
+            for value in values {
+                find_fields(value, path, res);
+            }
+        }
+        Value::Object(fields) => {
+            if let Some((first, rest)) = path.split_first() {
+                if let Some(value) = fields.get_mut(first) {
+                    if rest.is_empty() {
+                        if !value.is_null() {
+                            res.push(value);
+                        }
+                    } else {
+                        find_fields(value, rest, res);
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// A PEM encoded key pair, as (public, private).
+    fn key_pair() -> (Vec<u8>, Vec<u8>) {
+        let rsa = Rsa::generate(2048).unwrap();
+        (
+            rsa.public_key_to_pem().unwrap(),
+            rsa.private_key_to_pem().unwrap(),
+        )
+    }
+
+    fn event() -> Value {
+        json!({
+            "author": { "name": "Jane", "email": "jane@example.com" },
+            "co_authors": [
+                { "email": "bob@example.com" },
+                { "email": null }
+            ],
+            "lines": 42
+        })
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let (public_key, private_key) = key_pair();
+        let fields = vec!["author.email".to_owned(), "co_authors.email".to_owned()];
+        let encryptor = FieldEncryptor::new(&public_key, &fields).unwrap();
+        let mut event = event();
+        encryptor.encrypt(&mut event).unwrap();
+        assert!(event["author"]["email"]
+            .as_str()
+            .unwrap()
+            .starts_with(PREFIX));
+        assert!(event["co_authors"][0]["email"]
+            .as_str()
+            .unwrap()
+            .starts_with(PREFIX));
+        assert_eq!(event["co_authors"][1]["email"], Value::Null);
+        assert_eq!(event["author"]["name"], "Jane");
+
+        let mut decryptor = FieldDecryptor::new(&private_key).unwrap();
+        decryptor.decrypt(&mut event).unwrap();
+        assert_eq!(event, self::event());
+    }
+
+    #[test]
+    fn test_wrong_key() {
+        let (public_key, private_key) = key_pair();
+        let (_, other_private_key) = key_pair();
+        let encryptor = FieldEncryptor::new(&public_key, &["author.email".to_owned()]).unwrap();
+        let mut event = event();
+        encryptor.encrypt(&mut event).unwrap();
+        let mut decryptor = FieldDecryptor::new(&other_private_key).unwrap();
+        assert!(decryptor.decrypt(&mut event.clone()).is_err());
+
+        // nor is a tampered value with the right key
+        let mut decryptor = FieldDecryptor::new(&private_key).unwrap();
+        let encrypted = event["author"]["email"].as_str().unwrap();
+        let mut envelope = base64::decode(&encrypted[PREFIX.len()..]).unwrap();
+        *envelope.last_mut().unwrap() ^= 1;
+        let mut tampered = json!(format!("{}{}", PREFIX, base64::encode(&envelope)));
+        assert!(decryptor.decrypt(&mut tampered).is_err());
+
+        let mut truncated = json!(format!("{}AAE=", PREFIX));
+        match decryptor.decrypt(&mut truncated) {
+            Err(e) => match e.kind() {
+                ErrorKind::InvalidEncryptedField(_) => {}
+                kind => panic!("unexpected error {}", kind),
+            },
+            Ok(_) => panic!("truncated value decrypted"),
+        }
+    }
+}
@@ -1,16 +1,33 @@
+extern crate base64;
 extern crate chrono;
 extern crate codealong;
 #[macro_use]
 extern crate error_chain;
+extern crate hostname;
+extern crate openssl;
 extern crate reqwest;
 #[macro_use]
 extern crate serde_derive;
 extern crate serde;
+#[macro_use]
 extern crate serde_json;
 
+mod batch;
 mod client;
 mod error;
-mod event;
+mod event_log;
+mod exporter;
+mod field_encryption;
+mod schema;
+mod search;
+mod spool;
+mod subscriber;
 
 pub use crate::client::Client;
 pub use crate::error::{Error, ErrorKind};
+pub use crate::event_log::{Consumer, EventLog, LogReader, LogRecord};
+pub use crate::exporter::{CsvExporter, Exporter, NdjsonExporter};
+pub use crate::field_encryption::{FieldDecryptor, FieldEncryptor};
+pub use crate::schema::{FieldConflict, FieldKind};
+pub use crate::search::{SearchHit, SearchQuery};
+pub use crate::subscriber::{Notification, Subscriber};
@@ -0,0 +1,379 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Mutex;
+
+use chrono::DateTime;
+use serde_json::{Map, Value};
+
+/// Kind of a field of an event, as Elasticsearch sees it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FieldKind {
+    String,
+    /// A string in RFC 3339 format, which Elasticsearch detects as a date.
+    Date,
+    Long,
+    Double,
+    Boolean,
+    Object,
+}
+
+impl FieldKind {
+    /// Whether a field mapped as `mapped` in Elasticsearch takes values of
+    /// this kind without them being rejected or losing precision.
+    pub fn fits(self, mapped: &str) -> bool {
+        match mapped {
+            "text" | "keyword" | "wildcard" | "constant_keyword" => self != FieldKind::Object,
+            "long" | "integer" | "short" | "byte" | "unsigned_long" => self == FieldKind::Long,
+            "double" | "float" | "half_float" | "scaled_float" => {
+                self == FieldKind::Long || self == FieldKind::Double
+            }
+            "date" | "date_nanos" => self == FieldKind::Date || self == FieldKind::Long,
+            "boolean" => self == FieldKind::Boolean,
+            "object" | "nested" => self == FieldKind::Object,
+            // e.g. ip or geo_point, which codealong does not produce
+            _ => true,
+        }
+    }
+
+    /// The type Elasticsearch would map the kind as.
+    fn mapped_type(self) -> String {
+        self.mapping()
+            .and_then(|mapping| mapping["type"].as_str().map(str::to_owned))
+            .unwrap_or_else(|| "object".to_owned())
+    }
+
+    /// The mapping Elasticsearch would infer for the kind, if it is a leaf.
+    fn mapping(self) -> Option<Value> {
+        match self {
+            FieldKind::String => Some(json!({
+                "type": "text",
+                "fields": { "keyword": { "type": "keyword", "ignore_above": 256 } }
+            })),
+            FieldKind::Date => Some(json!({ "type": "date" })),
+            FieldKind::Long => Some(json!({ "type": "long" })),
+            FieldKind::Double => Some(json!({ "type": "float" })),
+            FieldKind::Boolean => Some(json!({ "type": "boolean" })),
+            FieldKind::Object => None,
+        }
+    }
+}
+
+impl fmt::Display for FieldKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            FieldKind::String => "string",
+            FieldKind::Date => "date",
+            FieldKind::Long => "integer",
+            FieldKind::Double => "floating point number",
+            FieldKind::Boolean => "boolean",
+            FieldKind::Object => "object",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A field of an event which its mapping would reject or truncate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldConflict {
+    /// Dotted path of the field, e.g. `diff.stats.weighted_churn`.
+    pub field: String,
+    pub mapped: String,
+    pub found: FieldKind,
+    pub event_type: String,
+    pub id: String,
+}
+
+impl fmt::Display for FieldConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: mapped as {}, got {} in {} {}",
+            self.field, self.mapped, self.found, self.event_type, self.id
+        )
+    }
+}
+
+/// The fields of a document by dotted path, as Elasticsearch maps them:
+/// the elements of arrays are fields of the array, and nulls are not
+/// mapped.
+#[derive(Debug, Default)]
+struct DocumentFields {
+    kinds: BTreeMap<String, FieldKind>,
+    /// Fields holding objects as well as other values, e.g. in the same
+    /// array, which no mapping takes, with the kind of the values which do
+    /// not fit the first.
+    mixed: BTreeMap<String, FieldKind>,
+}
+
+fn document_fields(document: &Value) -> DocumentFields {
+    let mut fields = DocumentFields::default();
+    if let Value::Object(ref map) = *document {
+        add_fields("", map, &mut fields);
+    }
+    fields
+}
+
+fn add_fields(prefix: &str, map: &Map<String, Value>, fields: &mut DocumentFields) {
+    for (key, value) in map {
+        add_field(&format!("{}{}", prefix, key), value, fields);
+    }
+}
+
+fn add_field(path: &str, value: &Value, fields: &mut DocumentFields) {
+    let kind = match *value {
+        Value::Null => return,
+        Value::Array(ref values) => {
+            for value in values {
+                add_field(path, value, fields);
+            }
+            return;
+        }
+        Value::Object(ref map) => {
+            add_fields(&format!("{}.", path), map, fields);
+            FieldKind::Object
+        }
+        Value::Bool(_) => FieldKind::Boolean,
+        Value::Number(ref number) if number.is_f64() => FieldKind::Double,
+        Value::Number(_) => FieldKind::Long,
+        Value::String(ref s) if DateTime::parse_from_rfc3339(s).is_ok() => FieldKind::Date,
+        Value::String(_) => FieldKind::String,
+    };
+    // a field which is sometimes an integer and sometimes not is mapped by
+    // its widest kind
+    let entry = fields.kinds.entry(path.to_owned()).or_insert(kind);
+    if *entry == FieldKind::Long && kind == FieldKind::Double {
+        *entry = kind;
+    } else if (*entry == FieldKind::Object) != (kind == FieldKind::Object) {
+        fields.mixed.entry(path.to_owned()).or_insert(kind);
+    }
+}
+
+/// The types of the fields of the responses of `GET <indices>/_mapping` by
+/// dotted path, merged across the indices. Fields mapped differently by
+/// different indices keep the type of the first.
+fn mapped_fields(response: &Value) -> BTreeMap<String, String> {
+    let mut fields = BTreeMap::new();
+    if let Value::Object(ref indices) = *response {
+        for index in indices.values() {
+            let mappings = &index["mappings"];
+            // mappings are keyed by type before Elasticsearch 7
+            let properties = match mappings.get("properties") {
+                Some(properties) => Some(properties),
+                None => mappings
+                    .as_object()
+                    .and_then(|types| types.values().next())
+                    .and_then(|mapping| mapping.get("properties")),
+            };
+            if let Some(Value::Object(properties)) = properties {
+                add_mapped_fields("", properties, &mut fields);
+            }
+        }
+    }
+    fields
+}
+
+fn add_mapped_fields(
+    prefix: &str,
+    properties: &Map<String, Value>,
+    fields: &mut BTreeMap<String, String>,
+) {
+    for (key, mapping) in properties {
+        let path = format!("{}{}", prefix, key);
+        let mapped = match mapping.get("type").and_then(|t| t.as_str()) {
+            Some(mapped) => mapped,
+            None if mapping.get("properties").is_some() => "object",
+            None => continue,
+        };
+        fields
+            .entry(path.clone())
+            .or_insert_with(|| mapped.to_owned());
+        if let Some(Value::Object(properties)) = mapping.get("properties") {
+            add_mapped_fields(&format!("{}.", path), properties, fields);
+        }
+    }
+}
+
+/// The body of a `PUT _mapping` request adding `fields`.
+pub(crate) fn mapping_for(fields: &BTreeMap<String, FieldKind>) -> Value {
+    let mut properties = Map::new();
+    'fields: for (path, kind) in fields {
+        let mapping = match kind.mapping() {
+            Some(mapping) => mapping,
+            None => continue,
+        };
+        let mut parts: Vec<&str> = path.split('.').collect();
+        let leaf = parts.pop().unwrap();
+        let mut parent = &mut properties;
+        for part in parts {
+            let object = parent
+                .entry(part.to_owned())
+                .or_insert_with(|| json!({ "properties": {} }));
+            parent = match object.get_mut("properties").and_then(Value::as_object_mut) {
+                Some(properties) => properties,
+                // a value as well as an object, which `SchemaState::check`
+                // reports as a conflict
+                None => continue 'fields,
+            };
+        }
+        parent.insert(leaf.to_owned(), mapping);
+    }
+    json!({ "properties": properties })
+}
+
+/// The mapping of the indices of a client, and the fields of the events
+/// checked against it so far, shared by the client and its clones.
+#[derive(Debug, Default)]
+pub(crate) struct SchemaCheck {
+    pub state: Mutex<Option<SchemaState>>,
+}
+
+#[derive(Debug)]
+pub(crate) struct SchemaState {
+    mapped: BTreeMap<String, String>,
+    /// Whether any of the indices exists to add the mappings of new fields
+    /// to. Indices created later map them on their own.
+    pub has_indices: bool,
+    checked: BTreeMap<String, FieldKind>,
+}
+
+impl SchemaState {
+    pub fn new(mapping: &Value) -> SchemaState {
+        SchemaState {
+            mapped: mapped_fields(mapping),
+            has_indices: mapping
+                .as_object()
+                .is_some_and(|indices| !indices.is_empty()),
+            checked: BTreeMap::new(),
+        }
+    }
+
+    /// The fields of `document` not checked yet which are not mapped yet,
+    /// or the conflicts of its fields with the mapping.
+    pub fn check(
+        &mut self,
+        event_type: &str,
+        id: &str,
+        document: &Value,
+    ) -> ::std::result::Result<BTreeMap<String, FieldKind>, Vec<FieldConflict>> {
+        let fields = document_fields(document);
+        let mut conflicts: Vec<FieldConflict> = fields
+            .mixed
+            .iter()
+            .map(|(path, &found)| FieldConflict {
+                field: path.clone(),
+                mapped: fields.kinds[path].mapped_type(),
+                found,
+                event_type: event_type.to_owned(),
+                id: id.to_owned(),
+            })
+            .collect();
+        let mut new_fields = BTreeMap::new();
+        for (path, kind) in fields.kinds {
+            if self.checked.get(&path) == Some(&kind) {
+                continue;
+            }
+            match self.mapped.get(&path) {
+                Some(mapped) if !kind.fits(mapped) => conflicts.push(FieldConflict {
+                    field: path,
+                    mapped: mapped.clone(),
+                    found: kind,
+                    event_type: event_type.to_owned(),
+                    id: id.to_owned(),
+                }),
+                Some(_) => {
+                    self.checked.insert(path, kind);
+                }
+                None => {
+                    new_fields.insert(path, kind);
+                }
+            }
+        }
+        if conflicts.is_empty() {
+            Ok(new_fields)
+        } else {
+            Err(conflicts)
+        }
+    }
+
+    /// Records that `fields` were mapped as their kinds.
+    pub fn add(&mut self, fields: BTreeMap<String, FieldKind>) {
+        for (path, kind) in fields {
+            self.mapped.insert(path.clone(), kind.mapped_type());
+            self.checked.insert(path, kind);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let mapping = json!({
+            "codealong-2019.01": {
+                "mappings": {
+                    "properties": {
+                        "id": { "type": "keyword" },
+                        "stats": { "properties": { "impact": { "type": "long" } } }
+                    }
+                }
+            }
+        });
+        let mut state = SchemaState::new(&mapping);
+        let new_fields = state
+            .check(
+                "commit",
+                "a",
+                &json!({ "id": "a", "stats": { "impact": 1, "churn": 2 } }),
+            )
+            .unwrap();
+        assert_eq!(
+            new_fields.into_iter().collect::<Vec<_>>(),
+            vec![("stats.churn".to_owned(), FieldKind::Long)]
+        );
+        let conflicts = state
+            .check("commit", "b", &json!({ "stats": { "impact": "high" } }))
+            .unwrap_err();
+        assert_eq!(
+            conflicts[0].to_string(),
+            "stats.impact: mapped as long, got string in commit b"
+        );
+    }
+
+    #[test]
+    fn test_check_mixed_array() {
+        let mut state = SchemaState::new(&json!({}));
+        let conflicts = state
+            .check("commit", "a", &json!({ "tags": [1, { "name": "x" }] }))
+            .unwrap_err();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(
+            (&*conflicts[0].field, &*conflicts[0].mapped),
+            ("tags", "long")
+        );
+        assert_eq!(conflicts[0].found, FieldKind::Object);
+        let conflicts = state
+            .check("commit", "b", &json!({ "tags": [{ "name": "x" }, "y"] }))
+            .unwrap_err();
+        assert_eq!(
+            (&*conflicts[0].mapped, conflicts[0].found),
+            ("object", FieldKind::String)
+        );
+    }
+
+    #[test]
+    fn test_mapping_for() {
+        let mut fields = BTreeMap::new();
+        fields.insert("stats.churn".to_owned(), FieldKind::Long);
+        fields.insert("tags".to_owned(), FieldKind::String);
+        // a value as well as an object is left out rather than panicking
+        fields.insert("tags.name".to_owned(), FieldKind::String);
+        let mapping = mapping_for(&fields);
+        assert_eq!(
+            mapping["properties"]["stats"]["properties"]["churn"]["type"],
+            "long"
+        );
+        assert_eq!(mapping["properties"]["tags"]["type"], "text");
+    }
+}
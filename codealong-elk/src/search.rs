@@ -0,0 +1,115 @@
+use chrono::prelude::*;
+use chrono::DateTime;
+use serde_json::Value;
+
+/// Full text search over the messages of analyzed commits and the titles and
+/// bodies of analyzed pull requests.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SearchQuery {
+    pub text: String,
+    /// Matches the normalized id, name, email or GitHub login of the author.
+    pub author: Option<String>,
+    pub repo: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub limit: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    pub event_type: String,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub id: String,
+    pub title: String,
+    pub repo: Option<String>,
+    pub author: Option<String>,
+    pub score: f64,
+}
+
+impl SearchQuery {
+    pub fn new(text: &str) -> SearchQuery {
+        SearchQuery {
+            text: text.to_owned(),
+            limit: 20,
+            ..SearchQuery::default()
+        }
+    }
+
+    pub(crate) fn to_query(&self) -> Value {
+        let mut filter = vec![json!({ "terms": { "type.keyword": ["commit", "pull_request"] } })];
+        if let Some(ref author) = self.author {
+            filter.push(json!({
+                "multi_match": {
+                    "query": author,
+                    "type": "phrase",
+                    "fields": [
+                        "normalized_author.id",
+                        "normalized_author.name",
+                        "normalized_author.email",
+                        "normalized_author.github_login"
+                    ]
+                }
+            }));
+        }
+        if let Some(ref repo) = self.repo {
+            // commits carry the repo name, pull requests their base repo
+            filter.push(json!({
+                "bool": {
+                    "should": [
+                        { "term": { "repo.name.keyword": repo } },
+                        { "term": { "base.repo.full_name.keyword": repo } }
+                    ],
+                    "minimum_should_match": 1
+                }
+            }));
+        }
+        if self.since.is_some() || self.until.is_some() {
+            filter.push(json!({
+                "range": {
+                    "@timestamp": {
+                        "gte": self.since.map(|since| since.to_rfc3339()),
+                        "lt": self.until.map(|until| until.to_rfc3339())
+                    }
+                }
+            }));
+        }
+        json!({
+            "size": self.limit,
+            "query": {
+                "bool": {
+                    "must": {
+                        "multi_match": {
+                            "query": self.text,
+                            "fields": ["summary", "title^2", "body"]
+                        }
+                    },
+                    "filter": filter
+                }
+            }
+        })
+    }
+}
+
+impl SearchHit {
+    pub(crate) fn from_hit(hit: &Value) -> SearchHit {
+        let source = &hit["_source"];
+        let text = |value: &Value| value.as_str().map(|s| s.to_owned());
+        SearchHit {
+            event_type: text(&source["type"]).unwrap_or_default(),
+            timestamp: source["@timestamp"]
+                .as_str()
+                .and_then(|timestamp| timestamp.parse().ok()),
+            id: text(&source["id"])
+                .or_else(|| source["number"].as_u64().map(|n| format!("#{}", n)))
+                .unwrap_or_default(),
+            title: text(&source["summary"])
+                .or_else(|| text(&source["title"]))
+                .unwrap_or_default(),
+            repo: text(&source["repo"]["name"])
+                .or_else(|| text(&source["base"]["repo"]["full_name"])),
+            author: text(&source["normalized_author"]["name"])
+                .or_else(|| text(&source["normalized_author"]["id"])),
+            score: hit["_score"].as_f64().unwrap_or(0.0),
+        }
+    }
+}
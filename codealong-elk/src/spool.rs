@@ -0,0 +1,75 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde_json::Value;
+
+use crate::error::Result;
+
+/// Distinguishes events spooled by one process within the same instant.
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// An event which could not be indexed, along with where it belongs.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpooledEvent {
+    pub index: String,
+    pub id: String,
+    pub document: Value,
+}
+
+/// A directory holding the events which could not be indexed while
+/// Elasticsearch was unreachable, one file per event, named so that they
+/// sort in the order they were spooled.
+#[derive(Debug, Clone)]
+pub struct Spool {
+    dir: PathBuf,
+}
+
+impl Spool {
+    pub fn new(dir: &Path) -> Spool {
+        Spool {
+            dir: dir.to_owned(),
+        }
+    }
+
+    pub fn push(&self, event: &SpooledEvent) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let name = format!(
+            "{:020}-{:010}-{:010}.json",
+            chrono::Utc::now().timestamp_nanos(),
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst)
+        );
+        // written aside and renamed so that a replay never sees a partial
+        // event
+        let tmp_path = self.dir.join(format!("{}.tmp", name));
+        fs::write(&tmp_path, serde_json::to_vec(event)?)?;
+        fs::rename(&tmp_path, self.dir.join(name))?;
+        Ok(())
+    }
+
+    /// Paths of the spooled events, oldest first.
+    pub fn paths(&self) -> Result<Vec<PathBuf>> {
+        if !self.dir.exists() {
+            return Ok(vec![]);
+        }
+        let mut paths = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().map_or(false, |ext| ext == "json") {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        Ok(paths)
+    }
+
+    pub fn read(&self, path: &Path) -> Result<SpooledEvent> {
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    /// Removes a spooled event once it has been indexed.
+    pub fn remove(&self, path: &Path) -> Result<()> {
+        Ok(fs::remove_file(path)?)
+    }
+}
@@ -0,0 +1,120 @@
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::error::Result;
+
+/// An event delivered to a `Subscriber` as it was written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub event_type: String,
+    pub id: String,
+    /// The event as written to the sink, stamped and with its designated
+    /// fields encrypted.
+    pub document: Value,
+}
+
+impl Notification {
+    /// Deserializes the document, e.g. into a `codealong::AnalyzedCommit`.
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T> {
+        Ok(serde_json::from_value(self.document.clone())?)
+    }
+}
+
+/// Receives the events indexed by a `Client`, and its clones, in process as
+/// they are indexed, so that embedders can react to fresh events without
+/// polling the sink. Events are queued until received, and the subscription
+/// ends when the subscriber is dropped.
+pub struct Subscriber {
+    receiver: Receiver<Notification>,
+}
+
+impl Subscriber {
+    /// Waits for the next event, or `None` once the client and all of its
+    /// clones are dropped.
+    pub fn recv(&self) -> Option<Notification> {
+        self.receiver.recv().ok()
+    }
+
+    /// The next event if one is queued.
+    pub fn try_recv(&self) -> Option<Notification> {
+        match self.receiver.try_recv() {
+            Ok(notification) => Some(notification),
+            Err(TryRecvError::Empty) | Err(TryRecvError::Disconnected) => None,
+        }
+    }
+
+    /// Waits for the next event for at most `timeout`.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<Notification> {
+        match self.receiver.recv_timeout(timeout) {
+            Ok(notification) => Some(notification),
+            Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => None,
+        }
+    }
+}
+
+impl Iterator for Subscriber {
+    type Item = Notification;
+
+    fn next(&mut self) -> Option<Notification> {
+        self.recv()
+    }
+}
+
+struct Subscription {
+    /// Only events of these types are delivered, or all if empty.
+    event_types: Vec<String>,
+    sender: Sender<Notification>,
+}
+
+/// The subscriptions shared by a client and its clones.
+#[derive(Clone, Default)]
+pub(crate) struct Subscriptions {
+    subscriptions: Arc<Mutex<Vec<Subscription>>>,
+}
+
+impl std::fmt::Debug for Subscriptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Subscriptions")
+            .field("len", &self.subscriptions.lock().unwrap().len())
+            .finish()
+    }
+}
+
+impl Subscriptions {
+    pub fn subscribe(&self, event_types: &[&str]) -> Subscriber {
+        let (sender, receiver) = channel();
+        self.subscriptions.lock().unwrap().push(Subscription {
+            event_types: event_types.iter().map(|t| (*t).to_owned()).collect(),
+            sender,
+        });
+        Subscriber { receiver }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.subscriptions.lock().unwrap().is_empty()
+    }
+
+    /// Delivers the event to the interested subscribers, dropping the
+    /// subscriptions of those which were dropped.
+    pub fn notify(&self, event_type: &str, id: &str, document: &Value) {
+        self.subscriptions.lock().unwrap().retain(|subscription| {
+            if !subscription.event_types.is_empty()
+                && !subscription.event_types.iter().any(|t| t == event_type)
+            {
+                return true;
+            }
+            subscription
+                .sender
+                .send(Notification {
+                    event_type: event_type.to_owned(),
+                    id: id.to_owned(),
+                    document: document.clone(),
+                })
+                .is_ok()
+        });
+    }
+}
@@ -0,0 +1,52 @@
+/*
+ * C bindings of the codealong git analysis library.
+ *
+ * Strings returned by these functions are owned by the caller and freed with
+ * codealong_string_free. Functions which fail return NULL, and
+ * codealong_last_error then returns why.
+ */
+
+#ifndef CODEALONG_H
+#define CODEALONG_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The commits of a repository being analyzed. */
+typedef struct CodealongAnalyzer CodealongAnalyzer;
+
+/*
+ * Opens the repository containing path and starts analyzing its commits with
+ * options, a JSON object which may be NULL, e.g.
+ * {"since": "2019-01-01T00:00:00Z", "branch": "main", "first_parent": true,
+ *  "default_branch_only": false, "config": "codealong.yml"}.
+ * Returns NULL on failure.
+ */
+CodealongAnalyzer *codealong_analyzer_open(const char *path, const char *options);
+
+/*
+ * The next analyzed commit as a JSON document owned by the caller. Returns
+ * NULL once every commit was analyzed, or if analyzing the next commit
+ * failed, in which case codealong_last_error returns why rather than NULL
+ * and the following commits can still be asked for.
+ */
+char *codealong_analyzer_next(CodealongAnalyzer *analyzer);
+
+/* Stops the analysis and frees analyzer. */
+void codealong_analyzer_free(CodealongAnalyzer *analyzer);
+
+/*
+ * Why the last call into the library on this thread failed, or NULL if it
+ * succeeded. The message is a copy owned by the caller.
+ */
+char *codealong_last_error(void);
+
+/* Frees a string returned by the library. */
+void codealong_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
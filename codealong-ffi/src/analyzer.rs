@@ -0,0 +1,207 @@
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::ptr;
+
+use chrono::prelude::*;
+use chrono::DateTime;
+use futures::stream::Wait;
+use futures::Stream;
+use slog::{Discard, Logger};
+
+use codealong::{
+    discover, AnalysisStream, AnalyzeOpts, AnalyzedCommit, Config, Error, RepoAnalyzer, RepoConfig,
+};
+
+use crate::last_error::{clear_last_error, into_c_string, set_last_error};
+
+/// Number of commits analyzed ahead of the caller.
+const BUFFER: usize = 16;
+
+/// The commits of a repository being analyzed on a thread of their own.
+pub struct CodealongAnalyzer {
+    commits: Wait<AnalysisStream<AnalyzedCommit, Error>>,
+}
+
+/// Options of `codealong_analyzer_open`, given as a JSON object of which
+/// every field is optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Options {
+    /// Only analyze commits authored after this time.
+    since: Option<DateTime<Utc>>,
+    /// Only analyze commits reachable from this branch.
+    branch: Option<String>,
+    default_branch_only: bool,
+    first_parent: bool,
+    /// Path to a codealong config file merged over that of the repository.
+    config: Option<String>,
+}
+
+/// Opens the repository containing `path` and starts analyzing its commits
+/// with `options`, a JSON object which may be null, e.g.
+/// `{"since": "2019-01-01T00:00:00Z", "first_parent": true}`. Returns null on
+/// failure.
+///
+/// # Safety
+///
+/// `path` must be a nul-terminated string and `options` null or one.
+#[no_mangle]
+pub unsafe extern "C" fn codealong_analyzer_open(
+    path: *const c_char,
+    options: *const c_char,
+) -> *mut CodealongAnalyzer {
+    clear_last_error();
+    if path.is_null() {
+        set_last_error("path is null");
+        return ptr::null_mut();
+    }
+    let path = CStr::from_ptr(path).to_string_lossy().into_owned();
+    let options = if options.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(options).to_string_lossy().into_owned())
+    };
+    let res = panic::catch_unwind(|| open(&path, options.as_ref().map(|o| &**o)));
+    match res {
+        Ok(Ok(analyzer)) => Box::into_raw(Box::new(analyzer)),
+        Ok(Err(message)) => {
+            set_last_error(&message);
+            ptr::null_mut()
+        }
+        Err(_) => {
+            set_last_error("panicked while opening the repository");
+            ptr::null_mut()
+        }
+    }
+}
+
+fn open(path: &str, options: Option<&str>) -> Result<CodealongAnalyzer, String> {
+    let options: Options = match options {
+        Some(options) => serde_json::from_str(options).map_err(|e| e.to_string())?,
+        None => Options::default(),
+    };
+    let repo = discover(Path::new(path)).map_err(|e| e.to_string())?;
+    let mut config = RepoConfig::from_repository(&repo)
+        .and_then(|config| config.with_mailmap(&repo))
+        .map_err(|e| e.to_string())?;
+    if let Some(ref path) = options.config {
+        config
+            .config
+            .merge(Config::from_path(Path::new(path)).map_err(|e| e.to_string())?);
+    }
+    let opts = AnalyzeOpts {
+        since: options.since,
+        branch: options.branch,
+        default_branch_only: options.default_branch_only,
+        first_parent: options.first_parent,
+        ..AnalyzeOpts::default()
+    };
+    let logger = Logger::root(Discard, o!());
+    let analyzer = RepoAnalyzer::new(repo, config, &logger);
+    Ok(CodealongAnalyzer {
+        commits: analyzer.stream(opts, BUFFER).wait(),
+    })
+}
+
+/// The next analyzed commit as a JSON document owned by the caller. Returns
+/// null once every commit was analyzed, or if analyzing the next commit
+/// failed, in which case `codealong_last_error` returns why rather than null
+/// and the following commits can still be asked for.
+///
+/// # Safety
+///
+/// `analyzer` must have been returned by `codealong_analyzer_open` and not
+/// freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn codealong_analyzer_next(analyzer: *mut CodealongAnalyzer) -> *mut c_char {
+    clear_last_error();
+    if analyzer.is_null() {
+        set_last_error("analyzer is null");
+        return ptr::null_mut();
+    }
+    let analyzer = &mut *analyzer;
+    let res = panic::catch_unwind(AssertUnwindSafe(|| match analyzer.commits.next() {
+        Some(Ok(commit)) => serde_json::to_string(&commit)
+            .map(Some)
+            .map_err(|e| e.to_string()),
+        Some(Err(e)) => Err(e.to_string()),
+        None => Ok(None),
+    }));
+    match res {
+        Ok(Ok(Some(json))) => into_c_string(json),
+        Ok(Ok(None)) => ptr::null_mut(),
+        Ok(Err(message)) => {
+            set_last_error(&message);
+            ptr::null_mut()
+        }
+        Err(_) => {
+            set_last_error("panicked while analyzing a commit");
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Stops the analysis and frees `analyzer`.
+///
+/// # Safety
+///
+/// `analyzer` must be null or have been returned by `codealong_analyzer_open`
+/// and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn codealong_analyzer_free(analyzer: *mut CodealongAnalyzer) {
+    if !analyzer.is_null() {
+        drop(Box::from_raw(analyzer));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::last_error::{codealong_last_error, codealong_string_free};
+    use std::ffi::CString;
+
+    #[test]
+    fn test_analyzer() {
+        let path = CString::new("../codealong/fixtures/repos/simple").unwrap();
+        let options = CString::new(r#"{"first_parent": true}"#).unwrap();
+        unsafe {
+            let analyzer = codealong_analyzer_open(path.as_ptr(), options.as_ptr());
+            assert!(!analyzer.is_null());
+            let mut ids = vec![];
+            loop {
+                let json = codealong_analyzer_next(analyzer);
+                if json.is_null() {
+                    assert!(codealong_last_error().is_null());
+                    break;
+                }
+                let commit: serde_json::Value =
+                    serde_json::from_str(CStr::from_ptr(json).to_str().unwrap()).unwrap();
+                ids.push(commit["id"].as_str().unwrap().to_owned());
+                codealong_string_free(json);
+            }
+            codealong_analyzer_free(analyzer);
+            assert!(!ids.is_empty());
+            assert!(ids.contains(&"86d242301830075e93ff039a4d1e88673a4a3020".to_owned()));
+        }
+    }
+
+    #[test]
+    fn test_open_error() {
+        let path = CString::new("../codealong/fixtures/repos/simple").unwrap();
+        let options = CString::new("{\"since\": 1}").unwrap();
+        unsafe {
+            assert!(codealong_analyzer_open(path.as_ptr(), options.as_ptr()).is_null());
+            assert!(!codealong_last_error().is_null());
+            assert!(codealong_analyzer_open(ptr::null(), ptr::null()).is_null());
+            let error = codealong_last_error();
+            assert_eq!(CStr::from_ptr(error).to_str().unwrap(), "path is null");
+            codealong_string_free(error);
+            // each call returns a copy of the message, which outlives the one freed
+            let error = codealong_last_error();
+            assert_eq!(CStr::from_ptr(error).to_str().unwrap(), "path is null");
+            codealong_string_free(error);
+        }
+    }
+}
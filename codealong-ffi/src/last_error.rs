@@ -0,0 +1,55 @@
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::ptr;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = RefCell::new(None);
+}
+
+/// Records `message` as the last error of this thread.
+pub fn set_last_error(message: &str) {
+    let message = CString::new(message.replace('\0', "")).unwrap();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
+
+pub fn clear_last_error() {
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = None);
+}
+
+/// Converts `s` into a string owned by the caller.
+pub fn into_c_string(s: String) -> *mut c_char {
+    match CString::new(s) {
+        Ok(s) => s.into_raw(),
+        Err(_) => {
+            set_last_error("string contains a nul byte");
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Why the last call into the library on this thread failed, or null if it
+/// succeeded. The message is a copy owned by the caller, like every string
+/// returned by the library.
+#[no_mangle]
+pub extern "C" fn codealong_last_error() -> *mut c_char {
+    LAST_ERROR.with(|last_error| {
+        last_error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null_mut(), |message| message.clone().into_raw())
+    })
+}
+
+/// Frees a string returned by the library.
+///
+/// # Safety
+///
+/// `s` must be null or a string returned by the library which has not been
+/// freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn codealong_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
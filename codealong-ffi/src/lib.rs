@@ -0,0 +1,49 @@
+//! A C ABI for the git analysis of codealong, so that it can be embedded
+//! from languages such as Python or Go without shelling out to the CLI.
+//! Analyzed commits are handed out as JSON documents, the same as the events
+//! which are indexed. `include/codealong.h` declares the functions below.
+//!
+//! Strings returned by these functions are owned by the caller and freed
+//! with `codealong_string_free`. Functions which fail return null, and
+//! `codealong_last_error` then returns why.
+
+extern crate chrono;
+extern crate codealong;
+extern crate futures;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_json;
+#[macro_use]
+extern crate slog;
+
+mod analyzer;
+mod last_error;
+
+pub use crate::analyzer::{
+    codealong_analyzer_free, codealong_analyzer_next, codealong_analyzer_open, CodealongAnalyzer,
+};
+pub use crate::last_error::{codealong_last_error, codealong_string_free};
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_header() {
+        let header = include_str!("../include/codealong.h");
+        let sources = [include_str!("analyzer.rs"), include_str!("last_error.rs")];
+        for source in sources.iter() {
+            for line in source
+                .lines()
+                .filter(|line| line.contains("extern \"C\" fn "))
+            {
+                let name = line.split("fn ").nth(1).unwrap().split('(').next().unwrap();
+                assert!(
+                    header.contains(&format!(" *{}(", name))
+                        || header.contains(&format!(" {}(", name)),
+                    "{} is not declared in codealong.h",
+                    name
+                );
+            }
+        }
+    }
+}
@@ -4,8 +4,12 @@ use std::borrow::Cow;
 use std::collections::HashSet;
 use std::iter::FromIterator;
 
-use codealong::{AnalyzedDiff, Event, Person};
+use codealong::{
+    metric_versions, AnalyzedDiff, Calendar, Config, Event, Hotspots, MetricVersions, Person,
+    PersonSnapshots, ReviewStats, Risk, SloSubject,
+};
 
+use crate::error::Result;
 use crate::pull_request::PullRequest;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -14,6 +18,9 @@ pub struct AnalyzedPullRequest {
 
     normalized_author: Person,
 
+    /// Id of the normalized author, shared with their commits.
+    author_id: String,
+
     #[serde(flatten)]
     pr: PullRequest,
 
@@ -21,6 +28,27 @@ pub struct AnalyzedPullRequest {
     pub diff: Option<AnalyzedDiff>,
 
     pub time_to_resolve: Option<i64>,
+
+    /// Working seconds between creation and merge according to the
+    /// configured business calendar.
+    pub business_time_to_resolve: Option<i64>,
+
+    pub business_days_to_resolve: Option<f64>,
+
+    #[serde(flatten, default)]
+    pub review_stats: ReviewStats,
+
+    /// Risk of the changes when assessed against the hotspots of the repo.
+    #[serde(default)]
+    pub risk: Option<Risk>,
+
+    /// References to tickets and issues in the title and body, e.g.
+    /// `ABC-123`.
+    #[serde(default)]
+    pub issues: Vec<String>,
+
+    #[serde(default)]
+    pub metric_versions: MetricVersions,
 }
 
 impl AnalyzedPullRequest {
@@ -28,18 +56,136 @@ impl AnalyzedPullRequest {
         pr: PullRequest,
         diff: Option<AnalyzedDiff>,
         normalized_author: Person,
+        calendar: &Calendar,
     ) -> AnalyzedPullRequest {
         AnalyzedPullRequest {
             timestamp: pr.merged_at.unwrap_or(pr.updated_at),
+            author_id: normalized_author.id.clone(),
             normalized_author,
             diff,
             time_to_resolve: pr
                 .merged_at
                 .as_ref()
                 .map(|ma| (ma.clone() - pr.created_at.clone()).num_seconds()),
+            business_time_to_resolve: pr
+                .merged_at
+                .as_ref()
+                .map(|ma| calendar.business_seconds(&pr.created_at, ma)),
+            business_days_to_resolve: pr
+                .merged_at
+                .as_ref()
+                .map(|ma| calendar.business_days(&pr.created_at, ma)),
+            review_stats: ReviewStats::default(),
+            risk: None,
+            issues: vec![],
+            metric_versions: metric_versions(&[
+                codealong::LINES_CHANGED,
+                codealong::TIME_TO_RESOLVE,
+                codealong::BUSINESS_TIME_TO_RESOLVE,
+                codealong::BUSINESS_DAYS_TO_RESOLVE,
+            ]),
             pr,
         }
     }
+
+    /// Assesses the risk of the changes of this pull request, if they were
+    /// analyzed.
+    pub fn assess_risk(&mut self, hotspots: &Hotspots) {
+        self.risk = self.diff.as_ref().map(|diff| Risk::assess(diff, hotspots));
+    }
+
+    /// Attaches how this pull request was reviewed.
+    pub fn set_review_stats(&mut self, review_stats: ReviewStats) {
+        self.review_stats = review_stats;
+        self.metric_versions.extend(metric_versions(&[
+            codealong::TIME_TO_FIRST_REVIEW,
+            codealong::BUSINESS_TIME_TO_FIRST_REVIEW,
+        ]));
+    }
+
+    pub fn updated_at(&self) -> &DateTime<Utc> {
+        &self.pr.updated_at
+    }
+
+    /// Names the repos of the base and head after following the
+    /// `repo_aliases` of `config`, so that the pull requests of a renamed repo are
+    /// not split across its names.
+    pub fn alias_repos(&mut self, config: &Config) {
+        let repos = self
+            .pr
+            .base
+            .repo
+            .iter_mut()
+            .chain(self.pr.head.repo.iter_mut());
+        for repo in repos {
+            repo.full_name = config.canonical_repo_name(&repo.full_name).to_owned();
+        }
+    }
+
+    /// Extracts the references to tickets and issues of the title and body,
+    /// before they are stored.
+    pub fn extract_issues(&mut self, config: &Config) -> Result<()> {
+        let text: Vec<&str> = self
+            .pr
+            .title
+            .iter()
+            .chain(self.pr.body.iter())
+            .map(|t| &**t)
+            .collect();
+        self.issues = config.issue_references.extract(&text.join("\n\n"))?;
+        Ok(())
+    }
+
+    /// Truncates, hashes or drops the title and body according to the
+    /// `summary_max_length` and `message_storage` of `config`.
+    pub fn store_messages(&mut self, config: &Config) {
+        self.pr.title = self.pr.title.take().and_then(|t| config.stored_summary(&t));
+        self.pr.body = self.pr.body.take().and_then(|b| config.stored_message(&b));
+    }
+
+    /// Records the author's work on this pull request in `snapshots`.
+    pub fn add_to_snapshots(&self, snapshots: &mut PersonSnapshots) {
+        if let Some(ref repo) = self.pr.base.repo {
+            snapshots.add_pull_request(
+                &self.normalized_author,
+                &self.timestamp,
+                &repo.full_name,
+                &self.pr.head.reference,
+                self.pr.number,
+                self.time_to_resolve,
+            );
+        }
+    }
+}
+
+impl SloSubject for AnalyzedPullRequest {
+    fn diff(&self) -> Option<&AnalyzedDiff> {
+        self.diff.as_ref()
+    }
+
+    fn time_to_resolve(&self) -> Option<i64> {
+        self.time_to_resolve
+    }
+
+    fn business_time_to_resolve(&self) -> Option<i64> {
+        self.business_time_to_resolve
+    }
+
+    fn review_stats(&self) -> &ReviewStats {
+        &self.review_stats
+    }
+
+    fn owner(&self) -> &Person {
+        &self.normalized_author
+    }
+
+    fn url(&self) -> Option<String> {
+        self.pr.html_url.clone()
+    }
+
+    fn repo(&self) -> Option<String> {
+        self.pr.base.repo.as_ref().map(|r| r.full_name.clone())
+    }
 }
 
 impl Event for AnalyzedPullRequest {
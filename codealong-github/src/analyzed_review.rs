@@ -0,0 +1,283 @@
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashSet};
+
+use chrono::prelude::*;
+use chrono::DateTime;
+
+use codealong::{
+    metric_versions, Config, Event, MetricVersions, Person, PersonSnapshots, ReviewStats,
+};
+
+use crate::pull_request::PullRequest;
+use crate::review::{Review, ReviewComment, ReviewState};
+
+/// The login Github reports for the authors of deleted accounts.
+const GHOST_LOGIN: &str = "ghost";
+
+/// The pull request a review was submitted on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReviewedPullRequest {
+    pub id: u64,
+    pub number: u64,
+    pub repo: Option<String>,
+    pub html_url: Option<String>,
+    /// Id of the normalized author of the pull request.
+    pub author_id: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A review submitted on a pull request by someone other than its author,
+/// attributed to the reviewer to measure review load and latency per person
+/// and team.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnalyzedReview {
+    timestamp: DateTime<Utc>,
+
+    id: u64,
+
+    pub reviewer: Person,
+
+    /// Id of the normalized reviewer.
+    pub reviewer_id: String,
+
+    pub state: ReviewState,
+
+    pub pull_request: ReviewedPullRequest,
+
+    /// Comments on the diff submitted along with the review.
+    pub comments: u64,
+
+    /// Whether this is the first review of the pull request.
+    pub first_review: bool,
+
+    /// Seconds from opening the pull request to submitting the review.
+    pub time_to_review: i64,
+
+    /// Working seconds from opening the pull request to submitting the
+    /// review according to the configured business calendar.
+    pub business_time_to_review: i64,
+
+    #[serde(default)]
+    pub metric_versions: MetricVersions,
+}
+
+impl AnalyzedReview {
+    pub fn add_to_snapshots(&self, snapshots: &mut PersonSnapshots) {
+        snapshots.add_review(
+            &self.reviewer,
+            self.pull_request.repo.as_deref(),
+            &self.timestamp,
+        );
+    }
+}
+
+impl Event for AnalyzedReview {
+    fn timestamp(&self) -> &DateTime<Utc> {
+        &self.timestamp
+    }
+
+    fn id(&self) -> Cow<str> {
+        self.id.to_string().into()
+    }
+
+    fn event_type(&self) -> &str {
+        "pull_request_review"
+    }
+
+    fn tags(&self) -> HashSet<String> {
+        HashSet::new()
+    }
+}
+
+/// Analyzes the reviews and comments of `pr`, authored by `author`, into the
+/// stats of the pull request and an event per submitted review. Pending
+/// reviews, which only their author can see, are left out.
+pub fn analyze_reviews(
+    pr: &PullRequest,
+    author: &Person,
+    reviews: &[Review],
+    comments: &[ReviewComment],
+    config: &Config,
+) -> (ReviewStats, Vec<AnalyzedReview>) {
+    let is_author = |login: &str| login == pr.user.login;
+    let login = |user: &Option<crate::user::User>| {
+        user.as_ref()
+            .map_or(GHOST_LOGIN.to_owned(), |user| user.login.clone())
+    };
+    let mut submitted: Vec<(&Review, String, DateTime<Utc>)> = reviews
+        .iter()
+        .filter(|review| review.state != ReviewState::Pending)
+        .filter_map(|review| {
+            let login = login(&review.user);
+            match review.submitted_at {
+                Some(submitted_at) if !is_author(&login) => Some((review, login, submitted_at)),
+                _ => None,
+            }
+        })
+        .collect();
+    submitted.sort_by_key(|&(review, _, submitted_at)| (submitted_at, review.id));
+
+    let calendar = config.calendar();
+    let mut stats = ReviewStats::default();
+    let mut reviewers = BTreeSet::new();
+    let mut analyzed = Vec::new();
+    for (index, (review, login, submitted_at)) in submitted.into_iter().enumerate() {
+        let reviewer = config.person_for_github_login_at(&login, &submitted_at);
+        stats.reviews += 1;
+        match review.state {
+            ReviewState::Approved => stats.approvals += 1,
+            ReviewState::ChangesRequested => stats.change_requests += 1,
+            _ => {}
+        }
+        reviewers.insert(reviewer.id.clone());
+        let time_to_review = (submitted_at - pr.created_at).num_seconds();
+        let business_time_to_review = calendar.business_seconds(&pr.created_at, &submitted_at);
+        if index == 0 {
+            stats.first_reviewed_at = Some(submitted_at);
+            stats.time_to_first_review = Some(time_to_review);
+            stats.business_time_to_first_review = Some(business_time_to_review);
+        }
+        analyzed.push(AnalyzedReview {
+            timestamp: submitted_at,
+            id: review.id,
+            reviewer_id: reviewer.id.clone(),
+            reviewer,
+            state: review.state,
+            pull_request: ReviewedPullRequest {
+                id: pr.id,
+                number: pr.number,
+                repo: pr
+                    .base
+                    .repo
+                    .as_ref()
+                    .map(|r| config.canonical_repo_name(&r.full_name).to_owned()),
+                html_url: pr.html_url.clone(),
+                author_id: author.id.clone(),
+                created_at: pr.created_at,
+            },
+            comments: comments
+                .iter()
+                .filter(|comment| comment.pull_request_review_id == Some(review.id))
+                .count() as u64,
+            first_review: index == 0,
+            time_to_review,
+            business_time_to_review,
+            metric_versions: metric_versions(&[
+                codealong::TIME_TO_FIRST_REVIEW,
+                codealong::BUSINESS_TIME_TO_FIRST_REVIEW,
+            ]),
+        });
+    }
+    stats.review_comments = comments
+        .iter()
+        .filter(|comment| !is_author(&login(&comment.user)))
+        .count() as u64;
+    stats.reviewers = reviewers.into_iter().collect();
+    (stats, analyzed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pull_request::Ref;
+    use crate::repo::Repo;
+    use crate::user::User;
+    use std::fs;
+
+    fn pull_request(author: &str) -> PullRequest {
+        let reference = Ref {
+            sha: "6dcb09b5b57875f334f61aebed695e2e4193db5e".to_owned(),
+            reference: "master".to_owned(),
+            repo: None,
+        };
+        PullRequest {
+            id: 1,
+            url: None,
+            number: 12,
+            base: reference.clone(),
+            head: reference,
+            html_url: None,
+            state: Some("open".to_owned()),
+            title: None,
+            body: None,
+            user: User {
+                id: 3,
+                login: author.to_owned(),
+                name: None,
+                email: None,
+            },
+            created_at: Utc.ymd(2019, 11, 15).and_hms(17, 43, 43),
+            updated_at: Utc.ymd(2019, 11, 18).and_hms(17, 43, 43),
+            closed_at: None,
+            merged_at: None,
+            merge_commit_sha: None,
+            requested_reviewers: vec![],
+        }
+    }
+
+    #[test]
+    fn test_analyze_reviews() {
+        let reviews: Vec<Review> =
+            serde_json::from_str(&fs::read_to_string("./fixtures/reviews.json").unwrap()).unwrap();
+        let mut comments: Vec<ReviewComment> =
+            serde_json::from_str(&fs::read_to_string("./fixtures/review_comments.json").unwrap())
+                .unwrap();
+        comments[0].pull_request_review_id = Some(81);
+        let config = Config::default();
+        let pr = pull_request("monalisa");
+        let author = config.person_for_github_login_at("monalisa", &pr.created_at);
+
+        let (stats, analyzed) = analyze_reviews(&pr, &author, &reviews, &comments, &config);
+        assert_eq!(stats.reviews, 2);
+        assert_eq!(stats.approvals, 1);
+        assert_eq!(stats.change_requests, 1);
+        assert_eq!(stats.review_comments, 1);
+        assert_eq!(stats.reviewers, vec!["hubot", "octocat"]);
+        assert_eq!(analyzed.len(), 2);
+        let first = analyzed.iter().find(|review| review.first_review).unwrap();
+        assert_eq!(stats.first_reviewed_at, Some(first.timestamp));
+        assert_eq!(stats.time_to_first_review, Some(first.time_to_review));
+        assert_eq!(first.pull_request.author_id, "monalisa");
+        let commented = analyzed.iter().find(|review| review.id == 81).unwrap();
+        assert_eq!(commented.comments, 1);
+
+        // replies of the author to reviews are not reviews
+        let pr = pull_request("octocat");
+        let (stats, analyzed) = analyze_reviews(&pr, &author, &reviews, &comments, &config);
+        assert_eq!(stats.reviews, 1);
+        assert_eq!(stats.reviewers, vec!["hubot"]);
+        assert_eq!(stats.review_comments, 0);
+        assert!(analyzed[0].first_review);
+    }
+
+    #[test]
+    fn test_repo_aliases() {
+        let reviews: Vec<Review> =
+            serde_json::from_str(&fs::read_to_string("./fixtures/reviews.json").unwrap()).unwrap();
+        let mut config = Config::default();
+        config
+            .repo_aliases
+            .insert("acme/old".to_owned(), "acme/new".to_owned());
+        let mut pr = pull_request("monalisa");
+        pr.base.repo = Some(Repo {
+            id: 1296269,
+            name: "old".to_owned(),
+            full_name: "acme/old".to_owned(),
+            html_url: "https://github.com/acme/old".to_owned(),
+            ssh_url: "git@github.com:acme/old.git".to_owned(),
+            clone_url: "https://github.com/acme/old.git".to_owned(),
+            git_url: "git://github.com/acme/old.git".to_owned(),
+            fork: false,
+            private: false,
+            visibility: None,
+            default_branch: None,
+            archived: false,
+            stargazers_count: 0,
+            language: None,
+            topics: vec![],
+        });
+        let author = config.person_for_github_login_at("monalisa", &pr.created_at);
+        let (_, analyzed) = analyze_reviews(&pr, &author, &reviews, &[], &config);
+        assert_eq!(analyzed[0].pull_request.repo, Some("acme/new".to_owned()));
+    }
+}
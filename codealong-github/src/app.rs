@@ -0,0 +1,11 @@
+use crate::user::User;
+
+/// Summary of the Github App behind a check run or deployment.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct App {
+    pub id: u64,
+    pub slug: Option<String>,
+    pub name: String,
+    pub owner: Option<User>,
+    pub html_url: Option<String>,
+}
@@ -0,0 +1,105 @@
+use chrono::prelude::*;
+use chrono::DateTime;
+
+use crate::app::App;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Queued,
+    InProgress,
+    Completed,
+    Waiting,
+    Requested,
+    Pending,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckConclusion {
+    Success,
+    Failure,
+    Neutral,
+    Cancelled,
+    Skipped,
+    TimedOut,
+    ActionRequired,
+    Stale,
+    StartupFailure,
+}
+
+/// A CI check run against a commit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(test, serde(deny_unknown_fields))]
+pub struct CheckRun {
+    pub id: u64,
+    pub node_id: String,
+    pub head_sha: String,
+    pub external_id: Option<String>,
+    pub url: String,
+    pub html_url: Option<String>,
+    pub details_url: Option<String>,
+    pub status: CheckStatus,
+    pub conclusion: Option<CheckConclusion>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub output: CheckRunOutput,
+    pub name: String,
+    pub check_suite: Option<CheckSuiteRef>,
+    pub app: Option<App>,
+    pub pull_requests: Vec<CheckRunPullRequest>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(test, serde(deny_unknown_fields))]
+pub struct CheckRunOutput {
+    pub title: Option<String>,
+    pub summary: Option<String>,
+    pub text: Option<String>,
+    pub annotations_count: u64,
+    pub annotations_url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(test, serde(deny_unknown_fields))]
+pub struct CheckSuiteRef {
+    pub id: u64,
+}
+
+/// The pull requests whose head a check run was run against. The `head` and
+/// `base` refs are left out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CheckRunPullRequest {
+    pub id: u64,
+    pub number: u64,
+    pub url: String,
+}
+
+/// The body of `/commits/{ref}/check-runs`, which wraps the check runs
+/// rather than returning them as a list.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(test, serde(deny_unknown_fields))]
+pub struct CheckRuns {
+    pub total_count: u64,
+    pub check_runs: Vec<CheckRun>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_deserialize_check_runs() {
+        let json = fs::read_to_string("./fixtures/check_runs.json").unwrap();
+        let runs: CheckRuns = serde_json::from_str(&json).unwrap();
+        assert_eq!(runs.total_count, 2);
+        assert_eq!(runs.check_runs[0].status, CheckStatus::Completed);
+        assert_eq!(
+            runs.check_runs[0].conclusion,
+            Some(CheckConclusion::Success)
+        );
+        assert_eq!(runs.check_runs[1].status, CheckStatus::InProgress);
+        assert_eq!(runs.check_runs[1].conclusion, None);
+    }
+}
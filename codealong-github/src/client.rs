@@ -1,27 +1,201 @@
+use futures::future::{self, Either};
+use futures::Future;
 use reqwest;
-use reqwest::Response;
+use reqwest::{Certificate, Proxy, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use slog::Logger;
+use std::collections::BTreeMap;
 use std::env;
+use std::fs;
+use std::sync::Arc;
+use std::thread;
 
-use crate::error::{ErrorKind, ErrorPayload, Result};
+use codealong::RepoMetadata;
 
+use crate::check_run::CheckRuns;
+use crate::cursor::next_page_url;
+use crate::deployment::Deployment;
+use crate::error::{Error, ErrorKind, ErrorPayload, Result};
+use crate::graphql::{GraphqlCursor, GraphqlRequest, GraphqlResponse};
+use crate::paginated::Paginated;
+use crate::pull_request::PullRequest;
+use crate::rate_limiter::{retry_delay, RateLimiter};
+use crate::repo::Repo;
+use crate::review::{Review, ReviewComment};
+use crate::team::Team;
+use crate::timeline_event::TimelineEvent;
+
+/// Attempts at a request which keeps being rate limited before giving up.
+const MAX_ATTEMPTS: u32 = 6;
+
+/// The REST api of github.com.
+pub const DEFAULT_API_URL: &str = "https://api.github.com";
+
+#[derive(Clone)]
 pub struct Client {
+    /// Base url of the REST api, e.g. `https://github.example.com/api/v3`
+    /// for a Github Enterprise Server.
+    api_url: String,
     token: Option<String>,
+    /// Tokens by host, sent with requests to those hosts instead of `token`.
+    host_tokens: BTreeMap<String, String>,
+    /// PEM encoded certificate authority trusted in addition to the system
+    /// ones, e.g. the internal one of a Github Enterprise Server.
+    ca_cert: Option<Arc<Vec<u8>>>,
+    proxy: Option<String>,
+    rate_limiter: RateLimiter,
 }
 
 /// Very basic wrapper around reqwest to interact with the github api
 impl Client {
     pub fn new(token: String) -> Client {
-        Client { token: Some(token) }
+        Client {
+            token: Some(token),
+            ..Client::public()
+        }
     }
 
     pub fn public() -> Client {
-        Client { token: None }
+        Client {
+            api_url: DEFAULT_API_URL.to_owned(),
+            token: None,
+            host_tokens: BTreeMap::new(),
+            ca_cert: None,
+            proxy: None,
+            rate_limiter: RateLimiter::default(),
+        }
     }
 
+    /// Reads the api from `GITHUB_API_URL`, defaulting to github.com, the
+    /// access token from `GITHUB_TOKEN` and the tokens of other hosts from
+    /// `GITHUB_HOST_TOKENS`, e.g. `github.example.com=<token>,...`. A
+    /// certificate authority to trust is read from the PEM file at
+    /// `GITHUB_CA_CERT` and requests go through `GITHUB_PROXY`, or else
+    /// `HTTPS_PROXY`, if set.
     pub fn from_env() -> Client {
-        Client {
-            token: env::var_os("GITHUB_TOKEN").and_then(|s| s.into_string().ok()),
+        let var = |name| env::var(name).ok().filter(|value| !value.is_empty());
+        let mut client = Client {
+            token: var("GITHUB_TOKEN"),
+            host_tokens: var("GITHUB_HOST_TOKENS")
+                .map(|tokens| parse_host_tokens(&tokens))
+                .unwrap_or_default(),
+            proxy: var("GITHUB_PROXY").or_else(|| var("HTTPS_PROXY")),
+            ..Client::public()
+        };
+        if let Some(api_url) = var("GITHUB_API_URL") {
+            client = client.with_api_url(&api_url);
+        }
+        if let Some(pem) = var("GITHUB_CA_CERT").and_then(|path| fs::read(path).ok()) {
+            client = client.with_ca_cert(pem);
         }
+        client
+    }
+
+    /// Sends requests to the REST api at `api_url`, e.g.
+    /// `https://github.example.com/api/v3` for a Github Enterprise Server.
+    pub fn with_api_url(mut self, api_url: &str) -> Client {
+        self.api_url = api_url.trim_end_matches('/').to_owned();
+        self
+    }
+
+    /// Sends `token` with requests to `host` instead of the default token.
+    pub fn with_host_token(mut self, host: &str, token: &str) -> Client {
+        self.host_tokens.insert(host.to_owned(), token.to_owned());
+        self
+    }
+
+    /// Trusts the certificate authority `pem` in addition to the system ones.
+    pub fn with_ca_cert(mut self, pem: Vec<u8>) -> Client {
+        self.ca_cert = Some(Arc::new(pem));
+        self
+    }
+
+    /// Sends requests through the proxy at `proxy`.
+    pub fn with_proxy(mut self, proxy: &str) -> Client {
+        self.proxy = Some(proxy.to_owned());
+        self
+    }
+
+    /// A client for the Github host of the repo cloned from `clone_url`:
+    /// this one if it is hosted where this client sends requests to, or
+    /// else one sending them to the api of the Github Enterprise Server it
+    /// is hosted on, with the token of that host.
+    pub fn for_clone_url(&self, clone_url: &str) -> Client {
+        let host = match codealong::parse_remote_url(clone_url) {
+            Some((host, _)) => host,
+            None => return self.clone(),
+        };
+        let api_url = if host == "github.com" {
+            DEFAULT_API_URL.to_owned()
+        } else {
+            format!("https://{}/api/v3", host)
+        };
+        if host_of(&api_url) == host_of(&self.api_url) {
+            return self.clone();
+        }
+        let mut client = self.clone().with_api_url(&api_url);
+        client.token = self.host_tokens.get(&host).cloned();
+        client
+    }
+
+    pub fn api_url(&self) -> &str {
+        &self.api_url
+    }
+
+    /// The url of `path` of the REST api, e.g. `repos/facebook/react`.
+    pub fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.api_url, path)
+    }
+
+    /// The GraphQL api, which Github Enterprise Server serves from
+    /// `/api/graphql` rather than beside the REST api.
+    pub fn graphql_url(&self) -> String {
+        match self.api_url.strip_suffix("/api/v3") {
+            Some(base) => format!("{}/api/graphql", base),
+            None => format!("{}/graphql", self.api_url),
+        }
+    }
+
+    /// The token sent with requests to `url`. Pagination links point at the
+    /// host of the api they paginate, so they get its token.
+    fn token_for(&self, url: &str) -> Option<&String> {
+        host_of(url)
+            .and_then(|host| self.host_tokens.get(&host))
+            .or(self.token.as_ref())
+    }
+
+    fn http_client(&self) -> Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder();
+        if let Some(ref pem) = self.ca_cert {
+            builder = builder.add_root_certificate(Certificate::from_pem(pem)?);
+        }
+        if let Some(ref proxy) = self.proxy {
+            builder = builder.proxy(Proxy::all(proxy.as_str())?);
+        }
+        Ok(builder.build()?)
+    }
+
+    fn async_http_client(&self) -> Result<reqwest::r#async::Client> {
+        let mut builder = reqwest::r#async::Client::builder();
+        if let Some(ref pem) = self.ca_cert {
+            builder = builder.add_root_certificate(Certificate::from_pem(pem)?);
+        }
+        if let Some(ref proxy) = self.proxy {
+            builder = builder.proxy(Proxy::all(proxy.as_str())?);
+        }
+        Ok(builder.build()?)
+    }
+
+    /// Shares `rate_limiter` with this client, e.g. to throttle the requests
+    /// of several clients, or of the application, together.
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Client {
+        self.rate_limiter = rate_limiter;
+        self
+    }
+
+    pub fn rate_limiter(&self) -> &RateLimiter {
+        &self.rate_limiter
     }
 
     pub fn get(&self, url: &str) -> Result<Response> {
@@ -29,24 +203,219 @@ impl Client {
     }
 
     pub fn get_with_content_type(&self, url: &str, content_type: &str) -> Result<Response> {
-        let client = reqwest::Client::new();
-        let mut builder = client.get(url).header("Accept", content_type);
-        if let Some(ref token) = self.token {
-            builder = builder.header("Authorization", format!("token {}", token));
+        self.send(url, |client| client.get(url).header("Accept", content_type))
+    }
+
+    /// Whether requests are authenticated, which the GraphQL api requires.
+    pub fn is_authenticated(&self) -> bool {
+        self.token.is_some()
+    }
+
+    /// Runs a GraphQL query, retrying it while it is rate limited.
+    pub fn graphql<T, V>(&self, query: &str, variables: V) -> Result<T>
+    where
+        T: DeserializeOwned,
+        V: Serialize,
+    {
+        let request = GraphqlRequest { query, variables };
+        let mut attempt = 0;
+        loop {
+            let url = self.graphql_url();
+            let mut res = self.send(&url, |client| client.post(&url).json(&request))?;
+            let payload: GraphqlResponse<T> = res.json()?;
+            if payload.is_rate_limited() && attempt + 1 < MAX_ATTEMPTS {
+                thread::sleep(retry_delay(res.headers(), attempt));
+                attempt += 1;
+            } else {
+                return payload.into_result();
+            }
         }
-        let mut res = builder.send()?;
-        if res.status().is_success() {
-            Ok(res)
-        } else {
-            Err(self.get_error_kind(&mut res).into())
+    }
+
+    /// Sends the request built by `build`, tracking the rate limit budget
+    /// and sleeping and retrying while the request is rate limited rather
+    /// than failing.
+    fn send<F>(&self, url: &str, build: F) -> Result<Response>
+    where
+        F: Fn(&reqwest::Client) -> RequestBuilder,
+    {
+        codealong::ensure_online("requesting the Github api")?;
+        let client = self.http_client()?;
+        let mut attempt = 0;
+        loop {
+            let mut builder = build(&client);
+            if let Some(token) = self.token_for(url) {
+                builder = builder.header("Authorization", format!("token {}", token));
+            }
+            let permit = self.rate_limiter.acquire();
+            let mut res = builder.send()?;
+            self.rate_limiter.update(res.headers());
+            drop(permit);
+            if res.status().is_success() {
+                return Ok(res);
+            }
+            let delay = retry_delay(res.headers(), attempt);
+            match self.get_error_kind(&mut res) {
+                ErrorKind::RateLimitted if attempt + 1 < MAX_ATTEMPTS => {
+                    thread::sleep(delay);
+                    attempt += 1;
+                }
+                kind => return Err(kind.into()),
+            }
+        }
+    }
+
+    /// All pull requests of the repository named `github_name`, e.g.
+    /// `facebook/react`.
+    pub fn pull_requests(&self, github_name: &str) -> Paginated<PullRequest> {
+        Paginated::new(
+            self,
+            &self.url(&format!("repos/{}/pulls?state=all", github_name)),
+        )
+    }
+
+    /// The open pull requests of the repository named `github_name`.
+    pub fn open_pull_requests(&self, github_name: &str) -> Paginated<PullRequest> {
+        Paginated::new(
+            self,
+            &self.url(&format!("repos/{}/pulls?state=open", github_name)),
+        )
+    }
+
+    /// All pull requests of the repository named `github_name` along with
+    /// their reviews and comments, most recently updated first, fetched in
+    /// batches through the GraphQL api. Requires authentication.
+    pub fn pull_requests_with_reviews(&self, github_name: &str, logger: &Logger) -> GraphqlCursor {
+        GraphqlCursor::new(self, github_name, logger)
+    }
+
+    pub fn reviews(&self, github_name: &str, number: u64) -> Paginated<Review> {
+        Paginated::new(
+            self,
+            &self.url(&format!("repos/{}/pulls/{}/reviews", github_name, number)),
+        )
+    }
+
+    pub fn review_comments(&self, github_name: &str, number: u64) -> Paginated<ReviewComment> {
+        Paginated::new(
+            self,
+            &self.url(&format!("repos/{}/pulls/{}/comments", github_name, number)),
+        )
+    }
+
+    /// The timeline of an issue or pull request.
+    pub fn timeline(&self, github_name: &str, number: u64) -> Paginated<TimelineEvent> {
+        Paginated::new(
+            self,
+            &self.url(&format!("repos/{}/issues/{}/timeline", github_name, number)),
+        )
+    }
+
+    pub fn deployments(&self, github_name: &str) -> Paginated<Deployment> {
+        Paginated::new(
+            self,
+            &self.url(&format!("repos/{}/deployments", github_name)),
+        )
+    }
+
+    pub fn teams(&self, github_org: &str) -> Paginated<Team> {
+        Paginated::new(self, &self.url(&format!("orgs/{}/teams", github_org)))
+    }
+
+    /// Topics, visibility, languages etc. of the repository named
+    /// `github_name`.
+    pub fn repo_metadata(&self, github_name: &str) -> Result<RepoMetadata> {
+        let url = self.url(&format!("repos/{}", github_name));
+        let repo: Repo = self.get(&url)?.json()?;
+        let languages: BTreeMap<String, u64> = self.get(&format!("{}/languages", url))?.json()?;
+        let private = repo.private;
+        Ok(RepoMetadata {
+            stars: repo.stargazers_count,
+            language: repo.language,
+            languages,
+            topics: repo.topics,
+            // older Github Enterprise versions only report `private`
+            visibility: repo
+                .visibility
+                .or_else(|| Some(if private { "private" } else { "public" }.to_owned())),
+            default_branch: repo.default_branch,
+            archived: repo.archived,
+        })
+    }
+
+    /// The check runs of the commit `reference`. Github wraps them in an
+    /// object rather than paginating a list, so only the first page is read.
+    pub fn check_runs(&self, github_name: &str, reference: &str) -> Result<CheckRuns> {
+        let mut res = self.get(&self.url(&format!(
+            "repos/{}/commits/{}/check-runs?per_page=100",
+            github_name, reference
+        )))?;
+        Ok(res.json()?)
+    }
+
+    /// Comments `body`, in markdown, on the issue or pull request `number`.
+    pub fn comment(&self, github_name: &str, number: u64, body: &str) -> Result<()> {
+        let url = self.url(&format!("repos/{}/issues/{}/comments", github_name, number));
+        self.send(&url, |client| {
+            client.post(&url).json(&serde_json::json!({ "body": body }))
+        })?;
+        Ok(())
+    }
+
+    /// Fetches a single page of a paginated resource without blocking,
+    /// resolving to its items and the url of the next page. Acquiring the
+    /// rate limiter still blocks while its budget is exhausted.
+    pub(crate) fn get_page_async<T>(
+        &self,
+        url: &str,
+    ) -> impl Future<Item = (Vec<T>, Option<String>), Error = Error> + Send
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        if let Err(e) = codealong::ensure_online("requesting the Github api") {
+            return Either::A(future::err(e.into()));
         }
+        let client = match self.async_http_client() {
+            Ok(client) => client,
+            Err(e) => return Either::A(future::err(e)),
+        };
+        let mut builder = client
+            .get(url)
+            .header("Accept", "application/vnd.github+json");
+        if let Some(token) = self.token_for(url) {
+            builder = builder.header("Authorization", format!("token {}", token));
+        }
+        let permit = self.rate_limiter.acquire();
+        let rate_limiter = self.rate_limiter.clone();
+        let page = builder
+            .send()
+            .map(move |res| {
+                rate_limiter.update(res.headers());
+                drop(permit);
+                res
+            })
+            .and_then(|res| res.error_for_status())
+            .and_then(|mut res| {
+                let next_url = next_page_url(res.headers());
+                res.json::<Vec<T>>().map(move |items| (items, next_url))
+            })
+            .map_err(Error::from);
+        Either::B(page)
     }
 
     fn get_error_kind(&self, res: &mut reqwest::Response) -> ErrorKind {
-        let payload = res.json::<ErrorPayload>().unwrap();
-        if payload
-            .message
-            .contains("have triggered an abuse detection mechanism")
+        let status = res.status();
+        let throttled =
+            res.headers().contains_key("retry-after") || self.rate_limiter.remaining() == Some(0);
+        let message = res
+            .json::<ErrorPayload>()
+            .map(|payload| payload.message)
+            .unwrap_or_default();
+        if status == StatusCode::TOO_MANY_REQUESTS
+            || (status == StatusCode::FORBIDDEN
+                && (throttled
+                    || message.contains("rate limit")
+                    || message.contains("have triggered an abuse detection mechanism")))
         {
             ErrorKind::RateLimitted
         } else {
@@ -54,3 +423,85 @@ impl Client {
         }
     }
 }
+
+fn host_of(url: &str) -> Option<String> {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| host.to_owned()))
+}
+
+/// Parses tokens by host given as `host=token` pairs separated by commas.
+fn parse_host_tokens(tokens: &str) -> BTreeMap<String, String> {
+    tokens
+        .split(',')
+        .filter_map(|pair| {
+            let mut parts = pair.splitn(2, '=');
+            let host = parts.next()?.trim();
+            let token = parts.next()?.trim();
+            if host.is_empty() || token.is_empty() {
+                None
+            } else {
+                Some((host.to_owned(), token.to_owned()))
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enterprise() {
+        let client = Client::new("public-token".to_owned())
+            .with_host_token("github.example.com", "enterprise-token");
+        assert_eq!(client.url("repos/a/b"), "https://api.github.com/repos/a/b");
+        assert_eq!(client.graphql_url(), "https://api.github.com/graphql");
+        assert_eq!(
+            client.for_clone_url("git@github.com:a/b.git").api_url(),
+            DEFAULT_API_URL
+        );
+
+        let enterprise = client.for_clone_url("https://github.example.com/a/b.git");
+        assert_eq!(
+            enterprise.url("repos/a/b"),
+            "https://github.example.com/api/v3/repos/a/b"
+        );
+        assert_eq!(
+            enterprise.graphql_url(),
+            "https://github.example.com/api/graphql"
+        );
+        assert_eq!(
+            enterprise
+                .token_for("https://github.example.com/api/v3/repos/a/b/pulls?page=2")
+                .map(|token| &**token),
+            Some("enterprise-token")
+        );
+        assert_eq!(
+            client
+                .token_for("https://api.github.com/repos/a/b")
+                .map(|token| &**token),
+            Some("public-token")
+        );
+
+        let configured = Client::public().with_api_url("https://github.example.com/api/v3/");
+        assert_eq!(
+            configured.url("user"),
+            "https://github.example.com/api/v3/user"
+        );
+        assert_eq!(
+            configured
+                .for_clone_url("git@github.example.com:a/b.git")
+                .api_url(),
+            "https://github.example.com/api/v3"
+        );
+    }
+
+    #[test]
+    fn test_parse_host_tokens() {
+        let tokens = parse_host_tokens("github.example.com=abc, ghe.internal = def,invalid,=x");
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens["github.example.com"], "abc");
+        assert_eq!(tokens["ghe.internal"], "def");
+    }
+}
@@ -1,7 +1,7 @@
 use slog::Logger;
 use std::collections::HashMap;
 
-use codealong::{AuthorConfig, Config, Identity, RepoEntry, RepoInfo, WorkspaceConfig};
+use codealong::{AuthorConfig, Config, Identity, RepoEntry, RepoInfo, TeamConfig, WorkspaceConfig};
 
 use crate::client::Client;
 use crate::cursor::Cursor;
@@ -19,8 +19,12 @@ pub fn config_from_org(
     logger: &Logger,
 ) -> Result<WorkspaceConfig> {
     let config = default_config_with_authors(client, github_org, logger)?;
-    let repos = build_repo_entries(client, github_org, logger)?;
-    Ok(WorkspaceConfig { config, repos })
+    let repos = repos_for_org(client, github_org, logger)?;
+    Ok(WorkspaceConfig {
+        config,
+        repos,
+        ..Default::default()
+    })
 }
 
 fn default_config_with_authors(
@@ -29,9 +33,10 @@ fn default_config_with_authors(
     logger: &Logger,
 ) -> Result<Config> {
     let all_teams = get_all_teams(client, github_org, logger)?;
-    let url = format!("https://api.github.com/orgs/{}/members", github_org);
+    let url = client.url(&format!("orgs/{}/members", github_org));
     let cursor: Cursor<User> = Cursor::new(&client, &url, &logger);
     let mut config = Config::default();
+    add_nested_teams_to_config(&mut config, all_teams.values().flatten());
     for user in cursor {
         let teams = all_teams.get(&user.login);
         add_user_to_config(&client, &mut config, user, teams, logger)?;
@@ -44,11 +49,11 @@ fn get_all_teams(
     github_org: &str,
     logger: &Logger,
 ) -> Result<HashMap<String, Vec<Team>>> {
-    let url = format!("https://api.github.com/orgs/{}/teams", github_org);
+    let url = client.url(&format!("orgs/{}/teams", github_org));
     let cursor: Cursor<Team> = Cursor::new(&client, &url, logger);
     let mut res: HashMap<String, Vec<Team>> = HashMap::new();
     for team in cursor {
-        let url = format!("https://api.github.com/teams/{}/members", &team.id);
+        let url = client.url(&format!("teams/{}/members", &team.id));
         let cursor: Cursor<User> = Cursor::new(&client, &url, logger);
         for user in cursor {
             let teams = res.entry(user.login).or_insert_with(|| Vec::new());
@@ -58,6 +63,22 @@ fn get_all_teams(
     Ok(res)
 }
 
+/// Records the parent of each nested team so that their members roll up into
+/// it.
+fn add_nested_teams_to_config<'a>(config: &mut Config, teams: impl Iterator<Item = &'a Team>) {
+    for team in teams {
+        if let Some(ref parent) = team.parent {
+            config.teams.insert(
+                team.name.clone(),
+                TeamConfig {
+                    parent: Some(parent.name.clone()),
+                    ..Default::default()
+                },
+            );
+        }
+    }
+}
+
 fn add_user_to_config(
     client: &Client,
     config: &mut Config,
@@ -95,10 +116,7 @@ fn add_user_to_config(
 
 // Use the github search API to attempt to get email/name directly from commits
 fn augment_with_search_data(client: &Client, user: &mut User, logger: &Logger) -> Result<()> {
-    let url = format!(
-        "https://api.github.com/search/commits?q=author:{}",
-        &user.login
-    );
+    let url = client.url(&format!("search/commits?q=author:{}", &user.login));
     let mut resp = retry_when_rate_limited(
         &mut || client.get_with_content_type(&url, "application/vnd.github.cloak-preview"),
         Some(&mut |seconds| warn!(logger, "Rate limit reached, sleeping {} seconds", seconds)),
@@ -133,12 +151,10 @@ struct Signature {
     pub email: Option<String>,
 }
 
-fn build_repo_entries(
-    client: &Client,
-    github_org: &str,
-    logger: &Logger,
-) -> Result<Vec<RepoEntry>> {
-    let url = format!("https://api.github.com/orgs/{}/repos", github_org);
+/// Lists the repos of a Github organization as workspace entries, cloned
+/// under the name of the organization.
+pub fn repos_for_org(client: &Client, github_org: &str, logger: &Logger) -> Result<Vec<RepoEntry>> {
+    let url = client.url(&format!("orgs/{}/repos", github_org));
     let cursor: Cursor<Repo> = Cursor::new(&client, &url, logger);
     let res = cursor.map(|repo| RepoEntry {
         repo_info: RepoInfo {
@@ -146,6 +162,7 @@ fn build_repo_entries(
             github_name: Some(repo.full_name.clone()),
             clone_url: repo.ssh_url,
             fork: repo.fork,
+            archived: repo.archived,
             ..Default::default()
         },
         path: Some(format!("{}.git", repo.full_name)),
@@ -158,6 +175,7 @@ fn build_repo_entries(
 mod test {
     use super::*;
     use codealong::test::build_test_logger;
+    use std::fs;
 
     #[test]
     fn test_config_from_org() -> Result<()> {
@@ -178,4 +196,17 @@ mod test {
         );
         Ok(())
     }
+
+    #[test]
+    fn test_add_nested_teams_to_config() {
+        let json = fs::read_to_string("./fixtures/teams.json").unwrap();
+        let teams: Vec<Team> = serde_json::from_str(&json).unwrap();
+        let mut config = Config::default();
+        add_nested_teams_to_config(&mut config, teams.iter());
+        assert_eq!(config.teams.len(), 1);
+        assert_eq!(
+            config.teams[&teams[1].name].parent,
+            Some("Justice League".to_owned())
+        );
+    }
 }
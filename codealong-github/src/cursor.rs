@@ -1,7 +1,12 @@
+use std::sync::Arc;
+use std::time::Instant;
+
 use regex::Regex;
 use reqwest::header::HeaderMap;
 use slog::Logger;
 
+use codealong::{NoProgress, Progress, FETCH_PAGE_STAGE};
+
 use crate::client::Client;
 use crate::error::Result;
 
@@ -16,6 +21,8 @@ where
     per_page: Option<usize>,
     current_page: Option<std::vec::IntoIter<T>>,
     has_loaded_page: bool,
+    pages_loaded: usize,
+    progress: Arc<dyn Progress>,
     logger: Logger,
 }
 
@@ -31,28 +38,24 @@ where
             num_pages: None,
             per_page: None,
             has_loaded_page: false,
+            pages_loaded: 0,
+            progress: Arc::new(NoProgress),
             logger: logger.clone(),
         }
     }
 
+    /// Reports each page fetched to `progress`.
+    pub fn with_progress(mut self, progress: Arc<dyn Progress>) -> Cursor<'client, T> {
+        self.progress = progress;
+        self
+    }
+
     pub fn guess_len(&mut self) -> Option<usize> {
         self.ensure_page_loaded();
         self.num_pages
             .and_then(|num_page| self.per_page.map(|per_page| num_page * per_page))
     }
 
-    fn get_next_url(&self, headers: &HeaderMap) -> Option<String> {
-        let link = headers.get("link");
-        link.and_then(|link| {
-            lazy_static! {
-                static ref LINK_NEXT_REGEX: Regex = Regex::new(r#"<([^ ]*)>; rel="next""#).unwrap();
-            }
-            LINK_NEXT_REGEX
-                .captures(link.to_str().unwrap())
-                .map(|captures| captures[1].to_owned())
-        })
-    }
-
     fn read_from_current_page(&mut self) -> Option<T> {
         self.current_page.as_mut().and_then(|iter| iter.next())
     }
@@ -85,11 +88,13 @@ where
 
     fn load_next_page_helper(&mut self) -> Result<()> {
         if let Some(next_url) = self.next_url.take() {
+            let start = Instant::now();
             let mut res = self.client.get(&next_url)?;
             self.has_loaded_page = true;
             let new_page = res.json::<Vec<T>>().unwrap().into_iter();
+            self.progress.timed(FETCH_PAGE_STAGE, start.elapsed());
             let headers = res.headers();
-            self.next_url = self.get_next_url(&headers);
+            self.next_url = next_page_url(&headers);
             if let None = self.num_pages {
                 self.num_pages = self.get_num_pages(&headers);
             }
@@ -97,6 +102,9 @@ where
                 self.per_page = Some(new_page.len());
             }
             self.current_page = Some(new_page);
+            self.pages_loaded += 1;
+            self.progress
+                .page_fetched(self.pages_loaded, self.num_pages);
             Ok(())
         } else {
             Ok(())
@@ -104,6 +112,19 @@ where
     }
 }
 
+/// Url of the next page from the `Link` header of a paginated response.
+pub(crate) fn next_page_url(headers: &HeaderMap) -> Option<String> {
+    let link = headers.get("link");
+    link.and_then(|link| {
+        lazy_static! {
+            static ref LINK_NEXT_REGEX: Regex = Regex::new(r#"<([^ ]*)>; rel="next""#).unwrap();
+        }
+        LINK_NEXT_REGEX
+            .captures(link.to_str().unwrap())
+            .map(|captures| captures[1].to_owned())
+    })
+}
+
 impl<'client, T> Iterator for Cursor<'client, T>
 where
     for<'de> T: serde::Deserialize<'de>,
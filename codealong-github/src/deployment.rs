@@ -0,0 +1,44 @@
+use chrono::prelude::*;
+use chrono::DateTime;
+
+use crate::app::App;
+use crate::user::User;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(test, serde(deny_unknown_fields))]
+pub struct Deployment {
+    pub id: u64,
+    pub node_id: String,
+    pub url: String,
+    pub sha: String,
+    #[serde(rename = "ref")]
+    pub reference: String,
+    pub task: String,
+    pub payload: serde_json::Value,
+    pub original_environment: Option<String>,
+    pub environment: String,
+    pub description: Option<String>,
+    pub creator: Option<User>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub statuses_url: String,
+    pub repository_url: String,
+    pub transient_environment: Option<bool>,
+    pub production_environment: Option<bool>,
+    pub performed_via_github_app: Option<App>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_deserialize_deployments() {
+        let json = fs::read_to_string("./fixtures/deployments.json").unwrap();
+        let deployments: Vec<Deployment> = serde_json::from_str(&json).unwrap();
+        assert_eq!(deployments.len(), 1);
+        assert_eq!(deployments[0].reference, "topic-branch");
+        assert_eq!(deployments[0].environment, "production");
+    }
+}
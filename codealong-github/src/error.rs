@@ -5,12 +5,18 @@ use reqwest;
 error_chain! {
     errors {
         RateLimitted {}
+        Graphql(messages: String) {
+            description("graphql error")
+            display("graphql error: {}", messages)
+        }
         Unknown {}
     }
 
     foreign_links {
         Git2(git2::Error);
         IO(std::io::Error);
+        Json(serde_json::Error);
+        OpenSsl(openssl::error::ErrorStack);
         Reqwest(reqwest::Error);
     }
 
@@ -0,0 +1,587 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+use chrono::prelude::*;
+use chrono::DateTime;
+use serde::de::DeserializeOwned;
+use slog::Logger;
+
+use codealong::{NoProgress, Progress, FETCH_PAGE_STAGE};
+
+use crate::client::Client;
+use crate::error::{ErrorKind, Result};
+use crate::pull_request::{PullRequest, Ref};
+use crate::repo::Repo;
+use crate::review::{Review, ReviewComment, ReviewState};
+use crate::user::User;
+
+/// Pull requests per page. With the nested reviews and comments this keeps
+/// the cost of a query well below the node limit of the GraphQL api.
+const PAGE_SIZE: usize = 25;
+
+/// The login and id Github reports for the authors of deleted accounts.
+const GHOST_LOGIN: &str = "ghost";
+const GHOST_ID: u64 = 10137;
+
+const PULL_REQUESTS_QUERY: &str = r#"
+query($owner: String!, $name: String!, $first: Int!, $after: String) {
+  repository(owner: $owner, name: $name) {
+    pullRequests(first: $first, after: $after, orderBy: {field: UPDATED_AT, direction: DESC}) {
+      totalCount
+      pageInfo { hasNextPage endCursor }
+      nodes {
+        databaseId number url state title body
+        createdAt updatedAt closedAt mergedAt
+        mergeCommit { oid }
+        author { ...actor }
+        baseRefName baseRefOid baseRepository { ...repo }
+        headRefName headRefOid headRepository { ...repo }
+        reviews(first: 50) {
+          pageInfo { hasNextPage }
+          nodes {
+            databaseId id url body state submittedAt authorAssociation
+            commit { oid }
+            author { ...actor }
+            comments(first: 50) {
+              pageInfo { hasNextPage }
+              nodes {
+                databaseId id url diffHunk path position originalPosition
+                commit { oid } originalCommit { oid } replyTo { databaseId }
+                body createdAt updatedAt authorAssociation
+                startLine originalStartLine line originalLine subjectType
+                author { ...actor }
+              }
+            }
+          }
+        }
+      }
+    }
+  }
+}
+
+fragment actor on Actor {
+  login
+  ... on User { databaseId name email }
+  ... on Bot { databaseId }
+}
+
+fragment repo on Repository {
+  databaseId name nameWithOwner url sshUrl
+  isFork isPrivate visibility isArchived stargazerCount
+  defaultBranchRef { name }
+  primaryLanguage { name }
+}
+"#;
+
+#[derive(Debug, Serialize)]
+pub(crate) struct GraphqlRequest<'a, V> {
+    pub query: &'a str,
+    pub variables: V,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct GraphqlResponse<T> {
+    pub data: Option<T>,
+    #[serde(default)]
+    pub errors: Vec<GraphqlError>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct GraphqlError {
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    pub message: String,
+}
+
+impl<T: DeserializeOwned> GraphqlResponse<T> {
+    /// Secondary rate limits are reported as errors of successful responses
+    /// rather than through the status.
+    pub fn is_rate_limited(&self) -> bool {
+        self.errors
+            .iter()
+            .any(|e| e.kind.as_ref().map_or(false, |kind| kind == "RATE_LIMITED"))
+    }
+
+    pub fn into_result(self) -> Result<T> {
+        match self.data {
+            Some(data) if self.errors.is_empty() => Ok(data),
+            _ => {
+                let messages: Vec<_> = self.errors.into_iter().map(|e| e.message).collect();
+                Err(ErrorKind::Graphql(messages.join(", ")).into())
+            }
+        }
+    }
+}
+
+/// A pull request along with its reviews and the comments on its diff,
+/// fetched together by `GraphqlCursor`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PullRequestDetails {
+    pub pull_request: PullRequest,
+    pub reviews: Vec<Review>,
+    pub review_comments: Vec<ReviewComment>,
+}
+
+/// Iterates over the pull requests of a repository, most recently updated
+/// first, fetching them with their reviews and comments in batches through
+/// the GraphQL api. Reviews and comments which don't fit in a batch are
+/// fetched through the REST api.
+///
+/// The GraphQL api requires authentication.
+pub struct GraphqlCursor<'client> {
+    client: &'client Client,
+    github_name: String,
+    after: Option<String>,
+    has_next_page: bool,
+    total_count: Option<usize>,
+    current_page: std::vec::IntoIter<PullRequestNode>,
+    pages_loaded: usize,
+    progress: Arc<dyn Progress>,
+    logger: Logger,
+}
+
+impl<'client> GraphqlCursor<'client> {
+    pub fn new(client: &'client Client, github_name: &str, logger: &Logger) -> Self {
+        GraphqlCursor {
+            client,
+            github_name: github_name.to_owned(),
+            after: None,
+            has_next_page: true,
+            total_count: None,
+            current_page: Vec::new().into_iter(),
+            pages_loaded: 0,
+            progress: Arc::new(NoProgress),
+            logger: logger.clone(),
+        }
+    }
+
+    /// Reports each batch fetched to `progress`.
+    pub fn with_progress(mut self, progress: Arc<dyn Progress>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    pub fn guess_len(&mut self) -> Option<usize> {
+        if self.total_count.is_none() {
+            self.load_next_page();
+        }
+        self.total_count
+    }
+
+    fn load_next_page(&mut self) {
+        if let Err(e) = self.load_next_page_helper() {
+            error!(self.logger, "Error loading page: {}", e);
+            self.has_next_page = false;
+        }
+    }
+
+    fn load_next_page_helper(&mut self) -> Result<()> {
+        if !self.has_next_page {
+            return Ok(());
+        }
+        let mut parts = self.github_name.splitn(2, '/');
+        let variables = PullRequestsVariables {
+            owner: parts.next().unwrap_or_default(),
+            name: parts.next().unwrap_or_default(),
+            first: PAGE_SIZE,
+            after: self.after.as_ref().map(|s| &**s),
+        };
+        let start = Instant::now();
+        let data: PullRequestsData = self.client.graphql(PULL_REQUESTS_QUERY, &variables)?;
+        self.progress.timed(FETCH_PAGE_STAGE, start.elapsed());
+        let connection = data
+            .repository
+            .ok_or_else(|| ErrorKind::Graphql(format!("{} not found", self.github_name)))?
+            .pull_requests;
+        self.total_count = Some(connection.total_count);
+        self.has_next_page = connection.page_info.has_next_page;
+        self.after = connection.page_info.end_cursor;
+        self.current_page = connection.nodes.into_iter();
+        self.pages_loaded += 1;
+        let pages = (connection.total_count + PAGE_SIZE - 1) / PAGE_SIZE;
+        self.progress
+            .page_fetched(self.pages_loaded, Some(pages.max(1)));
+        Ok(())
+    }
+
+    fn details(&self, node: PullRequestNode) -> PullRequestDetails {
+        let api_url = self
+            .client
+            .url(&format!("repos/{}/pulls/{}", self.github_name, node.number));
+        let mut reviews = Vec::new();
+        let mut review_comments = Vec::new();
+        let mut complete = !node.reviews.page_info.has_next_page;
+        for review in node.reviews.nodes {
+            complete &= !review.comments.page_info.has_next_page;
+            let review_id = review.database_id;
+            review_comments.extend(
+                review
+                    .comments
+                    .nodes
+                    .into_iter()
+                    .map(|comment| comment.into_review_comment(review_id, &api_url)),
+            );
+            reviews.push(Review {
+                id: review.database_id,
+                node_id: review.id,
+                user: review.author.map(Actor::into_user),
+                body: review.body,
+                state: review.state,
+                html_url: review.url,
+                pull_request_url: api_url.clone(),
+                commit_id: review.commit.map(|c| c.oid),
+                submitted_at: review.submitted_at,
+                author_association: review.author_association,
+                links: HashMap::new(),
+            });
+        }
+        if !complete {
+            debug!(self.logger, "Fetching remaining reviews"; "number" => node.number);
+            reviews = self
+                .client
+                .reviews(&self.github_name, node.number)
+                .iter(&self.logger)
+                .collect();
+            review_comments = self
+                .client
+                .review_comments(&self.github_name, node.number)
+                .iter(&self.logger)
+                .collect();
+        }
+        PullRequestDetails {
+            pull_request: PullRequest {
+                id: node.database_id,
+                url: Some(api_url),
+                number: node.number,
+                base: Ref {
+                    sha: node.base_ref_oid,
+                    reference: node.base_ref_name,
+                    repo: node.base_repository.map(RepoNode::into_repo),
+                },
+                head: Ref {
+                    sha: node.head_ref_oid,
+                    reference: node.head_ref_name,
+                    repo: node.head_repository.map(RepoNode::into_repo),
+                },
+                html_url: Some(node.url),
+                // merged pull requests are closed as far as the REST api is
+                // concerned
+                state: Some(
+                    if node.state == "OPEN" {
+                        "open"
+                    } else {
+                        "closed"
+                    }
+                    .to_owned(),
+                ),
+                title: Some(node.title),
+                body: Some(node.body),
+                user: node.author.map_or_else(Actor::ghost, Actor::into_user),
+                created_at: node.created_at,
+                updated_at: node.updated_at,
+                closed_at: node.closed_at,
+                merged_at: node.merged_at,
+                merge_commit_sha: node.merge_commit.map(|c| c.oid),
+                requested_reviewers: vec![],
+            },
+            reviews,
+            review_comments,
+        }
+    }
+}
+
+impl<'client> Iterator for GraphqlCursor<'client> {
+    type Item = PullRequestDetails;
+
+    fn next(&mut self) -> Option<PullRequestDetails> {
+        let node = self.current_page.next().or_else(|| {
+            self.load_next_page();
+            self.current_page.next()
+        })?;
+        Some(self.details(node))
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct PullRequestsVariables<'a> {
+    owner: &'a str,
+    name: &'a str,
+    first: usize,
+    after: Option<&'a str>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PullRequestsData {
+    repository: Option<RepositoryNode>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RepositoryNode {
+    pull_requests: Connection<PullRequestNode>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Connection<T> {
+    #[serde(default)]
+    total_count: usize,
+    page_info: PageInfo,
+    nodes: Vec<T>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PageInfo {
+    has_next_page: bool,
+    end_cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PullRequestNode {
+    database_id: u64,
+    number: u64,
+    url: String,
+    state: String,
+    title: String,
+    body: String,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    closed_at: Option<DateTime<Utc>>,
+    merged_at: Option<DateTime<Utc>>,
+    merge_commit: Option<CommitNode>,
+    author: Option<Actor>,
+    base_ref_name: String,
+    base_ref_oid: String,
+    base_repository: Option<RepoNode>,
+    head_ref_name: String,
+    head_ref_oid: String,
+    head_repository: Option<RepoNode>,
+    reviews: Connection<ReviewNode>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReviewNode {
+    database_id: u64,
+    id: String,
+    url: String,
+    body: Option<String>,
+    state: ReviewState,
+    submitted_at: Option<DateTime<Utc>>,
+    author_association: String,
+    commit: Option<CommitNode>,
+    author: Option<Actor>,
+    comments: Connection<ReviewCommentNode>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReviewCommentNode {
+    database_id: u64,
+    id: String,
+    url: String,
+    diff_hunk: String,
+    path: String,
+    position: Option<u64>,
+    original_position: Option<u64>,
+    commit: Option<CommitNode>,
+    original_commit: Option<CommitNode>,
+    reply_to: Option<ReplyTo>,
+    body: String,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    author_association: String,
+    start_line: Option<u64>,
+    original_start_line: Option<u64>,
+    line: Option<u64>,
+    original_line: Option<u64>,
+    subject_type: Option<String>,
+    author: Option<Actor>,
+}
+
+impl ReviewCommentNode {
+    fn into_review_comment(self, review_id: u64, pull_request_url: &str) -> ReviewComment {
+        ReviewComment {
+            id: self.database_id,
+            node_id: self.id,
+            url: format!(
+                "{}/comments/{}",
+                pull_request_url.rsplitn(2, '/').nth(1).unwrap_or_default(),
+                self.database_id
+            ),
+            pull_request_review_id: Some(review_id),
+            diff_hunk: self.diff_hunk,
+            path: self.path,
+            position: self.position,
+            original_position: self.original_position,
+            commit_id: self.commit.map(|c| c.oid).unwrap_or_default(),
+            original_commit_id: self.original_commit.map(|c| c.oid).unwrap_or_default(),
+            in_reply_to_id: self.reply_to.map(|r| r.database_id),
+            user: self.author.map(Actor::into_user),
+            body: self.body,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            html_url: self.url,
+            pull_request_url: pull_request_url.to_owned(),
+            author_association: self.author_association,
+            start_line: self.start_line,
+            original_start_line: self.original_start_line,
+            start_side: None,
+            line: self.line,
+            original_line: self.original_line,
+            side: None,
+            subject_type: self.subject_type.map(|s| s.to_lowercase()),
+            links: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReplyTo {
+    database_id: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommitNode {
+    oid: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Actor {
+    login: String,
+    database_id: Option<u64>,
+    name: Option<String>,
+    email: Option<String>,
+}
+
+impl Actor {
+    fn into_user(self) -> User {
+        User {
+            id: self.database_id.unwrap_or_default(),
+            login: self.login,
+            name: self.name,
+            // the GraphQL api reports hidden emails as empty
+            email: self.email.filter(|email| !email.is_empty()),
+        }
+    }
+
+    fn ghost() -> User {
+        User {
+            id: GHOST_ID,
+            login: GHOST_LOGIN.to_owned(),
+            name: None,
+            email: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RepoNode {
+    database_id: u64,
+    name: String,
+    name_with_owner: String,
+    url: String,
+    ssh_url: String,
+    is_fork: bool,
+    is_private: bool,
+    visibility: Option<String>,
+    is_archived: bool,
+    stargazer_count: u64,
+    default_branch_ref: Option<NameNode>,
+    primary_language: Option<NameNode>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NameNode {
+    name: String,
+}
+
+impl RepoNode {
+    fn into_repo(self) -> Repo {
+        Repo {
+            id: self.database_id,
+            name: self.name,
+            clone_url: format!("{}.git", self.url),
+            git_url: format!("git://github.com/{}.git", self.name_with_owner),
+            full_name: self.name_with_owner,
+            html_url: self.url,
+            ssh_url: self.ssh_url,
+            fork: self.is_fork,
+            private: self.is_private,
+            visibility: self.visibility.map(|v| v.to_lowercase()),
+            default_branch: self.default_branch_ref.map(|b| b.name),
+            archived: self.is_archived,
+            stargazers_count: self.stargazer_count,
+            language: self.primary_language.map(|l| l.name),
+            topics: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codealong::test::build_test_logger;
+    use std::fs;
+
+    #[test]
+    fn test_details() {
+        let json = fs::read_to_string("./fixtures/graphql_pull_requests.json").unwrap();
+        let res: GraphqlResponse<PullRequestsData> = serde_json::from_str(&json).unwrap();
+        assert!(!res.is_rate_limited());
+        let connection = res.into_result().unwrap().repository.unwrap().pull_requests;
+        assert_eq!(connection.total_count, 2);
+        assert!(!connection.page_info.has_next_page);
+
+        let client = Client::public();
+        let cursor = GraphqlCursor::new(&client, "octocat/Hello-World", &build_test_logger());
+        let mut details: Vec<_> = connection
+            .nodes
+            .into_iter()
+            .map(|node| cursor.details(node))
+            .collect();
+        let ghosted = details.pop().unwrap();
+        assert_eq!(ghosted.pull_request.user.login, "ghost");
+        assert_eq!(ghosted.pull_request.state, Some("open".to_owned()));
+        assert!(ghosted.pull_request.head.repo.is_none());
+
+        let merged = details.pop().unwrap();
+        let pr = &merged.pull_request;
+        assert_eq!(pr.id, 1347);
+        assert_eq!(pr.state, Some("closed".to_owned()));
+        assert_eq!(
+            pr.url,
+            Some("https://api.github.com/repos/octocat/Hello-World/pulls/1347".to_owned())
+        );
+        assert_eq!(pr.user.email, None);
+        assert_eq!(pr.base.reference, "master");
+        let repo = pr.base.repo.as_ref().unwrap();
+        assert_eq!(repo.full_name, "octocat/Hello-World");
+        assert_eq!(repo.clone_url, "https://github.com/octocat/Hello-World.git");
+        assert_eq!(repo.visibility, Some("public".to_owned()));
+        assert_eq!(merged.reviews.len(), 1);
+        assert_eq!(merged.reviews[0].state, ReviewState::Approved);
+        assert_eq!(merged.review_comments.len(), 1);
+        assert_eq!(merged.review_comments[0].pull_request_review_id, Some(80));
+        assert_eq!(
+            merged.review_comments[0].url,
+            "https://api.github.com/repos/octocat/Hello-World/pulls/comments/10"
+        );
+    }
+
+    #[test]
+    fn test_rate_limited() {
+        let res: GraphqlResponse<PullRequestsData> = serde_json::from_str(
+            r#"{"errors": [{"type": "RATE_LIMITED", "message": "API rate limit exceeded"}]}"#,
+        )
+        .unwrap();
+        assert!(res.is_rate_limited());
+        assert!(res.into_result().is_err());
+    }
+}
@@ -1,10 +1,12 @@
 extern crate chrono;
 #[macro_use]
 extern crate error_chain;
+extern crate futures;
 extern crate git2;
 extern crate regex;
 #[macro_use]
 extern crate lazy_static;
+extern crate openssl;
 extern crate reqwest;
 #[macro_use]
 extern crate serde_derive;
@@ -15,24 +17,58 @@ extern crate serde_json;
 extern crate slog;
 
 mod analyzed_pull_request;
+mod analyzed_review;
+mod app;
+mod check_run;
 mod client;
 mod config;
 mod cursor;
+mod deployment;
 mod error;
+mod graphql;
+mod paginated;
 mod pull_request;
 mod pull_request_analyzer;
 mod pull_requests_analyzer;
+mod rate_limiter;
 mod repo;
+mod review;
 mod team;
+mod timeline_event;
+mod traceability;
 mod user;
+mod webhook;
 
 pub use crate::analyzed_pull_request::AnalyzedPullRequest;
-pub use crate::client::Client;
-pub use crate::config::config_from_org;
+pub use crate::analyzed_review::{analyze_reviews, AnalyzedReview, ReviewedPullRequest};
+pub use crate::app::App;
+pub use crate::check_run::{
+    CheckConclusion, CheckRun, CheckRunOutput, CheckRunPullRequest, CheckRuns, CheckStatus,
+    CheckSuiteRef,
+};
+pub use crate::client::{Client, DEFAULT_API_URL};
+pub use crate::config::{config_from_org, repos_for_org};
 pub use crate::cursor::Cursor;
+pub use crate::deployment::Deployment;
 pub use crate::error::{Error, ErrorKind};
+pub use crate::graphql::{GraphqlCursor, PullRequestDetails};
+pub use crate::paginated::{PageStream, Paginated};
 pub use crate::pull_request::PullRequest;
 pub use crate::pull_request_analyzer::PullRequestAnalyzer;
 pub use crate::pull_requests_analyzer::PullRequestsAnalyzer;
+pub use crate::rate_limiter::{retry_delay, Permit, RateLimiter};
 pub use crate::repo::Repo;
+pub use crate::review::{Link, Review, ReviewComment, ReviewState};
+pub use crate::team::Team;
+pub use crate::timeline_event::{
+    AssignEvent, CommitRef, CrossReferenceEvent, CrossReferenceSource, GitActor, IssueComment,
+    IssueEvent, Label, LabelEvent, Rename, RenameEvent, ReviewRequestEvent, TimelineCommit,
+    TimelineEvent, Verification,
+};
+pub use crate::traceability::Traceability;
 pub use crate::user::User;
+pub use crate::webhook::{
+    sign_payload, verify_signature, CheckRunEvent, DeploymentEvent, PingEvent, PullRequestEvent,
+    PullRequestReviewCommentEvent, PullRequestReviewEvent, PushAuthor, PushCommit, PushEvent,
+    WebhookEvent,
+};
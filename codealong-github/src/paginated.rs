@@ -0,0 +1,142 @@
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+
+use futures::{Async, Future, Poll, Stream};
+use serde::de::DeserializeOwned;
+use slog::Logger;
+
+use crate::client::Client;
+use crate::cursor::Cursor;
+use crate::error::Error;
+
+/// A paginated Github resource which can be consumed lazily, either as a
+/// blocking iterator or as a stream.
+pub struct Paginated<'client, T> {
+    client: &'client Client,
+    url: String,
+    item: PhantomData<T>,
+}
+
+impl<'client, T> Paginated<'client, T>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    pub fn new(client: &'client Client, url: &str) -> Paginated<'client, T> {
+        Paginated {
+            client,
+            url: url.to_owned(),
+            item: PhantomData,
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Blocks while each page is fetched.
+    pub fn iter(&self, logger: &Logger) -> Cursor<'client, T> {
+        Cursor::new(self.client, &self.url, logger)
+    }
+
+    /// The next page is only requested once the items of the current page
+    /// have been consumed. Pages are requested with the async client of
+    /// reqwest, so the stream must be driven by a tokio runtime, e.g. with
+    /// `tokio::runtime::Runtime::block_on`, rather than with `wait`.
+    pub fn stream(&self) -> PageStream<T> {
+        let client = self.client.clone();
+        PageStream::new(
+            &self.url,
+            Box::new(move |url: &str| -> PendingPage<T> { Box::new(client.get_page_async(url)) }),
+        )
+    }
+}
+
+type PendingPage<T> = Box<dyn Future<Item = (Vec<T>, Option<String>), Error = Error> + Send>;
+
+/// Requests the page at a url, resolving to its items and the url of the
+/// next page.
+type FetchPage<T> = Box<dyn FnMut(&str) -> PendingPage<T> + Send>;
+
+/// Provides a `futures::Stream` on top of the Github pagination API, which
+/// must be polled within a tokio runtime.
+pub struct PageStream<T> {
+    fetch_page: FetchPage<T>,
+    next_url: Option<String>,
+    page: VecDeque<T>,
+    pending: Option<PendingPage<T>>,
+}
+
+impl<T> PageStream<T> {
+    fn new(url: &str, fetch_page: FetchPage<T>) -> PageStream<T> {
+        PageStream {
+            fetch_page,
+            next_url: Some(url.to_owned()),
+            page: VecDeque::new(),
+            pending: None,
+        }
+    }
+}
+
+impl<T> Stream for PageStream<T>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    type Item = T;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Option<T>, Error> {
+        loop {
+            if let Some(item) = self.page.pop_front() {
+                return Ok(Async::Ready(Some(item)));
+            }
+            if let Some(mut pending) = self.pending.take() {
+                match pending.poll()? {
+                    Async::Ready((items, next_url)) => {
+                        self.page.extend(items);
+                        self.next_url = next_url;
+                    }
+                    Async::NotReady => {
+                        self.pending = Some(pending);
+                        return Ok(Async::NotReady);
+                    }
+                }
+                continue;
+            }
+            match self.next_url.take() {
+                Some(url) => self.pending = Some((self.fetch_page)(&url)),
+                None => return Ok(Async::Ready(None)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::review::Review;
+    use futures::future;
+    use std::fs;
+
+    #[test]
+    fn test_stream() {
+        let reviews: Vec<Review> =
+            serde_json::from_str(&fs::read_to_string("./fixtures/reviews.json").unwrap()).unwrap();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let stream = PageStream::new(
+            "page/1",
+            Box::new(move |url: &str| -> PendingPage<Review> {
+                sender.send(url.to_owned()).unwrap();
+                let page: usize = url["page/".len()..].parse().unwrap();
+                let next_url = Some(format!("page/{}", page + 1)).filter(|_| page < 3);
+                Box::new(future::ok((reviews.clone(), next_url)))
+            }),
+        );
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        let streamed = runtime.block_on(stream.take(4).collect()).unwrap();
+        assert_eq!(streamed.len(), 4);
+        assert_eq!(streamed[0].id, streamed[2].id);
+        // the third page is never needed, so never requested
+        let requested: Vec<String> = receiver.try_iter().collect();
+        assert_eq!(requested, vec!["page/1", "page/2"]);
+    }
+}
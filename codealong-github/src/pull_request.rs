@@ -20,6 +20,13 @@ pub struct PullRequest {
     pub updated_at: DateTime<Utc>,
     pub closed_at: Option<DateTime<Utc>>,
     pub merged_at: Option<DateTime<Utc>>,
+    /// The commit the pull request landed as, i.e. the merge commit, the
+    /// squashed commit or the last rebased commit.
+    #[serde(default)]
+    pub merge_commit_sha: Option<String>,
+    /// Reviewers whose review is still pending.
+    #[serde(default)]
+    pub requested_reviewers: Vec<User>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
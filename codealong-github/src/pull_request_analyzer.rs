@@ -4,13 +4,16 @@ use slog::Logger;
 use codealong::{with_authentication, DiffAnalyzer, RepoConfig};
 
 use crate::analyzed_pull_request::AnalyzedPullRequest;
+use crate::analyzed_review::{analyze_reviews, AnalyzedReview};
 use crate::error::{Error, Result};
 use crate::pull_request::{PullRequest, Ref};
+use crate::review::{Review, ReviewComment};
 
 pub struct PullRequestAnalyzer<'a> {
     repo: &'a Repository,
     config: &'a RepoConfig,
     pr: PullRequest,
+    reviews: Option<(Vec<Review>, Vec<ReviewComment>)>,
     logger: Logger,
 }
 
@@ -25,12 +28,29 @@ impl<'a> PullRequestAnalyzer<'a> {
         PullRequestAnalyzer {
             repo,
             pr,
+            reviews: None,
             config,
             logger,
         }
     }
 
+    /// Analyzes the pull request along with its reviews and review comments.
+    pub fn with_reviews(
+        mut self,
+        reviews: Vec<Review>,
+        comments: Vec<ReviewComment>,
+    ) -> PullRequestAnalyzer<'a> {
+        self.reviews = Some((reviews, comments));
+        self
+    }
+
     pub fn analyze(self) -> Result<AnalyzedPullRequest> {
+        Ok(self.analyze_with_reviews()?.0)
+    }
+
+    /// Analyzes the pull request and each of its reviews, if they were
+    /// given with `with_reviews`.
+    pub fn analyze_with_reviews(self) -> Result<(AnalyzedPullRequest, Vec<AnalyzedReview>)> {
         debug!(self.logger, "Analyzing pull_request"; "updated_at" => &self.pr.updated_at.to_rfc2822(), "user" => &self.pr.user.login, "title" => &self.pr.title);
         self.fetch_remote(&self.pr.base)?;
         self.fetch_remote(&self.pr.head)?;
@@ -50,6 +70,7 @@ impl<'a> PullRequestAnalyzer<'a> {
                             Some(&parent),
                             &self.config.config,
                         )
+                        .with_paths(&self.config.repo.paths)
                         .analyze()?)
                     })
             })
@@ -58,9 +79,34 @@ impl<'a> PullRequestAnalyzer<'a> {
         let normalized_author = self
             .config
             .config
-            .person_for_github_login(&self.pr.user.login);
-        debug!(self.logger, "Done analyzing");
-        Ok(AnalyzedPullRequest::new(self.pr, diff, normalized_author))
+            .person_for_github_login_at(&self.pr.user.login, &self.pr.created_at);
+        let (review_stats, reviews) = match self.reviews {
+            Some((ref reviews, ref comments)) => {
+                let (stats, analyzed) = analyze_reviews(
+                    &self.pr,
+                    &normalized_author,
+                    reviews,
+                    comments,
+                    &self.config.config,
+                );
+                (Some(stats), analyzed)
+            }
+            None => (None, vec![]),
+        };
+        debug!(self.logger, "Done analyzing"; "reviews" => reviews.len());
+        let mut analyzed = AnalyzedPullRequest::new(
+            self.pr,
+            diff,
+            normalized_author,
+            &self.config.config.calendar(),
+        );
+        if let Some(review_stats) = review_stats {
+            analyzed.set_review_stats(review_stats);
+        }
+        analyzed.alias_repos(&self.config.config);
+        analyzed.extract_issues(&self.config.config)?;
+        analyzed.store_messages(&self.config.config);
+        Ok((analyzed, reviews))
     }
 
     fn fetch_remote(&self, reference: &Ref) -> Result<()> {
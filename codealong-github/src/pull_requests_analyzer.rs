@@ -1,19 +1,25 @@
+use std::sync::Arc;
+
 use git2::Repository;
 use slog::Logger;
 
-use codealong::{AnalyzeOpts, Repo, RepoConfig};
+use codealong::{AnalysisStream, AnalyzeOpts, NoProgress, Progress, Repo, RepoConfig};
 
+use crate::analyzed_pull_request::AnalyzedPullRequest;
 use crate::client::Client;
 use crate::cursor::Cursor;
 use crate::error::*;
+use crate::graphql::GraphqlCursor;
 use crate::pull_request::PullRequest;
 use crate::pull_request_analyzer::PullRequestAnalyzer;
+use crate::review::{Review, ReviewComment};
 
 pub struct PullRequestsAnalyzer<'client> {
     repo: Repository,
     config: RepoConfig,
     client: &'client Client,
     logger: Logger,
+    progress: Arc<dyn Progress>,
 }
 
 impl<'client> PullRequestsAnalyzer<'client> {
@@ -28,9 +34,16 @@ impl<'client> PullRequestsAnalyzer<'client> {
             logger: parent_logger.new(o!("repo" => config.repo.name.to_owned())),
             config,
             client,
+            progress: Arc::new(NoProgress),
         }
     }
 
+    /// Reports the pages of pull requests fetched to `progress`.
+    pub fn with_progress(mut self, progress: Arc<dyn Progress>) -> PullRequestsAnalyzer<'client> {
+        self.progress = progress;
+        self
+    }
+
     pub fn analyze(
         &self,
         opts: AnalyzeOpts,
@@ -39,12 +52,56 @@ impl<'client> PullRequestsAnalyzer<'client> {
         Ok(PullRequestsCursor {
             repo: &self.repo,
             cursor,
+            client: self.client,
             config: &self.config,
             opts,
             logger: self.logger.clone(),
         })
     }
 
+    /// Like `analyze`, but fetches and analyzes the pull requests on a
+    /// thread of its own, with its own handle on the repository, and yields
+    /// them as a stream with at most `buffer` pull requests analyzed ahead
+    /// of the consumer.
+    pub fn stream(
+        &self,
+        opts: AnalyzeOpts,
+        buffer: usize,
+    ) -> AnalysisStream<AnalyzedPullRequest, Error> {
+        let path = self.repo.path().to_owned();
+        let config = self.config.clone();
+        let client = self.client.clone();
+        let logger = self.logger.clone();
+        let progress = self.progress.clone();
+        AnalysisStream::spawn(buffer, move |emitter| {
+            let analyzer = match Repository::open(&path) {
+                Ok(repo) => PullRequestsAnalyzer {
+                    repo,
+                    config,
+                    client: &client,
+                    logger,
+                    progress,
+                },
+                Err(e) => {
+                    emitter.emit(Err(e.into()));
+                    return;
+                }
+            };
+            let pull_requests = match analyzer.analyze(opts) {
+                Ok(pull_requests) => pull_requests,
+                Err(e) => {
+                    emitter.emit(Err(e));
+                    return;
+                }
+            };
+            for pr_analyzer in pull_requests {
+                if !emitter.emit(pr_analyzer.and_then(|a| a.analyze())) {
+                    return;
+                }
+            }
+        })
+    }
+
     pub fn guess_len(&self, opts: AnalyzeOpts) -> Result<usize> {
         if opts.since.is_some() {
             Ok(self.analyze(opts)?.count())
@@ -60,19 +117,66 @@ impl<'client> PullRequestsAnalyzer<'client> {
         Ok(Self::new(repo.repository()?, repo.config(), client, logger))
     }
 
-    fn build_cursor(&self, _opts: AnalyzeOpts) -> Cursor<PullRequest> {
-        let url = format!(
-            "https://api.github.com/repos/{}/pulls?state=all",
-            self.config.repo.github_name.as_ref().unwrap()
-        );
-        Cursor::new(&self.client, &url, &self.logger)
+    /// Pull requests are fetched in batches through the GraphQL api when
+    /// authenticated, and page by page through the REST api otherwise.
+    fn build_cursor(&self, _opts: AnalyzeOpts) -> PullRequests<'client> {
+        let github_name = self.config.repo.github_name.as_ref().unwrap();
+        if self.client.is_authenticated() {
+            PullRequests::Graphql(
+                self.client
+                    .pull_requests_with_reviews(github_name, &self.logger)
+                    .with_progress(self.progress.clone()),
+            )
+        } else {
+            PullRequests::Rest(
+                self.client
+                    .pull_requests(github_name)
+                    .iter(&self.logger)
+                    .with_progress(self.progress.clone()),
+            )
+        }
+    }
+}
+
+enum PullRequests<'client> {
+    Rest(Cursor<'client, PullRequest>),
+    Graphql(GraphqlCursor<'client>),
+}
+
+impl<'client> PullRequests<'client> {
+    fn guess_len(&mut self) -> Option<usize> {
+        match self {
+            PullRequests::Rest(cursor) => cursor.guess_len(),
+            PullRequests::Graphql(cursor) => cursor.guess_len(),
+        }
+    }
+}
+
+/// A pull request along with its reviews and review comments, unless they
+/// still have to be fetched.
+type Fetched = (PullRequest, Option<(Vec<Review>, Vec<ReviewComment>)>);
+
+impl<'client> Iterator for PullRequests<'client> {
+    type Item = Fetched;
+
+    fn next(&mut self) -> Option<Fetched> {
+        match self {
+            PullRequests::Rest(cursor) => cursor.next().map(|pr| (pr, None)),
+            PullRequests::Graphql(cursor) => cursor.next().map(|details| {
+                (
+                    details.pull_request,
+                    Some((details.reviews, details.review_comments)),
+                )
+            }),
+        }
     }
 }
 
 struct PullRequestsCursor<'client> {
     repo: &'client Repository,
     config: &'client RepoConfig,
-    cursor: Cursor<'client, PullRequest>,
+    cursor: PullRequests<'client>,
+    client: &'client Client,
     opts: AnalyzeOpts,
     logger: Logger,
 }
@@ -85,7 +189,7 @@ impl<'client> Iterator for PullRequestsCursor<'client> {
             let pr = self.cursor.next();
             match pr {
                 None => break None,
-                Some(pr) => {
+                Some((pr, reviews)) => {
                     if let Some(ref since) = self.opts.since {
                         if since > &pr.updated_at {
                             break None;
@@ -95,8 +199,18 @@ impl<'client> Iterator for PullRequestsCursor<'client> {
                     if !self.opts.ignore_unknown_authors
                         || self.config.config.is_github_login_known(&pr.user.login)
                     {
+                        // reviews are only fetched through the REST api
+                        // for the pull requests which are analyzed
+                        let (reviews, comments) = match reviews {
+                            Some(reviews) => reviews,
+                            None => match self.fetch_reviews(&pr) {
+                                Ok(reviews) => reviews,
+                                Err(e) => break Some(Err(e)),
+                            },
+                        };
                         let analyzer =
-                            PullRequestAnalyzer::new(&self.repo, pr, &self.config, &self.logger);
+                            PullRequestAnalyzer::new(&self.repo, pr, &self.config, &self.logger)
+                                .with_reviews(reviews, comments);
                         break Some(Ok(analyzer));
                     }
                 }
@@ -104,3 +218,19 @@ impl<'client> Iterator for PullRequestsCursor<'client> {
         }
     }
 }
+
+impl<'client> PullRequestsCursor<'client> {
+    fn fetch_reviews(&self, pr: &PullRequest) -> Result<(Vec<Review>, Vec<ReviewComment>)> {
+        let github_name = self.config.repo.github_name.as_ref().unwrap();
+        Ok((
+            self.client
+                .reviews(github_name, pr.number)
+                .iter(&self.logger)
+                .collect(),
+            self.client
+                .review_comments(github_name, pr.number)
+                .iter(&self.logger)
+                .collect(),
+        ))
+    }
+}
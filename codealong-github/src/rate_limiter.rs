@@ -0,0 +1,232 @@
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use chrono::prelude::*;
+use reqwest::header::HeaderMap;
+
+const DEFAULT_MAX_CONCURRENT: usize = 4;
+
+/// Github asks clients hitting a secondary rate limit without being told
+/// when to retry to wait at least a minute, backing off exponentially.
+const MIN_BACKOFF_SECONDS: u64 = 60;
+const MAX_BACKOFF_SECONDS: u64 = 15 * 60;
+
+/// Tracks the Github rate limit budget, as reported in the `X-RateLimit-*`
+/// headers of responses, and the number of requests in flight.
+///
+/// Clones share the same budget, so an application can hand the limiter of
+/// its `Client` to its own code, or give several clients the same limiter,
+/// and all requests are throttled together.
+#[derive(Clone)]
+pub struct RateLimiter {
+    shared: Arc<(Mutex<Budget>, Condvar)>,
+}
+
+#[derive(Debug)]
+struct Budget {
+    max_concurrent: usize,
+    in_flight: usize,
+    remaining: Option<u64>,
+    reset_at: Option<DateTime<Utc>>,
+}
+
+impl Budget {
+    /// How long a new request has to wait for the budget to reset, if it
+    /// has run out.
+    fn wait_for_reset(&self, now: DateTime<Utc>) -> Option<Duration> {
+        match (self.remaining, self.reset_at) {
+            (Some(0), Some(reset_at)) if reset_at > now => (reset_at - now).to_std().ok(),
+            _ => None,
+        }
+    }
+
+    fn take(&mut self) {
+        self.in_flight += 1;
+        if let Some(ref mut remaining) = self.remaining {
+            *remaining = remaining.saturating_sub(1);
+        }
+    }
+}
+
+/// Permission to make one request. The slot is released when it is dropped.
+pub struct Permit {
+    limiter: RateLimiter,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let (ref lock, ref condvar) = *self.limiter.shared;
+        lock.lock().unwrap().in_flight -= 1;
+        condvar.notify_one();
+    }
+}
+
+impl RateLimiter {
+    pub fn new(max_concurrent: usize) -> RateLimiter {
+        RateLimiter {
+            shared: Arc::new((
+                Mutex::new(Budget {
+                    max_concurrent: max_concurrent.max(1),
+                    in_flight: 0,
+                    remaining: None,
+                    reset_at: None,
+                }),
+                Condvar::new(),
+            )),
+        }
+    }
+
+    /// Blocks until a request may be made, i.e. until fewer than
+    /// `max_concurrent` requests are in flight and the budget has not run
+    /// out, waiting for it to reset if it has.
+    pub fn acquire(&self) -> Permit {
+        let (ref lock, ref condvar) = *self.shared;
+        let mut budget = lock.lock().unwrap();
+        loop {
+            if budget.in_flight >= budget.max_concurrent {
+                budget = condvar.wait(budget).unwrap();
+            } else if let Some(wait) = budget.wait_for_reset(Utc::now()) {
+                budget = condvar.wait_timeout(budget, wait).unwrap().0;
+            } else {
+                break;
+            }
+        }
+        budget.take();
+        Permit {
+            limiter: self.clone(),
+        }
+    }
+
+    /// Like `acquire`, but returns `None` instead of blocking.
+    pub fn try_acquire(&self) -> Option<Permit> {
+        let (ref lock, _) = *self.shared;
+        let mut budget = lock.lock().unwrap();
+        if budget.in_flight >= budget.max_concurrent || budget.wait_for_reset(Utc::now()).is_some()
+        {
+            return None;
+        }
+        budget.take();
+        Some(Permit {
+            limiter: self.clone(),
+        })
+    }
+
+    /// Records the budget reported by Github in the headers of a response.
+    pub fn update(&self, headers: &HeaderMap) {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<u64>().ok())
+        };
+        let remaining = header("x-ratelimit-remaining");
+        let reset_at = header("x-ratelimit-reset").map(|reset| Utc.timestamp(reset as i64, 0));
+        if remaining.is_some() || reset_at.is_some() {
+            self.set_budget(remaining, reset_at);
+        }
+    }
+
+    pub fn set_budget(&self, remaining: Option<u64>, reset_at: Option<DateTime<Utc>>) {
+        let (ref lock, ref condvar) = *self.shared;
+        let mut budget = lock.lock().unwrap();
+        budget.remaining = remaining;
+        budget.reset_at = reset_at;
+        condvar.notify_all();
+    }
+
+    /// Requests left in the current window, as last reported by Github.
+    pub fn remaining(&self) -> Option<u64> {
+        self.shared.0.lock().unwrap().remaining
+    }
+
+    pub fn reset_at(&self) -> Option<DateTime<Utc>> {
+        self.shared.0.lock().unwrap().reset_at
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.shared.0.lock().unwrap().in_flight
+    }
+}
+
+/// How long to wait before retrying a request which was rate limited after
+/// `attempt` earlier retries, given the headers of its response: as long as
+/// `retry-after` says, not at all if the budget ran out as `acquire` waits for
+/// it to reset, or else backing off exponentially.
+pub fn retry_delay(headers: &HeaderMap, attempt: u32) -> Duration {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok())
+    };
+    if let Some(seconds) = header("retry-after") {
+        Duration::from_secs(seconds)
+    } else if header("x-ratelimit-remaining") == Some(0) {
+        Duration::from_secs(0)
+    } else {
+        let seconds = MIN_BACKOFF_SECONDS.saturating_mul(1 << attempt.min(16));
+        Duration::from_secs(seconds.min(MAX_BACKOFF_SECONDS))
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> RateLimiter {
+        RateLimiter::new(DEFAULT_MAX_CONCURRENT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+    use std::thread;
+
+    #[test]
+    fn test_concurrency() {
+        let limiter = RateLimiter::new(2);
+        let first = limiter.acquire();
+        let _second = limiter.acquire();
+        assert!(limiter.try_acquire().is_none());
+        let waiting = {
+            let limiter = limiter.clone();
+            thread::spawn(move || {
+                let _permit = limiter.acquire();
+            })
+        };
+        drop(first);
+        waiting.join().unwrap();
+        assert_eq!(limiter.in_flight(), 1);
+    }
+
+    #[test]
+    fn test_budget() {
+        let limiter = RateLimiter::default();
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("1"));
+        headers.insert("x-ratelimit-reset", HeaderValue::from_static("4102444800"));
+        limiter.update(&headers);
+        assert_eq!(limiter.remaining(), Some(1));
+        assert_eq!(
+            limiter.reset_at(),
+            Some(Utc.ymd(2100, 1, 1).and_hms(0, 0, 0))
+        );
+        let permit = limiter.try_acquire();
+        assert!(permit.is_some());
+        assert_eq!(limiter.remaining(), Some(0));
+        assert!(limiter.try_acquire().is_none());
+        limiter.set_budget(Some(0), Some(Utc::now() - chrono::Duration::seconds(1)));
+        assert!(limiter.try_acquire().is_some());
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_delay(&headers, 0), Duration::from_secs(60));
+        assert_eq!(retry_delay(&headers, 2), Duration::from_secs(240));
+        assert_eq!(retry_delay(&headers, 10), Duration::from_secs(900));
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_static("0"));
+        assert_eq!(retry_delay(&headers, 3), Duration::from_secs(0));
+        headers.insert("retry-after", HeaderValue::from_static("30"));
+        assert_eq!(retry_delay(&headers, 3), Duration::from_secs(30));
+    }
+}
@@ -8,4 +8,15 @@ pub struct Repo {
     pub clone_url: String,
     pub git_url: String,
     pub fork: bool,
+    #[serde(default)]
+    pub private: bool,
+    pub visibility: Option<String>,
+    pub default_branch: Option<String>,
+    #[serde(default)]
+    pub archived: bool,
+    #[serde(default)]
+    pub stargazers_count: u64,
+    pub language: Option<String>,
+    #[serde(default)]
+    pub topics: Vec<String>,
 }
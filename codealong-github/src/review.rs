@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use chrono::prelude::*;
+use chrono::DateTime;
+
+use crate::user::User;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReviewState {
+    Approved,
+    ChangesRequested,
+    Commented,
+    Dismissed,
+    Pending,
+}
+
+/// A review of a pull request, as listed by `/pulls/{number}/reviews`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(test, serde(deny_unknown_fields))]
+pub struct Review {
+    pub id: u64,
+    pub node_id: String,
+    pub user: Option<User>,
+    pub body: Option<String>,
+    pub state: ReviewState,
+    pub html_url: String,
+    pub pull_request_url: String,
+    pub commit_id: Option<String>,
+    pub submitted_at: Option<DateTime<Utc>>,
+    pub author_association: String,
+    #[serde(rename = "_links")]
+    pub links: HashMap<String, Link>,
+}
+
+/// A comment on the diff of a pull request, as listed by
+/// `/pulls/{number}/comments`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(test, serde(deny_unknown_fields))]
+pub struct ReviewComment {
+    pub id: u64,
+    pub node_id: String,
+    pub url: String,
+    pub pull_request_review_id: Option<u64>,
+    pub diff_hunk: String,
+    pub path: String,
+    pub position: Option<u64>,
+    pub original_position: Option<u64>,
+    pub commit_id: String,
+    pub original_commit_id: String,
+    pub in_reply_to_id: Option<u64>,
+    pub user: Option<User>,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub html_url: String,
+    pub pull_request_url: String,
+    pub author_association: String,
+    pub start_line: Option<u64>,
+    pub original_start_line: Option<u64>,
+    pub start_side: Option<String>,
+    pub line: Option<u64>,
+    pub original_line: Option<u64>,
+    pub side: Option<String>,
+    pub subject_type: Option<String>,
+    #[serde(rename = "_links")]
+    pub links: HashMap<String, Link>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(test, serde(deny_unknown_fields))]
+pub struct Link {
+    pub href: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_deserialize_reviews() {
+        let json = fs::read_to_string("./fixtures/reviews.json").unwrap();
+        let reviews: Vec<Review> = serde_json::from_str(&json).unwrap();
+        assert_eq!(reviews.len(), 2);
+        assert_eq!(reviews[0].state, ReviewState::Approved);
+        assert_eq!(reviews[1].state, ReviewState::ChangesRequested);
+        assert_eq!(reviews[0].user.as_ref().unwrap().login, "octocat");
+    }
+
+    #[test]
+    fn test_deserialize_review_comments() {
+        let json = fs::read_to_string("./fixtures/review_comments.json").unwrap();
+        let comments: Vec<ReviewComment> = serde_json::from_str(&json).unwrap();
+        assert_eq!(comments.len(), 1);
+        assert_eq!(comments[0].path, "file1.txt");
+        assert_eq!(comments[0].line, Some(2));
+    }
+}
@@ -1,5 +1,32 @@
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(test, serde(deny_unknown_fields))]
 pub struct Team {
     pub id: u64,
+    pub node_id: Option<String>,
+    pub url: Option<String>,
+    pub html_url: Option<String>,
     pub name: String,
+    pub slug: Option<String>,
+    pub description: Option<String>,
+    pub privacy: Option<String>,
+    pub notification_setting: Option<String>,
+    pub permission: Option<String>,
+    pub members_url: Option<String>,
+    pub repositories_url: Option<String>,
+    pub parent: Option<Box<Team>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_deserialize_teams() {
+        let json = fs::read_to_string("./fixtures/teams.json").unwrap();
+        let teams: Vec<Team> = serde_json::from_str(&json).unwrap();
+        assert_eq!(teams.len(), 2);
+        assert_eq!(teams[1].slug, Some("platform-ops".to_owned()));
+        assert_eq!(teams[1].parent.as_ref().unwrap().name, "Justice League");
+    }
 }
@@ -0,0 +1,266 @@
+use chrono::prelude::*;
+use chrono::DateTime;
+
+use crate::app::App;
+use crate::review::Review;
+use crate::team::Team;
+use crate::user::User;
+
+/// An entry of the timeline of an issue or pull request, as listed by
+/// `/issues/{number}/timeline`. The fields of an event depend on its kind,
+/// given by its `event` field. Kinds without a model of their own are
+/// `Other`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum TimelineEvent {
+    Assigned(AssignEvent),
+    Unassigned(AssignEvent),
+    Closed(IssueEvent),
+    Commented(IssueComment),
+    Committed(TimelineCommit),
+    ConvertToDraft(IssueEvent),
+    #[serde(rename = "cross-referenced")]
+    CrossReferenced(CrossReferenceEvent),
+    HeadRefDeleted(IssueEvent),
+    HeadRefForcePushed(IssueEvent),
+    HeadRefRestored(IssueEvent),
+    Labeled(LabelEvent),
+    Unlabeled(LabelEvent),
+    Locked(IssueEvent),
+    Unlocked(IssueEvent),
+    Mentioned(IssueEvent),
+    Merged(IssueEvent),
+    ReadyForReview(IssueEvent),
+    Referenced(IssueEvent),
+    Renamed(RenameEvent),
+    Reopened(IssueEvent),
+    ReviewRequested(ReviewRequestEvent),
+    ReviewRequestRemoved(ReviewRequestEvent),
+    Reviewed(Review),
+    Subscribed(IssueEvent),
+    Unsubscribed(IssueEvent),
+    #[serde(other)]
+    Other,
+}
+
+/// Fields shared by most kinds of timeline events.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(test, serde(deny_unknown_fields))]
+pub struct IssueEvent {
+    pub id: u64,
+    pub node_id: String,
+    pub url: String,
+    pub actor: Option<User>,
+    pub commit_id: Option<String>,
+    pub commit_url: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub performed_via_github_app: Option<App>,
+    pub state_reason: Option<String>,
+    pub lock_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(test, serde(deny_unknown_fields))]
+pub struct AssignEvent {
+    pub id: u64,
+    pub node_id: String,
+    pub url: String,
+    pub actor: Option<User>,
+    pub commit_id: Option<String>,
+    pub commit_url: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub performed_via_github_app: Option<App>,
+    pub assignee: User,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(test, serde(deny_unknown_fields))]
+pub struct LabelEvent {
+    pub id: u64,
+    pub node_id: String,
+    pub url: String,
+    pub actor: Option<User>,
+    pub commit_id: Option<String>,
+    pub commit_url: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub performed_via_github_app: Option<App>,
+    pub label: Label,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(test, serde(deny_unknown_fields))]
+pub struct Label {
+    pub name: String,
+    pub color: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(test, serde(deny_unknown_fields))]
+pub struct RenameEvent {
+    pub id: u64,
+    pub node_id: String,
+    pub url: String,
+    pub actor: Option<User>,
+    pub commit_id: Option<String>,
+    pub commit_url: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub performed_via_github_app: Option<App>,
+    pub rename: Rename,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(test, serde(deny_unknown_fields))]
+pub struct Rename {
+    pub from: String,
+    pub to: String,
+}
+
+/// A review requested from, or no longer requested from, either a person or
+/// a team.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(test, serde(deny_unknown_fields))]
+pub struct ReviewRequestEvent {
+    pub id: u64,
+    pub node_id: String,
+    pub url: String,
+    pub actor: Option<User>,
+    pub commit_id: Option<String>,
+    pub commit_url: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub performed_via_github_app: Option<App>,
+    pub review_requester: Option<User>,
+    pub requested_reviewer: Option<User>,
+    pub requested_team: Option<Team>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(test, serde(deny_unknown_fields))]
+pub struct IssueComment {
+    pub id: u64,
+    pub node_id: String,
+    pub url: String,
+    pub html_url: String,
+    pub issue_url: String,
+    pub actor: Option<User>,
+    pub user: Option<User>,
+    pub body: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub author_association: String,
+    pub performed_via_github_app: Option<App>,
+}
+
+/// A commit pushed to a pull request. Unlike other events it carries the git
+/// author and committer rather than Github users.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(test, serde(deny_unknown_fields))]
+pub struct TimelineCommit {
+    pub sha: String,
+    pub node_id: String,
+    pub url: String,
+    pub html_url: String,
+    pub author: GitActor,
+    pub committer: GitActor,
+    pub tree: CommitRef,
+    pub message: String,
+    pub parents: Vec<CommitRef>,
+    pub verification: Option<Verification>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(test, serde(deny_unknown_fields))]
+pub struct GitActor {
+    pub name: String,
+    pub email: String,
+    pub date: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(test, serde(deny_unknown_fields))]
+pub struct CommitRef {
+    pub sha: String,
+    pub url: String,
+    pub html_url: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(test, serde(deny_unknown_fields))]
+pub struct Verification {
+    pub verified: bool,
+    pub reason: String,
+    pub signature: Option<String>,
+    pub payload: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(test, serde(deny_unknown_fields))]
+pub struct CrossReferenceEvent {
+    pub actor: Option<User>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub source: CrossReferenceSource,
+}
+
+/// The issue or pull request that referenced this one. The referencing issue
+/// is kept as raw JSON since it embeds a whole issue.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(test, serde(deny_unknown_fields))]
+pub struct CrossReferenceSource {
+    #[serde(rename = "type")]
+    pub source_type: String,
+    pub issue: serde_json::Value,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_deserialize_timeline() {
+        let json = fs::read_to_string("./fixtures/timeline.json").unwrap();
+        let events: Vec<TimelineEvent> = serde_json::from_str(&json).unwrap();
+        assert_eq!(events.len(), 9);
+        match events[0] {
+            TimelineEvent::Committed(ref commit) => {
+                assert_eq!(commit.author.name, "Monalisa Octocat")
+            }
+            ref event => panic!("unexpected event {:?}", event),
+        }
+        match events[1] {
+            TimelineEvent::Labeled(ref event) => assert_eq!(event.label.name, "bug"),
+            ref event => panic!("unexpected event {:?}", event),
+        }
+        match events[2] {
+            TimelineEvent::ReviewRequested(ref event) => {
+                assert_eq!(event.requested_reviewer.as_ref().unwrap().login, "hubot")
+            }
+            ref event => panic!("unexpected event {:?}", event),
+        }
+        match events[3] {
+            TimelineEvent::Reviewed(ref review) => assert_eq!(review.id, 80),
+            ref event => panic!("unexpected event {:?}", event),
+        }
+        match events[4] {
+            TimelineEvent::Commented(ref comment) => {
+                assert_eq!(comment.body, Some("Me too".to_owned()))
+            }
+            ref event => panic!("unexpected event {:?}", event),
+        }
+        match events[5] {
+            TimelineEvent::Renamed(ref event) => assert_eq!(event.rename.to, "Fix the bug"),
+            ref event => panic!("unexpected event {:?}", event),
+        }
+        match events[6] {
+            TimelineEvent::CrossReferenced(ref event) => {
+                assert_eq!(event.source.source_type, "issue")
+            }
+            ref event => panic!("unexpected event {:?}", event),
+        }
+        match events[7] {
+            TimelineEvent::Merged(ref event) => assert!(event.commit_id.is_some()),
+            ref event => panic!("unexpected event {:?}", event),
+        }
+        assert_eq!(events[8], TimelineEvent::Other);
+    }
+}
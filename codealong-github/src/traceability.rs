@@ -0,0 +1,76 @@
+use std::collections::HashSet;
+
+use slog::Logger;
+
+use crate::client::Client;
+use crate::error::Result;
+use crate::pull_request::PullRequest;
+
+/// Traces the commits of a repository back to the merged pull requests they
+/// landed through, to find commits pushed without review.
+pub struct Traceability<'client> {
+    client: &'client Client,
+    github_name: String,
+    merge_commits: HashSet<String>,
+}
+
+impl<'client> Traceability<'client> {
+    /// Lists the pull requests of the repository named `github_name` and
+    /// remembers the commits the merged ones landed as.
+    pub fn fetch(
+        client: &'client Client,
+        github_name: &str,
+        logger: &Logger,
+    ) -> Result<Traceability<'client>> {
+        let merge_commits = client
+            .pull_requests(github_name)
+            .iter(logger)
+            .filter(|pr| pr.merged_at.is_some())
+            .filter_map(|pr| pr.merge_commit_sha)
+            .collect();
+        Ok(Traceability::new(client, github_name, merge_commits))
+    }
+
+    pub fn new(
+        client: &'client Client,
+        github_name: &str,
+        merge_commits: HashSet<String>,
+    ) -> Traceability<'client> {
+        Traceability {
+            client,
+            github_name: github_name.to_owned(),
+            merge_commits,
+        }
+    }
+
+    /// Whether the commit `sha` landed without a merged pull request. Only
+    /// the last commit of a rebase merge is known from the pull request, so
+    /// Github is asked about commits which are not a known merge commit.
+    pub fn is_direct_push(&self, sha: &str) -> Result<bool> {
+        if self.merge_commits.contains(sha) {
+            return Ok(false);
+        }
+        let url = self
+            .client
+            .url(&format!("repos/{}/commits/{}/pulls", self.github_name, sha));
+        let pull_requests: Vec<PullRequest> = self.client.get(&url)?.json()?;
+        Ok(!pull_requests.iter().any(|pr| pr.merged_at.is_some()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_merge_commit() {
+        let client = Client::public();
+        let merge_commits = vec!["6113728f27ae82c7b1a177c8d03f9e96e0adf246".to_owned()]
+            .into_iter()
+            .collect();
+        let traceability = Traceability::new(&client, "Codertocat/Hello-World", merge_commits);
+        assert!(!traceability
+            .is_direct_push("6113728f27ae82c7b1a177c8d03f9e96e0adf246")
+            .unwrap());
+    }
+}
@@ -0,0 +1,243 @@
+use chrono::prelude::*;
+use chrono::DateTime;
+use openssl::hash::MessageDigest;
+use openssl::memcmp;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+
+use crate::check_run::CheckRun;
+use crate::deployment::Deployment;
+use crate::error::Result;
+use crate::pull_request::PullRequest;
+use crate::repo::Repo;
+use crate::review::{Review, ReviewComment};
+use crate::user::User;
+
+/// A webhook delivery, parsed according to the event name Github sends in
+/// the `X-GitHub-Event` header. Events without a payload type of their own
+/// are kept as raw JSON. Payloads are boxed since they differ widely in size.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WebhookEvent {
+    CheckRun(Box<CheckRunEvent>),
+    Deployment(Box<DeploymentEvent>),
+    Ping(Box<PingEvent>),
+    PullRequest(Box<PullRequestEvent>),
+    PullRequestReview(Box<PullRequestReviewEvent>),
+    PullRequestReviewComment(Box<PullRequestReviewCommentEvent>),
+    Push(Box<PushEvent>),
+    Other(String, serde_json::Value),
+}
+
+impl WebhookEvent {
+    pub fn parse(event: &str, body: &[u8]) -> Result<WebhookEvent> {
+        Ok(match event {
+            "check_run" => WebhookEvent::CheckRun(Box::new(serde_json::from_slice(body)?)),
+            "deployment" => WebhookEvent::Deployment(Box::new(serde_json::from_slice(body)?)),
+            "ping" => WebhookEvent::Ping(Box::new(serde_json::from_slice(body)?)),
+            "pull_request" => WebhookEvent::PullRequest(Box::new(serde_json::from_slice(body)?)),
+            "pull_request_review" => {
+                WebhookEvent::PullRequestReview(Box::new(serde_json::from_slice(body)?))
+            }
+            "pull_request_review_comment" => {
+                WebhookEvent::PullRequestReviewComment(Box::new(serde_json::from_slice(body)?))
+            }
+            "push" => WebhookEvent::Push(Box::new(serde_json::from_slice(body)?)),
+            _ => WebhookEvent::Other(event.to_owned(), serde_json::from_slice(body)?),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CheckRunEvent {
+    pub action: String,
+    pub check_run: CheckRun,
+    pub repository: Repo,
+    pub sender: User,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeploymentEvent {
+    pub action: Option<String>,
+    pub deployment: Deployment,
+    pub repository: Repo,
+    pub sender: User,
+}
+
+/// Sent when a webhook is created.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PingEvent {
+    pub zen: String,
+    pub hook_id: u64,
+    pub repository: Option<Repo>,
+    pub sender: Option<User>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PullRequestEvent {
+    pub action: String,
+    pub number: u64,
+    pub pull_request: PullRequest,
+    pub repository: Repo,
+    pub sender: User,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PullRequestReviewEvent {
+    pub action: String,
+    pub review: Review,
+    pub pull_request: PullRequest,
+    pub repository: Repo,
+    pub sender: User,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PullRequestReviewCommentEvent {
+    pub action: String,
+    pub comment: ReviewComment,
+    pub pull_request: PullRequest,
+    pub repository: Repo,
+    pub sender: User,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PushEvent {
+    #[serde(rename = "ref")]
+    pub reference: String,
+    pub before: String,
+    pub after: String,
+    pub created: bool,
+    pub deleted: bool,
+    pub forced: bool,
+    pub commits: Vec<PushCommit>,
+    pub head_commit: Option<PushCommit>,
+    pub pusher: PushAuthor,
+    pub repository: Repo,
+    pub sender: User,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PushCommit {
+    pub id: String,
+    pub message: String,
+    pub timestamp: DateTime<Utc>,
+    pub url: String,
+    pub distinct: bool,
+    pub author: PushAuthor,
+    pub committer: PushAuthor,
+    #[serde(default)]
+    pub added: Vec<String>,
+    #[serde(default)]
+    pub removed: Vec<String>,
+    #[serde(default)]
+    pub modified: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PushAuthor {
+    pub name: String,
+    pub email: Option<String>,
+    pub username: Option<String>,
+}
+
+/// Signs `body` with `secret` the way Github signs webhook deliveries, i.e.
+/// the value of the `X-Hub-Signature-256` header.
+pub fn sign_payload(secret: &[u8], body: &[u8]) -> Result<String> {
+    Ok(format!(
+        "sha256={}",
+        hex(&hmac(MessageDigest::sha256(), secret, body)?)
+    ))
+}
+
+/// Checks the signature of a webhook delivery, given as either the
+/// `X-Hub-Signature-256` or the legacy, SHA-1 based, `X-Hub-Signature`
+/// header. The comparison is constant time.
+pub fn verify_signature(secret: &[u8], body: &[u8], signature: &str) -> bool {
+    let (digest, expected) = if let Some(expected) = signature.strip_prefix("sha256=") {
+        (MessageDigest::sha256(), expected)
+    } else if let Some(expected) = signature.strip_prefix("sha1=") {
+        (MessageDigest::sha1(), expected)
+    } else {
+        return false;
+    };
+    match hmac(digest, secret, body) {
+        Ok(actual) => {
+            let actual = hex(&actual);
+            actual.len() == expected.len()
+                && memcmp::eq(actual.as_bytes(), expected.to_lowercase().as_bytes())
+        }
+        Err(_) => false,
+    }
+}
+
+fn hmac(digest: MessageDigest, secret: &[u8], body: &[u8]) -> Result<Vec<u8>> {
+    let key = PKey::hmac(secret)?;
+    let mut signer = Signer::new(digest, &key)?;
+    signer.update(body)?;
+    Ok(signer.sign_to_vec()?)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const SECRET: &[u8] = b"It's a Secret to Everybody";
+    const BODY: &[u8] = b"Hello, World!";
+
+    #[test]
+    fn test_sign_payload() {
+        assert_eq!(
+            sign_payload(SECRET, BODY).unwrap(),
+            "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17"
+        );
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let signature = sign_payload(SECRET, BODY).unwrap();
+        assert!(verify_signature(SECRET, BODY, &signature));
+        assert!(verify_signature(
+            SECRET,
+            BODY,
+            &signature.to_uppercase().replace("SHA256=", "sha256=")
+        ));
+        assert!(!verify_signature(b"wrong", BODY, &signature));
+        assert!(!verify_signature(SECRET, b"Hello, World?", &signature));
+        assert!(!verify_signature(SECRET, BODY, &signature[..20]));
+        assert!(!verify_signature(SECRET, BODY, "md5=abc"));
+        let sha1 = format!(
+            "sha1={}",
+            hex(&hmac(MessageDigest::sha1(), SECRET, BODY).unwrap())
+        );
+        assert!(verify_signature(SECRET, BODY, &sha1));
+    }
+
+    #[test]
+    fn test_parse() {
+        let body = fs::read("./fixtures/webhooks/pull_request.json").unwrap();
+        match WebhookEvent::parse("pull_request", &body).unwrap() {
+            WebhookEvent::PullRequest(event) => {
+                assert_eq!(event.action, "opened");
+                assert_eq!(event.pull_request.number, 2);
+                assert_eq!(event.repository.full_name, "Codertocat/Hello-World");
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+        let body = fs::read("./fixtures/webhooks/push.json").unwrap();
+        match WebhookEvent::parse("push", &body).unwrap() {
+            WebhookEvent::Push(event) => {
+                assert_eq!(event.reference, "refs/heads/master");
+                assert_eq!(event.commits[0].modified, vec!["README.md".to_owned()]);
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+        match WebhookEvent::parse("star", b"{\"action\": \"created\"}").unwrap() {
+            WebhookEvent::Other(name, _) => assert_eq!(name, "star"),
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+}
@@ -0,0 +1,492 @@
+use chrono::prelude::*;
+use chrono::DateTime;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::iter::FromIterator;
+
+use codealong::{
+    metric_versions, AnalyzedDiff, Calendar, Config, Event, Hotspots, MetricVersions, Person,
+    PersonSnapshots, ReviewStats, Risk, SloSubject,
+};
+
+use crate::error::Result;
+use crate::merge_request::MergeRequest;
+use crate::note::Note;
+
+/// A merge request indexed as a `pull_request` event, with the same fields
+/// as those of Github pull requests, so that dashboards need not care which
+/// forge the data came from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnalyzedMergeRequest {
+    timestamp: DateTime<Utc>,
+
+    normalized_author: Person,
+
+    /// Id of the normalized author, shared with their commits.
+    author_id: String,
+
+    #[serde(flatten)]
+    pr: PullRequestFields,
+
+    #[serde(flatten)]
+    pub diff: Option<AnalyzedDiff>,
+
+    pub time_to_resolve: Option<i64>,
+
+    /// Working seconds between creation and merge according to the
+    /// configured business calendar.
+    pub business_time_to_resolve: Option<i64>,
+
+    pub business_days_to_resolve: Option<f64>,
+
+    #[serde(flatten, default)]
+    pub review_stats: ReviewStats,
+
+    /// Risk of the changes when assessed against the hotspots of the repo.
+    #[serde(default)]
+    pub risk: Option<Risk>,
+
+    /// References to tickets and issues in the title and body, e.g.
+    /// `ABC-123`.
+    #[serde(default)]
+    pub issues: Vec<String>,
+
+    #[serde(default)]
+    pub metric_versions: MetricVersions,
+}
+
+/// How `mr` was reviewed according to its `notes`. GitLab has no reviews as
+/// such, so each approval and request for changes, recorded as system
+/// notes, counts as a review, and comments of others than the author, of
+/// which the earliest may predate any approval, count towards the first
+/// review.
+pub fn review_stats(mr: &MergeRequest, notes: &[Note], config: &Config) -> ReviewStats {
+    let author = &mr.author.username;
+    let mut stats = ReviewStats::default();
+    let mut reviewed_at = vec![];
+    for note in notes {
+        if &note.author.username == author {
+            continue;
+        }
+        if note.system {
+            match note.body.as_str() {
+                "approved this merge request" => stats.approvals += 1,
+                "requested changes" => stats.change_requests += 1,
+                _ => continue,
+            }
+            stats.reviews += 1;
+            let reviewer = config
+                .person_for_gitlab_username_at(&note.author.username, &note.created_at)
+                .id;
+            if !stats.reviewers.contains(&reviewer) {
+                stats.reviewers.push(reviewer);
+            }
+        } else if note.note_type.as_ref().map(|t| &**t) == Some("DiffNote") {
+            stats.review_comments += 1;
+        }
+        reviewed_at.push(note.created_at);
+    }
+    stats.first_reviewed_at = reviewed_at.into_iter().min();
+    if let Some(first_reviewed_at) = stats.first_reviewed_at {
+        stats.time_to_first_review = Some((first_reviewed_at - mr.created_at).num_seconds());
+        stats.business_time_to_first_review = Some(
+            config
+                .calendar()
+                .business_seconds(&mr.created_at, &first_reviewed_at),
+        );
+    }
+    stats
+}
+
+/// The fields of a merge request named after their Github equivalents.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct PullRequestFields {
+    id: u64,
+    number: u64,
+    forge: String,
+    base: RefFields,
+    head: RefFields,
+    html_url: Option<String>,
+    state: Option<String>,
+    title: Option<String>,
+    body: Option<String>,
+    user: UserFields,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    closed_at: Option<DateTime<Utc>>,
+    merged_at: Option<DateTime<Utc>>,
+    merge_commit_sha: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct RefFields {
+    sha: Option<String>,
+    #[serde(rename = "ref")]
+    reference: String,
+    repo: Option<RepoFields>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct RepoFields {
+    full_name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct UserFields {
+    id: u64,
+    login: String,
+    name: Option<String>,
+}
+
+impl PullRequestFields {
+    fn new(mr: &MergeRequest, project: &str) -> PullRequestFields {
+        let repo = |project_id| {
+            // the path of a fork's project is not listed with the merge
+            // request
+            if project_id == mr.target_project_id {
+                Some(RepoFields {
+                    full_name: project.to_owned(),
+                })
+            } else {
+                None
+            }
+        };
+        let diff_refs = mr.diff_refs.as_ref();
+        PullRequestFields {
+            id: mr.id,
+            number: mr.iid,
+            forge: "gitlab".to_owned(),
+            base: RefFields {
+                sha: diff_refs.map(|refs| refs.base_sha.clone()),
+                reference: mr.target_branch.clone(),
+                repo: repo(mr.target_project_id),
+            },
+            head: RefFields {
+                sha: diff_refs
+                    .map(|refs| refs.head_sha.clone())
+                    .or_else(|| mr.sha.clone()),
+                reference: mr.source_branch.clone(),
+                repo: repo(mr.source_project_id),
+            },
+            html_url: Some(mr.web_url.clone()),
+            // Github only distinguishes open and closed pull requests
+            state: Some(
+                if mr.state == "opened" {
+                    "open"
+                } else {
+                    "closed"
+                }
+                .to_owned(),
+            ),
+            title: Some(mr.title.clone()),
+            body: mr.description.clone(),
+            user: UserFields {
+                id: mr.author.id,
+                login: mr.author.username.clone(),
+                name: mr.author.name.clone(),
+            },
+            created_at: mr.created_at,
+            updated_at: mr.updated_at,
+            closed_at: mr.closed_at.or(mr.merged_at),
+            merged_at: mr.merged_at,
+            merge_commit_sha: mr.landed_sha().map(|sha| sha.to_owned()),
+        }
+    }
+}
+
+impl AnalyzedMergeRequest {
+    /// `project` is the path of the project the merge request targets.
+    pub fn new(
+        mr: &MergeRequest,
+        project: &str,
+        diff: Option<AnalyzedDiff>,
+        normalized_author: Person,
+        calendar: &Calendar,
+    ) -> AnalyzedMergeRequest {
+        AnalyzedMergeRequest {
+            timestamp: mr.merged_at.unwrap_or(mr.updated_at),
+            author_id: normalized_author.id.clone(),
+            normalized_author,
+            diff,
+            time_to_resolve: mr
+                .merged_at
+                .map(|merged_at| (merged_at - mr.created_at).num_seconds()),
+            business_time_to_resolve: mr
+                .merged_at
+                .map(|merged_at| calendar.business_seconds(&mr.created_at, &merged_at)),
+            business_days_to_resolve: mr
+                .merged_at
+                .map(|merged_at| calendar.business_days(&mr.created_at, &merged_at)),
+            review_stats: ReviewStats::default(),
+            risk: None,
+            issues: vec![],
+            metric_versions: metric_versions(&[
+                codealong::LINES_CHANGED,
+                codealong::TIME_TO_RESOLVE,
+                codealong::BUSINESS_TIME_TO_RESOLVE,
+                codealong::BUSINESS_DAYS_TO_RESOLVE,
+            ]),
+            pr: PullRequestFields::new(mr, project),
+        }
+    }
+
+    /// Assesses the risk of the changes of this merge request, if they were
+    /// analyzed.
+    pub fn assess_risk(&mut self, hotspots: &Hotspots) {
+        self.risk = self.diff.as_ref().map(|diff| Risk::assess(diff, hotspots));
+    }
+
+    /// Attaches how this merge request was reviewed.
+    pub fn set_review_stats(&mut self, review_stats: ReviewStats) {
+        self.review_stats = review_stats;
+        self.metric_versions.extend(metric_versions(&[
+            codealong::TIME_TO_FIRST_REVIEW,
+            codealong::BUSINESS_TIME_TO_FIRST_REVIEW,
+        ]));
+    }
+
+    pub fn updated_at(&self) -> &DateTime<Utc> {
+        &self.pr.updated_at
+    }
+
+    /// Names the repos of the base and head after following the
+    /// `repo_aliases` of `config`, so that the merge requests of a renamed repo are
+    /// not split across its names.
+    pub fn alias_repos(&mut self, config: &Config) {
+        let repos = self
+            .pr
+            .base
+            .repo
+            .iter_mut()
+            .chain(self.pr.head.repo.iter_mut());
+        for repo in repos {
+            repo.full_name = config.canonical_repo_name(&repo.full_name).to_owned();
+        }
+    }
+
+    /// Extracts the references to tickets and issues of the title and body,
+    /// before they are stored.
+    pub fn extract_issues(&mut self, config: &Config) -> Result<()> {
+        let text: Vec<&str> = self
+            .pr
+            .title
+            .iter()
+            .chain(self.pr.body.iter())
+            .map(|t| &**t)
+            .collect();
+        self.issues = config.issue_references.extract(&text.join("\n\n"))?;
+        Ok(())
+    }
+
+    /// Truncates, hashes or drops the title and body according to the
+    /// `summary_max_length` and `message_storage` of `config`.
+    pub fn store_messages(&mut self, config: &Config) {
+        self.pr.title = self.pr.title.take().and_then(|t| config.stored_summary(&t));
+        self.pr.body = self.pr.body.take().and_then(|b| config.stored_message(&b));
+    }
+
+    /// Records the author's work on this merge request in `snapshots`.
+    pub fn add_to_snapshots(&self, snapshots: &mut PersonSnapshots) {
+        if let Some(ref repo) = self.pr.base.repo {
+            snapshots.add_pull_request(
+                &self.normalized_author,
+                &self.timestamp,
+                &repo.full_name,
+                &self.pr.head.reference,
+                self.pr.number,
+                self.time_to_resolve,
+            );
+        }
+    }
+}
+
+impl SloSubject for AnalyzedMergeRequest {
+    fn diff(&self) -> Option<&AnalyzedDiff> {
+        self.diff.as_ref()
+    }
+
+    fn time_to_resolve(&self) -> Option<i64> {
+        self.time_to_resolve
+    }
+
+    fn business_time_to_resolve(&self) -> Option<i64> {
+        self.business_time_to_resolve
+    }
+
+    fn review_stats(&self) -> &ReviewStats {
+        &self.review_stats
+    }
+
+    fn owner(&self) -> &Person {
+        &self.normalized_author
+    }
+
+    fn url(&self) -> Option<String> {
+        self.pr.html_url.clone()
+    }
+
+    fn repo(&self) -> Option<String> {
+        self.pr.base.repo.as_ref().map(|r| r.full_name.clone())
+    }
+}
+
+impl Event for AnalyzedMergeRequest {
+    fn timestamp(&self) -> &DateTime<Utc> {
+        &self.timestamp
+    }
+
+    /// Prefixed so as not to collide with the ids of Github pull requests.
+    fn id(&self) -> Cow<str> {
+        format!("gitlab-{}", self.pr.id).into()
+    }
+
+    fn event_type(&self) -> &str {
+        "pull_request"
+    }
+
+    fn tags(&self) -> HashSet<String> {
+        if let Some(ref diff) = self.diff {
+            HashSet::from_iter(diff.tag_stats.keys().map(|s| s.to_owned()))
+        } else {
+            HashSet::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codealong::MessageStorage;
+
+    #[test]
+    fn test_github_fields() {
+        let merge_requests: Vec<MergeRequest> =
+            serde_json::from_str(include_str!("../fixtures/merge_requests.json")).unwrap();
+        let config = Config::default();
+        let mr = &merge_requests[0];
+        let analyzed = AnalyzedMergeRequest::new(
+            mr,
+            "acme/uploader",
+            None,
+            config.person_for_gitlab_username_at(&mr.author.username, &mr.created_at),
+            &config.calendar(),
+        );
+        assert_eq!(analyzed.id(), "gitlab-84512");
+        assert_eq!(analyzed.event_type(), "pull_request");
+        assert_eq!(analyzed.time_to_resolve, Some(193764));
+
+        let json = serde_json::to_value(&analyzed).unwrap();
+        assert_eq!(json["number"], 42);
+        assert_eq!(json["state"], "closed");
+        assert_eq!(json["user"]["login"], "jdoe");
+        assert_eq!(json["base"]["ref"], "master");
+        assert_eq!(json["base"]["repo"]["full_name"], "acme/uploader");
+        assert_eq!(json["head"]["ref"], "retry-uploads");
+        assert_eq!(
+            json["merge_commit_sha"],
+            "8a3a7c0b4f1e2d3c4b5a69788796a5b4c3d2e1f0"
+        );
+        assert_eq!(json["normalized_author"]["id"], "jdoe");
+        assert_eq!(json["author_id"], "jdoe");
+    }
+
+    #[test]
+    fn test_review_stats() {
+        let merge_requests: Vec<MergeRequest> =
+            serde_json::from_str(include_str!("../fixtures/merge_requests.json")).unwrap();
+        let notes: Vec<Note> =
+            serde_json::from_str(include_str!("../fixtures/notes.json")).unwrap();
+        let config = Config::default();
+        let mut mr = merge_requests[0].clone();
+        mr.author.username = "mlee".to_owned();
+        let stats = review_stats(&mr, &notes, &config);
+        assert_eq!(stats.reviews, 1);
+        assert_eq!(stats.approvals, 1);
+        assert_eq!(stats.review_comments, 1);
+        assert_eq!(stats.reviewers, vec!["asmith"]);
+        assert_eq!(stats.first_reviewed_at, Some(notes[0].created_at));
+        assert_eq!(
+            stats.time_to_first_review,
+            Some((notes[0].created_at - mr.created_at).num_seconds())
+        );
+
+        // comments of the author are not reviews
+        mr.author.username = "jdoe".to_owned();
+        let stats = review_stats(&mr, &notes, &config);
+        assert_eq!(stats.review_comments, 0);
+        assert_eq!(stats.first_reviewed_at, Some(notes[1].created_at));
+    }
+
+    #[test]
+    fn test_alias_repos() {
+        let merge_requests: Vec<MergeRequest> =
+            serde_json::from_str(include_str!("../fixtures/merge_requests.json")).unwrap();
+        let mut config = Config::default();
+        config
+            .repo_aliases
+            .insert("acme/uploader".to_owned(), "acme/uploads".to_owned());
+        let mr = &merge_requests[0];
+        let mut analyzed = AnalyzedMergeRequest::new(
+            mr,
+            "acme/uploader",
+            None,
+            config.person_for_gitlab_username_at(&mr.author.username, &mr.created_at),
+            &config.calendar(),
+        );
+        analyzed.alias_repos(&config);
+        assert_eq!(analyzed.repo(), Some("acme/uploads".to_owned()));
+        let json = serde_json::to_value(&analyzed).unwrap();
+        assert_eq!(json["head"]["repo"]["full_name"], "acme/uploads");
+    }
+
+    #[test]
+    fn test_fork_head() {
+        let merge_requests: Vec<MergeRequest> =
+            serde_json::from_str(include_str!("../fixtures/merge_requests.json")).unwrap();
+        let config = Config::default();
+        let mr = &merge_requests[1];
+        let analyzed = AnalyzedMergeRequest::new(
+            mr,
+            "acme/uploader",
+            None,
+            config.person_for_gitlab_username_at(&mr.author.username, &mr.created_at),
+            &config.calendar(),
+        );
+        let json = serde_json::to_value(&analyzed).unwrap();
+        assert_eq!(json["state"], "open");
+        assert_eq!(json["head"]["repo"], serde_json::Value::Null);
+        assert_eq!(analyzed.time_to_resolve, None);
+    }
+
+    #[test]
+    fn test_store_messages() {
+        let merge_requests: Vec<MergeRequest> =
+            serde_json::from_str(include_str!("../fixtures/merge_requests.json")).unwrap();
+        let config = Config {
+            summary_max_length: Some(6),
+            message_storage: MessageStorage::Drop,
+            ..Config::default()
+        };
+        let mr = &merge_requests[0];
+        let mut analyzed = AnalyzedMergeRequest::new(
+            mr,
+            "acme/uploader",
+            None,
+            config.person_for_gitlab_username_at(&mr.author.username, &mr.created_at),
+            &config.calendar(),
+        );
+        analyzed.store_messages(&config);
+        assert_eq!(analyzed.pr.title, None);
+        assert_eq!(analyzed.pr.body, None);
+
+        let config = Config {
+            summary_max_length: Some(6),
+            ..Config::default()
+        };
+        analyzed.pr.title = Some(mr.title.clone());
+        analyzed.pr.body = mr.description.clone();
+        analyzed.store_messages(&config);
+        assert_eq!(analyzed.pr.title, Some("Retry…".to_owned()));
+        assert_eq!(analyzed.pr.body, mr.description);
+    }
+}
@@ -0,0 +1,150 @@
+use reqwest::{RequestBuilder, Response};
+use slog::Logger;
+use std::env;
+
+use crate::cursor::Cursor;
+use crate::error::{ErrorKind, ErrorPayload, Result};
+use crate::merge_request::MergeRequest;
+use crate::note::Note;
+
+const DEFAULT_URL: &str = "https://gitlab.com";
+
+/// Very basic wrapper around reqwest to interact with the v4 API of a GitLab
+/// instance
+#[derive(Debug, Clone)]
+pub struct Client {
+    url: String,
+    token: Option<String>,
+}
+
+impl Client {
+    /// `url` is the root of the instance, e.g. `https://gitlab.example.com`.
+    pub fn new(url: &str, token: String) -> Client {
+        Client {
+            url: url.trim_end_matches('/').to_owned(),
+            token: Some(token),
+        }
+    }
+
+    pub fn public(url: &str) -> Client {
+        Client {
+            url: url.trim_end_matches('/').to_owned(),
+            token: None,
+        }
+    }
+
+    /// Reads the instance from `GITLAB_URL`, defaulting to gitlab.com, and
+    /// the access token from `GITLAB_TOKEN`.
+    pub fn from_env() -> Client {
+        Client {
+            url: env::var("GITLAB_URL")
+                .map(|url| url.trim_end_matches('/').to_owned())
+                .unwrap_or_else(|_| DEFAULT_URL.to_owned()),
+            token: env::var_os("GITLAB_TOKEN").and_then(|s| s.into_string().ok()),
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn get(&self, url: &str) -> Result<Response> {
+        self.send(reqwest::Client::new().get(url))
+    }
+
+    fn send(&self, mut builder: RequestBuilder) -> Result<Response> {
+        codealong::ensure_online("requesting the GitLab api")?;
+        if let Some(ref token) = self.token {
+            builder = builder.header("PRIVATE-TOKEN", token.as_str());
+        }
+        let mut res = builder.send()?;
+        if res.status().is_success() {
+            Ok(res)
+        } else {
+            let message = res
+                .json::<ErrorPayload>()
+                .map(|payload| match payload.message {
+                    serde_json::Value::String(message) => message,
+                    message => message.to_string(),
+                })
+                .unwrap_or_default();
+            Err(ErrorKind::Api(res.status().as_u16(), message).into())
+        }
+    }
+
+    /// All merge requests of the project at `project`, e.g.
+    /// `gitlab-org/gitlab`, most recently updated first.
+    pub fn merge_requests(&self, project: &str, logger: &Logger) -> Cursor<MergeRequest> {
+        Cursor::new(
+            self,
+            &format!(
+                "{}/merge_requests?state=all&order_by=updated_at&sort=desc&per_page=100",
+                self.project_url(project)
+            ),
+            logger,
+        )
+    }
+
+    /// A single merge request, which unlike those listed includes its
+    /// `diff_refs`.
+    pub fn merge_request(&self, project: &str, iid: u64) -> Result<MergeRequest> {
+        let mut res = self.get(&format!(
+            "{}/merge_requests/{}",
+            self.project_url(project),
+            iid
+        ))?;
+        Ok(res.json()?)
+    }
+
+    /// The comments and system notes of the merge request `iid`.
+    pub fn notes(&self, project: &str, iid: u64, logger: &Logger) -> Cursor<Note> {
+        Cursor::new(
+            self,
+            &format!(
+                "{}/merge_requests/{}/notes?sort=asc&per_page=100",
+                self.project_url(project),
+                iid
+            ),
+            logger,
+        )
+    }
+
+    /// Comments `body`, in markdown, on the merge request `iid`.
+    pub fn create_note(&self, project: &str, iid: u64, body: &str) -> Result<()> {
+        let url = format!("{}/merge_requests/{}/notes", self.project_url(project), iid);
+        self.send(
+            reqwest::Client::new()
+                .post(&url)
+                .json(&serde_json::json!({ "body": body })),
+        )?;
+        Ok(())
+    }
+
+    fn project_url(&self, project: &str) -> String {
+        format!("{}/api/v4/projects/{}", self.url, encode_project(project))
+    }
+}
+
+/// Projects are identified by their url encoded path in the API.
+fn encode_project(project: &str) -> String {
+    project.trim_matches('/').replace('/', "%2F")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codealong::test::build_test_logger;
+
+    #[test]
+    fn test_project_url() {
+        let client = Client::public("https://gitlab.example.com/");
+        assert_eq!(
+            client.project_url("platform/infra/terraform"),
+            "https://gitlab.example.com/api/v4/projects/platform%2Finfra%2Fterraform"
+        );
+        assert_eq!(
+            client.merge_requests("acme/uploader", &build_test_logger()).url(),
+            Some("https://gitlab.example.com/api/v4/projects/acme%2Fuploader/merge_requests?state=all&order_by=updated_at&sort=desc&per_page=100")
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use regex::Regex;
+use reqwest::header::HeaderMap;
+use slog::Logger;
+
+use crate::client::Client;
+use crate::error::Result;
+
+/// Provides an iterator on top of the GitLab pagination API
+pub struct Cursor<'client, T>
+where
+    for<'de> T: serde::Deserialize<'de>,
+{
+    client: &'client Client,
+    next_url: Option<String>,
+    total: Option<usize>,
+    current_page: Option<std::vec::IntoIter<T>>,
+    has_loaded_page: bool,
+    logger: Logger,
+}
+
+impl<'client, T> Cursor<'client, T>
+where
+    for<'de> T: serde::Deserialize<'de>,
+{
+    pub fn new(client: &'client Client, url: &str, logger: &Logger) -> Cursor<'client, T> {
+        Cursor {
+            client,
+            next_url: Some(url.to_owned()),
+            total: None,
+            current_page: None,
+            has_loaded_page: false,
+            logger: logger.clone(),
+        }
+    }
+
+    /// The url of the next page to be loaded.
+    pub fn url(&self) -> Option<&str> {
+        self.next_url.as_ref().map(|url| &**url)
+    }
+
+    /// GitLab omits the total for very large collections.
+    pub fn guess_len(&mut self) -> Option<usize> {
+        self.ensure_page_loaded();
+        self.total
+    }
+
+    fn read_from_current_page(&mut self) -> Option<T> {
+        self.current_page.as_mut().and_then(|iter| iter.next())
+    }
+
+    fn ensure_page_loaded(&mut self) {
+        if !self.has_loaded_page {
+            self.load_next_page()
+        }
+    }
+
+    fn load_next_page(&mut self) {
+        match self.load_next_page_helper() {
+            Ok(_) => (),
+            Err(e) => error!(self.logger, "Error loading page: {}", e),
+        }
+    }
+
+    fn load_next_page_helper(&mut self) -> Result<()> {
+        if let Some(next_url) = self.next_url.take() {
+            let mut res = self.client.get(&next_url)?;
+            self.has_loaded_page = true;
+            let new_page = res.json::<Vec<T>>()?.into_iter();
+            let headers = res.headers();
+            self.next_url = next_page_url(&headers);
+            if self.total.is_none() {
+                self.total = headers
+                    .get("x-total")
+                    .and_then(|total| total.to_str().ok())
+                    .and_then(|total| total.parse().ok());
+            }
+            self.current_page = Some(new_page);
+        }
+        Ok(())
+    }
+}
+
+/// Url of the next page from the `Link` header of a paginated response.
+fn next_page_url(headers: &HeaderMap) -> Option<String> {
+    let link = headers.get("link")?.to_str().ok()?;
+    lazy_static! {
+        static ref LINK_NEXT_REGEX: Regex = Regex::new(r#"<([^ ]*)>; rel="next""#).unwrap();
+    }
+    LINK_NEXT_REGEX
+        .captures(link)
+        .map(|captures| captures[1].to_owned())
+}
+
+impl<'client, T> Iterator for Cursor<'client, T>
+where
+    for<'de> T: serde::Deserialize<'de>,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.read_from_current_page().or_else(|| {
+            self.load_next_page();
+            self.read_from_current_page()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_next_page_url() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "link",
+            HeaderValue::from_static(
+                "<https://gitlab.com/api/v4/projects/1/merge_requests?page=1&per_page=100>; rel=\"prev\", <https://gitlab.com/api/v4/projects/1/merge_requests?page=3&per_page=100>; rel=\"next\", <https://gitlab.com/api/v4/projects/1/merge_requests?page=1&per_page=100>; rel=\"first\"",
+            ),
+        );
+        assert_eq!(
+            next_page_url(&headers),
+            Some(
+                "https://gitlab.com/api/v4/projects/1/merge_requests?page=3&per_page=100"
+                    .to_owned()
+            )
+        );
+        assert_eq!(next_page_url(&HeaderMap::new()), None);
+    }
+}
@@ -0,0 +1,28 @@
+// error_chain 0.12 calls the deprecated `description` and `cause` of the
+// foreign errors
+#![allow(deprecated)]
+
+error_chain! {
+    errors {
+        Api(status: u16, message: String) {
+            description("GitLab API error")
+            display("GitLab API error {}: {}", status, message)
+        }
+    }
+
+    foreign_links {
+        Git2(git2::Error);
+        IO(std::io::Error);
+        Json(serde_json::Error);
+        Reqwest(reqwest::Error);
+    }
+
+    links {
+        Core(codealong::Error, codealong::ErrorKind);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorPayload {
+    pub message: serde_json::Value,
+}
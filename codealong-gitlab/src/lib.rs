@@ -0,0 +1,35 @@
+extern crate chrono;
+#[macro_use]
+extern crate error_chain;
+extern crate git2;
+extern crate regex;
+#[macro_use]
+extern crate lazy_static;
+extern crate reqwest;
+#[macro_use]
+extern crate serde_derive;
+extern crate codealong;
+extern crate serde;
+extern crate serde_json;
+#[macro_use]
+extern crate slog;
+
+mod analyzed_merge_request;
+mod client;
+mod cursor;
+mod error;
+mod merge_request;
+mod merge_request_analyzer;
+mod merge_requests_analyzer;
+mod note;
+mod user;
+
+pub use crate::analyzed_merge_request::{review_stats, AnalyzedMergeRequest};
+pub use crate::client::Client;
+pub use crate::cursor::Cursor;
+pub use crate::error::{Error, ErrorKind};
+pub use crate::merge_request::{DiffRefs, MergeRequest};
+pub use crate::merge_request_analyzer::MergeRequestAnalyzer;
+pub use crate::merge_requests_analyzer::MergeRequestsAnalyzer;
+pub use crate::note::Note;
+pub use crate::user::User;
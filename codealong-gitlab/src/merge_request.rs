@@ -0,0 +1,88 @@
+use chrono::prelude::*;
+use chrono::DateTime;
+
+use crate::user::User;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MergeRequest {
+    pub id: u64,
+    /// The number of the merge request within its project.
+    pub iid: u64,
+    pub project_id: u64,
+    pub title: String,
+    pub description: Option<String>,
+    /// One of `opened`, `closed`, `locked` or `merged`.
+    pub state: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub merged_at: Option<DateTime<Utc>>,
+    pub closed_at: Option<DateTime<Utc>>,
+    pub target_branch: String,
+    pub source_branch: String,
+    pub source_project_id: u64,
+    pub target_project_id: u64,
+    pub author: User,
+    /// The head commit of the source branch.
+    pub sha: Option<String>,
+    pub merge_commit_sha: Option<String>,
+    #[serde(default)]
+    pub squash_commit_sha: Option<String>,
+    /// Only included when a single merge request is requested.
+    #[serde(default)]
+    pub diff_refs: Option<DiffRefs>,
+    pub web_url: String,
+    #[serde(default)]
+    pub draft: bool,
+    #[serde(default)]
+    pub user_notes_count: u64,
+}
+
+impl MergeRequest {
+    /// The commit the merge request landed as, i.e. the merge commit, the
+    /// squashed commit or the last rebased commit.
+    pub fn landed_sha(&self) -> Option<&str> {
+        self.merge_commit_sha
+            .as_ref()
+            .or(self.squash_commit_sha.as_ref())
+            .or_else(|| {
+                if self.merged_at.is_some() {
+                    self.sha.as_ref()
+                } else {
+                    None
+                }
+            })
+            .map(|sha| &**sha)
+    }
+}
+
+/// The commits a merge request's diff is computed between.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiffRefs {
+    pub base_sha: String,
+    pub head_sha: String,
+    pub start_sha: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize() {
+        let merge_requests: Vec<MergeRequest> =
+            serde_json::from_str(include_str!("../fixtures/merge_requests.json")).unwrap();
+        assert_eq!(merge_requests.len(), 2);
+        let merged = &merge_requests[0];
+        assert_eq!(merged.iid, 42);
+        assert_eq!(merged.author.username, "jdoe");
+        assert_eq!(
+            merged.landed_sha(),
+            Some("8a3a7c0b4f1e2d3c4b5a69788796a5b4c3d2e1f0")
+        );
+        assert_eq!(merged.diff_refs, None);
+        let open = &merge_requests[1];
+        assert_eq!(open.state, "opened");
+        assert!(open.draft);
+        assert_eq!(open.landed_sha(), None);
+    }
+}
@@ -0,0 +1,106 @@
+use git2::{Oid, Repository};
+use slog::Logger;
+
+use codealong::{with_authentication, DiffAnalyzer, RepoConfig};
+
+use crate::analyzed_merge_request::{review_stats, AnalyzedMergeRequest};
+use crate::client::Client;
+use crate::error::{Error, Result};
+use crate::merge_request::{DiffRefs, MergeRequest};
+
+pub struct MergeRequestAnalyzer<'a> {
+    repo: &'a Repository,
+    config: &'a RepoConfig,
+    client: &'a Client,
+    mr: MergeRequest,
+    logger: Logger,
+}
+
+impl<'a> MergeRequestAnalyzer<'a> {
+    pub fn new(
+        repo: &'a Repository,
+        mr: MergeRequest,
+        config: &'a RepoConfig,
+        client: &'a Client,
+        parent_logger: &Logger,
+    ) -> MergeRequestAnalyzer<'a> {
+        let logger = parent_logger.new(o!("merge_request_id" => mr.id));
+        MergeRequestAnalyzer {
+            repo,
+            mr,
+            config,
+            client,
+            logger,
+        }
+    }
+
+    pub fn analyze(mut self) -> Result<AnalyzedMergeRequest> {
+        debug!(self.logger, "Analyzing merge_request"; "updated_at" => &self.mr.updated_at.to_rfc2822(), "user" => &self.mr.author.username, "title" => &self.mr.title);
+        let project = self.config.repo.gitlab_name.as_ref().unwrap().to_owned();
+        if self.mr.diff_refs.is_none() {
+            self.mr.diff_refs = self.client.merge_request(&project, self.mr.iid)?.diff_refs;
+        }
+        let notes: Vec<_> = self
+            .client
+            .notes(&project, self.mr.iid, &self.logger)
+            .collect();
+        let diff = match self.mr.diff_refs {
+            Some(ref diff_refs) => self
+                .fetch_refs()
+                .and_then(|_| self.analyze_diff(diff_refs))
+                .map_err(|e| warn!(self.logger, "Unable to analyze diff"; "error" => e.to_string()))
+                .ok(),
+            None => None,
+        };
+
+        let normalized_author = self
+            .config
+            .config
+            .person_for_gitlab_username_at(&self.mr.author.username, &self.mr.created_at);
+        debug!(self.logger, "Done analyzing");
+        let mut analyzed = AnalyzedMergeRequest::new(
+            &self.mr,
+            &project,
+            diff,
+            normalized_author,
+            &self.config.config.calendar(),
+        );
+        analyzed.set_review_stats(review_stats(&self.mr, &notes, &self.config.config));
+        analyzed.alias_repos(&self.config.config);
+        analyzed.extract_issues(&self.config.config)?;
+        analyzed.store_messages(&self.config.config);
+        Ok(analyzed)
+    }
+
+    fn analyze_diff(&self, diff_refs: &DiffRefs) -> Result<codealong::AnalyzedDiff> {
+        let parent = self.repo.find_commit(Oid::from_str(&diff_refs.base_sha)?)?;
+        let commit = self.repo.find_commit(Oid::from_str(&diff_refs.head_sha)?)?;
+        Ok(
+            DiffAnalyzer::new(&self.repo, &commit, Some(&parent), &self.config.config)
+                .with_paths(&self.config.repo.paths)
+                .analyze()
+                .map_err(Error::from)?,
+        )
+    }
+
+    /// GitLab keeps the head of every merge request, including those from
+    /// forks, under `refs/merge-requests` of the target project.
+    fn fetch_refs(&self) -> Result<()> {
+        let git_config = git2::Config::open_default()?;
+        let url = &self.config.repo.clone_url;
+        let head = format!("refs/merge-requests/{}/head", self.mr.iid);
+        let base = format!("refs/heads/{}", self.mr.target_branch);
+        with_authentication(url, &git_config, |f| {
+            let mut rcb = git2::RemoteCallbacks::new();
+            rcb.credentials(f);
+            let mut fo = git2::FetchOptions::new();
+            fo.remote_callbacks(rcb);
+
+            Ok(self
+                .repo
+                .remote_anonymous(url)
+                .and_then(|mut remote| remote.fetch(&[&head, &base], Some(&mut fo), None))?)
+        })?;
+        Ok(())
+    }
+}
@@ -0,0 +1,149 @@
+use git2::Repository;
+use slog::Logger;
+
+use codealong::{AnalysisStream, AnalyzeOpts, Repo, RepoConfig};
+
+use crate::analyzed_merge_request::AnalyzedMergeRequest;
+use crate::client::Client;
+use crate::cursor::Cursor;
+use crate::error::*;
+use crate::merge_request::MergeRequest;
+use crate::merge_request_analyzer::MergeRequestAnalyzer;
+
+pub struct MergeRequestsAnalyzer<'client> {
+    repo: Repository,
+    config: RepoConfig,
+    client: &'client Client,
+    logger: Logger,
+}
+
+impl<'client> MergeRequestsAnalyzer<'client> {
+    pub fn new(
+        repo: Repository,
+        config: RepoConfig,
+        client: &'client Client,
+        parent_logger: &Logger,
+    ) -> MergeRequestsAnalyzer<'client> {
+        MergeRequestsAnalyzer {
+            repo,
+            logger: parent_logger.new(o!("repo" => config.repo.name.to_owned())),
+            config,
+            client,
+        }
+    }
+
+    pub fn analyze(
+        &self,
+        opts: AnalyzeOpts,
+    ) -> Result<impl Iterator<Item = Result<MergeRequestAnalyzer>>> {
+        Ok(MergeRequestsCursor {
+            repo: &self.repo,
+            cursor: self.build_cursor(),
+            config: &self.config,
+            client: self.client,
+            opts,
+            logger: self.logger.clone(),
+        })
+    }
+
+    /// Like `analyze`, but fetches and analyzes the merge requests on a
+    /// thread of its own, with its own handle on the repository, and yields
+    /// them as a stream with at most `buffer` merge requests analyzed ahead
+    /// of the consumer.
+    pub fn stream(
+        &self,
+        opts: AnalyzeOpts,
+        buffer: usize,
+    ) -> AnalysisStream<AnalyzedMergeRequest, Error> {
+        let path = self.repo.path().to_owned();
+        let config = self.config.clone();
+        let client = self.client.clone();
+        let logger = self.logger.clone();
+        AnalysisStream::spawn(buffer, move |emitter| {
+            let analyzer = match Repository::open(&path) {
+                Ok(repo) => MergeRequestsAnalyzer {
+                    repo,
+                    config,
+                    client: &client,
+                    logger,
+                },
+                Err(e) => {
+                    emitter.emit(Err(e.into()));
+                    return;
+                }
+            };
+            let merge_requests = match analyzer.analyze(opts) {
+                Ok(merge_requests) => merge_requests,
+                Err(e) => {
+                    emitter.emit(Err(e));
+                    return;
+                }
+            };
+            for mr_analyzer in merge_requests {
+                if !emitter.emit(mr_analyzer.and_then(|a| a.analyze())) {
+                    return;
+                }
+            }
+        })
+    }
+
+    pub fn guess_len(&self, opts: AnalyzeOpts) -> Result<usize> {
+        if opts.since.is_some() {
+            Ok(self.analyze(opts)?.count())
+        } else {
+            Ok(self
+                .build_cursor()
+                .guess_len()
+                .ok_or("error estimating count of merge requests")?)
+        }
+    }
+
+    pub fn from_repo(repo: &Repo, client: &'client Client, logger: &Logger) -> Result<Self> {
+        Ok(Self::new(repo.repository()?, repo.config(), client, logger))
+    }
+
+    fn build_cursor(&self) -> Cursor<'client, MergeRequest> {
+        self.client
+            .merge_requests(self.config.repo.gitlab_name.as_ref().unwrap(), &self.logger)
+    }
+}
+
+struct MergeRequestsCursor<'client> {
+    repo: &'client Repository,
+    config: &'client RepoConfig,
+    client: &'client Client,
+    cursor: Cursor<'client, MergeRequest>,
+    opts: AnalyzeOpts,
+    logger: Logger,
+}
+
+impl<'client> Iterator for MergeRequestsCursor<'client> {
+    type Item = Result<MergeRequestAnalyzer<'client>>;
+
+    fn next(&mut self) -> Option<Result<MergeRequestAnalyzer<'client>>> {
+        loop {
+            let mr = self.cursor.next()?;
+            // listed most recently updated first
+            if let Some(ref since) = self.opts.since {
+                if since > &mr.updated_at {
+                    break None;
+                }
+            }
+
+            if !self.opts.ignore_unknown_authors
+                || self
+                    .config
+                    .config
+                    .is_gitlab_username_known(&mr.author.username)
+            {
+                break Some(Ok(MergeRequestAnalyzer::new(
+                    &self.repo,
+                    mr,
+                    &self.config,
+                    self.client,
+                    &self.logger,
+                )));
+            }
+        }
+    }
+}
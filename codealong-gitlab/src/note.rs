@@ -0,0 +1,42 @@
+use chrono::prelude::*;
+use chrono::DateTime;
+
+use crate::user::User;
+
+/// A comment on a merge request, or an event such as an approval or a push
+/// recorded by GitLab as a `system` note.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Note {
+    pub id: u64,
+    /// `DiffNote` for comments on the diff.
+    #[serde(rename = "type")]
+    pub note_type: Option<String>,
+    pub body: String,
+    pub author: User,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub system: bool,
+    pub noteable_type: String,
+    pub noteable_iid: Option<u64>,
+    #[serde(default)]
+    pub resolvable: bool,
+    #[serde(default)]
+    pub resolved: Option<bool>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize() {
+        let notes: Vec<Note> =
+            serde_json::from_str(include_str!("../fixtures/notes.json")).unwrap();
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes[0].author.username, "jdoe");
+        assert!(!notes[0].system);
+        assert!(notes[0].resolvable);
+        assert_eq!(notes[0].note_type, Some("DiffNote".to_owned()));
+        assert!(notes[1].system);
+    }
+}
@@ -0,0 +1,293 @@
+use std::ops::{Add, AddAssign};
+
+use crate::work_stats::WorkStats;
+
+/// Bytes of a file looked at by the content heuristics.
+const SAMPLE_LEN: usize = 8 * 1024;
+
+/// Languages of files recognized by their name alone.
+const FILENAMES: &[(&str, &str)] = &[
+    ("BUILD", "Starlark"),
+    ("BUILD.bazel", "Starlark"),
+    ("CMakeLists.txt", "CMake"),
+    ("Dockerfile", "Dockerfile"),
+    ("Gemfile", "Ruby"),
+    ("GNUmakefile", "Makefile"),
+    ("Jenkinsfile", "Groovy"),
+    ("Makefile", "Makefile"),
+    ("Rakefile", "Ruby"),
+    ("WORKSPACE", "Starlark"),
+    ("makefile", "Makefile"),
+];
+
+/// Languages of files recognized by their extension, for extensions which
+/// are not ambiguous.
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("bash", "Shell"),
+    ("c", "C"),
+    ("cc", "C++"),
+    ("clj", "Clojure"),
+    ("cljs", "Clojure"),
+    ("cmake", "CMake"),
+    ("coffee", "CoffeeScript"),
+    ("cpp", "C++"),
+    ("cs", "C#"),
+    ("css", "CSS"),
+    ("cxx", "C++"),
+    ("dart", "Dart"),
+    ("ex", "Elixir"),
+    ("exs", "Elixir"),
+    ("elm", "Elm"),
+    ("erl", "Erlang"),
+    ("fs", "F#"),
+    ("go", "Go"),
+    ("gradle", "Gradle"),
+    ("groovy", "Groovy"),
+    ("hpp", "C++"),
+    ("hrl", "Erlang"),
+    ("hs", "Haskell"),
+    ("htm", "HTML"),
+    ("html", "HTML"),
+    ("hxx", "C++"),
+    ("java", "Java"),
+    ("jl", "Julia"),
+    ("js", "JavaScript"),
+    ("json", "JSON"),
+    ("jsx", "JavaScript"),
+    ("kt", "Kotlin"),
+    ("kts", "Kotlin"),
+    ("less", "Less"),
+    ("lua", "Lua"),
+    ("md", "Markdown"),
+    ("markdown", "Markdown"),
+    ("mjs", "JavaScript"),
+    ("ml", "OCaml"),
+    ("mli", "OCaml"),
+    ("mm", "Objective-C++"),
+    ("php", "PHP"),
+    ("proto", "Protocol Buffer"),
+    ("ps1", "PowerShell"),
+    ("py", "Python"),
+    ("r", "R"),
+    ("rb", "Ruby"),
+    ("rs", "Rust"),
+    ("rst", "reStructuredText"),
+    ("sass", "Sass"),
+    ("scala", "Scala"),
+    ("scss", "SCSS"),
+    ("sh", "Shell"),
+    ("sql", "SQL"),
+    ("swift", "Swift"),
+    ("tf", "HCL"),
+    ("toml", "TOML"),
+    ("ts", "TypeScript"),
+    ("tsx", "TypeScript"),
+    ("vue", "Vue"),
+    ("xml", "XML"),
+    ("yaml", "YAML"),
+    ("yml", "YAML"),
+    ("zsh", "Shell"),
+];
+
+/// Languages of scripts recognized by the interpreter of their shebang.
+const INTERPRETERS: &[(&str, &str)] = &[
+    ("bash", "Shell"),
+    ("node", "JavaScript"),
+    ("perl", "Perl"),
+    ("php", "PHP"),
+    ("python", "Python"),
+    ("python2", "Python"),
+    ("python3", "Python"),
+    ("ruby", "Ruby"),
+    ("sh", "Shell"),
+    ("zsh", "Shell"),
+];
+
+/// Detects the language of the file at `path`, in the style of linguist: by
+/// its name, then by its extension, then by its content. `content` is only
+/// looked at for extensions shared by several languages, e.g. `.h`, and for
+/// files without an extension, e.g. scripts with a shebang. Languages are
+/// named as by linguist, e.g. `Rust` or `TypeScript`.
+pub fn detect_language(path: &str, content: Option<&[u8]>) -> Option<&'static str> {
+    let filename = path.rsplit('/').next().unwrap_or(path);
+    if let Some(&(_, language)) = FILENAMES.iter().find(|&&(name, _)| name == filename) {
+        return Some(language);
+    }
+    let content =
+        content.map(|content| String::from_utf8_lossy(&content[..content.len().min(SAMPLE_LEN)]));
+    let content = content.as_ref().map(|content| content.as_ref());
+    let extension = match filename.rfind('.') {
+        Some(index) if index > 0 => filename[index + 1..].to_lowercase(),
+        _ => return content.and_then(shebang_language),
+    };
+    match extension.as_str() {
+        "h" => Some(header_language(content.unwrap_or(""))),
+        "m" => Some(match content {
+            Some(content) if is_matlab(content) => "MATLAB",
+            _ => "Objective-C",
+        }),
+        "pl" => Some(match content {
+            Some(content) if is_prolog(content) => "Prolog",
+            _ => "Perl",
+        }),
+        extension => EXTENSIONS
+            .iter()
+            .find(|&&(known, _)| known == extension)
+            .map(|&(_, language)| language)
+            .or_else(|| content.and_then(shebang_language)),
+    }
+}
+
+/// Whether the language of the file at `path` is told by its path alone.
+pub fn is_conclusive(path: &str) -> bool {
+    let filename = path.rsplit('/').next().unwrap_or(path);
+    FILENAMES.iter().any(|&(name, _)| name == filename)
+        || match filename.rfind('.') {
+            Some(index) if index > 0 => {
+                let extension = filename[index + 1..].to_lowercase();
+                EXTENSIONS.iter().any(|&(known, _)| known == extension)
+            }
+            _ => false,
+        }
+}
+
+fn shebang_language(content: &str) -> Option<&'static str> {
+    let line = content.lines().next()?;
+    if !line.starts_with("#!") {
+        return None;
+    }
+    let mut words = line[2..].split_whitespace();
+    let mut interpreter = words.next()?.rsplit('/').next()?;
+    if interpreter == "env" {
+        interpreter = words.find(|word| !word.starts_with('-'))?;
+    }
+    INTERPRETERS
+        .iter()
+        .find(|&&(name, _)| name == interpreter)
+        .map(|&(_, language)| language)
+}
+
+/// `.h` headers are shared by C, C++ and Objective-C.
+fn header_language(content: &str) -> &'static str {
+    lazy_static! {
+        static ref OBJC: regex::Regex =
+            regex::Regex::new(r"(?m)^\s*(@interface|@protocol|@end|#import)\b").unwrap();
+        static ref CPP: regex::Regex = regex::Regex::new(
+            r"(?m)^\s*(class\s+\w+\s*[:{]|namespace\s+\w*\s*\{|template\s*<|#include\s*<(iostream|string|vector|memory|map)>)"
+        )
+        .unwrap();
+    }
+    if OBJC.is_match(content) {
+        "Objective-C"
+    } else if CPP.is_match(content) {
+        "C++"
+    } else {
+        "C"
+    }
+}
+
+/// `.m` files are shared by Objective-C and MATLAB.
+fn is_matlab(content: &str) -> bool {
+    !content.contains("#import")
+        && !content.contains('@')
+        && content
+            .lines()
+            .map(str::trim_start)
+            .any(|line| line.starts_with('%') || line.starts_with("function "))
+}
+
+/// `.pl` files are shared by Perl and Prolog.
+fn is_prolog(content: &str) -> bool {
+    !content.contains("use strict")
+        && !content.starts_with("#!")
+        && content.lines().any(|line| line.contains(":-"))
+}
+
+/// Lines changed in a language within a diff, along with their
+/// classification.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LanguageStats {
+    /// Files changed.
+    pub files: u64,
+    pub added: u64,
+    pub deleted: u64,
+    pub stats: WorkStats,
+}
+
+impl Add for LanguageStats {
+    type Output = LanguageStats;
+
+    fn add(self, other: LanguageStats) -> LanguageStats {
+        LanguageStats {
+            files: self.files + other.files,
+            added: self.added + other.added,
+            deleted: self.deleted + other.deleted,
+            stats: self.stats + other.stats,
+        }
+    }
+}
+
+impl AddAssign for LanguageStats {
+    fn add_assign(&mut self, other: LanguageStats) {
+        *self = *self + other;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_language() {
+        assert_eq!(detect_language("src/lib.rs", None), Some("Rust"));
+        assert_eq!(detect_language("web/App.TSX", None), Some("TypeScript"));
+        assert_eq!(
+            detect_language("docker/Dockerfile", None),
+            Some("Dockerfile")
+        );
+        assert_eq!(detect_language("README", None), None);
+        assert_eq!(detect_language(".gitignore", None), None);
+        assert_eq!(
+            detect_language("bin/deploy", Some(b"#!/usr/bin/env python3\nprint(1)\n")),
+            Some("Python")
+        );
+        assert_eq!(
+            detect_language("bin/setup", Some(b"#!/bin/bash -e\nmake\n")),
+            Some("Shell")
+        );
+    }
+
+    #[test]
+    fn test_ambiguous_extensions() {
+        assert_eq!(detect_language("include/list.h", None), Some("C"));
+        assert_eq!(
+            detect_language(
+                "include/list.h",
+                Some(b"namespace list {\ntemplate <typename T>\n")
+            ),
+            Some("C++")
+        );
+        assert_eq!(
+            detect_language(
+                "Classes/View.h",
+                Some(b"#import <UIKit/UIKit.h>\n@interface View\n@end\n")
+            ),
+            Some("Objective-C")
+        );
+        assert_eq!(
+            detect_language(
+                "analysis/fit.m",
+                Some(b"% fits the model\nfunction y = fit(x)\n")
+            ),
+            Some("MATLAB")
+        );
+        assert_eq!(
+            detect_language("rules/family.pl", Some(b"parent(X, Y) :- father(X, Y).\n")),
+            Some("Prolog")
+        );
+        assert_eq!(
+            detect_language("script/report.pl", Some(b"use strict;\nprint \"hi\";\n")),
+            Some("Perl")
+        );
+    }
+}
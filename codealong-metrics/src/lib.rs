@@ -0,0 +1,20 @@
+//! The metrics codealong computes from the paths and lines of a diff alone,
+//! without a repository to blame them against. They depend on neither git
+//! nor the file system, so that they also build for `wasm32` targets.
+
+#[macro_use]
+extern crate lazy_static;
+extern crate regex;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+
+mod language;
+mod line_stats;
+mod move_detector;
+mod work_stats;
+
+pub use crate::language::{detect_language, is_conclusive, LanguageStats};
+pub use crate::line_stats::LineStats;
+pub use crate::move_detector::MoveDetector;
+pub use crate::work_stats::{calculate_impact, WorkStats};
@@ -0,0 +1,77 @@
+use std::ops::{Add, AddAssign};
+
+/// Raw line counts for a diff, independent of any blame-based classification.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LineStats {
+    pub added: u64,
+    pub deleted: u64,
+    /// Lines which were deleted in one place and re-added elsewhere within
+    /// the same diff.
+    pub moved: u64,
+}
+
+impl LineStats {
+    pub fn empty() -> LineStats {
+        LineStats::default()
+    }
+
+    /// Ratio of moved lines to the larger of the added and deleted counts.
+    pub fn moved_ratio(&self) -> f64 {
+        let total = std::cmp::max(self.added, self.deleted);
+        if total == 0 {
+            0.0
+        } else {
+            self.moved as f64 / total as f64
+        }
+    }
+
+    /// Ratio of the smaller of the added and deleted counts to the larger. A
+    /// value of 1.0 means the diff added exactly as many lines as it removed.
+    pub fn balance(&self) -> f64 {
+        let max = std::cmp::max(self.added, self.deleted);
+        let min = std::cmp::min(self.added, self.deleted);
+        if max == 0 {
+            0.0
+        } else {
+            min as f64 / max as f64
+        }
+    }
+}
+
+impl Add for LineStats {
+    type Output = LineStats;
+
+    fn add(self, other: LineStats) -> Self::Output {
+        LineStats {
+            added: self.added + other.added,
+            deleted: self.deleted + other.deleted,
+            moved: self.moved + other.moved,
+        }
+    }
+}
+
+impl AddAssign for LineStats {
+    fn add_assign(&mut self, other: LineStats) {
+        self.added += other.added;
+        self.deleted += other.deleted;
+        self.moved += other.moved;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ratios() {
+        let stats = LineStats {
+            added: 10,
+            deleted: 8,
+            moved: 5,
+        };
+        assert_eq!(stats.moved_ratio(), 0.5);
+        assert_eq!(stats.balance(), 0.8);
+        assert_eq!(LineStats::empty().moved_ratio(), 0.0);
+        assert_eq!(LineStats::empty().balance(), 0.0);
+    }
+}
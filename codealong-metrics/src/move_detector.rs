@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+
+use crate::line_stats::LineStats;
+
+/// Lines shorter than this (after trimming) are too generic, e.g. `}` or
+/// `end`, to be meaningful when matching moved code.
+const MIN_MOVED_LINE_LENGTH: usize = 4;
+
+/// Counts added and deleted lines across a diff and matches them up by
+/// content to approximate how many lines were simply moved around.
+#[derive(Default)]
+pub struct MoveDetector {
+    stats: LineStats,
+    lines: HashMap<Vec<u8>, (u64, u64)>,
+}
+
+impl MoveDetector {
+    pub fn new() -> MoveDetector {
+        MoveDetector {
+            stats: LineStats::empty(),
+            lines: HashMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, origin: char, content: &[u8]) {
+        match origin {
+            '+' => self.stats.added += 1,
+            '-' => self.stats.deleted += 1,
+            _ => return,
+        }
+        let trimmed = trim(content);
+        if trimmed.len() < MIN_MOVED_LINE_LENGTH {
+            return;
+        }
+        let counts = self.lines.entry(trimmed.to_vec()).or_insert((0, 0));
+        if origin == '+' {
+            counts.0 += 1;
+        } else {
+            counts.1 += 1;
+        }
+    }
+
+    pub fn finish(self) -> LineStats {
+        let moved = self
+            .lines
+            .values()
+            .map(|(added, deleted)| std::cmp::min(added, deleted))
+            .sum();
+        LineStats {
+            moved,
+            ..self.stats
+        }
+    }
+}
+
+fn trim(content: &[u8]) -> &[u8] {
+    let is_space = |b: &u8| b.is_ascii_whitespace();
+    let start = content.iter().position(|b| !is_space(b));
+    let end = content.iter().rposition(|b| !is_space(b));
+    match (start, end) {
+        (Some(start), Some(end)) => &content[start..=end],
+        _ => &[],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_move_detector() {
+        let mut detector = MoveDetector::new();
+        detector.record('-', b"    fn foo() -> u64 {\n");
+        detector.record('-', b"}\n");
+        detector.record('+', b"fn foo() -> u64 {\n");
+        detector.record('+', b"}\n");
+        detector.record('+', b"let x = 1;\n");
+        detector.record(' ', b"let y = 1;\n");
+        assert_eq!(
+            detector.finish(),
+            LineStats {
+                added: 3,
+                deleted: 2,
+                moved: 1
+            }
+        );
+    }
+}
@@ -8,6 +8,9 @@ pub struct WorkStats {
     pub help_others: u64,
     pub other: u64,
     pub impact: u64,
+    /// Churn scaled by the weight of the language it was written in.
+    #[serde(default)]
+    pub weighted_churn: f64,
 }
 
 impl WorkStats {
@@ -51,6 +54,31 @@ impl WorkStats {
     }
 }
 
+/// Current line impact calculation is a simple function that tries to
+/// incoporate the following ideas:
+///
+/// 1. Large hunks of code have less cognitive overhead on a per-line basis
+///    than small or single-line changes. To incorporate this, the impact
+///    of a hunk grows sub-linearly with its size.
+///
+/// 2. Different types of work have less cognitive overhead. Dealing with
+///    refactorings of legacy code requires more context than greenfield
+///    code. Similarly, helping others by changing their recently added code
+///    also has more overhead than greenfield, but less than legacy. This
+///    is incorporated into the calculation by a constant multiplier based
+///    on work type. Churn does not have positive impact and is removed from
+///    the calculation.
+///
+/// 3. Different languages, file-types, and repositories carry different
+///    cognitive burdens. This is where Configuration-based weights of work
+///    stats comes into play. The "weight" field is used as a multiplier.
+pub fn calculate_impact(work_stats: &WorkStats, weight: f64) -> u64 {
+    let line_value =
+        work_stats.legacy_refactor * 4 + work_stats.help_others * 2 + work_stats.new_work;
+    let scaled_line_value = (line_value as f64).powf(0.5);
+    (scaled_line_value * weight).round() as u64
+}
+
 impl Default for WorkStats {
     fn default() -> WorkStats {
         WorkStats {
@@ -60,6 +88,7 @@ impl Default for WorkStats {
             help_others: 0,
             other: 0,
             impact: 0,
+            weighted_churn: 0.0,
         }
     }
 }
@@ -99,6 +128,7 @@ impl<'a, 'b> Add<&'b WorkStats> for &'a WorkStats {
             help_others: self.help_others + other.help_others,
             other: self.other + other.other,
             impact: self.impact + other.impact,
+            weighted_churn: self.weighted_churn + other.weighted_churn,
         }
     }
 }
@@ -117,6 +147,7 @@ impl<'a> AddAssign<&'a WorkStats> for WorkStats {
         self.help_others += other.help_others;
         self.other += other.other;
         self.impact += other.impact;
+        self.weighted_churn += other.weighted_churn;
     }
 }
 
@@ -132,5 +163,42 @@ mod tests {
         assert_eq!(stats.help_others, 0);
         assert_eq!(stats.other, 0);
         assert_eq!(stats.impact, 0);
+        assert_eq!(stats.weighted_churn, 0.0);
+    }
+
+    #[test]
+    fn test_impact() {
+        assert_eq!(
+            calculate_impact(
+                &WorkStats {
+                    new_work: 100,
+                    ..Default::default()
+                },
+                1.0
+            ),
+            10
+        );
+
+        assert_eq!(
+            calculate_impact(
+                &WorkStats {
+                    legacy_refactor: 100,
+                    ..Default::default()
+                },
+                1.0
+            ),
+            20
+        );
+
+        assert_eq!(
+            calculate_impact(
+                &WorkStats {
+                    churn: 100,
+                    ..Default::default()
+                },
+                1.0
+            ),
+            0
+        );
     }
 }
@@ -0,0 +1,133 @@
+use chrono::prelude::*;
+use chrono::DateTime;
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+use crate::event::Event;
+
+/// An event as it is indexed: the event itself flattened alongside where,
+/// when and by what it was produced.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Envelope<T: Event> {
+    #[serde(rename = "@timestamp")]
+    timestamp: DateTime<Utc>,
+
+    #[serde(rename = "@version")]
+    version: u64,
+
+    host: Option<String>,
+
+    /// Version of codealong which produced the event.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    codealong_version: Option<String>,
+
+    /// Hash of the effective config the event was produced with.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    config_hash: Option<String>,
+
+    /// Organization the event belongs to, when one deployment serves
+    /// several.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    tenant: Option<String>,
+
+    #[serde(rename = "type")]
+    event_type: String,
+
+    #[serde(flatten)]
+    inner: T,
+
+    tags: HashSet<String>,
+}
+
+impl<T> Envelope<T>
+where
+    T: Event,
+{
+    pub fn new(inner: T) -> Envelope<T> {
+        Envelope {
+            event_type: inner.event_type().to_string(),
+            version: 1,
+            host: None,
+            codealong_version: None,
+            config_hash: None,
+            tenant: None,
+            timestamp: *inner.timestamp(),
+            tags: inner.tags(),
+            inner,
+        }
+    }
+
+    /// Records what produced the event, so that events produced by other
+    /// versions or configs can be found and reprocessed.
+    pub fn stamp(mut self, codealong_version: Option<&str>, config_hash: Option<&str>) -> Self {
+        self.codealong_version = codealong_version.map(|version| version.to_owned());
+        self.config_hash = config_hash.map(|hash| hash.to_owned());
+        self
+    }
+
+    pub fn with_host(mut self, host: Option<String>) -> Self {
+        self.host = host;
+        self
+    }
+
+    pub fn with_tenant(mut self, tenant: Option<&str>) -> Self {
+        self.tenant = tenant.map(|tenant| tenant.to_owned());
+        self
+    }
+
+    pub fn id(&self) -> Cow<'_, str> {
+        self.inner.id()
+    }
+
+    pub fn timestamp(&self) -> &DateTime<Utc> {
+        &self.timestamp
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Deploy {
+        id: String,
+        at: DateTime<Utc>,
+    }
+
+    impl Event for Deploy {
+        fn id(&self) -> Cow<'_, str> {
+            Cow::Borrowed(&self.id)
+        }
+
+        fn timestamp(&self) -> &DateTime<Utc> {
+            &self.at
+        }
+
+        fn event_type(&self) -> &str {
+            "deploy"
+        }
+
+        fn tags(&self) -> HashSet<String> {
+            HashSet::new()
+        }
+    }
+
+    #[test]
+    fn test_envelope() {
+        let deploy = Deploy {
+            id: "1".to_owned(),
+            at: Utc.ymd(2019, 3, 4).and_hms(9, 0, 0),
+        };
+        let envelope = Envelope::new(deploy.clone())
+            .with_host(Some("ci".to_owned()))
+            .with_tenant(Some("acme"));
+        assert_eq!(envelope.id(), "1");
+        assert_eq!(envelope.timestamp(), &deploy.at);
+        assert_eq!(envelope.clone().into_inner(), deploy);
+    }
+}
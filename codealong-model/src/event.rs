@@ -4,7 +4,7 @@ use std::borrow::Cow;
 use std::collections::HashSet;
 
 pub trait Event {
-    fn id(&self) -> Cow<str>;
+    fn id(&self) -> Cow<'_, str>;
     fn timestamp(&self) -> &DateTime<Utc>;
     fn event_type(&self) -> &str;
     fn tags(&self) -> HashSet<String>;
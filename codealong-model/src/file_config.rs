@@ -0,0 +1,138 @@
+use std::collections::HashSet;
+
+use chrono::prelude::*;
+use chrono::DateTime;
+use glob::Pattern;
+
+/// The tags, weight and language of the files matching a glob of the
+/// `files` of a config, e.g. `"vendor/**": { ignore: true }`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GlobConfig {
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    #[serde(default = "GlobConfig::default_weight")]
+    pub weight: f64,
+
+    #[serde(default)]
+    pub ignore: bool,
+
+    #[serde(default)]
+    pub language: Option<String>,
+
+    #[serde(default, flatten)]
+    pub validity: Validity,
+}
+
+impl GlobConfig {
+    fn default_weight() -> f64 {
+        1.0
+    }
+}
+
+/// Represents multiple underlying glob-level configurations. A file can have
+/// mulitiple configurations if it matches multiple globs.
+pub struct FileConfig<'a> {
+    configs: Vec<&'a GlobConfig>,
+}
+
+impl<'a> FileConfig<'a> {
+    pub fn new(configs: Vec<&'a GlobConfig>) -> FileConfig<'a> {
+        FileConfig { configs }
+    }
+
+    pub fn tags(&self) -> HashSet<&str> {
+        let mut res = HashSet::new();
+        for config in &self.configs {
+            res.extend(config.tags.iter().map(|s| &**s));
+        }
+        res
+    }
+
+    pub fn weight(&self) -> f64 {
+        self.configs.last().unwrap().weight
+    }
+
+    pub fn ignore(&self) -> bool {
+        self.configs.iter().any(|c| c.ignore)
+    }
+
+    /// The language declared by the last matching glob which declares one.
+    pub fn language(&self) -> Option<&str> {
+        self.configs
+            .iter()
+            .rev()
+            .filter_map(|c| c.language.as_ref())
+            .next()
+            .map(|s| &**s)
+    }
+}
+
+/// Dates between which a config entry applies. Both dates are inclusive and
+/// either may be omitted.
+// the dates are not optional since linked_hash_map cannot hold values
+// containing an `Option<NaiveDate>`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Validity {
+    #[serde(default = "Validity::min", skip_serializing_if = "Validity::is_min")]
+    pub from: NaiveDate,
+
+    #[serde(default = "Validity::max", skip_serializing_if = "Validity::is_max")]
+    pub until: NaiveDate,
+}
+
+impl Validity {
+    pub fn contains(&self, at: &DateTime<Utc>) -> bool {
+        let date = at.naive_utc().date();
+        self.from <= date && date <= self.until
+    }
+
+    fn min() -> NaiveDate {
+        chrono::naive::MIN_DATE
+    }
+
+    fn max() -> NaiveDate {
+        chrono::naive::MAX_DATE
+    }
+
+    fn is_min(date: &NaiveDate) -> bool {
+        *date == Validity::min()
+    }
+
+    fn is_max(date: &NaiveDate) -> bool {
+        *date == Validity::max()
+    }
+}
+
+impl Default for Validity {
+    fn default() -> Validity {
+        Validity {
+            from: Validity::min(),
+            until: Validity::max(),
+        }
+    }
+}
+
+/// The configs of the globs of `files` matching `path`, in order, if any.
+/// Only those valid `at` apply, or every one when it is not given, e.g.
+/// for patches which are not dated.
+pub fn config_for_file<'a, I>(
+    files: I,
+    path: &str,
+    at: Option<&DateTime<Utc>>,
+) -> Option<FileConfig<'a>>
+where
+    I: IntoIterator<Item = (&'a String, &'a GlobConfig)>,
+{
+    let glob_configs: Vec<&GlobConfig> = files
+        .into_iter()
+        .filter(|(_, config)| at.is_none_or(|at| config.validity.contains(at)))
+        .filter(|(glob, _)| Pattern::new(glob).is_ok_and(|pattern| pattern.matches(path)))
+        .map(|(_, config)| config)
+        .collect();
+    if glob_configs.is_empty() {
+        None
+    } else {
+        Some(FileConfig::new(glob_configs))
+    }
+}
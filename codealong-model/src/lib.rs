@@ -0,0 +1,28 @@
+//! The types of the events produced by codealong and shared by every crate
+//! indexing or consuming them, so that a field is added in one place only,
+//! along with the parts of the config which builds without git also use.
+
+extern crate chrono;
+extern crate glob;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+
+mod envelope;
+mod event;
+mod file_config;
+mod metric_def;
+mod person;
+mod review_stats;
+
+pub use crate::envelope::Envelope;
+pub use crate::event::Event;
+pub use crate::file_config::{config_for_file, FileConfig, GlobConfig, Validity};
+pub use crate::metric_def::{
+    metric_def, metric_versions, MetricDef, MetricVersions, BUSINESS_DAYS_TO_RESOLVE,
+    BUSINESS_TIME_TO_FIRST_REVIEW, BUSINESS_TIME_TO_RESOLVE, COMPLIANCE_RATE, LIKELY_REFACTOR,
+    LINES_CHANGED, METRICS, SIGNING_RATE, TIME_TO_FIRST_REVIEW, TIME_TO_RESOLVE, WORK_IN_PROGRESS,
+    WORK_STATS, WORK_TYPE,
+};
+pub use crate::person::Person;
+pub use crate::review_stats::ReviewStats;
@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+
+/// A named, versioned definition of a derived metric. The version is bumped
+/// whenever the calculation changes so that values computed before and after
+/// the change can be told apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MetricDef {
+    pub name: &'static str,
+    pub version: u32,
+    pub description: &'static str,
+}
+
+/// Versions of the metrics an event was computed with, keyed by metric name.
+pub type MetricVersions = BTreeMap<String, u32>;
+
+pub const WORK_STATS: MetricDef = MetricDef {
+    name: "work_stats",
+    version: 1,
+    description: "Classification of changed lines into new work, churn of lines younger than \
+                  the churn cutoff, legacy refactoring and helping others",
+};
+
+pub const LIKELY_REFACTOR: MetricDef = MetricDef {
+    name: "likely_refactor",
+    version: 1,
+    description: "Commits mostly moving lines, or describing a refactor without growing the code",
+};
+
+pub const WORK_TYPE: MetricDef = MetricDef {
+    name: "work_type",
+    version: 1,
+    description: "Commits updating only dependencies, else fixing bugs, else likely refactors",
+};
+
+pub const LINES_CHANGED: MetricDef = MetricDef {
+    name: "lines_changed",
+    version: 1,
+    description: "Lines added plus lines deleted",
+};
+
+pub const TIME_TO_RESOLVE: MetricDef = MetricDef {
+    name: "time_to_resolve",
+    version: 1,
+    description: "Wall-clock seconds from opening to merging a pull request",
+};
+
+pub const BUSINESS_TIME_TO_RESOLVE: MetricDef = MetricDef {
+    name: "business_time_to_resolve",
+    version: 1,
+    description: "Working seconds from opening to merging a pull request according to the \
+                  business calendar",
+};
+
+pub const BUSINESS_DAYS_TO_RESOLVE: MetricDef = MetricDef {
+    name: "business_days_to_resolve",
+    version: 1,
+    description: "Working days from opening to merging a pull request according to the \
+                  business calendar",
+};
+
+pub const TIME_TO_FIRST_REVIEW: MetricDef = MetricDef {
+    name: "time_to_first_review",
+    version: 1,
+    description: "Wall-clock seconds from opening a pull request to the first review submitted \
+                  by someone other than its author",
+};
+
+pub const BUSINESS_TIME_TO_FIRST_REVIEW: MetricDef = MetricDef {
+    name: "business_time_to_first_review",
+    version: 1,
+    description: "Working seconds from opening a pull request to its first review according to \
+                  the business calendar",
+};
+
+pub const WORK_IN_PROGRESS: MetricDef = MetricDef {
+    name: "wip",
+    version: 1,
+    description: "Distinct repos, branches and pull requests touched during a period",
+};
+
+pub const COMPLIANCE_RATE: MetricDef = MetricDef {
+    name: "compliance_rate",
+    version: 1,
+    description: "Share of checked commits containing all required trailers",
+};
+
+pub const SIGNING_RATE: MetricDef = MetricDef {
+    name: "signing_rate",
+    version: 1,
+    description: "Share of commits carrying a signature",
+};
+
+/// Every metric definition known to codealong.
+pub const METRICS: &[MetricDef] = &[
+    WORK_STATS,
+    LIKELY_REFACTOR,
+    WORK_TYPE,
+    LINES_CHANGED,
+    TIME_TO_RESOLVE,
+    BUSINESS_TIME_TO_RESOLVE,
+    BUSINESS_DAYS_TO_RESOLVE,
+    TIME_TO_FIRST_REVIEW,
+    BUSINESS_TIME_TO_FIRST_REVIEW,
+    WORK_IN_PROGRESS,
+    COMPLIANCE_RATE,
+    SIGNING_RATE,
+];
+
+/// Looks up the definition of a metric by name.
+pub fn metric_def(name: &str) -> Option<&'static MetricDef> {
+    METRICS.iter().find(|def| def.name == name)
+}
+
+/// Stamps the current versions of `defs` onto an event.
+pub fn metric_versions(defs: &[MetricDef]) -> MetricVersions {
+    defs.iter()
+        .map(|def| (def.name.to_owned(), def.version))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_registry() {
+        let names: HashSet<&str> = METRICS.iter().map(|def| def.name).collect();
+        assert_eq!(names.len(), METRICS.len());
+        assert_eq!(metric_def("time_to_resolve"), Some(&TIME_TO_RESOLVE));
+        assert_eq!(metric_def("unknown"), None);
+        assert_eq!(
+            metric_versions(&[TIME_TO_RESOLVE]).get("time_to_resolve"),
+            Some(&1)
+        );
+    }
+}
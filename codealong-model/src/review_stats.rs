@@ -0,0 +1,23 @@
+use chrono::prelude::*;
+use chrono::DateTime;
+
+/// How a pull request was reviewed, counting only the reviews and comments
+/// submitted by people other than its author. Providers which only keep the
+/// latest review of each participant, such as Bitbucket, count a participant
+/// as a single review.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReviewStats {
+    pub reviews: u64,
+    pub approvals: u64,
+    pub change_requests: u64,
+    /// Inline comments of others than the author.
+    pub review_comments: u64,
+    /// Ids of the normalized reviewers.
+    pub reviewers: Vec<String>,
+    pub first_reviewed_at: Option<DateTime<Utc>>,
+    pub time_to_first_review: Option<i64>,
+    /// Working seconds from opening the pull request to its first review
+    /// according to the configured business calendar.
+    pub business_time_to_first_review: Option<i64>,
+}
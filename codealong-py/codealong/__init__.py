@@ -0,0 +1,49 @@
+"""Python bindings of the codealong git analysis library.
+
+Commits are analyzed by the native ``codealong._native`` module, built from
+this crate with maturin, and yielded as dicts, the same documents as the
+indexed ``commit`` events, which ``records`` flattens into rows for
+``pandas.DataFrame``::
+
+    import pandas as pd
+    import codealong
+
+    df = pd.DataFrame(codealong.records("path/to/repo", since="2019-01-01T00:00:00Z"))
+"""
+
+import datetime
+import os
+
+from codealong._native import Analyzer, CodealongError
+
+__all__ = ["Analyzer", "CodealongError", "analyze", "flatten", "records"]
+
+
+def analyze(path, since=None, **options):
+    """The analyzed commits of the repository containing ``path``, as dicts.
+    ``since`` is a ``datetime``, naive ones being taken as UTC, or an RFC
+    3339 string. See ``Analyzer`` for the other options."""
+    if isinstance(since, datetime.datetime):
+        if since.tzinfo is None:
+            since = since.replace(tzinfo=datetime.timezone.utc)
+        since = since.isoformat()
+    return Analyzer(os.fspath(path), since=since, **options)
+
+
+def flatten(document, prefix=""):
+    """Flattens nested dicts into a single dict with dotted keys, e.g.
+    ``line_stats.added``, leaving lists as they are."""
+    res = {}
+    for key, value in document.items():
+        name = prefix + key
+        if isinstance(value, dict):
+            res.update(flatten(value, name + "."))
+        else:
+            res[name] = value
+    return res
+
+
+def records(path, **options):
+    """The analyzed commits of the repository containing ``path`` as flat
+    dicts, ready for ``pandas.DataFrame``."""
+    return [flatten(commit) for commit in analyze(path, **options)]
@@ -0,0 +1,166 @@
+//! Python bindings of the git analysis of codealong, built with maturin into
+//! the `codealong._native` module which the `codealong` package wraps.
+//! Analyzed commits are handed out as dicts, the same documents as the
+//! events which are indexed.
+
+extern crate chrono;
+extern crate codealong;
+extern crate futures;
+extern crate pyo3;
+extern crate serde_json;
+#[macro_use]
+extern crate slog;
+
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use chrono::prelude::*;
+use chrono::DateTime;
+use futures::stream::Wait;
+use futures::Stream;
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use slog::{Discard, Logger};
+
+use codealong::{
+    discover, AnalysisStream, AnalyzeOpts, AnalyzedCommit, Config, Error, RepoAnalyzer, RepoConfig,
+};
+
+/// Number of commits analyzed ahead of the caller.
+const BUFFER: usize = 16;
+
+create_exception!(
+    _native,
+    CodealongError,
+    PyException,
+    "Raised when a repository fails to be opened or analyzed."
+);
+
+fn py_err(e: impl ToString) -> PyErr {
+    CodealongError::new_err(e.to_string())
+}
+
+/// Iterates over the analyzed commits of the repository containing `path`
+/// as dicts, analyzing them on a thread of their own. Commits which fail to
+/// be analyzed raise `CodealongError` unless `skip_errors` is set, in which
+/// case they are left out.
+///
+/// `since` is an RFC 3339 string; `branch`, `default_branch_only` and
+/// `first_parent` select the commits as the options of the same names of
+/// `codealong analyze` do, and `config` is the path of a codealong config
+/// file merged over that of the repository.
+#[pyclass(module = "codealong._native")]
+pub struct Analyzer {
+    /// Only locked by the methods taking the analyzer mutably, which Python
+    /// already serializes, as classes must be `Sync`.
+    commits: Mutex<Option<Wait<AnalysisStream<AnalyzedCommit, Error>>>>,
+    skip_errors: bool,
+}
+
+#[pymethods]
+impl Analyzer {
+    #[new]
+    #[pyo3(signature = (
+        path,
+        since = None,
+        branch = None,
+        default_branch_only = false,
+        first_parent = false,
+        config = None,
+        skip_errors = false
+    ))]
+    fn new(
+        path: PathBuf,
+        since: Option<&str>,
+        branch: Option<String>,
+        default_branch_only: bool,
+        first_parent: bool,
+        config: Option<PathBuf>,
+        skip_errors: bool,
+    ) -> PyResult<Analyzer> {
+        let since = match since {
+            Some(since) => Some(
+                DateTime::parse_from_rfc3339(since)
+                    .map_err(|e| py_err(format!("invalid since {:?}: {}", since, e)))?
+                    .with_timezone(&Utc),
+            ),
+            None => None,
+        };
+        let repo = discover(&path).map_err(py_err)?;
+        let mut repo_config = RepoConfig::from_repository(&repo)
+            .and_then(|repo_config| repo_config.with_mailmap(&repo))
+            .map_err(py_err)?;
+        if let Some(ref config) = config {
+            repo_config
+                .config
+                .merge(Config::from_path(config).map_err(py_err)?);
+        }
+        let opts = AnalyzeOpts {
+            since,
+            branch,
+            default_branch_only,
+            first_parent,
+            ..AnalyzeOpts::default()
+        };
+        let logger = Logger::root(Discard, o!());
+        let analyzer = RepoAnalyzer::new(repo, repo_config, &logger);
+        Ok(Analyzer {
+            commits: Mutex::new(Some(analyzer.stream(opts, BUFFER).wait())),
+            skip_errors,
+        })
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python<'_>) -> PyResult<Option<PyObject>> {
+        loop {
+            let commits = match self.commits.get_mut().unwrap() {
+                Some(commits) => commits,
+                None => return Ok(None),
+            };
+            // the analysis thread does not need the interpreter
+            match py.allow_threads(|| commits.next()) {
+                Some(Ok(commit)) => return to_dict(py, &commit).map(Some),
+                Some(Err(e)) => {
+                    if !self.skip_errors {
+                        return Err(py_err(e));
+                    }
+                }
+                None => {
+                    self.close();
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    /// Stops the analysis. Called once every commit was yielded.
+    fn close(&mut self) {
+        *self.commits.get_mut().unwrap() = None;
+    }
+
+    fn __enter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    #[pyo3(signature = (*_exc))]
+    fn __exit__(&mut self, _exc: &Bound<'_, PyAny>) {
+        self.close();
+    }
+}
+
+/// `commit` as the dict of its JSON document.
+fn to_dict(py: Python<'_>, commit: &AnalyzedCommit) -> PyResult<PyObject> {
+    let json = serde_json::to_string(commit).map_err(py_err)?;
+    Ok(py.import("json")?.call_method1("loads", (json,))?.unbind())
+}
+
+#[pymodule]
+fn _native(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Analyzer>()?;
+    m.add("CodealongError", m.py().get_type::<CodealongError>())?;
+    Ok(())
+}
@@ -0,0 +1,43 @@
+import datetime
+import os
+import unittest
+
+import codealong
+
+REPO = os.path.join(
+    os.path.dirname(__file__), "..", "..", "codealong", "fixtures", "repos", "simple"
+)
+
+
+class AnalyzerTest(unittest.TestCase):
+    def test_analyze(self):
+        commits = list(codealong.analyze(REPO, first_parent=True))
+        ids = [commit["id"] for commit in commits]
+        self.assertIn("86d242301830075e93ff039a4d1e88673a4a3020", ids)
+
+    def test_records(self):
+        rows = codealong.records(REPO)
+        self.assertTrue(rows)
+        self.assertIn("line_stats.added", rows[0])
+
+    def test_since(self):
+        since = datetime.datetime(2100, 1, 1)
+        self.assertEqual(list(codealong.analyze(REPO, since=since)), [])
+
+    def test_error(self):
+        with self.assertRaises(codealong.CodealongError):
+            codealong.analyze(REPO, since="not a date")
+        with self.assertRaises(codealong.CodealongError):
+            codealong.analyze("/")
+
+
+class FlattenTest(unittest.TestCase):
+    def test_flatten(self):
+        self.assertEqual(
+            codealong.flatten({"a": {"b": 1, "c": [2]}, "d": None}),
+            {"a.b": 1, "a.c": [2], "d": None},
+        )
+
+
+if __name__ == "__main__":
+    unittest.main()
@@ -0,0 +1,235 @@
+use std::collections::BTreeMap;
+
+use chrono::prelude::*;
+use linked_hash_map::LinkedHashMap;
+
+use codealong_model::{config_for_file, GlobConfig};
+
+use codealong_metrics::{
+    calculate_impact, detect_language, LanguageStats, LineStats, MoveDetector, WorkStats,
+};
+
+use crate::patch::{parse_patch, FilePatch, FileStatus};
+
+/// The subset of the codealong config which applies to patches: the tags,
+/// weights and languages of files.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct PatchConfig {
+    /// The `files` of the config, whose weight and language of the last
+    /// matching glob win.
+    #[serde(default)]
+    pub files: LinkedHashMap<String, GlobConfig>,
+
+    /// When the patch was made, to only apply the globs valid then. Every
+    /// glob applies if it is not given.
+    #[serde(default)]
+    pub at: Option<DateTime<Utc>>,
+}
+
+/// The stats of a patch, as those of the diff of an analyzed commit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct PatchAnalysis {
+    pub stats: WorkStats,
+    pub tag_stats: BTreeMap<String, WorkStats>,
+    pub line_stats: LineStats,
+    /// Stats of each file changed, other than ignored ones.
+    pub files: Vec<PatchFileStats>,
+    pub language_stats: BTreeMap<String, LanguageStats>,
+}
+
+/// Stats of a single file changed by a patch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PatchFileStats {
+    pub path: String,
+    pub old_path: Option<String>,
+    pub renamed: bool,
+    pub copied: bool,
+    pub tags: Vec<String>,
+    pub weight: f64,
+    pub language: Option<String>,
+    pub stats: WorkStats,
+    /// Whether the file is binary, in which case its lines are not analyzed.
+    pub binary: bool,
+}
+
+/// Analyzes the files changed by `patch`, in any format `parse_patch`
+/// understands.
+pub fn analyze_patch(patch: &str, config: &PatchConfig) -> PatchAnalysis {
+    let mut result = PatchAnalysis::default();
+    let mut move_detector = MoveDetector::new();
+    for file in parse_patch(patch) {
+        let path = file.path().to_owned();
+        let file_config = config_for_file(&config.files, &path, config.at.as_ref());
+        if file_config.as_ref().is_some_and(|c| c.ignore()) {
+            continue;
+        }
+        let mut tags: Vec<String> = file_config
+            .as_ref()
+            .map(|c| c.tags().into_iter().map(|tag| tag.to_owned()).collect())
+            .unwrap_or_default();
+        tags.sort();
+        let weight = file_config.as_ref().map_or(1.0, |c| c.weight());
+        let language = file_config
+            .as_ref()
+            .and_then(|c| c.language())
+            .map(|language| language.to_owned())
+            .or_else(|| {
+                let content = added_content(&file);
+                detect_language(&path, content.as_ref().map(|c| c.as_bytes()))
+                    .map(|language| language.to_owned())
+            });
+        let (mut added, mut deleted) = (0, 0);
+        let mut stats = WorkStats::empty();
+        if !file.binary {
+            for hunk in &file.hunks {
+                let mut hunk_stats = WorkStats::empty();
+                for line in &hunk.lines {
+                    // the file a copy was made from is still there, so only
+                    // the lines the copy adds to it are work
+                    if file.status == FileStatus::Copied && line.origin != '+' {
+                        continue;
+                    }
+                    move_detector.record(line.origin, line.content.as_bytes());
+                    hunk_stats += match line.origin {
+                        '+' => {
+                            added += 1;
+                            WorkStats::new_work()
+                        }
+                        '-' => {
+                            deleted += 1;
+                            WorkStats::other()
+                        }
+                        _ => WorkStats::legacy_refactor(),
+                    };
+                }
+                hunk_stats.impact = calculate_impact(&hunk_stats, weight);
+                stats += hunk_stats;
+            }
+        }
+        result.stats += stats;
+        for tag in &tags {
+            *result.tag_stats.entry(tag.clone()).or_default() += stats;
+        }
+        if let (Some(language), false) = (&language, file.binary) {
+            *result.language_stats.entry(language.clone()).or_default() += LanguageStats {
+                files: 1,
+                added,
+                deleted,
+                stats,
+            };
+        }
+        result.files.push(PatchFileStats {
+            path,
+            old_path: match file.status {
+                FileStatus::Renamed | FileStatus::Copied => file.old_path.clone(),
+                _ => None,
+            },
+            renamed: file.status == FileStatus::Renamed,
+            copied: file.status == FileStatus::Copied,
+            tags,
+            weight,
+            language,
+            stats,
+            binary: file.binary,
+        });
+    }
+    result.line_stats = move_detector.finish();
+    result
+}
+
+/// The content of a file added by a patch, from which the language of files
+/// without an extension can be told.
+fn added_content(file: &FilePatch) -> Option<String> {
+    if file.status != FileStatus::Added {
+        return None;
+    }
+    let lines: Vec<&str> = file
+        .hunks
+        .iter()
+        .flat_map(|hunk| &hunk.lines)
+        .map(|line| line.content.as_str())
+        .collect();
+    Some(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze_patch() {
+        let patch = "diff --git a/src/lib.rs b/src/lib.rs
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,3 +1,4 @@
+ fn main() {
+-    println!(\"hello\");
++    println!(\"hello, world\");
++    println!(\"bye\");
+ }
+diff --git a/bin/run b/bin/run
+new file mode 100755
+--- /dev/null
++++ b/bin/run
+@@ -0,0 +1,2 @@
++#!/usr/bin/env python3
++print(1)
+diff --git a/vendor/lib.js b/vendor/lib.js
+--- a/vendor/lib.js
++++ b/vendor/lib.js
+@@ -1 +1 @@
+-var a = 1;
++var a = 2;
+";
+        let config: PatchConfig = serde_json::from_str(
+            r#"{"files": {
+                "src/**/*": {"tags": ["core"], "weight": 2.0},
+                "vendor/**/*": {"ignore": true, "until": "2019-01-01"}
+            }}"#,
+        )
+        .unwrap();
+        let analysis = analyze_patch(patch, &config);
+        let files: Vec<(&str, Option<&str>, &[String], u64)> = analysis
+            .files
+            .iter()
+            .map(|file| {
+                (
+                    file.path.as_str(),
+                    file.language.as_deref(),
+                    file.tags.as_slice(),
+                    file.stats.new_work,
+                )
+            })
+            .collect();
+        assert_eq!(
+            files,
+            vec![
+                ("src/lib.rs", Some("Rust"), &["core".to_owned()][..], 2),
+                ("bin/run", Some("Python"), &[][..], 2),
+            ]
+        );
+        let lib = &analysis.files[0].stats;
+        assert_eq!((lib.legacy_refactor, lib.other), (2, 1));
+        assert_eq!(lib.impact, 6);
+        assert_eq!(analysis.stats.new_work, 4);
+        assert_eq!(analysis.tag_stats["core"], *lib);
+        assert_eq!(
+            analysis.line_stats,
+            LineStats {
+                added: 4,
+                deleted: 1,
+                moved: 0
+            }
+        );
+        assert_eq!(analysis.language_stats["Python"].added, 2);
+
+        // vendored files were only ignored until 2019
+        let config = PatchConfig {
+            at: Some(Utc.ymd(2019, 6, 1).and_hms(0, 0, 0)),
+            ..config
+        };
+        let analysis = analyze_patch(patch, &config);
+        assert_eq!(analysis.files.len(), 3);
+        assert_eq!(analysis.files[2].path, "vendor/lib.js");
+    }
+}
@@ -0,0 +1,110 @@
+use std::mem;
+use std::os::raw::c_char;
+use std::panic;
+use std::ptr;
+use std::slice;
+
+use crate::analyzer::{analyze_patch, PatchConfig};
+use crate::last_error::{clear_last_error, into_c_string, set_last_error};
+
+/// Allocates `len` bytes in the memory of the module, e.g. for the caller to
+/// write a patch into before analyzing it.
+#[no_mangle]
+pub extern "C" fn codealong_alloc(len: usize) -> *mut u8 {
+    let mut buf: Vec<u8> = Vec::with_capacity(len);
+    let ptr = buf.as_mut_ptr();
+    mem::forget(buf);
+    ptr
+}
+
+/// Frees bytes allocated with `codealong_alloc`.
+///
+/// # Safety
+///
+/// `ptr` must have been returned by `codealong_alloc(len)` and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn codealong_dealloc(ptr: *mut u8, len: usize) {
+    if !ptr.is_null() {
+        drop(Vec::from_raw_parts(ptr, 0, len));
+    }
+}
+
+/// Analyzes the `patch_len` bytes of UTF-8 at `patch`, with the
+/// `config_len` bytes of JSON at `config`, if any, as a `PatchConfig`.
+/// Returns the analysis as a JSON document to be freed with
+/// `codealong_string_free`, or null if it failed.
+///
+/// # Safety
+///
+/// `patch` and `config` must be null or point to that many readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn codealong_analyze_patch(
+    patch: *const u8,
+    patch_len: usize,
+    config: *const u8,
+    config_len: usize,
+) -> *mut c_char {
+    clear_last_error();
+    let bytes = |ptr: *const u8, len: usize| {
+        if ptr.is_null() {
+            &[][..]
+        } else {
+            slice::from_raw_parts(ptr, len)
+        }
+    };
+    let patch = String::from_utf8_lossy(bytes(patch, patch_len)).into_owned();
+    let config = bytes(config, config_len).to_vec();
+    let res = panic::catch_unwind(move || {
+        let config: PatchConfig = if config.is_empty() {
+            PatchConfig::default()
+        } else {
+            serde_json::from_slice(&config).map_err(|e| format!("invalid config: {}", e))?
+        };
+        serde_json::to_string(&analyze_patch(&patch, &config)).map_err(|e| e.to_string())
+    });
+    match res {
+        Ok(Ok(json)) => into_c_string(json),
+        Ok(Err(message)) => {
+            set_last_error(&message);
+            ptr::null_mut()
+        }
+        Err(_) => {
+            set_last_error("panicked while analyzing the patch");
+            ptr::null_mut()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::last_error::{codealong_last_error, codealong_string_free};
+    use std::ffi::CStr;
+
+    #[test]
+    fn test_analyze_patch() {
+        let patch = b"--- /dev/null\n+++ b/a.rs\n@@ -0,0 +1 @@\n+fn main() {}\n";
+        unsafe {
+            let ptr = codealong_alloc(patch.len());
+            ptr::copy_nonoverlapping(patch.as_ptr(), ptr, patch.len());
+            let json = codealong_analyze_patch(ptr, patch.len(), ptr::null(), 0);
+            codealong_dealloc(ptr, patch.len());
+            assert!(!json.is_null());
+            let analysis: serde_json::Value =
+                serde_json::from_str(&CStr::from_ptr(json).to_string_lossy()).unwrap();
+            codealong_string_free(json);
+            assert_eq!(analysis["stats"]["new_work"], 1);
+            assert_eq!(analysis["files"][0]["language"], "Rust");
+
+            let config = b"{\"files\": 1}";
+            let json =
+                codealong_analyze_patch(patch.as_ptr(), patch.len(), config.as_ptr(), config.len());
+            assert!(json.is_null());
+            let error = codealong_last_error();
+            assert!(CStr::from_ptr(error)
+                .to_string_lossy()
+                .starts_with("invalid config"));
+            codealong_string_free(error);
+        }
+    }
+}
@@ -0,0 +1,32 @@
+//! The analysis of codealong which runs without git, built for `wasm32`
+//! targets so that a browser can analyze the patches of an uploaded bundle
+//! or of a small repo fetched with isomorphic-git, e.g. for a demo.
+//!
+//! Patches in the format of `git diff`, `git show` or `git format-patch` are
+//! parsed, and their lines counted and classified as by the core library.
+//! Without history to blame them against, the lines a patch changes are
+//! counted as legacy refactoring rather than told apart from churn.
+//!
+//! Build it with `cargo build -p codealong-wasm --release --target
+//! wasm32-unknown-unknown`. The module exports the functions of `exports`,
+//! which take and return strings in its memory, as `demo/index.html` shows.
+
+extern crate chrono;
+extern crate codealong_metrics;
+extern crate codealong_model;
+extern crate linked_hash_map;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_json;
+
+mod analyzer;
+mod exports;
+#[path = "../../codealong-ffi/src/last_error.rs"]
+mod last_error;
+mod patch;
+
+pub use crate::analyzer::{analyze_patch, PatchAnalysis, PatchConfig, PatchFileStats};
+pub use crate::exports::{codealong_alloc, codealong_analyze_patch, codealong_dealloc};
+pub use crate::last_error::{codealong_last_error, codealong_string_free};
+pub use crate::patch::{parse_patch, FilePatch, FileStatus, Hunk, PatchLine};
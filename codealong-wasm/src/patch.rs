@@ -0,0 +1,301 @@
+/// How a patch changes a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    Added,
+    Deleted,
+    Modified,
+    Renamed,
+    Copied,
+}
+
+/// A line of a hunk, with `origin` `+` if added, `-` if deleted and ` ` if
+/// unchanged, as in git2.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatchLine {
+    pub origin: char,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Hunk {
+    pub lines: Vec<PatchLine>,
+}
+
+/// The changes of a patch to a single file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilePatch {
+    /// Path of the file before the patch, unless it adds it.
+    pub old_path: Option<String>,
+    /// Path of the file after the patch, unless it deletes it.
+    pub new_path: Option<String>,
+    pub status: FileStatus,
+    pub binary: bool,
+    pub hunks: Vec<Hunk>,
+}
+
+impl FilePatch {
+    fn new(old_path: Option<String>, new_path: Option<String>) -> FilePatch {
+        FilePatch {
+            old_path,
+            new_path,
+            status: FileStatus::Modified,
+            binary: false,
+            hunks: vec![],
+        }
+    }
+
+    /// The path of the file after the patch, or before it if it is deleted.
+    pub fn path(&self) -> &str {
+        self.new_path
+            .as_ref()
+            .or(self.old_path.as_ref())
+            .map_or("", |path| path)
+    }
+}
+
+/// Parses the files changed by a patch in the unified format of `git diff`,
+/// `git show` or `git format-patch`, several of which may be concatenated.
+/// Lines outside of the diffs themselves, such as commit messages and
+/// signatures, are skipped.
+pub fn parse_patch(patch: &str) -> Vec<FilePatch> {
+    let mut files: Vec<FilePatch> = vec![];
+    let mut file: Option<FilePatch> = None;
+    // lines of the current hunk which are still expected on each side
+    let (mut old_lines, mut new_lines) = (0u64, 0u64);
+    for line in patch.lines() {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if old_lines > 0 || new_lines > 0 {
+            let origin = line.chars().next().unwrap_or(' ');
+            match origin {
+                '+' => new_lines = new_lines.saturating_sub(1),
+                '-' => old_lines = old_lines.saturating_sub(1),
+                '\\' => continue,
+                _ => {
+                    old_lines = old_lines.saturating_sub(1);
+                    new_lines = new_lines.saturating_sub(1);
+                }
+            }
+            let hunk = file.as_mut().and_then(|file| file.hunks.last_mut());
+            if let Some(hunk) = hunk {
+                hunk.lines.push(PatchLine {
+                    origin: if origin == '+' || origin == '-' {
+                        origin
+                    } else {
+                        ' '
+                    },
+                    content: line.get(1..).unwrap_or("").to_owned(),
+                });
+            }
+            continue;
+        }
+        if let Some(paths) = line.strip_prefix("diff --git ") {
+            files.extend(file.take());
+            let (old_path, new_path) = match paths.rfind(" b/") {
+                Some(index) => (&paths[..index], &paths[index + 1..]),
+                None => (paths, paths),
+            };
+            file = Some(FilePatch::new(
+                Some(strip_prefix(old_path, "a/")),
+                Some(strip_prefix(new_path, "b/")),
+            ));
+        } else if let Some(path) = line.strip_prefix("--- ") {
+            // plain unified diffs start each file with its paths
+            if file.as_ref().map_or(true, |file| !file.hunks.is_empty()) {
+                files.extend(file.take());
+                file = Some(FilePatch::new(None, None));
+            }
+            let file = file.as_mut().unwrap();
+            file.old_path = diff_path(path, "a/");
+            if file.old_path.is_none() {
+                file.status = FileStatus::Added;
+            }
+        } else if let Some(path) = line.strip_prefix("+++ ") {
+            if let Some(file) = file.as_mut() {
+                file.new_path = diff_path(path, "b/");
+                if file.new_path.is_none() {
+                    file.status = FileStatus::Deleted;
+                }
+            }
+        } else if line.starts_with("@@ ") {
+            if let (Some(file), Some((old, new))) = (file.as_mut(), hunk_lines(line)) {
+                file.hunks.push(Hunk::default());
+                old_lines = old;
+                new_lines = new;
+            }
+        } else if let Some(file) = file.as_mut() {
+            if line.starts_with("new file mode") {
+                file.status = FileStatus::Added;
+                file.old_path = None;
+            } else if line.starts_with("deleted file mode") {
+                file.status = FileStatus::Deleted;
+                file.new_path = None;
+            } else if let Some(path) = line.strip_prefix("rename from ") {
+                file.status = FileStatus::Renamed;
+                file.old_path = Some(unquote(path));
+            } else if let Some(path) = line.strip_prefix("rename to ") {
+                file.new_path = Some(unquote(path));
+            } else if let Some(path) = line.strip_prefix("copy from ") {
+                file.status = FileStatus::Copied;
+                file.old_path = Some(unquote(path));
+            } else if let Some(path) = line.strip_prefix("copy to ") {
+                file.new_path = Some(unquote(path));
+            } else if line.starts_with("Binary files ") || line == "GIT binary patch" {
+                file.binary = true;
+            }
+        }
+    }
+    files.extend(file);
+    files
+}
+
+/// The path of a `---` or `+++` line, or none for `/dev/null`.
+fn diff_path(path: &str, prefix: &str) -> Option<String> {
+    // timestamps of `diff -u` follow a tab
+    let path = path.split('\t').next().unwrap_or(path).trim_end();
+    if path == "/dev/null" {
+        None
+    } else {
+        Some(strip_prefix(path, prefix))
+    }
+}
+
+fn strip_prefix(path: &str, prefix: &str) -> String {
+    let path = unquote(path);
+    match path.strip_prefix(prefix) {
+        Some(path) => path.to_owned(),
+        None => path,
+    }
+}
+
+/// Paths with special characters are quoted by git.
+fn unquote(path: &str) -> String {
+    path.strip_prefix('"')
+        .and_then(|path| path.strip_suffix('"'))
+        .unwrap_or(path)
+        .replace("\\\"", "\"")
+        .replace("\\\\", "\\")
+}
+
+/// Lines of each side of a hunk, from its header, e.g.
+/// `@@ -1,3 +1,4 @@ fn main() {`.
+fn hunk_lines(header: &str) -> Option<(u64, u64)> {
+    let mut ranges = header.split_whitespace().skip(1);
+    let count = |range: &str| -> Option<u64> {
+        match range.split_once(',') {
+            Some((_, count)) => count.parse().ok(),
+            None => Some(1),
+        }
+    };
+    let old = count(ranges.next()?.strip_prefix('-')?)?;
+    let new = count(ranges.next()?.strip_prefix('+')?)?;
+    Some((old, new))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PATCH: &str = "From 1b70351ae0018e6bf7fd4c9e3262a93b475003c4 Mon Sep 17 00:00:00 2001
+From: Test <test@example.com>
+Subject: [PATCH] Change files
+
+---
+ src/lib.rs | 3 ++-
+ 2 files changed, 2 insertions(+), 1 deletion(-)
+
+diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,3 +1,4 @@
+ fn main() {
+-    println!(\"hello\");
++    println!(\"hello, world\");
++    println!(\"bye\");
+ }
+diff --git a/docs/old.md b/docs/new.md
+similarity index 90%
+rename from docs/old.md
+rename to docs/new.md
+index 3333333..4444444 100644
+--- a/docs/old.md
++++ b/docs/new.md
+@@ -1 +1 @@
+-# Old
++# New
+diff --git a/logo.png b/logo.png
+new file mode 100644
+index 0000000..5555555
+Binary files /dev/null and b/logo.png differ
+diff --git a/gone.txt b/gone.txt
+deleted file mode 100644
+index 6666666..0000000
+--- a/gone.txt
++++ /dev/null
+@@ -1,2 +0,0 @@
+-one
+-two
+-- 
+2.20.1
+";
+
+    #[test]
+    fn test_parse_patch() {
+        let files = parse_patch(PATCH);
+        let summary: Vec<(Option<&str>, Option<&str>, FileStatus, bool, usize)> = files
+            .iter()
+            .map(|file| {
+                (
+                    file.old_path.as_deref(),
+                    file.new_path.as_deref(),
+                    file.status,
+                    file.binary,
+                    file.hunks.iter().map(|hunk| hunk.lines.len()).sum(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    Some("src/lib.rs"),
+                    Some("src/lib.rs"),
+                    FileStatus::Modified,
+                    false,
+                    5
+                ),
+                (
+                    Some("docs/old.md"),
+                    Some("docs/new.md"),
+                    FileStatus::Renamed,
+                    false,
+                    2
+                ),
+                (None, Some("logo.png"), FileStatus::Added, true, 0),
+                (Some("gone.txt"), None, FileStatus::Deleted, false, 2),
+            ]
+        );
+        assert_eq!(
+            files[0].hunks[0].lines[2],
+            PatchLine {
+                origin: '+',
+                content: "    println!(\"hello, world\");".to_owned(),
+            }
+        );
+        assert_eq!(files[3].path(), "gone.txt");
+    }
+
+    #[test]
+    fn test_parse_unified_diff() {
+        let files = parse_patch(
+            "--- a.txt\t2019-01-01 00:00:00\n+++ a.txt\t2019-01-02 00:00:00\n@@ -1 +1,2 @@\n one\n+two\n\
+             --- /dev/null\n+++ b.txt\n@@ -0,0 +1 @@\n+three\n",
+        );
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path(), "a.txt");
+        assert_eq!(files[0].status, FileStatus::Modified);
+        assert_eq!(files[1].status, FileStatus::Added);
+        assert_eq!(files[1].hunks[0].lines.len(), 1);
+    }
+}
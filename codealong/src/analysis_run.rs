@@ -0,0 +1,136 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
+
+use chrono::prelude::*;
+use chrono::DateTime;
+
+use crate::config::Config;
+use crate::pipeline_stats::StageSummary;
+use codealong_model::Event;
+
+/// A record of an analysis run, so that any number derived from the events
+/// can be traced back to the run, and the configuration, that produced them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnalysisRun {
+    pub id: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub user: Option<String>,
+    pub host: Option<String>,
+    pub working_dir: Option<String>,
+    /// Arguments the run was invoked with.
+    pub command: Vec<String>,
+    /// Version of codealong which ran the analysis.
+    pub version: String,
+    pub config_hash: String,
+    pub repos: Vec<RunRepo>,
+    /// Number of events emitted, by event type.
+    pub event_counts: BTreeMap<String, u64>,
+    /// Throughput and queue depths of the stages commits went through.
+    #[serde(default)]
+    pub pipeline: Vec<StageSummary>,
+}
+
+/// What was analyzed of a single repo during a run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunRepo {
+    pub name: String,
+    /// Commit the analyzed history was walked from.
+    pub head: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub commits: u64,
+}
+
+impl AnalysisRun {
+    pub fn new(id: &str, started_at: DateTime<Utc>, version: &str, config: &Config) -> AnalysisRun {
+        AnalysisRun {
+            id: id.to_owned(),
+            started_at,
+            finished_at: None,
+            user: None,
+            host: None,
+            working_dir: None,
+            command: vec![],
+            version: version.to_owned(),
+            config_hash: config_hash(config),
+            repos: vec![],
+            event_counts: BTreeMap::new(),
+            pipeline: vec![],
+        }
+    }
+
+    pub fn finish(&mut self, finished_at: DateTime<Utc>, event_counts: BTreeMap<String, u64>) {
+        self.finished_at = Some(finished_at);
+        self.event_counts = event_counts;
+        self.repos.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+}
+
+impl Event for AnalysisRun {
+    fn timestamp(&self) -> &DateTime<Utc> {
+        &self.started_at
+    }
+
+    fn event_type(&self) -> &str {
+        "analysis_run"
+    }
+
+    fn id(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.id)
+    }
+
+    fn tags(&self) -> HashSet<String> {
+        HashSet::new()
+    }
+}
+
+/// A stable fingerprint of `config`, which changes whenever any setting
+/// does, so that runs with differing configurations can be told apart.
+pub fn config_hash(config: &Config) -> String {
+    // FNV-1a, since the std hashers are not stable across releases
+    let yaml = serde_yaml::to_string(config).unwrap_or_default();
+    let hash = yaml.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_hash() {
+        let config = Config::default();
+        assert_eq!(config_hash(&config), config_hash(&Config::default()));
+        assert_eq!(config_hash(&config).len(), 16);
+        let other = Config {
+            churn_cutoff: 7,
+            ..Config::default()
+        };
+        assert_ne!(config_hash(&config), config_hash(&other));
+    }
+
+    #[test]
+    fn test_finish() {
+        let mut run = AnalysisRun::new("run", Utc::now(), "0.1.0", &Config::default());
+        run.repos.push(RunRepo {
+            name: "b".to_owned(),
+            head: None,
+            since: None,
+            commits: 1,
+        });
+        run.repos.push(RunRepo {
+            name: "a".to_owned(),
+            head: None,
+            since: None,
+            commits: 2,
+        });
+        let mut counts = BTreeMap::new();
+        counts.insert("commit".to_owned(), 3);
+        run.finish(Utc::now(), counts);
+        assert!(run.finished_at.is_some());
+        assert_eq!(run.repos[0].name, "a");
+        assert_eq!(run.event_counts["commit"], 3);
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::path::Path;
+
+use chrono::prelude::*;
+use chrono::DateTime;
+
+use crate::error::*;
+
+/// What previous runs analyzed of each repo, persisted in the workspace so
+/// that a run can analyze only what changed since.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AnalysisState {
+    #[serde(default)]
+    pub repos: BTreeMap<String, RepoState>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RepoState {
+    /// Commit each ref pointed to when its history was last analyzed.
+    #[serde(default)]
+    pub refs: BTreeMap<String, String>,
+    /// Most recent `updated_at` of the pull requests analyzed.
+    #[serde(default)]
+    pub pull_requests_updated_at: Option<DateTime<Utc>>,
+}
+
+impl AnalysisState {
+    /// Reads the state saved at `path`, or an empty state if there is none.
+    pub fn from_path(path: &Path) -> Result<AnalysisState> {
+        if !path.exists() {
+            return Ok(AnalysisState::default());
+        }
+        Ok(serde_json::from_reader(File::open(path)?)?)
+    }
+
+    /// Writes the state to `path`, replacing it at once so that an
+    /// interrupted write does not lose the previous state.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp_path = path.with_extension("json.tmp");
+        serde_json::to_writer_pretty(File::create(&tmp_path)?, self)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Takes the state of the repos named `names` from `other`, e.g. to
+    /// save what a run analyzed over state saved by an overlapping run.
+    pub fn update_repos(&mut self, other: &AnalysisState, names: &[String]) {
+        for name in names {
+            if let Some(repo) = other.repos.get(name) {
+                self.repos.insert(name.clone(), repo.clone());
+            }
+        }
+    }
+
+    pub fn repo(&self, name: &str) -> Option<&RepoState> {
+        self.repos.get(name)
+    }
+
+    pub fn repo_mut(&mut self, name: &str) -> &mut RepoState {
+        self.repos.entry(name.to_owned()).or_default()
+    }
+}
+
+impl RepoState {
+    /// Records that pull requests updated up to `updated_at` were analyzed.
+    pub fn pull_requests_analyzed(&mut self, updated_at: DateTime<Utc>) {
+        self.pull_requests_updated_at = Some(match self.pull_requests_updated_at {
+            Some(previous) => previous.max(updated_at),
+            None => updated_at,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save() -> Result<()> {
+        let tmp_dir = tempdir::TempDir::new("analysis_state")?;
+        let path = tmp_dir.path().join("state.json");
+        assert_eq!(AnalysisState::from_path(&path)?, AnalysisState::default());
+
+        let mut state = AnalysisState::default();
+        let repo = state.repo_mut("kevinastone/codealong");
+        repo.refs.insert(
+            "refs/heads/master".to_owned(),
+            "86d242301830075e93ff039a4d1e88673a4a3020".to_owned(),
+        );
+        repo.pull_requests_analyzed(Utc.ymd(2019, 3, 1).and_hms(0, 0, 0));
+        repo.pull_requests_analyzed(Utc.ymd(2019, 2, 1).and_hms(0, 0, 0));
+        state.save(&path)?;
+        let read = AnalysisState::from_path(&path)?;
+        assert_eq!(read, state);
+        assert_eq!(
+            read.repo("kevinastone/codealong")
+                .unwrap()
+                .pull_requests_updated_at,
+            Some(Utc.ymd(2019, 3, 1).and_hms(0, 0, 0))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_repos() {
+        let mut saved = AnalysisState::default();
+        saved
+            .repo_mut("acme/app")
+            .pull_requests_analyzed(Utc.ymd(2019, 3, 1).and_hms(0, 0, 0));
+        saved
+            .repo_mut("acme/lib")
+            .pull_requests_analyzed(Utc.ymd(2019, 3, 1).and_hms(0, 0, 0));
+        let mut run = AnalysisState::default();
+        run.repo_mut("acme/app")
+            .pull_requests_analyzed(Utc.ymd(2019, 4, 1).and_hms(0, 0, 0));
+        run.repo_mut("acme/lib");
+        saved.update_repos(&run, &["acme/app".to_owned()]);
+        assert_eq!(saved.repo("acme/app"), run.repo("acme/app"));
+        assert!(saved
+            .repo("acme/lib")
+            .unwrap()
+            .pull_requests_updated_at
+            .is_some());
+    }
+}
@@ -0,0 +1,119 @@
+use std::thread;
+
+use futures::sync::mpsc::{channel, Receiver, Sender};
+use futures::{Async, Future, Poll, Sink, Stream};
+
+/// A `futures::Stream` of the results of an analysis run on a thread of its
+/// own, e.g. that of the commits of a repo, so that consumers can interleave
+/// it with other work on an executor such as tokio. At most `buffer` results
+/// are produced ahead of the consumer, and dropping the stream stops the
+/// analysis once the result being produced is done. An error does not end
+/// the stream: the results after it are still yielded.
+pub struct AnalysisStream<T, E> {
+    receiver: Receiver<Result<T, E>>,
+}
+
+/// The end of an `AnalysisStream` the analysis emits its results to.
+pub struct Emitter<T, E> {
+    sender: Option<Sender<Result<T, E>>>,
+}
+
+impl<T, E> AnalysisStream<T, E>
+where
+    T: Send + 'static,
+    E: Send + 'static,
+{
+    /// Runs `analysis` on a thread of its own, streaming what it emits.
+    pub fn spawn<F>(buffer: usize, analysis: F) -> AnalysisStream<T, E>
+    where
+        F: FnOnce(&mut Emitter<T, E>) + Send + 'static,
+    {
+        let (sender, receiver) = channel(buffer);
+        thread::spawn(move || {
+            analysis(&mut Emitter {
+                sender: Some(sender),
+            })
+        });
+        AnalysisStream { receiver }
+    }
+}
+
+impl<T, E> Emitter<T, E> {
+    /// Blocks until the consumer has room for `result`. Returns false if the
+    /// stream was dropped, in which case the analysis should stop.
+    pub fn emit(&mut self, result: Result<T, E>) -> bool {
+        match self.sender.take() {
+            Some(sender) => match sender.send(result).wait() {
+                Ok(sender) => {
+                    self.sender = Some(sender);
+                    true
+                }
+                Err(_) => false,
+            },
+            None => false,
+        }
+    }
+}
+
+impl<T, E> Stream for AnalysisStream<T, E> {
+    type Item = T;
+    type Error = E;
+
+    fn poll(&mut self) -> Poll<Option<T>, E> {
+        match self.receiver.poll() {
+            Ok(Async::Ready(Some(Ok(item)))) => Ok(Async::Ready(Some(item))),
+            Ok(Async::Ready(Some(Err(e)))) => Err(e),
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Ok(Async::Ready(None)) | Err(()) => Ok(Async::Ready(None)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_stream() {
+        let stream: AnalysisStream<u32, String> = AnalysisStream::spawn(1, |emitter| {
+            for i in 0..3 {
+                emitter.emit(Ok(i));
+            }
+            emitter.emit(Err("failed".to_owned()));
+            emitter.emit(Ok(3));
+        });
+        let results: Vec<std::result::Result<u32, String>> = stream
+            .then(Ok::<_, ()>)
+            .wait()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(
+            results,
+            vec![Ok(0), Ok(1), Ok(2), Err("failed".to_owned()), Ok(3)]
+        );
+    }
+
+    #[test]
+    fn test_cancel() {
+        let emitted = Arc::new(AtomicUsize::new(0));
+        let (done_sender, done) = std::sync::mpsc::channel();
+        let stream: AnalysisStream<usize, ()> = {
+            let emitted = emitted.clone();
+            AnalysisStream::spawn(1, move |emitter| {
+                for i in 0..1000 {
+                    if !emitter.emit(Ok(i)) {
+                        break;
+                    }
+                    emitted.fetch_add(1, Ordering::SeqCst);
+                }
+                done_sender.send(()).unwrap();
+            })
+        };
+        let first: Vec<usize> = stream.take(2).collect().wait().unwrap();
+        assert_eq!(first, vec![0, 1]);
+        done.recv().unwrap();
+        assert!(emitted.load(Ordering::SeqCst) < 1000);
+    }
+}
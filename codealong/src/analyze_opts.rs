@@ -5,6 +5,37 @@ use chrono::DateTime;
 pub struct AnalyzeOpts {
     pub ignore_unknown_authors: bool,
     pub since: Option<DateTime<Utc>>,
+    /// Only analyze commits added since the last checkpoint.
+    pub resume: bool,
+    /// Stop analyzing once this time has passed. Commits are analyzed oldest
+    /// first when set so that an interrupted analysis can be resumed from
+    /// the last commit analyzed.
+    #[serde(default)]
+    pub deadline: Option<DateTime<Utc>>,
+    /// Only analyze commits reachable from the default branch of the repo,
+    /// rather than from all the configured refs, so that work in progress on
+    /// feature branches is not counted.
+    #[serde(default)]
+    pub default_branch_only: bool,
+    /// Only analyze commits reachable from this branch, which takes
+    /// precedence over `default_branch_only`. How each commit landed is
+    /// recorded against this branch rather than the default branch.
+    #[serde(default)]
+    pub branch: Option<String>,
+    /// Only follow the first parent of merges, so that the commits of merged
+    /// branches are left out and only the merges themselves are analyzed.
+    #[serde(default)]
+    pub first_parent: bool,
+    /// Heads analyzed by a previous run. Commits reachable from them are
+    /// skipped, so that only new commits are analyzed.
+    #[serde(default)]
+    pub analyzed_heads: Vec<String>,
+}
+
+impl AnalyzeOpts {
+    pub fn is_past_deadline(&self) -> bool {
+        self.deadline.is_some_and(|deadline| Utc::now() >= deadline)
+    }
 }
 
 impl Default for AnalyzeOpts {
@@ -12,6 +43,12 @@ impl Default for AnalyzeOpts {
         AnalyzeOpts {
             ignore_unknown_authors: false,
             since: None,
+            resume: false,
+            deadline: None,
+            default_branch_only: false,
+            branch: None,
+            first_parent: false,
+            analyzed_heads: vec![],
         }
     }
 }
@@ -1,13 +1,25 @@
 use crate::analyzed_diff::AnalyzedDiff;
-use crate::event::Event;
+use crate::compliance::Compliance;
+use crate::defect::DefectOrigin;
 use crate::identity::Identity;
-use crate::person::Person;
+use crate::imported::ImportedRevision;
+use crate::landing::LandedVia;
+use crate::notes::Note;
 use crate::repo_info::PartialRepoInfo;
+use crate::risk::{Hotspots, Risk};
+use crate::signature::CommitSignature;
+use crate::text::{commit_message, commit_summary};
 use crate::utils::convert_time;
+use crate::work_type::WorkType;
+use codealong_model::{
+    metric_versions, Event, MetricVersions, Person, LIKELY_REFACTOR, LINES_CHANGED, WORK_STATS,
+    WORK_TYPE,
+};
 
 use chrono::prelude::*;
 use chrono::DateTime;
 use git2::Commit;
+use linked_hash_map::LinkedHashMap;
 use std::borrow::Cow;
 use std::collections::HashSet;
 use std::iter::FromIterator;
@@ -15,36 +27,144 @@ use std::iter::FromIterator;
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AnalyzedCommit {
     pub id: String,
+    pub parents: Vec<String>,
     #[serde(flatten)]
     pub diff: AnalyzedDiff,
     pub summary: Option<String>,
     pub author: Identity,
     pub authored_at: DateTime<Utc>,
     pub normalized_author: Option<Person>,
+    /// Id of the normalized author, the same across all the emails and
+    /// names the author committed under.
+    pub author_id: Option<String>,
+    /// People credited through `Co-authored-by` trailers.
+    pub co_authors: Vec<Person>,
     pub committer: Identity,
     pub committed_at: DateTime<Utc>,
     pub normalized_committer: Option<Person>,
     pub repo: Option<PartialRepoInfo>,
     pub github_url: Option<String>,
+    pub likely_refactor: bool,
+    pub bug_fix: bool,
+    #[serde(default)]
+    pub work_type: WorkType,
+    /// Bot or package manager which made the update, for dependency
+    /// updates.
+    #[serde(default)]
+    pub dependency_tool: Option<String>,
+    /// Commits suspected of introducing the bug fixed by this commit.
+    pub defect_origins: Vec<DefectOrigin>,
+    /// Only present when required trailers are configured.
+    pub compliance: Option<Compliance>,
+    #[serde(flatten)]
+    pub signature: CommitSignature,
+    /// Whether the author signed off on the commit per the DCO.
+    pub dco_signed_off: bool,
+    /// Svn or Perforce origin of commits converted by `git-svn` or `git-p4`.
+    pub imported: Option<ImportedRevision>,
+    /// Set when the commit is no longer reachable after a history rewrite.
+    pub orphaned: bool,
+    /// Id of the `git replace` replacement analyzed in place of this commit.
+    pub replaced_by: Option<String>,
+    /// Notes from the configured notes refs.
+    pub notes: LinkedHashMap<String, Note>,
+    /// Set for commits on the first parent history of the default branch
+    /// when checked against merged pull requests: whether the commit landed
+    /// without one, bypassing review.
+    pub direct_push: Option<bool>,
+    /// Short name of the branch the commit was analyzed against, the
+    /// default branch unless another was given, if the commit landed on it.
+    pub target_branch: Option<String>,
+    /// How the commit landed on `target_branch`.
+    pub landed_via: Option<LandedVia>,
+    /// Risk of the commit when assessed against the hotspots of the repo.
+    pub risk: Option<Risk>,
+    /// Share of the lines the commit added which still exist at the head the
+    /// survival of lines was computed at, if it was.
+    pub survival_rate: Option<f64>,
+    /// Work session of the author the commit belongs to, if effort was
+    /// estimated.
+    pub session_id: Option<String>,
+    /// Seconds of the work session estimated to have been spent on the
+    /// commit.
+    pub effort: Option<i64>,
+    /// References to tickets and issues in the message, e.g. `ABC-123`.
+    #[serde(default)]
+    pub issues: Vec<String>,
+    /// Conventional Commits type of the message, e.g. `feat` or `fix`.
+    pub commit_type: Option<String>,
+    /// Conventional Commits scope of the message, e.g. `parser` in
+    /// `feat(parser): ...`.
+    pub scope: Option<String>,
+    /// Whether the message marks the commit as a breaking change.
+    #[serde(default)]
+    pub breaking: bool,
+    /// Trailers of the message keyed by their token in snake case, e.g.
+    /// `reviewed_by` for `Reviewed-by`.
+    #[serde(default)]
+    pub trailers: LinkedHashMap<String, Vec<String>>,
+    #[serde(default)]
+    pub metric_versions: MetricVersions,
+    /// Values of the configured `promoted_trailers`, as top-level fields.
+    #[serde(flatten)]
+    pub promoted_trailers: LinkedHashMap<String, String>,
 }
 
 impl AnalyzedCommit {
     pub fn new(commit: &Commit) -> AnalyzedCommit {
         AnalyzedCommit {
             id: commit.id().to_string(),
+            parents: commit.parent_ids().map(|id| id.to_string()).collect(),
             diff: AnalyzedDiff::empty(),
-            summary: commit.summary().map(|s| s.to_string()),
+            summary: commit_summary(commit),
             author: Identity::from(commit.author()),
             authored_at: convert_time(&commit.author().when()),
             normalized_author: None,
+            author_id: None,
+            co_authors: vec![],
             committer: Identity::from(commit.committer()),
             committed_at: convert_time(&commit.committer().when()),
             normalized_committer: None,
             repo: None,
             github_url: None,
+            likely_refactor: false,
+            bug_fix: false,
+            work_type: WorkType::Other,
+            dependency_tool: None,
+            defect_origins: vec![],
+            compliance: None,
+            signature: CommitSignature::default(),
+            dco_signed_off: false,
+            imported: ImportedRevision::parse(&commit_message(commit)),
+            orphaned: false,
+            replaced_by: None,
+            notes: LinkedHashMap::new(),
+            direct_push: None,
+            target_branch: None,
+            landed_via: None,
+            risk: None,
+            survival_rate: None,
+            session_id: None,
+            effort: None,
+            issues: vec![],
+            commit_type: None,
+            scope: None,
+            breaking: false,
+            trailers: LinkedHashMap::new(),
+            metric_versions: metric_versions(&[
+                WORK_STATS,
+                LINES_CHANGED,
+                LIKELY_REFACTOR,
+                WORK_TYPE,
+            ]),
+            promoted_trailers: LinkedHashMap::new(),
         }
     }
 
+    pub fn assess_risk(&mut self, hotspots: &Hotspots) {
+        self.risk = Some(Risk::assess(&self.diff, hotspots));
+    }
+
     pub fn merge_diff(&mut self, diff: &AnalyzedDiff) {
         self.diff = &self.diff + diff;
     }
@@ -1,11 +1,74 @@
-use crate::work_stats::WorkStats;
-use std::collections::HashMap;
+use crate::complexity::ComplexityDelta;
+use crate::diff_sampling::DiffSnippet;
+use crate::lfs::LfsStats;
+use codealong_metrics::LanguageStats;
+use codealong_metrics::LineStats;
+use codealong_metrics::WorkStats;
+use std::collections::{BTreeMap, HashMap};
 use std::ops::{Add, AddAssign};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct AnalyzedDiff {
     pub stats: WorkStats,
     pub tag_stats: HashMap<String, WorkStats>,
+    #[serde(default)]
+    pub line_stats: LineStats,
+    #[serde(default)]
+    pub lfs: LfsStats,
+    /// Stats of each file changed, other than ignored ones.
+    #[serde(default)]
+    pub files: Vec<FileStats>,
+    /// Stats of the files changed in each language, other than ignored ones.
+    #[serde(default)]
+    pub language_stats: BTreeMap<String, LanguageStats>,
+    /// Redacted excerpts of the changed lines, if the diff was sampled.
+    #[serde(default)]
+    pub snippets: Vec<DiffSnippet>,
+}
+
+/// Stats of a single changed file, along with the tags and weight of the
+/// globs its path matches.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileStats {
+    /// Path of the file as of the commit.
+    pub path: String,
+    /// Path the file had in the parent, if it was renamed, or that of the
+    /// file it was copied from.
+    #[serde(default)]
+    pub old_path: Option<String>,
+    #[serde(default)]
+    pub renamed: bool,
+    #[serde(default)]
+    pub copied: bool,
+    /// Path the file was added with, following its renames back through
+    /// history, so that its metrics carry over when it moves.
+    #[serde(default)]
+    pub canonical_path: Option<String>,
+    pub tags: Vec<String>,
+    pub weight: f64,
+    /// Declared by the globs the path matches, or else detected from the
+    /// path and content of the file.
+    #[serde(default)]
+    pub language: Option<String>,
+    pub stats: WorkStats,
+    /// How the size and complexity of the file changed, unless it is
+    /// binary.
+    #[serde(default)]
+    pub complexity: Option<ComplexityDelta>,
+    /// Why the lines of the file were not analyzed, if they were not.
+    #[serde(default)]
+    pub skipped_reason: Option<SkippedReason>,
+}
+
+/// Why the lines of a changed file were not analyzed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkippedReason {
+    /// Either version of the file is a binary blob, which has no lines.
+    Binary,
+    /// Either version of the file is larger than the `max_size` of
+    /// `large_files`.
+    TooLarge,
 }
 
 impl AnalyzedDiff {
@@ -32,6 +95,11 @@ impl Default for AnalyzedDiff {
         AnalyzedDiff {
             stats: WorkStats::empty(),
             tag_stats: HashMap::new(),
+            line_stats: LineStats::empty(),
+            lfs: LfsStats::empty(),
+            files: vec![],
+            language_stats: BTreeMap::new(),
+            snippets: vec![],
         }
     }
 }
@@ -67,6 +135,16 @@ impl<'a, 'b> Add<&'b AnalyzedDiff> for &'a AnalyzedDiff {
         AnalyzedDiff {
             stats: self.stats + other.stats,
             tag_stats: merge_tag_stats(&self.tag_stats, &other.tag_stats),
+            line_stats: self.line_stats + other.line_stats,
+            lfs: self.lfs + other.lfs,
+            files: self.files.iter().chain(&other.files).cloned().collect(),
+            language_stats: merge_language_stats(&self.language_stats, &other.language_stats),
+            snippets: self
+                .snippets
+                .iter()
+                .chain(&other.snippets)
+                .cloned()
+                .collect(),
         }
     }
 }
@@ -81,6 +159,11 @@ impl<'a> AddAssign<&'a AnalyzedDiff> for AnalyzedDiff {
     fn add_assign(&mut self, other: &'a AnalyzedDiff) {
         self.stats += other.stats;
         self.tag_stats = merge_tag_stats(&self.tag_stats, &other.tag_stats);
+        self.line_stats += other.line_stats;
+        self.lfs += other.lfs;
+        self.files.extend(other.files.iter().cloned());
+        self.language_stats = merge_language_stats(&self.language_stats, &other.language_stats);
+        self.snippets.extend(other.snippets.iter().cloned());
     }
 }
 
@@ -96,6 +179,17 @@ fn merge_tag_stats(
     res
 }
 
+fn merge_language_stats(
+    a: &BTreeMap<String, LanguageStats>,
+    b: &BTreeMap<String, LanguageStats>,
+) -> BTreeMap<String, LanguageStats> {
+    let mut res = a.clone();
+    for (language, stats) in b {
+        *res.entry(language.to_owned()).or_default() += *stats;
+    }
+    res
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -107,6 +201,7 @@ mod tests {
         let diff = AnalyzedDiff {
             stats: WorkStats::new_work(),
             tag_stats,
+            ..AnalyzedDiff::empty()
         };
 
         let mut tag_stats2 = HashMap::new();
@@ -114,6 +209,7 @@ mod tests {
         let diff2 = AnalyzedDiff {
             stats: WorkStats::new_work(),
             tag_stats: tag_stats2,
+            ..AnalyzedDiff::empty()
         };
 
         let result = diff + diff2;
@@ -0,0 +1,146 @@
+/// A shields style badge showing a single metric, for embedding in a README.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Badge {
+    pub label: String,
+    pub message: String,
+    pub color: &'static str,
+}
+
+const GREEN: &str = "#4c1";
+const YELLOW: &str = "#dfb317";
+const RED: &str = "#e05d44";
+const BLUE: &str = "#007ec6";
+const GREY: &str = "#9f9f9f";
+
+impl Badge {
+    pub fn new(label: &str, message: &str, color: &'static str) -> Badge {
+        Badge {
+            label: label.to_owned(),
+            message: message.to_owned(),
+            color,
+        }
+    }
+
+    /// Badge for the smallest number of authors who together made more than
+    /// half of the `commit_counts`, which are commits per author.
+    pub fn bus_factor(commit_counts: &[u64]) -> Badge {
+        let factor = bus_factor(commit_counts);
+        let color = match factor {
+            0 => GREY,
+            1 => RED,
+            2 => YELLOW,
+            _ => GREEN,
+        };
+        Badge::new("bus factor", &factor.to_string(), color)
+    }
+
+    pub fn active_contributors(count: u64) -> Badge {
+        Badge::new("active contributors", &count.to_string(), BLUE)
+    }
+
+    /// Badge for the typical number of seconds pull requests wait to be
+    /// merged, if any were.
+    pub fn review_latency(seconds: Option<f64>) -> Badge {
+        match seconds {
+            Some(seconds) => {
+                let hours = seconds / 3600.0;
+                let color = if hours < 24.0 {
+                    GREEN
+                } else if hours < 72.0 {
+                    YELLOW
+                } else {
+                    RED
+                };
+                let message = if hours < 24.0 {
+                    format!("{:.1}h", hours)
+                } else {
+                    format!("{:.1}d", hours / 24.0)
+                };
+                Badge::new("review latency", &message, color)
+            }
+            None => Badge::new("review latency", "n/a", GREY),
+        }
+    }
+
+    pub fn to_svg(&self) -> String {
+        let label_width = text_width(&self.label);
+        let message_width = text_width(&self.message);
+        let width = label_width + message_width;
+        format!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{label}: {message}">
+  <title>{label}: {message}</title>
+  <linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#bbb" stop-opacity=".1"/><stop offset="1" stop-opacity=".1"/></linearGradient>
+  <clipPath id="r"><rect width="{width}" height="20" rx="3" fill="#fff"/></clipPath>
+  <g clip-path="url(#r)">
+    <rect width="{label_width}" height="20" fill="#555"/>
+    <rect x="{label_width}" width="{message_width}" height="20" fill="{color}"/>
+    <rect width="{width}" height="20" fill="url(#s)"/>
+  </g>
+  <g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11">
+    <text x="{label_x}" y="14">{label}</text>
+    <text x="{message_x}" y="14">{message}</text>
+  </g>
+</svg>
+"##,
+            width = width,
+            label_width = label_width,
+            message_width = message_width,
+            label_x = label_width / 2,
+            message_x = label_width + message_width / 2,
+            label = escape(&self.label),
+            message = escape(&self.message),
+            color = self.color,
+        )
+    }
+}
+
+/// The smallest number of the largest `counts` which sum to more than half of
+/// the total.
+pub fn bus_factor(counts: &[u64]) -> usize {
+    let mut counts = counts.to_vec();
+    counts.sort_unstable_by(|a, b| b.cmp(a));
+    let total: u64 = counts.iter().sum();
+    let mut covered = 0;
+    for (i, count) in counts.iter().enumerate() {
+        covered += count;
+        if covered * 2 > total {
+            return i + 1;
+        }
+    }
+    0
+}
+
+/// Approximate width in pixels of `text` in 11px Verdana, plus padding.
+fn text_width(text: &str) -> usize {
+    text.chars().count() * 7 + 10
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bus_factor() {
+        assert_eq!(bus_factor(&[]), 0);
+        assert_eq!(bus_factor(&[10, 1, 1]), 1);
+        assert_eq!(bus_factor(&[3, 5, 2, 1]), 2);
+        assert_eq!(Badge::bus_factor(&[1, 1, 1, 1, 1]).message, "3");
+    }
+
+    #[test]
+    fn test_to_svg() {
+        let badge = Badge::review_latency(Some(2.0 * 86400.0));
+        assert_eq!(badge.message, "2.0d");
+        assert_eq!(badge.color, YELLOW);
+        let svg = Badge::new("a<b", "1", GREEN).to_svg();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("a&lt;b: 1"));
+    }
+}
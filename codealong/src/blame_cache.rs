@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use git2::Oid;
+use sha2::{Digest, Sha256};
+
+use crate::diff_analyzer::RenameDetection;
+use crate::git_blame::BlameEngine;
+use crate::replace_refs::ReplaceRefs;
+use crate::utils::hex;
+
+/// Maximum number of blames kept in memory. Consecutive commits mostly touch
+/// the same few files, so recent blames are the ones which get reused.
+const CAPACITY: usize = 512;
+
+/// Commit which last changed each line of a file, by line number.
+pub type BlameLines = Arc<HashMap<usize, Oid>>;
+
+lazy_static! {
+    /// Shared by every analyzer of the process, including those running on
+    /// other threads.
+    static ref CACHE: Mutex<Cache> = Mutex::new(Cache::default());
+}
+
+/// Blames along with when they were last used, so that the least recently
+/// used one is evicted first.
+#[derive(Default)]
+struct Cache {
+    blames: HashMap<BlameKey, (BlameLines, u64)>,
+    clock: u64,
+}
+
+impl Cache {
+    fn get(&mut self, key: &BlameKey) -> Option<BlameLines> {
+        self.clock += 1;
+        let clock = self.clock;
+        self.blames.get_mut(key).map(|(lines, used)| {
+            *used = clock;
+            lines.clone()
+        })
+    }
+
+    fn insert(&mut self, key: BlameKey, lines: BlameLines) {
+        self.clock += 1;
+        self.blames.insert(key, (lines, self.clock));
+        if self.blames.len() > CAPACITY {
+            let oldest = self
+                .blames
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.blames.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// Distinguishes temporary files written concurrently by one process.
+static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// What a blame depends on: the file, the commit it is run against and how
+/// history is walked from there.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BlameKey {
+    pub parent: Oid,
+    pub path: PathBuf,
+    /// Unix timestamp before which history is not walked, if any.
+    pub since: Option<i64>,
+    pub replace_refs: ReplaceRefs,
+    pub engine: BlameEngine,
+    /// Whether lines are followed when moved or copied across files.
+    pub rename_detection: RenameDetection,
+}
+
+impl BlameKey {
+    fn file_name(&self) -> String {
+        let key = format!(
+            "{}:{:?}:{}:{:?}:{:?}:{:?}",
+            self.parent,
+            self.since,
+            self.path.display(),
+            self.replace_refs,
+            self.engine,
+            self.rename_detection
+        );
+        hex(&Sha256::digest(key.as_bytes()))
+    }
+}
+
+/// Looks up a blame in memory and then, if `dir` is given, on disk.
+pub fn get(key: &BlameKey, dir: Option<&Path>) -> Option<BlameLines> {
+    if let Some(lines) = CACHE.lock().unwrap().get(key) {
+        return Some(lines);
+    }
+    let lines = Arc::new(read(&dir?.join(key.file_name()))?);
+    CACHE.lock().unwrap().insert(key.clone(), lines.clone());
+    Some(lines)
+}
+
+/// Caches a complete blame in memory and, if `dir` is given, on disk.
+pub fn insert(key: BlameKey, lines: BlameLines, dir: Option<&Path>) {
+    if let Some(dir) = dir {
+        // the cache only saves work, so failing to write it is not an error
+        let _ = write(&dir.join(key.file_name()), &lines);
+    }
+    CACHE.lock().unwrap().insert(key, lines);
+}
+
+/// Reads a blame written by `write`, one `<line number> <commit id>` per
+/// line.
+fn read(path: &Path) -> Option<HashMap<usize, Oid>> {
+    let contents = fs::read_to_string(path).ok()?;
+    let mut lines = HashMap::new();
+    for line in contents.lines() {
+        let mut parts = line.splitn(2, ' ');
+        let lineno = parts.next()?.parse().ok()?;
+        let oid = Oid::from_str(parts.next()?).ok()?;
+        lines.insert(lineno, oid);
+    }
+    Some(lines)
+}
+
+fn write(path: &Path, lines: &HashMap<usize, Oid>) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut contents = String::new();
+    for (lineno, oid) in lines {
+        contents.push_str(&format!("{} {}\n", lineno, oid));
+    }
+    // written aside and renamed so that readers never see a partial blame
+    let tmp_path = path.with_extension(format!(
+        "{}.{}.tmp",
+        std::process::id(),
+        TMP_COUNTER.fetch_add(1, Ordering::SeqCst)
+    ));
+    fs::write(&tmp_path, contents)?;
+    fs::rename(&tmp_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(path: &str) -> BlameKey {
+        BlameKey {
+            parent: Oid::from_str("86d242301830075e93ff039a4d1e88673a4a3020").unwrap(),
+            path: PathBuf::from(path),
+            since: None,
+            replace_refs: ReplaceRefs::Honor,
+            engine: BlameEngine::External,
+            rename_detection: RenameDetection::Copies,
+        }
+    }
+
+    #[test]
+    fn test_disk_cache() {
+        let tmp_dir = tempdir::TempDir::new("blame_cache").unwrap();
+        let mut lines = HashMap::new();
+        lines.insert(1, key("").parent);
+        insert(
+            key("cached_on_disk.rs"),
+            Arc::new(lines.clone()),
+            Some(tmp_dir.path()),
+        );
+        CACHE
+            .lock()
+            .unwrap()
+            .blames
+            .remove(&key("cached_on_disk.rs"));
+
+        assert_eq!(get(&key("cached_on_disk.rs"), None), None);
+        assert_eq!(
+            get(&key("cached_on_disk.rs"), Some(tmp_dir.path())),
+            Some(Arc::new(lines))
+        );
+        // and now from memory
+        assert!(get(&key("cached_on_disk.rs"), None).is_some());
+        assert_eq!(get(&key("uncached.rs"), Some(tmp_dir.path())), None);
+    }
+
+    #[test]
+    fn test_evict_least_recently_used() {
+        let mut cache = Cache::default();
+        for i in 0..=CAPACITY {
+            if i == CAPACITY {
+                // refreshes the first, so the second is evicted
+                cache.get(&key("0"));
+            }
+            cache.insert(key(&i.to_string()), Arc::new(HashMap::new()));
+        }
+        assert_eq!(cache.blames.len(), CAPACITY);
+        assert!(cache.get(&key("0")).is_some());
+        assert!(cache.get(&key("1")).is_none());
+    }
+}
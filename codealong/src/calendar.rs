@@ -0,0 +1,172 @@
+use chrono::prelude::*;
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate};
+
+/// A business calendar used to measure latencies in working time rather
+/// than wall-clock time, so that e.g. a pull request opened on a Friday
+/// evening is not penalized for the weekend.
+///
+/// Example configuration:
+///
+/// ```yaml
+/// calendar:
+///   utc_offset: -7
+///   workday_start: 9
+///   workday_end: 17
+///   weekend: ["Sat", "Sun"]
+///   holidays:
+///     - "2019-12-25"
+///   leave:
+///     - start: "2019-12-23"
+///       end: "2019-12-27"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Calendar {
+    /// Offset from UTC, in hours, of the timezone the calendar is in.
+    #[serde(default)]
+    pub utc_offset: i32,
+
+    /// Hour of the day at which the working day starts.
+    #[serde(default = "Calendar::default_workday_start")]
+    pub workday_start: u32,
+
+    /// Hour of the day at which the working day ends.
+    #[serde(default = "Calendar::default_workday_end")]
+    pub workday_end: u32,
+
+    #[serde(default = "Calendar::default_weekend")]
+    pub weekend: Vec<Weekday>,
+
+    #[serde(default)]
+    pub holidays: Vec<NaiveDate>,
+
+    /// Team-wide leave, e.g. a company shutdown or offsite.
+    #[serde(default)]
+    pub leave: Vec<Leave>,
+}
+
+/// An inclusive range of days off.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Leave {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+impl Calendar {
+    fn default_workday_start() -> u32 {
+        9
+    }
+
+    fn default_workday_end() -> u32 {
+        17
+    }
+
+    fn default_weekend() -> Vec<Weekday> {
+        vec![Weekday::Sat, Weekday::Sun]
+    }
+
+    pub fn is_business_day(&self, date: NaiveDate) -> bool {
+        !self.weekend.contains(&date.weekday())
+            && !self.holidays.contains(&date)
+            && !self
+                .leave
+                .iter()
+                .any(|leave| leave.start <= date && date <= leave.end)
+    }
+
+    /// Length of a single working day in seconds.
+    pub fn workday_seconds(&self) -> i64 {
+        i64::from(self.workday_end.saturating_sub(self.workday_start)) * 3600
+    }
+
+    /// Number of working seconds elapsed between `start` and `end`.
+    pub fn business_seconds(&self, start: &DateTime<Utc>, end: &DateTime<Utc>) -> i64 {
+        if end <= start {
+            return 0;
+        }
+        let offset = FixedOffset::east(self.utc_offset * 3600);
+        let local_start = start.with_timezone(&offset);
+        let local_end = end.with_timezone(&offset);
+        let mut date = local_start.date();
+        let mut res = 0;
+        while date <= local_end.date() {
+            if self.is_business_day(date.naive_local()) {
+                let open = date.and_hms(self.workday_start, 0, 0);
+                let close = date.and_hms(0, 0, 0) + Duration::hours(i64::from(self.workday_end));
+                let from = std::cmp::max(open, local_start);
+                let to = std::cmp::min(close, local_end);
+                if to > from {
+                    res += (to - from).num_seconds();
+                }
+            }
+            date = date.succ();
+        }
+        res
+    }
+
+    /// Number of working days elapsed between `start` and `end`.
+    pub fn business_days(&self, start: &DateTime<Utc>, end: &DateTime<Utc>) -> f64 {
+        let workday = self.workday_seconds();
+        if workday == 0 {
+            0.0
+        } else {
+            self.business_seconds(start, end) as f64 / workday as f64
+        }
+    }
+}
+
+impl Default for Calendar {
+    fn default() -> Calendar {
+        Calendar {
+            utc_offset: 0,
+            workday_start: Calendar::default_workday_start(),
+            workday_end: Calendar::default_workday_end(),
+            weekend: Calendar::default_weekend(),
+            holidays: vec![],
+            leave: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_business_seconds() {
+        let calendar = Calendar {
+            holidays: vec![NaiveDate::from_ymd(2019, 12, 25)],
+            ..Calendar::default()
+        };
+        // Friday 16:00 to Monday 10:00 is two working hours
+        let friday = Utc.ymd(2019, 12, 20).and_hms(16, 0, 0);
+        let monday = Utc.ymd(2019, 12, 23).and_hms(10, 0, 0);
+        assert_eq!(calendar.business_seconds(&friday, &monday), 2 * 3600);
+        // Christmas is skipped
+        let thursday = Utc.ymd(2019, 12, 26).and_hms(17, 0, 0);
+        assert_eq!(calendar.business_days(&monday, &thursday), 2.875);
+        assert_eq!(calendar.business_seconds(&monday, &friday), 0);
+    }
+
+    #[test]
+    fn test_utc_offset() {
+        let calendar = Calendar {
+            utc_offset: -7,
+            ..Calendar::default()
+        };
+        // 09:00 to 18:00 UTC is 02:00 to 11:00 in UTC-7
+        let start = Utc.ymd(2019, 12, 18).and_hms(9, 0, 0);
+        let end = Utc.ymd(2019, 12, 18).and_hms(18, 0, 0);
+        assert_eq!(calendar.business_seconds(&start, &end), 2 * 3600);
+    }
+
+    #[test]
+    fn test_deserialization() {
+        let calendar: Calendar = serde_yaml::from_str(
+            "weekend: [\"Fri\", \"Sat\"]\nleave:\n  - start: \"2019-12-23\"\n    end: \"2019-12-27\"\n",
+        )
+        .unwrap();
+        assert_eq!(calendar.weekend, vec![Weekday::Fri, Weekday::Sat]);
+        assert!(!calendar.is_business_day(NaiveDate::from_ymd(2019, 12, 24)));
+        assert!(calendar.is_business_day(NaiveDate::from_ymd(2019, 12, 22)));
+    }
+}
@@ -0,0 +1,141 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+use chrono::prelude::*;
+use chrono::DateTime;
+use git2::{Oid, Repository};
+
+use crate::error::*;
+use crate::repo_info::PartialRepoInfo;
+use codealong_model::Event;
+
+/// Ref recording the head which was last analyzed. Since it is a ref, it also
+/// keeps rewritten commits from being garbage collected so that they can be
+/// reported as orphaned.
+pub const CHECKPOINT_REF: &str = "refs/codealong/checkpoint";
+
+pub fn read_checkpoint(repo: &Repository) -> Option<Oid> {
+    repo.refname_to_id(CHECKPOINT_REF).ok()
+}
+
+pub fn write_checkpoint(repo: &Repository, oid: Oid) -> Result<()> {
+    repo.reference(CHECKPOINT_REF, oid, true, "codealong checkpoint")?;
+    Ok(())
+}
+
+/// Emitted when the checkpoint is no longer reachable from the analyzed refs,
+/// e.g. after a force push.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryRewrite {
+    pub id: String,
+    pub repo: Option<PartialRepoInfo>,
+    pub checkpoint: String,
+    pub heads: Vec<String>,
+    /// Most recent commit shared by the old and new history, if any.
+    pub merge_base: Option<String>,
+    /// Commits analyzed previously which are no longer part of the history.
+    pub orphaned_commits: Vec<String>,
+    pub detected_at: DateTime<Utc>,
+}
+
+impl HistoryRewrite {
+    /// Checks whether `checkpoint` is still reachable from `heads`.
+    pub fn detect(
+        repo: &Repository,
+        checkpoint: Oid,
+        heads: &[Oid],
+    ) -> Result<Option<HistoryRewrite>> {
+        for head in heads {
+            if *head == checkpoint || repo.graph_descendant_of(*head, checkpoint).unwrap_or(false) {
+                return Ok(None);
+            }
+        }
+
+        let mut merge_base = None;
+        let mut orphaned_commits = vec![];
+        // the old commits are gone entirely if the checkpoint was lost
+        if repo.find_commit(checkpoint).is_ok() {
+            merge_base = heads
+                .iter()
+                .filter_map(|head| repo.merge_base(*head, checkpoint).ok())
+                .filter_map(|oid| repo.find_commit(oid).ok())
+                .max_by_key(|commit| commit.time())
+                .map(|commit| commit.id());
+            let mut revwalk = repo.revwalk()?;
+            revwalk.push(checkpoint)?;
+            for head in heads {
+                revwalk.hide(*head)?;
+            }
+            for oid in revwalk {
+                orphaned_commits.push(oid?.to_string());
+            }
+        }
+
+        Ok(Some(HistoryRewrite {
+            id: format!(
+                "{}-{}",
+                checkpoint,
+                heads.first().cloned().unwrap_or_else(Oid::zero)
+            ),
+            repo: None,
+            checkpoint: checkpoint.to_string(),
+            heads: heads.iter().map(|oid| oid.to_string()).collect(),
+            merge_base: merge_base.map(|oid| oid.to_string()),
+            orphaned_commits,
+            detected_at: Utc::now(),
+        }))
+    }
+}
+
+impl Event for HistoryRewrite {
+    fn timestamp(&self) -> &DateTime<Utc> {
+        &self.detected_at
+    }
+
+    fn event_type(&self) -> &str {
+        "history_rewrite"
+    }
+
+    fn id(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.id)
+    }
+
+    fn tags(&self) -> HashSet<String> {
+        HashSet::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(repo: &Repository, parents: &[Oid], message: &str) -> Result<Oid> {
+        let sig = git2::Signature::new("Test", "test@example.com", &git2::Time::new(0, 0))?;
+        let tree = repo.find_tree(repo.treebuilder(None)?.write()?)?;
+        let parents = parents
+            .iter()
+            .map(|oid| repo.find_commit(*oid))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let parents: Vec<_> = parents.iter().collect();
+        Ok(repo.commit(None, &sig, &sig, message, &tree, &parents)?)
+    }
+
+    #[test]
+    fn test_detect() -> Result<()> {
+        let tmp_dir = tempdir::TempDir::new("checkpoint")?;
+        let repo = Repository::init(tmp_dir.path())?;
+        let base = commit(&repo, &[], "base")?;
+        let old = commit(&repo, &[base], "old")?;
+        let new = commit(&repo, &[base], "new")?;
+        let next = commit(&repo, &[old], "next")?;
+
+        write_checkpoint(&repo, old)?;
+        assert_eq!(read_checkpoint(&repo), Some(old));
+        assert_eq!(HistoryRewrite::detect(&repo, old, &[next])?, None);
+
+        let rewrite = HistoryRewrite::detect(&repo, old, &[new])?.unwrap();
+        assert_eq!(rewrite.merge_base, Some(base.to_string()));
+        assert_eq!(rewrite.orphaned_commits, vec![old.to_string()]);
+        Ok(())
+    }
+}
@@ -0,0 +1,376 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use chrono::prelude::*;
+use chrono::DateTime;
+use git2::{Commit, DiffOptions, Oid, Patch, Repository, Sort};
+
+use crate::config::Config;
+use crate::diff_analyzer::DiffAnalyzer;
+use crate::error::Result;
+use crate::identity::Identity;
+use crate::utils::convert_time;
+use codealong_metrics::LineStats;
+use codealong_metrics::WorkStats;
+use codealong_model::Person;
+
+/// Tag of the files holding tests.
+const TEST_TAG: &str = "test";
+
+/// Hotspots and owners listed in the markdown of a report, beyond which
+/// only their number is given.
+const MAX_LISTED: usize = 10;
+
+/// Summary of the changes of a pull request against its base branch, meant
+/// to be posted on the pull request by its CI pipeline.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CiReport {
+    /// Where the pull request branched off its base, which it is diffed
+    /// against.
+    pub merge_base: String,
+    pub head: String,
+    pub commits: u64,
+    pub files_changed: u64,
+    pub line_stats: LineStats,
+    /// Lines changed in files tagged `test`.
+    pub test_lines: u64,
+    /// Share of the lines changed which are in tests, if any changed.
+    pub test_ratio: Option<f64>,
+    /// Changed files among the top tenth of the files of the base by churn,
+    /// most churned first.
+    pub hotspots: Vec<Hotspot>,
+    /// The people who changed the changed files the most within the churn
+    /// window, most files first.
+    pub owners: Vec<Owner>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hotspot {
+    pub path: String,
+    /// Lines added and deleted on the base within the churn window.
+    pub churn: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Owner {
+    pub person: Person,
+    /// Changed files this person changed the most lines of.
+    pub paths: Vec<String>,
+}
+
+/// Churn of the files of the base within the churn window, in total and per
+/// author.
+#[derive(Default)]
+pub(crate) struct History {
+    pub(crate) churn: HashMap<String, u64>,
+    authors: HashMap<String, BTreeMap<String, (Person, u64)>>,
+}
+
+impl CiReport {
+    /// Reports on the commits of `head` which are not on `base`, with the
+    /// hotspots and owners of the files they change found from the commits
+    /// of the base authored since `churn_since`.
+    pub fn build(
+        repo: &Repository,
+        base: &Commit,
+        head: &Commit,
+        config: &Config,
+        churn_since: &DateTime<Utc>,
+    ) -> Result<CiReport> {
+        let merge_base = repo.find_commit(repo.merge_base(base.id(), head.id())?)?;
+        let mut revwalk = repo.revwalk()?;
+        revwalk.push(head.id())?;
+        revwalk.hide(merge_base.id())?;
+        let commits = revwalk.count() as u64;
+
+        let diff = DiffAnalyzer::new(repo, head, Some(&merge_base), config).analyze()?;
+        let lines_changed = diff.line_stats.added + diff.line_stats.deleted;
+        let test_lines = diff
+            .files
+            .iter()
+            .filter(|file| file.tags.iter().any(|tag| tag == TEST_TAG))
+            .map(|file| lines(&file.stats))
+            .sum();
+
+        let history = History::walk(repo, merge_base.id(), config, churn_since)?;
+        let threshold = history.hotspot_threshold();
+        let mut hotspots: Vec<Hotspot> = diff
+            .files
+            .iter()
+            .filter_map(|file| {
+                let churn = history.churn.get(&file.path).cloned()?;
+                Some(Hotspot {
+                    path: file.path.clone(),
+                    churn,
+                })
+                .filter(|_| churn >= threshold)
+            })
+            .collect();
+        hotspots.sort_by(|a, b| b.churn.cmp(&a.churn).then_with(|| a.path.cmp(&b.path)));
+
+        let mut owners: BTreeMap<String, Owner> = BTreeMap::new();
+        for file in &diff.files {
+            let top = history.authors.get(&file.path).and_then(|authors| {
+                authors
+                    .values()
+                    .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.id.cmp(&a.0.id)))
+            });
+            if let Some((person, _)) = top {
+                owners
+                    .entry(person.id.clone())
+                    .or_insert_with(|| Owner {
+                        person: person.clone(),
+                        paths: vec![],
+                    })
+                    .paths
+                    .push(file.path.clone());
+            }
+        }
+        let mut owners: Vec<Owner> = owners.into_values().collect();
+        owners.sort_by_key(|owner| std::cmp::Reverse(owner.paths.len()));
+
+        Ok(CiReport {
+            merge_base: merge_base.id().to_string(),
+            head: head.id().to_string(),
+            commits,
+            files_changed: diff.files.len() as u64,
+            line_stats: diff.line_stats,
+            test_lines,
+            test_ratio: if lines_changed > 0 {
+                Some(test_lines as f64 / lines_changed as f64)
+            } else {
+                None
+            },
+            hotspots,
+            owners,
+        })
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "### codealong report\n").unwrap();
+        writeln!(
+            out,
+            "{} commits, {} files changed, +{} -{} lines ({} moved)",
+            self.commits,
+            self.files_changed,
+            self.line_stats.added,
+            self.line_stats.deleted,
+            self.line_stats.moved
+        )
+        .unwrap();
+        match self.test_ratio {
+            Some(ratio) => writeln!(
+                out,
+                "\nTests: {:.0}% of the lines changed ({} lines)",
+                ratio * 100.0,
+                self.test_lines
+            ),
+            None => writeln!(out, "\nTests: no lines changed"),
+        }
+        .unwrap();
+        if !self.hotspots.is_empty() {
+            writeln!(out, "\n#### Hotspots touched\n").unwrap();
+            for hotspot in self.hotspots.iter().take(MAX_LISTED) {
+                writeln!(out, "- `{}`, churn {}", hotspot.path, hotspot.churn).unwrap();
+            }
+            if self.hotspots.len() > MAX_LISTED {
+                writeln!(out, "- and {} more", self.hotspots.len() - MAX_LISTED).unwrap();
+            }
+        }
+        if !self.owners.is_empty() {
+            writeln!(out, "\n#### Affected owners\n").unwrap();
+            for owner in self.owners.iter().take(MAX_LISTED) {
+                let person = &owner.person;
+                let name = person
+                    .name
+                    .as_ref()
+                    .or(person.github_login.as_ref())
+                    .unwrap_or(&person.id);
+                let mut paths: Vec<String> = owner
+                    .paths
+                    .iter()
+                    .take(3)
+                    .map(|path| format!("`{}`", path))
+                    .collect();
+                if owner.paths.len() > 3 {
+                    paths.push(format!("{} more", owner.paths.len() - 3));
+                }
+                match person.github_login {
+                    Some(ref login) if login != name => {
+                        writeln!(out, "- {} (@{}): {}", name, login, paths.join(", "))
+                    }
+                    _ => writeln!(out, "- {}: {}", name, paths.join(", ")),
+                }
+                .unwrap();
+            }
+            if self.owners.len() > MAX_LISTED {
+                writeln!(out, "- and {} more", self.owners.len() - MAX_LISTED).unwrap();
+            }
+        }
+        out
+    }
+}
+
+impl History {
+    /// Walks the non-merge commits reachable from `base` authored since
+    /// `since`, most recent first.
+    pub(crate) fn walk(
+        repo: &Repository,
+        base: Oid,
+        config: &Config,
+        since: &DateTime<Utc>,
+    ) -> Result<History> {
+        let mut history = History::default();
+        let mut revwalk = repo.revwalk()?;
+        revwalk.set_sorting(Sort::TIME);
+        revwalk.push(base)?;
+        for oid in revwalk {
+            let commit = repo.find_commit(oid?)?;
+            let authored_at = convert_time(&commit.author().when());
+            if authored_at < *since {
+                break;
+            }
+            if commit.parent_count() > 1 {
+                continue;
+            }
+            let author =
+                config.person_for_identity_at(&Identity::from(commit.author()), &authored_at);
+            let parent_tree = commit.parent(0).ok().and_then(|p| p.tree().ok());
+            let diff = repo.diff_tree_to_tree(
+                parent_tree.as_ref(),
+                Some(&commit.tree()?),
+                Some(&mut DiffOptions::new()),
+            )?;
+            for idx in 0..diff.deltas().len() {
+                let patch = match Patch::from_diff(&diff, idx)? {
+                    Some(patch) => patch,
+                    None => continue,
+                };
+                let path = match patch
+                    .delta()
+                    .new_file()
+                    .path()
+                    .or_else(|| patch.delta().old_file().path())
+                    .and_then(|path| path.to_str())
+                {
+                    Some(path) => path.to_owned(),
+                    None => continue,
+                };
+                let (_, additions, deletions) = patch.line_stats()?;
+                let changed = (additions + deletions) as u64;
+                *history.churn.entry(path.clone()).or_insert(0) += changed;
+                history
+                    .authors
+                    .entry(path)
+                    .or_default()
+                    .entry(author.id.clone())
+                    .or_insert_with(|| (author.clone(), 0))
+                    .1 += changed;
+            }
+        }
+        Ok(history)
+    }
+
+    /// Churn from which a file is among the top tenth of the files by churn.
+    pub(crate) fn hotspot_threshold(&self) -> u64 {
+        let mut churns: Vec<u64> = self.churn.values().cloned().collect();
+        churns.sort_unstable_by(|a, b| b.cmp(a));
+        churns
+            .get((churns.len().max(1) - 1) / 10)
+            .cloned()
+            .unwrap_or(u64::MAX)
+    }
+}
+
+/// Lines changed according to the work stats of a file.
+fn lines(stats: &WorkStats) -> u64 {
+    stats.new_work + stats.legacy_refactor + stats.churn + stats.help_others + stats.other
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Signature;
+    use std::path::Path;
+
+    fn commit(repo: &Repository, dir: &Path, author: &str, files: &[(&str, &str)]) -> Result<Oid> {
+        for (path, content) in files {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap())?;
+            std::fs::write(&path, content)?;
+        }
+        let mut index = repo.index()?;
+        index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
+        index.write()?;
+        let tree = repo.find_tree(index.write_tree()?)?;
+        let sig = Signature::now(author, &format!("{}@example.com", author.to_lowercase()))?;
+        let parents = match repo.head() {
+            Ok(head) => vec![head.peel_to_commit()?],
+            Err(_) => vec![],
+        };
+        let parents: Vec<&Commit> = parents.iter().collect();
+        Ok(repo.commit(Some("HEAD"), &sig, &sig, "Change", &tree, &parents)?)
+    }
+
+    #[test]
+    fn test_build() -> Result<()> {
+        let tmp_dir = tempdir::TempDir::new("ci_report")?;
+        let dir = tmp_dir.path();
+        let repo = Repository::init(dir)?;
+        commit(
+            &repo,
+            dir,
+            "Alice",
+            &[
+                ("src/lib.rs", "fn a() {}\nfn b() {}\n"),
+                ("README.md", "hi\n"),
+            ],
+        )?;
+        commit(
+            &repo,
+            dir,
+            "Alice",
+            &[("src/lib.rs", "fn a() {}\nfn c() {}\n")],
+        )?;
+        let base = commit(&repo, dir, "Bob", &[("README.md", "hello\n")])?;
+        commit(
+            &repo,
+            dir,
+            "Carol",
+            &[
+                ("src/lib.rs", "fn a() {}\nfn c() {}\nfn d() {}\n"),
+                ("tests/lib_test.go", "package lib\n"),
+            ],
+        )?;
+        let head = commit(&repo, dir, "Carol", &[("README.md", "hello!\n")])?;
+
+        let config = Config::base();
+        let report = CiReport::build(
+            &repo,
+            &repo.find_commit(base)?,
+            &repo.find_commit(head)?,
+            &config,
+            &(Utc::now() - chrono::Duration::days(1)),
+        )?;
+        assert_eq!(report.merge_base, base.to_string());
+        assert_eq!(report.commits, 2);
+        assert_eq!(report.files_changed, 3);
+        assert_eq!(report.test_lines, 1);
+        assert_eq!(
+            report.hotspots,
+            vec![Hotspot {
+                path: "src/lib.rs".to_owned(),
+                churn: 4,
+            }]
+        );
+        assert_eq!(report.owners.len(), 2);
+        assert_eq!(report.owners[0].paths.len(), 1);
+        let markdown = report.to_markdown();
+        assert!(markdown.contains("2 commits, 3 files changed"));
+        assert!(markdown.contains("- `src/lib.rs`, churn 4"));
+        assert!(markdown.contains("- Alice: `src/lib.rs`"));
+        assert!(markdown.contains("- Bob: `README.md`"));
+        Ok(())
+    }
+}
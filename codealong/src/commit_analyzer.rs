@@ -1,16 +1,55 @@
-use git2::{Commit, Repository};
+use git2::{Commit, Oid, Repository};
 use slog::Logger;
+use std::path::Path;
+use std::sync::Arc;
 
 use crate::analyzed_commit::AnalyzedCommit;
+use crate::compliance::{check_compliance, promote_trailers, trailer_fields};
+use crate::dco::is_signed_off;
+use crate::defect::{is_bug_fix, DefectLinker, IntroducedDefect};
 use crate::diff_analyzer::DiffAnalyzer;
 use crate::error::Error;
+use crate::identity::Identity;
+use crate::landing::Landings;
+use crate::lite_commit::LiteCommit;
+use crate::notes::read_notes;
+use crate::pairing::co_authors;
+use crate::progress::{NoProgress, Progress};
+use crate::refactor::is_likely_refactor;
+use crate::rename_graph::RenameGraph;
 use crate::repo_config::RepoConfig;
+use crate::repo_info::parse_remote_url;
+use crate::signature::verify_commit;
+use crate::text::commit_message;
+use crate::utils::changed_paths;
+use crate::work_type::{detect_dependency_update, WorkType};
+use codealong_model::Person;
+
+/// How the diff of an octopus merge, i.e. a merge with more than two parents,
+/// is attributed to its author.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OctopusMerges {
+    /// Only the changes relative to the first parent, i.e. the combined
+    /// changes brought in by the merged branches.
+    #[default]
+    FirstParent,
+    /// The sum of the changes relative to each parent, as with regular
+    /// merges.
+    AllParents,
+    /// No changes at all.
+    Skip,
+}
 
 pub struct CommitAnalyzer<'a> {
     repo: &'a Repository,
     commit: Commit<'a>,
     config: &'a RepoConfig,
     logger: Logger,
+    /// Id of the commit in history when `commit` is its replacement.
+    replaced: Option<Oid>,
+    progress: Arc<dyn Progress>,
+    landings: Option<Arc<Landings>>,
 }
 
 impl<'a> CommitAnalyzer<'a> {
@@ -26,46 +65,230 @@ impl<'a> CommitAnalyzer<'a> {
             commit,
             config,
             logger,
+            replaced: None,
+            progress: Arc::new(NoProgress),
+            landings: None,
         }
     }
 
+    /// Analyzes `commit` as the `git replace` replacement of `replaced`.
+    pub fn replacing(mut self, replaced: Oid) -> CommitAnalyzer<'a> {
+        self.replaced = Some(replaced);
+        self
+    }
+
+    /// Reports the progress of diffing the commit to `progress`.
+    pub fn with_progress(mut self, progress: Arc<dyn Progress>) -> CommitAnalyzer<'a> {
+        self.progress = progress;
+        self
+    }
+
+    /// Records how the commit landed on the branch of `landings`.
+    pub fn with_landings(mut self, landings: Arc<Landings>) -> CommitAnalyzer<'a> {
+        self.landings = Some(landings);
+        self
+    }
+
+    /// Id of the analyzed commit, and of the commit it replaces, if any.
+    pub(crate) fn ids(&self) -> (Oid, Option<Oid>) {
+        (self.commit.id(), self.replaced)
+    }
+
     pub fn analyze(&self) -> Result<AnalyzedCommit, Error> {
         let mut result = AnalyzedCommit::new(&self.commit);
         debug!(self.logger, "Analyzing commit"; "commit_time" => &result.authored_at.to_rfc2822(), "commit_author" => &result.author.to_string(), "commit_summary" => &result.summary);
-        // TODO: deal with merge commits
-        let mut has_parents = false;
-        for parent in self.commit.parents() {
+        let parents: Vec<Commit> = self.commit.parents().collect();
+        let parents = if parents.len() > 2 {
+            match self.config.config.octopus_merges {
+                OctopusMerges::FirstParent => &parents[..1],
+                OctopusMerges::AllParents => &parents[..],
+                OctopusMerges::Skip => &[],
+            }
+        } else {
+            &parents[..]
+        };
+        let sampling = self
+            .config
+            .config
+            .diff_sampling
+            .as_ref()
+            .filter(|sampling| sampling.is_sampled(&result.id));
+        for parent in parents {
             let diff_analyzer =
-                DiffAnalyzer::new(self.repo, &self.commit, Some(&parent), &self.config.config);
+                DiffAnalyzer::new(self.repo, &self.commit, Some(parent), &self.config.config)
+                    .with_paths(&self.config.repo.paths)
+                    .with_churn_cutoff(self.config.repo.churn_cutoff)
+                    .with_sampling(sampling)
+                    .with_progress(&*self.progress);
             result.merge_diff(&diff_analyzer.analyze()?);
-            has_parents = true;
         }
         // handle initial commit
-        if !has_parents {
+        if self.commit.parent_count() == 0 {
             let diff_analyzer =
-                DiffAnalyzer::new(self.repo, &self.commit, None, &self.config.config);
+                DiffAnalyzer::new(self.repo, &self.commit, None, &self.config.config)
+                    .with_paths(&self.config.repo.paths)
+                    .with_churn_cutoff(self.config.repo.churn_cutoff)
+                    .with_sampling(sampling)
+                    .with_progress(&*self.progress);
             result.merge_diff(&diff_analyzer.analyze()?);
         }
+        RenameGraph::for_commit(self.repo, self.commit.id())?.stamp(
+            self.repo,
+            self.commit.id(),
+            &mut result.diff,
+        );
         if let Some(ref github_name) = self.config.repo.github_name {
+            let host = parse_remote_url(&self.config.repo.clone_url)
+                .map(|(host, _)| host)
+                .filter(|host| host.contains("github"))
+                .unwrap_or_else(|| "github.com".to_owned());
             result.github_url = Some(format!(
-                "https://github.com/{}/commit/{}",
-                github_name, result.id
+                "https://{}/{}/commit/{}",
+                host, github_name, result.id
+            ));
+        }
+        if let Some(replaced) = self.replaced {
+            result.replaced_by = Some(result.id);
+            result.id = replaced.to_string();
+        }
+        if let Some(ref landings) = self.landings {
+            landings.apply(&mut result);
+        }
+        // notes are attached to the commit as it appears in history
+        result.notes = read_notes(
+            self.repo,
+            self.replaced.unwrap_or_else(|| self.commit.id()),
+            &self.config.config.notes,
+        )?;
+        result.likely_refactor = is_likely_refactor(result.summary.as_deref(), &result.diff);
+        let message = commit_message(&self.commit);
+        if is_bug_fix(&message) {
+            result.bug_fix = true;
+            result.defect_origins =
+                DefectLinker::new(self.repo, &self.commit, &self.config.config).origins()?;
+        }
+        // merges bring in changes of their own, whatever the files they touch
+        let dependency_update = if self.commit.parent_count() > 1 {
+            None
+        } else {
+            detect_dependency_update(&changed_paths(self.repo, &self.commit)?, &result.author)
+        };
+        result.work_type = if let Some(dependency_update) = dependency_update {
+            result.dependency_tool = dependency_update.tool;
+            WorkType::DependencyUpdate
+        } else if result.bug_fix {
+            WorkType::BugFix
+        } else if result.likely_refactor {
+            WorkType::Refactor
+        } else {
+            WorkType::Other
+        };
+        result.repo = Some(self.config.partial_repo());
+        let normalized_author = self.normalize(&result, &result.author);
+        result.author_id = Some(normalized_author.id.clone());
+        result.normalized_author = Some(normalized_author);
+        result.normalized_committer = Some(self.normalize(&result, &result.committer));
+        result.co_authors = co_authors(&message)
+            .iter()
+            .map(|identity| {
+                self.config
+                    .config
+                    .person_for_identity_at(identity, &result.authored_at)
+            })
+            .collect();
+        if !self.config.config.required_trailers.is_empty() {
+            result.compliance = Some(check_compliance(
+                &message,
+                &self.config.config.required_trailers,
             ));
         }
-        result.repo = Some(self.config.repo.partial());
-        result.normalized_author = Some(self.config.config.person_for_identity(&result.author));
-        result.normalized_committer =
-            Some(self.config.config.person_for_identity(&result.committer));
+        result.trailers = trailer_fields(&message);
+        result.promoted_trailers =
+            promote_trailers(&result.trailers, &self.config.config.promoted_trailers);
+        result.issues = self.config.config.issue_references.extract(&message)?;
+        if let Some(conventional) = self.config.config.commit_convention.parse(&message) {
+            result.commit_type = Some(conventional.commit_type);
+            result.scope = conventional.scope;
+            result.breaking = conventional.breaking;
+        }
+        result.dco_signed_off = is_signed_off(&message, &result.author);
+        result.signature = verify_commit(
+            self.repo,
+            &self.commit.id(),
+            self.config.config.signing_keyring.as_ref().map(Path::new),
+        )?;
+        // truncated last so that keywords past the limit are still detected
+        result.summary = result
+            .summary
+            .and_then(|summary| self.config.config.stored_summary(&summary));
         debug!(self.logger, "Done analyzing");
         return Ok(result);
     }
+
+    /// Analyzes only the metadata and numstat of `commit`, skipping blame.
+    pub fn analyze_lite(&self) -> Result<LiteCommit, Error> {
+        debug!(self.logger, "Analyzing commit metadata");
+        let mut result = LiteCommit::new(
+            self.repo,
+            &self.commit,
+            &self.config.repo.paths,
+            &self.config.config,
+        )?;
+        result.repo = Some(self.config.partial_repo());
+        if let Some(replaced) = self.replaced {
+            result.id = replaced.to_string();
+        }
+        Ok(result)
+    }
+
+    /// Maps an identity to a person, using the svn or Perforce username for
+    /// identities synthesized by `git-svn` or `git-p4`.
+    fn normalize(&self, analyzed: &AnalyzedCommit, identity: &Identity) -> Person {
+        match analyzed
+            .imported
+            .as_ref()
+            .and_then(|imported| imported.username(identity))
+        {
+            Some(username) => self
+                .config
+                .config
+                .person_for_imported_username_at(&username, &analyzed.authored_at),
+            None => self
+                .config
+                .config
+                .person_for_identity_at(identity, &analyzed.authored_at),
+        }
+    }
+
+    /// Builds an annotation event for each commit suspected of introducing
+    /// the bug fixed by the analyzed commit.
+    pub fn introduced_defects(
+        &self,
+        analyzed: &AnalyzedCommit,
+    ) -> Result<Vec<IntroducedDefect>, Error> {
+        let mut res = vec![];
+        for origin in &analyzed.defect_origins {
+            let introducing_commit = self.repo.find_commit(Oid::from_str(&origin.commit)?)?;
+            res.push(IntroducedDefect::new(
+                &introducing_commit,
+                &self.commit,
+                origin.method,
+                &self.config.config,
+                analyzed.repo.clone(),
+            ));
+        }
+        Ok(res)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::analyzed_diff::SkippedReason;
+    use crate::diff_analyzer::RenameDetection;
+    use crate::diff_sampling::{DiffSampling, DiffSnippet};
     use crate::test::build_test_logger;
-    use crate::work_stats::WorkStats;
+    use codealong_metrics::WorkStats;
     use git2::Oid;
     use std::path::Path;
 
@@ -99,9 +322,19 @@ mod tests {
                 churn: 1,
                 help_others: 0,
                 other: 0,
-                impact: 10
+                impact: 10,
+                weighted_churn: 1.0
             }
         );
+        let languages: u64 = res.diff.language_stats.values().map(|l| l.files).sum();
+        assert_eq!(
+            languages,
+            res.diff
+                .files
+                .iter()
+                .filter(|file| file.language.is_some())
+                .count() as u64
+        );
     }
 
     #[test]
@@ -115,5 +348,263 @@ mod tests {
         let res = analyzer.analyze().unwrap();
         assert_eq!(res.github_url, Some("https://github.com/ghempton/codealong/commit/86d242301830075e93ff039a4d1e88673a4a3020".to_string()));
         assert_eq!(res.diff.tag_stats.get("docs").unwrap().new_work, 1);
+        assert_eq!(res.diff.files.len(), 1);
+        assert_eq!(res.diff.files[0].path, "README.md");
+        assert_eq!(res.diff.files[0].tags, vec!["docs".to_owned()]);
+        assert_eq!(res.diff.files[0].weight, 0.25);
+        assert_eq!(res.diff.files[0].stats, res.diff.stats);
+        assert_eq!(res.diff.files[0].language.as_deref(), Some("Markdown"));
+        let markdown = &res.diff.language_stats["Markdown"];
+        assert_eq!(
+            (markdown.files, markdown.added, markdown.deleted),
+            (1, 1, 0)
+        );
+        assert_eq!(markdown.stats, res.diff.stats);
+        let complexity = res.diff.files[0].complexity.unwrap();
+        assert!(complexity.size > 0);
+        assert_eq!(
+            complexity.after.size as i64 - complexity.before.size as i64,
+            complexity.size
+        );
+        assert_eq!(complexity.functions, None);
+    }
+
+    #[test]
+    fn test_skipped_files() -> Result<(), Error> {
+        let tmp_dir = tempdir::TempDir::new("skipped_files")?;
+        let dir = tmp_dir.path();
+        let repo = Repository::init(dir)?;
+        std::fs::write(dir.join("image.png"), b"\x89PNG\0\0\0\rIHDR")?;
+        std::fs::write(dir.join("data.csv"), "a,b\n".repeat(100))?;
+        std::fs::write(dir.join("main.rs"), "fn main() {}\n")?;
+        let mut index = repo.index()?;
+        index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
+        let tree = repo.find_tree(index.write_tree()?)?;
+        let sig = git2::Signature::now("Test", "test@example.com")?;
+        let oid = repo.commit(Some("HEAD"), &sig, &sig, "Add files", &tree, &[])?;
+
+        let mut config = RepoConfig::default();
+        config.config.large_files.max_size = 100;
+        let analyze = |config: &RepoConfig| {
+            let commit = repo.find_commit(oid).unwrap();
+            CommitAnalyzer::new(&repo, commit, config, &build_test_logger()).analyze()
+        };
+        let res = analyze(&config)?;
+        let skipped: Vec<(&str, Option<SkippedReason>, u64)> = res
+            .diff
+            .files
+            .iter()
+            .map(|file| (&*file.path, file.skipped_reason, file.stats.new_work))
+            .collect();
+        assert_eq!(
+            skipped,
+            vec![
+                ("data.csv", Some(SkippedReason::TooLarge), 0),
+                ("image.png", Some(SkippedReason::Binary), 0),
+                ("main.rs", None, 1),
+            ]
+        );
+        assert_eq!(res.diff.stats.new_work, 1);
+
+        config.config.large_files.weight = Some(0.5);
+        let res = analyze(&config)?;
+        assert_eq!(res.diff.files[0].skipped_reason, None);
+        assert_eq!(res.diff.files[0].weight, 0.5);
+        assert_eq!(res.diff.stats.new_work, 101);
+        Ok(())
+    }
+
+    #[test]
+    fn test_trailers() -> Result<(), Error> {
+        let tmp_dir = tempdir::TempDir::new("trailers")?;
+        let dir = tmp_dir.path();
+        let repo = Repository::init(dir)?;
+        std::fs::write(dir.join("main.rs"), "fn main() {}\n")?;
+        let mut index = repo.index()?;
+        index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
+        let tree = repo.find_tree(index.write_tree()?)?;
+        let sig = git2::Signature::now("Test", "test@example.com")?;
+        let message = "Add main\n\nReviewed-by: Bob <bob@example.com>\nChange-Id: I1234\n";
+        let oid = repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &[])?;
+
+        let mut config = RepoConfig::default();
+        config
+            .config
+            .promoted_trailers
+            .insert("change_id".to_owned(), "Change-Id".to_owned());
+        let commit = repo.find_commit(oid)?;
+        let res = CommitAnalyzer::new(&repo, commit, &config, &build_test_logger()).analyze()?;
+        assert_eq!(res.trailers["reviewed_by"], vec!["Bob <bob@example.com>"]);
+        let json = serde_json::to_value(&res).unwrap();
+        assert_eq!(json["change_id"], "I1234");
+        assert_eq!(json["trailers"]["change_id"][0], "I1234");
+        let parsed: AnalyzedCommit = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.promoted_trailers, res.promoted_trailers);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dependency_update() -> Result<(), Error> {
+        let tmp_dir = tempdir::TempDir::new("dependency_update")?;
+        let dir = tmp_dir.path();
+        let repo = Repository::init(dir)?;
+        let sig = git2::Signature::now("Test", "test@example.com")?;
+        let commit = |files: &[(&str, &str)], message: &str| -> Result<Oid, Error> {
+            for (path, content) in files {
+                std::fs::write(dir.join(path), content)?;
+            }
+            let mut index = repo.index()?;
+            index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
+            let tree = repo.find_tree(index.write_tree()?)?;
+            let parents = match repo.head() {
+                Ok(head) => vec![head.peel_to_commit()?],
+                Err(_) => vec![],
+            };
+            let parents: Vec<&git2::Commit> = parents.iter().collect();
+            Ok(repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)?)
+        };
+        let initial = commit(
+            &[
+                ("main.rs", "fn main() {}\n"),
+                ("Cargo.lock", "version = 3\n"),
+            ],
+            "Add main",
+        )?;
+        let update = commit(&[("Cargo.lock", "version = 4\n")], "Update dependencies")?;
+
+        let config = RepoConfig::default();
+        let analyze = |oid| {
+            CommitAnalyzer::new(&repo, repo.find_commit(oid)?, &config, &build_test_logger())
+                .analyze()
+        };
+        let res = analyze(initial)?;
+        assert_eq!(res.work_type, WorkType::Other);
+        assert_eq!(res.dependency_tool, None);
+        let res = analyze(update)?;
+        assert_eq!(res.work_type, WorkType::DependencyUpdate);
+        assert_eq!(res.dependency_tool, Some("cargo".to_owned()));
+        assert_eq!(
+            serde_json::to_value(&res).unwrap()["work_type"],
+            "dependency_update"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_sampling() -> Result<(), Error> {
+        let tmp_dir = tempdir::TempDir::new("diff_sampling")?;
+        let dir = tmp_dir.path();
+        let repo = Repository::init(dir)?;
+        std::fs::write(
+            dir.join("main.rs"),
+            "fn main() {\n    let password = \"hunter2\";\n}\n",
+        )?;
+        let mut index = repo.index()?;
+        index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
+        let tree = repo.find_tree(index.write_tree()?)?;
+        let sig = git2::Signature::now("Test", "test@example.com")?;
+        let oid = repo.commit(Some("HEAD"), &sig, &sig, "Add main", &tree, &[])?;
+
+        let mut config = RepoConfig::default();
+        let analyze = |config: &RepoConfig| {
+            CommitAnalyzer::new(&repo, repo.find_commit(oid)?, config, &build_test_logger())
+                .analyze()
+        };
+        assert!(analyze(&config)?.diff.snippets.is_empty());
+        config.config.diff_sampling = Some(DiffSampling {
+            rate: 1.0,
+            max_lines: 2,
+            ..Default::default()
+        });
+        let res = analyze(&config)?;
+        assert_eq!(
+            res.diff.snippets,
+            vec![DiffSnippet {
+                path: "main.rs".to_owned(),
+                lines: vec![
+                    "+fn main() {".to_owned(),
+                    "+    let password = [REDACTED]".to_owned()
+                ],
+            }]
+        );
+        config.config.diff_sampling = Some(DiffSampling {
+            rate: 0.0,
+            ..Default::default()
+        });
+        assert!(analyze(&config)?.diff.snippets.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_renamed_files() -> Result<(), Error> {
+        let tmp_dir = tempdir::TempDir::new("renamed_files")?;
+        let dir = tmp_dir.path();
+        let repo = Repository::init(dir)?;
+        let commit = |remove: &[&str], add: &[(&str, &str)]| -> Result<Oid, Error> {
+            for path in remove {
+                std::fs::remove_file(dir.join(path))?;
+            }
+            for (path, content) in add {
+                std::fs::write(dir.join(path), content)?;
+            }
+            let mut index = repo.index()?;
+            index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
+            index.update_all(["*"].iter(), None)?;
+            let tree = repo.find_tree(index.write_tree()?)?;
+            let sig = git2::Signature::now("Test", "test@example.com")?;
+            let parents = match repo.head() {
+                Ok(head) => vec![head.peel_to_commit()?],
+                Err(_) => vec![],
+            };
+            let parents: Vec<&git2::Commit> = parents.iter().collect();
+            Ok(repo.commit(Some("HEAD"), &sig, &sig, "Change", &tree, &parents)?)
+        };
+        let lines: Vec<String> = (0..10).map(|i| format!("let x{} = {};", i, i)).collect();
+        let content = lines.join("\n") + "\n";
+        let changed = content.replace("x9 = 9", "x9 = 10");
+        commit(&[], &[("a.rs", &content)])?;
+        let renamed = commit(&["a.rs"], &[("b.rs", &changed)])?;
+        let copied = commit(
+            &[],
+            &[
+                ("b.rs", &changed.replace("x0 = 0", "x0 = 1")),
+                ("c.rs", &(changed.clone() + "let y = 0;\n")),
+            ],
+        )?;
+
+        let analyze = |oid: Oid, config: &RepoConfig| {
+            let commit = repo.find_commit(oid).unwrap();
+            CommitAnalyzer::new(&repo, commit, config, &build_test_logger()).analyze()
+        };
+        let mut config = RepoConfig::default();
+        let res = analyze(renamed, &config)?;
+        assert_eq!(res.diff.files.len(), 1);
+        let file = &res.diff.files[0];
+        assert_eq!(file.path, "b.rs");
+        assert_eq!(file.old_path.as_deref(), Some("a.rs"));
+        assert!(file.renamed && !file.copied);
+        // the unchanged lines around the change are blamed under the old path
+        assert_eq!((file.stats.new_work, file.stats.other), (1, 1));
+        assert_eq!(file.stats.churn + file.stats.legacy_refactor, 3);
+
+        let res = analyze(copied, &config)?;
+        let file = res.diff.files.iter().find(|f| f.path == "c.rs").unwrap();
+        assert_eq!(file.old_path.as_deref(), Some("b.rs"));
+        assert!(file.copied && !file.renamed);
+        assert_eq!(file.stats.new_work, 1);
+        assert_eq!(file.stats.churn + file.stats.legacy_refactor, 0);
+        assert_eq!(res.diff.line_stats.added, 2);
+
+        config.config.rename_detection = RenameDetection::Off;
+        let res = analyze(renamed, &config)?;
+        let paths: Vec<(&str, bool)> = res
+            .diff
+            .files
+            .iter()
+            .map(|file| (&*file.path, file.renamed))
+            .collect();
+        assert_eq!(paths, vec![("a.rs", false), ("b.rs", false)]);
+        assert_eq!(res.diff.stats.new_work, 10);
+        Ok(())
     }
 }
@@ -0,0 +1,130 @@
+use regex::Regex;
+
+/// Types of the Conventional Commits specification and of its common
+/// presets, recognized unless others are configured.
+const STANDARD_TYPES: &[&str] = &[
+    "build", "chore", "ci", "docs", "feat", "fix", "perf", "refactor", "revert", "style", "test",
+];
+
+/// How commits are classified from messages following the Conventional
+/// Commits convention, `<type>[(<scope>)][!]: <description>`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommitConvention {
+    /// Whether commits are classified.
+    #[serde(default = "CommitConvention::default_enabled")]
+    pub enabled: bool,
+
+    /// Types recognized, e.g. `hotfix` for teams using prefixes of their own,
+    /// instead of the standard ones if not empty.
+    #[serde(default)]
+    pub types: Vec<String>,
+}
+
+/// The classification of a commit by its message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConventionalCommit {
+    pub commit_type: String,
+    pub scope: Option<String>,
+    /// Whether the type is followed by `!` or a footer starts with
+    /// `BREAKING CHANGE:`.
+    pub breaking: bool,
+}
+
+impl Default for CommitConvention {
+    fn default() -> CommitConvention {
+        CommitConvention {
+            enabled: true,
+            types: vec![],
+        }
+    }
+}
+
+impl CommitConvention {
+    fn default_enabled() -> bool {
+        true
+    }
+
+    pub fn merge(&mut self, other: CommitConvention) {
+        self.types.extend(other.types);
+    }
+
+    /// Classifies the commit with `message`, unless its summary does not
+    /// follow the convention or has a type which is not recognized.
+    pub fn parse(&self, message: &str) -> Option<ConventionalCommit> {
+        lazy_static! {
+            static ref SUMMARY_REGEX: Regex =
+                Regex::new(r"^([A-Za-z][\w-]*)(?:\(([^()\r\n]*)\))?(!)?: \S").unwrap();
+            static ref BREAKING_REGEX: Regex = Regex::new(r"(?m)^BREAKING[ -]CHANGE: ").unwrap();
+        }
+        if !self.enabled {
+            return None;
+        }
+        let captures = SUMMARY_REGEX.captures(message.trim_start())?;
+        let commit_type = captures[1].to_lowercase();
+        let recognized = if self.types.is_empty() {
+            STANDARD_TYPES.contains(&commit_type.as_str())
+        } else {
+            self.types.iter().any(|t| t.to_lowercase() == commit_type)
+        };
+        if !recognized {
+            return None;
+        }
+        Some(ConventionalCommit {
+            commit_type,
+            scope: captures
+                .get(2)
+                .map(|scope| scope.as_str().trim().to_owned())
+                .filter(|scope| !scope.is_empty()),
+            breaking: captures.get(3).is_some() || BREAKING_REGEX.is_match(message),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let convention = CommitConvention::default();
+        assert_eq!(
+            convention.parse("feat(parser): add arrays\n\nSupports nesting."),
+            Some(ConventionalCommit {
+                commit_type: "feat".to_owned(),
+                scope: Some("parser".to_owned()),
+                breaking: false,
+            })
+        );
+        assert_eq!(
+            convention.parse("Fix!: drop the legacy flag"),
+            Some(ConventionalCommit {
+                commit_type: "fix".to_owned(),
+                scope: None,
+                breaking: true,
+            })
+        );
+        let breaking = convention
+            .parse("refactor: rename config\n\nBREAKING CHANGE: `foo` is now `bar`")
+            .unwrap();
+        assert!(breaking.breaking);
+        assert_eq!(convention.parse("Merge branch 'main'"), None);
+        assert_eq!(convention.parse("hotfix: patch prod"), None);
+        assert_eq!(convention.parse("feat:no space"), None);
+
+        let convention = CommitConvention {
+            types: vec!["hotfix".to_owned()],
+            ..Default::default()
+        };
+        assert_eq!(
+            convention.parse("hotfix: patch prod").unwrap().commit_type,
+            "hotfix"
+        );
+        assert_eq!(convention.parse("feat: add arrays"), None);
+
+        let convention = CommitConvention {
+            enabled: false,
+            ..Default::default()
+        };
+        assert_eq!(convention.parse("feat: add arrays"), None);
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read};
+
+use crate::error::Result;
+use crate::repo_info::RepoInfo;
+
+/// An explicit list of commits to analyze instead of walking history, read
+/// one commit per line as either `<sha>` or `<repo> <sha>`. Bare SHAs apply to
+/// every repo; repos may be given by name or URL. Blank lines and lines
+/// starting with `#` are ignored.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CommitList {
+    any_repo: Vec<String>,
+    by_repo: BTreeMap<String, Vec<String>>,
+}
+
+impl CommitList {
+    pub fn new() -> CommitList {
+        CommitList::default()
+    }
+
+    pub fn read_from<R: Read>(reader: R) -> Result<CommitList> {
+        let mut list = CommitList::new();
+        for line in BufReader::new(reader).lines() {
+            list.add_line(&line?);
+        }
+        Ok(list)
+    }
+
+    pub fn add_line(&mut self, line: &str) {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return;
+        }
+        let mut parts = line.split_whitespace();
+        match (parts.next(), parts.next()) {
+            (Some(sha), None) => self.any_repo.push(sha.to_owned()),
+            (Some(repo), Some(sha)) => self
+                .by_repo
+                .entry(repo_key(repo))
+                .or_default()
+                .push(sha.to_owned()),
+            _ => {}
+        }
+    }
+
+    /// The commits listed for the repo known by any of `names`, in the order
+    /// they were given.
+    pub fn for_repo(&self, names: &[&str]) -> Vec<String> {
+        let mut commits = self.any_repo.clone();
+        for name in names {
+            if let Some(listed) = self.by_repo.get(&repo_key(name)) {
+                commits.extend(listed.iter().cloned());
+            }
+        }
+        commits
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.any_repo.is_empty() && self.by_repo.is_empty()
+    }
+}
+
+/// Repos may be listed by URL or by name.
+fn repo_key(repo: &str) -> String {
+    if repo.contains(':') {
+        if let Ok(info) = RepoInfo::from_url(repo) {
+            return info.name;
+        }
+    }
+    repo.to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_from() -> Result<()> {
+        let input = "# deploy 42\n\
+                     abc123\n\
+                     \n\
+                     https://github.com/kevinastone/codealong.git def456\n\
+                     other/repo 789abc\n";
+        let list = CommitList::read_from(input.as_bytes())?;
+        assert_eq!(
+            list.for_repo(&["kevinastone/codealong"]),
+            vec!["abc123", "def456"]
+        );
+        assert_eq!(list.for_repo(&["other/repo"]), vec!["abc123", "789abc"]);
+        assert_eq!(list.for_repo(&["unlisted"]), vec!["abc123"]);
+        assert!(CommitList::read_from("\n# nothing\n".as_bytes())?.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::analyzed_commit::AnalyzedCommit;
+
+/// Which of the two analyses being compared an output belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Side {
+    Baseline,
+    Candidate,
+}
+
+/// The metrics of one or more analyzed commits which are compared.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct CommitMetrics {
+    commits: u64,
+    new_work: u64,
+    legacy_refactor: u64,
+    churn: u64,
+    help_others: u64,
+    other: u64,
+    impact: u64,
+    weighted_churn: f64,
+    likely_refactors: u64,
+    bug_fixes: u64,
+}
+
+impl CommitMetrics {
+    fn from_commit(commit: &AnalyzedCommit) -> CommitMetrics {
+        let stats = &commit.diff.stats;
+        CommitMetrics {
+            commits: 1,
+            new_work: stats.new_work,
+            legacy_refactor: stats.legacy_refactor,
+            churn: stats.churn,
+            help_others: stats.help_others,
+            other: stats.other,
+            impact: stats.impact,
+            weighted_churn: stats.weighted_churn,
+            likely_refactors: commit.likely_refactor as u64,
+            bug_fixes: commit.bug_fix as u64,
+        }
+    }
+
+    fn add(&mut self, other: &CommitMetrics) {
+        self.commits += other.commits;
+        self.new_work += other.new_work;
+        self.legacy_refactor += other.legacy_refactor;
+        self.churn += other.churn;
+        self.help_others += other.help_others;
+        self.other += other.other;
+        self.impact += other.impact;
+        self.weighted_churn += other.weighted_churn;
+        self.likely_refactors += other.likely_refactors;
+        self.bug_fixes += other.bug_fixes;
+    }
+
+    fn values(&self) -> Vec<(&'static str, f64)> {
+        vec![
+            ("commits", self.commits as f64),
+            ("new_work", self.new_work as f64),
+            ("legacy_refactor", self.legacy_refactor as f64),
+            ("churn", self.churn as f64),
+            ("help_others", self.help_others as f64),
+            ("other", self.other as f64),
+            ("impact", self.impact as f64),
+            ("weighted_churn", self.weighted_churn),
+            ("likely_refactors", self.likely_refactors as f64),
+            ("bug_fixes", self.bug_fixes as f64),
+        ]
+    }
+}
+
+/// The change in a metric of a repo between the baseline and candidate
+/// analyses.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricDelta {
+    pub repo: String,
+    pub metric: String,
+    pub baseline: f64,
+    pub candidate: f64,
+}
+
+impl MetricDelta {
+    pub fn delta(&self) -> f64 {
+        self.candidate - self.baseline
+    }
+
+    /// Change relative to the baseline, e.g. 0.5 for a 50% increase, or
+    /// `None` when the baseline is zero.
+    pub fn relative_change(&self) -> Option<f64> {
+        if self.baseline == 0.0 {
+            None
+        } else {
+            Some(self.delta() / self.baseline)
+        }
+    }
+}
+
+/// Compares the commits analyzed by two analyzer configurations over the
+/// same range, e.g. to validate an upgrade before switching dashboards to
+/// its output.
+#[derive(Debug, Default)]
+pub struct Comparison {
+    totals: BTreeMap<String, HashMap<Side, CommitMetrics>>,
+    commits: HashMap<(String, String), HashMap<Side, CommitMetrics>>,
+}
+
+impl Comparison {
+    pub fn new() -> Comparison {
+        Comparison::default()
+    }
+
+    pub fn add_commit(&mut self, side: Side, repo: &str, commit: &AnalyzedCommit) {
+        let metrics = CommitMetrics::from_commit(commit);
+        self.totals
+            .entry(repo.to_owned())
+            .or_default()
+            .entry(side)
+            .or_default()
+            .add(&metrics);
+        self.commits
+            .entry((repo.to_owned(), commit.id.clone()))
+            .or_default()
+            .insert(side, metrics);
+    }
+
+    pub fn repos(&self) -> impl Iterator<Item = &str> {
+        self.totals.keys().map(|repo| &**repo)
+    }
+
+    /// The deltas of every metric of every repo, including unchanged ones.
+    pub fn deltas(&self) -> Vec<MetricDelta> {
+        let mut deltas = vec![];
+        for (repo, sides) in &self.totals {
+            let baseline = sides.get(&Side::Baseline).cloned().unwrap_or_default();
+            let candidate = sides.get(&Side::Candidate).cloned().unwrap_or_default();
+            for ((metric, baseline), (_, candidate)) in
+                baseline.values().into_iter().zip(candidate.values())
+            {
+                deltas.push(MetricDelta {
+                    repo: repo.to_owned(),
+                    metric: metric.to_owned(),
+                    baseline,
+                    candidate,
+                });
+            }
+        }
+        deltas
+    }
+
+    /// Number of commits of `repo` whose metrics differ between the
+    /// analyses, or which only one of them analyzed.
+    pub fn changed_commits(&self, repo: &str) -> usize {
+        self.commits
+            .iter()
+            .filter(|((commit_repo, _), _)| commit_repo == repo)
+            .filter(|(_, sides)| sides.get(&Side::Baseline) != sides.get(&Side::Candidate))
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{Oid, Repository};
+
+    #[test]
+    fn test_deltas() {
+        let repo = Repository::open("./fixtures/repos/simple").unwrap();
+        let commit = repo
+            .find_commit(Oid::from_str("86d242301830075e93ff039a4d1e88673a4a3020").unwrap())
+            .unwrap();
+        let mut baseline = AnalyzedCommit::new(&commit);
+        baseline.diff.stats.churn = 4;
+        let mut candidate = baseline.clone();
+        candidate.diff.stats.churn = 6;
+        candidate.bug_fix = true;
+
+        let mut comparison = Comparison::new();
+        comparison.add_commit(Side::Baseline, "simple", &baseline);
+        comparison.add_commit(Side::Candidate, "simple", &candidate);
+
+        let deltas = comparison.deltas();
+        let churn = deltas.iter().find(|d| d.metric == "churn").unwrap();
+        assert_eq!(churn.delta(), 2.0);
+        assert_eq!(churn.relative_change(), Some(0.5));
+        let bug_fixes = deltas.iter().find(|d| d.metric == "bug_fixes").unwrap();
+        assert_eq!(bug_fixes.relative_change(), None);
+        let commits = deltas.iter().find(|d| d.metric == "commits").unwrap();
+        assert_eq!(commits.delta(), 0.0);
+        assert_eq!(comparison.changed_commits("simple"), 1);
+    }
+
+    #[test]
+    fn test_unchanged_commits() {
+        let repo = Repository::open("./fixtures/repos/simple").unwrap();
+        let commit = repo
+            .find_commit(Oid::from_str("86d242301830075e93ff039a4d1e88673a4a3020").unwrap())
+            .unwrap();
+        let analyzed = AnalyzedCommit::new(&commit);
+        let mut comparison = Comparison::new();
+        comparison.add_commit(Side::Baseline, "simple", &analyzed);
+        comparison.add_commit(Side::Candidate, "simple", &analyzed);
+        assert_eq!(comparison.changed_commits("simple"), 0);
+        assert!(comparison.deltas().iter().all(|d| d.delta() == 0.0));
+    }
+}
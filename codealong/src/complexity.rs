@@ -0,0 +1,192 @@
+use git2::{Oid, Repository};
+use regex::Regex;
+
+/// Lightweight metrics of the content of a file, cheap enough to compute for
+/// every file changed by every commit.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileMetrics {
+    /// Size in bytes.
+    pub size: u64,
+    pub max_line_length: u64,
+    /// Sum of the indentation levels of the non-blank lines, which tracks
+    /// the nesting of the code in any language.
+    pub indentation: u64,
+    /// Functions declared, if the language has a pattern for them.
+    pub functions: Option<u64>,
+}
+
+impl FileMetrics {
+    pub fn of(content: &[u8], language: Option<&str>) -> FileMetrics {
+        let content = String::from_utf8_lossy(content);
+        let unit = indentation_unit(&content);
+        let mut metrics = FileMetrics {
+            size: content.len() as u64,
+            functions: function_pattern(language).map(|pattern| {
+                content
+                    .lines()
+                    .filter(|line| pattern.is_match(line))
+                    .count() as u64
+            }),
+            ..FileMetrics::default()
+        };
+        for line in content.lines() {
+            metrics.max_line_length = metrics.max_line_length.max(line.chars().count() as u64);
+            if line.trim().is_empty() {
+                continue;
+            }
+            let tabs = line.chars().take_while(|&c| c == '\t').count();
+            let spaces = line[tabs..].chars().take_while(|&c| c == ' ').count();
+            metrics.indentation += (tabs + spaces / unit) as u64;
+        }
+        metrics
+    }
+
+    /// Metrics of a blob, unless it is binary.
+    pub(crate) fn of_blob(
+        repo: &Repository,
+        id: Oid,
+        language: Option<&str>,
+    ) -> Option<FileMetrics> {
+        // the side of an added or deleted file which does not exist
+        if id.is_zero() {
+            return Some(FileMetrics::of(b"", language));
+        }
+        let blob = repo.find_blob(id).ok()?;
+        if blob.is_binary() {
+            return None;
+        }
+        Some(FileMetrics::of(blob.content(), language))
+    }
+}
+
+/// How the metrics of a file changed with a commit, from those of the
+/// version of its parent, all zero for added files, to those of its version,
+/// all zero for deleted files.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ComplexityDelta {
+    pub size: i64,
+    pub max_line_length: i64,
+    pub indentation: i64,
+    pub functions: Option<i64>,
+    pub before: FileMetrics,
+    pub after: FileMetrics,
+}
+
+impl ComplexityDelta {
+    pub fn new(before: FileMetrics, after: FileMetrics) -> ComplexityDelta {
+        ComplexityDelta {
+            size: after.size as i64 - before.size as i64,
+            max_line_length: after.max_line_length as i64 - before.max_line_length as i64,
+            indentation: after.indentation as i64 - before.indentation as i64,
+            functions: match (before.functions, after.functions) {
+                (Some(before), Some(after)) => Some(after as i64 - before as i64),
+                _ => None,
+            },
+            before,
+            after,
+        }
+    }
+
+    /// Whether the commit made the file more nested, e.g. to flag it for a
+    /// closer review.
+    pub fn increases_complexity(&self) -> bool {
+        self.indentation > 0
+    }
+}
+
+/// Spaces per indentation level: the smallest indentation with spaces in
+/// the file, between 1 and 8.
+fn indentation_unit(content: &str) -> usize {
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.chars().take_while(|&c| c == ' ').count())
+        .filter(|&spaces| spaces > 0)
+        .min()
+        .unwrap_or(4)
+        .min(8)
+}
+
+/// Lines declaring a function in `language`, for the languages with a
+/// simple enough syntax for it.
+fn function_pattern(language: Option<&str>) -> Option<&'static Regex> {
+    lazy_static! {
+        static ref RUST: Regex = Regex::new(
+            r#"^\s*(pub(\([^)]*\))?\s+)?((const|async|unsafe|extern\s+"[^"]*")\s+)*fn\s+\w+"#
+        )
+        .unwrap();
+        static ref PYTHON: Regex = Regex::new(r"^\s*(async\s+)?def\s+\w+").unwrap();
+        static ref RUBY: Regex = Regex::new(r"^\s*def\s+[\w.]+").unwrap();
+        static ref ELIXIR: Regex = Regex::new(r"^\s*defp?\s+\w+").unwrap();
+        static ref GO: Regex = Regex::new(r"^func\s").unwrap();
+        static ref JAVASCRIPT: Regex =
+            Regex::new(r"\bfunction\b\s*\*?\s*\w*\s*\(|=>\s*[{(]?").unwrap();
+        static ref PHP: Regex = Regex::new(r"\bfunction\s+&?\w+\s*\(").unwrap();
+        static ref LUA: Regex = Regex::new(r"\bfunction\b").unwrap();
+        static ref KOTLIN: Regex = Regex::new(r"\bfun\s+[\w.<>]+\s*\(").unwrap();
+        static ref SWIFT: Regex = Regex::new(r"\bfunc\s+\w+").unwrap();
+        static ref SCALA: Regex = Regex::new(r"\bdef\s+\w+").unwrap();
+        static ref JAVA: Regex = Regex::new(
+            r"^\s*((public|private|protected|internal|static|final|abstract|synchronized|override|virtual|async)\s+)+[\w<>\[\],.?]+\s+\w+\s*\("
+        )
+        .unwrap();
+        static ref C: Regex =
+            Regex::new(r"^([A-Za-z_][\w:<>,]*[\s*&]+)+[A-Za-z_~][\w:~]*\s*\([^;]*$").unwrap();
+        static ref SHELL: Regex =
+            Regex::new(r"^\s*(function\s+[\w-]+|[\w-]+\s*\(\s*\))").unwrap();
+    }
+    Some(match language? {
+        "Rust" => &RUST,
+        "Python" => &PYTHON,
+        "Ruby" => &RUBY,
+        "Elixir" => &ELIXIR,
+        "Go" => &GO,
+        "JavaScript" | "TypeScript" | "Vue" | "CoffeeScript" => &JAVASCRIPT,
+        "PHP" => &PHP,
+        "Lua" => &LUA,
+        "Kotlin" => &KOTLIN,
+        "Swift" => &SWIFT,
+        "Scala" => &SCALA,
+        "Java" | "C#" | "Groovy" | "Dart" => &JAVA,
+        "C" | "C++" | "Objective-C" | "Objective-C++" => &C,
+        "Shell" => &SHELL,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_metrics() {
+        let content = b"fn main() {\n    if true {\n        println!(\"hi\");\n    }\n}\n\npub(crate) async fn run() {}\n";
+        let metrics = FileMetrics::of(content, Some("Rust"));
+        assert_eq!(metrics.size, content.len() as u64);
+        assert_eq!(metrics.max_line_length, 28);
+        assert_eq!(metrics.indentation, 4);
+        assert_eq!(metrics.functions, Some(2));
+        assert_eq!(FileMetrics::of(b"\tif x:\n\t\tpass\n", None).indentation, 3);
+        assert_eq!(
+            FileMetrics::of(b"hello\n", Some("Markdown")).functions,
+            None
+        );
+    }
+
+    #[test]
+    fn test_complexity_delta() {
+        let before = FileMetrics::of(b"def f():\n  return 1\n", Some("Python"));
+        let after = FileMetrics::of(
+            b"def f(x):\n  if x:\n    return 1\n  return 2\n\ndef g():\n  pass\n",
+            Some("Python"),
+        );
+        let delta = ComplexityDelta::new(before, after);
+        assert_eq!(delta.indentation, 4);
+        assert_eq!(delta.functions, Some(1));
+        assert!(delta.increases_complexity());
+        let deleted = ComplexityDelta::new(after, FileMetrics::of(b"", Some("Python")));
+        assert_eq!(deleted.size, -(after.size as i64));
+        assert_eq!(deleted.functions, Some(-2));
+        assert!(!deleted.increases_complexity());
+    }
+}
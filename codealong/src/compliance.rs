@@ -0,0 +1,189 @@
+use linked_hash_map::LinkedHashMap;
+use regex::Regex;
+
+use crate::pattern::Pattern;
+
+/// A trailer, or other reference, which every commit message is required to
+/// contain, e.g. a `Signed-off-by` trailer or a ticket reference.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct RequiredTrailer {
+    /// Trailer token, e.g. `Signed-off-by`. When omitted, `pattern` is
+    /// matched against the whole message instead.
+    #[serde(default)]
+    pub trailer: Option<String>,
+
+    /// Regex which the trailer value, or message, must match.
+    #[serde(default)]
+    pub pattern: Option<Pattern>,
+}
+
+/// Result of checking a commit message against the required trailers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Compliance {
+    pub compliant: bool,
+    /// Names of the requirements which were not met.
+    pub missing: Vec<String>,
+}
+
+/// Parses `Token: value` trailers from the last paragraph of a commit
+/// message.
+pub fn trailers(message: &str) -> Vec<(String, String)> {
+    lazy_static! {
+        static ref TRAILER_REGEX: Regex =
+            Regex::new(r"^([A-Za-z0-9][A-Za-z0-9-]*):\s*(.*?)\s*$").unwrap();
+    }
+    let paragraphs: Vec<&str> = message
+        .trim()
+        .split("\n\n")
+        .filter(|p| !p.trim().is_empty())
+        .collect();
+    // a single paragraph is just the subject
+    if paragraphs.len() < 2 {
+        return vec![];
+    }
+    let last = paragraphs[paragraphs.len() - 1];
+    let res: Vec<(String, String)> = last
+        .lines()
+        .filter_map(|line| TRAILER_REGEX.captures(line))
+        .map(|c| (c[1].to_owned(), c[2].to_owned()))
+        .collect();
+    if res.len() == last.lines().count() {
+        res
+    } else {
+        vec![]
+    }
+}
+
+/// The trailers of `message` keyed by their token in snake case, e.g.
+/// `Reviewed-by` becomes `reviewed_by`, with the values of repeated trailers
+/// in order.
+pub fn trailer_fields(message: &str) -> LinkedHashMap<String, Vec<String>> {
+    let mut res: LinkedHashMap<String, Vec<String>> = LinkedHashMap::new();
+    for (token, value) in trailers(message) {
+        let key = token.to_lowercase().replace('-', "_");
+        res.entry(key).or_insert_with(Vec::new).push(value);
+    }
+    res
+}
+
+/// The first value of each of the `promoted` trailers found in `fields`,
+/// keyed by the field it is promoted to.
+pub fn promote_trailers(
+    fields: &LinkedHashMap<String, Vec<String>>,
+    promoted: &LinkedHashMap<String, String>,
+) -> LinkedHashMap<String, String> {
+    promoted
+        .iter()
+        .filter_map(|(field, token)| {
+            let key = token.to_lowercase().replace('-', "_");
+            let value = fields.get(&key)?.first()?;
+            Some((field.clone(), value.clone()))
+        })
+        .collect()
+}
+
+/// Checks `message` against each of `requirements`.
+pub fn check_compliance(
+    message: &str,
+    requirements: &LinkedHashMap<String, RequiredTrailer>,
+) -> Compliance {
+    let trailers = trailers(message);
+    let mut missing = vec![];
+    for (name, requirement) in requirements {
+        let matches = |value: &str| {
+            requirement
+                .pattern
+                .as_ref()
+                .is_none_or(|p| p.is_match(value))
+        };
+        let met = match requirement.trailer {
+            Some(ref token) => trailers
+                .iter()
+                .any(|(t, value)| t.eq_ignore_ascii_case(token) && matches(value)),
+            None => matches(message),
+        };
+        if !met {
+            missing.push(name.to_owned());
+        }
+    }
+    Compliance {
+        compliant: missing.is_empty(),
+        missing,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trailers() {
+        let message = "Fix parser\n\nSee: the docs\n\nSigned-off-by: Jane <jane@example.com>\nReviewed-by: Bob\n";
+        assert_eq!(
+            trailers(message),
+            vec![
+                (
+                    "Signed-off-by".to_owned(),
+                    "Jane <jane@example.com>".to_owned()
+                ),
+                ("Reviewed-by".to_owned(), "Bob".to_owned())
+            ]
+        );
+        assert_eq!(trailers("Signed-off-by: Jane"), vec![]);
+        assert_eq!(
+            trailers("Fix\n\nThis is a body: not a trailer\nat all"),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_trailer_fields() {
+        let message = "Fix parser\n\nReviewed-by: Bob\nChange-Id: I1234\nreviewed-by: Eve\n";
+        let fields = trailer_fields(message);
+        let keys: Vec<&str> = fields.keys().map(|key| key.as_str()).collect();
+        assert_eq!(keys, vec!["reviewed_by", "change_id"]);
+        assert_eq!(fields["reviewed_by"], vec!["Bob", "Eve"]);
+
+        let mut promoted = LinkedHashMap::new();
+        promoted.insert("change_id".to_owned(), "Change-Id".to_owned());
+        promoted.insert("reviewer".to_owned(), "Reviewed-By".to_owned());
+        promoted.insert("ticket".to_owned(), "Ticket".to_owned());
+        let promoted = promote_trailers(&fields, &promoted);
+        assert_eq!(
+            promoted.into_iter().collect::<Vec<(String, String)>>(),
+            vec![
+                ("change_id".to_owned(), "I1234".to_owned()),
+                ("reviewer".to_owned(), "Bob".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_compliance() {
+        let mut requirements = LinkedHashMap::new();
+        requirements.insert(
+            "dco".to_owned(),
+            RequiredTrailer {
+                trailer: Some("Signed-off-by".to_owned()),
+                pattern: None,
+            },
+        );
+        requirements.insert(
+            "ticket".to_owned(),
+            RequiredTrailer {
+                trailer: None,
+                pattern: Some(Pattern::new(r"\b[A-Z]+-\d+\b").unwrap()),
+            },
+        );
+        let compliance = check_compliance(
+            "PAY-12 Fix rounding\n\nsigned-off-by: Jane <jane@example.com>",
+            &requirements,
+        );
+        assert!(compliance.compliant);
+        let compliance = check_compliance("Fix rounding", &requirements);
+        assert_eq!(compliance.missing, vec!["dco", "ticket"]);
+
+        let invalid = "ticket:\n  pattern: '[A-Z+-\\d+'";
+        assert!(serde_yaml::from_str::<LinkedHashMap<String, RequiredTrailer>>(invalid).is_err());
+    }
+}
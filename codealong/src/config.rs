@@ -1,15 +1,34 @@
-use std::collections::HashSet;
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::iter;
 use std::path::Path;
 
+use chrono::prelude::*;
+use chrono::DateTime;
 use glob::Pattern;
 use linked_hash_map::LinkedHashMap;
 use serde_yaml;
 
+use crate::calendar::Calendar;
+use crate::commit_analyzer::OctopusMerges;
+use crate::commit_convention::CommitConvention;
+use crate::compliance::RequiredTrailer;
+use crate::diff_analyzer::RenameDetection;
+use crate::diff_sampling::DiffSampling;
 use crate::error::{Error, Result};
+use crate::git_blame::BlameEngine;
 use crate::identity::Identity;
-use crate::person::Person;
+use crate::issues::IssueReferences;
+use crate::mailmap::Mailmap;
+use crate::message_storage::MessageStorage;
+use crate::path_filter::PathFilter;
+use crate::period::Period;
+use crate::replace_refs::ReplaceRefs;
+use crate::repo::ArchivedRepos;
+use crate::slo::Slo;
+use crate::text::truncate_graphemes;
+use codealong_model::{config_for_file, Person};
+pub use codealong_model::{FileConfig, GlobConfig, Validity};
 
 use include_dir::Dir;
 
@@ -22,6 +41,8 @@ static BASE_CONFIGS: Dir = include_dir!("./config");
 /// ```yaml
 /// github: ghempton/codealong
 /// churn_cutoff: 14
+/// pairing_window: 30
+/// session_gap: 120
 ///
 /// merge_defaults: true
 ///
@@ -41,14 +62,134 @@ static BASE_CONFIGS: Dir = include_dir!("./config");
 ///       - "styles"
 ///       - "css"
 ///     weight: 0.5
+///   "**/*.pb.go":
+///     language: "go-protobuf"
+///   "legacy/**/*":
+///     tags:
+///       - "legacy"
+///     until: "2018-12-31"
+///
+/// language_weights:
+///   go-protobuf: 0.1
+///
+/// churn_cutoffs:
+///   "src/**/*": 90
+///   "docs/**/*": 14
+///
+/// large_files:
+///   max_size: 524288
+///   weight: 0.1
+///
+/// only_paths:
+///   - "services/billing/**"
+/// exclude_paths:
+///   - "**/vendor/**"
+///
+/// calendar:
+///   utc_offset: -7
+///   holidays:
+///     - "2019-12-25"
+///
+/// slos:
+///   small_pull_requests:
+///     metric: lines_changed
+///     max: 400
+///
+/// signing_keyring: "/etc/codealong/trusted.gpg"
+/// blame_cache_dir: "/var/cache/codealong/blame"
+/// blame_engine: external
+/// rename_detection: copies
+/// rollup_period:
+///   sprint:
+///     start: "2020-01-06"
+///     days: 14
+///
+/// notes:
+///   ci: "refs/notes/ci"
+///
+/// repo_aliases:
+///   ghempton/codealong-old: ghempton/codealong
+///
+/// replace_refs: honor
+/// octopus_merges: first_parent
+/// archived_repos: freeze
+/// summary_max_length: 72
+/// message_storage: hash
+/// diff_sampling:
+///   rate: 0.01
+///   max_lines: 20
+/// tenant: acme
+///
+/// field_encryption:
+///   public_key: "/etc/codealong/fields.pub.pem"
+///   fields:
+///     - author.email
+///     - committer.email
+///
+/// required_trailers:
+///   dco:
+///     trailer: "Signed-off-by"
+///   ticket:
+///     pattern: "\\b[A-Z]+-\\d+\\b"
+///
+/// promoted_trailers:
+///   change_id: "Change-Id"
+///
+/// issue_references:
+///   jira_projects:
+///     - "ABC"
+///   github: false
+///   patterns:
+///     - "RT\\s*(\\d+)"
+///
+/// commit_convention:
+///   types:
+///     - feat
+///     - fix
+///     - hotfix
+///
+/// sinks:
+///   elasticsearch:
+///     batch_size: 500
+///     max_in_flight: 4
+///     flush_interval_ms: 5000
+///     max_retries: 5
+///     retry_backoff_ms: 1000
+///   ndjson:
+///     batch_size: 1000
+///
+/// mailmap:
+///   - "Gordon Hempton <ghempton@gmail.com> <gordon@laptop.local>"
 ///
 /// authors:
 ///   "Gordon Hempton <ghempton@gmail.com>":
 ///     aliases:
 ///       - "Gordon Hempton <gordon@hempton.com>"
+///     gitlab_usernames:
+///       - "ghempton"
+///     bitbucket_usernames:
+///       - "ghempton"
+///     imported_usernames:
+///       - "ghempton"
 ///     tags:
 ///       - "team-apollo"
+///     teams:
+///       - "apollo"
+///     history:
+///       - until: "2018-06-30"
+///         teams:
+///           - "zeus"
+///
+/// teams:
+///   apollo:
+///     parent: "platform"
+///   platform:
+///     members:
+///       - "octocat"
 /// ```
+///
+/// Entries with `from` and `until` dates only apply to events between those
+/// dates, so that the analysis of old commits is not skewed by today's rules.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Config {
     #[serde(default = "Config::default_merge_defaults")]
@@ -57,11 +198,166 @@ pub struct Config {
     #[serde(default = "Config::default_churn_cutoff")]
     pub churn_cutoff: u64,
 
+    /// Maximum number of minutes between consecutive commits by different
+    /// authors for them to be considered part of the same pairing session.
+    #[serde(default = "Config::default_pairing_window")]
+    pub pairing_window: u64,
+
+    /// Maximum number of minutes between consecutive commits by the same
+    /// author for them to be considered part of the same work session when
+    /// estimating effort.
+    #[serde(default = "Config::default_session_gap")]
+    pub session_gap: u64,
+
     #[serde(default)]
     pub files: LinkedHashMap<String, GlobConfig>,
 
     #[serde(default)]
     pub authors: LinkedHashMap<String, AuthorConfig>,
+
+    /// Globs of the files analyzed within each commit. All files are analyzed
+    /// if empty.
+    #[serde(default)]
+    pub only_paths: Vec<String>,
+
+    /// Globs of files which are never analyzed. Unlike ignored files, these
+    /// are skipped entirely rather than analyzed without being counted.
+    #[serde(default)]
+    pub exclude_paths: Vec<String>,
+
+    /// Multipliers applied to churn based on the language of each file, since
+    /// raw line counts are not comparable across languages. Languages are
+    /// those declared by the globs of `files`, or else those detected, e.g.
+    /// `Rust`.
+    #[serde(default)]
+    pub language_weights: LinkedHashMap<String, f64>,
+
+    /// How files too large to be worth blaming are analyzed.
+    #[serde(default)]
+    pub large_files: LargeFiles,
+
+    /// Churn cutoffs of the files matching each glob, overriding
+    /// `churn_cutoff` for areas changing at a different pace. The last
+    /// matching glob wins.
+    #[serde(default)]
+    pub churn_cutoffs: LinkedHashMap<String, u64>,
+
+    /// Business calendar used to report latencies in working time.
+    #[serde(default)]
+    pub calendar: Option<Calendar>,
+
+    /// Period snapshots are rolled up over, ISO weeks unless configured,
+    /// e.g. to follow sprints or fiscal quarters.
+    #[serde(default)]
+    pub rollup_period: Option<Period>,
+
+    /// Service level objectives which emit breach events when violated.
+    #[serde(default)]
+    pub slos: LinkedHashMap<String, Slo>,
+
+    /// Trailers or references every commit message must contain.
+    #[serde(default)]
+    pub required_trailers: LinkedHashMap<String, RequiredTrailer>,
+
+    /// Trailers copied from `trailers` to top-level fields of commit events,
+    /// keyed by the name of the field, e.g. `change_id: Change-Id`. The
+    /// field holds the first value of the trailer.
+    #[serde(default)]
+    pub promoted_trailers: LinkedHashMap<String, String>,
+
+    /// References to tickets and issues extracted from commit messages and
+    /// pull requests.
+    #[serde(default)]
+    pub issue_references: IssueReferences,
+
+    /// How commits are classified from messages following the Conventional
+    /// Commits convention.
+    #[serde(default)]
+    pub commit_convention: CommitConvention,
+
+    /// Path to a GPG keyring used to verify commit signatures.
+    #[serde(default)]
+    pub signing_keyring: Option<String>,
+
+    /// Directory in which blames are cached across runs, in addition to
+    /// the in-memory cache shared across the commits of a run.
+    #[serde(default)]
+    pub blame_cache_dir: Option<String>,
+
+    /// Whether blames are run by the `git` binary or by libgit2.
+    #[serde(default)]
+    pub blame_engine: BlameEngine,
+
+    /// Whether files renamed or copied by a commit are detected, so that
+    /// churn follows them rather than their lines counting as new work.
+    #[serde(default)]
+    pub rename_detection: RenameDetection,
+
+    /// Notes refs to attach to commit events, keyed by the name of the field
+    /// the notes are attached under.
+    #[serde(default)]
+    pub notes: LinkedHashMap<String, String>,
+
+    /// Former names of repos mapped to their current names, e.g. after a
+    /// rename or a transfer to another org. Events are emitted under the
+    /// current name.
+    #[serde(default)]
+    pub repo_aliases: LinkedHashMap<String, String>,
+
+    /// Whether `git replace` refs are honored when walking history.
+    #[serde(default)]
+    pub replace_refs: ReplaceRefs,
+
+    /// How the diff of a merge with more than two parents is attributed.
+    #[serde(default)]
+    pub octopus_merges: OctopusMerges,
+
+    /// Whether archived repos are analyzed on every run, only once, or not
+    /// at all.
+    #[serde(default)]
+    pub archived_repos: ArchivedRepos,
+
+    /// Maximum length of commit summaries and pull request titles, in
+    /// user-perceived characters rather than bytes so that CJK text and
+    /// emoji are not cut mid-character.
+    #[serde(default)]
+    pub summary_max_length: Option<usize>,
+
+    /// Whether commit summaries and pull request titles and bodies are
+    /// stored in full, truncated, hashed or dropped from emitted events.
+    #[serde(default)]
+    pub message_storage: MessageStorage,
+
+    /// Attaches redacted excerpts of their diffs to a sample of commits.
+    #[serde(default)]
+    pub diff_sampling: Option<DiffSampling>,
+
+    /// Fields of emitted events which are encrypted before being written.
+    #[serde(default)]
+    pub field_encryption: Option<EncryptionConfig>,
+
+    /// Organization the events belong to, when one deployment serves
+    /// several. Emitted events are stamped with it and written to indices
+    /// prefixed with it, so that the data of each stays apart.
+    #[serde(default)]
+    pub tenant: Option<String>,
+
+    /// Entries in the format of git's `.mailmap`, applied to identities
+    /// before they are matched against `authors`, along with the entries of
+    /// the `.mailmap` of the repo being analyzed.
+    #[serde(default)]
+    pub mailmap: Mailmap,
+
+    /// Batching of the events written to each sink, keyed by sink:
+    /// `elasticsearch`, `ndjson` or `csv`. Events are written one at a time
+    /// to sinks without an entry.
+    #[serde(default)]
+    pub sinks: BTreeMap<String, SinkConfig>,
+
+    /// Teams keyed by name, along with the members they have in addition to
+    /// the authors listing them under `teams`.
+    #[serde(default)]
+    pub teams: BTreeMap<String, TeamConfig>,
 }
 
 impl Config {
@@ -80,6 +376,13 @@ impl Config {
         }
     }
 
+    /// Reads a config to be merged over another as it is, without applying
+    /// the base config, so that the base does not override what the other
+    /// config changed.
+    pub fn overlay_from_path(path: &Path) -> Result<Self> {
+        Ok(serde_yaml::from_reader(File::open(path)?)?)
+    }
+
     /// Base config with embedded defaults
     pub fn base() -> Self {
         let mut config = Config::default();
@@ -91,7 +394,11 @@ impl Config {
 
     pub fn maybe_apply_base(&mut self) {
         if self.merge_defaults {
-            self.merge(Self::base());
+            // merged over the base so that the config takes precedence, e.g.
+            // for the weight of files matching globs of both
+            let config = std::mem::take(self);
+            *self = Self::base();
+            self.merge(config);
         }
     }
 
@@ -103,36 +410,173 @@ impl Config {
         14
     }
 
-    /// Merges in all file and author configs
+    fn default_pairing_window() -> u64 {
+        30
+    }
+
+    fn default_session_gap() -> u64 {
+        120
+    }
+
+    /// Merges `other` into the config, `other` taking precedence: the
+    /// entries of its maps replace those with the same keys, and its fields
+    /// which are set, or differ from their defaults for those which are not
+    /// optional, replace those of the config.
     pub fn merge(&mut self, other: Config) {
         self.files.extend(other.files);
         self.authors.extend(other.authors);
+        self.only_paths.extend(other.only_paths);
+        self.exclude_paths.extend(other.exclude_paths);
+        self.language_weights.extend(other.language_weights);
+        self.churn_cutoffs.extend(other.churn_cutoffs);
+        self.slos.extend(other.slos);
+        self.required_trailers.extend(other.required_trailers);
+        self.promoted_trailers.extend(other.promoted_trailers);
+        self.issue_references.merge(other.issue_references);
+        self.commit_convention.merge(other.commit_convention);
+        self.notes.extend(other.notes);
+        self.repo_aliases.extend(other.repo_aliases);
+        self.sinks.extend(other.sinks);
+        self.teams.extend(other.teams);
+        // later entries of the mailmap win
+        self.mailmap.extend(other.mailmap);
+        merge_option(&mut self.calendar, other.calendar);
+        merge_option(&mut self.rollup_period, other.rollup_period);
+        merge_option(&mut self.signing_keyring, other.signing_keyring);
+        merge_option(&mut self.blame_cache_dir, other.blame_cache_dir);
+        merge_option(&mut self.summary_max_length, other.summary_max_length);
+        merge_option(&mut self.diff_sampling, other.diff_sampling);
+        merge_option(&mut self.field_encryption, other.field_encryption);
+        merge_option(&mut self.tenant, other.tenant);
+        let defaults = Config::default();
+        merge_value(
+            &mut self.churn_cutoff,
+            other.churn_cutoff,
+            defaults.churn_cutoff,
+        );
+        merge_value(
+            &mut self.pairing_window,
+            other.pairing_window,
+            defaults.pairing_window,
+        );
+        merge_value(
+            &mut self.session_gap,
+            other.session_gap,
+            defaults.session_gap,
+        );
+        merge_value(
+            &mut self.large_files,
+            other.large_files,
+            defaults.large_files,
+        );
+        merge_value(
+            &mut self.blame_engine,
+            other.blame_engine,
+            defaults.blame_engine,
+        );
+        merge_value(
+            &mut self.rename_detection,
+            other.rename_detection,
+            defaults.rename_detection,
+        );
+        merge_value(
+            &mut self.replace_refs,
+            other.replace_refs,
+            defaults.replace_refs,
+        );
+        merge_value(
+            &mut self.octopus_merges,
+            other.octopus_merges,
+            defaults.octopus_merges,
+        );
+        merge_value(
+            &mut self.archived_repos,
+            other.archived_repos,
+            defaults.archived_repos,
+        );
+        merge_value(
+            &mut self.message_storage,
+            other.message_storage,
+            defaults.message_storage,
+        );
+    }
+
+    /// Follows `repo_aliases` from `name` to the current name of the repo.
+    pub fn canonical_repo_name<'a>(&'a self, name: &'a str) -> &'a str {
+        let mut res = name;
+        // bounded in case the aliases contain a cycle
+        for _ in 0..self.repo_aliases.len() {
+            match self.repo_aliases.get(res) {
+                Some(alias) if alias != res => res = alias,
+                _ => break,
+            }
+        }
+        res
     }
 
     pub fn config_for_file(&self, path: &str) -> Option<FileConfig> {
-        let glob_configs: Vec<&GlobConfig> = self
-            .files
+        self.config_for_file_at(path, &Utc::now())
+    }
+
+    /// Combines the configs of the globs matching `path` which apply at `at`.
+    pub fn config_for_file_at(&self, path: &str, at: &DateTime<Utc>) -> Option<FileConfig<'_>> {
+        config_for_file(&self.files, path, Some(at))
+    }
+
+    pub fn path_filter(&self) -> PathFilter {
+        PathFilter::new(&self.only_paths, &self.exclude_paths)
+    }
+
+    /// The churn weight of a language, defaulting to 1.0 when not configured.
+    pub fn language_weight(&self, language: &str) -> f64 {
+        self.language_weights.get(language).cloned().unwrap_or(1.0)
+    }
+
+    /// The churn cutoff of the last glob of `churn_cutoffs` matching
+    /// `path`, if any.
+    pub fn churn_cutoff_for_file(&self, path: &str) -> Option<u64> {
+        self.churn_cutoffs
             .iter()
-            .filter_map(|(s, config)| {
-                if let Ok(pattern) = Pattern::new(&s) {
-                    if pattern.matches(path) {
-                        Some(config)
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
-            })
-            .collect();
-        if glob_configs.is_empty() {
-            None
-        } else {
-            Some(FileConfig::new(glob_configs))
+            .rev()
+            .find(|(glob, _)| Pattern::new(glob).is_ok_and(|pattern| pattern.matches(path)))
+            .map(|(_, churn_cutoff)| *churn_cutoff)
+    }
+
+    /// The configured rollup period, defaulting to ISO weeks.
+    pub fn rollup_period(&self) -> Period {
+        self.rollup_period.unwrap_or_default()
+    }
+
+    /// The configured business calendar, defaulting to Monday through Friday,
+    /// 9 to 5 UTC.
+    pub fn calendar(&self) -> Calendar {
+        self.calendar.clone().unwrap_or_default()
+    }
+
+    /// A commit summary or pull request title as stored in events, according
+    /// to `summary_max_length` and `message_storage`.
+    pub fn stored_summary(&self, summary: &str) -> Option<String> {
+        match self.summary_max_length {
+            Some(max) => self
+                .message_storage
+                .store(&truncate_graphemes(summary, max)),
+            None => self.message_storage.store(summary),
         }
     }
 
+    /// A pull request body as stored in events, according to
+    /// `message_storage`.
+    pub fn stored_message(&self, message: &str) -> Option<String> {
+        self.message_storage.store(message)
+    }
+
+    /// The identity `identity` maps to according to the `mailmap`.
+    pub fn resolve_identity(&self, identity: &Identity) -> Identity {
+        self.mailmap.resolve(identity)
+    }
+
     pub fn config_for_identity(&self, identity: &Identity) -> Option<PersonConfig> {
+        let identity = &self.resolve_identity(identity);
         for (key, author_config) in &self.authors {
             for alias in iter::once(key).chain(&author_config.aliases) {
                 if &Identity::parse(alias) == identity {
@@ -154,17 +598,58 @@ impl Config {
         None
     }
 
+    pub fn config_for_gitlab_username(&self, username: &str) -> Option<PersonConfig<'_>> {
+        self.authors
+            .iter()
+            .find(|(_, author_config)| author_config.gitlab_usernames.iter().any(|u| u == username))
+            .map(|(key, author_config)| PersonConfig::new(key, author_config))
+    }
+
+    pub fn config_for_bitbucket_username(&self, username: &str) -> Option<PersonConfig<'_>> {
+        self.authors
+            .iter()
+            .find(|(_, author_config)| {
+                author_config
+                    .bitbucket_usernames
+                    .iter()
+                    .any(|u| u == username)
+            })
+            .map(|(key, author_config)| PersonConfig::new(key, author_config))
+    }
+
+    pub fn config_for_imported_username(&self, username: &str) -> Option<PersonConfig<'_>> {
+        self.authors
+            .iter()
+            .find(|(_, author_config)| {
+                author_config
+                    .imported_usernames
+                    .iter()
+                    .any(|u| u == username)
+            })
+            .map(|(key, author_config)| PersonConfig::new(key, author_config))
+    }
+
     pub fn person_for_identity(&self, identity: &Identity) -> Person {
-        if let Some(person_config) = self.config_for_identity(identity) {
-            person_config.to_person()
+        self.person_for_identity_at(identity, &Utc::now())
+    }
+
+    /// The person for `identity` with the tags and teams they had at `at`.
+    pub fn person_for_identity_at(&self, identity: &Identity, at: &DateTime<Utc>) -> Person {
+        let person = if let Some(person_config) = self.config_for_identity(identity) {
+            person_config.at(at).to_person()
         } else {
-            identity.to_person()
-        }
+            self.resolve_identity(identity).to_person()
+        };
+        self.with_teams(person)
     }
 
     pub fn person_for_github_login(&self, github_login: &str) -> Person {
-        if let Some(person_config) = self.config_for_github_login(github_login) {
-            person_config.to_person()
+        self.person_for_github_login_at(github_login, &Utc::now())
+    }
+
+    pub fn person_for_github_login_at(&self, github_login: &str, at: &DateTime<Utc>) -> Person {
+        let person = if let Some(person_config) = self.config_for_github_login(github_login) {
+            person_config.at(at).to_person()
         } else {
             Person {
                 id: github_login.to_owned(),
@@ -173,7 +658,122 @@ impl Config {
                 email: None,
                 teams: vec![],
             }
+        };
+        self.with_teams(person)
+    }
+
+    pub fn person_for_gitlab_username_at(&self, username: &str, at: &DateTime<Utc>) -> Person {
+        let person = if let Some(person_config) = self.config_for_gitlab_username(username) {
+            person_config.at(at).to_person()
+        } else {
+            Person {
+                id: username.to_owned(),
+                name: None,
+                email: None,
+                github_login: None,
+                teams: vec![],
+            }
+        };
+        self.with_teams(person)
+    }
+
+    /// Bitbucket users are matched by their nickname or account id.
+    pub fn person_for_bitbucket_username_at(&self, username: &str, at: &DateTime<Utc>) -> Person {
+        let person = if let Some(person_config) = self.config_for_bitbucket_username(username) {
+            person_config.at(at).to_person()
+        } else {
+            Person {
+                id: username.to_owned(),
+                name: None,
+                email: None,
+                github_login: None,
+                teams: vec![],
+            }
+        };
+        self.with_teams(person)
+    }
+
+    pub fn person_for_imported_username(&self, username: &str) -> Person {
+        self.person_for_imported_username_at(username, &Utc::now())
+    }
+
+    pub fn person_for_imported_username_at(&self, username: &str, at: &DateTime<Utc>) -> Person {
+        let person = if let Some(person_config) = self.config_for_imported_username(username) {
+            person_config.at(at).to_person()
+        } else {
+            Person {
+                id: username.to_owned(),
+                name: Some(username.to_owned()),
+                email: None,
+                github_login: None,
+                teams: vec![],
+            }
+        };
+        self.with_teams(person)
+    }
+
+    /// Adds to the teams of `person` those listing them under `members`,
+    /// and the parents of each of their teams.
+    fn with_teams(&self, mut person: Person) -> Person {
+        if self.teams.is_empty() {
+            return person;
+        }
+        for (name, team) in &self.teams {
+            let is_member = team
+                .members
+                .iter()
+                .any(|member| *member == person.id || person.github_login.as_ref() == Some(member));
+            if is_member && !person.teams.contains(name) {
+                person.teams.push(name.clone());
+            }
+        }
+        let mut i = 0;
+        while i < person.teams.len() {
+            let parent = self
+                .teams
+                .get(&person.teams[i])
+                .and_then(|team| team.parent.as_ref());
+            if let Some(parent) = parent {
+                // the check also stops cycles between parents
+                if !person.teams.contains(parent) {
+                    person.teams.push(parent.clone());
+                }
+            }
+            i += 1;
         }
+        person
+    }
+
+    /// The raw identities which are mapped to the person with the id
+    /// `author_id`, through `authors` or the `mailmap`.
+    pub fn identities_for(&self, author_id: &str) -> AuthorIdentities {
+        let mut res = AuthorIdentities::default();
+        match self.authors.get(author_id) {
+            Some(author_config) => {
+                for alias in iter::once(author_id).chain(author_config.aliases.iter().map(|a| &**a))
+                {
+                    let identity = Identity::parse(alias);
+                    res.identities.extend(self.mailmap.sources(&identity));
+                    res.identities.push(identity);
+                }
+                res.github_logins = author_config.github_logins.clone();
+                res.gitlab_usernames = author_config.gitlab_usernames.clone();
+                res.bitbucket_usernames = author_config.bitbucket_usernames.clone();
+                res.imported_usernames = author_config.imported_usernames.clone();
+            }
+            None => {
+                let identity = Identity::parse(author_id);
+                res.identities.extend(self.mailmap.sources(&identity));
+                res.identities.push(identity);
+            }
+        }
+        let mut seen = vec![];
+        res.identities.retain(|identity| {
+            let new = !seen.contains(identity);
+            seen.push(identity.clone());
+            new
+        });
+        res
     }
 
     pub fn is_known(&self, identity: &Identity) -> bool {
@@ -183,6 +783,14 @@ impl Config {
     pub fn is_github_login_known(&self, github_login: &str) -> bool {
         self.config_for_github_login(github_login).is_some()
     }
+
+    pub fn is_gitlab_username_known(&self, username: &str) -> bool {
+        self.config_for_gitlab_username(username).is_some()
+    }
+
+    pub fn is_bitbucket_username_known(&self, username: &str) -> bool {
+        self.config_for_bitbucket_username(username).is_some()
+    }
 }
 
 impl Default for Config {
@@ -190,55 +798,143 @@ impl Default for Config {
         Config {
             merge_defaults: true,
             churn_cutoff: 14,
+            pairing_window: 30,
+            session_gap: 120,
             files: LinkedHashMap::new(),
             authors: LinkedHashMap::new(),
+            only_paths: vec![],
+            exclude_paths: vec![],
+            language_weights: LinkedHashMap::new(),
+            churn_cutoffs: LinkedHashMap::new(),
+            large_files: LargeFiles::default(),
+            calendar: None,
+            rollup_period: None,
+            slos: LinkedHashMap::new(),
+            required_trailers: LinkedHashMap::new(),
+            promoted_trailers: LinkedHashMap::new(),
+            issue_references: IssueReferences::default(),
+            commit_convention: CommitConvention::default(),
+            signing_keyring: None,
+            blame_cache_dir: None,
+            blame_engine: BlameEngine::default(),
+            rename_detection: RenameDetection::default(),
+            notes: LinkedHashMap::new(),
+            repo_aliases: LinkedHashMap::new(),
+            replace_refs: ReplaceRefs::default(),
+            octopus_merges: OctopusMerges::default(),
+            archived_repos: ArchivedRepos::default(),
+            summary_max_length: None,
+            message_storage: MessageStorage::default(),
+            diff_sampling: None,
+            field_encryption: None,
+            tenant: None,
+            mailmap: Mailmap::default(),
+            sinks: BTreeMap::new(),
+            teams: BTreeMap::new(),
         }
     }
 }
 
+/// Envelope encryption of event fields holding personal data, for
+/// organizations which must encrypt it before it leaves the application.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct GlobConfig {
-    #[serde(default)]
-    pub tags: Vec<String>,
+pub struct EncryptionConfig {
+    /// Path to the PEM encoded RSA public key the keys of the encrypted
+    /// fields are wrapped with. Only the holder of the private key can
+    /// decrypt them, with `codealong decrypt`.
+    pub public_key: String,
 
-    #[serde(default = "GlobConfig::default_weight")]
-    pub weight: f64,
+    /// Dotted paths of the fields to encrypt, e.g. `author.email`. Arrays
+    /// along the path have the field of each of their elements encrypted.
+    pub fields: Vec<String>,
+}
+
+/// How events are batched before being written to a sink.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SinkConfig {
+    /// Number of events written at once, e.g. in a single bulk request to
+    /// Elasticsearch.
+    #[serde(default = "SinkConfig::default_batch_size")]
+    pub batch_size: usize,
 
+    /// Number of batches which may be being written at the same time before
+    /// the analysis waits for one of them to complete.
+    #[serde(default = "SinkConfig::default_max_in_flight")]
+    pub max_in_flight: usize,
+
+    /// Milliseconds after which a batch is written even if it is not full,
+    /// checked as events are added to it.
     #[serde(default)]
-    pub ignore: bool,
+    pub flush_interval_ms: Option<u64>,
+
+    /// Number of times a write rejected by Elasticsearch as overloaded or
+    /// unavailable, with a 429 or 503, is retried before it fails.
+    #[serde(default = "SinkConfig::default_max_retries")]
+    pub max_retries: u32,
+
+    /// Milliseconds waited before the first retry, doubled for each retry
+    /// after it.
+    #[serde(default = "SinkConfig::default_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
 }
 
-impl GlobConfig {
-    fn default_weight() -> f64 {
-        1.0
+impl SinkConfig {
+    fn default_batch_size() -> usize {
+        1
     }
-}
 
-/// Represents multiple underlying glob-level configurations. A file can have
-/// mulitiple configurations if it matches multiple globs.
-pub struct FileConfig<'a> {
-    configs: Vec<&'a GlobConfig>,
-}
+    fn default_max_in_flight() -> usize {
+        1
+    }
 
-impl<'a> FileConfig<'a> {
-    pub fn new(configs: Vec<&'a GlobConfig>) -> FileConfig<'a> {
-        FileConfig { configs: configs }
+    pub fn default_max_retries() -> u32 {
+        3
     }
 
-    pub fn tags(&self) -> HashSet<&str> {
-        let mut res = HashSet::new();
-        for config in &self.configs {
-            res.extend(config.tags.iter().map(|s| &**s));
+    pub fn default_retry_backoff_ms() -> u64 {
+        500
+    }
+}
+
+impl Default for SinkConfig {
+    fn default() -> SinkConfig {
+        SinkConfig {
+            batch_size: SinkConfig::default_batch_size(),
+            max_in_flight: SinkConfig::default_max_in_flight(),
+            flush_interval_ms: None,
+            max_retries: SinkConfig::default_max_retries(),
+            retry_backoff_ms: SinkConfig::default_retry_backoff_ms(),
         }
-        res
     }
+}
+
+/// How files larger than `max_size` are analyzed. Their lines, e.g. those of
+/// a generated lockfile or a data dump, skew churn and are slow to blame, so
+/// they are skipped unless a `weight` is given to count them with.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LargeFiles {
+    /// Size in bytes of either version of a file above which it is large.
+    #[serde(default = "LargeFiles::default_max_size")]
+    pub max_size: u64,
 
-    pub fn weight(&self) -> f64 {
-        self.configs.last().unwrap().weight
+    /// Weight the lines of large files are counted with, instead of being
+    /// skipped.
+    #[serde(default)]
+    pub weight: Option<f64>,
+}
+
+impl LargeFiles {
+    fn default_max_size() -> u64 {
+        1024 * 1024
     }
+}
 
-    pub fn ignore(&self) -> bool {
-        self.configs.iter().any(|c| c.ignore)
+impl Default for LargeFiles {
+    fn default() -> LargeFiles {
+        LargeFiles {
+            max_size: LargeFiles::default_max_size(),
+            weight: None,
+        }
     }
 }
 
@@ -250,6 +946,18 @@ pub struct AuthorConfig {
     #[serde(default)]
     pub github_logins: Vec<String>,
 
+    #[serde(default)]
+    pub gitlab_usernames: Vec<String>,
+
+    /// Nicknames or account ids on Bitbucket Cloud.
+    #[serde(default)]
+    pub bitbucket_usernames: Vec<String>,
+
+    /// Svn or Perforce usernames from history converted by `git-svn` or
+    /// `git-p4` without an authors mapping.
+    #[serde(default)]
+    pub imported_usernames: Vec<String>,
+
     #[serde(default)]
     pub tags: Vec<String>,
 
@@ -258,20 +966,87 @@ pub struct AuthorConfig {
 
     #[serde(default)]
     pub ignore: bool,
+
+    /// Tags and teams the author had in the past, or will have in the
+    /// future.
+    #[serde(default)]
+    pub history: Vec<AuthorPeriod>,
+}
+
+/// The raw identities of an author, as they appear in commits and on forges.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct AuthorIdentities {
+    pub identities: Vec<Identity>,
+    pub github_logins: Vec<String>,
+    pub gitlab_usernames: Vec<String>,
+    pub bitbucket_usernames: Vec<String>,
+    pub imported_usernames: Vec<String>,
+}
+
+/// A team, for metrics rolled up per team rather than per person.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct TeamConfig {
+    /// Keys of `authors`, ids of authors without an entry, or Github logins.
+    #[serde(default)]
+    pub members: Vec<String>,
+
+    /// Team this team rolls up into, e.g. a department. Members of the team
+    /// are counted as members of its parent as well.
+    #[serde(default)]
+    pub parent: Option<String>,
+}
+
+/// Overrides the tags and teams of an author between two dates.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuthorPeriod {
+    #[serde(default, flatten)]
+    pub validity: Validity,
+
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+
+    #[serde(default)]
+    pub teams: Option<Vec<String>>,
 }
 
 pub struct PersonConfig<'a> {
     key: &'a str,
     config: &'a AuthorConfig,
+    period: Option<&'a AuthorPeriod>,
 }
 
 impl<'a> PersonConfig<'a> {
     pub fn new(key: &'a str, config: &'a AuthorConfig) -> PersonConfig<'a> {
-        PersonConfig { key, config }
+        PersonConfig {
+            key,
+            config,
+            period: None,
+        }
+        .at(&Utc::now())
+    }
+
+    /// Resolves the tags and teams of the author as of `at`. The last period
+    /// of the history containing `at` wins.
+    pub fn at(mut self, at: &DateTime<Utc>) -> PersonConfig<'a> {
+        self.period = self
+            .config
+            .history
+            .iter()
+            .rev()
+            .find(|period| period.validity.contains(at));
+        self
     }
 
     pub fn tags(&self) -> &'a Vec<String> {
-        &self.config.tags
+        self.period
+            .and_then(|period| period.tags.as_ref())
+            .unwrap_or(&self.config.tags)
+    }
+
+    pub fn teams(&self) -> &'a Vec<String> {
+        self.period
+            .and_then(|period| period.teams.as_ref())
+            .unwrap_or(&self.config.teams)
     }
 
     pub fn ignore(&self) -> bool {
@@ -285,19 +1060,37 @@ impl<'a> PersonConfig<'a> {
             name: id.name,
             email: id.email,
             github_login: self.config.github_logins.first().map(|s| s.to_owned()),
-            teams: self.config.teams.clone(),
+            teams: self.teams().clone(),
         }
     }
 }
 
+/// Replaces `value` with `other` if it is set.
+fn merge_option<T>(value: &mut Option<T>, other: Option<T>) {
+    if other.is_some() {
+        *value = other;
+    }
+}
+
+/// Replaces `value` with `other` if it differs from `default`.
+fn merge_value<T: PartialEq>(value: &mut T, other: T, default: T) {
+    if other != default {
+        *value = other;
+    }
+}
+
 impl Default for AuthorConfig {
     fn default() -> AuthorConfig {
         AuthorConfig {
             aliases: vec![],
             github_logins: vec![],
+            gitlab_usernames: vec![],
+            bitbucket_usernames: vec![],
+            imported_usernames: vec![],
             tags: vec![],
             teams: vec![],
             ignore: false,
+            history: vec![],
         }
     }
 }
@@ -305,6 +1098,7 @@ impl Default for AuthorConfig {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashSet;
 
     #[test]
     fn test_deserialization() {
@@ -321,6 +1115,57 @@ mod tests {
         assert!(config.config_for_file("rusty.rs").is_none());
     }
 
+    #[test]
+    fn test_churn_cutoff_for_file() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+churn_cutoff: 30
+churn_cutoffs:
+  "src/**/*": 90
+  "src/generated/**/*": 7
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.churn_cutoff_for_file("src/lib.rs"), Some(90));
+        assert_eq!(
+            config.churn_cutoff_for_file("src/generated/schema.rs"),
+            Some(7)
+        );
+        assert_eq!(config.churn_cutoff_for_file("docs/index.md"), None);
+    }
+
+    #[test]
+    fn test_base_classification() {
+        let mut config: Config = serde_yaml::from_str(
+            r#"
+files:
+  "src/**/*":
+    weight: 0.5
+"#,
+        )
+        .unwrap();
+        config.maybe_apply_base();
+        let tags = |path| {
+            let mut tags: Vec<_> = config
+                .config_for_file(path)
+                .map(|c| c.tags().into_iter().map(|s| s.to_owned()).collect())
+                .unwrap_or_default();
+            tags.sort();
+            tags
+        };
+        assert_eq!(tags("pkg/server/server_test.go"), vec!["test"]);
+        assert_eq!(tags("README.md"), vec!["docs"]);
+        assert_eq!(tags("docs/guide/intro.html"), vec!["docs"]);
+        assert_eq!(tags("api/user.pb.go"), vec!["generated"]);
+        assert_eq!(tags("vendor/github.com/foo/bar.go"), vec!["vendored"]);
+        assert_eq!(tags("src/main.rs"), Vec::<String>::new());
+        // the globs of the config win over those of the base
+        let file_config = config
+            .config_for_file("src/components/Button.test.js")
+            .unwrap();
+        assert_eq!(file_config.weight(), 0.5);
+    }
+
     #[test]
     fn test_config_for_identity() {
         let config = Config::from_path(Path::new("fixtures/configs/simple.yml")).unwrap();
@@ -338,6 +1183,47 @@ mod tests {
             .is_none());
     }
 
+    #[test]
+    fn test_mailmap() {
+        let mut config = Config::from_path(Path::new("fixtures/configs/simple.yml")).unwrap();
+        config.mailmap = Mailmap::parse(
+            "Gordon Hempton <ghempton@gmail.com> <gordon@laptop.local>\n\
+             Jane Doe <jane@example.com> <jdoe@old.example.com>",
+        );
+        let laptop = Identity::parse("gordon <gordon@laptop.local>");
+        assert_eq!(
+            config.person_for_identity(&laptop).id,
+            "Gordon Hempton <ghempton@gmail.com>"
+        );
+        assert_eq!(
+            config
+                .person_for_identity(&Identity::parse("jdoe <jdoe@old.example.com>"))
+                .id,
+            "Jane Doe <jane@example.com>"
+        );
+
+        let identities = config.identities_for("Gordon Hempton <ghempton@gmail.com>");
+        assert_eq!(
+            identities.identities[..2],
+            [
+                Identity::parse("<gordon@laptop.local>"),
+                Identity::parse("Gordon Hempton <ghempton@gmail.com>")
+            ]
+        );
+        assert!(identities
+            .identities
+            .contains(&Identity::parse("Gordon Hempton <gordon@outreach.io>")));
+        assert_eq!(
+            config
+                .identities_for("Jane Doe <jane@example.com>")
+                .identities,
+            vec![
+                Identity::parse("<jdoe@old.example.com>"),
+                Identity::parse("Jane Doe <jane@example.com>")
+            ]
+        );
+    }
+
     #[test]
     fn test_merge() {
         let mut config = Config::default();
@@ -348,10 +1234,20 @@ mod tests {
                 weight: 1.0,
                 ignore: false,
                 tags: vec!["ruby".to_string()],
+                language: None,
+                validity: Validity::default(),
             },
         );
 
         let mut config2 = Config::default();
+        config2.tenant = Some("acme".to_owned());
+        config2.sinks.insert(
+            "elasticsearch".to_owned(),
+            SinkConfig {
+                batch_size: 500,
+                ..SinkConfig::default()
+            },
+        );
 
         config2.files.insert(
             "**/*.rs".to_string(),
@@ -359,12 +1255,182 @@ mod tests {
                 weight: 1.0,
                 ignore: false,
                 tags: vec!["rust".to_string()],
+                language: None,
+                validity: Validity::default(),
             },
         );
 
         config.merge(config2);
 
         assert!(config.files.keys().len() == 2);
+        assert_eq!(config.tenant, Some("acme".to_owned()));
+        assert_eq!(config.sinks["elasticsearch"].batch_size, 500);
+
+        let mut config3 = Config::default();
+        config3.tenant = Some("globex".to_owned());
+        config3.churn_cutoff = 30;
+        config3.rollup_period = Some(Period::Month);
+        config.merge(config3);
+        assert_eq!(config.tenant, Some("globex".to_owned()));
+        assert_eq!(config.churn_cutoff, 30);
+        assert_eq!(config.rollup_period(), Period::Month);
+        assert_eq!(config.sinks["elasticsearch"].batch_size, 500);
+
+        // unset fields and fields left at their defaults do not override
+        config.merge(Config::default());
+        assert_eq!(config.tenant, Some("globex".to_owned()));
+        assert_eq!(config.churn_cutoff, 30);
+        assert_eq!(config.rollup_period(), Period::Month);
+    }
+
+    #[test]
+    fn test_overlay_from_path() -> Result<()> {
+        let dir = tempdir::TempDir::new("overlay")?;
+        let path = dir.path().join("candidate.yml");
+        std::fs::write(&path, "churn_cutoff: 30\n")?;
+        let mut config = Config::base();
+        config.files.get_mut("**/Cargo.lock").unwrap().weight = 0.5;
+        config.merge(Config::overlay_from_path(&path)?);
+        assert_eq!(config.churn_cutoff, 30);
+        assert_eq!(config.files["**/Cargo.lock"].weight, 0.5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_as_of() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+files:
+  "vendor/**/*":
+    ignore: true
+    until: "2018-12-31"
+authors:
+  "Jane <jane@example.com>":
+    teams:
+      - "apollo"
+    history:
+      - from: "2017-01-01"
+        until: "2018-06-30"
+        teams:
+          - "zeus"
+"#,
+        )
+        .unwrap();
+        let before = Utc.ymd(2018, 6, 30).and_hms(23, 0, 0);
+        let after = Utc.ymd(2019, 1, 1).and_hms(0, 0, 0);
+        assert!(config
+            .config_for_file_at("vendor/lib.js", &before)
+            .is_some());
+        assert!(config.config_for_file_at("vendor/lib.js", &after).is_none());
+
+        let jane = Identity::parse("Jane <jane@example.com>");
+        assert_eq!(
+            config.person_for_identity_at(&jane, &before).teams,
+            vec!["zeus".to_string()]
+        );
+        assert_eq!(
+            config.person_for_identity_at(&jane, &after).teams,
+            vec!["apollo".to_string()]
+        );
+        assert_eq!(
+            config.person_for_identity(&jane).teams,
+            vec!["apollo".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_teams() {
+        let config: Config = serde_yaml::from_str(
+            r#"
+authors:
+  "Jane <jane@example.com>":
+    github_logins:
+      - "jane"
+    teams:
+      - "apollo"
+teams:
+  apollo:
+    parent: "platform"
+  platform:
+    parent: "engineering"
+  engineering:
+    parent: "platform"
+  payments:
+    members:
+      - "jane"
+      - "Bob <bob@example.com>"
+"#,
+        )
+        .unwrap();
+        let jane = Identity::parse("Jane <jane@example.com>");
+        assert_eq!(
+            config.person_for_identity(&jane).teams,
+            vec!["apollo", "payments", "platform", "engineering"]
+        );
+        assert_eq!(
+            config.person_for_github_login("jane").teams,
+            vec!["apollo", "payments", "platform", "engineering"]
+        );
+        let bob = Identity::parse("Bob <bob@example.com>");
+        assert_eq!(config.person_for_identity(&bob).teams, vec!["payments"]);
+        assert!(config.person_for_github_login("bob").teams.is_empty());
+    }
+
+    #[test]
+    fn test_canonical_repo_name() {
+        let mut config = Config::default();
+        config
+            .repo_aliases
+            .insert("old/foo".to_string(), "older/foo".to_string());
+        config
+            .repo_aliases
+            .insert("older/foo".to_string(), "new/foo".to_string());
+        config
+            .repo_aliases
+            .insert("a/loop".to_string(), "b/loop".to_string());
+        config
+            .repo_aliases
+            .insert("b/loop".to_string(), "a/loop".to_string());
+        assert_eq!(config.canonical_repo_name("old/foo"), "new/foo");
+        assert_eq!(config.canonical_repo_name("new/foo"), "new/foo");
+        assert_eq!(config.canonical_repo_name("other/bar"), "other/bar");
+        // cycles stop at either of their names
+        assert!(["a/loop", "b/loop"].contains(&config.canonical_repo_name("a/loop")));
+    }
+
+    #[test]
+    fn test_language_weight() {
+        let mut config = Config::default();
+        config.files.insert(
+            "**/*.go".to_string(),
+            GlobConfig {
+                weight: 1.0,
+                ignore: false,
+                tags: vec![],
+                language: Some("go".to_string()),
+                validity: Validity::default(),
+            },
+        );
+        config.files.insert(
+            "**/*.pb.go".to_string(),
+            GlobConfig {
+                weight: 1.0,
+                ignore: false,
+                tags: vec![],
+                language: Some("go-protobuf".to_string()),
+                validity: Validity::default(),
+            },
+        );
+        config
+            .language_weights
+            .insert("go-protobuf".to_string(), 0.1);
+
+        let file_config = config.config_for_file("api/service.pb.go").unwrap();
+        assert_eq!(file_config.language(), Some("go-protobuf"));
+        assert_eq!(config.language_weight("go-protobuf"), 0.1);
+        let file_config = config.config_for_file("api/service.go").unwrap();
+        assert_eq!(file_config.language(), Some("go"));
+        assert_eq!(config.language_weight("go"), 1.0);
     }
 
     #[test]
@@ -373,6 +1439,9 @@ mod tests {
         assert!(config.config_for_file("schema.rb").is_some());
         assert!(config.config_for_file("package.json").is_some());
         assert!(config.config_for_file("asdasd.asdasdasd").is_none());
+        // languages are left to detection, which names them like linguist
+        assert_eq!(config.config_for_file("app.js").unwrap().language(), None);
+        assert_eq!(config.config_for_file("app.rb").unwrap().language(), None);
     }
 
     #[test]
@@ -385,6 +1454,8 @@ mod tests {
                 weight: 1.0,
                 ignore: false,
                 tags: vec!["ruby".to_string()],
+                language: None,
+                validity: Validity::default(),
             },
         );
 
@@ -394,6 +1465,8 @@ mod tests {
                 weight: 0.5,
                 ignore: false,
                 tags: vec!["rspec".to_string()],
+                language: None,
+                validity: Validity::default(),
             },
         );
 
@@ -403,6 +1476,8 @@ mod tests {
                 weight: 1.0,
                 ignore: true,
                 tags: vec![],
+                language: None,
+                validity: Validity::default(),
             },
         );
 
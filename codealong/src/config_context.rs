@@ -1,28 +1,49 @@
-use crate::config::{FileConfig, PersonConfig};
+use crate::config::{Config, FileConfig, PersonConfig};
 
 pub struct ConfigContext {
     tags: Vec<String>,
     weight: f64,
+    churn_weight: f64,
 }
 
 /// During analysis, this struct stores the current applicable config.
 impl ConfigContext {
+    /// `language` is that of the file, as declared by its globs or detected.
     pub fn new(
+        config: &Config,
         file_config: Option<&FileConfig>,
         author_config: Option<&PersonConfig>,
+        language: Option<&str>,
     ) -> ConfigContext {
         let weight = file_config.map(|c| c.weight()).unwrap_or(1.0);
+        let churn_weight = language
+            .map(|language| config.language_weight(language))
+            .unwrap_or(1.0);
         let mut tags: Vec<String> = vec![];
         file_config.map(|c| tags.extend(c.tags().iter().map(|s| s.to_string())));
         author_config.map(|c| tags.extend(c.tags().iter().map(|s| s.to_string())));
-        ConfigContext { tags, weight }
+        ConfigContext {
+            tags,
+            weight,
+            churn_weight,
+        }
     }
 
     pub fn tags(&self) -> &Vec<String> {
         &self.tags
     }
 
+    /// Scales the weight of the file, e.g. to count the lines of large files
+    /// for less.
+    pub fn scale_weight(&mut self, factor: f64) {
+        self.weight *= factor;
+    }
+
     pub fn weight(&self) -> f64 {
         self.weight
     }
+
+    pub fn churn_weight(&self) -> f64 {
+        self.churn_weight
+    }
 }
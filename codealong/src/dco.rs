@@ -0,0 +1,156 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
+
+use chrono::prelude::*;
+use chrono::DateTime;
+
+use crate::analyzed_commit::AnalyzedCommit;
+use crate::compliance::trailers;
+use crate::identity::Identity;
+use codealong_model::{Event, Person};
+
+const SIGNED_OFF_BY: &str = "Signed-off-by";
+
+/// Returns true if the message has a `Signed-off-by` trailer for the author,
+/// as required by the Developer Certificate of Origin. Identities are matched
+/// by email when present, and by name otherwise.
+pub fn is_signed_off(message: &str, author: &Identity) -> bool {
+    trailers(message)
+        .iter()
+        .filter(|(token, _)| token.eq_ignore_ascii_case(SIGNED_OFF_BY))
+        .map(|(_, value)| Identity::parse(value))
+        .any(|signer| match (&signer.email, &author.email) {
+            (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+            _ => signer.name.is_some() && signer.name == author.name,
+        })
+}
+
+/// DCO compliance of a single contributor to a repo.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DcoReport {
+    pub id: String,
+    pub repo: String,
+    pub contributor: Person,
+    pub commits: u64,
+    pub signed_off: u64,
+    pub compliant: bool,
+    /// Commits which are missing a sign-off.
+    pub missing: Vec<String>,
+    pub last_commit_at: DateTime<Utc>,
+}
+
+impl Event for DcoReport {
+    fn timestamp(&self) -> &DateTime<Utc> {
+        &self.last_commit_at
+    }
+
+    fn event_type(&self) -> &str {
+        "dco_report"
+    }
+
+    fn id(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.id)
+    }
+
+    fn tags(&self) -> HashSet<String> {
+        HashSet::new()
+    }
+}
+
+/// Accumulates the DCO compliance of each contributor to a repo. Merge
+/// commits are exempt.
+pub struct DcoReports {
+    repo: String,
+    reports: BTreeMap<String, DcoReport>,
+}
+
+impl DcoReports {
+    pub fn new(repo: &str) -> DcoReports {
+        DcoReports {
+            repo: repo.to_owned(),
+            reports: BTreeMap::new(),
+        }
+    }
+
+    pub fn add_commit(&mut self, commit: &AnalyzedCommit) {
+        if commit.parents.len() > 1 {
+            return;
+        }
+        if let Some(ref author) = commit.normalized_author {
+            self.add(
+                &commit.id,
+                author,
+                commit.dco_signed_off,
+                &commit.authored_at,
+            );
+        }
+    }
+
+    pub fn add(
+        &mut self,
+        commit_id: &str,
+        contributor: &Person,
+        signed_off: bool,
+        committed_at: &DateTime<Utc>,
+    ) {
+        let repo = &self.repo;
+        let report = self
+            .reports
+            .entry(contributor.id.clone())
+            .or_insert_with(|| DcoReport {
+                id: format!("{}-{}-dco", repo, contributor.id),
+                repo: repo.to_owned(),
+                contributor: contributor.clone(),
+                commits: 0,
+                signed_off: 0,
+                compliant: true,
+                missing: vec![],
+                last_commit_at: *committed_at,
+            });
+        report.commits += 1;
+        if signed_off {
+            report.signed_off += 1;
+        } else {
+            report.compliant = false;
+            report.missing.push(commit_id.to_owned());
+        }
+        report.last_commit_at = std::cmp::max(report.last_commit_at, *committed_at);
+    }
+
+    pub fn finish(self) -> Vec<DcoReport> {
+        self.reports.into_values().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_signed_off() {
+        let author = Identity::parse("Jane Doe <jane@example.com>");
+        assert!(is_signed_off(
+            "Fix\n\nSigned-off-by: Jane D <JANE@example.com>",
+            &author
+        ));
+        assert!(!is_signed_off(
+            "Fix\n\nSigned-off-by: Bob <bob@example.com>",
+            &author
+        ));
+        assert!(!is_signed_off("Fix", &author));
+    }
+
+    #[test]
+    fn test_reports() {
+        let jane = Identity::parse("Jane <jane@example.com>").to_person();
+        let now = Utc.ymd(2019, 12, 16).and_hms(9, 0, 0);
+        let mut reports = DcoReports::new("org/api");
+        reports.add("a", &jane, true, &now);
+        reports.add("b", &jane, false, &now);
+        let reports = reports.finish();
+        assert_eq!(reports.len(), 1);
+        assert!(!reports[0].compliant);
+        assert_eq!(reports[0].signed_off, 1);
+        assert_eq!(reports[0].missing, vec!["b"]);
+    }
+}
@@ -0,0 +1,71 @@
+use git2::Repository;
+
+/// Detects the default branch of `repo`, returning its full refname. In
+/// order of preference this is the branch `refs/remotes/<remote>/HEAD`
+/// points to, i.e. the default branch of the remote when it was cloned, the
+/// branch checked out, or else a `main` or `master` branch.
+pub fn default_branch(repo: &Repository, remote: &str) -> Option<String> {
+    let symbolic_target = |name: &str| {
+        repo.find_reference(name)
+            .ok()
+            .and_then(|reference| reference.symbolic_target().map(str::to_owned))
+            .filter(|target| repo.refname_to_id(target).is_ok())
+    };
+    symbolic_target(&format!("refs/remotes/{}/HEAD", remote))
+        .or_else(|| symbolic_target("HEAD"))
+        .or_else(|| {
+            ["main", "master"]
+                .iter()
+                .flat_map(|branch| {
+                    vec![
+                        format!("refs/remotes/{}/{}", remote, branch),
+                        format!("refs/heads/{}", branch),
+                    ]
+                })
+                .find(|name| repo.refname_to_id(name).is_ok())
+        })
+}
+
+/// The refname of the branch named `branch`, e.g. as reported by the host of
+/// the repo, preferring the remote tracking branch.
+pub fn branch_refname(repo: &Repository, remote: &str, branch: &str) -> Option<String> {
+    vec![
+        format!("refs/remotes/{}/{}", remote, branch),
+        format!("refs/heads/{}", branch),
+    ]
+    .into_iter()
+    .find(|name| repo.refname_to_id(name).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Signature;
+
+    #[test]
+    fn test_default_branch() -> crate::error::Result<()> {
+        let repo = Repository::open("./fixtures/repos/simple")?;
+        assert_eq!(
+            default_branch(&repo, "origin"),
+            Some("refs/remotes/origin/master".to_owned())
+        );
+
+        let tmp_dir = tempdir::TempDir::new("default_branch")?;
+        let repo = Repository::init(tmp_dir.path())?;
+        assert_eq!(default_branch(&repo, "origin"), None);
+        repo.set_head("refs/heads/trunk")?;
+        let sig = Signature::now("Test", "test@example.com")?;
+        let tree = repo.find_tree(repo.treebuilder(None)?.write()?)?;
+        repo.commit(Some("HEAD"), &sig, &sig, "first", &tree, &[])?;
+        assert_eq!(
+            default_branch(&repo, "origin"),
+            Some("refs/heads/trunk".to_owned())
+        );
+        assert_eq!(
+            branch_refname(&repo, "origin", "trunk"),
+            Some("refs/heads/trunk".to_owned())
+        );
+        assert_eq!(branch_refname(&repo, "origin", "main"), None);
+        Ok(())
+    }
+}
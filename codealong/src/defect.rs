@@ -0,0 +1,271 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::PathBuf;
+
+use chrono::prelude::*;
+use chrono::DateTime;
+use git2::{Commit, Delta, DiffOptions, Oid, Repository};
+use regex::Regex;
+
+use crate::config::Config;
+use crate::error::*;
+use crate::git_blame::GitBlame;
+use crate::identity::Identity;
+use crate::partial_clone::fetch_missing_blobs_for;
+use crate::repo_info::PartialRepoInfo;
+use crate::text::commit_message;
+use crate::utils::convert_time;
+use codealong_model::{Event, Person};
+
+/// How a suspected bug-introducing commit was linked to the commit which
+/// fixed it. Both methods are heuristics and will produce false positives.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DefectLinkMethod {
+    /// The fix explicitly referenced the commit, e.g. `Fixes: 1a2b3c4d`.
+    FixesTrailer,
+    /// The commit last touched lines which the fix removed or changed (SZZ).
+    BlameDeletedLines,
+}
+
+/// A commit suspected of introducing the defect fixed by another commit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DefectOrigin {
+    pub commit: String,
+    pub method: DefectLinkMethod,
+}
+
+/// Returns true if the commit message looks like it describes a bug fix.
+pub fn is_bug_fix(message: &str) -> bool {
+    lazy_static! {
+        static ref BUG_FIX_REGEX: Regex =
+            Regex::new(r"(?i)\b(fix(es|ed|ing)?|bug(s|fix)?|hotfix|defect|regression)\b").unwrap();
+    }
+    BUG_FIX_REGEX.is_match(message)
+}
+
+/// Extracts commit ids referenced by `Fixes: <sha>` trailers.
+pub fn fixes_references(message: &str) -> Vec<String> {
+    lazy_static! {
+        static ref FIXES_TRAILER_REGEX: Regex =
+            Regex::new(r"(?im)^fixes:\s*([0-9a-f]{7,40})\b").unwrap();
+    }
+    FIXES_TRAILER_REGEX
+        .captures_iter(message)
+        .map(|captures| captures[1].to_owned())
+        .collect()
+}
+
+/// Links a bug-fix commit back to the commits which most likely introduced
+/// the bug using a variant of the SZZ algorithm: every line removed or
+/// changed by the fix is blamed in the parent and the commits which last
+/// touched those lines are considered suspects.
+pub struct DefectLinker<'a> {
+    repo: &'a Repository,
+    commit: &'a Commit<'a>,
+    config: &'a Config,
+}
+
+impl<'a> DefectLinker<'a> {
+    pub fn new(
+        repo: &'a Repository,
+        commit: &'a Commit<'a>,
+        config: &'a Config,
+    ) -> DefectLinker<'a> {
+        DefectLinker {
+            repo,
+            commit,
+            config,
+        }
+    }
+
+    pub fn origins(&self) -> Result<Vec<DefectOrigin>> {
+        let mut origins: Vec<DefectOrigin> = vec![];
+        let mut seen: HashSet<Oid> = HashSet::new();
+        for reference in fixes_references(&commit_message(self.commit)) {
+            if let Ok(commit) = self
+                .repo
+                .revparse_single(&reference)
+                .and_then(|o| o.peel_to_commit())
+            {
+                if seen.insert(commit.id()) {
+                    origins.push(DefectOrigin {
+                        commit: commit.id().to_string(),
+                        method: DefectLinkMethod::FixesTrailer,
+                    });
+                }
+            }
+        }
+
+        // SZZ is only well defined against a single parent
+        if self.commit.parent_count() == 1 {
+            for oid in self.blame_deleted_lines(&self.commit.parent(0)?)? {
+                if seen.insert(oid) {
+                    origins.push(DefectOrigin {
+                        commit: oid.to_string(),
+                        method: DefectLinkMethod::BlameDeletedLines,
+                    });
+                }
+            }
+        }
+        Ok(origins)
+    }
+
+    fn blame_deleted_lines(&self, parent: &Commit) -> Result<BTreeSet<Oid>> {
+        let mut diff_opts = DiffOptions::new();
+        diff_opts.ignore_whitespace(true);
+        let old_tree = parent.tree()?;
+        let new_tree = self.commit.tree()?;
+        let diff =
+            self.repo
+                .diff_tree_to_tree(Some(&old_tree), Some(&new_tree), Some(&mut diff_opts))?;
+        let path_filter = self.config.path_filter();
+        fetch_missing_blobs_for(
+            self.repo,
+            diff.deltas()
+                .filter(|delta| path_filter.includes_delta(delta)),
+            Some(&old_tree),
+            Some(&new_tree),
+        )?;
+
+        let mut deleted_lines: BTreeMap<PathBuf, Vec<usize>> = BTreeMap::new();
+        diff.foreach(
+            &mut |_delta, _progress| true,
+            None,
+            None,
+            Some(&mut |delta, _hunk, line| {
+                if delta.status() != Delta::Modified || line.origin() != '-' {
+                    return true;
+                }
+                if String::from_utf8_lossy(line.content()).trim().is_empty() {
+                    return true;
+                }
+                if let (Some(path), Some(lineno)) = (delta.old_file().path(), line.old_lineno()) {
+                    if !self.is_ignored(path.to_str()) {
+                        deleted_lines
+                            .entry(path.to_owned())
+                            .or_default()
+                            .push(lineno as usize);
+                    }
+                }
+                true
+            }),
+        )?;
+
+        let mut suspects = BTreeSet::new();
+        for (path, linenos) in deleted_lines {
+            let blame = GitBlame::full(self.repo, &parent.id(), &path, self.config)?;
+            for lineno in linenos {
+                if let Some(oid) = blame.get_line(lineno)? {
+                    suspects.insert(oid);
+                }
+            }
+        }
+        Ok(suspects)
+    }
+
+    fn is_ignored(&self, path: Option<&str>) -> bool {
+        let at = convert_time(&self.commit.author().when());
+        if let Some(path) = path {
+            // excluded by only_paths or exclude_paths
+            if !self.config.path_filter().includes(path) {
+                return true;
+            }
+        }
+        path.and_then(|path| self.config.config_for_file_at(path, &at))
+            .map(|c| c.ignore())
+            .unwrap_or(false)
+    }
+}
+
+/// Annotation event emitted for each commit suspected of introducing a
+/// defect. Tagged as `heuristic` since the linkage is approximate.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IntroducedDefect {
+    pub introducing_commit: String,
+    pub fixing_commit: String,
+    pub method: DefectLinkMethod,
+    pub introduced_at: DateTime<Utc>,
+    pub fixed_at: DateTime<Utc>,
+    pub author: Identity,
+    pub normalized_author: Person,
+    pub repo: Option<PartialRepoInfo>,
+}
+
+impl IntroducedDefect {
+    pub fn new(
+        introducing_commit: &Commit,
+        fixing_commit: &Commit,
+        method: DefectLinkMethod,
+        config: &Config,
+        repo: Option<PartialRepoInfo>,
+    ) -> IntroducedDefect {
+        let author = Identity::from(introducing_commit.author());
+        let introduced_at = convert_time(&introducing_commit.author().when());
+        IntroducedDefect {
+            introducing_commit: introducing_commit.id().to_string(),
+            fixing_commit: fixing_commit.id().to_string(),
+            method,
+            introduced_at,
+            fixed_at: convert_time(&fixing_commit.author().when()),
+            normalized_author: config.person_for_identity_at(&author, &introduced_at),
+            author,
+            repo,
+        }
+    }
+}
+
+impl Event for IntroducedDefect {
+    fn timestamp(&self) -> &DateTime<Utc> {
+        &self.introduced_at
+    }
+
+    fn event_type(&self) -> &str {
+        "introduced_defect"
+    }
+
+    fn id(&self) -> Cow<'_, str> {
+        Cow::Owned(format!(
+            "{}-{}",
+            self.introducing_commit, self.fixing_commit
+        ))
+    }
+
+    fn tags(&self) -> HashSet<String> {
+        let mut tags = HashSet::new();
+        tags.insert("heuristic".to_owned());
+        tags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_bug_fix() {
+        assert!(is_bug_fix("Fix crash when config is missing"));
+        assert!(is_bug_fix("hotfix: null pointer"));
+        assert!(is_bug_fix("Resolve regression in parser"));
+        assert!(!is_bug_fix("Add prefix option"));
+        assert!(!is_bug_fix("Update debug output"));
+    }
+
+    #[test]
+    fn test_fixes_references() {
+        let message = "Handle empty input\n\nFixes: 86d242301830 (\"Initial commit\")\nFixes #12\n";
+        assert_eq!(fixes_references(message), vec!["86d242301830".to_owned()]);
+    }
+
+    #[test]
+    fn test_origins_ignore_whitespace_changes() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/simple")?;
+        // only changes the trailing newline of a line from the initial commit
+        let commit =
+            repo.find_commit(Oid::from_str("e3f67bc3313bc6a913da966afa0bb8a1bf043175")?)?;
+        let config = Config::default();
+        let origins = DefectLinker::new(&repo, &commit, &config).origins()?;
+        assert_eq!(origins, vec![]);
+        Ok(())
+    }
+}
@@ -0,0 +1,418 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt::Write;
+
+use chrono::prelude::*;
+use chrono::DateTime;
+use git2::{
+    Commit, DiffOptions, ObjectType, Patch, Repository, Sort, TreeWalkMode, TreeWalkResult,
+};
+
+use crate::error::Result;
+use crate::graph::escape_dot;
+use crate::imports::{parse_imports, Import};
+use crate::utils::convert_time;
+use codealong_model::Event;
+
+/// Files larger than this are assumed to be generated and are not parsed.
+const MAX_SOURCE_SIZE: usize = 512 * 1024;
+
+const ROOT: &str = ".";
+
+/// Dependencies between the directories of a repo at a single commit, found
+/// by parsing the import statements of its source files, along with the
+/// recent churn of each directory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DependencyGraph {
+    pub commit: String,
+    pub timestamp: DateTime<Utc>,
+    directories: BTreeSet<String>,
+    edges: BTreeMap<String, BTreeMap<String, u64>>,
+    churn: BTreeMap<String, u64>,
+}
+
+/// Dependencies and stability of a single directory, indexed per snapshot.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DirectoryDependencies {
+    pub id: String,
+    pub repo: String,
+    pub commit: String,
+    pub timestamp: DateTime<Utc>,
+    pub directory: String,
+    /// Directories this directory imports from, with the number of imports.
+    pub depends_on: BTreeMap<String, u64>,
+    /// Number of directories depending on this one.
+    pub fan_in: u64,
+    /// Number of directories this one depends on.
+    pub fan_out: u64,
+    /// `fan_out / (fan_in + fan_out)`: 0 for directories which only others
+    /// depend on, 1 for those which only depend on others.
+    pub instability: f64,
+    /// Lines added and deleted within the churn window.
+    pub churn: u64,
+    /// Directories many others depend on that also change a lot.
+    pub hotspot: bool,
+}
+
+impl DependencyGraph {
+    /// Builds the graph for the tree of `commit`, counting churn of commits
+    /// authored since `churn_since`.
+    pub fn build(
+        repo: &Repository,
+        commit: &Commit,
+        churn_since: &DateTime<Utc>,
+    ) -> Result<DependencyGraph> {
+        let mut files = vec![];
+        let mut directories = BTreeSet::new();
+        directories.insert(ROOT.to_owned());
+        commit.tree()?.walk(TreeWalkMode::PreOrder, |root, entry| {
+            let path = format!("{}{}", root, entry.name().unwrap_or(""));
+            match entry.kind() {
+                Some(ObjectType::Tree) => {
+                    directories.insert(path);
+                }
+                Some(ObjectType::Blob) => files.push((path, entry.id())),
+                _ => {}
+            }
+            TreeWalkResult::Ok
+        })?;
+
+        let mut graph = DependencyGraph {
+            commit: commit.id().to_string(),
+            timestamp: convert_time(&commit.author().when()),
+            directories,
+            edges: BTreeMap::new(),
+            churn: BTreeMap::new(),
+        };
+        for (path, id) in files {
+            let blob = match repo.find_blob(id) {
+                Ok(blob) => blob,
+                Err(_) => continue,
+            };
+            if blob.content().len() > MAX_SOURCE_SIZE || blob.is_binary() {
+                continue;
+            }
+            let content = String::from_utf8_lossy(blob.content());
+            let source = directory_of(&path);
+            for import in parse_imports(&path, &content) {
+                if let Some(target) = graph.resolve(&source, &import) {
+                    if target != source {
+                        *graph
+                            .edges
+                            .entry(source.clone())
+                            .or_default()
+                            .entry(target)
+                            .or_insert(0) += 1;
+                    }
+                }
+            }
+        }
+        graph.add_churn(repo, commit, churn_since)?;
+        Ok(graph)
+    }
+
+    /// The directory an import refers to, if it is within the repo.
+    fn resolve(&self, source: &str, import: &Import) -> Option<String> {
+        match import {
+            Import::Relative(path) => {
+                let joined = normalize(&format!("{}/{}", source, path))?;
+                if self.directories.contains(&joined) {
+                    Some(joined)
+                } else {
+                    // a file, possibly without its extension
+                    Some(directory_of(&joined)).filter(|dir| self.directories.contains(dir))
+                }
+            }
+            Import::Crate(segments) => {
+                let root = crate_root(source);
+                (1..=segments.len())
+                    .rev()
+                    .map(|len| normalize(&format!("{}/{}", root, segments[..len].join("/"))))
+                    .find(|dir| {
+                        dir.as_ref()
+                            .is_some_and(|dir| self.directories.contains(dir))
+                    })
+                    .unwrap_or_else(|| Some(root.to_owned()))
+            }
+            Import::Module(segments) => (1..=segments.len()).rev().find_map(|len| {
+                let candidate = segments[..len].join("/");
+                let suffix = format!("/{}", candidate);
+                self.directories
+                    .iter()
+                    .filter(|dir| **dir == candidate || dir.ends_with(&suffix))
+                    .min_by_key(|dir| dir.len())
+                    .cloned()
+            }),
+        }
+    }
+
+    fn add_churn(&mut self, repo: &Repository, head: &Commit, since: &DateTime<Utc>) -> Result<()> {
+        let mut revwalk = repo.revwalk()?;
+        revwalk.set_sorting(Sort::TIME);
+        revwalk.push(head.id())?;
+        for oid in revwalk {
+            let commit = repo.find_commit(oid?)?;
+            if convert_time(&commit.author().when()) < *since {
+                break;
+            }
+            if commit.parent_count() > 1 {
+                continue;
+            }
+            let parent_tree = commit.parent(0).ok().and_then(|p| p.tree().ok());
+            let diff = repo.diff_tree_to_tree(
+                parent_tree.as_ref(),
+                Some(&commit.tree()?),
+                Some(&mut DiffOptions::new()),
+            )?;
+            for idx in 0..diff.deltas().len() {
+                let patch = match Patch::from_diff(&diff, idx)? {
+                    Some(patch) => patch,
+                    None => continue,
+                };
+                let path = patch
+                    .delta()
+                    .new_file()
+                    .path()
+                    .or_else(|| patch.delta().old_file().path())
+                    .and_then(|path| path.to_str())
+                    .map(directory_of);
+                if let Some(directory) = path {
+                    let (_, additions, deletions) = patch.line_stats()?;
+                    *self.churn.entry(directory).or_insert(0) += (additions + deletions) as u64;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Per directory dependencies and stability. The top tenth of the
+    /// directories by fan-in times churn are marked as hotspots.
+    pub fn directories(&self, repo: &str) -> Vec<DirectoryDependencies> {
+        let mut fan_in: BTreeMap<&str, u64> = BTreeMap::new();
+        for targets in self.edges.values() {
+            for target in targets.keys() {
+                *fan_in.entry(target).or_insert(0) += 1;
+            }
+        }
+        let mut res: Vec<DirectoryDependencies> = self
+            .directories
+            .iter()
+            .map(|directory| {
+                let depends_on = self.edges.get(directory).cloned().unwrap_or_default();
+                let fan_in = fan_in.get(directory.as_str()).cloned().unwrap_or(0);
+                let fan_out = depends_on.len() as u64;
+                DirectoryDependencies {
+                    id: format!("{}-{}-{}", repo, self.commit, directory),
+                    repo: repo.to_owned(),
+                    commit: self.commit.clone(),
+                    timestamp: self.timestamp,
+                    directory: directory.clone(),
+                    depends_on,
+                    fan_in,
+                    fan_out,
+                    instability: if fan_in + fan_out == 0 {
+                        0.0
+                    } else {
+                        fan_out as f64 / (fan_in + fan_out) as f64
+                    },
+                    churn: self.churn.get(directory).cloned().unwrap_or(0),
+                    hotspot: false,
+                }
+            })
+            .collect();
+        let mut risks: Vec<u64> = res
+            .iter()
+            .map(|d| d.fan_in * d.churn)
+            .filter(|risk| *risk > 0)
+            .collect();
+        risks.sort_unstable_by(|a, b| b.cmp(a));
+        if let Some(threshold) = risks.get((risks.len().max(1) - 1) / 10) {
+            let threshold = *threshold;
+            for directory in &mut res {
+                directory.hotspot = directory.fan_in * directory.churn >= threshold;
+            }
+        }
+        res
+    }
+
+    pub fn to_dot(&self, repo: &str) -> String {
+        let mut out = String::new();
+        writeln!(out, "digraph \"{}\" {{", escape_dot(repo)).unwrap();
+        let directories = self.directories(repo);
+        let connected: HashSet<&str> = self
+            .edges
+            .iter()
+            .flat_map(|(source, targets)| {
+                std::iter::once(source.as_str()).chain(targets.keys().map(|t| t.as_str()))
+            })
+            .collect();
+        for directory in directories
+            .iter()
+            .filter(|d| connected.contains(d.directory.as_str()))
+        {
+            writeln!(
+                out,
+                "  \"{}\" [label=\"{}\\nchurn {}\"{}];",
+                escape_dot(&directory.directory),
+                escape_dot(&directory.directory),
+                directory.churn,
+                if directory.hotspot { ", color=red" } else { "" }
+            )
+            .unwrap();
+        }
+        for (source, targets) in &self.edges {
+            for (target, weight) in targets {
+                writeln!(
+                    out,
+                    "  \"{}\" -> \"{}\" [weight={}];",
+                    escape_dot(source),
+                    escape_dot(target),
+                    weight
+                )
+                .unwrap();
+            }
+        }
+        writeln!(out, "}}").unwrap();
+        out
+    }
+}
+
+impl Event for DirectoryDependencies {
+    fn timestamp(&self) -> &DateTime<Utc> {
+        &self.timestamp
+    }
+
+    fn event_type(&self) -> &str {
+        "directory_dependencies"
+    }
+
+    fn id(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.id)
+    }
+
+    fn tags(&self) -> HashSet<String> {
+        HashSet::new()
+    }
+}
+
+fn directory_of(path: &str) -> String {
+    match path.rfind('/') {
+        Some(idx) => path[..idx].to_owned(),
+        None => ROOT.to_owned(),
+    }
+}
+
+/// The nearest `src` directory containing `directory`, which is the root of
+/// `crate::` paths, or `directory` itself if there is none.
+fn crate_root(directory: &str) -> &str {
+    if directory == "src" || directory.ends_with("/src") {
+        return directory;
+    }
+    match directory.rfind("/src/") {
+        Some(idx) => &directory[..idx + 4],
+        None if directory.starts_with("src/") => "src",
+        None => directory,
+    }
+}
+
+/// Resolves `.` and `..` segments, or `None` if the path leaves the repo.
+fn normalize(path: &str) -> Option<String> {
+    let mut segments: Vec<&str> = vec![];
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop()?;
+            }
+            segment => segments.push(segment),
+        }
+    }
+    if segments.is_empty() {
+        Some(ROOT.to_owned())
+    } else {
+        Some(segments.join("/"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Signature;
+
+    #[test]
+    fn test_build() -> Result<()> {
+        let tmp_dir = tempdir::TempDir::new("dependency_graph")?;
+        let repo = Repository::init(tmp_dir.path())?;
+        let files = [
+            (
+                "web/app.js",
+                "import a from '../lib/a';\nimport b from '../lib/b';\n",
+            ),
+            ("web/admin.js", "import a from '../lib/a';\n"),
+            ("cli/main.js", "const a = require('../lib/a');\n"),
+            ("lib/a.js", "export default 1;\n"),
+            ("lib/b.js", "export default 2;\n"),
+        ];
+        let mut index = repo.index()?;
+        for (path, content) in files.iter() {
+            let path = tmp_dir.path().join(path);
+            std::fs::create_dir_all(path.parent().unwrap())?;
+            std::fs::write(&path, content)?;
+        }
+        index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
+        let tree = repo.find_tree(index.write_tree()?)?;
+        let sig = Signature::now("Test", "test@example.com")?;
+        let id = repo.commit(Some("HEAD"), &sig, &sig, "Add files", &tree, &[])?;
+        let commit = repo.find_commit(id)?;
+
+        let graph =
+            DependencyGraph::build(&repo, &commit, &(Utc::now() - chrono::Duration::days(1)))?;
+        let directories = graph.directories("test");
+        let lib = directories.iter().find(|d| d.directory == "lib").unwrap();
+        assert_eq!(lib.fan_in, 2);
+        assert_eq!(lib.fan_out, 0);
+        assert_eq!(lib.instability, 0.0);
+        assert_eq!(lib.churn, 2);
+        assert!(lib.hotspot);
+        let web = directories.iter().find(|d| d.directory == "web").unwrap();
+        assert_eq!(web.depends_on.get("lib"), Some(&3));
+        assert_eq!(web.instability, 1.0);
+        assert!(!web.hotspot);
+        assert!(graph
+            .to_dot("test")
+            .contains("\"web\" -> \"lib\" [weight=3];"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_dot_escapes_names() {
+        let mut edges = BTreeMap::new();
+        edges.insert(
+            "say \"hi\"".to_owned(),
+            vec![("back\\slash".to_owned(), 1)].into_iter().collect(),
+        );
+        let graph = DependencyGraph {
+            commit: "abc".to_owned(),
+            timestamp: Utc::now(),
+            directories: vec!["say \"hi\"".to_owned(), "back\\slash".to_owned()]
+                .into_iter()
+                .collect(),
+            edges,
+            churn: BTreeMap::new(),
+        };
+        let dot = graph.to_dot("a \"repo\"");
+        assert!(dot.starts_with(r#"digraph "a \"repo\"" {"#));
+        assert!(dot.contains(r#"  "say \"hi\"" -> "back\\slash" [weight=1];"#));
+        assert!(dot.contains(r#"  "back\\slash" [label="back\\slash\nchurn 0"#));
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("web/../lib/a"), Some("lib/a".to_owned()));
+        assert_eq!(normalize("./x/."), Some("x".to_owned()));
+        assert_eq!(normalize("a/../.."), None);
+        assert_eq!(crate_root("core/src/config"), "core/src");
+        assert_eq!(crate_root("src"), "src");
+        assert_eq!(crate_root("lib"), "lib");
+    }
+}
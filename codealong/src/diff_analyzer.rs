@@ -1,19 +1,57 @@
-use git2::{Commit, Diff, DiffOptions, Repository};
+use git2::{Commit, Diff, DiffFindOptions, DiffOptions, Repository};
 
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::time::Instant;
 
 use crate::analyzed_diff::AnalyzedDiff;
 use crate::config::Config;
+use crate::diff_sampling::{DiffSampling, DiffSnippet};
 use crate::error::Error;
 use crate::file_analyzer::FileAnalyzer;
-use crate::work_stats::WorkStats;
+use crate::partial_clone::fetch_missing_blobs_for;
+use crate::path_filter::PathFilter;
+use crate::progress::{NoProgress, Progress, BLAME_STAGE, DIFF_STAGE};
+use codealong_metrics::MoveDetector;
+
+/// How files renamed or copied by a commit are told apart from files
+/// deleted and added, so that their lines are blamed on those of the file
+/// they came from rather than all counted as new work.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RenameDetection {
+    /// Renamed files are analyzed as deleted and added.
+    Off,
+    /// Renamed files are detected, as `git diff -M` does, and blames follow
+    /// lines moved within files.
+    Renames,
+    /// Copies of files changed by the same commit are detected too, as
+    /// `git diff -C` does, and blames follow lines moved or copied from
+    /// other files changed by the same commit.
+    #[default]
+    Copies,
+}
+
+impl RenameDetection {
+    fn find_options(self) -> Option<DiffFindOptions> {
+        let mut opts = DiffFindOptions::new();
+        match self {
+            RenameDetection::Off => return None,
+            RenameDetection::Renames => opts.renames(true),
+            RenameDetection::Copies => opts.renames(true).copies(true),
+        };
+        Some(opts)
+    }
+}
 
 pub struct DiffAnalyzer<'a> {
     repo: &'a Repository,
     commit: &'a Commit<'a>,
     parent: Option<&'a Commit<'a>>,
     config: &'a Config,
+    paths: &'a [String],
+    churn_cutoff: Option<u64>,
+    sampling: Option<&'a DiffSampling>,
+    progress: &'a dyn Progress,
 }
 
 impl<'a> DiffAnalyzer<'a> {
@@ -28,40 +66,123 @@ impl<'a> DiffAnalyzer<'a> {
             commit,
             parent,
             config,
+            paths: &[],
+            churn_cutoff: None,
+            sampling: None,
+            progress: &NoProgress,
         }
     }
 
+    /// Limits the diff to `paths`.
+    pub fn with_paths(mut self, paths: &'a [String]) -> DiffAnalyzer<'a> {
+        self.paths = paths;
+        self
+    }
+
+    /// Counts lines rewritten within `churn_cutoff` days as churn, rather
+    /// than within the `churn_cutoff` of the config, unless the globs
+    /// matching a file set their own.
+    pub fn with_churn_cutoff(mut self, churn_cutoff: Option<u64>) -> DiffAnalyzer<'a> {
+        self.churn_cutoff = churn_cutoff;
+        self
+    }
+
+    /// Excerpts the changed lines into the snippets of the result, as
+    /// limited and redacted by `sampling`.
+    pub fn with_sampling(mut self, sampling: Option<&'a DiffSampling>) -> DiffAnalyzer<'a> {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Reports the files analyzed, and the time spent diffing and blaming,
+    /// to `progress`.
+    pub fn with_progress(mut self, progress: &'a dyn Progress) -> DiffAnalyzer<'a> {
+        self.progress = progress;
+        self
+    }
+
     pub fn analyze(&self) -> Result<AnalyzedDiff, Error> {
-        let mut result = AnalyzedDiff {
-            tag_stats: HashMap::new(),
-            stats: WorkStats::empty(),
-        };
+        let mut result = AnalyzedDiff::empty();
         let file_analyzer: RefCell<Option<FileAnalyzer>> = RefCell::new(None);
-        let diff = self.build_diff()?;
+        let move_detector = RefCell::new(MoveDetector::new());
+        let snippets: RefCell<Vec<DiffSnippet>> = RefCell::new(vec![]);
+        let mut snippet_lines = 0;
+        let path_filter = self.config.path_filter();
+        let start = Instant::now();
+        let diff = self.build_diff(&path_filter)?;
+        self.progress.timed(DIFF_STAGE, start.elapsed());
         diff.foreach(
             &mut |diff_delta, _| {
                 if let Some(file_analyzer) = file_analyzer.borrow_mut().take() {
                     result += file_analyzer.finish();
                 }
-                file_analyzer.replace(Some(FileAnalyzer::new(
-                    self.repo,
-                    self.commit,
-                    self.parent,
-                    &diff_delta,
-                    self.config,
-                )));
+                // excluded files are left without an analyzer and skipped
+                if path_filter.includes_delta(&diff_delta) {
+                    if let Some(path) = diff_delta.new_file().path() {
+                        self.progress.file_started(&path.to_string_lossy());
+                    }
+                    // the previous version of the file is blamed up front
+                    let start = Instant::now();
+                    file_analyzer.replace(Some(FileAnalyzer::new(
+                        self.repo,
+                        self.commit,
+                        self.parent,
+                        &diff_delta,
+                        self.config,
+                        self.churn_cutoff,
+                    )));
+                    self.progress.timed(BLAME_STAGE, start.elapsed());
+                }
                 true
             },
             None,
             Some(&mut |_diff_delta, _diff_hunk| {
-                let mut inner = file_analyzer.borrow_mut().take().unwrap();
-                inner.start_hunk().expect("unable to start hunk");
-                file_analyzer.replace(Some(inner));
+                let inner = file_analyzer.borrow_mut().take();
+                if let Some(mut inner) = inner {
+                    inner.start_hunk().expect("unable to start hunk");
+                    file_analyzer.replace(Some(inner));
+                }
                 true
             }),
-            Some(&mut |_diff_delta, _diff_hunk, diff_line| {
+            Some(&mut |diff_delta, _diff_hunk, diff_line| {
                 // TODO: figure out case where diff_hunk is none
-                let mut inner = file_analyzer.borrow_mut().take().unwrap();
+                let inner = file_analyzer.borrow_mut().take();
+                let mut inner = match inner {
+                    Some(inner) => inner,
+                    None => return true,
+                };
+                if !inner.ignores_line(diff_line.origin()) {
+                    move_detector
+                        .borrow_mut()
+                        .record(diff_line.origin(), diff_line.content());
+                    match (self.sampling, diff_line.origin()) {
+                        (Some(sampling), origin @ '+') | (Some(sampling), origin @ '-')
+                            if snippet_lines < sampling.max_lines =>
+                        {
+                            let path = diff_delta
+                                .new_file()
+                                .path()
+                                .map(|path| path.to_string_lossy().into_owned())
+                                .unwrap_or_default();
+                            let content = String::from_utf8_lossy(diff_line.content());
+                            let line = format!(
+                                "{}{}",
+                                origin,
+                                sampling.redact(content.trim_end_matches(['\r', '\n']))
+                            );
+                            let mut snippets = snippets.borrow_mut();
+                            match snippets.last_mut() {
+                                Some(snippet) if snippet.path == path => snippet.lines.push(line),
+                                _ => snippets.push(DiffSnippet {
+                                    path,
+                                    lines: vec![line],
+                                }),
+                            }
+                            snippet_lines += 1;
+                        }
+                        _ => {}
+                    }
+                }
                 inner
                     .analyze_line(&diff_line)
                     .expect("unable to analyze line");
@@ -72,16 +193,35 @@ impl<'a> DiffAnalyzer<'a> {
         if let Some(file_analyzer) = file_analyzer.borrow_mut().take() {
             result += file_analyzer.finish();
         }
+        result.line_stats = move_detector.into_inner().finish();
+        result.snippets = snippets.into_inner();
         Ok(result)
     }
 
-    fn build_diff(&self) -> Result<Diff, Error> {
+    fn build_diff(&self, path_filter: &PathFilter) -> Result<Diff, Error> {
         let mut diff_opts = DiffOptions::new();
         diff_opts.ignore_whitespace(true);
-        Ok(self.repo.diff_tree_to_tree(
-            self.parent.map(|p| p.tree().unwrap()).as_ref(),
-            Some(&self.commit.tree()?),
+        for path in self.paths {
+            diff_opts.pathspec(path);
+        }
+        let old_tree = self.parent.map(|p| p.tree().unwrap());
+        let new_tree = self.commit.tree()?;
+        let mut diff = self.repo.diff_tree_to_tree(
+            old_tree.as_ref(),
+            Some(&new_tree),
             Some(&mut diff_opts),
-        )?)
+        )?;
+        fetch_missing_blobs_for(
+            self.repo,
+            diff.deltas()
+                .filter(|delta| path_filter.includes_delta(delta)),
+            old_tree.as_ref(),
+            Some(&new_tree),
+        )?;
+        if let Some(mut find_opts) = self.config.rename_detection.find_options() {
+            // blobs missing from a partial clone only leave renames undetected
+            let _ = diff.find_similar(Some(&mut find_opts));
+        }
+        Ok(diff)
     }
 }
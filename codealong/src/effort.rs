@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use chrono::prelude::*;
+use chrono::{DateTime, Duration};
+use git2::{Oid, Repository};
+
+use crate::analyzed_commit::AnalyzedCommit;
+use crate::config::Config;
+use crate::error::*;
+use crate::identity::Identity;
+use crate::utils::convert_time;
+
+/// Time assumed to have been spent on the first commit of a session, before
+/// which there is no earlier commit to measure from.
+const SESSION_LEAD_TIME_MINUTES: i64 = 30;
+
+/// Effort estimated for each commit by clustering the commits of each author
+/// into work sessions, a new session starting whenever more than
+/// `session_gap` minutes pass between two commits, and apportioning the time
+/// of each session across its commits: each commit is credited with the time
+/// since the previous commit of the session.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Effort {
+    commits: HashMap<Oid, CommitEffort>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct CommitEffort {
+    session_id: String,
+    seconds: i64,
+}
+
+impl Effort {
+    /// Estimates the effort of the non-merge commits reachable from `heads`.
+    pub fn walk(repo: &Repository, heads: &[Oid], config: &Config) -> Result<Effort> {
+        let mut revwalk = repo.revwalk()?;
+        for head in heads {
+            revwalk.push(*head)?;
+        }
+        let mut commits = vec![];
+        for oid in revwalk {
+            let commit = repo.find_commit(oid?)?;
+            if commit.parent_count() > 1 {
+                continue;
+            }
+            let authored_at = convert_time(&commit.author().when());
+            let author =
+                config.person_for_identity_at(&Identity::from(commit.author()), &authored_at);
+            commits.push((commit.id(), author.id, authored_at));
+        }
+        Ok(Effort::estimate(commits, config.session_gap))
+    }
+
+    /// Estimates the effort of `commits`, given by id, author id and when
+    /// they were authored.
+    pub fn estimate(commits: Vec<(Oid, String, DateTime<Utc>)>, session_gap: u64) -> Effort {
+        let mut by_author: HashMap<String, Vec<(DateTime<Utc>, Oid)>> = HashMap::new();
+        for (oid, author_id, authored_at) in commits {
+            by_author
+                .entry(author_id)
+                .or_default()
+                .push((authored_at, oid));
+        }
+        let session_gap = Duration::minutes(session_gap as i64);
+        let mut effort = Effort::default();
+        for (author_id, mut commits) in by_author {
+            commits.sort();
+            let mut session_id = String::new();
+            let mut previous: Option<DateTime<Utc>> = None;
+            for (authored_at, oid) in commits {
+                let seconds = match previous {
+                    Some(previous) if authored_at - previous <= session_gap => {
+                        (authored_at - previous).num_seconds()
+                    }
+                    _ => {
+                        session_id = format!("{}-{}", author_id, authored_at.timestamp());
+                        SESSION_LEAD_TIME_MINUTES * 60
+                    }
+                };
+                previous = Some(authored_at);
+                effort.commits.insert(
+                    oid,
+                    CommitEffort {
+                        session_id: session_id.clone(),
+                        seconds,
+                    },
+                );
+            }
+        }
+        effort
+    }
+
+    /// Records on `commit` the session it belongs to and its share of the
+    /// time of the session, if it was estimated.
+    pub fn apply(&self, commit: &mut AnalyzedCommit) {
+        if let Some(effort) = Oid::from_str(&commit.id)
+            .ok()
+            .and_then(|oid| self.commits.get(&oid))
+        {
+            commit.session_id = Some(effort.session_id.clone());
+            commit.effort = Some(effort.seconds);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate() {
+        let at = |hour: u32, minute: u32| Utc.ymd(2019, 3, 4).and_hms(hour, minute, 0);
+        let oid = |n: u8| Oid::from_bytes(&[n; 20]).unwrap();
+        let effort = Effort::estimate(
+            vec![
+                (oid(3), "alice".to_owned(), at(10, 45)),
+                (oid(1), "alice".to_owned(), at(9, 0)),
+                (oid(2), "alice".to_owned(), at(10, 0)),
+                (oid(4), "alice".to_owned(), at(14, 0)),
+                (oid(5), "bob".to_owned(), at(10, 30)),
+            ],
+            120,
+        );
+        let estimated = |n: u8| {
+            let effort = &effort.commits[&oid(n)];
+            (effort.session_id.as_str(), effort.seconds)
+        };
+        let morning = format!("alice-{}", at(9, 0).timestamp());
+        assert_eq!(estimated(1), (morning.as_str(), 30 * 60));
+        assert_eq!(estimated(2), (morning.as_str(), 60 * 60));
+        assert_eq!(estimated(3), (morning.as_str(), 45 * 60));
+        let afternoon = format!("alice-{}", at(14, 0).timestamp());
+        assert_eq!(estimated(4), (afternoon.as_str(), 30 * 60));
+        let bob = format!("bob-{}", at(10, 30).timestamp());
+        assert_eq!(estimated(5), (bob.as_str(), 30 * 60));
+    }
+}
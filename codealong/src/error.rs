@@ -12,11 +12,33 @@ error_chain! {
             description("error running git blame")
             display("blame error: {}", message)
         }
+        GitError(message: String) {
+            description("error running git")
+            display("git error: {}", message)
+        }
+        InvalidSurvey(message: String) {
+            description("invalid survey data")
+            display("invalid survey data: {}", message)
+        }
+        Offline(action: String) {
+            description("network access is disabled")
+            display("network access is disabled in offline mode: {}", action)
+        }
+        Locked(path: String, pid: u32) {
+            description("locked")
+            display("{} is locked by process {}", path, pid)
+        }
+        PluginFailed(plugin: String, message: String) {
+            description("plugin failed")
+            display("plugin {} failed: {}", plugin, message)
+        }
     }
 
     foreign_links {
         Git2(git2::Error);
         IO(io::Error);
         Config(serde_yaml::Error);
+        Json(serde_json::Error);
+        Regex(regex::Error);
     }
 }
@@ -1,11 +1,18 @@
-use git2::{Commit, Delta, DiffDelta, DiffLine, Repository};
+use chrono::prelude::*;
+use chrono::DateTime;
+use git2::{Commit, Delta, DiffDelta, DiffLine, Oid, Repository};
 
-use crate::analyzed_diff::AnalyzedDiff;
-use crate::config::{Config, FileConfig, PersonConfig};
+use crate::analyzed_diff::{AnalyzedDiff, FileStats, SkippedReason};
+use crate::complexity::{ComplexityDelta, FileMetrics};
+use crate::config::{Config, LargeFiles, PersonConfig};
 use crate::config_context::ConfigContext;
 use crate::error::Error;
 use crate::git_blame::GitBlame;
 use crate::hunk_analyzer::HunkAnalyzer;
+use crate::language::detect_blob_language;
+use crate::lfs::{LfsPointer, LfsStats};
+use crate::utils::convert_time;
+use codealong_metrics::LanguageStats;
 
 pub struct FileAnalyzer<'a> {
     repo: &'a Repository,
@@ -15,6 +22,18 @@ pub struct FileAnalyzer<'a> {
     config_context: ConfigContext,
     current_hunk: Option<HunkAnalyzer<'a>>,
     ignored: bool,
+    path: Option<String>,
+    /// Path the file had before it was renamed or copied.
+    old_path: Option<String>,
+    status: Delta,
+    /// Tags of the globs the path matches, without those of the author.
+    file_tags: Vec<String>,
+    language: Option<String>,
+    complexity: Option<ComplexityDelta>,
+    skipped_reason: Option<SkippedReason>,
+    /// Lines added and deleted, counted for the language stats.
+    added: u64,
+    deleted: u64,
 }
 
 impl<'a> FileAnalyzer<'a> {
@@ -24,20 +43,114 @@ impl<'a> FileAnalyzer<'a> {
         parent: Option<&'a Commit<'a>>,
         diff_delta: &DiffDelta,
         config: &'a Config,
+        churn_cutoff: Option<u64>,
     ) -> FileAnalyzer<'a> {
-        let file_config = get_file_config(config, &diff_delta);
-        let author_config = get_author_config(config, commit);
-        let config_context = ConfigContext::new(file_config.as_ref(), author_config.as_ref());
-        let blame = get_blame(repo, diff_delta, parent, config);
+        let at = convert_time(&commit.author().when());
+        let path = diff_delta
+            .new_file()
+            .path()
+            .or(diff_delta.old_file().path())
+            .and_then(|path| path.to_str())
+            .map(|path| path.to_owned());
+        let status = diff_delta.status();
+        let old_path = match status {
+            Delta::Renamed | Delta::Copied => diff_delta
+                .old_file()
+                .path()
+                .and_then(|path| path.to_str())
+                .map(|path| path.to_owned()),
+            _ => None,
+        };
+        let file_config = path
+            .as_ref()
+            .and_then(|path| config.config_for_file_at(path, &at));
+        let mut file_tags: Vec<String> = file_config
+            .as_ref()
+            .map(|c| c.tags().iter().map(|s| s.to_string()).collect())
+            .unwrap_or_default();
+        file_tags.sort();
+        // a language declared by the globs takes precedence over detection
+        let language = file_config
+            .as_ref()
+            .and_then(|c| c.language().map(|language| language.to_owned()))
+            .or_else(|| {
+                let file = if diff_delta.status() == Delta::Deleted {
+                    diff_delta.old_file()
+                } else {
+                    diff_delta.new_file()
+                };
+                path.as_ref()
+                    .and_then(|path| detect_blob_language(repo, path, file.id()))
+                    .map(|language| language.to_owned())
+            });
+        let author_config = get_author_config(config, commit, &at);
+        let mut config_context = ConfigContext::new(
+            config,
+            file_config.as_ref(),
+            author_config.as_ref(),
+            language.as_deref(),
+        );
+        let mut result = AnalyzedDiff::empty();
+        let mut ignored = file_config.as_ref().is_some_and(|c| c.ignore());
+        // the lines of an LFS pointer say nothing about the work done on the
+        // real file, so only its size change is recorded
+        let old_pointer = LfsPointer::from_blob(repo, diff_delta.old_file().id());
+        let new_pointer = LfsPointer::from_blob(repo, diff_delta.new_file().id());
+        if !ignored && (old_pointer.is_some() || new_pointer.is_some()) {
+            result.lfs = LfsStats::change(old_pointer.as_ref(), new_pointer.as_ref());
+            ignored = true;
+        }
+        let mut skipped_reason = None;
+        if !ignored {
+            match (
+                skip(repo, diff_delta, &config.large_files),
+                config.large_files.weight,
+            ) {
+                (Some(SkippedReason::TooLarge), Some(weight)) => {
+                    config_context.scale_weight(weight)
+                }
+                (Some(reason), _) => {
+                    skipped_reason = Some(reason);
+                    ignored = true;
+                }
+                (None, _) => {}
+            }
+        }
+        let blame = if ignored {
+            None
+        } else {
+            // the cutoff of the globs matching the file takes precedence
+            // over that of the repo, and that over that of the config
+            let churn_cutoff = path
+                .as_ref()
+                .and_then(|path| config.churn_cutoff_for_file(path))
+                .or(churn_cutoff)
+                .unwrap_or(config.churn_cutoff);
+            get_blame(repo, diff_delta, parent, churn_cutoff, config)
+        };
+        let complexity = if ignored {
+            None
+        } else {
+            get_complexity(repo, diff_delta, language.as_deref())
+        };
 
         FileAnalyzer {
             repo,
             commit,
-            result: AnalyzedDiff::empty(),
+            result,
             config_context,
             blame,
             current_hunk: None,
-            ignored: file_config.map(|c| c.ignore()).unwrap_or(false),
+            ignored,
+            path,
+            old_path,
+            status,
+            file_tags,
+            language,
+            complexity,
+            skipped_reason,
+            added: 0,
+            deleted: 0,
         }
     }
 
@@ -48,12 +161,18 @@ impl<'a> FileAnalyzer<'a> {
             self.commit,
             self.blame.take(),
             self.config_context.weight(),
+            self.config_context.churn_weight(),
         ));
         Ok(())
     }
 
     pub fn analyze_line(&mut self, diff_line: &DiffLine) -> Result<(), Error> {
-        if !self.ignored {
+        if !self.ignores_line(diff_line.origin()) {
+            match diff_line.origin() {
+                '+' => self.added += 1,
+                '-' => self.deleted += 1,
+                _ => {}
+            }
             let mut current_hunk = self.current_hunk.take().expect("no hunk started");
             current_hunk.analyze_line(diff_line)?;
             self.current_hunk.replace(current_hunk);
@@ -61,6 +180,13 @@ impl<'a> FileAnalyzer<'a> {
         Ok(())
     }
 
+    /// Whether lines with `origin` are left out of the stats.
+    pub fn ignores_line(&self, origin: char) -> bool {
+        // the file a copy was made from is still there, so only the lines the
+        // copy adds to it are work: those it leaves out are not deleted
+        self.ignored || (self.status == Delta::Copied && origin != '+')
+    }
+
     fn finish_hunk(&mut self) {
         if let Some(current_hunk) = self.current_hunk.take() {
             let (blame, hunk_result) = current_hunk.finish();
@@ -72,40 +198,114 @@ impl<'a> FileAnalyzer<'a> {
 
     pub fn finish(mut self) -> AnalyzedDiff {
         self.finish_hunk();
+        let path = match self.path {
+            Some(path) if !self.ignored || self.skipped_reason.is_some() => path,
+            _ => return self.result,
+        };
+        // skipped files are listed with the reason, without their lines
+        if self.skipped_reason.is_none() {
+            if let Some(ref language) = self.language {
+                self.result.language_stats.insert(
+                    language.clone(),
+                    LanguageStats {
+                        files: 1,
+                        added: self.added,
+                        deleted: self.deleted,
+                        stats: self.result.stats,
+                    },
+                );
+            }
+        }
+        self.result.files.push(FileStats {
+            path,
+            canonical_path: None,
+            old_path: self.old_path,
+            renamed: self.status == Delta::Renamed,
+            copied: self.status == Delta::Copied,
+            tags: self.file_tags,
+            weight: self.config_context.weight(),
+            language: self.language,
+            stats: self.result.stats,
+            complexity: self.complexity,
+            skipped_reason: self.skipped_reason,
+        });
         self.result
     }
 }
 
-fn get_file_config<'a>(config: &'a Config, diff_delta: &DiffDelta) -> Option<FileConfig<'a>> {
-    diff_delta
-        .new_file()
-        .path()
-        .or(diff_delta.old_file().path())
-        .and_then(|path| path.to_str().and_then(|path| config.config_for_file(path)))
+fn get_author_config<'a>(
+    config: &'a Config,
+    commit: &Commit,
+    at: &DateTime<Utc>,
+) -> Option<PersonConfig<'a>> {
+    config
+        .config_for_identity(&commit.author().into())
+        .map(|c| c.at(at))
 }
 
-fn get_author_config<'a>(config: &'a Config, commit: &Commit) -> Option<PersonConfig<'a>> {
-    config.config_for_identity(&commit.author().into())
+/// Why the lines of a file are not worth analyzing: binary blobs have no
+/// lines, and those of large files skew churn and are slow to blame.
+fn skip(
+    repo: &Repository,
+    diff_delta: &DiffDelta,
+    large_files: &LargeFiles,
+) -> Option<SkippedReason> {
+    let ids: Vec<Oid> = [diff_delta.old_file().id(), diff_delta.new_file().id()]
+        .iter()
+        .filter(|id| !id.is_zero())
+        .cloned()
+        .collect();
+    let odb = repo.odb().ok()?;
+    // the size is read from the header so that large blobs are not loaded
+    let too_large = ids.iter().any(|id| {
+        odb.read_header(*id)
+            .is_ok_and(|(size, _)| size as u64 > large_files.max_size)
+    });
+    if too_large {
+        return Some(SkippedReason::TooLarge);
+    }
+    let binary = ids
+        .iter()
+        .any(|id| repo.find_blob(*id).is_ok_and(|blob| blob.is_binary()));
+    if binary {
+        Some(SkippedReason::Binary)
+    } else {
+        None
+    }
+}
+
+fn get_complexity(
+    repo: &Repository,
+    diff_delta: &DiffDelta,
+    language: Option<&str>,
+) -> Option<ComplexityDelta> {
+    // a copy is a new file, whatever it was copied from
+    let before_id = if diff_delta.status() == Delta::Copied {
+        Oid::zero()
+    } else {
+        diff_delta.old_file().id()
+    };
+    let before = FileMetrics::of_blob(repo, before_id, language)?;
+    let after = FileMetrics::of_blob(repo, diff_delta.new_file().id(), language)?;
+    Some(ComplexityDelta::new(before, after))
 }
 
 fn get_blame(
     repo: &Repository,
     diff_delta: &DiffDelta,
     parent: Option<&Commit>,
+    churn_cutoff: u64,
     config: &Config,
 ) -> Option<GitBlame> {
-    if diff_delta.status() != Delta::Modified {
-        return None;
+    // the lines of a renamed file are blamed under its old path, so that
+    // churn follows it
+    match diff_delta.status() {
+        Delta::Modified | Delta::Renamed => {}
+        _ => return None,
     }
     diff_delta.old_file().path().and_then(|old_path| {
         parent.and_then(|parent| {
-            if let Ok(new_blame) =
-                GitBlame::new(&repo, &parent.id(), &old_path, config.churn_cutoff)
-            {
-                Some(new_blame)
-            } else {
-                None
-            }
+            GitBlame::new(repo, &parent.id(), old_path, churn_cutoff, config).ok()
         })
     })
 }
@@ -0,0 +1,100 @@
+use chrono::prelude::*;
+use chrono::{DateTime, Duration};
+use rand::Rng;
+
+/// Percentiles of the simulated completion time which are reported.
+const PERCENTILES: [u32; 3] = [50, 85, 95];
+
+/// Upper bound on the number of simulated weeks so that a history with very
+/// low throughput cannot simulate forever.
+const MAX_WEEKS: u64 = 520;
+
+/// The simulated number of weeks within which the work completed in at least
+/// `percentile` percent of trials.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForecastPercentile {
+    pub percentile: u32,
+    pub weeks: u64,
+    pub completed_by: DateTime<Utc>,
+}
+
+/// A probabilistic forecast of when a number of work items will be done.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Forecast {
+    pub items: u64,
+    pub trials: usize,
+    pub percentiles: Vec<ForecastPercentile>,
+}
+
+/// Forecasts the completion of `items` using Monte Carlo simulation: each
+/// trial repeatedly samples a week from the historical weekly throughput
+/// until enough items are done. Returns `None` if the history contains no
+/// completed items.
+pub fn forecast<R: Rng>(
+    history: &[u64],
+    items: u64,
+    trials: usize,
+    start: &DateTime<Utc>,
+    rng: &mut R,
+) -> Option<Forecast> {
+    if trials == 0 || history.iter().all(|&count| count == 0) {
+        return None;
+    }
+    let mut results: Vec<u64> = (0..trials)
+        .map(|_| {
+            let mut done = 0;
+            let mut weeks = 0;
+            while done < items && weeks < MAX_WEEKS {
+                done += history[rng.gen_range(0, history.len())];
+                weeks += 1;
+            }
+            weeks
+        })
+        .collect();
+    results.sort();
+    Some(Forecast {
+        items,
+        trials,
+        percentiles: PERCENTILES
+            .iter()
+            .map(|&percentile| {
+                let index = (trials * percentile as usize).div_ceil(100).max(1) - 1;
+                let weeks = results[index];
+                ForecastPercentile {
+                    percentile,
+                    weeks,
+                    completed_by: *start + Duration::weeks(weeks as i64),
+                }
+            })
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, StdRng};
+
+    #[test]
+    fn test_forecast() {
+        let mut rng = StdRng::from_seed([7; 32]);
+        let start = Utc.ymd(2019, 1, 7).and_hms(0, 0, 0);
+        let forecast = forecast(&[4, 6, 5, 5, 0, 10], 30, 1000, &start, &mut rng).unwrap();
+        assert_eq!(forecast.percentiles.len(), 3);
+        let weeks: Vec<u64> = forecast.percentiles.iter().map(|p| p.weeks).collect();
+        assert!(weeks[0] >= 3 && weeks[0] <= 9);
+        assert!(weeks[0] <= weeks[1] && weeks[1] <= weeks[2]);
+        assert_eq!(
+            forecast.percentiles[0].completed_by,
+            start + Duration::weeks(weeks[0] as i64)
+        );
+    }
+
+    #[test]
+    fn test_forecast_without_throughput() {
+        let mut rng = StdRng::from_seed([7; 32]);
+        let start = Utc.ymd(2019, 1, 7).and_hms(0, 0, 0);
+        assert_eq!(forecast(&[0, 0], 30, 1000, &start, &mut rng), None);
+        assert_eq!(forecast(&[], 30, 1000, &start, &mut rng), None);
+    }
+}
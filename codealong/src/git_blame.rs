@@ -1,106 +1,256 @@
-use git2::{Oid, Repository};
-use std::cell::RefCell;
+use chrono::prelude::*;
+use git2::{BlameOptions, Oid, Repository};
 use std::collections::HashMap;
-use std::io::BufRead;
-use std::io::BufReader;
-use std::io::Read;
+use std::io;
 use std::path::Path;
-use std::process::{Child, ChildStderr, ChildStdout, Command, Stdio};
+use std::process::Command;
+use std::sync::Arc;
 
+use crate::blame_cache::{self, BlameKey, BlameLines};
+use crate::config::Config;
+use crate::diff_analyzer::RenameDetection;
 use crate::error::*;
+use crate::partial_clone::fetch_missing_history;
+use crate::replace_refs::ReplaceRefs;
 
 use regex::Regex;
 
+/// What runs blames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BlameEngine {
+    /// The `git` binary on the current path.
+    #[default]
+    External,
+    /// libgit2, for environments without a `git` binary. It is much slower,
+    /// always walks the entire history rather than stopping at the churn
+    /// cutoff and does not honor `git replace` refs.
+    Libgit2,
+}
+
 // libgit2 has an extremely slow blame implementation:
 // https://github.com/libgit2/libgit2/issues/3027
-// so we instead defer to a git binary on the current path
+// so we instead defer to a git binary on the current path. Spawning it still
+// dominates the analysis of churn-heavy repos, so blames are cached by the
+// parent and path they are run against and reused across commits.
 pub struct GitBlame {
-    child: Child,
-    reader: RefCell<BufReader<ChildStdout>>,
-    error_reader: RefCell<BufReader<ChildStderr>>,
-    line_map: RefCell<HashMap<usize, Oid>>,
+    lines: BlameLines,
+    error: Option<String>,
 }
 
 impl GitBlame {
+    /// Blame which stops at commits older than `churn_cutoff` days, which
+    /// may differ from the `churn_cutoff` of `config` for the path or repo.
     pub fn new(
         repo: &Repository,
         parent: &Oid,
         old_path: &Path,
         churn_cutoff: u64,
+        config: &Config,
+    ) -> Result<GitBlame> {
+        // the cutoff is taken from the start of the day so that the blame,
+        // and hence its cache entry, stays the same throughout the day
+        let since = Utc::today().and_hms(0, 0, 0).timestamp() - churn_cutoff as i64 * 86400;
+        Self::get(repo, parent, old_path, Some(since), config)
+    }
+
+    /// Blame which walks the entire history rather than stopping at the churn
+    /// cutoff.
+    pub fn full(
+        repo: &Repository,
+        parent: &Oid,
+        old_path: &Path,
+        config: &Config,
     ) -> Result<GitBlame> {
-        let mut child = Command::new("git")
-            .current_dir(repo.path())
+        Self::get(repo, parent, old_path, None, config)
+    }
+
+    fn get(
+        repo: &Repository,
+        parent: &Oid,
+        old_path: &Path,
+        since: Option<i64>,
+        config: &Config,
+    ) -> Result<GitBlame> {
+        let key = BlameKey {
+            parent: *parent,
+            path: old_path.to_owned(),
+            since: match config.blame_engine {
+                BlameEngine::External => since,
+                BlameEngine::Libgit2 => None,
+            },
+            replace_refs: config.replace_refs,
+            engine: config.blame_engine,
+            rename_detection: config.rename_detection,
+        };
+        let cache_dir = config.blame_cache_dir.as_ref().map(Path::new);
+        if let Some(lines) = blame_cache::get(&key, cache_dir) {
+            return Ok(GitBlame { lines, error: None });
+        }
+        let blame = match key.engine {
+            BlameEngine::External => Self::run(repo, &key)?,
+            BlameEngine::Libgit2 => Self::run_libgit2(repo, &key),
+        };
+        if blame.error.is_none() {
+            blame_cache::insert(key, blame.lines.clone(), cache_dir);
+        }
+        Ok(blame)
+    }
+
+    // see https://git-scm.com/docs/git-blame#_the_porcelain_format
+    fn run(repo: &Repository, key: &BlameKey) -> Result<GitBlame> {
+        fetch_missing_history(repo, &key.parent, &key.path)?;
+        let mut command = blame_command(repo, key.replace_refs);
+        command
             .arg("blame")
-            .arg(parent.to_string())
+            .arg(key.parent.to_string())
             .arg("-s")
             .arg("-l")
             .arg("-p")
-            .arg("--incremental")
-            .arg(format!("--since={}.days", churn_cutoff))
-            .arg("--")
-            .arg(old_path)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()?;
+            .arg("--incremental");
+        if let Some(since) = key.since {
+            command.arg(format!("--since=@{}", since));
+        }
+        match key.rename_detection {
+            RenameDetection::Off => {}
+            RenameDetection::Renames => {
+                command.arg("-M");
+            }
+            RenameDetection::Copies => {
+                command.arg("-M").arg("-C");
+            }
+        }
+        let output = match command.arg("--").arg(&key.path).output() {
+            Ok(output) => output,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+                return Err(ErrorKind::BlameError(
+                    "git not found on the path, set `blame_engine: libgit2` to blame without it"
+                        .to_owned(),
+                )
+                .into());
+            }
+            Err(e) => return Err(e.into()),
+        };
 
+        let mut lines = HashMap::new();
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            if let Some(blame_line) = BlameLine::new(line) {
+                lines.insert(blame_line.original_lineno, blame_line.oid);
+            }
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
         Ok(GitBlame {
-            reader: RefCell::new(BufReader::new(
-                child
-                    .stdout
-                    .take()
-                    .ok_or_else(|| "Could not capture standard output.")?,
-            )),
-            error_reader: RefCell::new(BufReader::new(
-                child
-                    .stderr
-                    .take()
-                    .ok_or_else(|| "Could not capture standard error.")?,
-            )),
-            child: child,
-            line_map: RefCell::new(HashMap::new()),
+            lines: Arc::new(lines),
+            error: if stderr.is_empty() {
+                None
+            } else {
+                Some(stderr)
+            },
         })
     }
 
-    pub fn get_line(&self, lineno: usize) -> Result<Option<Oid>> {
-        if let Some(l) = self.line_map.borrow().get(&lineno) {
-            return Ok(Some(l.clone()));
+    fn run_libgit2(repo: &Repository, key: &BlameKey) -> GitBlame {
+        let mut opts = BlameOptions::new();
+        opts.newest_commit(key.parent);
+        match key.rename_detection {
+            RenameDetection::Off => {}
+            RenameDetection::Renames => {
+                opts.track_copies_same_file(true);
+            }
+            RenameDetection::Copies => {
+                opts.track_copies_same_file(true)
+                    .track_copies_same_commit_moves(true)
+                    .track_copies_same_commit_copies(true);
+            }
+        }
+        match repo.blame_file(&key.path, Some(&mut opts)) {
+            Ok(blame) => {
+                let mut lines = HashMap::new();
+                for hunk in blame.iter() {
+                    let start = hunk.final_start_line();
+                    for lineno in start..start + hunk.lines_in_hunk() {
+                        lines.insert(lineno, hunk.final_commit_id());
+                    }
+                }
+                GitBlame {
+                    lines: Arc::new(lines),
+                    error: None,
+                }
+            }
+            Err(e) => GitBlame {
+                lines: Arc::new(HashMap::new()),
+                error: Some(e.message().to_owned()),
+            },
         }
-
-        self.scan_for_line(lineno)
     }
 
-    // see https://git-scm.com/docs/git-blame#_the_porcelain_format
-    fn scan_for_line(&self, lineno: usize) -> Result<Option<Oid>> {
-        let mut line = String::new();
-        let mut reader = self.reader.borrow_mut();
-        let mut line_map = self.line_map.borrow_mut();
-        while let Ok(num_bytes) = reader.read_line(&mut line) {
-            if num_bytes == 0 {
-                break;
+    /// Number of the lines of `path` as of `head` which each commit last
+    /// changed, walking the entire history.
+    pub fn line_counts(
+        repo: &Repository,
+        head: &Oid,
+        path: &Path,
+        config: &Config,
+    ) -> Result<HashMap<Oid, u64>> {
+        lazy_static! {
+            static ref GROUP_REGEX: Regex = Regex::new(r"^([0-9a-f]{40}) \d+ \d+ (\d+)$").unwrap();
+        }
+        let mut counts = HashMap::new();
+        if config.blame_engine == BlameEngine::Libgit2 {
+            let mut opts = BlameOptions::new();
+            opts.newest_commit(*head);
+            for hunk in repo.blame_file(path, Some(&mut opts))?.iter() {
+                *counts.entry(hunk.final_commit_id()).or_insert(0) += hunk.lines_in_hunk() as u64;
             }
-            if let Some(blame_line) = BlameLine::new(&line) {
-                line_map.insert(blame_line.original_lineno, blame_line.oid);
-                if blame_line.original_lineno == lineno {
-                    return Ok(Some(blame_line.oid.clone()));
-                }
+            return Ok(counts);
+        }
+        fetch_missing_history(repo, head, path)?;
+        let output = blame_command(repo, config.replace_refs)
+            .arg("blame")
+            .arg(head.to_string())
+            .arg("--incremental")
+            .arg("--")
+            .arg(path)
+            .output()?;
+        if !output.status.success() {
+            return Err(ErrorKind::BlameError(
+                String::from_utf8_lossy(&output.stderr).into_owned(),
+            )
+            .into());
+        }
+        // each group of lines is listed once, along with its size
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            if let Some(captures) = GROUP_REGEX.captures(line) {
+                *counts
+                    .entry(Oid::from_str(&captures[1]).unwrap())
+                    .or_insert(0) += captures[2].parse::<u64>().unwrap();
             }
-            line.clear();
         }
-        let mut reader = self.error_reader.borrow_mut();
-        if reader.read_to_string(&mut line)? > 0 {
-            Err(ErrorKind::BlameError(line).into())
-        } else {
-            Ok(None)
+        Ok(counts)
+    }
+
+    pub fn get_line(&self, lineno: usize) -> Result<Option<Oid>> {
+        if let Some(oid) = self.lines.get(&lineno) {
+            return Ok(Some(*oid));
+        }
+        match self.error {
+            Some(ref error) => Err(ErrorKind::BlameError(error.clone()).into()),
+            None => Ok(None),
         }
     }
 }
 
-impl Drop for GitBlame {
-    fn drop(&mut self) {
-        // need this to prevent zombie "Z+" processes from occuring
-        self.child.kill().expect("unable to kill process");
-        self.child.wait().expect("unable to wait for process");
+/// A git command run in `repo` which fails rather than lazily fetching the
+/// objects missing from a partial clone, one at a time and even offline.
+fn blame_command(repo: &Repository, replace_refs: ReplaceRefs) -> Command {
+    let mut command = Command::new("git");
+    command
+        .current_dir(repo.path())
+        .env("GIT_NO_LAZY_FETCH", "1");
+    if replace_refs == ReplaceRefs::Ignore {
+        command.env("GIT_NO_REPLACE_OBJECTS", "1");
     }
+    command
 }
 
 struct BlameLine {
@@ -112,7 +262,7 @@ impl BlameLine {
     pub fn new(line: &str) -> Option<BlameLine> {
         lazy_static! {
             static ref BLAME_LINE_REGEX: Regex =
-                Regex::new(r"^([0-9a-f]{40}) (\d+) \d+ \d+\n$").unwrap();
+                Regex::new(r"^([0-9a-f]{40}) (\d+) \d+ \d+$").unwrap();
         }
         if let Some(captures) = BLAME_LINE_REGEX.captures(line) {
             Some(BlameLine {
@@ -138,6 +288,7 @@ mod tests {
             &Oid::from_str("86d242301830075e93ff039a4d1e88673a4a3020").unwrap(),
             Path::new("README.md"),
             14,
+            &Config::default(),
         )
         .unwrap();
         assert!(
@@ -150,8 +301,45 @@ mod tests {
             &Oid::from_str("86d242301830075e93ff039a4d1e88673a4a3020").unwrap(),
             Path::new("bad_path.rs"),
             14,
+            &Config::default(),
         )
         .unwrap();
         assert!(blame.get_line(1).is_err())
     }
+
+    #[test]
+    fn test_cached() {
+        let repo = Repository::open(Path::new("./fixtures/repos/simple")).unwrap();
+        let parent = Oid::from_str("86d242301830075e93ff039a4d1e88673a4a3020").unwrap();
+        let key = BlameKey {
+            parent,
+            path: Path::new("README.md").to_owned(),
+            since: None,
+            replace_refs: ReplaceRefs::Honor,
+            engine: BlameEngine::External,
+            rename_detection: RenameDetection::Copies,
+        };
+        let blame =
+            GitBlame::full(&repo, &parent, Path::new("README.md"), &Config::default()).unwrap();
+        let cached = blame_cache::get(&key, None).unwrap();
+        assert_eq!(cached.get(&1), Some(&parent));
+        assert!(Arc::ptr_eq(&cached, &blame.lines));
+    }
+
+    #[test]
+    fn test_libgit2() {
+        let repo = Repository::open(Path::new("./fixtures/repos/simple")).unwrap();
+        let config = Config {
+            blame_engine: BlameEngine::Libgit2,
+            ..Config::default()
+        };
+        let head = Oid::from_str("86d242301830075e93ff039a4d1e88673a4a3020").unwrap();
+        let external =
+            GitBlame::full(&repo, &head, Path::new("README.md"), &Config::default()).unwrap();
+        let blame = GitBlame::new(&repo, &head, Path::new("README.md"), 14, &config).unwrap();
+        assert_eq!(blame.lines, external.lines);
+
+        let blame = GitBlame::new(&repo, &head, Path::new("bad_path.rs"), 14, &config).unwrap();
+        assert!(blame.get_line(1).is_err())
+    }
 }
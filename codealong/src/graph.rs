@@ -0,0 +1,332 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use git2::Repository;
+
+use crate::compliance::trailers;
+use crate::config::Config;
+use crate::error::Result;
+use crate::identity::Identity;
+use crate::text::commit_message;
+use crate::utils::{changed_paths, convert_time};
+use codealong_model::Person;
+
+/// Relationship the edges of a collaboration graph stand for.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GraphKind {
+    /// Undirected edges between people who changed the same files, weighted
+    /// by the number of files.
+    CoChange,
+    /// Directed edges from reviewers to the authors whose commits they
+    /// reviewed, acked or tested, weighted by the number of commits.
+    ReviewNetwork,
+}
+
+impl GraphKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            GraphKind::CoChange => "co-change",
+            GraphKind::ReviewNetwork => "review-network",
+        }
+    }
+
+    pub fn is_directed(self) -> bool {
+        self == GraphKind::ReviewNetwork
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+    pub weight: u64,
+}
+
+/// A weighted graph of how people collaborate, for organizational network
+/// analysis in other tools.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CollaborationGraph {
+    pub kind: GraphKind,
+    pub directed: bool,
+    pub nodes: Vec<Person>,
+    pub edges: Vec<GraphEdge>,
+}
+
+impl CollaborationGraph {
+    /// Builds the graph from the commits reachable from `range`, or from
+    /// `HEAD` if none is given. People are normalized through `config`.
+    pub fn from_repository(
+        repo: &Repository,
+        config: &Config,
+        kind: GraphKind,
+        range: Option<&str>,
+    ) -> Result<CollaborationGraph> {
+        let mut revwalk = repo.revwalk()?;
+        match range {
+            Some(range) => revwalk.push_range(range)?,
+            None => revwalk.push_head()?,
+        }
+        let mut builder = GraphBuilder::new(kind);
+        let mut file_authors: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        for oid in revwalk {
+            let commit = repo.find_commit(oid?)?;
+            if commit.parent_count() > 1 {
+                continue;
+            }
+            let at = convert_time(&commit.author().when());
+            let author = config.person_for_identity_at(&Identity::from(commit.author()), &at);
+            match kind {
+                GraphKind::CoChange => {
+                    for path in changed_paths(repo, &commit)? {
+                        file_authors
+                            .entry(path)
+                            .or_default()
+                            .insert(author.id.clone());
+                    }
+                    builder.add_node(&author);
+                }
+                GraphKind::ReviewNetwork => {
+                    for reviewer in reviewers(&commit_message(&commit)) {
+                        let reviewer = config.person_for_identity_at(&reviewer, &at);
+                        if reviewer.id != author.id {
+                            builder.add_edge(&reviewer, &author);
+                        }
+                    }
+                }
+            }
+        }
+        for authors in file_authors.values() {
+            let authors: Vec<&String> = authors.iter().collect();
+            for (i, a) in authors.iter().enumerate() {
+                for b in &authors[i + 1..] {
+                    builder.add_edge_ids(a, b);
+                }
+            }
+        }
+        Ok(builder.finish())
+    }
+
+    pub fn to_dot(&self) -> String {
+        let (keyword, arrow) = if self.directed {
+            ("digraph", "->")
+        } else {
+            ("graph", "--")
+        };
+        let mut out = String::new();
+        writeln!(out, "{} \"{}\" {{", keyword, self.kind.name()).unwrap();
+        for node in &self.nodes {
+            writeln!(
+                out,
+                "  \"{}\" [label=\"{}\"];",
+                escape_dot(&node.id),
+                escape_dot(label(node))
+            )
+            .unwrap();
+        }
+        for edge in &self.edges {
+            writeln!(
+                out,
+                "  \"{}\" {} \"{}\" [weight={}];",
+                escape_dot(&edge.source),
+                arrow,
+                escape_dot(&edge.target),
+                edge.weight
+            )
+            .unwrap();
+        }
+        writeln!(out, "}}").unwrap();
+        out
+    }
+
+    pub fn to_graphml(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>").unwrap();
+        writeln!(
+            out,
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">"
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "  <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>"
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "  <key id=\"weight\" for=\"edge\" attr.name=\"weight\" attr.type=\"long\"/>"
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "  <graph id=\"{}\" edgedefault=\"{}\">",
+            self.kind.name(),
+            if self.directed {
+                "directed"
+            } else {
+                "undirected"
+            }
+        )
+        .unwrap();
+        for node in &self.nodes {
+            writeln!(
+                out,
+                "    <node id=\"{}\"><data key=\"label\">{}</data></node>",
+                escape_xml(&node.id),
+                escape_xml(label(node))
+            )
+            .unwrap();
+        }
+        for edge in &self.edges {
+            writeln!(
+                out,
+                "    <edge source=\"{}\" target=\"{}\"><data key=\"weight\">{}</data></edge>",
+                escape_xml(&edge.source),
+                escape_xml(&edge.target),
+                edge.weight
+            )
+            .unwrap();
+        }
+        writeln!(out, "  </graph>").unwrap();
+        writeln!(out, "</graphml>").unwrap();
+        out
+    }
+}
+
+struct GraphBuilder {
+    kind: GraphKind,
+    nodes: BTreeMap<String, Person>,
+    edges: BTreeMap<(String, String), u64>,
+}
+
+impl GraphBuilder {
+    fn new(kind: GraphKind) -> GraphBuilder {
+        GraphBuilder {
+            kind,
+            nodes: BTreeMap::new(),
+            edges: BTreeMap::new(),
+        }
+    }
+
+    fn add_node(&mut self, person: &Person) {
+        self.nodes
+            .entry(person.id.clone())
+            .or_insert_with(|| person.clone());
+    }
+
+    fn add_edge(&mut self, source: &Person, target: &Person) {
+        self.add_node(source);
+        self.add_node(target);
+        self.add_edge_ids(&source.id, &target.id);
+    }
+
+    fn add_edge_ids(&mut self, source: &str, target: &str) {
+        let key = if self.kind.is_directed() || source <= target {
+            (source.to_owned(), target.to_owned())
+        } else {
+            (target.to_owned(), source.to_owned())
+        };
+        *self.edges.entry(key).or_insert(0) += 1;
+    }
+
+    fn finish(self) -> CollaborationGraph {
+        CollaborationGraph {
+            kind: self.kind,
+            directed: self.kind.is_directed(),
+            nodes: self.nodes.into_values().collect(),
+            edges: self
+                .edges
+                .into_iter()
+                .map(|((source, target), weight)| GraphEdge {
+                    source,
+                    target,
+                    weight,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// People credited in `Reviewed-by`, `Acked-by` and `Tested-by` trailers.
+fn reviewers(message: &str) -> Vec<Identity> {
+    trailers(message)
+        .into_iter()
+        .filter(|(token, _)| {
+            ["reviewed-by", "acked-by", "tested-by"]
+                .iter()
+                .any(|t| token.eq_ignore_ascii_case(t))
+        })
+        .map(|(_, value)| Identity::parse(&value))
+        .collect()
+}
+
+fn label(person: &Person) -> &str {
+    person.name.as_ref().unwrap_or(&person.id)
+}
+
+pub(crate) fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{Oid, Signature};
+
+    fn commit(
+        repo: &Repository,
+        parent: Option<Oid>,
+        author: &str,
+        file: &str,
+        message: &str,
+    ) -> Result<Oid> {
+        let sig = Signature::now(author, &format!("{}@example.com", author.to_lowercase()))?;
+        let parent = match parent {
+            Some(parent) => Some(repo.find_commit(parent)?),
+            None => None,
+        };
+        let mut builder =
+            repo.treebuilder(parent.as_ref().map(|p| p.tree()).transpose()?.as_ref())?;
+        builder.insert(file, repo.blob(message.as_bytes())?, 0o100644)?;
+        let tree = repo.find_tree(builder.write()?)?;
+        let parents: Vec<_> = parent.iter().collect();
+        Ok(repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents)?)
+    }
+
+    #[test]
+    fn test_from_repository() -> Result<()> {
+        let tmp_dir = tempdir::TempDir::new("graph")?;
+        let repo = Repository::init(tmp_dir.path())?;
+        let first = commit(&repo, None, "Alice", "a.rs", "Add a")?;
+        let second = commit(
+            &repo,
+            Some(first),
+            "Bob",
+            "a.rs",
+            "Change a\n\nReviewed-by: Alice <alice@example.com>",
+        )?;
+        commit(&repo, Some(second), "Carol", "c.rs", "Add c")?;
+        let config = Config::default();
+
+        let graph = CollaborationGraph::from_repository(&repo, &config, GraphKind::CoChange, None)?;
+        assert_eq!(graph.nodes.len(), 3);
+        assert_eq!(graph.edges.len(), 1);
+        assert_eq!(graph.edges[0].weight, 1);
+        assert!(graph.to_dot().contains("\" -- \""));
+
+        let graph =
+            CollaborationGraph::from_repository(&repo, &config, GraphKind::ReviewNetwork, None)?;
+        assert_eq!(graph.nodes.len(), 2);
+        assert_eq!(graph.edges[0].source, "Alice <alice@example.com>");
+        assert_eq!(graph.edges[0].target, "Bob <bob@example.com>");
+        assert!(graph.to_graphml().contains("edgedefault=\"directed\""));
+        Ok(())
+    }
+}
@@ -0,0 +1,209 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+use chrono::prelude::*;
+use chrono::DateTime;
+
+use crate::analyzed_commit::AnalyzedCommit;
+use codealong_model::{Event, Person};
+
+/// Activity on each day of a year, either of a person across every repo or
+/// of a repo across every person, from which a contribution heatmap can be
+/// rendered without aggregating events. The counts of each day are at the
+/// position of the day in the year, starting from 0 for January 1st.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Heatmap {
+    pub id: String,
+    pub person: Option<Person>,
+    pub repo: Option<String>,
+    pub year: i32,
+    pub year_start: DateTime<Utc>,
+    pub commits: Vec<u64>,
+    /// Lines added and deleted by the commits of each day.
+    pub churn: Vec<u64>,
+    pub reviews: Vec<u64>,
+    /// Number of days with any activity.
+    pub active_days: u64,
+}
+
+impl Heatmap {
+    fn new(person: Option<Person>, repo: Option<String>, year: i32) -> Heatmap {
+        let days = if NaiveDate::from_ymd_opt(year, 2, 29).is_some() {
+            366
+        } else {
+            365
+        };
+        let id = match (&person, &repo) {
+            (Some(person), _) => format!("person-{}-{}", person.id, year),
+            (None, repo) => format!("repo-{}-{}", repo.as_deref().unwrap_or_default(), year),
+        };
+        Heatmap {
+            id,
+            person,
+            repo,
+            year,
+            year_start: Utc.ymd(year, 1, 1).and_hms(0, 0, 0),
+            commits: vec![0; days],
+            churn: vec![0; days],
+            reviews: vec![0; days],
+            active_days: 0,
+        }
+    }
+
+    fn update(&mut self) {
+        self.active_days = (0..self.commits.len())
+            .filter(|&day| self.commits[day] > 0 || self.reviews[day] > 0)
+            .count() as u64;
+    }
+}
+
+impl Event for Heatmap {
+    fn timestamp(&self) -> &DateTime<Utc> {
+        &self.year_start
+    }
+
+    fn event_type(&self) -> &str {
+        "heatmap"
+    }
+
+    fn id(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.id)
+    }
+
+    fn tags(&self) -> HashSet<String> {
+        let mut tags = HashSet::new();
+        tags.insert(
+            if self.person.is_some() {
+                "person"
+            } else {
+                "repo"
+            }
+            .to_owned(),
+        );
+        tags
+    }
+}
+
+/// Accumulates analyzed commits and reviews into the heatmaps of their
+/// authors and of their repos.
+#[derive(Debug, Default)]
+pub struct Heatmaps {
+    /// Heatmaps by person id or repo name, and year.
+    heatmaps: HashMap<(Option<String>, Option<String>, i32), Heatmap>,
+}
+
+impl Heatmaps {
+    pub fn add_commit(&mut self, commit: &AnalyzedCommit) {
+        let repo = commit.repo.as_ref().map(|repo| repo.name());
+        let day = commit.authored_at.ordinal0() as usize;
+        let churn = commit.diff.line_stats.added + commit.diff.line_stats.deleted;
+        for (person, repo) in owners(commit.normalized_author.as_ref(), repo) {
+            let heatmap = self.heatmap(person, repo, commit.authored_at.year());
+            heatmap.commits[day] += 1;
+            heatmap.churn[day] += churn;
+        }
+    }
+
+    /// Records that `reviewer` submitted a review on a pull request of `repo`
+    /// at `timestamp`.
+    pub fn add_review(&mut self, reviewer: &Person, repo: Option<&str>, timestamp: &DateTime<Utc>) {
+        for (person, repo) in owners(Some(reviewer), repo) {
+            self.heatmap(person, repo, timestamp.year()).reviews[timestamp.ordinal0() as usize] +=
+                1;
+        }
+    }
+
+    pub fn finish(self) -> Vec<Heatmap> {
+        let mut res: Vec<Heatmap> = self
+            .heatmaps
+            .into_values()
+            .map(|mut heatmap| {
+                heatmap.update();
+                heatmap
+            })
+            .collect();
+        res.sort_by(|a, b| a.id.cmp(&b.id));
+        res
+    }
+
+    fn heatmap(&mut self, person: Option<&Person>, repo: Option<&str>, year: i32) -> &mut Heatmap {
+        let key = (
+            person.map(|person| person.id.clone()),
+            repo.map(|repo| repo.to_owned()),
+            year,
+        );
+        self.heatmaps.entry(key).or_insert_with(|| {
+            Heatmap::new(person.cloned(), repo.map(|repo| repo.to_owned()), year)
+        })
+    }
+}
+
+/// The heatmaps an event counts towards: that of the person, and that of the
+/// repo, of those which are known.
+fn owners<'a>(
+    person: Option<&'a Person>,
+    repo: Option<&'a str>,
+) -> Vec<(Option<&'a Person>, Option<&'a str>)> {
+    let mut res = vec![];
+    if person.is_some() {
+        res.push((person, None));
+    }
+    if repo.is_some() {
+        res.push((None, repo));
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo_info::PartialRepoInfo;
+    use git2::{Oid, Repository};
+
+    #[test]
+    fn test_heatmaps() {
+        let repo = Repository::open("./fixtures/repos/simple").unwrap();
+        let commit = repo
+            .find_commit(Oid::from_str("86d242301830075e93ff039a4d1e88673a4a3020").unwrap())
+            .unwrap();
+        let alice = Person {
+            id: "alice".to_owned(),
+            name: None,
+            email: None,
+            github_login: None,
+            teams: vec![],
+        };
+        let mut heatmaps = Heatmaps::default();
+        for (month, day) in &[(2, 1), (2, 1), (3, 2)] {
+            let mut analyzed = AnalyzedCommit::new(&commit);
+            analyzed.authored_at = Utc.ymd(2020, *month, *day).and_hms(12, 0, 0);
+            analyzed.normalized_author = Some(alice.clone());
+            analyzed.repo = Some(PartialRepoInfo::new("simple", false, false));
+            analyzed.diff.line_stats.added = 3;
+            analyzed.diff.line_stats.deleted = 1;
+            heatmaps.add_commit(&analyzed);
+        }
+        heatmaps.add_review(&alice, Some("other"), &Utc.ymd(2021, 1, 1).and_hms(9, 0, 0));
+        let heatmaps = heatmaps.finish();
+        let ids: Vec<&str> = heatmaps.iter().map(|h| h.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                "person-alice-2020",
+                "person-alice-2021",
+                "repo-other-2021",
+                "repo-simple-2020"
+            ]
+        );
+        let alice_2020 = &heatmaps[0];
+        assert_eq!(alice_2020.commits.len(), 366);
+        assert_eq!(alice_2020.commits[31], 2);
+        assert_eq!(alice_2020.churn[31], 8);
+        assert_eq!(alice_2020.commits[61], 1);
+        assert_eq!(alice_2020.active_days, 2);
+        assert_eq!(heatmaps[1].commits.len(), 365);
+        assert_eq!(heatmaps[1].reviews[0], 1);
+        assert_eq!(heatmaps[2].person, None);
+        assert_eq!(heatmaps[3].commits[31], 2);
+    }
+}
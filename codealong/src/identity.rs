@@ -1,7 +1,7 @@
 use regex::Regex;
 use std::fmt;
 
-use crate::person::Person;
+use codealong_model::Person;
 
 /// Simple wrapper for Name <Email> strings
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -74,8 +74,10 @@ impl Default for Identity {
 impl<'a> From<git2::Signature<'a>> for Identity {
     fn from(sig: git2::Signature<'a>) -> Self {
         Identity {
-            name: sig.name().map(|s| s.to_owned()),
-            email: sig.email().map(|s| s.to_owned()),
+            // names which are not valid UTF-8 are kept, with the invalid
+            // sequences replaced
+            name: Some(String::from_utf8_lossy(sig.name_bytes()).into_owned()),
+            email: Some(String::from_utf8_lossy(sig.email_bytes()).into_owned()),
         }
     }
 }
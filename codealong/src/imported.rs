@@ -0,0 +1,127 @@
+use regex::Regex;
+
+use crate::identity::Identity;
+
+/// Version control system a commit was converted from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportedVcs {
+    Svn,
+    Perforce,
+}
+
+/// Origin of a commit converted by `git-svn` or `git-p4`, as recorded in its
+/// message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImportedRevision {
+    pub vcs: ImportedVcs,
+    /// Svn revision or Perforce changelist number.
+    pub revision: u64,
+    /// Svn URL or Perforce depot path.
+    pub path: String,
+    /// Svn repository UUID.
+    pub repository_uuid: Option<String>,
+}
+
+impl ImportedRevision {
+    /// Parses a `git-svn-id: <url>@<rev> <uuid>` line or a
+    /// `[git-p4: depot-paths = "<path>": change = <change>]` line from a
+    /// commit message.
+    pub fn parse(message: &str) -> Option<ImportedRevision> {
+        lazy_static! {
+            static ref SVN_REGEX: Regex =
+                Regex::new(r"(?m)^\s*git-svn-id:\s*(\S+)@(\d+)(?:\s+(\S+))?\s*$").unwrap();
+            static ref P4_REGEX: Regex =
+                Regex::new(r#"\[git-p4:\s*depot-paths\s*=\s*"([^"]*)":\s*change\s*=\s*(\d+)"#)
+                    .unwrap();
+        }
+        if let Some(c) = SVN_REGEX.captures(message) {
+            Some(ImportedRevision {
+                vcs: ImportedVcs::Svn,
+                revision: c[2].parse().ok()?,
+                path: c[1].to_owned(),
+                repository_uuid: c.get(3).map(|m| m.as_str().to_owned()),
+            })
+        } else if let Some(c) = P4_REGEX.captures(message) {
+            Some(ImportedRevision {
+                vcs: ImportedVcs::Perforce,
+                revision: c[2].parse().ok()?,
+                path: c[1].to_owned(),
+                repository_uuid: None,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// The svn or Perforce username of `identity` when the converter had no
+    /// mapping for the user and synthesized the identity from it.
+    ///
+    /// `git-svn` synthesizes `user <user@repository-uuid>` and `git-p4`
+    /// synthesizes `user <a@b>`.
+    pub fn username(&self, identity: &Identity) -> Option<String> {
+        let email = identity.email.as_ref()?;
+        let at = email.find('@')?;
+        match self.vcs {
+            ImportedVcs::Svn => {
+                if self.repository_uuid.as_deref() == Some(&email[at + 1..]) {
+                    Some(email[..at].to_owned())
+                } else {
+                    None
+                }
+            }
+            ImportedVcs::Perforce => {
+                if email == "a@b" {
+                    identity.name.clone()
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UUID: &str = "6e21b7ba-4c2a-0410-9a1b-a2c6ab3ae0b3";
+
+    #[test]
+    fn test_parse_svn() {
+        let message = format!(
+            "Fix the build\n\ngit-svn-id: https://svn.example.com/repo/trunk@1234 {}\n",
+            UUID
+        );
+        let imported = ImportedRevision::parse(&message).unwrap();
+        assert_eq!(imported.vcs, ImportedVcs::Svn);
+        assert_eq!(imported.revision, 1234);
+        assert_eq!(imported.path, "https://svn.example.com/repo/trunk");
+        assert_eq!(
+            imported.username(&Identity::parse(&format!("jdoe <jdoe@{}>", UUID))),
+            Some("jdoe".to_owned())
+        );
+        assert_eq!(
+            imported.username(&Identity::parse("Jane Doe <jane@example.com>")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_p4() {
+        let message = "Fix the build\n\n[git-p4: depot-paths = \"//depot/proj/\": change = 5678]";
+        let imported = ImportedRevision::parse(message).unwrap();
+        assert_eq!(imported.vcs, ImportedVcs::Perforce);
+        assert_eq!(imported.revision, 5678);
+        assert_eq!(imported.path, "//depot/proj/");
+        assert_eq!(
+            imported.username(&Identity::parse("jdoe <a@b>")),
+            Some("jdoe".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_parse_native() {
+        assert_eq!(ImportedRevision::parse("Fix the build"), None);
+    }
+}
@@ -0,0 +1,194 @@
+use regex::Regex;
+
+/// A dependency of a source file found by a lightweight, regex based scan of
+/// its import statements.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Import {
+    /// A path relative to the directory of the importing file, e.g.
+    /// `../util` in JavaScript or `"util.h"` in C.
+    Relative(String),
+    /// Segments of a module path, e.g. `["com", "example", "util"]`, which
+    /// is resolved by looking for a directory with the same trailing path.
+    Module(Vec<String>),
+    /// Segments of a path relative to the root of the crate, i.e. its `src`
+    /// directory, for Rust `crate::` paths.
+    Crate(Vec<String>),
+}
+
+lazy_static! {
+    static ref RUST_REGEX: Regex =
+        Regex::new(r"(?m)^\s*(?:pub(?:\([^)]*\))?\s+)?use\s+((?:crate|super|self)(?:::\w+)+)").unwrap();
+    static ref PYTHON_REGEX: Regex =
+        Regex::new(r"(?m)^\s*(?:from\s+(\.*[\w.]*)\s+import|import\s+([\w.]+))").unwrap();
+    static ref JS_REGEX: Regex = Regex::new(
+        r#"(?m)(?:^\s*import\s[^'"]*?from\s*|^\s*import\s*|require\(\s*|import\(\s*)['"]([^'"]+)['"]"#
+    )
+    .unwrap();
+    static ref GO_REGEX: Regex = Regex::new(r#"(?m)^\s*(?:import\s+)?(?:\w+\s+)?"([\w./-]+)"\s*$"#).unwrap();
+    static ref JAVA_REGEX: Regex =
+        Regex::new(r"(?m)^\s*import\s+(?:static\s+)?([\w.]+?)(?:\.\*)?\s*;?\s*$").unwrap();
+    static ref C_REGEX: Regex = Regex::new(r#"(?m)^\s*#\s*include\s+"([^"]+)""#).unwrap();
+    static ref RUBY_REGEX: Regex =
+        Regex::new(r#"(?m)^\s*require(_relative)?\s*\(?\s*['"]([^'"]+)['"]"#).unwrap();
+}
+
+/// Parses the imports of the file at `path`, using its extension to pick the
+/// language. Unknown languages have no imports.
+pub fn parse_imports(path: &str, content: &str) -> Vec<Import> {
+    let extension = path.rsplit('.').next().unwrap_or("");
+    match extension {
+        "rs" => RUST_REGEX
+            .captures_iter(content)
+            .map(|c| rust_import(&c[1]))
+            .collect(),
+        "py" => PYTHON_REGEX
+            .captures_iter(content)
+            .filter_map(|c| c.get(1).or_else(|| c.get(2)))
+            .map(|m| python_import(m.as_str()))
+            .collect(),
+        "js" | "jsx" | "ts" | "tsx" | "mjs" => JS_REGEX
+            .captures_iter(content)
+            .filter(|c| c[1].starts_with('.'))
+            .map(|c| Import::Relative(c[1].to_owned()))
+            .collect(),
+        "go" => go_imports(content),
+        "java" | "kt" | "scala" => JAVA_REGEX
+            .captures_iter(content)
+            .map(|c| Import::Module(c[1].split('.').map(str::to_owned).collect()))
+            .collect(),
+        "c" | "h" | "cc" | "cpp" | "cxx" | "hpp" | "hh" => C_REGEX
+            .captures_iter(content)
+            .map(|c| Import::Relative(c[1].to_owned()))
+            .collect(),
+        "rb" => RUBY_REGEX
+            .captures_iter(content)
+            .map(|c| {
+                if c.get(1).is_some() {
+                    Import::Relative(c[2].to_owned())
+                } else {
+                    Import::Module(c[2].split('/').map(str::to_owned).collect())
+                }
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+fn rust_import(path: &str) -> Import {
+    let mut segments = path.split("::");
+    let relative = match segments.next() {
+        Some("super") => "..".to_owned(),
+        Some("self") => ".".to_owned(),
+        _ => return Import::Crate(path.split("::").skip(1).map(str::to_owned).collect()),
+    };
+    let rest: Vec<&str> = segments.collect();
+    Import::Relative(format!("{}/{}", relative, rest.join("/")))
+}
+
+fn python_import(module: &str) -> Import {
+    let dots = module.chars().take_while(|c| *c == '.').count();
+    let rest = module[dots..].replace('.', "/");
+    if dots == 0 {
+        Import::Module(rest.split('/').map(str::to_owned).collect())
+    } else {
+        let up = vec![".."; dots - 1];
+        let mut path = if up.is_empty() {
+            ".".to_owned()
+        } else {
+            up.join("/")
+        };
+        if !rest.is_empty() {
+            path = format!("{}/{}", path, rest);
+        }
+        Import::Relative(path)
+    }
+}
+
+/// Go imports are either single line or grouped in an `import (...)` block.
+fn go_imports(content: &str) -> Vec<Import> {
+    let mut imports = vec![];
+    let mut in_block = false;
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("import (") {
+            in_block = true;
+            continue;
+        }
+        if in_block && trimmed.starts_with(')') {
+            in_block = false;
+            continue;
+        }
+        if in_block || trimmed.starts_with("import ") {
+            if let Some(c) = GO_REGEX.captures(line) {
+                imports.push(Import::Module(c[1].split('/').map(str::to_owned).collect()));
+            }
+        }
+    }
+    imports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(path: &str) -> Import {
+        Import::Module(path.split('/').map(str::to_owned).collect())
+    }
+
+    #[test]
+    fn test_parse_imports() {
+        assert_eq!(
+            parse_imports(
+                "src/lib.rs",
+                "use crate::config::Config;\npub use super::util;\nuse std::fs;\n"
+            ),
+            vec![
+                Import::Crate(vec!["config".to_owned(), "Config".to_owned()]),
+                Import::Relative("../util".to_owned())
+            ]
+        );
+        assert_eq!(
+            parse_imports(
+                "a/b.py",
+                "import os.path\nfrom ..util import x\nfrom . import y\n"
+            ),
+            vec![
+                module("os/path"),
+                Import::Relative("../util".to_owned()),
+                Import::Relative(".".to_owned())
+            ]
+        );
+        assert_eq!(
+            parse_imports(
+                "web/app.ts",
+                "import { a } from './lib/a';\nimport React from 'react';\nconst b = require('../b');\n"
+            ),
+            vec![
+                Import::Relative("./lib/a".to_owned()),
+                Import::Relative("../b".to_owned())
+            ]
+        );
+        assert_eq!(
+            parse_imports(
+                "main.go",
+                "package main\n\nimport (\n\t\"fmt\"\n\tlog \"github.com/org/repo/log\"\n)\n"
+            ),
+            vec![module("fmt"), module("github.com/org/repo/log")]
+        );
+        assert_eq!(
+            parse_imports(
+                "A.java",
+                "import com.example.util.*;\nimport static org.Foo.bar;\n"
+            ),
+            vec![module("com/example/util"), module("org/Foo/bar")]
+        );
+        assert_eq!(
+            parse_imports("x.c", "#include <stdio.h>\n#include \"../util/util.h\"\n"),
+            vec![Import::Relative("../util/util.h".to_owned())]
+        );
+        assert_eq!(
+            parse_imports("x.rb", "require 'json'\nrequire_relative '../lib/x'\n"),
+            vec![module("json"), Import::Relative("../lib/x".to_owned())]
+        );
+    }
+}
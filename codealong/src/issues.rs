@@ -0,0 +1,127 @@
+use regex::Regex;
+
+use crate::error::*;
+
+/// Which references to tickets and issues are extracted from commit messages
+/// and from the titles and bodies of pull requests, so that engineering
+/// activity can be joined to planning tools.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IssueReferences {
+    /// Whether Jira keys, e.g. `ABC-123`, are extracted.
+    #[serde(default = "IssueReferences::default_enabled")]
+    pub jira: bool,
+
+    /// Keys of the Jira projects whose issues are extracted, e.g. `ABC`.
+    /// Keys of any project are extracted if empty.
+    #[serde(default)]
+    pub jira_projects: Vec<String>,
+
+    /// Whether Github references, e.g. `#123` or `octocat/hello#123`, are
+    /// extracted.
+    #[serde(default = "IssueReferences::default_enabled")]
+    pub github: bool,
+
+    /// Additional regexes, extracting their first capture group if they have
+    /// one, or else the whole match.
+    #[serde(default)]
+    pub patterns: Vec<String>,
+}
+
+impl Default for IssueReferences {
+    fn default() -> IssueReferences {
+        IssueReferences {
+            jira: true,
+            jira_projects: vec![],
+            github: true,
+            patterns: vec![],
+        }
+    }
+}
+
+impl IssueReferences {
+    fn default_enabled() -> bool {
+        true
+    }
+
+    pub fn merge(&mut self, other: IssueReferences) {
+        self.jira_projects.extend(other.jira_projects);
+        self.patterns.extend(other.patterns);
+    }
+
+    /// The distinct references in `text`, in the order they first appear.
+    pub fn extract(&self, text: &str) -> Result<Vec<String>> {
+        lazy_static! {
+            static ref JIRA_REGEX: Regex =
+                Regex::new(r"\b([A-Z][A-Z0-9_]+)-([1-9][0-9]*)\b").unwrap();
+            static ref GITHUB_REGEX: Regex =
+                Regex::new(r"(?:^|[^\w/#&])((?:[\w.-]+/[\w.-]+)?#[1-9][0-9]*)\b").unwrap();
+        }
+        // matches are sorted by where they start so that references keep
+        // their order across extractors
+        let mut found: Vec<(usize, String)> = vec![];
+        if self.jira {
+            for captures in JIRA_REGEX.captures_iter(text) {
+                if self.jira_projects.is_empty()
+                    || self.jira_projects.iter().any(|p| *p == captures[1])
+                {
+                    let whole = captures.get(0).unwrap();
+                    found.push((whole.start(), whole.as_str().to_owned()));
+                }
+            }
+        }
+        if self.github {
+            for captures in GITHUB_REGEX.captures_iter(text) {
+                let reference = captures.get(1).unwrap();
+                found.push((reference.start(), reference.as_str().to_owned()));
+            }
+        }
+        for pattern in &self.patterns {
+            for captures in Regex::new(pattern)?.captures_iter(text) {
+                let reference = captures.get(1).or_else(|| captures.get(0)).unwrap();
+                found.push((reference.start(), reference.as_str().to_owned()));
+            }
+        }
+        found.sort_by_key(|(start, _)| *start);
+        let mut res: Vec<String> = vec![];
+        for (_, reference) in found {
+            if !res.contains(&reference) {
+                res.push(reference);
+            }
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract() -> Result<()> {
+        let references = IssueReferences::default();
+        assert_eq!(
+            references.extract(
+                "ABC-12: fix crash (#34)\n\nSee octocat/hello#5, ABC-12 and &#39; #0.\n\nRefs: OPS-7"
+            )?,
+            vec!["ABC-12", "#34", "octocat/hello#5", "OPS-7"]
+        );
+
+        let references = IssueReferences {
+            jira_projects: vec!["OPS".to_owned()],
+            github: false,
+            patterns: vec![r"RT\s*(\d+)".to_owned(), r"INC\d+".to_owned()],
+            ..Default::default()
+        };
+        assert_eq!(
+            references.extract("ABC-12 OPS-7 #34 RT 99 INC0042")?,
+            vec!["OPS-7", "99", "INC0042"]
+        );
+
+        let references = IssueReferences {
+            patterns: vec!["(".to_owned()],
+            ..Default::default()
+        };
+        assert!(references.extract("ABC-12").is_err());
+        Ok(())
+    }
+}
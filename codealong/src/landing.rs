@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+
+use git2::{Commit, Oid, Repository};
+use regex::Regex;
+
+use crate::analyzed_commit::AnalyzedCommit;
+use crate::error::Result;
+use crate::text::{commit_message, commit_summary};
+
+/// Seconds between authoring and committing beyond which a commit on the
+/// first parent history is taken to have been rebased onto it.
+const REBASE_THRESHOLD: i64 = 60;
+
+/// How a commit landed on the branch it was analyzed against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LandedVia {
+    /// A merge commit on the first parent history of the branch, or a commit
+    /// of a branch it merged.
+    Merge,
+    /// A commit squashing the commits of a branch, as made when squash
+    /// merging a pull request.
+    Squash,
+    /// A commit committed well after it was authored, or by someone else, as
+    /// when rebasing a branch onto the target branch.
+    Rebase,
+    /// A commit made on the branch itself.
+    Direct,
+}
+
+/// How each commit reachable from a branch landed on it, found by walking
+/// the first parent history of the branch and the commits each of its merges
+/// brought in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Landings {
+    /// Short name of the branch, e.g. `main`.
+    pub branch: String,
+    landings: HashMap<Oid, LandedVia>,
+}
+
+impl Landings {
+    /// Walks the history of `refname`, e.g. `refs/remotes/origin/main`.
+    pub fn walk(repo: &Repository, refname: &str) -> Result<Landings> {
+        let mut landings = HashMap::new();
+        let mut revwalk = repo.revwalk()?;
+        revwalk.simplify_first_parent();
+        revwalk.push_ref(refname)?;
+        for oid in revwalk {
+            let commit = repo.find_commit(oid?)?;
+            if commit.parent_count() < 2 {
+                landings.insert(commit.id(), classify(&commit));
+                continue;
+            }
+            landings.insert(commit.id(), LandedVia::Merge);
+            let mut merged = repo.revwalk()?;
+            for parent in commit.parent_ids().skip(1) {
+                merged.push(parent)?;
+            }
+            merged.hide(commit.parent_id(0)?)?;
+            for oid in merged {
+                landings.entry(oid?).or_insert(LandedVia::Merge);
+            }
+        }
+        Ok(Landings {
+            branch: short_name(refname).to_owned(),
+            landings,
+        })
+    }
+
+    pub fn get(&self, id: &Oid) -> Option<LandedVia> {
+        self.landings.get(id).cloned()
+    }
+
+    /// Records on `commit` how it landed on the branch, if it did.
+    pub fn apply(&self, commit: &mut AnalyzedCommit) {
+        if let Some(landed_via) = Oid::from_str(&commit.id).ok().and_then(|id| self.get(&id)) {
+            commit.target_branch = Some(self.branch.clone());
+            commit.landed_via = Some(landed_via);
+        }
+    }
+}
+
+/// How a commit with a single parent on the first parent history landed.
+fn classify(commit: &Commit) -> LandedVia {
+    lazy_static! {
+        // as appended to the title of squash merged pull requests by Github,
+        // or of merge requests by GitLab
+        static ref PULL_REQUEST_SUFFIX: Regex = Regex::new(r"\((#|!)\d+\)$").unwrap();
+    }
+    if commit.parent_count() == 0 {
+        return LandedVia::Direct;
+    }
+    let squashed = commit_summary(commit)
+        .is_some_and(|summary| PULL_REQUEST_SUFFIX.is_match(summary.trim_end()))
+        || commit_message(commit).contains("Squashed commit of the following");
+    if squashed {
+        return LandedVia::Squash;
+    }
+    let (author, committer) = (commit.author(), commit.committer());
+    if committer.when().seconds() - author.when().seconds() > REBASE_THRESHOLD
+        || committer.email() != author.email()
+    {
+        LandedVia::Rebase
+    } else {
+        LandedVia::Direct
+    }
+}
+
+/// The name of a branch without the prefix of its refname.
+fn short_name(refname: &str) -> &str {
+    if let Some(name) = refname.strip_prefix("refs/heads/") {
+        return name;
+    }
+    refname
+        .strip_prefix("refs/remotes/")
+        .and_then(|name| name.split_once('/').map(|(_, name)| name))
+        .unwrap_or(refname)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{Signature, Time};
+
+    fn commit(
+        repo: &Repository,
+        update_ref: Option<&str>,
+        message: &str,
+        committed_after: i64,
+        parents: &[Oid],
+    ) -> Result<Oid> {
+        let author = Signature::new("Test", "test@example.com", &Time::new(1_000_000, 0))?;
+        let committer = Signature::new(
+            "Test",
+            "test@example.com",
+            &Time::new(1_000_000 + committed_after, 0),
+        )?;
+        let tree = repo.find_tree(repo.treebuilder(None)?.write()?)?;
+        let parents = parents
+            .iter()
+            .map(|oid| repo.find_commit(*oid))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let parents: Vec<&Commit> = parents.iter().collect();
+        Ok(repo.commit(update_ref, &author, &committer, message, &tree, &parents)?)
+    }
+
+    #[test]
+    fn test_walk() -> Result<()> {
+        let tmp_dir = tempdir::TempDir::new("landing")?;
+        let repo = Repository::init(tmp_dir.path())?;
+        let root = commit(&repo, None, "root", 0, &[])?;
+        let direct = commit(&repo, None, "direct", 0, &[root])?;
+        let feature = commit(&repo, None, "feature", 0, &[direct])?;
+        let merge = commit(&repo, None, "Merge feature", 0, &[direct, feature])?;
+        let squash = commit(&repo, None, "Add thing (#12)", 0, &[merge])?;
+        let rebased = commit(&repo, Some("refs/heads/main"), "rebased", 3600, &[squash])?;
+        let unmerged = commit(&repo, Some("refs/heads/wip"), "wip", 0, &[rebased])?;
+
+        let landings = Landings::walk(&repo, "refs/heads/main")?;
+        assert_eq!(landings.branch, "main");
+        assert_eq!(landings.get(&root), Some(LandedVia::Direct));
+        assert_eq!(landings.get(&direct), Some(LandedVia::Direct));
+        assert_eq!(landings.get(&feature), Some(LandedVia::Merge));
+        assert_eq!(landings.get(&merge), Some(LandedVia::Merge));
+        assert_eq!(landings.get(&squash), Some(LandedVia::Squash));
+        assert_eq!(landings.get(&rebased), Some(LandedVia::Rebase));
+        assert_eq!(landings.get(&unmerged), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_short_name() {
+        assert_eq!(short_name("refs/heads/main"), "main");
+        assert_eq!(short_name("refs/remotes/origin/release/1.0"), "release/1.0");
+        assert_eq!(short_name("HEAD"), "HEAD");
+    }
+}
@@ -0,0 +1,15 @@
+use git2::{Oid, Repository};
+
+use codealong_metrics::{detect_language, is_conclusive};
+
+/// Detects the language of a blob, reading it only when its path is not
+/// enough.
+pub(crate) fn detect_blob_language(repo: &Repository, path: &str, id: Oid) -> Option<&'static str> {
+    if is_conclusive(path) {
+        return detect_language(path, None);
+    }
+    match repo.find_blob(id) {
+        Ok(blob) => detect_language(path, Some(blob.content())),
+        Err(_) => detect_language(path, None),
+    }
+}
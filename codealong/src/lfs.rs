@@ -0,0 +1,134 @@
+use std::ops::{Add, AddAssign};
+
+use git2::{Oid, Repository};
+
+/// Pointers are tiny text files; anything larger is real content.
+const MAX_POINTER_SIZE: usize = 1024;
+
+const POINTER_VERSION: &str = "version https://git-lfs.github.com/spec/v1";
+
+/// A Git LFS pointer file, which stands in for the real content of the file
+/// in the git history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LfsPointer {
+    /// sha256 of the object in the LFS store.
+    pub oid: String,
+    /// Size in bytes of the real object.
+    pub size: u64,
+}
+
+impl LfsPointer {
+    pub fn parse(content: &[u8]) -> Option<LfsPointer> {
+        if content.len() > MAX_POINTER_SIZE {
+            return None;
+        }
+        let content = std::str::from_utf8(content).ok()?;
+        let mut lines = content.lines();
+        if lines.next()?.trim_end() != POINTER_VERSION {
+            return None;
+        }
+        let mut oid = None;
+        let mut size = None;
+        for line in lines {
+            if let Some(value) = line.strip_prefix("oid sha256:") {
+                oid = Some(value.trim().to_owned());
+            } else if let Some(value) = line.strip_prefix("size ") {
+                size = value.trim().parse().ok();
+            }
+        }
+        Some(LfsPointer {
+            oid: oid?,
+            size: size?,
+        })
+    }
+
+    /// Reads the pointer stored in blob `id`, if it is one.
+    pub fn from_blob(repo: &Repository, id: Oid) -> Option<LfsPointer> {
+        if id.is_zero() {
+            return None;
+        }
+        let blob = repo.find_blob(id).ok()?;
+        LfsPointer::parse(blob.content())
+    }
+}
+
+/// Size changes of files stored in Git LFS. Line based stats are meaningless
+/// for these files, so only the sizes recorded in their pointers are counted.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LfsStats {
+    pub files: u64,
+    pub bytes_added: u64,
+    pub bytes_deleted: u64,
+}
+
+impl LfsStats {
+    pub fn empty() -> LfsStats {
+        LfsStats::default()
+    }
+
+    /// Stats for a single file changing from `old` to `new`.
+    pub fn change(old: Option<&LfsPointer>, new: Option<&LfsPointer>) -> LfsStats {
+        LfsStats {
+            files: 1,
+            bytes_added: new.map(|p| p.size).unwrap_or(0),
+            bytes_deleted: old.map(|p| p.size).unwrap_or(0),
+        }
+    }
+}
+
+impl Add for LfsStats {
+    type Output = LfsStats;
+
+    fn add(self, other: LfsStats) -> Self::Output {
+        LfsStats {
+            files: self.files + other.files,
+            bytes_added: self.bytes_added + other.bytes_added,
+            bytes_deleted: self.bytes_deleted + other.bytes_deleted,
+        }
+    }
+}
+
+impl AddAssign for LfsStats {
+    fn add_assign(&mut self, other: LfsStats) {
+        self.files += other.files;
+        self.bytes_added += other.bytes_added;
+        self.bytes_deleted += other.bytes_deleted;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POINTER: &str = "version https://git-lfs.github.com/spec/v1\n\
+                           oid sha256:4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393\n\
+                           size 12345\n";
+
+    #[test]
+    fn test_parse() {
+        let pointer = LfsPointer::parse(POINTER.as_bytes()).unwrap();
+        assert_eq!(
+            pointer.oid,
+            "4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393"
+        );
+        assert_eq!(pointer.size, 12345);
+        assert_eq!(LfsPointer::parse(b"fn main() {}\n"), None);
+        assert_eq!(
+            LfsPointer::parse(b"version https://git-lfs.github.com/spec/v1\nsize 1\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_change() {
+        let old = LfsPointer::parse(POINTER.as_bytes()).unwrap();
+        let new = LfsPointer {
+            size: 20000,
+            ..old.clone()
+        };
+        let stats = LfsStats::change(Some(&old), Some(&new)) + LfsStats::change(None, Some(&new));
+        assert_eq!(stats.files, 2);
+        assert_eq!(stats.bytes_added, 40000);
+        assert_eq!(stats.bytes_deleted, 12345);
+    }
+}
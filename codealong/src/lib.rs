@@ -1,6 +1,11 @@
+extern crate base64;
 extern crate chrono;
+extern crate codealong_metrics;
+extern crate codealong_model;
+extern crate encoding_rs;
 #[macro_use]
 extern crate error_chain;
+extern crate futures;
 extern crate git2;
 extern crate glob;
 #[macro_use]
@@ -9,52 +14,203 @@ extern crate regex;
 #[macro_use]
 extern crate lazy_static;
 extern crate linked_hash_map;
+extern crate rand;
 #[macro_use]
 extern crate serde_derive;
 extern crate serde;
+extern crate serde_json;
 extern crate serde_yaml;
+extern crate sha2;
 #[macro_use]
 extern crate slog;
+extern crate unicode_normalization;
 
+mod analysis_run;
+mod analysis_state;
+mod analysis_stream;
 mod analyze_opts;
 mod analyzed_commit;
 mod analyzed_diff;
+mod badge;
+mod blame_cache;
+mod calendar;
+mod checkpoint;
+mod ci_report;
 mod commit_analyzer;
+mod commit_convention;
+mod commit_list;
+mod comparison;
+mod complexity;
+mod compliance;
 mod config;
 mod config_context;
+mod dco;
+mod default_branch;
+mod defect;
+mod dependency_graph;
 mod diff_analyzer;
+mod diff_sampling;
+mod effort;
 mod error;
-mod event;
 mod file_analyzer;
+mod forecast;
 mod git_blame;
+mod graph;
+mod heatmap;
 mod hunk_analyzer;
 mod identity;
+mod imported;
+mod imports;
+mod issues;
+mod landing;
+mod language;
+mod lfs;
 mod line_analyzer;
-mod person;
+mod lite_commit;
+mod mailmap;
+mod mbox;
+mod message_storage;
+mod metric_shift;
+mod mirrors;
+mod notes;
+mod offline;
+mod org_rollup;
+mod pairing;
+mod parallel_revwalk;
+mod partial_clone;
+mod patch_series;
+mod path_filter;
+mod pattern;
+mod period;
+mod person_snapshot;
+mod pipeline_stats;
+mod plugin;
+mod policy;
+mod preview;
+mod profile;
+mod progress;
+mod reconstructed_pull_request;
+mod refactor;
+mod release;
+mod rename_graph;
+mod replace_refs;
 mod repo;
 mod repo_analyzer;
 mod repo_config;
 mod repo_info;
+mod repo_lock;
+mod repo_metadata;
+mod repo_snapshot;
+mod report;
+mod reviewers;
+mod risk;
+mod sample_workspace;
+mod signature;
+mod slo;
+mod sparse;
+mod survey;
+mod survival;
+mod team_snapshot;
 pub mod test;
+mod text;
 mod utils;
-mod work_stats;
+mod work_type;
 mod workspace;
 mod workspace_config;
 
+pub use crate::analysis_run::{config_hash, AnalysisRun, RunRepo};
+pub use crate::analysis_state::{AnalysisState, RepoState};
+pub use crate::analysis_stream::{AnalysisStream, Emitter};
 pub use crate::analyze_opts::AnalyzeOpts;
 pub use crate::analyzed_commit::AnalyzedCommit;
-pub use crate::analyzed_diff::AnalyzedDiff;
-pub use crate::commit_analyzer::CommitAnalyzer;
-pub use crate::config::{AuthorConfig, Config, GlobConfig};
-pub use crate::diff_analyzer::DiffAnalyzer;
+pub use crate::analyzed_diff::{AnalyzedDiff, FileStats, SkippedReason};
+pub use crate::badge::{bus_factor, Badge};
+pub use crate::calendar::{Calendar, Leave};
+pub use crate::checkpoint::HistoryRewrite;
+pub use crate::ci_report::{CiReport, Hotspot, Owner};
+pub use crate::commit_analyzer::{CommitAnalyzer, OctopusMerges};
+pub use crate::commit_convention::{CommitConvention, ConventionalCommit};
+pub use crate::commit_list::CommitList;
+pub use crate::comparison::{Comparison, MetricDelta, Side};
+pub use crate::complexity::{ComplexityDelta, FileMetrics};
+pub use crate::compliance::{Compliance, RequiredTrailer};
+pub use crate::config::{
+    AuthorConfig, AuthorIdentities, AuthorPeriod, Config, EncryptionConfig, GlobConfig, LargeFiles,
+    SinkConfig, TeamConfig, Validity,
+};
+pub use crate::dco::{is_signed_off, DcoReport, DcoReports};
+pub use crate::default_branch::default_branch;
+pub use crate::defect::{DefectLinkMethod, DefectOrigin, IntroducedDefect};
+pub use crate::dependency_graph::{DependencyGraph, DirectoryDependencies};
+pub use crate::diff_analyzer::{DiffAnalyzer, RenameDetection};
+pub use crate::diff_sampling::{DiffSampling, DiffSnippet};
+pub use crate::effort::Effort;
 pub use crate::error::{Error, ErrorKind};
-pub use crate::event::Event;
+pub use crate::forecast::{forecast, Forecast, ForecastPercentile};
+pub use crate::git_blame::BlameEngine;
+pub use crate::graph::{CollaborationGraph, GraphEdge, GraphKind};
+pub use crate::heatmap::{Heatmap, Heatmaps};
 pub use crate::identity::Identity;
-pub use crate::person::Person;
-pub use crate::repo::Repo;
+pub use crate::imported::{ImportedRevision, ImportedVcs};
+pub use crate::imports::{parse_imports, Import};
+pub use crate::issues::IssueReferences;
+pub use crate::landing::{LandedVia, Landings};
+pub use crate::lfs::{LfsPointer, LfsStats};
+pub use crate::lite_commit::{LiteCommit, NumStat};
+pub use crate::mailmap::Mailmap;
+pub use crate::mbox::{read_mbox, read_mbox_from, MailMessage};
+pub use crate::message_storage::MessageStorage;
+pub use crate::metric_shift::{detect_shifts, detect_snapshot_shifts, MetricShift, ShiftDirection};
+pub use crate::mirrors::discover_mirrors;
+pub use crate::notes::Note;
+pub use crate::offline::{ensure_online, is_offline, set_offline};
+pub use crate::org_rollup::rollup_org;
+pub use crate::pairing::{PairingSession, PairingSessions};
+pub use crate::parallel_revwalk::{CommitAnalysis, ParallelRevwalk};
+pub use crate::partial_clone::{
+    discover, discover_compatible, fetch_missing_blobs, fetch_missing_blobs_for, promisor_remote,
+};
+pub use crate::patch_series::{patch_series, PatchReview, PatchSeries, PatchSubject, ReviewKind};
+pub use crate::pattern::Pattern;
+pub use crate::period::Period;
+pub use crate::person_snapshot::{PersonSnapshot, PersonSnapshots, WorkInProgress};
+pub use crate::pipeline_stats::{PipelineStats, Stage, StageSummary};
+pub use crate::plugin::{Hook, Plugin, PluginManifest, Plugins};
+pub use crate::policy::{Policy, PolicyChecker, PolicyViolation};
+pub use crate::preview::preview;
+pub use crate::profile::{Profiler, Span};
+pub use crate::progress::{NoProgress, Progress, BLAME_STAGE, DIFF_STAGE, FETCH_PAGE_STAGE};
+pub use crate::reconstructed_pull_request::{MergeMessage, MergeSource, ReconstructedPullRequest};
+pub use crate::release::{previous_release, Change, ChangeKind, Contributor, ReleaseSummary};
+pub use crate::rename_graph::{Rename, RenameGraph};
+pub use crate::replace_refs::ReplaceRefs;
+pub use crate::repo::{ArchivedRepos, Repo};
 pub use crate::repo_analyzer::{AnalyzedRevwalk, RepoAnalyzer};
 pub use crate::repo_config::RepoConfig;
-pub use crate::repo_info::RepoInfo;
+pub use crate::repo_info::{normalize_url, parse_remote_url, RepoInfo};
+pub use crate::repo_lock::{LockHolder, RepoLock, STALE_LOCK_HOURS};
+pub use crate::repo_metadata::{RepoMetadata, RepoMetadataSnapshot};
+pub use crate::repo_snapshot::{RepoSnapshot, RepoSnapshots};
+pub use crate::report::{Report, ReportRow};
+pub use crate::reviewers::{Codeowners, ReviewerCandidate, ReviewerSuggestions};
+pub use crate::risk::{Hotspots, Risk, RiskLevel};
+pub use crate::sample_workspace::generate_sample_workspace;
+pub use crate::signature::CommitSignature;
+pub use crate::slo::{Slo, SloBreach, SloMetric, SloSubject};
+pub use crate::survey::SurveyScores;
+pub use crate::survival::Survival;
+pub use crate::team_snapshot::{rollup_teams, TeamSnapshot};
+pub use crate::text::{
+    commit_message, commit_summary, decode, decode_encoded_words, truncate_graphemes,
+};
 pub use crate::utils::with_authentication;
+pub use crate::work_type::{detect_dependency_update, DependencyUpdate, WorkType};
 pub use crate::workspace::Workspace;
 pub use crate::workspace_config::{RepoEntry, WorkspaceConfig};
+pub use codealong_metrics::{detect_language, LanguageStats, LineStats, WorkStats};
+pub use codealong_model::{
+    metric_def, metric_versions, Envelope, Event, MetricDef, MetricVersions, Person, ReviewStats,
+    BUSINESS_DAYS_TO_RESOLVE, BUSINESS_TIME_TO_FIRST_REVIEW, BUSINESS_TIME_TO_RESOLVE,
+    COMPLIANCE_RATE, LIKELY_REFACTOR, LINES_CHANGED, METRICS, SIGNING_RATE, TIME_TO_FIRST_REVIEW,
+    TIME_TO_RESOLVE, WORK_IN_PROGRESS, WORK_STATS, WORK_TYPE,
+};
@@ -2,7 +2,7 @@ use git2::{Commit, DiffLine, Repository, Signature};
 
 use crate::error::Error;
 use crate::git_blame::GitBlame;
-use crate::work_stats::WorkStats;
+use codealong_metrics::WorkStats;
 
 pub struct LineAnalyzer<'a> {
     repo: &'a Repository,
@@ -0,0 +1,193 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::iter::FromIterator;
+
+use chrono::prelude::*;
+use chrono::DateTime;
+use git2::{Commit, DiffOptions, Patch, Repository};
+
+use crate::config::Config;
+use crate::error::*;
+use crate::identity::Identity;
+use crate::partial_clone::fetch_missing_blobs_for;
+use crate::repo_info::PartialRepoInfo;
+use crate::text::commit_summary;
+use crate::utils::convert_time;
+use codealong_metrics::LineStats;
+use codealong_model::{Event, Person};
+
+/// Lines added and deleted in a file, as listed by `git log --numstat`.
+/// Binary files count no lines.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NumStat {
+    pub path: String,
+    pub added: u64,
+    pub deleted: u64,
+    pub tags: Vec<String>,
+}
+
+/// A commit analyzed from its metadata and numstat alone, without blaming
+/// its diff, for throughput and activity metrics on histories too large to
+/// analyze in full. Merges are diffed against their first parent only.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LiteCommit {
+    pub id: String,
+    pub parents: Vec<String>,
+    pub summary: Option<String>,
+    pub author: Identity,
+    pub authored_at: DateTime<Utc>,
+    pub normalized_author: Person,
+    pub author_id: String,
+    pub committer: Identity,
+    pub committed_at: DateTime<Utc>,
+    pub repo: Option<PartialRepoInfo>,
+    pub line_stats: LineStats,
+    pub files_changed: usize,
+    pub files: Vec<NumStat>,
+}
+
+impl LiteCommit {
+    pub fn new(
+        repo: &Repository,
+        commit: &Commit,
+        paths: &[String],
+        config: &Config,
+    ) -> Result<LiteCommit> {
+        let author = Identity::from(commit.author());
+        let authored_at = convert_time(&commit.author().when());
+        let normalized_author = config.person_for_identity_at(&author, &authored_at);
+        let files = numstat(repo, commit, paths, config)?;
+        let line_stats = files.iter().fold(LineStats::empty(), |stats, file| {
+            stats
+                + LineStats {
+                    added: file.added,
+                    deleted: file.deleted,
+                    moved: 0,
+                }
+        });
+        Ok(LiteCommit {
+            id: commit.id().to_string(),
+            parents: commit.parent_ids().map(|id| id.to_string()).collect(),
+            summary: commit_summary(commit).and_then(|summary| config.stored_summary(&summary)),
+            author,
+            authored_at,
+            author_id: normalized_author.id.clone(),
+            normalized_author,
+            committer: Identity::from(commit.committer()),
+            committed_at: convert_time(&commit.committer().when()),
+            repo: None,
+            line_stats,
+            files_changed: files.len(),
+            files,
+        })
+    }
+}
+
+fn numstat(
+    repo: &Repository,
+    commit: &Commit,
+    paths: &[String],
+    config: &Config,
+) -> Result<Vec<NumStat>> {
+    let mut diff_opts = DiffOptions::new();
+    diff_opts.ignore_whitespace(true);
+    for path in paths {
+        diff_opts.pathspec(path);
+    }
+    let old_tree = match commit.parents().next() {
+        Some(parent) => Some(parent.tree()?),
+        None => None,
+    };
+    let new_tree = commit.tree()?;
+    let diff = repo.diff_tree_to_tree(old_tree.as_ref(), Some(&new_tree), Some(&mut diff_opts))?;
+    let path_filter = config.path_filter();
+    let included: Vec<usize> = diff
+        .deltas()
+        .enumerate()
+        .filter(|(_, delta)| path_filter.includes_delta(delta))
+        .map(|(i, _)| i)
+        .collect();
+    fetch_missing_blobs_for(
+        repo,
+        included.iter().filter_map(|&i| diff.get_delta(i)),
+        old_tree.as_ref(),
+        Some(&new_tree),
+    )?;
+    let mut res = vec![];
+    for i in included {
+        let patch = match Patch::from_diff(&diff, i)? {
+            Some(patch) => patch,
+            None => continue,
+        };
+        let delta = patch.delta();
+        let path = match delta.new_file().path().or_else(|| delta.old_file().path()) {
+            Some(path) => path.to_string_lossy().into_owned(),
+            None => continue,
+        };
+        let file_config = config.config_for_file_at(&path, &convert_time(&commit.author().when()));
+        if file_config.as_ref().is_some_and(|c| c.ignore()) {
+            continue;
+        }
+        let mut tags: Vec<String> = file_config
+            .map(|c| c.tags().into_iter().map(|t| t.to_owned()).collect())
+            .unwrap_or_default();
+        tags.sort();
+        let (_, added, deleted) = patch.line_stats()?;
+        res.push(NumStat {
+            path,
+            added: added as u64,
+            deleted: deleted as u64,
+            tags,
+        });
+    }
+    Ok(res)
+}
+
+impl Event for LiteCommit {
+    fn timestamp(&self) -> &DateTime<Utc> {
+        &self.authored_at
+    }
+
+    fn event_type(&self) -> &str {
+        "lite_commit"
+    }
+
+    fn id(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.id)
+    }
+
+    fn tags(&self) -> HashSet<String> {
+        HashSet::from_iter(self.files.iter().flat_map(|file| file.tags.iter().cloned()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Oid;
+
+    #[test]
+    fn test_lite_commit() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/simple")?;
+        let config = Config::default();
+        let commit =
+            repo.find_commit(Oid::from_str("86d242301830075e93ff039a4d1e88673a4a3020")?)?;
+        let lite = LiteCommit::new(&repo, &commit, &[], &config)?;
+        assert!(lite.parents.is_empty());
+        assert_eq!(lite.files_changed, 1);
+        assert_eq!(lite.files[0].path, "README.md");
+        assert_eq!(lite.line_stats.added, 1);
+        assert_eq!(lite.line_stats.deleted, 0);
+        assert_eq!(lite.author_id, lite.normalized_author.id);
+
+        // merges are diffed against their first parent
+        let merge = repo.find_commit(Oid::from_str("bf51d175af7e82fbd43cb8598b45aa1830890ff0")?)?;
+        let lite = LiteCommit::new(&repo, &merge, &[], &config)?;
+        assert_eq!(lite.parents.len(), 2);
+        assert_eq!(
+            lite.line_stats.added,
+            lite.files.iter().map(|f| f.added).sum::<u64>()
+        );
+        Ok(())
+    }
+}
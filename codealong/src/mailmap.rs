@@ -0,0 +1,248 @@
+use std::fmt;
+
+use git2::Repository;
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+
+use crate::error::*;
+use crate::identity::Identity;
+
+/// Maps the names and emails commits were made under to those of their
+/// authors, in the format of git's `.mailmap`:
+///
+/// ```text
+/// Proper Name <commit@email>
+/// <proper@email> <commit@email>
+/// Proper Name <proper@email> <commit@email>
+/// Proper Name <proper@email> Commit Name <commit@email>
+/// ```
+///
+/// In configs the entries are listed one per string.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Mailmap {
+    entries: Vec<MailmapEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct MailmapEntry {
+    proper_name: Option<String>,
+    proper_email: Option<String>,
+    commit_name: Option<String>,
+    commit_email: String,
+}
+
+impl Mailmap {
+    /// Parses a `.mailmap` file, skipping comments and malformed lines as git
+    /// does.
+    pub fn parse(s: &str) -> Mailmap {
+        Mailmap {
+            entries: s.lines().filter_map(MailmapEntry::parse).collect(),
+        }
+    }
+
+    /// The `.mailmap` at the root of the tree of `HEAD`, read from the object
+    /// database so that bare clones are supported.
+    pub fn from_repository(repo: &Repository) -> Result<Mailmap> {
+        let tree = match repo.head().and_then(|head| head.peel_to_tree()) {
+            Ok(tree) => tree,
+            // e.g. a freshly initialized repo
+            Err(_) => return Ok(Mailmap::default()),
+        };
+        match tree.get_path(std::path::Path::new(".mailmap")) {
+            Ok(entry) => {
+                let blob = entry.to_object(repo)?.peel_to_blob()?;
+                Ok(Mailmap::parse(&String::from_utf8_lossy(blob.content())))
+            }
+            Err(_) => Ok(Mailmap::default()),
+        }
+    }
+
+    /// Appends the entries of `other`, which take precedence over those
+    /// already present.
+    pub fn extend(&mut self, other: Mailmap) {
+        self.entries.extend(other.entries);
+    }
+
+    /// The identity `identity` maps to, or `identity` itself if no entry
+    /// matches. Emails and names are matched case-insensitively; entries
+    /// matching on both the name and the email win over those matching on
+    /// the email only, and later entries win over earlier ones.
+    pub fn resolve(&self, identity: &Identity) -> Identity {
+        let email = match identity.email {
+            Some(ref email) => email,
+            None => return identity.clone(),
+        };
+        let matches = |entry: &&MailmapEntry| entry.commit_email.eq_ignore_ascii_case(email);
+        let entry = self
+            .entries
+            .iter()
+            .rev()
+            .filter(matches)
+            .find(|entry| match (&entry.commit_name, &identity.name) {
+                (Some(commit_name), Some(name)) => commit_name.eq_ignore_ascii_case(name),
+                _ => false,
+            })
+            .or_else(|| {
+                self.entries
+                    .iter()
+                    .rev()
+                    .filter(matches)
+                    .find(|entry| entry.commit_name.is_none())
+            });
+        match entry {
+            Some(entry) => Identity {
+                name: entry.proper_name.clone().or_else(|| identity.name.clone()),
+                email: entry
+                    .proper_email
+                    .clone()
+                    .or_else(|| identity.email.clone()),
+            },
+            None => identity.clone(),
+        }
+    }
+
+    /// The identities mapped to `identity` by an entry, as listed in the
+    /// entries. Names are only known for entries which match on them.
+    pub fn sources(&self, identity: &Identity) -> Vec<Identity> {
+        self.entries
+            .iter()
+            .map(|entry| Identity {
+                name: entry.commit_name.clone(),
+                email: Some(entry.commit_email.clone()),
+            })
+            .filter(|source| &self.resolve(source) == identity && source != identity)
+            .collect()
+    }
+}
+
+impl MailmapEntry {
+    fn parse(line: &str) -> Option<MailmapEntry> {
+        let line = line.trim();
+        if line.starts_with('#') {
+            return None;
+        }
+        // alternating names, which may be empty, and bracketed emails
+        let mut names = vec![];
+        let mut emails = vec![];
+        let mut rest = line;
+        while let Some(start) = rest.find('<') {
+            let end = start + rest[start..].find('>')?;
+            names.push(non_empty(&rest[..start]));
+            emails.push(non_empty(&rest[start + 1..end]));
+            rest = &rest[end + 1..];
+        }
+        match (names.len(), emails.len()) {
+            (1, 1) => Some(MailmapEntry {
+                proper_name: names.remove(0),
+                proper_email: None,
+                commit_name: None,
+                commit_email: emails.remove(0)?,
+            }),
+            (2, 2) => Some(MailmapEntry {
+                proper_name: names.remove(0),
+                proper_email: emails.remove(0),
+                commit_name: names.remove(0),
+                commit_email: emails.remove(0)?,
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for MailmapEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ref name) = self.proper_name {
+            write!(f, "{} ", name)?;
+        }
+        if let Some(ref email) = self.proper_email {
+            write!(f, "<{}> ", email)?;
+        }
+        if let Some(ref name) = self.commit_name {
+            write!(f, "{} ", name)?;
+        }
+        write!(f, "<{}>", self.commit_email)
+    }
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    let s = s.trim();
+    if s.is_empty() {
+        None
+    } else {
+        Some(s.to_owned())
+    }
+}
+
+impl Serialize for Mailmap {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.entries.iter().map(|entry| entry.to_string()))
+    }
+}
+
+impl<'de> Deserialize<'de> for Mailmap {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Mailmap, D::Error> {
+        let lines = Vec::<String>::deserialize(deserializer)?;
+        Ok(Mailmap::parse(&lines.join("\n")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAILMAP: &str = "
+# the canonical identities
+Jane Doe <jane@example.com>
+<jane@example.com> <jdoe@old.example.com>
+Jane Doe <jane@example.com> Janey <JANE@laptop.local>
+Build Bot <bot@example.com> <ci@example.com>
+";
+
+    #[test]
+    fn test_resolve() {
+        let mailmap = Mailmap::parse(MAILMAP);
+        assert_eq!(mailmap.entries.len(), 4);
+        assert_eq!(
+            mailmap.resolve(&Identity::parse("jane <Jane@Example.com>")),
+            Identity::parse("Jane Doe <Jane@Example.com>")
+        );
+        assert_eq!(
+            mailmap.resolve(&Identity::parse("J. Doe <jdoe@old.example.com>")),
+            Identity::parse("J. Doe <jane@example.com>")
+        );
+        assert_eq!(
+            mailmap.resolve(&Identity::parse("janey <jane@laptop.local>")),
+            Identity::parse("Jane Doe <jane@example.com>")
+        );
+        assert_eq!(
+            mailmap.resolve(&Identity::parse("Someone <jane@laptop.local>")),
+            Identity::parse("Someone <jane@laptop.local>")
+        );
+        assert_eq!(
+            mailmap.resolve(&Identity::parse("jenkins <ci@example.com>")),
+            Identity::parse("Build Bot <bot@example.com>")
+        );
+    }
+
+    #[test]
+    fn test_sources() {
+        let mailmap = Mailmap::parse(MAILMAP);
+        assert_eq!(
+            mailmap.sources(&Identity::parse("Build Bot <bot@example.com>")),
+            vec![Identity::parse("<ci@example.com>")]
+        );
+    }
+
+    #[test]
+    fn test_serde() {
+        let mailmap: Mailmap = serde_yaml::from_str(
+            "- \"Jane Doe <jane@example.com> Janey <jane@laptop.local>\"\n- \"<jane@example.com> <jdoe@old.example.com>\"",
+        )
+        .unwrap();
+        assert_eq!(mailmap.entries.len(), 2);
+        let yaml = serde_yaml::to_string(&mailmap).unwrap();
+        assert_eq!(serde_yaml::from_str::<Mailmap>(&yaml).unwrap(), mailmap);
+    }
+}
@@ -0,0 +1,179 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+use chrono::prelude::*;
+use chrono::DateTime;
+
+use crate::error::Result;
+use crate::text::decode_encoded_words;
+
+/// A single message from an mbox archive. Only the headers needed to thread
+/// patch emails are retained.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MailMessage {
+    pub message_id: String,
+    pub in_reply_to: Option<String>,
+    pub references: Vec<String>,
+    pub from: String,
+    pub subject: String,
+    pub date: DateTime<Utc>,
+    pub body: String,
+}
+
+impl MailMessage {
+    /// The message id of the root of the thread this message belongs to.
+    pub fn thread_root(&self) -> &str {
+        self.references
+            .first()
+            .or(self.in_reply_to.as_ref())
+            .unwrap_or(&self.message_id)
+    }
+
+    /// Lines of the body which are not quoted from another message.
+    pub fn unquoted_lines(&self) -> impl Iterator<Item = &str> {
+        self.body
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.starts_with('>'))
+    }
+}
+
+/// Reads all messages from the mbox archive at `path`.
+pub fn read_mbox(path: &Path) -> Result<Vec<MailMessage>> {
+    read_mbox_from(File::open(path)?)
+}
+
+/// Reads all messages from an mbox archive. Messages without a parseable
+/// `Message-Id` or `Date` are skipped. Lines which are not valid UTF-8, e.g.
+/// the bodies of messages sent in a legacy charset, are decoded lossily
+/// rather than failing the whole archive.
+pub fn read_mbox_from<R: Read>(reader: R) -> Result<Vec<MailMessage>> {
+    let mut messages = vec![];
+    let mut lines: Vec<String> = vec![];
+    let mut reader = BufReader::new(reader);
+    let mut buf = vec![];
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            break;
+        }
+        let line = String::from_utf8_lossy(&buf);
+        let line = line.trim_end_matches(&['\n', '\r'][..]).to_owned();
+        if line.starts_with("From ") {
+            messages.extend(parse_message(&lines));
+            lines.clear();
+        } else {
+            lines.push(line);
+        }
+    }
+    messages.extend(parse_message(&lines));
+    Ok(messages)
+}
+
+fn parse_message(lines: &[String]) -> Option<MailMessage> {
+    let split = lines.iter().position(|line| line.is_empty())?;
+    let mut headers: Vec<(String, String)> = vec![];
+    for line in &lines[..split] {
+        if line.starts_with(' ') || line.starts_with('\t') {
+            if let Some(last) = headers.last_mut() {
+                last.1.push(' ');
+                last.1.push_str(line.trim());
+            }
+        } else if let Some(index) = line.find(':') {
+            headers.push((
+                line[..index].trim().to_lowercase(),
+                line[index + 1..].trim().to_owned(),
+            ));
+        }
+    }
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    };
+    let body = lines[split + 1..]
+        .iter()
+        .map(|line| unescape_from(line))
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some(MailMessage {
+        message_id: message_ids(header("message-id")?).pop()?,
+        in_reply_to: header("in-reply-to").and_then(|v| message_ids(v).pop()),
+        references: header("references").map(message_ids).unwrap_or_default(),
+        from: decode_encoded_words(header("from").unwrap_or("")),
+        subject: decode_encoded_words(header("subject").unwrap_or("")),
+        date: DateTime::parse_from_rfc2822(header("date")?)
+            .ok()?
+            .with_timezone(&Utc),
+        body,
+    })
+}
+
+/// Undoes mboxrd quoting of lines which begin with `From `.
+fn unescape_from(line: &str) -> &str {
+    if line.starts_with('>') && line.trim_start_matches('>').starts_with("From ") {
+        &line[1..]
+    } else {
+        line
+    }
+}
+
+fn message_ids(value: &str) -> Vec<String> {
+    value
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .map(|id| id.trim_matches(|c| c == '<' || c == '>'))
+        .filter(|id| !id.is_empty())
+        .map(|id| id.to_owned())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_mbox() {
+        let mbox = "From abc Mon Sep 17 00:00:00 2001\n\
+                    From: Jane Doe <jane@example.com>\n\
+                    Subject: [PATCH 1/2] foo: add\n the bar\n\
+                    Date: Mon, 16 Dec 2019 09:00:00 +0100\n\
+                    Message-Id: <1@example.com>\n\
+                    In-Reply-To: <0@example.com>\n\
+                    References: <0@example.com>\n\
+                    \n\
+                    Body\n\
+                    >From here\n\
+                    From def Mon Sep 17 00:00:00 2001\n\
+                    Subject: no id\n\
+                    \n\
+                    Body\n";
+        let messages = read_mbox_from(mbox.as_bytes()).unwrap();
+        assert_eq!(messages.len(), 1);
+        let message = &messages[0];
+        assert_eq!(message.subject, "[PATCH 1/2] foo: add the bar");
+        assert_eq!(message.date, Utc.ymd(2019, 12, 16).and_hms(8, 0, 0));
+        assert_eq!(message.thread_root(), "0@example.com");
+        assert_eq!(message.body, "Body\nFrom here");
+    }
+
+    #[test]
+    fn test_read_mbox_encoded() {
+        let mut mbox = b"From abc Mon Sep 17 00:00:00 2001\n\
+                    From: =?UTF-8?B?5bGx55Sw5aSq6YOO?= <taro@example.jp>\n\
+                    Subject: [PATCH] =?ISO-8859-1?Q?Caf=E9?=\n\
+                    Date: Mon, 16 Dec 2019 09:00:00 +0100\n\
+                    Message-Id: <1@example.jp>\n\
+                    \n\
+                    Caf"
+        .to_vec();
+        mbox.extend(b"\xe9\n");
+        let messages = read_mbox_from(&mbox[..]).unwrap();
+        assert_eq!(messages.len(), 1);
+        let message = &messages[0];
+        assert_eq!(message.from, "山田太郎 <taro@example.jp>");
+        assert_eq!(message.subject, "[PATCH] Café");
+        assert_eq!(message.body, "Caf\u{fffd}");
+    }
+}
@@ -0,0 +1,74 @@
+use sha2::{Digest, Sha256};
+
+use crate::text::truncate_graphemes;
+use crate::utils::hex;
+
+/// How free text written by people, i.e. commit summaries and pull request
+/// titles and bodies, is stored in emitted events. Some organizations do not
+/// allow exporting this content to analytics systems.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageStorage {
+    /// Store the text as is.
+    #[default]
+    Full,
+    /// Store at most this many user-perceived characters of the text.
+    Truncate(usize),
+    /// Store a SHA-256 of the text, which still allows matching identical
+    /// messages, e.g. reverts, without revealing them.
+    Hash,
+    /// Do not store the text at all.
+    Drop,
+}
+
+impl MessageStorage {
+    /// `text` as it should be stored, or `None` when it is dropped.
+    pub fn store(self, text: &str) -> Option<String> {
+        match self {
+            MessageStorage::Full => Some(text.to_owned()),
+            MessageStorage::Truncate(max) => Some(truncate_graphemes(text, max).into_owned()),
+            MessageStorage::Hash => {
+                Some(format!("sha256:{}", hex(&Sha256::digest(text.as_bytes()))))
+            }
+            MessageStorage::Drop => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_store() {
+        let text = "Fix the build on Windows";
+        assert_eq!(MessageStorage::Full.store(text), Some(text.to_owned()));
+        assert_eq!(
+            MessageStorage::Truncate(8).store(text),
+            Some("Fix the…".to_owned())
+        );
+        assert_eq!(
+            MessageStorage::Hash.store("abc"),
+            Some(
+                "sha256:ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+                    .to_owned()
+            )
+        );
+        assert_eq!(MessageStorage::Drop.store(text), None);
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let storage: Vec<MessageStorage> =
+            serde_yaml::from_str("- full\n- truncate: 72\n- hash\n- drop").unwrap();
+        assert_eq!(
+            storage,
+            vec![
+                MessageStorage::Full,
+                MessageStorage::Truncate(72),
+                MessageStorage::Hash,
+                MessageStorage::Drop,
+            ]
+        );
+    }
+}
@@ -0,0 +1,283 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
+
+use chrono::prelude::*;
+use chrono::DateTime;
+
+use crate::period::Period;
+use crate::person_snapshot::PersonSnapshot;
+use codealong_model::Event;
+
+/// Number of recent periods compared against the baseline.
+const CURRENT_PERIODS: usize = 4;
+
+/// Number of periods preceding the current window used as a baseline.
+const BASELINE_PERIODS: usize = 8;
+
+/// Minimum relative change between the baseline and current means.
+const MIN_RELATIVE_CHANGE: f64 = 0.25;
+
+/// Minimum change, in standard errors of the baseline, for a shift to be
+/// considered significant rather than noise.
+const MIN_STANDARD_ERRORS: f64 = 2.0;
+
+/// Scope used for metrics rolled up across all people.
+const ORG_SCOPE: &str = "org";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShiftDirection {
+    Increase,
+    Decrease,
+}
+
+/// A sustained change in a rollup metric, e.g. review latency doubling for a
+/// team over the last month.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricShift {
+    pub id: String,
+    pub metric: String,
+    /// The team the metric was rolled up for, or `org` for everyone.
+    pub scope: String,
+    pub direction: ShiftDirection,
+    /// Change relative to the baseline, e.g. 0.5 for a 50% increase.
+    pub magnitude: f64,
+    pub baseline: f64,
+    pub current: f64,
+    pub detected_at: DateTime<Utc>,
+}
+
+impl Event for MetricShift {
+    fn timestamp(&self) -> &DateTime<Utc> {
+        &self.detected_at
+    }
+
+    fn event_type(&self) -> &str {
+        "metric_shift"
+    }
+
+    fn id(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.id)
+    }
+
+    fn tags(&self) -> HashSet<String> {
+        let mut tags = HashSet::new();
+        tags.insert("heuristic".to_owned());
+        tags
+    }
+}
+
+/// Detects shifts in a series of rollups over `period`. `series` must contain
+/// one entry per consecutive period starting with the one containing `start`,
+/// with `None` for periods without data.
+pub fn detect_shifts(
+    metric: &str,
+    scope: &str,
+    period: Period,
+    start: &DateTime<Utc>,
+    series: &[Option<f64>],
+) -> Vec<MetricShift> {
+    let starts = period_starts(period, start, series.len());
+    let mut res = vec![];
+    let mut i = BASELINE_PERIODS;
+    while i + CURRENT_PERIODS <= series.len() {
+        let baseline: Vec<f64> = series[i - BASELINE_PERIODS..i]
+            .iter()
+            .flatten()
+            .cloned()
+            .collect();
+        let current: Vec<f64> = series[i..i + CURRENT_PERIODS]
+            .iter()
+            .flatten()
+            .cloned()
+            .collect();
+        if let Some(shift) = compare(&baseline, &current) {
+            let detected_at = starts[i];
+            res.push(MetricShift {
+                id: format!("{}-{}-{}", scope, metric, detected_at.format("%Y-%m-%d")),
+                metric: metric.to_owned(),
+                scope: scope.to_owned(),
+                detected_at,
+                ..shift
+            });
+            // don't report the same shift for each period it persists
+            i += CURRENT_PERIODS;
+        } else {
+            i += 1;
+        }
+    }
+    res
+}
+
+fn compare(baseline: &[f64], current: &[f64]) -> Option<MetricShift> {
+    if baseline.len() < 2 || current.len() < 2 {
+        return None;
+    }
+    let baseline_mean = mean(baseline);
+    let current_mean = mean(current);
+    if baseline_mean == 0.0 {
+        return None;
+    }
+    let magnitude = (current_mean - baseline_mean) / baseline_mean;
+    let variance = baseline
+        .iter()
+        .map(|v| (v - baseline_mean).powi(2))
+        .sum::<f64>()
+        / (baseline.len() - 1) as f64;
+    let standard_error = variance.sqrt() / (current.len() as f64).sqrt();
+    if magnitude.abs() < MIN_RELATIVE_CHANGE
+        || (current_mean - baseline_mean).abs() < MIN_STANDARD_ERRORS * standard_error
+    {
+        return None;
+    }
+    Some(MetricShift {
+        id: String::new(),
+        metric: String::new(),
+        scope: String::new(),
+        direction: if magnitude > 0.0 {
+            ShiftDirection::Increase
+        } else {
+            ShiftDirection::Decrease
+        },
+        magnitude,
+        baseline: baseline_mean,
+        current: current_mean,
+        detected_at: Utc.timestamp(0, 0),
+    })
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// The starts of `len` consecutive periods, starting with the one containing
+/// `start`.
+fn period_starts(period: Period, start: &DateTime<Utc>, len: usize) -> Vec<DateTime<Utc>> {
+    let mut res = Vec::with_capacity(len);
+    let mut bounds = period.bounds(start);
+    for _ in 0..len {
+        res.push(bounds.0);
+        bounds = period.bounds(&bounds.1);
+    }
+    res
+}
+
+#[derive(Default)]
+struct Rollup {
+    pull_requests_merged: u64,
+    time_to_resolve: f64,
+    churn: u64,
+}
+
+/// Rolls org-wide person snapshots over `period` up by team and across the
+/// org and detects shifts in throughput, churn and time to resolve.
+pub fn detect_snapshot_shifts(snapshots: &[PersonSnapshot], period: Period) -> Vec<MetricShift> {
+    let mut scopes: BTreeMap<&str, BTreeMap<DateTime<Utc>, Rollup>> = BTreeMap::new();
+    for snapshot in snapshots {
+        let teams = snapshot.person.teams.iter().map(|t| &**t);
+        for scope in std::iter::once(ORG_SCOPE).chain(teams) {
+            let rollup = scopes
+                .entry(scope)
+                .or_default()
+                .entry(snapshot.period_start)
+                .or_default();
+            rollup.pull_requests_merged += snapshot.pull_requests_merged;
+            rollup.time_to_resolve +=
+                snapshot.time_to_resolve.unwrap_or(0.0) * snapshot.pull_requests_merged as f64;
+            rollup.churn += snapshot.stats.churn;
+        }
+    }
+
+    let mut res = vec![];
+    for (scope, rollups) in scopes {
+        let (start, end) = match (rollups.keys().next(), rollups.keys().next_back()) {
+            (Some(start), Some(end)) => (*start, *end),
+            _ => continue,
+        };
+        let mut indices = BTreeMap::new();
+        let mut bounds = period.bounds(&start);
+        while bounds.0 <= end {
+            indices.insert(bounds.0, indices.len());
+            bounds = period.bounds(&bounds.1);
+        }
+        let len = indices.len();
+        let mut throughput = vec![Some(0.0); len];
+        let mut churn = vec![Some(0.0); len];
+        let mut time_to_resolve = vec![None; len];
+        for (period_start, rollup) in rollups {
+            let i = indices[&period.bounds(&period_start).0];
+            throughput[i] = Some(rollup.pull_requests_merged as f64);
+            churn[i] = Some(rollup.churn as f64);
+            if rollup.pull_requests_merged > 0 {
+                time_to_resolve[i] =
+                    Some(rollup.time_to_resolve / rollup.pull_requests_merged as f64);
+            }
+        }
+        res.extend(detect_shifts(
+            "throughput",
+            scope,
+            period,
+            &start,
+            &throughput,
+        ));
+        res.extend(detect_shifts("churn", scope, period, &start, &churn));
+        res.extend(detect_shifts(
+            "time_to_resolve",
+            scope,
+            period,
+            &start,
+            &time_to_resolve,
+        ));
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_shifts() {
+        let start = Utc.ymd(2019, 1, 7).and_hms(0, 0, 0);
+        let mut series: Vec<Option<f64>> = vec![10.0, 11.0, 9.0, 10.0, 12.0, 8.0, 10.0, 10.0]
+            .into_iter()
+            .map(Some)
+            .collect();
+        series.extend(vec![Some(20.0), Some(19.0), None, Some(21.0)]);
+        let shifts = detect_shifts("throughput", "org", Period::Week, &start, &series);
+        assert_eq!(shifts.len(), 1);
+        assert_eq!(shifts[0].direction, ShiftDirection::Increase);
+        assert_eq!(shifts[0].magnitude, 1.0);
+        assert_eq!(shifts[0].detected_at, Utc.ymd(2019, 3, 4).and_hms(0, 0, 0));
+        assert_eq!(shifts[0].id, "org-throughput-2019-03-04");
+    }
+
+    #[test]
+    fn test_noise_is_not_a_shift() {
+        let start = Utc.ymd(2019, 1, 7).and_hms(0, 0, 0);
+        let series: Vec<Option<f64>> = vec![
+            10.0, 2.0, 18.0, 10.0, 4.0, 16.0, 10.0, 10.0, 13.0, 12.0, 14.0, 13.0,
+        ]
+        .into_iter()
+        .map(Some)
+        .collect();
+        assert_eq!(
+            detect_shifts("churn", "org", Period::Week, &start, &series),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_detect_monthly_shifts() {
+        let start = Utc.ymd(2019, 1, 1).and_hms(0, 0, 0);
+        let series: Vec<Option<f64>> = vec![
+            10.0, 11.0, 9.0, 10.0, 12.0, 8.0, 10.0, 10.0, 20.0, 19.0, 21.0, 20.0,
+        ]
+        .into_iter()
+        .map(Some)
+        .collect();
+        let shifts = detect_shifts("throughput", "org", Period::Month, &start, &series);
+        assert_eq!(shifts.len(), 1);
+        assert_eq!(shifts[0].detected_at, Utc.ymd(2019, 9, 1).and_hms(0, 0, 0));
+    }
+}
@@ -0,0 +1,106 @@
+use std::fs;
+use std::path::Path;
+
+use git2::{Repository, RepositoryOpenFlags};
+
+use crate::error::*;
+use crate::repo_info::{parse_remote_url, RepoInfo};
+use crate::workspace_config::RepoEntry;
+
+/// Finds the bare repos under `dir`, e.g. mirror clones kept by backup
+/// tooling, to be analyzed in place without being fetched. The identity of
+/// each is inferred from the url of its canonical remote, or else from its
+/// path under `dir`, so that no forge needs to be reachable.
+pub fn discover_mirrors(dir: &Path) -> Result<Vec<RepoEntry>> {
+    let mut res = vec![];
+    find_bare_repos(dir, dir, &mut res)?;
+    res.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(res)
+}
+
+fn find_bare_repos(root: &Path, dir: &Path, res: &mut Vec<RepoEntry>) -> Result<()> {
+    let no_ceilings: &[&Path] = &[];
+    if let Ok(repo) = Repository::open_ext(dir, RepositoryOpenFlags::NO_SEARCH, no_ceilings) {
+        // checkouts, as opposed to mirrors, are left alone along with
+        // their contents
+        if repo.is_bare() {
+            res.push(mirror_entry(root, dir, &repo)?);
+        }
+        return Ok(());
+    }
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        // symlinks are not followed so that cycles cannot be walked forever
+        if entry.file_type()?.is_dir() {
+            find_bare_repos(root, &entry.path(), res)?;
+        }
+    }
+    Ok(())
+}
+
+fn mirror_entry(root: &Path, dir: &Path, repo: &Repository) -> Result<RepoEntry> {
+    let mut repo_info = RepoInfo {
+        // the default branch of the mirrored repo
+        refs: vec!["HEAD".to_owned()],
+        mirror: true,
+        ..Default::default()
+    }
+    .resolve(repo)?;
+    if repo_info.github_name.is_none()
+        && repo_info.gitlab_name.is_none()
+        && repo_info.bitbucket_name.is_none()
+    {
+        repo_info.name = match parse_remote_url(&repo_info.clone_url) {
+            Some((_, path)) => path,
+            None => {
+                let path = dir.strip_prefix(root).unwrap_or(dir).to_string_lossy();
+                path.trim_end_matches(".git").to_owned()
+            }
+        };
+    }
+    if repo_info.clone_url.is_empty() {
+        repo_info.clone_url = dir.to_string_lossy().into_owned();
+    }
+    Ok(RepoEntry {
+        repo_info,
+        ignore: false,
+        path: Some(dir.to_string_lossy().into_owned()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discover_mirrors() -> Result<()> {
+        let tmp_dir = tempdir::TempDir::new("mirrors")?;
+        let github = Repository::init_bare(tmp_dir.path().join("github/acme/app.git"))?;
+        github.remote("origin", "https://github.com/acme/app.git")?;
+        let gerrit = Repository::init_bare(tmp_dir.path().join("gerrit/tools.git"))?;
+        gerrit.remote("origin", "ssh://git@review.example.com:29418/infra/tools")?;
+        Repository::init_bare(tmp_dir.path().join("orphans/legacy.git"))?;
+        Repository::init(tmp_dir.path().join("checkouts/app"))?;
+        fs::create_dir_all(tmp_dir.path().join("empty"))?;
+
+        let entries = discover_mirrors(tmp_dir.path())?;
+        let names: Vec<_> = entries.iter().map(|e| &*e.repo_info.name).collect();
+        assert_eq!(names, vec!["infra/tools", "acme/app", "orphans/legacy"]);
+        assert!(entries.iter().all(|e| e.repo_info.mirror));
+        assert_eq!(
+            entries[1].repo_info.github_name,
+            Some("acme/app".to_owned())
+        );
+        assert_eq!(
+            entries[1].path,
+            Some(
+                tmp_dir
+                    .path()
+                    .join("github/acme/app.git")
+                    .to_string_lossy()
+                    .into_owned()
+            )
+        );
+        Ok(())
+    }
+}
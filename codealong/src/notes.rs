@@ -0,0 +1,100 @@
+use git2::{ErrorCode, Oid, Repository};
+use linked_hash_map::LinkedHashMap;
+use regex::Regex;
+
+use crate::error::*;
+
+/// A git note attached to a commit, e.g. CI results or review metadata.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Note {
+    pub text: String,
+    /// `Key: value` lines of the note, with keys converted to snake case,
+    /// e.g. `Build-Status: passed` becomes `build_status`.
+    pub fields: LinkedHashMap<String, String>,
+}
+
+impl Note {
+    pub fn parse(text: &str) -> Note {
+        lazy_static! {
+            static ref FIELD_REGEX: Regex =
+                Regex::new(r"^\s*([A-Za-z][A-Za-z0-9 _-]*?)\s*:\s*(.*?)\s*$").unwrap();
+        }
+        let fields = text
+            .lines()
+            .filter_map(|line| FIELD_REGEX.captures(line))
+            .map(|c| {
+                let key = c[1].to_lowercase().replace(['-', ' '], "_");
+                (key, c[2].to_owned())
+            })
+            .collect();
+        Note {
+            text: text.trim_end().to_owned(),
+            fields,
+        }
+    }
+}
+
+/// Reads the notes attached to `oid` from each of the configured notes refs,
+/// keyed by the configured name.
+pub fn read_notes(
+    repo: &Repository,
+    oid: Oid,
+    notes_refs: &LinkedHashMap<String, String>,
+) -> Result<LinkedHashMap<String, Note>> {
+    let mut res = LinkedHashMap::new();
+    for (name, notes_ref) in notes_refs {
+        match repo.find_note(Some(notes_ref), oid) {
+            Ok(note) => {
+                res.insert(
+                    name.to_owned(),
+                    Note::parse(&String::from_utf8_lossy(note.message_bytes())),
+                );
+            }
+            Err(ref e) if e.code() == ErrorCode::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let note = Note::parse("Build-Status: passed\nCoverage: 81.5%\n\nSee the CI logs\n");
+        assert_eq!(
+            note.text,
+            "Build-Status: passed\nCoverage: 81.5%\n\nSee the CI logs"
+        );
+        assert_eq!(note.fields.get("build_status"), Some(&"passed".to_owned()));
+        assert_eq!(note.fields.get("coverage"), Some(&"81.5%".to_owned()));
+        assert_eq!(note.fields.len(), 2);
+    }
+
+    #[test]
+    fn test_read_notes() -> Result<()> {
+        let tmp_dir = tempdir::TempDir::new("notes")?;
+        let repo = Repository::init(tmp_dir.path())?;
+        let sig = git2::Signature::now("Test", "test@example.com")?;
+        let tree = repo.find_tree(repo.treebuilder(None)?.write()?)?;
+        let oid = repo.commit(None, &sig, &sig, "commit", &tree, &[])?;
+        repo.note(
+            &sig,
+            &sig,
+            Some("refs/notes/ci"),
+            oid,
+            "Status: passed",
+            false,
+        )?;
+
+        let mut notes_refs = LinkedHashMap::new();
+        notes_refs.insert("ci".to_owned(), "refs/notes/ci".to_owned());
+        notes_refs.insert("review".to_owned(), "refs/notes/review".to_owned());
+        let notes = read_notes(&repo, oid, &notes_refs)?;
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes["ci"].fields.get("status"), Some(&"passed".to_owned()));
+        Ok(())
+    }
+}
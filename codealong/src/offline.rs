@@ -0,0 +1,28 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::error::*;
+
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+/// Forbids, or allows again, any network access by this process: fetching
+/// and cloning repos as well as requests to forges and remote sinks, which
+/// fail with `ErrorKind::Offline` instead.
+pub fn set_offline(offline: bool) {
+    OFFLINE.store(offline, Ordering::SeqCst);
+}
+
+/// Builds without the `network` feature are always offline, as they cannot
+/// clone or fetch repos.
+pub fn is_offline() -> bool {
+    !cfg!(feature = "network") || OFFLINE.load(Ordering::SeqCst)
+}
+
+/// Fails if network access is forbidden, to be called before `action`
+/// accesses the network.
+pub fn ensure_online(action: &str) -> Result<()> {
+    if is_offline() {
+        Err(ErrorKind::Offline(action.to_owned()).into())
+    } else {
+        Ok(())
+    }
+}
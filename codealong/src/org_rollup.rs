@@ -0,0 +1,236 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::prelude::*;
+use chrono::DateTime;
+
+use crate::person_snapshot::PersonSnapshot;
+use codealong_model::Person;
+
+/// Merges per-repo person snapshots into a single org-wide snapshot per
+/// person and period.
+///
+/// The same person is frequently known by different identities in different
+/// repos, e.g. `Jane <jane@example.com>` in one repo and
+/// `Jane Doe <jane@example.com>` in another, or a GitHub login for pull
+/// requests. People are considered the same if they share an id, an email
+/// address or a GitHub login.
+///
+/// The teams of each org-wide snapshot are those the person was on during the
+/// period, as recorded on the per-repo snapshots, so that team rollups follow
+/// re-orgs. Identities without any teams fall back to the teams of the other
+/// identities of the person.
+pub fn rollup_org(snapshots: &[PersonSnapshot]) -> Vec<PersonSnapshot> {
+    let people = Identities::new(snapshots.iter().map(|s| &s.person));
+
+    let mut res: BTreeMap<(DateTime<Utc>, usize), PersonSnapshot> = BTreeMap::new();
+    let mut teams: HashMap<(DateTime<Utc>, usize), Vec<String>> = HashMap::new();
+    for snapshot in snapshots {
+        let cluster = people.cluster(&snapshot.person.id);
+        let key = (snapshot.period_start, cluster);
+        res.entry(key)
+            .or_insert_with(|| {
+                PersonSnapshot::for_org(
+                    people.canonical[cluster].clone(),
+                    snapshot.period_start,
+                    snapshot.period_end,
+                )
+            })
+            .merge(snapshot);
+        let period_teams = teams.entry(key).or_default();
+        for team in &snapshot.person.teams {
+            if !period_teams.contains(team) {
+                period_teams.push(team.clone());
+            }
+        }
+    }
+    for (key, period_teams) in teams {
+        if !period_teams.is_empty() {
+            res.get_mut(&key).unwrap().person.teams = period_teams;
+        }
+    }
+    res.into_values().collect()
+}
+
+/// Clusters people with overlapping identities.
+struct Identities {
+    clusters: HashMap<String, usize>,
+    canonical: Vec<Person>,
+}
+
+impl Identities {
+    fn new<'a>(people: impl Iterator<Item = &'a Person>) -> Identities {
+        let mut distinct: Vec<&Person> = vec![];
+        let mut parents: Vec<usize> = vec![];
+        let mut keys: HashMap<String, usize> = HashMap::new();
+        for person in people {
+            if keys.contains_key(&id_key(person)) {
+                continue;
+            }
+            let index = distinct.len();
+            distinct.push(person);
+            parents.push(index);
+            for key in identity_keys(person) {
+                match keys.get(&key) {
+                    Some(&other) => {
+                        let (a, b) = (find(&parents, index), find(&parents, other));
+                        parents[a] = b;
+                    }
+                    None => {
+                        keys.insert(key, index);
+                    }
+                }
+            }
+        }
+
+        let mut members: BTreeMap<usize, Vec<&Person>> = BTreeMap::new();
+        for (index, person) in distinct.iter().enumerate() {
+            members
+                .entry(find(&parents, index))
+                .or_default()
+                .push(person);
+        }
+        let mut clusters = HashMap::new();
+        let mut canonical = vec![];
+        for people in members.values() {
+            for person in people {
+                clusters.insert(person.id.clone(), canonical.len());
+            }
+            canonical.push(merge_people(people));
+        }
+        Identities {
+            clusters,
+            canonical,
+        }
+    }
+
+    fn cluster(&self, id: &str) -> usize {
+        self.clusters[id]
+    }
+}
+
+fn find(parents: &[usize], mut index: usize) -> usize {
+    while parents[index] != index {
+        index = parents[index];
+    }
+    index
+}
+
+fn id_key(person: &Person) -> String {
+    format!("id:{}", person.id)
+}
+
+fn identity_keys(person: &Person) -> Vec<String> {
+    let mut res = vec![id_key(person)];
+    res.extend(
+        person
+            .email
+            .as_ref()
+            .map(|e| format!("email:{}", e.to_lowercase())),
+    );
+    res.extend(
+        person
+            .github_login
+            .as_ref()
+            .map(|l| format!("github:{}", l.to_lowercase())),
+    );
+    res
+}
+
+/// Picks the most complete identity of a cluster, preferring configured
+/// people with teams, and combines the teams of all identities.
+fn merge_people(people: &[&Person]) -> Person {
+    let completeness = |p: &Person| {
+        (
+            !p.teams.is_empty(),
+            p.github_login.is_some() as u8 + p.email.is_some() as u8 + p.name.is_some() as u8,
+        )
+    };
+    let mut res = (*people
+        .iter()
+        .max_by(|a, b| {
+            completeness(a)
+                .cmp(&completeness(b))
+                .then_with(|| b.id.cmp(&a.id))
+        })
+        .unwrap())
+    .clone();
+    for person in people {
+        for team in &person.teams {
+            if !res.teams.contains(team) {
+                res.teams.push(team.clone());
+            }
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::Identity;
+    use crate::period::Period;
+    use crate::person_snapshot::{PersonSnapshots, WorkInProgress};
+
+    #[test]
+    fn test_rollup_org() {
+        let jane = Identity::parse("Jane <jane@example.com>").to_person();
+        let jane_doe = Identity::parse("Jane Doe <JANE@example.com>").to_person();
+        let bob = Identity::parse("Bob <bob@example.com>").to_person();
+        let monday = Utc.ymd(2019, 12, 16).and_hms(9, 0, 0);
+        let friday = Utc.ymd(2019, 12, 20).and_hms(9, 0, 0);
+
+        let mut snapshots = PersonSnapshots::new(Period::Week);
+        snapshots.add_pull_request(&jane, &monday, "org/api", "feature-a", 1, Some(3600));
+        snapshots.add_pull_request(&jane_doe, &friday, "org/web", "feature-b", 7, Some(7200));
+        snapshots.add_pull_request(&jane_doe, &friday, "org/web", "feature-c", 8, None);
+        snapshots.add_pull_request(&bob, &friday, "org/web", "feature-d", 9, None);
+        let snapshots = rollup_org(&snapshots.finish());
+
+        assert_eq!(snapshots.len(), 2);
+        let jane = snapshots
+            .iter()
+            .find(|s| s.person.email.as_ref().unwrap().starts_with("jane"))
+            .unwrap();
+        assert_eq!(jane.repo, None);
+        assert_eq!(
+            jane.wip,
+            WorkInProgress {
+                repos: 2,
+                branches: 3,
+                pull_requests: 3
+            }
+        );
+        assert_eq!(jane.repos, vec!["org/api", "org/web"]);
+        assert_eq!(jane.pull_requests_merged, 2);
+        assert_eq!(jane.time_to_resolve, Some(5400.0));
+    }
+
+    #[test]
+    fn test_rollup_org_historical_teams() {
+        let mut before = Identity::parse("Jane <jane@example.com>").to_person();
+        before.teams = vec!["payments".to_owned()];
+        let mut after = before.clone();
+        after.teams = vec!["platform".to_owned()];
+        let unconfigured = Person {
+            id: "jane".to_owned(),
+            github_login: Some("jane".to_owned()),
+            name: None,
+            email: Some("jane@example.com".to_owned()),
+            teams: vec![],
+        };
+        let first_week = Utc.ymd(2019, 12, 16).and_hms(9, 0, 0);
+        let second_week = Utc.ymd(2019, 12, 23).and_hms(9, 0, 0);
+        let third_week = Utc.ymd(2019, 12, 30).and_hms(9, 0, 0);
+
+        let mut snapshots = PersonSnapshots::new(Period::Week);
+        snapshots.add_pull_request(&before, &first_week, "org/api", "a", 1, None);
+        snapshots.add_pull_request(&after, &second_week, "org/api", "b", 2, None);
+        snapshots.add_pull_request(&unconfigured, &third_week, "org/api", "c", 3, None);
+        let snapshots = rollup_org(&snapshots.finish());
+
+        assert_eq!(snapshots.len(), 3);
+        assert_eq!(snapshots[0].person.teams, vec!["payments"]);
+        assert_eq!(snapshots[1].person.teams, vec!["platform"]);
+        assert_eq!(snapshots[2].person.teams, vec!["payments"]);
+    }
+}
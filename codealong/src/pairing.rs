@@ -0,0 +1,233 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+use chrono::prelude::*;
+use chrono::{DateTime, Duration};
+use regex::Regex;
+
+use crate::analyzed_commit::AnalyzedCommit;
+use crate::identity::Identity;
+use crate::repo_info::PartialRepoInfo;
+use codealong_model::{Event, Person};
+
+/// Time assumed to have been spent before the first commit of a session.
+const SESSION_LEAD_TIME_MINUTES: i64 = 15;
+
+/// Extracts identities from `Co-authored-by: Name <email>` trailers.
+pub fn co_authors(message: &str) -> Vec<Identity> {
+    lazy_static! {
+        static ref CO_AUTHORED_BY_REGEX: Regex =
+            Regex::new(r"(?im)^co-authored-by:\s*(.+?)\s*$").unwrap();
+    }
+    CO_AUTHORED_BY_REGEX
+        .captures_iter(message)
+        .map(|captures| Identity::parse(&captures[1]))
+        .collect()
+}
+
+/// An inferred pair or mob programming session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PairingSession {
+    pub id: String,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    /// Estimated length of the session in seconds.
+    pub duration: i64,
+    pub participants: Vec<Person>,
+    pub commits: Vec<String>,
+    pub repo: Option<PartialRepoInfo>,
+}
+
+impl Event for PairingSession {
+    fn timestamp(&self) -> &DateTime<Utc> {
+        &self.started_at
+    }
+
+    fn event_type(&self) -> &str {
+        "pairing_session"
+    }
+
+    fn id(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.id)
+    }
+
+    fn tags(&self) -> HashSet<String> {
+        let mut tags = HashSet::new();
+        tags.insert("heuristic".to_owned());
+        tags
+    }
+}
+
+struct SessionCommit {
+    id: String,
+    parent: Option<String>,
+    authored_at: DateTime<Utc>,
+    participants: Vec<Person>,
+    repo: Option<PartialRepoInfo>,
+}
+
+/// Infers pairing sessions from analyzed commits. Consecutive commits by
+/// different authors which are authored within `window` of each other are
+/// assumed to be part of the same session, as are commits with co-authors.
+pub struct PairingSessions {
+    window: Duration,
+    commits: HashMap<String, SessionCommit>,
+}
+
+impl PairingSessions {
+    pub fn new(window: Duration) -> PairingSessions {
+        PairingSessions {
+            window,
+            commits: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, commit: &AnalyzedCommit) {
+        let mut participants: Vec<Person> = vec![];
+        participants.extend(commit.normalized_author.clone());
+        participants.extend(commit.co_authors.iter().cloned());
+        self.commits.insert(
+            commit.id.clone(),
+            SessionCommit {
+                id: commit.id.clone(),
+                parent: commit.parents.first().cloned(),
+                authored_at: commit.authored_at,
+                participants,
+                repo: commit.repo.clone(),
+            },
+        );
+    }
+
+    pub fn finish(self) -> Vec<PairingSession> {
+        let mut roots: HashMap<&str, &str> = HashMap::new();
+        for commit in self.commits.values() {
+            let parent = commit.parent.as_ref().and_then(|p| self.commits.get(p));
+            if let Some(parent) = parent {
+                if (commit.authored_at - parent.authored_at)
+                    .num_seconds()
+                    .abs()
+                    <= self.window.num_seconds()
+                {
+                    let a = find_root(&roots, &commit.id);
+                    let b = find_root(&roots, &parent.id);
+                    if a != b {
+                        roots.insert(a, b);
+                    }
+                }
+            }
+        }
+
+        let mut groups: HashMap<&str, Vec<&SessionCommit>> = HashMap::new();
+        for commit in self.commits.values() {
+            groups
+                .entry(find_root(&roots, &commit.id))
+                .or_default()
+                .push(commit);
+        }
+
+        let mut sessions: Vec<PairingSession> =
+            groups.into_values().filter_map(build_session).collect();
+        sessions.sort_by_key(|s| s.started_at);
+        sessions
+    }
+}
+
+fn find_root<'a>(roots: &HashMap<&'a str, &'a str>, id: &'a str) -> &'a str {
+    let mut current = id;
+    while let Some(next) = roots.get(current) {
+        current = next;
+    }
+    current
+}
+
+fn build_session(mut commits: Vec<&SessionCommit>) -> Option<PairingSession> {
+    commits.sort_by_key(|c| c.authored_at);
+    let mut seen: HashSet<&str> = HashSet::new();
+    let mut participants: Vec<Person> = vec![];
+    for commit in &commits {
+        for person in &commit.participants {
+            if seen.insert(&person.id) {
+                participants.push(person.clone());
+            }
+        }
+    }
+    if participants.len() < 2 {
+        return None;
+    }
+    let first = commits.first()?;
+    let last = commits.last()?;
+    Some(PairingSession {
+        id: format!("{}-session", first.id),
+        started_at: first.authored_at,
+        ended_at: last.authored_at,
+        duration: (last.authored_at - first.authored_at).num_seconds()
+            + SESSION_LEAD_TIME_MINUTES * 60,
+        participants,
+        commits: commits.iter().map(|c| c.id.clone()).collect(),
+        repo: first.repo.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{Oid, Repository};
+
+    fn build_commit(id: &str, parent: Option<&str>, author: &str, minutes: i64) -> AnalyzedCommit {
+        let repo = Repository::open("./fixtures/repos/simple").unwrap();
+        let commit = repo
+            .find_commit(Oid::from_str("86d242301830075e93ff039a4d1e88673a4a3020").unwrap())
+            .unwrap();
+        let mut res = AnalyzedCommit::new(&commit);
+        res.id = id.to_owned();
+        res.parents = parent.into_iter().map(|p| p.to_owned()).collect();
+        res.authored_at = Utc.timestamp(minutes * 60, 0);
+        res.normalized_author = Some(Identity::parse(author).to_person());
+        res
+    }
+
+    #[test]
+    fn test_co_authors() {
+        let message = "Add feature\n\nCo-authored-by: Jane Doe <jane@example.com>\nco-authored-by: <joe@example.com>\n";
+        assert_eq!(
+            co_authors(message),
+            vec![
+                Identity::parse("Jane Doe <jane@example.com>"),
+                Identity::parse("<joe@example.com>")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_interleaved_commits() {
+        let mut sessions = PairingSessions::new(Duration::minutes(30));
+        sessions.add(&build_commit("a", None, "Alice <alice@example.com>", 0));
+        sessions.add(&build_commit("b", Some("a"), "Bob <bob@example.com>", 20));
+        sessions.add(&build_commit(
+            "c",
+            Some("b"),
+            "Alice <alice@example.com>",
+            40,
+        ));
+        sessions.add(&build_commit("d", Some("c"), "Bob <bob@example.com>", 600));
+        sessions.add(&build_commit("e", Some("d"), "Bob <bob@example.com>", 610));
+        let sessions = sessions.finish();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].commits, vec!["a", "b", "c"]);
+        assert_eq!(sessions[0].participants.len(), 2);
+        assert_eq!(sessions[0].duration, 55 * 60);
+    }
+
+    #[test]
+    fn test_co_authored_commit() {
+        let mut commit = build_commit("a", None, "Alice <alice@example.com>", 0);
+        commit
+            .co_authors
+            .push(Identity::parse("Bob <bob@example.com>").to_person());
+        let mut sessions = PairingSessions::new(Duration::minutes(30));
+        sessions.add(&commit);
+        let sessions = sessions.finish();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].commits, vec!["a"]);
+    }
+}
@@ -0,0 +1,301 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use git2::{Oid, Repository};
+use slog::Logger;
+
+use crate::analyze_opts::AnalyzeOpts;
+use crate::analyzed_commit::AnalyzedCommit;
+use crate::commit_analyzer::CommitAnalyzer;
+use crate::defect::IntroducedDefect;
+use crate::error::*;
+use crate::landing::Landings;
+use crate::pipeline_stats::{PipelineStats, Stage};
+use crate::progress::Progress;
+use crate::repo_analyzer::RepoAnalyzer;
+use crate::repo_config::RepoConfig;
+
+/// Maximum number of consecutive commits a worker claims at once.
+/// Neighbouring commits share most of their trees, so this keeps the object
+/// cache of each worker warm.
+const MAX_CHUNK_SIZE: usize = 16;
+
+/// A commit analyzed on a worker thread, with the events derived from it
+/// which need the repository to build.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommitAnalysis {
+    pub commit: AnalyzedCommit,
+    pub introduced_defects: Vec<IntroducedDefect>,
+}
+
+/// The results of a chunk of consecutive commits claimed by a worker, along
+/// with the number of commits in the chunk.
+type Chunk = (Receiver<Result<CommitAnalysis>>, usize);
+
+/// Analyzes the commits of a walk on a pool of threads, each with its own
+/// handle on the repository, yielding them in the order of the walk so that
+/// the output is identical to that of a serial analysis.
+///
+/// Workers queue the chunks they claim in the order of the walk, on a
+/// bounded channel of `jobs` chunks, so that they stop claiming while the
+/// results of `jobs` chunks are waiting to be yielded, and at most around
+/// `2 * jobs * MAX_CHUNK_SIZE` results are held at once however far the
+/// consumer falls behind.
+pub struct ParallelRevwalk {
+    chunks: Option<Receiver<Chunk>>,
+    /// The chunk the next result is yielded from, and the number of its
+    /// results left.
+    chunk: Option<Chunk>,
+    next: usize,
+    len: usize,
+    /// Number of commits claimed by the workers so far.
+    claimed: Arc<AtomicUsize>,
+    /// Number of results sent by the workers but not yet received.
+    queued: Arc<AtomicUsize>,
+    stats: PipelineStats,
+    progress: Arc<dyn Progress>,
+    cancelled: Arc<AtomicBool>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl ParallelRevwalk {
+    /// `commits` are the ids of the commits to analyze, in order, each with
+    /// the id of the commit it replaces, if any.
+    pub(crate) fn new(
+        analyzer: &RepoAnalyzer,
+        commits: Vec<(Oid, Option<Oid>)>,
+        landings: Option<Arc<Landings>>,
+        opts: AnalyzeOpts,
+        jobs: usize,
+        stats: &PipelineStats,
+    ) -> Result<ParallelRevwalk> {
+        let len = commits.len();
+        let jobs = jobs.max(1);
+        let (sender, receiver) = sync_channel(jobs);
+        // chunks are claimed and queued together so that they are queued in
+        // the order of the walk
+        let sender = Arc::new(Mutex::new(sender));
+        // smaller chunks for short walks so that every worker gets some
+        let chunk_size = (len / (jobs * 4)).clamp(1, MAX_CHUNK_SIZE);
+        let commits = Arc::new(commits);
+        let claimed = Arc::new(AtomicUsize::new(0));
+        let queued = Arc::new(AtomicUsize::new(0));
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let mut workers = vec![];
+        for _ in 0..jobs {
+            let repo = Repository::open(analyzer.repo.path())?;
+            let commits = commits.clone();
+            let claimed = claimed.clone();
+            let queued = queued.clone();
+            let stats = stats.clone();
+            let cancelled = cancelled.clone();
+            let config = analyzer.config.clone();
+            let opts = opts.clone();
+            let landings = landings.clone();
+            let logger = analyzer.logger.clone();
+            let progress = analyzer.progress.clone();
+            let sender = sender.clone();
+            workers.push(thread::spawn(move || loop {
+                let (start, results) = {
+                    let sender = sender.lock().unwrap();
+                    let start = claimed.load(Ordering::SeqCst);
+                    if start >= commits.len() || cancelled.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let count = chunk_size.min(commits.len() - start);
+                    // never blocks, since it holds the whole chunk
+                    let (results, chunk) = sync_channel(count);
+                    // blocks while `jobs` chunks are waiting to be yielded
+                    if sender.send((chunk, count)).is_err() {
+                        return;
+                    }
+                    claimed.store(start + count, Ordering::SeqCst);
+                    (start, results)
+                };
+                for &(id, replaced) in commits.iter().skip(start).take(chunk_size) {
+                    if cancelled.load(Ordering::SeqCst) || opts.is_past_deadline() {
+                        return;
+                    }
+                    let res = stats.time(Stage::Analyze, || {
+                        analyze_commit(&repo, id, replaced, &config, &landings, &progress, &logger)
+                    });
+                    queued.fetch_add(1, Ordering::SeqCst);
+                    if results.send(res).is_err() {
+                        return;
+                    }
+                }
+            }));
+        }
+        Ok(ParallelRevwalk {
+            chunks: Some(receiver),
+            chunk: None,
+            next: 0,
+            len,
+            claimed,
+            queued,
+            stats: stats.clone(),
+            progress: analyzer.progress.clone(),
+            cancelled,
+            workers,
+        })
+    }
+}
+
+fn analyze_commit(
+    repo: &Repository,
+    id: Oid,
+    replaced: Option<Oid>,
+    config: &RepoConfig,
+    landings: &Option<Arc<Landings>>,
+    progress: &Arc<dyn Progress>,
+    logger: &Logger,
+) -> Result<CommitAnalysis> {
+    let mut analyzer = CommitAnalyzer::new(repo, repo.find_commit(id)?, config, logger)
+        .with_progress(progress.clone());
+    if let Some(replaced) = replaced {
+        analyzer = analyzer.replacing(replaced);
+    }
+    if let Some(landings) = landings {
+        analyzer = analyzer.with_landings(landings.clone());
+    }
+    let commit = analyzer.analyze()?;
+    let introduced_defects = analyzer.introduced_defects(&commit)?;
+    Ok(CommitAnalysis {
+        commit,
+        introduced_defects,
+    })
+}
+
+impl Iterator for ParallelRevwalk {
+    type Item = Result<CommitAnalysis>;
+
+    fn next(&mut self) -> Option<Result<CommitAnalysis>> {
+        if self.next >= self.len {
+            return None;
+        }
+        let (results, left) = match self.chunk.take() {
+            Some(chunk) => chunk,
+            None => self.chunks.as_ref()?.recv().ok()?,
+        };
+        // the workers stopped at the deadline when a chunk ends early, and
+        // the commits after it are dropped so that the output stays a prefix
+        // of the walk
+        let res = results.recv().ok()?;
+        if left > 1 {
+            self.chunk = Some((results, left - 1));
+        }
+        self.queued.fetch_sub(1, Ordering::SeqCst);
+        self.next += 1;
+        self.progress.commits_analyzed(self.next, self.len);
+        // results waiting to be indexed, and commits waiting to be analyzed
+        self.stats
+            .sample_queue(Stage::Sink, self.queued.load(Ordering::SeqCst));
+        self.stats.sample_queue(
+            Stage::Analyze,
+            self.len.saturating_sub(self.claimed.load(Ordering::SeqCst)),
+        );
+        Some(res)
+    }
+}
+
+impl Drop for ParallelRevwalk {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        // unblocks the workers waiting to queue a chunk
+        self.chunks = None;
+        self.chunk = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analyze_opts::AnalyzeOpts;
+    use crate::pipeline_stats::{PipelineStats, Stage};
+    use crate::progress::{Progress, BLAME_STAGE};
+    use crate::repo_analyzer::RepoAnalyzer;
+    use crate::repo_config::RepoConfig;
+    use crate::test::build_test_logger;
+    use git2::Repository;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[test]
+    fn test_same_as_serial() {
+        let repo = Repository::open("./fixtures/repos/simple").unwrap();
+        let analyzer = RepoAnalyzer::new(repo, RepoConfig::default(), &build_test_logger());
+        let serial: Vec<_> = analyzer
+            .analyze(AnalyzeOpts::default())
+            .unwrap()
+            .map(|commit_analyzer| commit_analyzer.unwrap().analyze().unwrap())
+            .collect();
+        for jobs in 1..4 {
+            let stats = PipelineStats::new();
+            let parallel: Vec<_> = analyzer
+                .analyze_parallel(AnalyzeOpts::default(), jobs, &stats)
+                .unwrap()
+                .map(|analysis| analysis.unwrap().commit)
+                .collect();
+            assert_eq!(parallel, serial);
+            let summary = stats.summary();
+            assert_eq!(summary[0].stage, Stage::Walk);
+            assert_eq!(summary[1].stage, Stage::Analyze);
+            assert_eq!(summary[1].items, serial.len() as u64);
+        }
+    }
+
+    #[test]
+    fn test_early_drop() {
+        let repo = Repository::open("./fixtures/repos/simple").unwrap();
+        let analyzer = RepoAnalyzer::new(repo, RepoConfig::default(), &build_test_logger());
+        let mut revwalk = analyzer
+            .analyze_parallel(AnalyzeOpts::default(), 2, &PipelineStats::new())
+            .unwrap();
+        assert!(revwalk.next().unwrap().is_ok());
+    }
+
+    #[derive(Default)]
+    struct RecordedProgress {
+        commits: Mutex<Vec<(usize, usize)>>,
+        files: Mutex<Vec<String>>,
+        blames: Mutex<usize>,
+    }
+
+    impl Progress for RecordedProgress {
+        fn commits_analyzed(&self, done: usize, total: usize) {
+            self.commits.lock().unwrap().push((done, total));
+        }
+
+        fn file_started(&self, path: &str) {
+            self.files.lock().unwrap().push(path.to_owned());
+        }
+
+        fn timed(&self, stage: &str, _elapsed: Duration) {
+            if stage == BLAME_STAGE {
+                *self.blames.lock().unwrap() += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn test_progress() {
+        let repo = Repository::open("./fixtures/repos/simple").unwrap();
+        let progress = Arc::new(RecordedProgress::default());
+        let analyzer = RepoAnalyzer::new(repo, RepoConfig::default(), &build_test_logger())
+            .with_progress(progress.clone());
+        let count = analyzer
+            .analyze_parallel(AnalyzeOpts::default(), 2, &PipelineStats::new())
+            .unwrap()
+            .count();
+        let commits = progress.commits.lock().unwrap();
+        assert_eq!(commits.len(), count);
+        assert_eq!(commits.last(), Some(&(count, count)));
+        let files = progress.files.lock().unwrap();
+        assert!(files.contains(&"README.md".to_owned()));
+        assert_eq!(*progress.blames.lock().unwrap(), files.len());
+    }
+}
@@ -0,0 +1,293 @@
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use git2::{Diff, DiffDelta, ObjectType, Oid, Repository, Tree};
+
+use crate::error::*;
+use crate::offline::ensure_online;
+
+// libgit2 does not support partial clones, so the missing objects of partial
+// clones are fetched with a git binary on the current path
+
+/// Opens the repository containing `path`, leaving it as it is. Partial
+/// clones git has not made readable by libgit2 fail with how to make them so.
+pub fn discover(path: &Path) -> Result<Repository> {
+    match Repository::discover(path) {
+        Err(ref e) if is_unsupported_version(e) => Err(ErrorKind::GitError(format!(
+            "{} is a partial clone libgit2 cannot read, run `git config core.repositoryformatversion 0` in it to analyze it",
+            path.display()
+        ))
+        .into()),
+        res => Ok(res?),
+    }
+}
+
+/// Opens the repository containing `path`, first making it readable by
+/// libgit2 if it is a partial clone. Only meant for the clones codealong
+/// manages itself, since it rewrites their config.
+pub fn discover_compatible(path: &Path) -> Result<Repository> {
+    match Repository::discover(path) {
+        Err(ref e) if is_unsupported_version(e) => {
+            make_compatible(path)?;
+            Ok(Repository::discover(path)?)
+        }
+        res => Ok(res?),
+    }
+}
+
+fn is_unsupported_version(error: &git2::Error) -> bool {
+    error.message().contains("unsupported repository version")
+}
+
+/// libgit2 refuses to open repositories with `core.repositoryformatversion`
+/// 1, which git uses for partial clones. git still honors the partial clone
+/// extensions in version 0 repositories, so downgrade when those are the only
+/// extensions in use.
+pub(crate) fn make_compatible(path: &Path) -> Result<()> {
+    const V0_EXTENSIONS: &[&str] = &[
+        "extensions.noop",
+        "extensions.partialclone",
+        "extensions.preciousobjects",
+        "extensions.worktreeconfig",
+    ];
+    // --get-regexp exits with 1 when nothing matches
+    let extensions =
+        git(path, &["config", "--get-regexp", r"^extensions\."], None).unwrap_or_default();
+    let unsupported: Vec<&str> = extensions
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .filter(|key| !V0_EXTENSIONS.contains(&&*key.to_lowercase()))
+        .collect();
+    if !unsupported.is_empty() {
+        return Err(ErrorKind::GitError(format!(
+            "unsupported repository extensions: {}",
+            unsupported.join(", ")
+        ))
+        .into());
+    }
+    git(path, &["config", "core.repositoryformatversion", "0"], None)?;
+    Ok(())
+}
+
+/// The name of the promisor remote if `repo` is a partial clone.
+pub fn promisor_remote(repo: &Repository) -> Option<String> {
+    let config = repo.config().ok()?;
+    if let Ok(remote) = config.get_string("extensions.partialclone") {
+        return Some(remote);
+    }
+    repo.remotes()
+        .ok()?
+        .iter()
+        .flatten()
+        .find(|name| {
+            config
+                .get_bool(&format!("remote.{}.promisor", name))
+                .unwrap_or(false)
+        })
+        .map(|name| name.to_owned())
+}
+
+/// Fetches, in a single batch, the blobs on either side of `diff` which are
+/// missing from a partial clone, since libgit2 cannot fetch them on demand.
+/// Returns the number of blobs fetched.
+pub fn fetch_missing_blobs(
+    repo: &Repository,
+    diff: &Diff,
+    old_tree: Option<&Tree>,
+    new_tree: Option<&Tree>,
+) -> Result<usize> {
+    fetch_missing_blobs_for(repo, diff.deltas(), old_tree, new_tree)
+}
+
+/// Like `fetch_missing_blobs` but only for some of the deltas of a diff.
+pub fn fetch_missing_blobs_for<'a>(
+    repo: &Repository,
+    deltas: impl Iterator<Item = DiffDelta<'a>>,
+    old_tree: Option<&Tree>,
+    new_tree: Option<&Tree>,
+) -> Result<usize> {
+    let remote = match promisor_remote(repo) {
+        Some(remote) => remote,
+        None => return Ok(0),
+    };
+    let odb = repo.odb()?;
+    let mut missing = BTreeSet::new();
+    for delta in deltas {
+        for (file, tree) in &[(delta.old_file(), old_tree), (delta.new_file(), new_tree)] {
+            let id = file.id();
+            if id.is_zero() || odb.read_header(id).is_ok() {
+                continue;
+            }
+            // submodules are commits in another repository
+            let is_blob = match (tree, file.path()) {
+                (Some(tree), Some(path)) => tree
+                    .get_path(path)
+                    .map(|entry| entry.kind() == Some(ObjectType::Blob))
+                    .unwrap_or(false),
+                _ => false,
+            };
+            if is_blob {
+                missing.insert(id.to_string());
+            }
+        }
+    }
+    let missing: Vec<&str> = missing.iter().map(|s| &**s).collect();
+    fetch_objects(repo.path(), &remote, &missing)?;
+    Ok(missing.len())
+}
+
+/// Fetches, in a single batch, the blobs of the history of `path` up to
+/// `rev` which are missing from `repo` if it is a partial clone, so that
+/// `git blame`, which must not fetch them lazily one at a time, can read them.
+/// Returns the number of blobs fetched.
+pub(crate) fn fetch_missing_history(repo: &Repository, rev: &Oid, path: &Path) -> Result<usize> {
+    let remote = match promisor_remote(repo) {
+        Some(remote) => remote,
+        None => return Ok(0),
+    };
+    let missing = missing_objects(
+        repo.path(),
+        &[rev.to_string()],
+        &[path.to_string_lossy().into_owned()],
+    )?;
+    let missing: Vec<&str> = missing.iter().map(|s| &**s).collect();
+    fetch_objects(repo.path(), &remote, &missing)?;
+    Ok(missing.len())
+}
+
+/// The objects under `paths` which are reachable from `revs` but missing
+/// from a partial clone.
+pub(crate) fn missing_objects(
+    dir: &Path,
+    revs: &[String],
+    paths: &[String],
+) -> Result<Vec<String>> {
+    let mut args = vec!["rev-list", "--objects", "--missing=print"];
+    args.extend(revs.iter().map(|r| &**r));
+    args.push("--");
+    args.extend(paths.iter().map(|p| &**p));
+    let output = git(dir, &args, None)?;
+    Ok(output
+        .lines()
+        .filter_map(|line| line.strip_prefix('?'))
+        .map(|oid| oid.to_owned())
+        .collect())
+}
+
+pub(crate) fn fetch_objects(dir: &Path, remote: &str, oids: &[&str]) -> Result<()> {
+    if oids.is_empty() {
+        return Ok(());
+    }
+    ensure_online("fetching the missing blobs of a partial clone")?;
+    // this is how git itself lazily fetches objects from a promisor remote
+    git(
+        dir,
+        &[
+            "fetch",
+            "--quiet",
+            "--no-tags",
+            "--no-write-fetch-head",
+            "--recurse-submodules=no",
+            "--filter=blob:none",
+            "--stdin",
+            remote,
+        ],
+        Some(&oids.join("\n")),
+    )?;
+    Ok(())
+}
+
+pub(crate) fn git(dir: &Path, args: &[&str], stdin: Option<&str>) -> Result<String> {
+    let mut child = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    {
+        let mut child_stdin = child.stdin.take().unwrap();
+        if let Some(stdin) = stdin {
+            child_stdin.write_all(stdin.as_bytes())?;
+            child_stdin.write_all(b"\n")?;
+        }
+    }
+    let output = child.wait_with_output()?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(ErrorKind::GitError(format!(
+            "git {}: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+        .into())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) fn build_origin(dir: &Path) -> Result<()> {
+        let run = |args: &[&str]| git(dir, args, None);
+        std::fs::create_dir_all(dir.join("a"))?;
+        std::fs::create_dir_all(dir.join("b"))?;
+        run(&["init", "--quiet"])?;
+        run(&["config", "user.name", "Test"])?;
+        run(&["config", "user.email", "test@example.com"])?;
+        run(&["config", "uploadpack.allowFilter", "true"])?;
+        run(&["config", "uploadpack.allowAnySHA1InWant", "true"])?;
+        for i in 0..2 {
+            std::fs::write(dir.join("a/x"), format!("{}", i))?;
+            std::fs::write(dir.join("b/y"), format!("{}", i))?;
+            run(&["add", "."])?;
+            run(&["commit", "--quiet", "-m", "change"])?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_fetch_missing_blobs() -> Result<()> {
+        let tmp_dir = tempdir::TempDir::new("blobless")?;
+        let origin = tmp_dir.path().join("origin");
+        build_origin(&origin)?;
+        let into = tmp_dir.path().join("clone");
+        git(
+            tmp_dir.path(),
+            &[
+                "clone",
+                "--quiet",
+                "--filter=blob:none",
+                "--no-checkout",
+                &format!("file://{}", origin.to_string_lossy()),
+                &into.to_string_lossy(),
+            ],
+            None,
+        )?;
+        assert!(discover(&into).is_err());
+        let repo = discover_compatible(&into)?;
+        assert_eq!(promisor_remote(&repo), Some("origin".to_owned()));
+        let commit = repo.head()?.peel_to_commit()?;
+        let new_tree = commit.tree()?;
+        let old_tree = commit.parent(0)?.tree()?;
+        let diff = repo.diff_tree_to_tree(Some(&old_tree), Some(&new_tree), None)?;
+        assert_eq!(
+            fetch_missing_blobs(&repo, &diff, Some(&old_tree), Some(&new_tree))?,
+            2
+        );
+        assert_eq!(
+            fetch_missing_blobs(&repo, &diff, Some(&old_tree), Some(&new_tree))?,
+            0
+        );
+        assert!(diff.stats().is_ok());
+        // the blobs of both files are the same
+        assert_eq!(
+            fetch_missing_history(&repo, &commit.id(), Path::new("a/x"))?,
+            0
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,330 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::prelude::*;
+use chrono::DateTime;
+use regex::Regex;
+
+use crate::config::Config;
+use crate::identity::Identity;
+use crate::mbox::MailMessage;
+use codealong_model::{Event, Person};
+
+/// Kind of review given through a trailer on a patch email.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewKind {
+    Reviewed,
+    Acked,
+    Tested,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PatchReview {
+    pub reviewer: Person,
+    pub kind: ReviewKind,
+    pub reviewed_at: DateTime<Utc>,
+}
+
+/// A `git format-patch` style series sent to a mailing list. This is the
+/// email workflow equivalent of a pull request: the series is "merged" once a
+/// maintainer replies that it has been applied.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PatchSeries {
+    pub id: String,
+    pub repo: Option<String>,
+    pub title: String,
+    pub version: u32,
+    pub normalized_author: Person,
+    /// Number of patches received, excluding the cover letter.
+    pub patches: u32,
+    /// Number of patches announced in the subject, e.g. 3 for `[PATCH 1/3]`.
+    pub expected_patches: u32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub applied_at: Option<DateTime<Utc>>,
+    pub applied_by: Option<Person>,
+    pub time_to_resolve: Option<i64>,
+    pub reviews: Vec<PatchReview>,
+    timestamp: DateTime<Utc>,
+}
+
+impl Event for PatchSeries {
+    fn timestamp(&self) -> &DateTime<Utc> {
+        &self.timestamp
+    }
+
+    fn event_type(&self) -> &str {
+        "patch_series"
+    }
+
+    fn id(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.id)
+    }
+
+    fn tags(&self) -> HashSet<String> {
+        HashSet::new()
+    }
+}
+
+/// Parsed `[PATCH v2 1/3] title` subject line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatchSubject {
+    pub version: u32,
+    pub index: u32,
+    pub total: u32,
+    pub title: String,
+}
+
+impl PatchSubject {
+    /// Parses a patch subject. Replies, i.e. subjects starting with `Re:`,
+    /// are not patches.
+    pub fn parse(subject: &str) -> Option<PatchSubject> {
+        lazy_static! {
+            static ref SUBJECT_REGEX: Regex =
+                Regex::new(r"^\[([^\]]*\bPATCH\b[^\]]*)\]\s*(.*?)\s*$").unwrap();
+            static ref VERSION_REGEX: Regex = Regex::new(r"^[vV](\d+)$").unwrap();
+            static ref INDEX_REGEX: Regex = Regex::new(r"^(\d+)/(\d+)$").unwrap();
+        }
+        let captures = SUBJECT_REGEX.captures(subject.trim())?;
+        let mut res = PatchSubject {
+            version: 1,
+            index: 1,
+            total: 1,
+            title: captures[2].to_owned(),
+        };
+        for tag in captures[1].split_whitespace() {
+            if let Some(c) = VERSION_REGEX.captures(tag) {
+                res.version = c[1].parse().ok()?;
+            } else if let Some(c) = INDEX_REGEX.captures(tag) {
+                res.index = c[1].parse().ok()?;
+                res.total = c[2].parse().ok()?;
+            }
+        }
+        Some(res)
+    }
+}
+
+/// Parses `Reviewed-by`, `Acked-by` and `Tested-by` trailers from the
+/// unquoted lines of a message.
+fn reviews(message: &MailMessage) -> Vec<(ReviewKind, Identity)> {
+    lazy_static! {
+        static ref REVIEW_REGEX: Regex =
+            Regex::new(r"(?i)^(reviewed|acked|tested)-by:\s*(.+?)\s*$").unwrap();
+    }
+    message
+        .unquoted_lines()
+        .filter_map(|line| REVIEW_REGEX.captures(line))
+        .map(|c| {
+            let kind = match c[1].to_lowercase().as_str() {
+                "reviewed" => ReviewKind::Reviewed,
+                "acked" => ReviewKind::Acked,
+                _ => ReviewKind::Tested,
+            };
+            (kind, Identity::parse(&c[2]))
+        })
+        .collect()
+}
+
+/// Whether a reply announces that the series has been applied, e.g.
+/// `Applied, thanks!` or `Thanks, applied to next.`
+fn is_applied_reply(message: &MailMessage) -> bool {
+    lazy_static! {
+        static ref APPLIED_REGEX: Regex = Regex::new(r"(?i)^(thanks[,.!]?\s+)?applied\b").unwrap();
+    }
+    message
+        .unquoted_lines()
+        .any(|line| APPLIED_REGEX.is_match(line))
+}
+
+/// Groups the messages of an mbox archive into patch series, attaching
+/// reviews and apply events from replies.
+pub fn patch_series(
+    messages: &[MailMessage],
+    repo: Option<&str>,
+    config: &Config,
+) -> Vec<PatchSeries> {
+    let mut messages: Vec<&MailMessage> = messages.iter().collect();
+    messages.sort_by_key(|m| m.date);
+
+    // series are keyed by thread root and version since a v2 is commonly
+    // sent in reply to the v1 cover letter
+    let mut keys: HashMap<&str, (String, u32)> = HashMap::new();
+    let mut series: BTreeMap<(String, u32), PatchSeries> = BTreeMap::new();
+    for message in &messages {
+        let subject = match PatchSubject::parse(&message.subject) {
+            Some(subject) => subject,
+            None => continue,
+        };
+        let key = (message.thread_root().to_owned(), subject.version);
+        keys.insert(&message.message_id, key.clone());
+        let s = series.entry(key.clone()).or_insert_with(|| {
+            let author =
+                config.person_for_identity_at(&Identity::parse(&message.from), &message.date);
+            PatchSeries {
+                id: format!("{}-v{}", key.0, key.1),
+                repo: repo.map(|r| r.to_owned()),
+                title: subject.title.clone(),
+                version: subject.version,
+                normalized_author: author,
+                patches: 0,
+                expected_patches: subject.total,
+                created_at: message.date,
+                updated_at: message.date,
+                applied_at: None,
+                applied_by: None,
+                time_to_resolve: None,
+                reviews: vec![],
+                timestamp: message.date,
+            }
+        });
+        if subject.index == 0 {
+            s.title = subject.title;
+        } else {
+            s.patches += 1;
+        }
+    }
+
+    for message in &messages {
+        let key = match keys.get(message.message_id.as_str()) {
+            Some(key) => key.clone(),
+            None => {
+                // a reply, or a reply to a reply, of a patch
+                let parent = message
+                    .in_reply_to
+                    .iter()
+                    .chain(message.references.iter().rev())
+                    .find_map(|id| keys.get(id.as_str()).cloned());
+                match parent {
+                    Some(key) => {
+                        keys.insert(&message.message_id, key.clone());
+                        if is_applied_reply(message) {
+                            let s = series.get_mut(&key).unwrap();
+                            if s.applied_at.is_none() {
+                                s.applied_at = Some(message.date);
+                                s.applied_by = Some(config.person_for_identity_at(
+                                    &Identity::parse(&message.from),
+                                    &message.date,
+                                ));
+                            }
+                        }
+                        key
+                    }
+                    None => continue,
+                }
+            }
+        };
+        let s = series.get_mut(&key).unwrap();
+        s.updated_at = std::cmp::max(s.updated_at, message.date);
+        for (kind, identity) in reviews(message) {
+            let reviewer = config.person_for_identity_at(&identity, &message.date);
+            if reviewer.id == s.normalized_author.id
+                || s.reviews
+                    .iter()
+                    .any(|r| r.kind == kind && r.reviewer.id == reviewer.id)
+            {
+                continue;
+            }
+            s.reviews.push(PatchReview {
+                reviewer,
+                kind,
+                reviewed_at: message.date,
+            });
+        }
+    }
+
+    series
+        .into_values()
+        .map(|mut s| {
+            s.time_to_resolve = s.applied_at.map(|at| (at - s.created_at).num_seconds());
+            s.timestamp = s.applied_at.unwrap_or(s.updated_at);
+            s.title = config.stored_summary(&s.title).unwrap_or_default();
+            s
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(
+        id: &str,
+        parent: Option<&str>,
+        from: &str,
+        subject: &str,
+        hour: u32,
+        body: &str,
+    ) -> MailMessage {
+        MailMessage {
+            message_id: id.to_owned(),
+            in_reply_to: parent.map(|p| p.to_owned()),
+            references: parent.iter().map(|p| p.to_string()).collect(),
+            from: from.to_owned(),
+            subject: subject.to_owned(),
+            date: Utc.ymd(2019, 12, 16).and_hms(hour, 0, 0),
+            body: body.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_parse_subject() {
+        assert_eq!(
+            PatchSubject::parse("[PATCH v3 2/5] foo: fix bar"),
+            Some(PatchSubject {
+                version: 3,
+                index: 2,
+                total: 5,
+                title: "foo: fix bar".to_owned()
+            })
+        );
+        assert_eq!(PatchSubject::parse("[RFC PATCH] foo").unwrap().total, 1);
+        assert_eq!(PatchSubject::parse("Re: [PATCH 1/2] foo"), None);
+    }
+
+    #[test]
+    fn test_patch_series() {
+        let jane = "Jane <jane@example.com>";
+        let bob = "Bob <bob@example.com>";
+        let messages = vec![
+            message("0", None, jane, "[PATCH 0/2] Add bar", 1, "Cover"),
+            message("1", Some("0"), jane, "[PATCH 1/2] foo: a", 1, "a"),
+            message("2", Some("0"), jane, "[PATCH 2/2] foo: b", 1, "b"),
+            message(
+                "3",
+                Some("1"),
+                bob,
+                "Re: [PATCH 1/2] foo: a",
+                3,
+                "> Reviewed-by: Quoted <q@example.com>\nReviewed-by: Bob <bob@example.com>",
+            ),
+            message(
+                "4",
+                Some("0"),
+                bob,
+                "Re: [PATCH 0/2] Add bar",
+                5,
+                "Applied, thanks!",
+            ),
+            message("5", Some("0"), jane, "[PATCH v2 0/1] Add bar", 6, "Cover"),
+        ];
+        let series = patch_series(&messages, Some("linux"), &Config::default());
+        assert_eq!(series.len(), 2);
+        let v1 = &series[0];
+        assert_eq!(v1.title, "Add bar");
+        assert_eq!(v1.patches, 2);
+        assert_eq!(v1.reviews.len(), 1);
+        assert_eq!(v1.reviews[0].kind, ReviewKind::Reviewed);
+        assert_eq!(
+            v1.reviews[0].reviewer.email,
+            Some("bob@example.com".to_owned())
+        );
+        assert_eq!(
+            v1.applied_by.as_ref().unwrap().email,
+            Some("bob@example.com".to_owned())
+        );
+        assert_eq!(v1.time_to_resolve, Some(4 * 3600));
+        assert_eq!(series[1].version, 2);
+        assert_eq!(series[1].applied_at, None);
+    }
+}
@@ -0,0 +1,66 @@
+use git2::DiffDelta;
+use glob::Pattern;
+
+/// Limits which files within a commit are analyzed. A file is analyzed if it
+/// matches any of the `only` globs, or there are none, and matches none of
+/// the `exclude` globs.
+pub struct PathFilter {
+    only: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl PathFilter {
+    /// Invalid globs are ignored, as they are for file configs.
+    pub fn new(only: &[String], exclude: &[String]) -> PathFilter {
+        let compile = |globs: &[String]| {
+            globs
+                .iter()
+                .filter_map(|glob| Pattern::new(glob).ok())
+                .collect()
+        };
+        PathFilter {
+            only: compile(only),
+            exclude: compile(exclude),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.only.is_empty() && self.exclude.is_empty()
+    }
+
+    pub fn includes(&self, path: &str) -> bool {
+        (self.only.is_empty() || self.only.iter().any(|p| p.matches(path)))
+            && !self.exclude.iter().any(|p| p.matches(path))
+    }
+
+    /// Whether either side of a renamed file is included.
+    pub fn includes_delta(&self, delta: &DiffDelta) -> bool {
+        self.is_empty()
+            || [delta.old_file().path(), delta.new_file().path()]
+                .iter()
+                .filter_map(|path| path.and_then(|path| path.to_str()))
+                .any(|path| self.includes(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_includes() {
+        let filter = PathFilter::new(
+            &["src/**".to_string(), "Cargo.toml".to_string()],
+            &["src/vendor/**".to_string()],
+        );
+        assert!(filter.includes("src/lib.rs"));
+        assert!(filter.includes("Cargo.toml"));
+        assert!(!filter.includes("src/vendor/lib.rs"));
+        assert!(!filter.includes("docs/index.md"));
+
+        let filter = PathFilter::new(&[], &["vendor/**".to_string()]);
+        assert!(filter.includes("src/lib.rs"));
+        assert!(!filter.includes("vendor/lib.rs"));
+        assert!(PathFilter::new(&[], &[]).is_empty());
+    }
+}
@@ -0,0 +1,78 @@
+use std::fmt;
+use std::ops::Deref;
+
+use regex::Regex;
+use serde::de::{self, Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+
+use crate::error::*;
+
+/// A regex of a config, compiled as the config is loaded so that invalid
+/// ones fail the load rather than being skipped or reported for each commit.
+/// In configs it is written as a string.
+#[derive(Debug, Clone)]
+pub struct Pattern(Regex);
+
+impl Pattern {
+    pub fn new(pattern: &str) -> Result<Pattern> {
+        Ok(Pattern(Regex::new(pattern)?))
+    }
+
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+impl Deref for Pattern {
+    type Target = Regex;
+
+    fn deref(&self) -> &Regex {
+        &self.0
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Pattern) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for Pattern {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Pattern, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Regex::new(&pattern)
+            .map(Pattern)
+            .map_err(|e| de::Error::custom(format!("invalid pattern {:?}: {}", pattern, e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize() {
+        let pattern: Pattern = serde_json::from_str(r#""acme-[0-9]{6}""#).unwrap();
+        assert!(pattern.is_match("acme-123456"));
+        assert_eq!(
+            serde_json::to_string(&pattern).unwrap(),
+            r#""acme-[0-9]{6}""#
+        );
+        let err = serde_yaml::from_str::<Pattern>(r#""acme-[0-9""#).unwrap_err();
+        assert!(err.to_string().contains("invalid pattern"), "{}", err);
+    }
+}
@@ -0,0 +1,142 @@
+use chrono::prelude::*;
+use chrono::{DateTime, Duration, NaiveDate};
+
+/// Calendar period used to bucket events into snapshots.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Period {
+    /// ISO weeks starting on Monday
+    #[default]
+    Week,
+    Month,
+    /// Sprints of `days` days, one of which starts on `start`
+    Sprint {
+        start: NaiveDate,
+        days: u32,
+    },
+    /// Quarters of a fiscal year starting on the first of `start_month`,
+    /// 1 for January
+    FiscalQuarter {
+        start_month: u32,
+    },
+}
+
+impl Period {
+    /// Returns the start (inclusive) and end (exclusive) of the period
+    /// containing `timestamp`.
+    pub fn bounds(&self, timestamp: &DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+        let date = timestamp.date();
+        match self {
+            Period::Week => {
+                let start = date - Duration::days(i64::from(date.weekday().num_days_from_monday()));
+                (
+                    start.and_hms(0, 0, 0),
+                    (start + Duration::days(7)).and_hms(0, 0, 0),
+                )
+            }
+            Period::Month => {
+                let start = Utc.ymd(date.year(), date.month(), 1);
+                let end = if date.month() == 12 {
+                    Utc.ymd(date.year() + 1, 1, 1)
+                } else {
+                    Utc.ymd(date.year(), date.month() + 1, 1)
+                };
+                (start.and_hms(0, 0, 0), end.and_hms(0, 0, 0))
+            }
+            Period::Sprint { start, days } => {
+                let days = i64::from((*days).max(1));
+                let sprints = (date.naive_utc() - *start).num_days().div_euclid(days);
+                let start = *start + Duration::days(sprints * days);
+                (
+                    Utc.from_utc_date(&start).and_hms(0, 0, 0),
+                    Utc.from_utc_date(&(start + Duration::days(days)))
+                        .and_hms(0, 0, 0),
+                )
+            }
+            Period::FiscalQuarter { start_month } => {
+                let months = date.year() * 12 + date.month0() as i32;
+                let offset =
+                    (date.month0() as i32 - ((*start_month).clamp(1, 12) - 1) as i32).rem_euclid(3);
+                let first_month = |months: i32| {
+                    Utc.ymd(months.div_euclid(12), months.rem_euclid(12) as u32 + 1, 1)
+                        .and_hms(0, 0, 0)
+                };
+                (
+                    first_month(months - offset),
+                    first_month(months - offset + 3),
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bounds() {
+        // a Wednesday
+        let timestamp = Utc.ymd(2019, 12, 18).and_hms(13, 30, 0);
+        assert_eq!(
+            Period::Week.bounds(&timestamp),
+            (
+                Utc.ymd(2019, 12, 16).and_hms(0, 0, 0),
+                Utc.ymd(2019, 12, 23).and_hms(0, 0, 0)
+            )
+        );
+        assert_eq!(
+            Period::Month.bounds(&timestamp),
+            (
+                Utc.ymd(2019, 12, 1).and_hms(0, 0, 0),
+                Utc.ymd(2020, 1, 1).and_hms(0, 0, 0)
+            )
+        );
+        let sprint = Period::Sprint {
+            start: NaiveDate::from_ymd(2019, 12, 30),
+            days: 14,
+        };
+        assert_eq!(
+            sprint.bounds(&timestamp),
+            (
+                Utc.ymd(2019, 12, 16).and_hms(0, 0, 0),
+                Utc.ymd(2019, 12, 30).and_hms(0, 0, 0)
+            )
+        );
+        assert_eq!(
+            sprint.bounds(&Utc.ymd(2020, 1, 13).and_hms(0, 0, 0)).0,
+            Utc.ymd(2020, 1, 13).and_hms(0, 0, 0)
+        );
+        // fiscal years starting in February
+        let quarter = Period::FiscalQuarter { start_month: 2 };
+        assert_eq!(
+            quarter.bounds(&timestamp),
+            (
+                Utc.ymd(2019, 11, 1).and_hms(0, 0, 0),
+                Utc.ymd(2020, 2, 1).and_hms(0, 0, 0)
+            )
+        );
+        assert_eq!(
+            quarter.bounds(&Utc.ymd(2020, 1, 31).and_hms(23, 0, 0)).0,
+            Utc.ymd(2019, 11, 1).and_hms(0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let period: Period = serde_yaml::from_str("sprint: {start: 2020-01-06, days: 14}").unwrap();
+        assert_eq!(
+            period,
+            Period::Sprint {
+                start: NaiveDate::from_ymd(2020, 1, 6),
+                days: 14
+            }
+        );
+        let period: Period = serde_yaml::from_str("fiscal_quarter: {start_month: 7}").unwrap();
+        assert_eq!(period, Period::FiscalQuarter { start_month: 7 });
+        assert_eq!(
+            serde_yaml::from_str::<Period>("month").unwrap(),
+            Period::Month
+        );
+    }
+}
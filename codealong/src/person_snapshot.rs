@@ -0,0 +1,276 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+use chrono::prelude::*;
+use chrono::DateTime;
+
+use crate::analyzed_commit::AnalyzedCommit;
+use crate::heatmap::{Heatmap, Heatmaps};
+use crate::period::Period;
+use codealong_metrics::WorkStats;
+use codealong_model::{
+    metric_versions, Event, MetricVersions, Person, TIME_TO_RESOLVE, WORK_IN_PROGRESS, WORK_STATS,
+};
+
+/// Number of distinct things a person touched during a snapshot period. High
+/// work in progress is a signal of frequent context switching.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+pub struct WorkInProgress {
+    pub repos: usize,
+    pub branches: usize,
+    pub pull_requests: usize,
+}
+
+/// Per-person activity over a single period, either within a single repo or,
+/// when `repo` is `None`, across the whole org.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PersonSnapshot {
+    pub id: String,
+    pub person: Person,
+    pub repo: Option<String>,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub commits: u64,
+    pub stats: WorkStats,
+    pub pull_requests_merged: u64,
+    /// Mean seconds from opening to merging for pull requests merged during
+    /// the period.
+    pub time_to_resolve: Option<f64>,
+    pub repos: Vec<String>,
+    pub branches: Vec<String>,
+    pub pull_requests: Vec<String>,
+    pub wip: WorkInProgress,
+    #[serde(default)]
+    pub metric_versions: MetricVersions,
+}
+
+impl PersonSnapshot {
+    fn new(
+        person: Person,
+        repo: Option<String>,
+        period_start: DateTime<Utc>,
+        period_end: DateTime<Utc>,
+    ) -> PersonSnapshot {
+        PersonSnapshot {
+            id: String::new(),
+            person,
+            repos: repo.iter().cloned().collect(),
+            repo,
+            period_start,
+            period_end,
+            commits: 0,
+            stats: WorkStats::empty(),
+            pull_requests_merged: 0,
+            time_to_resolve: None,
+            branches: vec![],
+            pull_requests: vec![],
+            wip: WorkInProgress::default(),
+            metric_versions: metric_versions(&[WORK_STATS, TIME_TO_RESOLVE, WORK_IN_PROGRESS]),
+        }
+    }
+
+    /// An org-wide snapshot for `person` which `merge` can be used to add
+    /// per-repo snapshots to.
+    pub fn for_org(person: Person, period_start: DateTime<Utc>, period_end: DateTime<Utc>) -> Self {
+        let mut res = PersonSnapshot::new(person, None, period_start, period_end);
+        res.update();
+        res
+    }
+
+    /// Adds the activity of another snapshot for the same period.
+    pub fn merge(&mut self, other: &PersonSnapshot) {
+        let merged = self.pull_requests_merged + other.pull_requests_merged;
+        if merged > 0 {
+            self.time_to_resolve = Some(
+                (self.total_time_to_resolve() + other.total_time_to_resolve()) / merged as f64,
+            );
+        }
+        self.pull_requests_merged = merged;
+        self.commits += other.commits;
+        self.stats += other.stats;
+        self.repos.extend(other.repos.iter().cloned());
+        self.branches.extend(other.branches.iter().cloned());
+        self.pull_requests
+            .extend(other.pull_requests.iter().cloned());
+        self.update();
+    }
+
+    fn total_time_to_resolve(&self) -> f64 {
+        self.time_to_resolve.unwrap_or(0.0) * self.pull_requests_merged as f64
+    }
+
+    /// Dedups the work item lists and recomputes the id and WIP counts.
+    fn update(&mut self) {
+        for items in &mut [&mut self.repos, &mut self.branches, &mut self.pull_requests] {
+            items.sort();
+            items.dedup();
+        }
+        self.wip = WorkInProgress {
+            repos: self.repos.len(),
+            branches: self.branches.len(),
+            pull_requests: self.pull_requests.len(),
+        };
+        let period = self.period_start.format("%Y-%m-%d");
+        self.id = match self.repo {
+            Some(ref repo) => format!("{}-{}-{}", repo, self.person.id, period),
+            None => format!("{}-{}", self.person.id, period),
+        };
+    }
+}
+
+impl Event for PersonSnapshot {
+    fn timestamp(&self) -> &DateTime<Utc> {
+        &self.period_start
+    }
+
+    fn event_type(&self) -> &str {
+        "person_snapshot"
+    }
+
+    fn id(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.id)
+    }
+
+    fn tags(&self) -> HashSet<String> {
+        let mut tags = HashSet::new();
+        tags.insert(if self.repo.is_some() { "repo" } else { "org" }.to_owned());
+        tags
+    }
+}
+
+/// Accumulates analyzed events into per-repo, per-person snapshots, and
+/// into the daily heatmaps of each person and repo.
+pub struct PersonSnapshots {
+    period: Period,
+    snapshots: HashMap<(String, String, DateTime<Utc>), PersonSnapshot>,
+    heatmaps: Heatmaps,
+}
+
+impl PersonSnapshots {
+    pub fn new(period: Period) -> PersonSnapshots {
+        PersonSnapshots {
+            period,
+            snapshots: HashMap::new(),
+            heatmaps: Heatmaps::default(),
+        }
+    }
+
+    pub fn add_commit(&mut self, commit: &AnalyzedCommit) {
+        self.heatmaps.add_commit(commit);
+        if let (Some(person), Some(repo)) = (&commit.normalized_author, &commit.repo) {
+            let snapshot = self.snapshot(person, repo.name(), &commit.authored_at);
+            snapshot.commits += 1;
+            snapshot.stats += commit.diff.stats;
+        }
+    }
+
+    /// Records that `person` worked on a pull request from `branch` in `repo`
+    /// at the given time. `time_to_resolve` should be provided if the pull
+    /// request was merged at that time.
+    pub fn add_pull_request(
+        &mut self,
+        person: &Person,
+        timestamp: &DateTime<Utc>,
+        repo: &str,
+        branch: &str,
+        number: u64,
+        time_to_resolve: Option<i64>,
+    ) {
+        let snapshot = self.snapshot(person, repo, timestamp);
+        let mut other = PersonSnapshot::new(
+            person.clone(),
+            None,
+            snapshot.period_start,
+            snapshot.period_end,
+        );
+        if let Some(time_to_resolve) = time_to_resolve {
+            other.pull_requests_merged = 1;
+            other.time_to_resolve = Some(time_to_resolve as f64);
+        }
+        other.branches.push(format!("{}:{}", repo, branch));
+        other.pull_requests.push(format!("{}#{}", repo, number));
+        snapshot.merge(&other);
+    }
+
+    /// Records that `reviewer` submitted a review on a pull request of `repo`
+    /// at the given time.
+    pub fn add_review(&mut self, reviewer: &Person, repo: Option<&str>, timestamp: &DateTime<Utc>) {
+        self.heatmaps.add_review(reviewer, repo, timestamp);
+    }
+
+    /// Takes the heatmaps accumulated so far.
+    pub fn heatmaps(&mut self) -> Vec<Heatmap> {
+        std::mem::take(&mut self.heatmaps).finish()
+    }
+
+    pub fn finish(self) -> Vec<PersonSnapshot> {
+        let mut res: Vec<PersonSnapshot> = self
+            .snapshots
+            .into_values()
+            .map(|mut snapshot| {
+                snapshot.update();
+                snapshot
+            })
+            .collect();
+        res.sort_by(|a, b| a.id.cmp(&b.id));
+        res
+    }
+
+    fn snapshot(
+        &mut self,
+        person: &Person,
+        repo: &str,
+        timestamp: &DateTime<Utc>,
+    ) -> &mut PersonSnapshot {
+        let (period_start, period_end) = self.period.bounds(timestamp);
+        self.snapshots
+            .entry((repo.to_owned(), person.id.clone(), period_start))
+            .or_insert_with(|| {
+                PersonSnapshot::new(
+                    person.clone(),
+                    Some(repo.to_owned()),
+                    period_start,
+                    period_end,
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::Identity;
+
+    #[test]
+    fn test_per_repo_snapshots() {
+        let person = Identity::parse("Alice <alice@example.com>").to_person();
+        let monday = Utc.ymd(2019, 12, 16).and_hms(9, 0, 0);
+        let friday = Utc.ymd(2019, 12, 20).and_hms(9, 0, 0);
+        let next_week = Utc.ymd(2019, 12, 23).and_hms(9, 0, 0);
+
+        let mut snapshots = PersonSnapshots::new(Period::Week);
+        snapshots.add_pull_request(&person, &monday, "org/api", "feature-a", 1, None);
+        snapshots.add_pull_request(&person, &friday, "org/api", "feature-a", 1, Some(3600));
+        snapshots.add_pull_request(&person, &friday, "org/api", "feature-b", 7, Some(7200));
+        snapshots.add_pull_request(&person, &next_week, "org/web", "feature-c", 8, None);
+        let snapshots = snapshots.finish();
+
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(
+            snapshots[0].id,
+            "org/api-Alice <alice@example.com>-2019-12-16"
+        );
+        assert_eq!(
+            snapshots[0].wip,
+            WorkInProgress {
+                repos: 1,
+                branches: 2,
+                pull_requests: 2
+            }
+        );
+        assert_eq!(snapshots[0].pull_requests_merged, 2);
+        assert_eq!(snapshots[0].time_to_resolve, Some(5400.0));
+        assert_eq!(snapshots[1].repo, Some("org/web".to_owned()));
+        assert_eq!(snapshots[1].time_to_resolve, None);
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A stage of the commit analysis pipeline, in the order commits pass
+/// through them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    /// Walking the history for the commits to analyze.
+    Walk,
+    /// Analyzing the diffs of commits.
+    Analyze,
+    /// Writing the analyzed commits to Elasticsearch or an exporter.
+    Sink,
+}
+
+impl Stage {
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::Walk => "walk",
+            Stage::Analyze => "analyze",
+            Stage::Sink => "sink",
+        }
+    }
+}
+
+/// Throughput of a stage of the pipeline and how many items were waiting in
+/// front of it, to tell which stage is the bottleneck: a deep queue in front
+/// of the sink means indexing cannot keep up with the analysis, while an
+/// empty one means the analysis is the bottleneck and more `--jobs` help.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StageSummary {
+    pub stage: Stage,
+    pub items: u64,
+    /// Time spent in the stage, summed across threads.
+    pub busy_seconds: f64,
+    /// Items per busy second, i.e. the throughput of a single thread.
+    pub items_per_second: Option<f64>,
+    /// Depth of the queue in front of the stage, sampled as items leave it.
+    pub mean_queue_depth: Option<f64>,
+    pub max_queue_depth: u64,
+}
+
+/// Name, type, help and value of a metric.
+type Metric = (
+    &'static str,
+    &'static str,
+    &'static str,
+    fn(&StageSummary) -> Option<f64>,
+);
+
+#[derive(Debug, Clone, Default)]
+struct StageStats {
+    items: u64,
+    busy: Duration,
+    queue_samples: u64,
+    queue_depth_total: u64,
+    queue_depth_max: u64,
+}
+
+/// Collects the throughput and queue depths of the stages of the pipeline.
+/// Clones share the collected stats, so they can be recorded from several
+/// threads.
+#[derive(Debug, Clone, Default)]
+pub struct PipelineStats {
+    stages: Arc<Mutex<BTreeMap<Stage, StageStats>>>,
+}
+
+impl PipelineStats {
+    pub fn new() -> PipelineStats {
+        PipelineStats::default()
+    }
+
+    /// Runs `f` as the processing of one item by `stage`.
+    pub fn time<T, F: FnOnce() -> T>(&self, stage: Stage, f: F) -> T {
+        let start = Instant::now();
+        let res = f();
+        self.record(stage, 1, start.elapsed());
+        res
+    }
+
+    /// Records `items` processed by `stage` in `busy`.
+    pub fn record(&self, stage: Stage, items: u64, busy: Duration) {
+        let mut stages = self.stages.lock().unwrap();
+        let stats = stages.entry(stage).or_default();
+        stats.items += items;
+        stats.busy += busy;
+    }
+
+    /// Records the number of items waiting to be processed by `stage`.
+    pub fn sample_queue(&self, stage: Stage, depth: usize) {
+        let mut stages = self.stages.lock().unwrap();
+        let stats = stages.entry(stage).or_default();
+        stats.queue_samples += 1;
+        stats.queue_depth_total += depth as u64;
+        stats.queue_depth_max = stats.queue_depth_max.max(depth as u64);
+    }
+
+    /// The stats of each stage which processed any items, in pipeline order.
+    pub fn summary(&self) -> Vec<StageSummary> {
+        self.stages
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(&stage, stats)| {
+                let busy_seconds = stats.busy.as_secs_f64();
+                StageSummary {
+                    stage,
+                    items: stats.items,
+                    busy_seconds,
+                    items_per_second: if busy_seconds > 0.0 {
+                        Some(stats.items as f64 / busy_seconds)
+                    } else {
+                        None
+                    },
+                    mean_queue_depth: if stats.queue_samples > 0 {
+                        Some(stats.queue_depth_total as f64 / stats.queue_samples as f64)
+                    } else {
+                        None
+                    },
+                    max_queue_depth: stats.queue_depth_max,
+                }
+            })
+            .collect()
+    }
+
+    /// Writes the stats in the Prometheus text exposition format, e.g. for
+    /// the textfile collector of the node exporter.
+    pub fn write_prometheus<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        let summary = self.summary();
+        let metrics: [Metric; 4] = [
+            (
+                "codealong_pipeline_items_total",
+                "counter",
+                "Items processed by each stage of the analysis pipeline.",
+                |s| Some(s.items as f64),
+            ),
+            (
+                "codealong_pipeline_busy_seconds_total",
+                "counter",
+                "Time spent in each stage of the analysis pipeline, summed across threads.",
+                |s| Some(s.busy_seconds),
+            ),
+            (
+                "codealong_pipeline_queue_depth_mean",
+                "gauge",
+                "Mean number of items waiting in front of each stage of the analysis pipeline.",
+                |s| s.mean_queue_depth,
+            ),
+            (
+                "codealong_pipeline_queue_depth_max",
+                "gauge",
+                "Maximum number of items waiting in front of each stage of the analysis pipeline.",
+                |s| Some(s.max_queue_depth as f64),
+            ),
+        ];
+        for (name, kind, help, value) in metrics.iter() {
+            writeln!(writer, "# HELP {} {}", name, help)?;
+            writeln!(writer, "# TYPE {} {}", name, kind)?;
+            for stage in &summary {
+                if let Some(value) = value(stage) {
+                    writeln!(
+                        writer,
+                        "{}{{stage=\"{}\"}} {}",
+                        name,
+                        stage.stage.name(),
+                        value
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        let stats = PipelineStats::new();
+        stats.record(Stage::Sink, 2, Duration::from_secs(1));
+        stats.record(Stage::Analyze, 4, Duration::from_secs(1));
+        stats.record(Stage::Analyze, 4, Duration::from_secs(1));
+        stats.sample_queue(Stage::Sink, 3);
+        stats.sample_queue(Stage::Sink, 0);
+        assert_eq!(stats.time(Stage::Walk, || 1), 1);
+
+        let summary = stats.summary();
+        assert_eq!(
+            summary.iter().map(|s| s.stage).collect::<Vec<_>>(),
+            vec![Stage::Walk, Stage::Analyze, Stage::Sink]
+        );
+        assert_eq!(summary[1].items, 8);
+        assert_eq!(summary[1].items_per_second, Some(4.0));
+        assert_eq!(summary[1].mean_queue_depth, None);
+        assert_eq!(summary[2].mean_queue_depth, Some(1.5));
+        assert_eq!(summary[2].max_queue_depth, 3);
+    }
+
+    #[test]
+    fn test_write_prometheus() {
+        let stats = PipelineStats::new();
+        stats.record(Stage::Sink, 2, Duration::from_millis(500));
+        stats.sample_queue(Stage::Sink, 3);
+        let mut out = vec![];
+        stats.write_prometheus(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("# TYPE codealong_pipeline_items_total counter\n"));
+        assert!(out.contains("codealong_pipeline_items_total{stage=\"sink\"} 2\n"));
+        assert!(out.contains("codealong_pipeline_busy_seconds_total{stage=\"sink\"} 0.5\n"));
+        assert!(out.contains("codealong_pipeline_queue_depth_max{stage=\"sink\"} 3\n"));
+    }
+}
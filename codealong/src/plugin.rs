@@ -0,0 +1,390 @@
+use std::fmt;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::Mutex;
+
+use serde_json::{Map, Value};
+
+use crate::error::{ErrorKind, Result};
+
+/// What a plugin does with the events of an analysis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Hook {
+    /// Returns tags which are added to the `tags` of the event.
+    Tagger,
+    /// Returns fields which are added to the event, replacing those of the
+    /// same name.
+    Enricher,
+    /// Receives every event written, in addition to the sink of the run.
+    Sink,
+}
+
+/// What a plugin prints when run with `describe`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PluginManifest {
+    pub hooks: Vec<Hook>,
+    /// Event types the plugin is given, e.g. `commit`, all if empty.
+    #[serde(default)]
+    pub event_types: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Request<'a> {
+    hook: Hook,
+    event_type: &'a str,
+    id: &'a str,
+    event: &'a Value,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Response {
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    fields: Map<String, Value>,
+    error: Option<String>,
+}
+
+/// An executable extending the analysis, e.g. to tag commits by the rules of
+/// an organization or to write events to an internal system, without
+/// rebuilding codealong.
+///
+/// A plugin is run with `describe` to print its `PluginManifest` as JSON,
+/// and then once per run with `serve`, after which it is sent a request per
+/// event on stdin, as a line of JSON with the `hook`, `event_type`, `id` and
+/// `event`, and answers each with a line of JSON: `{"tags": [...]}` for a
+/// tagger, `{"fields": {...}}` for an enricher, `{}` for a sink, or
+/// `{"error": "..."}`.
+pub struct Plugin {
+    name: String,
+    path: PathBuf,
+    manifest: PluginManifest,
+    process: Mutex<Option<PluginProcess>>,
+}
+
+struct PluginProcess {
+    child: Child,
+    /// Closed when the process is dropped, which tells the plugin there are
+    /// no more events.
+    stdin: Option<ChildStdin>,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Plugin {
+    /// Loads the plugin at `path` by running it with `describe`.
+    pub fn load(path: &Path) -> Result<Plugin> {
+        let name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or_default()
+            .to_owned();
+        let output = Command::new(path).arg("describe").output()?;
+        if !output.status.success() {
+            return Err(ErrorKind::PluginFailed(
+                name,
+                format!(
+                    "describe exited with {}: {}",
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            )
+            .into());
+        }
+        let manifest = serde_json::from_slice(&output.stdout).map_err(|e| {
+            ErrorKind::PluginFailed(name.clone(), format!("invalid manifest: {}", e))
+        })?;
+        Ok(Plugin {
+            name,
+            path: path.to_owned(),
+            manifest,
+            process: Mutex::new(None),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn manifest(&self) -> &PluginManifest {
+        &self.manifest
+    }
+
+    fn handles(&self, hook: Hook, event_type: &str) -> bool {
+        self.manifest.hooks.contains(&hook)
+            && (self.manifest.event_types.is_empty()
+                || self.manifest.event_types.iter().any(|t| t == event_type))
+    }
+
+    fn call(&self, hook: Hook, event_type: &str, id: &str, event: &Value) -> Result<Response> {
+        let mut process = self.process.lock().unwrap();
+        if process.is_none() {
+            *process = Some(self.spawn()?);
+        }
+        let process = process.as_mut().unwrap();
+        let request = Request {
+            hook,
+            event_type,
+            id,
+            event,
+        };
+        let stdin = process.stdin.as_mut().unwrap();
+        serde_json::to_writer(&mut *stdin, &request)?;
+        stdin.write_all(b"\n")?;
+        stdin.flush()?;
+        let mut line = String::new();
+        if process.stdout.read_line(&mut line)? == 0 {
+            return Err(ErrorKind::PluginFailed(
+                self.name.clone(),
+                "exited while serving".to_owned(),
+            )
+            .into());
+        }
+        let response: Response = serde_json::from_str(&line).map_err(|e| {
+            ErrorKind::PluginFailed(self.name.clone(), format!("invalid response: {}", e))
+        })?;
+        match response.error {
+            Some(error) => Err(ErrorKind::PluginFailed(self.name.clone(), error).into()),
+            None => Ok(response),
+        }
+    }
+
+    fn spawn(&self) -> Result<PluginProcess> {
+        let mut child = Command::new(&self.path)
+            .arg("serve")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        Ok(PluginProcess {
+            stdin: child.stdin.take(),
+            stdout: BufReader::new(child.stdout.take().unwrap()),
+            child,
+        })
+    }
+}
+
+impl fmt::Debug for Plugin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Plugin")
+            .field("name", &self.name)
+            .field("path", &self.path)
+            .field("manifest", &self.manifest)
+            .finish()
+    }
+}
+
+impl Drop for PluginProcess {
+    fn drop(&mut self) {
+        self.stdin.take();
+        let _ = self.child.wait();
+    }
+}
+
+/// The plugins discovered in a plugins directory, by default
+/// `~/.config/codealong/plugins`.
+#[derive(Debug, Default)]
+pub struct Plugins {
+    plugins: Vec<Plugin>,
+    /// Files of the directory which could not be loaded, and why.
+    skipped: Vec<(PathBuf, String)>,
+}
+
+impl Plugins {
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("codealong").join("plugins"))
+    }
+
+    /// Loads the executables in `dir`, in order of their names. Files which
+    /// are not executable are ignored, and plugins which cannot be loaded,
+    /// e.g. WebAssembly modules, which need a runtime codealong is not built
+    /// with, are skipped.
+    pub fn discover(dir: &Path) -> Result<Plugins> {
+        let mut plugins = Plugins::default();
+        if !dir.is_dir() {
+            return Ok(plugins);
+        }
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::result::Result<_, _>>()?;
+        paths.sort();
+        for path in paths {
+            if path.extension().is_some_and(|ext| ext == "wasm") {
+                plugins.skipped.push((
+                    path,
+                    "WebAssembly plugins are not supported by this build".to_owned(),
+                ));
+                continue;
+            }
+            if !is_executable(&path) {
+                continue;
+            }
+            match Plugin::load(&path) {
+                Ok(plugin) => plugins.plugins.push(plugin),
+                Err(e) => plugins.skipped.push((path, e.to_string())),
+            }
+        }
+        Ok(plugins)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.plugins.is_empty()
+    }
+
+    pub fn plugins(&self) -> &[Plugin] {
+        &self.plugins
+    }
+
+    pub fn skipped(&self) -> &[(PathBuf, String)] {
+        &self.skipped
+    }
+
+    /// Adds the tags of the taggers to the `tags` of `event`, then the
+    /// fields of the enrichers, in the order of the plugins.
+    pub fn process(&self, event_type: &str, id: &str, event: &mut Value) -> Result<()> {
+        for plugin in &self.plugins {
+            if plugin.handles(Hook::Tagger, event_type) {
+                let tags = plugin.call(Hook::Tagger, event_type, id, event)?.tags;
+                add_tags(event, tags);
+            }
+        }
+        for plugin in &self.plugins {
+            if plugin.handles(Hook::Enricher, event_type) {
+                let fields = plugin.call(Hook::Enricher, event_type, id, event)?.fields;
+                if let Value::Object(ref mut map) = *event {
+                    map.extend(fields);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Passes `event` to the sinks.
+    pub fn sink(&self, event_type: &str, id: &str, event: &Value) -> Result<()> {
+        for plugin in &self.plugins {
+            if plugin.handles(Hook::Sink, event_type) {
+                plugin.call(Hook::Sink, event_type, id, event)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether any plugin is a sink, for any event type.
+    pub fn has_sinks(&self) -> bool {
+        self.plugins
+            .iter()
+            .any(|plugin| plugin.manifest.hooks.contains(&Hook::Sink))
+    }
+}
+
+fn add_tags(event: &mut Value, tags: Vec<String>) {
+    if tags.is_empty() {
+        return;
+    }
+    if let Value::Object(ref mut map) = *event {
+        let existing = map.entry("tags").or_insert_with(|| Value::Array(vec![]));
+        if !existing.is_array() {
+            *existing = Value::Array(vec![existing.clone()]);
+        }
+        let existing = existing.as_array_mut().unwrap();
+        for tag in tags {
+            let tag = Value::String(tag);
+            if !existing.contains(&tag) {
+                existing.push(tag);
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path)
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .is_some_and(|ext| ext == "exe" || ext == "bat" || ext == "cmd")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::os::unix::fs::PermissionsExt;
+    use tempdir::TempDir;
+
+    fn write_plugin(dir: &Path, name: &str, script: &str) {
+        let path = dir.join(name);
+        fs::write(&path, script).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn test_discover_and_process() {
+        let dir = TempDir::new("plugins").unwrap();
+        write_plugin(
+            dir.path(),
+            "a-tagger",
+            "#!/bin/sh\n\
+             [ \"$1\" = describe ] && { echo '{\"hooks\": [\"tagger\"], \"event_types\": [\"commit\"]}'; exit; }\n\
+             while read -r line; do echo '{\"tags\": [\"reviewed\"]}'; done\n",
+        );
+        write_plugin(
+            dir.path(),
+            "b-enricher",
+            "#!/bin/sh\n\
+             [ \"$1\" = describe ] && { echo '{\"hooks\": [\"enricher\", \"sink\"]}'; exit; }\n\
+             while read -r line; do\n\
+               case \"$line\" in *'\"hook\":\"sink\"'*) echo '{}';; *) echo '{\"fields\": {\"cost_center\": \"42\"}}';; esac\n\
+             done\n",
+        );
+        write_plugin(dir.path(), "c-broken", "#!/bin/sh\necho not json\n");
+        fs::write(dir.path().join("d.wasm"), b"\0asm").unwrap();
+        fs::write(dir.path().join("README"), b"not a plugin").unwrap();
+
+        let plugins = Plugins::discover(dir.path()).unwrap();
+        let names: Vec<_> = plugins.plugins().iter().map(|p| p.name()).collect();
+        assert_eq!(names, vec!["a-tagger", "b-enricher"]);
+        assert_eq!(plugins.skipped().len(), 2);
+        assert!(plugins.has_sinks());
+
+        let mut commit = json!({ "id": "abc", "tags": ["docs"] });
+        plugins.process("commit", "abc", &mut commit).unwrap();
+        assert_eq!(
+            commit,
+            json!({ "id": "abc", "tags": ["docs", "reviewed"], "cost_center": "42" })
+        );
+        let mut pull_request = json!({ "id": 1 });
+        plugins
+            .process("pull_request", "1", &mut pull_request)
+            .unwrap();
+        assert_eq!(pull_request, json!({ "id": 1, "cost_center": "42" }));
+        plugins.sink("commit", "abc", &commit).unwrap();
+    }
+
+    #[test]
+    fn test_plugin_error() {
+        let dir = TempDir::new("plugins").unwrap();
+        write_plugin(
+            dir.path(),
+            "failing",
+            "#!/bin/sh\n\
+             [ \"$1\" = describe ] && { echo '{\"hooks\": [\"tagger\"]}'; exit; }\n\
+             while read -r line; do echo '{\"error\": \"no rules\"}'; done\n",
+        );
+        let plugins = Plugins::discover(dir.path()).unwrap();
+        let err = plugins
+            .process("commit", "abc", &mut json!({}))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "plugin failing failed: no rules");
+        assert!(Plugins::discover(&dir.path().join("missing"))
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,167 @@
+use std::fs::File;
+use std::path::Path;
+
+use git2::{Commit, Repository};
+use linked_hash_map::LinkedHashMap;
+
+use crate::analyzed_commit::AnalyzedCommit;
+use crate::compliance::{check_compliance, RequiredTrailer};
+use crate::error::*;
+use crate::path_filter::PathFilter;
+use crate::text::commit_message;
+use crate::utils::changed_paths;
+
+/// Rules a range of commits must follow, e.g. to gate pushes in CI.
+///
+/// ```yaml
+/// max_lines: 800
+/// max_commit_lines: 400
+/// required:
+///   ticket:
+///     pattern: '[A-Z]+-[0-9]+'
+/// frozen_paths:
+///   - 'migrations/**'
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct Policy {
+    /// Maximum lines added and deleted across the whole range.
+    #[serde(default)]
+    pub max_lines: Option<u64>,
+
+    /// Maximum lines added and deleted by a single commit.
+    #[serde(default)]
+    pub max_commit_lines: Option<u64>,
+
+    /// References every commit message must contain.
+    #[serde(default)]
+    pub required: LinkedHashMap<String, RequiredTrailer>,
+
+    /// Globs of files which must not be changed.
+    #[serde(default)]
+    pub frozen_paths: Vec<String>,
+}
+
+/// A rule of a policy broken by a commit, or by the range as a whole.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyViolation {
+    pub rule: String,
+    pub commit: Option<String>,
+    pub message: String,
+}
+
+impl Policy {
+    pub fn from_path(path: &Path) -> Result<Policy> {
+        Ok(serde_yaml::from_reader(File::open(path)?)?)
+    }
+
+    pub fn checker(&self) -> PolicyChecker<'_> {
+        PolicyChecker {
+            policy: self,
+            frozen: PathFilter::new(&self.frozen_paths, &[]),
+            lines: 0,
+            violations: vec![],
+        }
+    }
+}
+
+/// Checks commits of a range one at a time against a policy.
+pub struct PolicyChecker<'a> {
+    policy: &'a Policy,
+    frozen: PathFilter,
+    lines: u64,
+    violations: Vec<PolicyViolation>,
+}
+
+impl<'a> PolicyChecker<'a> {
+    pub fn check_commit(
+        &mut self,
+        repo: &Repository,
+        commit: &Commit,
+        analyzed: &AnalyzedCommit,
+    ) -> Result<()> {
+        let id = Some(analyzed.id.clone());
+        let lines = analyzed.diff.line_stats.added + analyzed.diff.line_stats.deleted;
+        self.lines += lines;
+        if let Some(max) = self.policy.max_commit_lines {
+            if lines > max {
+                self.violations.push(PolicyViolation {
+                    rule: "max_commit_lines".to_owned(),
+                    commit: id.clone(),
+                    message: format!("changes {} lines, more than the limit of {}", lines, max),
+                });
+            }
+        }
+        let compliance = check_compliance(&commit_message(commit), &self.policy.required);
+        for missing in compliance.missing {
+            self.violations.push(PolicyViolation {
+                rule: "required".to_owned(),
+                commit: id.clone(),
+                message: format!("commit message is missing the required {}", missing),
+            });
+        }
+        if !self.policy.frozen_paths.is_empty() {
+            for path in changed_paths(repo, commit)? {
+                if self.frozen.includes(&path) {
+                    self.violations.push(PolicyViolation {
+                        rule: "frozen_paths".to_owned(),
+                        commit: id.clone(),
+                        message: format!("changes frozen path {}", path),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Vec<PolicyViolation> {
+        if let Some(max) = self.policy.max_lines {
+            if self.lines > max {
+                self.violations.push(PolicyViolation {
+                    rule: "max_lines".to_owned(),
+                    commit: None,
+                    message: format!(
+                        "changes {} lines in total, more than the limit of {}",
+                        self.lines, max
+                    ),
+                });
+            }
+        }
+        self.violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commit_analyzer::CommitAnalyzer;
+    use crate::repo_config::RepoConfig;
+    use crate::test::build_test_logger;
+
+    #[test]
+    fn test_check() -> Result<()> {
+        let policy: Policy = serde_yaml::from_str(
+            "max_lines: 1\n\
+             required:\n  ticket:\n    pattern: '[A-Z]+-[0-9]+'\n\
+             frozen_paths:\n  - '*'\n",
+        )?;
+        let repo = Repository::open("./fixtures/repos/simple")?;
+        let commit = repo.head()?.peel_to_commit()?;
+        let config = RepoConfig::default();
+        let analyzed =
+            CommitAnalyzer::new(&repo, commit.clone(), &config, &build_test_logger()).analyze()?;
+
+        let mut checker = policy.checker();
+        checker.check_commit(&repo, &commit, &analyzed)?;
+        let violations = checker.finish();
+        let rules: Vec<&str> = violations.iter().map(|v| v.rule.as_str()).collect();
+        assert!(rules.contains(&"required"));
+        assert!(rules.contains(&"frozen_paths"));
+        assert_eq!(rules.last(), Some(&"max_lines"));
+
+        let policy = Policy::default();
+        let mut checker = policy.checker();
+        checker.check_commit(&repo, &commit, &analyzed)?;
+        assert!(checker.finish().is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+use git2::{IndexAddOption, Repository, Signature};
+
+use crate::analyzed_commit::AnalyzedCommit;
+use crate::commit_analyzer::CommitAnalyzer;
+use crate::error::Result;
+use crate::repo_config::RepoConfig;
+use crate::slog::Logger;
+
+/// Analyzes uncommitted changes against `HEAD` as if they were committed
+/// with `message`, so that a change can be previewed before committing it.
+/// Only staged changes are included when `staged` is set, otherwise all
+/// changes in the working tree, including untracked files, are.
+///
+/// The tree and commit are written to the object database so that the
+/// regular commit analysis can be used, but no reference or index is
+/// updated.
+pub fn preview(
+    repo: &Repository,
+    config: &RepoConfig,
+    staged: bool,
+    message: &str,
+    logger: &Logger,
+) -> Result<AnalyzedCommit> {
+    let mut index = repo.index()?;
+    if !staged {
+        index.add_all(["*"].iter(), IndexAddOption::DEFAULT, None)?;
+        index.update_all(["*"].iter(), None)?;
+    }
+    let tree = repo.find_tree(index.write_tree()?)?;
+    // discard the working tree changes added to the in-memory index
+    index.read(true)?;
+    let head = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+    let signature = repo
+        .signature()
+        .or_else(|_| Signature::now("unknown", "unknown"))?;
+    let parents: Vec<_> = head.iter().collect();
+    let id = repo.commit(None, &signature, &signature, message, &tree, &parents)?;
+    let commit = repo.find_commit(id)?;
+    CommitAnalyzer::new(repo, commit, config, logger).analyze()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::build_test_logger;
+    use std::fs;
+
+    #[test]
+    fn test_preview() -> Result<()> {
+        let tmp_dir = tempdir::TempDir::new("preview")?;
+        let repo = Repository::init(tmp_dir.path())?;
+        let sig = Signature::now("Test", "test@example.com")?;
+        let tree = repo.find_tree(repo.treebuilder(None)?.write()?)?;
+        repo.commit(Some("HEAD"), &sig, &sig, "base", &tree, &[])?;
+
+        fs::write(tmp_dir.path().join("staged.txt"), "one\ntwo\n")?;
+        let mut index = repo.index()?;
+        index.add_path(std::path::Path::new("staged.txt"))?;
+        index.write()?;
+        fs::write(tmp_dir.path().join("unstaged.txt"), "three\n")?;
+
+        let config = RepoConfig::default();
+        let logger = build_test_logger();
+        let analyzed = preview(&repo, &config, true, "Preview", &logger)?;
+        assert_eq!(analyzed.diff.line_stats.added, 2);
+        assert_eq!(analyzed.summary, Some("Preview".to_owned()));
+        let analyzed = preview(&repo, &config, false, "Preview", &logger)?;
+        assert_eq!(analyzed.diff.line_stats.added, 3);
+
+        // nothing is committed
+        assert_eq!(repo.head()?.peel_to_commit()?.summary(), Some("base"));
+        assert!(repo
+            .index()?
+            .get_path(std::path::Path::new("unstaged.txt"), 0)
+            .is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,223 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Collects the wall time spent in nested spans of work, e.g. analyzing the
+/// commits of a repo, to show where the time of an analysis goes. Clones
+/// share the collected times, so spans can be opened from several threads.
+#[derive(Debug, Clone, Default)]
+pub struct Profiler {
+    /// Time spent in each stack of spans, excluding their child spans, in
+    /// microseconds.
+    stacks: Arc<Mutex<BTreeMap<String, u64>>>,
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler::default()
+    }
+
+    /// Opens a top level span, which is closed when dropped.
+    pub fn span(&self, name: &str) -> Span {
+        Span::new(self.clone(), frame(name), None)
+    }
+
+    /// Time spent in each stack of spans, excluding their child spans, in
+    /// microseconds.
+    pub fn stacks(&self) -> BTreeMap<String, u64> {
+        self.stacks.lock().unwrap().clone()
+    }
+
+    /// Writes the collected times in the folded stack format read by
+    /// flamegraph tools, e.g. `inferno-flamegraph` or speedscope: one line
+    /// per stack of `;` separated spans followed by its microseconds.
+    pub fn write_folded<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        for (stack, micros) in self.stacks() {
+            writeln!(writer, "{} {}", stack, micros)?;
+        }
+        Ok(())
+    }
+
+    /// Records `elapsed` spent in `name`, for work timed by someone else,
+    /// e.g. a `Progress`. The time is nested within the innermost span open
+    /// on the current thread, or recorded as a top level span if there is
+    /// none, e.g. on the worker threads of a parallel analysis.
+    pub fn record_nested(&self, name: &str, elapsed: Duration) {
+        let micros = micros(elapsed);
+        let parent = OPEN_SPANS.with(|open| {
+            open.borrow()
+                .iter()
+                .rev()
+                .find(|span| Arc::ptr_eq(&span.stacks, &self.stacks))
+                .map(|span| (span.stack.clone(), span.children.clone()))
+        });
+        match parent {
+            Some((stack, children)) => {
+                self.record(&format!("{};{}", stack, frame(name)), micros);
+                children.fetch_add(micros, Ordering::SeqCst);
+            }
+            None => self.record(&frame(name), micros),
+        }
+    }
+
+    fn record(&self, stack: &str, micros: u64) {
+        *self
+            .stacks
+            .lock()
+            .unwrap()
+            .entry(stack.to_owned())
+            .or_insert(0) += micros;
+    }
+}
+
+/// A span open on a thread, which work timed by someone else is nested in.
+struct OpenSpan {
+    stacks: Arc<Mutex<BTreeMap<String, u64>>>,
+    stack: String,
+    children: Arc<AtomicU64>,
+}
+
+thread_local! {
+    static OPEN_SPANS: RefCell<Vec<OpenSpan>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A span of work being timed, see `Profiler`.
+#[derive(Debug)]
+pub struct Span {
+    profiler: Profiler,
+    stack: String,
+    start: Instant,
+    /// Microseconds spent in the child spans closed so far.
+    children: Arc<AtomicU64>,
+    parent_children: Option<Arc<AtomicU64>>,
+}
+
+impl Span {
+    fn new(profiler: Profiler, stack: String, parent_children: Option<Arc<AtomicU64>>) -> Span {
+        let children = Arc::new(AtomicU64::new(0));
+        OPEN_SPANS.with(|open| {
+            open.borrow_mut().push(OpenSpan {
+                stacks: profiler.stacks.clone(),
+                stack: stack.clone(),
+                children: children.clone(),
+            })
+        });
+        Span {
+            profiler,
+            stack,
+            start: Instant::now(),
+            children,
+            parent_children,
+        }
+    }
+
+    pub fn profiler(&self) -> &Profiler {
+        &self.profiler
+    }
+
+    /// Opens a span nested within this one, which is closed when dropped.
+    pub fn child(&self, name: &str) -> Span {
+        Span::new(
+            self.profiler.clone(),
+            format!("{};{}", self.stack, frame(name)),
+            Some(self.children.clone()),
+        )
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let micros = micros(self.start.elapsed());
+        // spans are usually closed on the thread which opened them
+        OPEN_SPANS.with(|open| {
+            let mut open = open.borrow_mut();
+            if let Some(index) = open
+                .iter()
+                .rposition(|span| Arc::ptr_eq(&span.children, &self.children))
+            {
+                open.remove(index);
+            }
+        });
+        let children = self.children.load(Ordering::SeqCst);
+        self.profiler
+            .record(&self.stack, micros.saturating_sub(children));
+        if let Some(ref parent_children) = self.parent_children {
+            parent_children.fetch_add(micros, Ordering::SeqCst);
+        }
+    }
+}
+
+fn micros(elapsed: Duration) -> u64 {
+    elapsed.as_secs() * 1_000_000 + u64::from(elapsed.subsec_micros())
+}
+
+/// `;` separates the spans of a stack, and the last space the stack from
+/// its time.
+fn frame(name: &str) -> String {
+    name.replace(';', ":").replace(' ', "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    #[test]
+    fn test_nested_spans() {
+        let profiler = Profiler::new();
+        {
+            let repo = profiler.span("kevinastone/codealong");
+            sleep(Duration::from_millis(2));
+            let commits = repo.child("commits");
+            for _ in 0..2 {
+                let _commit = commits.child("analyze commit");
+                sleep(Duration::from_millis(2));
+            }
+        }
+        let stacks = profiler.stacks();
+        assert_eq!(
+            stacks.keys().collect::<Vec<_>>(),
+            vec![
+                "kevinastone/codealong",
+                "kevinastone/codealong;commits",
+                "kevinastone/codealong;commits;analyze_commit",
+            ]
+        );
+        // child spans are excluded from the time of their parents
+        assert!(stacks["kevinastone/codealong;commits;analyze_commit"] >= 4_000);
+        assert!(stacks["kevinastone/codealong;commits"] < 4_000);
+        assert!(stacks["kevinastone/codealong"] >= 2_000);
+    }
+
+    #[test]
+    fn test_record_nested() {
+        let profiler = Profiler::new();
+        profiler.record_nested("blame", Duration::from_millis(1));
+        {
+            let _commit = profiler.span("analyze_commit");
+            profiler.record_nested("blame", Duration::from_secs(60));
+            // spans of other profilers are not nested in
+            Profiler::new().record_nested("blame", Duration::from_secs(1));
+        }
+        let stacks = profiler.stacks();
+        assert_eq!(stacks["blame"], 1_000);
+        assert_eq!(stacks["analyze_commit;blame"], 60_000_000);
+        // recorded spans are excluded from the time of their parents
+        assert!(stacks["analyze_commit"] < 1_000_000);
+    }
+
+    #[test]
+    fn test_write_folded() {
+        let profiler = Profiler::new();
+        profiler.record("a;b", 5);
+        profiler.record("a", 2);
+        profiler.record("a;b", 1);
+        let mut folded = vec![];
+        profiler.write_folded(&mut folded).unwrap();
+        assert_eq!(String::from_utf8(folded).unwrap(), "a 2\na;b 6\n");
+    }
+}
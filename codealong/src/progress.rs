@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+/// Stage of analyzing a commit which diffs it against a parent.
+pub const DIFF_STAGE: &str = "diff";
+
+/// Stage of analyzing a file which blames its previous version.
+pub const BLAME_STAGE: &str = "blame";
+
+/// Stage of listing pull requests which fetches a page over the network.
+pub const FETCH_PAGE_STAGE: &str = "fetch_page";
+
+/// Receives the progress of an analysis while it runs, e.g. to render it or to
+/// time where the analysis spends its time. Analyses report to it from the
+/// threads they run on, and every method does nothing by default.
+pub trait Progress: Send + Sync {
+    /// `done` of the `total` commits of a walk were analyzed.
+    fn commits_analyzed(&self, _done: usize, _total: usize) {}
+
+    /// Started analyzing the changes of a commit to `path`, which on a giant
+    /// commit may take a while.
+    fn file_started(&self, _path: &str) {}
+
+    /// Page `page`, counting from one, of the `pages` of a listing, if known,
+    /// was fetched.
+    fn page_fetched(&self, _page: usize, _pages: Option<usize>) {}
+
+    /// A `stage`, e.g. `DIFF_STAGE`, took `elapsed`.
+    fn timed(&self, _stage: &str, _elapsed: Duration) {}
+}
+
+/// Reports nothing.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoProgress;
+
+impl Progress for NoProgress {}
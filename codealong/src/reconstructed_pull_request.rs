@@ -0,0 +1,330 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+use chrono::prelude::*;
+use chrono::DateTime;
+use git2::{Commit, Repository};
+use regex::Regex;
+
+use crate::error::*;
+use crate::identity::Identity;
+use crate::repo_config::RepoConfig;
+use crate::repo_info::PartialRepoInfo;
+use crate::text::commit_message;
+use crate::utils::convert_time;
+use codealong_metrics::LineStats;
+use codealong_model::{Event, Person};
+
+/// How the pull request a merge commit landed was recognized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeSource {
+    /// `Merge pull request #123 from owner/branch`
+    Github,
+    /// `Merge branch 'branch' into 'main'` with `See merge request group/project!123`
+    Gitlab,
+    /// `Merged in branch (pull request #123)`
+    Bitbucket,
+    /// `Merged PR 123: title`
+    AzureDevops,
+    /// `Merge branch 'branch'`, e.g. merged locally and pushed.
+    Branch,
+    /// A merge commit with any other message.
+    Topology,
+}
+
+/// What the message of a merge commit tells about the pull request it
+/// landed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeMessage {
+    pub source: MergeSource,
+    pub number: Option<u64>,
+    pub source_branch: Option<String>,
+    pub target_branch: Option<String>,
+    pub title: Option<String>,
+}
+
+impl MergeMessage {
+    /// Parses the message of a merge commit. Merges of a remote into a
+    /// local branch, as made by `git pull`, did not land a pull request and
+    /// are `None`.
+    pub fn parse(message: &str) -> Option<MergeMessage> {
+        lazy_static! {
+            static ref GITHUB_REGEX: Regex =
+                Regex::new(r"^Merge pull request #(\d+) from (\S+)").unwrap();
+            static ref GITLAB_REGEX: Regex =
+                Regex::new(r"(?m)^See merge request \S+!(\d+)\s*$").unwrap();
+            static ref BITBUCKET_REGEX: Regex =
+                Regex::new(r"^Merged in (\S+) \(pull request #(\d+)\)").unwrap();
+            static ref AZURE_DEVOPS_REGEX: Regex = Regex::new(r"^Merged PR (\d+): (.+)$").unwrap();
+            static ref PULL_REGEX: Regex =
+                Regex::new(r"^Merge (remote-tracking )?branch '[^']+' of ").unwrap();
+            static ref BRANCH_REGEX: Regex =
+                Regex::new(r"^Merge (?:remote-tracking )?branch '([^']+)'(?: into '?([^'\s]+)'?)?")
+                    .unwrap();
+        }
+        let mut lines = message.lines();
+        let subject = lines.next().unwrap_or_default().trim();
+        // the title of the pull request, if any, follows the subject
+        let title = lines
+            .map(|line| line.trim())
+            .find(|line| !line.is_empty())
+            .filter(|line| !GITLAB_REGEX.is_match(line))
+            .map(|line| line.to_owned());
+        let mut res = MergeMessage {
+            source: MergeSource::Topology,
+            number: None,
+            source_branch: None,
+            target_branch: None,
+            title: None,
+        };
+        if let Some(c) = GITHUB_REGEX.captures(subject) {
+            res.source = MergeSource::Github;
+            res.number = c[1].parse().ok();
+            // the branch is qualified with the owner of the fork
+            res.source_branch = Some(match c[2].find('/') {
+                Some(i) => c[2][i + 1..].to_owned(),
+                None => c[2].to_owned(),
+            });
+            res.title = title;
+        } else if let Some(c) = BITBUCKET_REGEX.captures(subject) {
+            res.source = MergeSource::Bitbucket;
+            res.source_branch = Some(c[1].to_owned());
+            res.number = c[2].parse().ok();
+            res.title = title;
+        } else if let Some(c) = AZURE_DEVOPS_REGEX.captures(subject) {
+            res.source = MergeSource::AzureDevops;
+            res.number = c[1].parse().ok();
+            res.title = Some(c[2].trim().to_owned());
+        } else if PULL_REGEX.is_match(subject) {
+            return None;
+        } else if let Some(c) = BRANCH_REGEX.captures(subject) {
+            res.source_branch = Some(c[1].to_owned());
+            res.target_branch = c.get(2).map(|m| m.as_str().to_owned());
+            match GITLAB_REGEX.captures(message) {
+                Some(c) => {
+                    res.source = MergeSource::Gitlab;
+                    res.number = c[1].parse().ok();
+                    res.title = title;
+                }
+                None => res.source = MergeSource::Branch,
+            }
+        }
+        Some(res)
+    }
+}
+
+/// A pull request reconstructed from the merge commit which landed it, for
+/// repos whose forge is not reachable. It is opened when the first of the
+/// commits it merged was authored, which approximates the cycle time of the
+/// actual pull request from below, and it is authored by the author of that
+/// commit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReconstructedPullRequest {
+    /// Id of the merge commit.
+    pub id: String,
+    pub repo: PartialRepoInfo,
+    pub source: MergeSource,
+    pub number: Option<u64>,
+    pub source_branch: Option<String>,
+    pub target_branch: Option<String>,
+    pub title: Option<String>,
+    pub normalized_author: Person,
+    pub author_id: String,
+    /// Author of the merge commit.
+    pub merged_by: Person,
+    /// Number of commits merged.
+    pub commits: usize,
+    pub line_stats: LineStats,
+    pub files_changed: usize,
+    pub created_at: DateTime<Utc>,
+    pub merged_at: DateTime<Utc>,
+    pub time_to_resolve: i64,
+    /// Working seconds between the first commit and the merge according to
+    /// the configured business calendar.
+    pub business_time_to_resolve: i64,
+    pub business_days_to_resolve: f64,
+    timestamp: DateTime<Utc>,
+}
+
+impl ReconstructedPullRequest {
+    /// Reconstructs the pull request landed by `merge`, if it is a merge
+    /// commit which landed one.
+    pub fn from_merge(
+        repo: &Repository,
+        merge: &Commit,
+        config: &RepoConfig,
+    ) -> Result<Option<ReconstructedPullRequest>> {
+        if merge.parent_count() < 2 {
+            return Ok(None);
+        }
+        let message = match MergeMessage::parse(&commit_message(merge)) {
+            Some(message) => message,
+            None => return Ok(None),
+        };
+        let mainline = merge.parent_id(0)?;
+        let mut revwalk = repo.revwalk()?;
+        for id in merge.parent_ids().skip(1) {
+            revwalk.push(id)?;
+        }
+        revwalk.hide(mainline)?;
+        let mut commits = 0;
+        let mut first: Option<Commit> = None;
+        for id in revwalk {
+            let commit = repo.find_commit(id?)?;
+            commits += 1;
+            if first
+                .as_ref()
+                .is_none_or(|first| commit.author().when() < first.author().when())
+            {
+                first = Some(commit);
+            }
+        }
+        let first = match first {
+            Some(first) => first,
+            // the merged commits had already landed
+            None => return Ok(None),
+        };
+
+        let base = repo.merge_base(mainline, merge.parent_id(1)?)?;
+        let diff = repo.diff_tree_to_tree(
+            Some(&repo.find_commit(base)?.tree()?),
+            Some(&merge.parent(1)?.tree()?),
+            None,
+        )?;
+        let stats = diff.stats()?;
+
+        let created_at = convert_time(&first.author().when());
+        let merged_at = convert_time(&merge.committer().when());
+        let author = config
+            .config
+            .person_for_identity_at(&Identity::from(first.author()), &created_at);
+        let merged_by = config
+            .config
+            .person_for_identity_at(&Identity::from(merge.author()), &merged_at);
+        let calendar = config.config.calendar();
+        Ok(Some(ReconstructedPullRequest {
+            id: merge.id().to_string(),
+            repo: config.partial_repo(),
+            source: message.source,
+            number: message.number,
+            source_branch: message.source_branch,
+            target_branch: message.target_branch,
+            title: message.title.and_then(|t| config.config.stored_summary(&t)),
+            author_id: author.id.clone(),
+            normalized_author: author,
+            merged_by,
+            commits,
+            line_stats: LineStats {
+                added: stats.insertions() as u64,
+                deleted: stats.deletions() as u64,
+                moved: 0,
+            },
+            files_changed: stats.files_changed(),
+            created_at,
+            merged_at,
+            time_to_resolve: (merged_at - created_at).num_seconds(),
+            business_time_to_resolve: calendar.business_seconds(&created_at, &merged_at),
+            business_days_to_resolve: calendar.business_days(&created_at, &merged_at),
+            timestamp: merged_at,
+        }))
+    }
+}
+
+impl Event for ReconstructedPullRequest {
+    fn timestamp(&self) -> &DateTime<Utc> {
+        &self.timestamp
+    }
+
+    fn event_type(&self) -> &str {
+        "reconstructed_pull_request"
+    }
+
+    fn id(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.id)
+    }
+
+    fn tags(&self) -> HashSet<String> {
+        HashSet::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Oid;
+
+    #[test]
+    fn test_parse() {
+        let github = MergeMessage::parse(
+            "Merge pull request #123 from octocat/feature/login\n\nAdd a login page",
+        )
+        .unwrap();
+        assert_eq!(github.source, MergeSource::Github);
+        assert_eq!(github.number, Some(123));
+        assert_eq!(github.source_branch, Some("feature/login".to_owned()));
+        assert_eq!(github.title, Some("Add a login page".to_owned()));
+
+        let gitlab = MergeMessage::parse(
+            "Merge branch 'fix-auth' into 'main'\n\nFix auth\n\nSee merge request group/app!42",
+        )
+        .unwrap();
+        assert_eq!(gitlab.source, MergeSource::Gitlab);
+        assert_eq!(gitlab.number, Some(42));
+        assert_eq!(gitlab.source_branch, Some("fix-auth".to_owned()));
+        assert_eq!(gitlab.target_branch, Some("main".to_owned()));
+        assert_eq!(gitlab.title, Some("Fix auth".to_owned()));
+
+        let untitled = MergeMessage::parse(
+            "Merge branch 'fix-auth' into 'main'\n\nSee merge request group/app!42",
+        )
+        .unwrap();
+        assert_eq!(untitled.title, None);
+
+        let bitbucket =
+            MergeMessage::parse("Merged in feature/x (pull request #7)\n\nDo x").unwrap();
+        assert_eq!(bitbucket.source, MergeSource::Bitbucket);
+        assert_eq!(bitbucket.number, Some(7));
+        assert_eq!(bitbucket.source_branch, Some("feature/x".to_owned()));
+
+        let azure = MergeMessage::parse("Merged PR 99: Do y").unwrap();
+        assert_eq!(azure.source, MergeSource::AzureDevops);
+        assert_eq!(azure.number, Some(99));
+        assert_eq!(azure.title, Some("Do y".to_owned()));
+
+        let branch = MergeMessage::parse("Merge branch 'feature' into develop").unwrap();
+        assert_eq!(branch.source, MergeSource::Branch);
+        assert_eq!(branch.target_branch, Some("develop".to_owned()));
+
+        assert_eq!(
+            MergeMessage::parse("Sync with upstream").unwrap().source,
+            MergeSource::Topology
+        );
+        assert_eq!(
+            MergeMessage::parse("Merge branch 'main' of github.com:acme/app"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_from_merge() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/simple")?;
+        let config = RepoConfig::default();
+        let merge = repo.find_commit(Oid::from_str("bf51d175af7e82fbd43cb8598b45aa1830890ff0")?)?;
+        let pr = ReconstructedPullRequest::from_merge(&repo, &merge, &config)?.unwrap();
+        assert_eq!(pr.source, MergeSource::Branch);
+        assert_eq!(pr.source_branch, Some("feature".to_owned()));
+        assert_eq!(pr.commits, 1);
+        assert_eq!(pr.created_at, Utc.ymd(2019, 2, 9).and_hms(19, 0, 19));
+        assert_eq!(pr.merged_at, Utc.ymd(2019, 2, 9).and_hms(19, 0, 51));
+        assert_eq!(pr.time_to_resolve, 32);
+        assert_eq!(pr.files_changed, 1);
+
+        let parent = merge.parent(0)?;
+        assert_eq!(
+            ReconstructedPullRequest::from_merge(&repo, &parent, &config)?,
+            None
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+use regex::Regex;
+
+use crate::analyzed_diff::AnalyzedDiff;
+
+/// Tag which, when present on any changed file, indicates that a commit
+/// touched tests.
+const TEST_TAG: &str = "test";
+
+const MIN_BALANCE: f64 = 0.8;
+const MIN_MOVED_RATIO: f64 = 0.5;
+
+/// Heuristically determines whether a commit is a pure refactor. A commit is
+/// considered a likely refactor when it does not touch tests, removes roughly
+/// as many lines as it adds, and either mostly moves existing lines around or
+/// says that it is a refactor in its summary.
+pub fn is_likely_refactor(summary: Option<&str>, diff: &AnalyzedDiff) -> bool {
+    if diff.tag_stats.contains_key(TEST_TAG) {
+        return false;
+    }
+    if diff.line_stats.balance() < MIN_BALANCE {
+        return false;
+    }
+    diff.line_stats.moved_ratio() >= MIN_MOVED_RATIO || summary.is_some_and(has_refactor_keyword)
+}
+
+fn has_refactor_keyword(summary: &str) -> bool {
+    lazy_static! {
+        static ref REFACTOR_KEYWORD_REGEX: Regex = Regex::new(
+            r"(?i)\b(refactor\w*|renam\w*|mov(e|es|ed|ing)|extract\w*|clean ?up|reorganiz\w*|restructur\w*|simplif\w*)\b"
+        )
+        .unwrap();
+    }
+    REFACTOR_KEYWORD_REGEX.is_match(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codealong_metrics::{LineStats, WorkStats};
+
+    fn diff_with_lines(added: u64, deleted: u64, moved: u64) -> AnalyzedDiff {
+        AnalyzedDiff {
+            line_stats: LineStats {
+                added,
+                deleted,
+                moved,
+            },
+            ..AnalyzedDiff::empty()
+        }
+    }
+
+    #[test]
+    fn test_is_likely_refactor() {
+        assert!(is_likely_refactor(None, &diff_with_lines(10, 10, 8)));
+        assert!(is_likely_refactor(
+            Some("Extract helper for parsing"),
+            &diff_with_lines(10, 9, 0)
+        ));
+        assert!(!is_likely_refactor(
+            Some("Add parsing"),
+            &diff_with_lines(10, 9, 0)
+        ));
+        assert!(!is_likely_refactor(
+            Some("Refactor parser"),
+            &diff_with_lines(100, 10, 10)
+        ));
+
+        let mut diff = diff_with_lines(10, 10, 10);
+        diff.add_stats(WorkStats::new_work(), &vec!["test".to_owned()]);
+        assert!(!is_likely_refactor(None, &diff));
+    }
+}
@@ -0,0 +1,239 @@
+use std::fmt::Write;
+
+use git2::{DescribeFormatOptions, DescribeOptions, Repository};
+
+use crate::analyzed_commit::AnalyzedCommit;
+use crate::error::Result;
+use codealong_metrics::WorkStats;
+use codealong_model::Person;
+
+/// Kinds of change a release summary groups commits by.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Feature,
+    BugFix,
+    Refactor,
+}
+
+impl ChangeKind {
+    pub fn of(commit: &AnalyzedCommit) -> ChangeKind {
+        if commit.bug_fix {
+            ChangeKind::BugFix
+        } else if commit.likely_refactor {
+            ChangeKind::Refactor
+        } else {
+            ChangeKind::Feature
+        }
+    }
+
+    fn heading(self) -> &'static str {
+        match self {
+            ChangeKind::Feature => "Features",
+            ChangeKind::BugFix => "Bug fixes",
+            ChangeKind::Refactor => "Refactoring",
+        }
+    }
+}
+
+/// A person who authored or co-authored commits of a release.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Contributor {
+    pub person: Person,
+    pub commits: u64,
+    pub stats: WorkStats,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub id: String,
+    pub summary: String,
+}
+
+/// Contributors and changes of the commits between two releases. People are
+/// identified by their normalized identity so that someone committing under
+/// several emails is only listed once.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct ReleaseSummary {
+    pub contributors: Vec<Contributor>,
+    pub changes: Vec<Change>,
+}
+
+impl ReleaseSummary {
+    pub fn new() -> ReleaseSummary {
+        ReleaseSummary::default()
+    }
+
+    pub fn add_commit(&mut self, commit: &AnalyzedCommit) {
+        // merges only repeat the changes they bring in
+        if commit.parents.len() > 1 {
+            return;
+        }
+        if let Some(ref author) = commit.normalized_author {
+            self.contributor(author).stats += commit.diff.stats;
+        }
+        for person in commit.normalized_author.iter().chain(&commit.co_authors) {
+            self.contributor(person).commits += 1;
+        }
+        self.changes.push(Change {
+            kind: ChangeKind::of(commit),
+            id: commit.id.clone(),
+            summary: commit.summary.clone().unwrap_or_default(),
+        });
+    }
+
+    fn contributor(&mut self, person: &Person) -> &mut Contributor {
+        let index = match self
+            .contributors
+            .iter()
+            .position(|c| c.person.id == person.id)
+        {
+            Some(index) => index,
+            None => {
+                self.contributors.push(Contributor {
+                    person: person.clone(),
+                    commits: 0,
+                    stats: WorkStats::empty(),
+                });
+                self.contributors.len() - 1
+            }
+        };
+        &mut self.contributors[index]
+    }
+
+    /// Contributors ordered by the number of commits, most first.
+    pub fn finish(mut self) -> ReleaseSummary {
+        self.contributors.sort_by(|a, b| {
+            b.commits
+                .cmp(&a.commits)
+                .then(a.person.id.cmp(&b.person.id))
+        });
+        self
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        for kind in &[
+            ChangeKind::Feature,
+            ChangeKind::BugFix,
+            ChangeKind::Refactor,
+        ] {
+            let changes: Vec<&Change> = self.changes.iter().filter(|c| c.kind == *kind).collect();
+            if changes.is_empty() {
+                continue;
+            }
+            writeln!(out, "### {}\n", kind.heading()).unwrap();
+            for change in changes {
+                writeln!(
+                    out,
+                    "- {} ({})",
+                    change.summary,
+                    &change.id[..7.min(change.id.len())]
+                )
+                .unwrap();
+            }
+            writeln!(out).unwrap();
+        }
+        writeln!(out, "### Contributors\n").unwrap();
+        for contributor in &self.contributors {
+            let person = &contributor.person;
+            let name = person
+                .name
+                .as_ref()
+                .or(person.github_login.as_ref())
+                .unwrap_or(&person.id);
+            match person.github_login {
+                Some(ref login) if login != name => writeln!(
+                    out,
+                    "- {} (@{}), {} commits",
+                    name, login, contributor.commits
+                ),
+                _ => writeln!(out, "- {}, {} commits", name, contributor.commits),
+            }
+            .unwrap();
+        }
+        out
+    }
+}
+
+/// The most recent tag reachable from the parents of `release`, i.e. the
+/// release before it.
+pub fn previous_release(repo: &Repository, release: &str) -> Result<Option<String>> {
+    let commit = repo.revparse_single(release)?.peel_to_commit()?;
+    let parent = match commit.parent(0) {
+        Ok(parent) => parent,
+        Err(_) => return Ok(None),
+    };
+    let mut opts = DescribeOptions::new();
+    opts.describe_tags();
+    Ok(parent
+        .as_object()
+        .describe(&opts)
+        .and_then(|describe| {
+            describe.format(Some(DescribeFormatOptions::new().abbreviated_size(0)))
+        })
+        .ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::Identity;
+    use git2::{Oid, Signature};
+
+    fn commit(repo: &Repository, parents: &[Oid], message: &str) -> Result<Oid> {
+        let sig = Signature::now("Test", "test@example.com")?;
+        let tree = repo.find_tree(repo.treebuilder(None)?.write()?)?;
+        let parents = parents
+            .iter()
+            .map(|oid| repo.find_commit(*oid))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let parents: Vec<_> = parents.iter().collect();
+        Ok(repo.commit(None, &sig, &sig, message, &tree, &parents)?)
+    }
+
+    #[test]
+    fn test_previous_release() -> Result<()> {
+        let tmp_dir = tempdir::TempDir::new("release")?;
+        let repo = Repository::init(tmp_dir.path())?;
+        let first = commit(&repo, &[], "first")?;
+        let second = commit(&repo, &[first], "second")?;
+        let third = commit(&repo, &[second], "third")?;
+        repo.tag_lightweight("v1.0.0", &repo.find_object(first, None)?, false)?;
+        repo.tag_lightweight("v1.1.0", &repo.find_object(third, None)?, false)?;
+        assert_eq!(
+            previous_release(&repo, "v1.1.0")?,
+            Some("v1.0.0".to_owned())
+        );
+        assert_eq!(previous_release(&repo, "v1.0.0")?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_summary() {
+        let person = Identity::parse("Test <test@example.com>").to_person();
+        let repo = Repository::open("./fixtures/repos/simple").unwrap();
+        let commit = repo
+            .find_commit(Oid::from_str("86d242301830075e93ff039a4d1e88673a4a3020").unwrap())
+            .unwrap();
+        let analyzed = |id: &str, bug_fix: bool| {
+            let mut res = AnalyzedCommit::new(&commit);
+            res.id = id.to_owned();
+            res.summary = Some(format!("change {}", id));
+            res.normalized_author = Some(person.clone());
+            res.bug_fix = bug_fix;
+            res
+        };
+        let mut summary = ReleaseSummary::new();
+        summary.add_commit(&analyzed("aaaaaaaa", false));
+        summary.add_commit(&analyzed("bbbbbbbb", true));
+        let summary = summary.finish();
+        assert_eq!(summary.contributors.len(), 1);
+        assert_eq!(summary.contributors[0].commits, 2);
+        let markdown = summary.to_markdown();
+        assert!(markdown.contains("### Features\n\n- change aaaaaaaa (aaaaaaa)"));
+        assert!(markdown.contains("### Bug fixes\n\n- change bbbbbbbb (bbbbbbb)"));
+        assert!(markdown.contains("- Test, 2 commits"));
+    }
+}
@@ -0,0 +1,295 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use git2::{DiffFindOptions, Oid, Repository, Sort};
+
+use crate::analyzed_diff::AnalyzedDiff;
+use crate::error::*;
+
+/// File the rename graph of a repo is cached in, within its git directory.
+const CACHE_FILE: &str = "codealong/renames.json";
+
+lazy_static! {
+    /// Rename graphs by git directory, shared by every analyzer of the
+    /// process so that the history is only walked once.
+    static ref GRAPHS: Mutex<HashMap<PathBuf, Arc<RenameGraph>>> = Mutex::new(HashMap::new());
+}
+
+/// A file renamed by a commit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rename {
+    pub commit: String,
+    pub from: String,
+    pub to: String,
+    /// Position of the commit in the walked history, every commit coming
+    /// after its ancestors.
+    pub seq: u64,
+}
+
+/// The renames of files across the history of a repo, which tell the
+/// identity of a file whatever path it had at a commit: its canonical path,
+/// the path it was added with.
+#[derive(Debug, Clone, Default)]
+pub struct RenameGraph {
+    /// Commits the history was walked from.
+    tips: Vec<Oid>,
+    walked: HashSet<Oid>,
+    /// Renames by the path files were renamed to.
+    renames: HashMap<String, Vec<Rename>>,
+    next_seq: u64,
+}
+
+/// What is cached on disk of a rename graph, from which the walked commits
+/// are found again.
+#[derive(Serialize, Deserialize)]
+struct Cached {
+    tips: Vec<String>,
+    renames: Vec<Rename>,
+}
+
+impl RenameGraph {
+    /// The rename graph of `repo`, covering the history of `oid` and of the
+    /// branches of the repo. It is cached in memory and in the git directory of
+    /// the repo, and extended with the commits which are new since.
+    pub fn for_commit(repo: &Repository, oid: Oid) -> Result<Arc<RenameGraph>> {
+        let mut graphs = GRAPHS.lock().unwrap();
+        let key = repo.path().to_owned();
+        if let Some(graph) = graphs.get(&key).filter(|graph| graph.walked.contains(&oid)) {
+            return Ok(graph.clone());
+        }
+        let mut graph = match graphs.get(&key) {
+            Some(graph) => (**graph).clone(),
+            None => RenameGraph::read(repo).unwrap_or_default(),
+        };
+        let mut tips = vec![oid];
+        for reference in repo.references()? {
+            let reference = reference?;
+            let is_branch = reference.name().is_some_and(|name| {
+                name.starts_with("refs/heads/") || name.starts_with("refs/remotes/")
+            });
+            if let Some(commit) = Some(reference)
+                .filter(|_| is_branch)
+                .and_then(|r| r.peel_to_commit().ok())
+            {
+                tips.push(commit.id());
+            }
+        }
+        if tips.iter().any(|tip| !graph.walked.contains(tip)) {
+            graph.update(repo, &tips)?;
+            // the cache only saves work, so failing to write it is not an error
+            let _ = graph.write(repo);
+        }
+        let graph = Arc::new(graph);
+        graphs.insert(key, graph.clone());
+        Ok(graph)
+    }
+
+    /// Walks the history of `tips` which was not walked yet, recording the
+    /// renames of its commits. Merges are skipped since the renames they
+    /// bring in are those of the commits they merge.
+    pub fn update(&mut self, repo: &Repository, tips: &[Oid]) -> Result<()> {
+        let mut revwalk = repo.revwalk()?;
+        revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE);
+        for tip in tips {
+            if repo.find_commit(*tip).is_ok() {
+                revwalk.push(*tip)?;
+            }
+        }
+        for tip in &self.tips {
+            revwalk.hide(*tip)?;
+        }
+        for oid in revwalk {
+            let oid = oid?;
+            self.walked.insert(oid);
+            let commit = repo.find_commit(oid)?;
+            if commit.parent_count() != 1 {
+                continue;
+            }
+            let mut diff = repo.diff_tree_to_tree(
+                Some(&commit.parent(0)?.tree()?),
+                Some(&commit.tree()?),
+                None,
+            )?;
+            // blobs missing from a partial clone only leave renames undetected
+            if diff
+                .find_similar(Some(DiffFindOptions::new().renames(true)))
+                .is_err()
+            {
+                continue;
+            }
+            for delta in diff.deltas() {
+                if delta.status() != git2::Delta::Renamed {
+                    continue;
+                }
+                let from = delta.old_file().path().and_then(|path| path.to_str());
+                let to = delta.new_file().path().and_then(|path| path.to_str());
+                if let (Some(from), Some(to)) = (from, to) {
+                    self.insert(Rename {
+                        commit: oid.to_string(),
+                        from: from.to_owned(),
+                        to: to.to_owned(),
+                        seq: self.next_seq,
+                    });
+                }
+            }
+            self.next_seq += 1;
+        }
+        for tip in tips {
+            if self.walked.contains(tip) && !self.tips.contains(tip) {
+                self.tips.push(*tip);
+            }
+        }
+        Ok(())
+    }
+
+    /// The path the file at `path` as of `oid` was added with, following its
+    /// renames back through the history of `oid`.
+    pub fn canonical_path_at(&self, repo: &Repository, path: &str, oid: Oid) -> String {
+        let mut path = path.to_owned();
+        let mut at = oid;
+        let mut inclusive = true;
+        loop {
+            let rename = self.renames.get(&path).and_then(|renames| {
+                renames
+                    .iter()
+                    .filter(|rename| {
+                        let commit = match Oid::from_str(&rename.commit) {
+                            Ok(commit) => commit,
+                            Err(_) => return false,
+                        };
+                        (inclusive && commit == at)
+                            || repo.graph_descendant_of(at, commit).unwrap_or(false)
+                    })
+                    .max_by_key(|rename| rename.seq)
+            });
+            match rename {
+                Some(rename) => {
+                    path = rename.from.clone();
+                    at = Oid::from_str(&rename.commit).unwrap();
+                    inclusive = false;
+                }
+                None => return path,
+            }
+        }
+    }
+
+    /// Stamps the canonical path of each file of `diff`, a diff of the
+    /// commit `oid`.
+    pub fn stamp(&self, repo: &Repository, oid: Oid, diff: &mut AnalyzedDiff) {
+        for file in &mut diff.files {
+            file.canonical_path = Some(self.canonical_path_at(repo, &file.path, oid));
+        }
+    }
+
+    fn insert(&mut self, rename: Rename) {
+        self.renames
+            .entry(rename.to.clone())
+            .or_default()
+            .push(rename);
+    }
+
+    fn read(repo: &Repository) -> Option<RenameGraph> {
+        let cached: Cached =
+            serde_json::from_reader(File::open(repo.path().join(CACHE_FILE)).ok()?).ok()?;
+        let mut graph = RenameGraph::default();
+        let mut revwalk = repo.revwalk().ok()?;
+        for tip in &cached.tips {
+            let tip = Oid::from_str(tip).ok()?;
+            // a tip lost to a history rewrite leaves the cache unusable
+            revwalk.push(tip).ok()?;
+            graph.tips.push(tip);
+        }
+        for oid in revwalk {
+            graph.walked.insert(oid.ok()?);
+        }
+        graph.next_seq = cached.renames.iter().map(|r| r.seq + 1).max().unwrap_or(0);
+        for rename in cached.renames {
+            graph.insert(rename);
+        }
+        Some(graph)
+    }
+
+    fn write(&self, repo: &Repository) -> Result<()> {
+        let path = repo.path().join(CACHE_FILE);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut renames: Vec<&Rename> = self.renames.values().flatten().collect();
+        renames.sort_by_key(|rename| rename.seq);
+        let cached = Cached {
+            tips: self.tips.iter().map(|tip| tip.to_string()).collect(),
+            renames: renames.into_iter().cloned().collect(),
+        };
+        // written aside and renamed so that readers never see a partial graph
+        let tmp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+        serde_json::to_writer(File::create(&tmp_path)?, &cached)?;
+        fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{Commit, Signature};
+    use std::path::Path;
+
+    fn commit(repo: &Repository, dir: &Path, remove: &[&str], add: &[(&str, &str)]) -> Result<Oid> {
+        for path in remove {
+            std::fs::remove_file(dir.join(path))?;
+        }
+        for (path, content) in add {
+            std::fs::write(dir.join(path), content)?;
+        }
+        let mut index = repo.index()?;
+        index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
+        index.update_all(["*"].iter(), None)?;
+        index.write()?;
+        let tree = repo.find_tree(index.write_tree()?)?;
+        let sig = Signature::now("Test", "test@example.com")?;
+        let parents = match repo.head() {
+            Ok(head) => vec![head.peel_to_commit()?],
+            Err(_) => vec![],
+        };
+        let parents: Vec<&Commit> = parents.iter().collect();
+        Ok(repo.commit(Some("HEAD"), &sig, &sig, "Change", &tree, &parents)?)
+    }
+
+    #[test]
+    fn test_canonical_path_at() -> Result<()> {
+        let tmp_dir = tempdir::TempDir::new("rename_graph")?;
+        let dir = tmp_dir.path();
+        let repo = Repository::init(dir)?;
+        let content = "one\ntwo\nthree\nfour\nfive\n";
+        let added = commit(&repo, dir, &[], &[("a.txt", content), ("other.txt", "x\n")])?;
+        let moved = commit(&repo, dir, &["a.txt"], &[("b.txt", content)])?;
+
+        let mut graph = RenameGraph::default();
+        graph.update(&repo, &[moved])?;
+        assert_eq!(graph.canonical_path_at(&repo, "b.txt", moved), "a.txt");
+        assert_eq!(graph.canonical_path_at(&repo, "a.txt", added), "a.txt");
+
+        // moved back and forth, and then a new file takes the old name
+        let again = commit(&repo, dir, &["b.txt"], &[("c.txt", content)])?;
+        let back = commit(&repo, dir, &["c.txt"], &[("b.txt", content)])?;
+        let reused = commit(&repo, dir, &[], &[("a.txt", "new\n")])?;
+        graph.update(&repo, &[reused])?;
+        assert_eq!(graph.canonical_path_at(&repo, "c.txt", again), "a.txt");
+        assert_eq!(graph.canonical_path_at(&repo, "b.txt", back), "a.txt");
+        assert_eq!(graph.canonical_path_at(&repo, "b.txt", reused), "a.txt");
+        assert_eq!(graph.canonical_path_at(&repo, "a.txt", reused), "a.txt");
+        assert_eq!(
+            graph.canonical_path_at(&repo, "other.txt", reused),
+            "other.txt"
+        );
+
+        let graph = RenameGraph::for_commit(&repo, reused)?;
+        assert_eq!(graph.canonical_path_at(&repo, "b.txt", back), "a.txt");
+        let cached = RenameGraph::read(&repo).unwrap();
+        assert!(cached.walked.contains(&added));
+        assert_eq!(cached.canonical_path_at(&repo, "b.txt", reused), "a.txt");
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use git2::{Oid, Repository};
+
+use crate::error::*;
+
+/// Whether `git replace` refs are honored when walking and analyzing history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplaceRefs {
+    /// Analyze replacement commits in place of the commits they replace, as
+    /// git does by default.
+    #[default]
+    Honor,
+    /// Analyze the original objects, as with `git --no-replace-objects`.
+    Ignore,
+}
+
+/// Maps replaced objects to their replacements from `refs/replace/*`.
+pub fn replacements(repo: &Repository) -> Result<HashMap<Oid, Oid>> {
+    let mut res = HashMap::new();
+    // references_glob fails when refs/replace does not exist
+    for reference in repo.references()? {
+        let reference = reference?;
+        let replaced = reference
+            .name()
+            .and_then(|name| name.strip_prefix("refs/replace/"))
+            .and_then(|oid| Oid::from_str(oid).ok());
+        if let (Some(replaced), Some(replacement)) = (replaced, reference.target()) {
+            res.insert(replaced, replacement);
+        }
+    }
+    Ok(res)
+}
+
+/// Walks history from `heads` in reverse chronological order, following the
+/// parents of replacement commits rather than those of the commits they
+/// replace. libgit2 has no support for replace refs so the walk is done
+/// here. Yields the ids of the commits as they appear in history, i.e. before
+/// replacement. Only the first parent of merges is followed if
+/// `first_parent` is set.
+pub fn walk(
+    repo: &Repository,
+    heads: &[Oid],
+    replacements: &HashMap<Oid, Oid>,
+    first_parent: bool,
+) -> Result<Vec<Oid>> {
+    let resolve = |oid: Oid| repo.find_commit(*replacements.get(&oid).unwrap_or(&oid));
+    let mut queue = BinaryHeap::new();
+    for head in heads {
+        queue.push((resolve(*head)?.time().seconds(), *head));
+    }
+    let mut seen = HashSet::new();
+    let mut res = vec![];
+    while let Some((_, oid)) = queue.pop() {
+        if !seen.insert(oid) {
+            continue;
+        }
+        res.push(oid);
+        let parents = resolve(oid)?.parent_ids().collect::<Vec<_>>();
+        let parents = if first_parent {
+            &parents[..parents.len().min(1)]
+        } else {
+            &parents[..]
+        };
+        for &parent in parents {
+            if !seen.contains(&parent) {
+                queue.push((resolve(parent)?.time().seconds(), parent));
+            }
+        }
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(repo: &Repository, parents: &[Oid], time: i64) -> Result<Oid> {
+        let sig = git2::Signature::new("Test", "test@example.com", &git2::Time::new(time, 0))?;
+        let tree = repo.find_tree(repo.treebuilder(None)?.write()?)?;
+        let parents = parents
+            .iter()
+            .map(|oid| repo.find_commit(*oid))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let parents: Vec<_> = parents.iter().collect();
+        Ok(repo.commit(None, &sig, &sig, "commit", &tree, &parents)?)
+    }
+
+    #[test]
+    fn test_walk() -> Result<()> {
+        let tmp_dir = tempdir::TempDir::new("replace")?;
+        let repo = Repository::init(tmp_dir.path())?;
+        let ancient = commit(&repo, &[], 0)?;
+        let root = commit(&repo, &[], 1)?;
+        let head = commit(&repo, &[root], 2)?;
+        // graft the old history onto the root of the new history
+        let graft = commit(&repo, &[ancient], 1)?;
+        repo.reference(&format!("refs/replace/{}", root), graft, false, "graft")?;
+
+        let replacements = replacements(&repo)?;
+        assert_eq!(replacements.get(&root), Some(&graft));
+        assert_eq!(
+            walk(&repo, &[head], &replacements, false)?,
+            vec![head, root, ancient]
+        );
+        assert_eq!(
+            walk(&repo, &[head], &HashMap::new(), false)?,
+            vec![head, root]
+        );
+        Ok(())
+    }
+}
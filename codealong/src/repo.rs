@@ -1,16 +1,35 @@
 use std::path::PathBuf;
 
 use git2::build::{CheckoutBuilder, RepoBuilder};
-use git2::{FetchOptions, RemoteCallbacks, Repository};
+use git2::{FetchOptions, Oid, RemoteCallbacks, Repository};
 
 use crate::config::Config;
 use crate::error::*;
+use crate::offline::is_offline;
+use crate::partial_clone::discover_compatible;
+use crate::rename_graph::RenameGraph;
 use crate::repo_config::RepoConfig;
 use crate::repo_info::RepoInfo;
+use crate::repo_metadata::RepoMetadata;
+use crate::sparse::{clone_sparse, fetch_sparse, prefetch_blobs};
 use crate::utils::with_authentication;
 
 pub type ProgressCallback<'a> = FnMut(usize, usize) + 'a;
 
+/// How repos that are archived, and so no longer change, are analyzed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchivedRepos {
+    /// Fetch and analyze them like any other repo.
+    #[default]
+    Analyze,
+    /// Analyze them once, when they are first cloned, and leave them alone
+    /// afterwards.
+    Freeze,
+    /// Neither clone nor analyze them.
+    Skip,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Repo {
     base_config: Config,
@@ -27,11 +46,25 @@ impl Repo {
         }
     }
 
+    /// Opens the clone of the repo, which codealong manages, making it
+    /// readable by libgit2 if it is a partial clone.
     pub fn repository(&self) -> Result<Repository> {
-        Ok(Repository::discover(&self.path)?)
+        discover_compatible(&self.path)
     }
 
     pub fn init<'a>(&self, cb: Option<Box<ProgressCallback<'a>>>) -> Result<Repository> {
+        if self.repo_info.mirror {
+            return self.repository();
+        }
+        if is_offline() {
+            // checkouts are analyzed as they are
+            return self.repository().map_err(|_| {
+                ErrorKind::Offline(format!("cloning {}", self.repo_info.clone_url)).into()
+            });
+        }
+        if !self.repo_info.paths.is_empty() {
+            return self.init_sparse(cb);
+        }
         if let Ok(repository) = self.repository() {
             self.fetch(&repository, cb)?;
             Ok(repository)
@@ -98,10 +131,72 @@ impl Repo {
         })?)
     }
 
+    /// Clones or fetches a path-scoped repo as a blobless partial clone and
+    /// prefetches the historical blobs under the scoped paths.
+    fn init_sparse<'a>(&self, mut cb: Option<Box<ProgressCallback<'a>>>) -> Result<Repository> {
+        let paths = &self.repo_info.paths;
+        if let Ok(repository) = self.repository() {
+            fetch_sparse(repository.path())?;
+        } else {
+            clone_sparse(&self.repo_info.clone_url, &self.path, paths)?;
+        }
+        let repository = self.repository()?;
+        let revs: Vec<String> = self
+            .repo_info
+            .refs
+            .iter()
+            .filter(|r| repository.find_reference(r).is_ok())
+            .cloned()
+            .collect();
+        let revs = if revs.is_empty() {
+            vec!["HEAD".to_owned()]
+        } else {
+            revs
+        };
+        prefetch_blobs(repository.path(), &revs, paths)?;
+        if let Some(ref mut cb) = cb {
+            cb(1, 1);
+        }
+        Ok(repository)
+    }
+
     pub fn repo_info(&self) -> &RepoInfo {
         &self.repo_info
     }
 
+    pub fn set_metadata(&mut self, metadata: RepoMetadata) {
+        self.repo_info.archived |= metadata.archived;
+        self.repo_info.metadata = Some(metadata);
+    }
+
+    /// The same repo analyzed with `config` merged over its base config.
+    pub fn with_config(&self, config: Config) -> Repo {
+        let mut base_config = self.base_config.clone();
+        base_config.merge(config);
+        Repo::new(base_config, self.path.clone(), self.repo_info.clone())
+    }
+
+    /// Whether the repo should be fetched and analyzed, according to the
+    /// `archived_repos` policy of the config.
+    pub fn is_active(&self) -> bool {
+        if !self.repo_info.archived {
+            return true;
+        }
+        match self.base_config.archived_repos {
+            ArchivedRepos::Analyze => true,
+            ArchivedRepos::Freeze => !self.path.exists(),
+            ArchivedRepos::Skip => false,
+        }
+    }
+
+    /// The path the file at `path` as of the commit `oid` was added with,
+    /// following its renames back through history.
+    pub fn canonical_path_at(&self, path: &str, oid: Oid) -> Result<String> {
+        let repository = self.repository()?;
+        let graph = RenameGraph::for_commit(&repository, oid)?;
+        Ok(graph.canonical_path_at(&repository, path, oid))
+    }
+
     /// Combines base config with any config found in the repo itself
     pub fn config(&self) -> RepoConfig {
         // TODO once we go to bare repos we need to
@@ -130,4 +225,29 @@ mod tests {
         repo.clone_repo(None)?;
         Ok(())
     }
+
+    #[test]
+    fn test_is_active() -> Result<()> {
+        let tmp_dir = tempdir::TempDir::new("example")?;
+        let repo_info = RepoInfo {
+            name: "simple".to_owned(),
+            clone_url: "./fixtures/repos/simple".to_owned(),
+            archived: true,
+            ..Default::default()
+        };
+        let repo = |archived_repos| {
+            let config = Config {
+                archived_repos,
+                ..Config::default()
+            };
+            Repo::new(config, tmp_dir.path().join("simple.git"), repo_info.clone())
+        };
+        assert!(repo(ArchivedRepos::Analyze).is_active());
+        assert!(!repo(ArchivedRepos::Skip).is_active());
+        assert!(repo(ArchivedRepos::Freeze).is_active());
+        repo(ArchivedRepos::Freeze).clone_repo(None)?;
+        assert!(!repo(ArchivedRepos::Freeze).is_active());
+        assert!(repo(ArchivedRepos::Analyze).is_active());
+        Ok(())
+    }
 }
@@ -1,18 +1,39 @@
-use git2::{Repository, Revwalk};
+use chrono::prelude::*;
+use chrono::DateTime;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Instant;
 
+use git2::{Commit, DiffOptions, Oid, Repository, Sort};
+
+use crate::analysis_stream::AnalysisStream;
 use crate::analyze_opts::AnalyzeOpts;
+use crate::analyzed_commit::AnalyzedCommit;
+use crate::checkpoint::{read_checkpoint, write_checkpoint, HistoryRewrite};
 use crate::commit_analyzer::CommitAnalyzer;
+use crate::default_branch::{branch_refname, default_branch};
+use crate::dependency_graph::DependencyGraph;
+use crate::effort::Effort;
 use crate::error::*;
 use crate::identity::Identity;
+use crate::landing::Landings;
+use crate::parallel_revwalk::ParallelRevwalk;
+use crate::pipeline_stats::{PipelineStats, Stage};
+use crate::progress::{NoProgress, Progress};
+use crate::reconstructed_pull_request::ReconstructedPullRequest;
+use crate::replace_refs::{replacements, walk, ReplaceRefs};
 use crate::repo::Repo;
 use crate::repo_config::RepoConfig;
+use crate::risk::Hotspots;
 use crate::slog::Logger;
+use crate::survival::Survival;
 use crate::utils::convert_time;
 
 pub struct RepoAnalyzer {
-    repo: Repository,
-    config: RepoConfig,
-    logger: Logger,
+    pub(crate) repo: Repository,
+    pub(crate) config: RepoConfig,
+    pub(crate) logger: Logger,
+    pub(crate) progress: Arc<dyn Progress>,
 }
 
 impl RepoAnalyzer {
@@ -21,49 +42,427 @@ impl RepoAnalyzer {
             repo,
             logger: parent_logger.new(o!("repo" => config.repo.name.to_owned())),
             config,
+            progress: Arc::new(NoProgress),
         }
     }
 
+    /// Reports the progress of analyzing the commits to `progress`.
+    pub fn with_progress(mut self, progress: Arc<dyn Progress>) -> RepoAnalyzer {
+        self.progress = progress;
+        self
+    }
+
     pub fn analyze(
         &self,
         opts: AnalyzeOpts,
     ) -> Result<impl Iterator<Item = Result<CommitAnalyzer>>> {
-        let mut revwalk = self.repo.revwalk()?;
-        for reference in &self.config.repo.refs {
-            if let Ok(_) = self.repo.find_reference(reference) {
-                revwalk.push_ref(&reference)?;
-            } else {
-                warn!(
-                    self.logger,
-                    "Could not find reference: {}, using HEAD", reference
-                );
-                revwalk.push_head()?;
+        let landings = self.landings(&opts)?;
+        self.walk(opts, landings)
+    }
+
+    /// Like `analyze`, but analyzes the commits on a thread of their own,
+    /// with its own handle on the repository, and yields them as a stream
+    /// with at most `buffer` commits analyzed ahead of the consumer.
+    pub fn stream(
+        &self,
+        opts: AnalyzeOpts,
+        buffer: usize,
+    ) -> AnalysisStream<AnalyzedCommit, Error> {
+        let path = self.repo.path().to_owned();
+        let config = self.config.clone();
+        let logger = self.logger.clone();
+        let progress = self.progress.clone();
+        AnalysisStream::spawn(buffer, move |emitter| {
+            let analyzer = match Repository::open(&path) {
+                Ok(repo) => RepoAnalyzer {
+                    repo,
+                    config,
+                    logger,
+                    progress,
+                },
+                Err(e) => {
+                    emitter.emit(Err(e.into()));
+                    return;
+                }
+            };
+            let commits = match analyzer.analyze(opts) {
+                Ok(commits) => commits,
+                Err(e) => {
+                    emitter.emit(Err(e));
+                    return;
+                }
+            };
+            for commit_analyzer in commits {
+                if !emitter.emit(commit_analyzer.and_then(|c| c.analyze())) {
+                    return;
+                }
             }
-        }
+        })
+    }
+
+    /// Walks the commits to analyze with `opts`, recording how they landed
+    /// on the branch of `landings`, if given.
+    fn walk(
+        &self,
+        opts: AnalyzeOpts,
+        landings: Option<Arc<Landings>>,
+    ) -> Result<AnalyzedRevwalk<'_>> {
+        let replacements = match self.config.config.replace_refs {
+            ReplaceRefs::Honor => replacements(&self.repo)?,
+            ReplaceRefs::Ignore => HashMap::new(),
+        };
+        let revwalk: Box<dyn Iterator<Item = Result<Oid>>> = if replacements.is_empty() {
+            let mut revwalk = self.repo.revwalk()?;
+            if opts.deadline.is_some() {
+                revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME | Sort::REVERSE);
+            }
+            if opts.first_parent {
+                revwalk.simplify_first_parent();
+            }
+            for head in self.heads_for(&opts)? {
+                revwalk.push(head)?;
+            }
+            for analyzed in self.analyzed_heads(&opts) {
+                revwalk.hide(analyzed)?;
+            }
+            Box::new(revwalk.map(|oid| Ok(oid?)))
+        } else {
+            let mut oids = walk(
+                &self.repo,
+                &self.heads_for(&opts)?,
+                &replacements,
+                opts.first_parent,
+            )?;
+            let analyzed_heads = self.analyzed_heads(&opts);
+            if !analyzed_heads.is_empty() {
+                let analyzed: HashSet<Oid> = walk(
+                    &self.repo,
+                    &analyzed_heads,
+                    &replacements,
+                    opts.first_parent,
+                )?
+                .into_iter()
+                .collect();
+                oids.retain(|oid| !analyzed.contains(oid));
+            }
+            if opts.deadline.is_some() {
+                oids.reverse();
+            }
+            Box::new(oids.into_iter().map(Ok))
+        };
         Ok(AnalyzedRevwalk {
             repo: &self.repo,
             revwalk,
+            replacements,
             config: &self.config,
             opts,
+            landings,
             logger: self.logger.clone(),
+            progress: self.progress.clone(),
+        })
+    }
+
+    /// Like `analyze`, but analyzes the commits on `jobs` threads. Commits
+    /// are yielded in the same order, and with the same results, as by
+    /// `analyze`. The throughput and queue depths of the walk and of the
+    /// analysis are recorded in `stats`.
+    pub fn analyze_parallel(
+        &self,
+        opts: AnalyzeOpts,
+        jobs: usize,
+        stats: &PipelineStats,
+    ) -> Result<ParallelRevwalk> {
+        let start = Instant::now();
+        let commits = self
+            .walk(opts.clone(), None)?
+            .map(|commit_analyzer| commit_analyzer.map(|c| c.ids()))
+            .collect::<Result<Vec<_>>>()?;
+        stats.record(Stage::Walk, commits.len() as u64, start.elapsed());
+        let landings = self.landings(&opts)?;
+        ParallelRevwalk::new(self, commits, landings, opts, jobs, stats)
+    }
+
+    /// The commits the configured refs point to.
+    fn heads(&self) -> Result<Vec<Oid>> {
+        Ok(self
+            .named_heads()?
+            .into_iter()
+            .map(|(_, oid)| oid)
+            .collect())
+    }
+
+    /// The configured refs, or their fallback if they do not exist, with the
+    /// commits they point to.
+    fn named_heads(&self) -> Result<Vec<(String, Oid)>> {
+        let mut heads = vec![];
+        for reference in &self.config.repo.refs {
+            if let Ok(oid) = self.repo.refname_to_id(reference) {
+                heads.push((reference.to_owned(), oid));
+            } else {
+                let fallback = self.default_branch().unwrap_or_else(|| "HEAD".to_owned());
+                warn!(
+                    self.logger,
+                    "Could not find reference: {}, using {}", reference, fallback
+                );
+                heads.push((fallback.clone(), self.repo.refname_to_id(&fallback)?));
+            }
+        }
+        Ok(heads)
+    }
+
+    /// The refs history is walked from when analyzing with `opts`, with the
+    /// commits they point to, e.g. to record what was analyzed.
+    pub fn ref_heads(&self, opts: &AnalyzeOpts) -> Result<BTreeMap<String, String>> {
+        let heads = if let Some(ref branch) = opts.branch {
+            let branch = self.branch_refname(branch);
+            let oid = self.repo.refname_to_id(&branch)?;
+            vec![(branch, oid)]
+        } else if opts.default_branch_only {
+            let branch = self.default_branch().unwrap_or_else(|| "HEAD".to_owned());
+            let oid = self.repo.refname_to_id(&branch)?;
+            vec![(branch, oid)]
+        } else {
+            self.named_heads()?
+        };
+        Ok(heads
+            .into_iter()
+            .map(|(reference, oid)| (reference, oid.to_string()))
+            .collect())
+    }
+
+    /// The `analyzed_heads` of `opts` which are still in the repo.
+    fn analyzed_heads(&self, opts: &AnalyzeOpts) -> Vec<Oid> {
+        opts.analyzed_heads
+            .iter()
+            .filter_map(|id| Oid::from_str(id).ok())
+            .filter(|oid| self.repo.find_commit(*oid).is_ok())
+            .collect()
+    }
+
+    /// The commit history is walked from when analyzing with `opts`, or the
+    /// first of them if several refs are configured.
+    pub fn head(&self, opts: &AnalyzeOpts) -> Result<Option<String>> {
+        Ok(self.heads_for(opts)?.first().map(|oid| oid.to_string()))
+    }
+
+    fn heads_for(&self, opts: &AnalyzeOpts) -> Result<Vec<Oid>> {
+        if let Some(ref branch) = opts.branch {
+            return Ok(vec![self
+                .repo
+                .refname_to_id(&self.branch_refname(branch))?]);
+        }
+        if !opts.default_branch_only {
+            return self.heads();
+        }
+        let branch = self.default_branch().unwrap_or_else(|| "HEAD".to_owned());
+        Ok(vec![self.repo.refname_to_id(&branch)?])
+    }
+
+    /// Full refname of the default branch of the repo, as reported by its
+    /// host if known, or else as detected from the clone.
+    pub fn default_branch(&self) -> Option<String> {
+        let remote = self.config.repo.remote.as_ref().map_or("origin", |r| &**r);
+        self.config
+            .repo
+            .metadata
+            .as_ref()
+            .and_then(|metadata| metadata.default_branch.as_ref())
+            .and_then(|branch| branch_refname(&self.repo, remote, branch))
+            .or_else(|| default_branch(&self.repo, remote))
+    }
+
+    /// Full refname of `branch`, which may be given by its name or refname.
+    fn branch_refname(&self, branch: &str) -> String {
+        let remote = self.config.repo.remote.as_ref().map_or("origin", |r| &**r);
+        branch_refname(&self.repo, remote, branch).unwrap_or_else(|| branch.to_owned())
+    }
+
+    /// How the commits landed on the `branch` of `opts`, or else on the
+    /// default branch, if the repo has one.
+    pub fn landings(&self, opts: &AnalyzeOpts) -> Result<Option<Arc<Landings>>> {
+        let branch = match opts.branch {
+            Some(ref branch) => Some(self.branch_refname(branch)),
+            None => self.default_branch(),
+        };
+        match branch {
+            Some(branch) => Ok(Some(Arc::new(Landings::walk(&self.repo, &branch)?))),
+            None => Ok(None),
+        }
+    }
+
+    /// The hotspots of the history walked when analyzing with `opts`, from
+    /// the commits authored since `since`.
+    pub fn hotspots(&self, opts: &AnalyzeOpts, since: &DateTime<Utc>) -> Result<Hotspots> {
+        match self.heads_for(opts)?.first() {
+            Some(head) => Hotspots::walk(&self.repo, *head, &self.config.config, since),
+            None => Ok(Hotspots::default()),
+        }
+    }
+
+    /// The lines of each commit which still exist at the head of the history
+    /// walked when analyzing with `opts`.
+    pub fn survival(&self, opts: &AnalyzeOpts) -> Result<Survival> {
+        match self.heads_for(opts)?.first() {
+            Some(head) => Survival::at(&self.repo, *head, &self.config.config),
+            None => Ok(Survival::default()),
+        }
+    }
+
+    /// The effort of the commits of the history walked when analyzing with
+    /// `opts`, estimated from the work sessions of their authors.
+    pub fn effort(&self, opts: &AnalyzeOpts) -> Result<Effort> {
+        Effort::walk(&self.repo, &self.heads_for(opts)?, &self.config.config)
+    }
+
+    /// Detects whether history was rewritten since the last checkpoint.
+    pub fn history_rewrite(&self) -> Result<Option<HistoryRewrite>> {
+        match read_checkpoint(&self.repo) {
+            Some(checkpoint) => {
+                let rewrite = HistoryRewrite::detect(&self.repo, checkpoint, &self.heads()?)?;
+                Ok(rewrite.map(|mut rewrite| {
+                    rewrite.repo = Some(self.config.partial_repo());
+                    rewrite
+                }))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Author time of the commit analysis can resume from: the checkpoint
+    /// or, if history was rewritten, the point the old and new history
+    /// diverged. `None` if everything must be analyzed.
+    pub fn resume_point(&self) -> Result<Option<DateTime<Utc>>> {
+        let anchor = match self.history_rewrite()? {
+            Some(rewrite) => rewrite.merge_base.and_then(|oid| Oid::from_str(&oid).ok()),
+            None => read_checkpoint(&self.repo),
+        };
+        Ok(anchor
+            .and_then(|oid| self.repo.find_commit(oid).ok())
+            .map(|commit| convert_time(&commit.author().when())))
+    }
+
+    /// Analyzers for the commits which were orphaned by a history rewrite.
+    pub fn orphaned<'a>(
+        &'a self,
+        rewrite: &'a HistoryRewrite,
+    ) -> impl Iterator<Item = Result<CommitAnalyzer<'a>>> + 'a {
+        rewrite.orphaned_commits.iter().map(move |id| {
+            let commit = self.repo.find_commit(Oid::from_str(id)?)?;
+            Ok(
+                CommitAnalyzer::new(&self.repo, commit, &self.config, &self.logger)
+                    .with_progress(self.progress.clone()),
+            )
+        })
+    }
+
+    /// Analyzers for exactly the commits `ids`, which may be abbreviated,
+    /// without walking history.
+    pub fn commits<'a>(
+        &'a self,
+        ids: &'a [String],
+    ) -> impl Iterator<Item = Result<CommitAnalyzer<'a>>> + 'a {
+        ids.iter().map(move |id| {
+            let commit = self.repo.revparse_single(id)?.peel_to_commit()?;
+            Ok(
+                CommitAnalyzer::new(&self.repo, commit, &self.config, &self.logger)
+                    .with_progress(self.progress.clone()),
+            )
         })
     }
 
+    /// Ids of the commits on the first parent history of the default branch,
+    /// i.e. the commits which landed on it directly or as the merge of a
+    /// branch.
+    pub fn mainline(&self) -> Result<HashSet<String>> {
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.simplify_first_parent();
+        match self.default_branch() {
+            Some(branch) => revwalk.push_ref(&branch)?,
+            None => revwalk.push_head()?,
+        }
+        let mut res = HashSet::new();
+        for oid in revwalk {
+            res.insert(oid?.to_string());
+        }
+        Ok(res)
+    }
+
+    /// Pull requests reconstructed from the merge commits on the first parent
+    /// history of the default branch, merged since the `since` of `opts`.
+    pub fn reconstructed_pull_requests(
+        &self,
+        opts: &AnalyzeOpts,
+    ) -> Result<Vec<ReconstructedPullRequest>> {
+        let mut revwalk = self.repo.revwalk()?;
+        revwalk.simplify_first_parent();
+        match self.default_branch() {
+            Some(branch) => revwalk.push_ref(&branch)?,
+            None => revwalk.push_head()?,
+        }
+        let mut res = Vec::new();
+        for oid in revwalk {
+            let commit = self.repo.find_commit(oid?)?;
+            if let Some(ref since) = opts.since {
+                if convert_time(&commit.committer().when()) < *since {
+                    continue;
+                }
+            }
+            if let Some(pr) =
+                ReconstructedPullRequest::from_merge(&self.repo, &commit, &self.config)?
+            {
+                res.push(pr);
+            }
+        }
+        Ok(res)
+    }
+
+    /// Dependencies between the directories at the current head, with churn
+    /// counted since `churn_since`.
+    pub fn dependency_graph(&self, churn_since: &DateTime<Utc>) -> Result<Option<DependencyGraph>> {
+        match self.heads()?.first() {
+            Some(head) => Ok(Some(DependencyGraph::build(
+                &self.repo,
+                &self.repo.find_commit(*head)?,
+                churn_since,
+            )?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Records the current head as the checkpoint for the next analysis.
+    pub fn save_checkpoint(&self) -> Result<()> {
+        if let Some(head) = self.heads()?.first() {
+            write_checkpoint(&self.repo, *head)?;
+        }
+        Ok(())
+    }
+
+    /// Records the last commit analyzed by an interrupted analysis as the
+    /// checkpoint, so that the next analysis picks up from there.
+    pub fn save_checkpoint_at(&self, id: &str) -> Result<()> {
+        write_checkpoint(&self.repo, Oid::from_str(id)?)
+    }
+
     pub fn guess_len(&self, opts: AnalyzeOpts) -> Result<usize> {
-        Ok(self.analyze(opts)?.count())
+        Ok(self.walk(opts, None)?.count())
     }
 
     pub fn from_repo(repo: &Repo, logger: &Logger) -> Result<Self> {
-        Ok(Self::new(repo.repository()?, repo.config(), logger))
+        let repository = repo.repository()?;
+        let config = repo.config().with_mailmap(&repository)?;
+        Ok(Self::new(repository, config, logger))
     }
 }
 
 pub struct AnalyzedRevwalk<'repo> {
     repo: &'repo Repository,
-    revwalk: Revwalk<'repo>,
+    revwalk: Box<dyn Iterator<Item = Result<Oid>> + 'repo>,
+    replacements: HashMap<Oid, Oid>,
     config: &'repo RepoConfig,
     opts: AnalyzeOpts,
+    landings: Option<Arc<Landings>>,
     logger: Logger,
+    progress: Arc<dyn Progress>,
 }
 
 impl<'repo> Iterator for AnalyzedRevwalk<'repo> {
@@ -71,12 +470,16 @@ impl<'repo> Iterator for AnalyzedRevwalk<'repo> {
 
     fn next(&mut self) -> Option<Result<CommitAnalyzer<'repo>>> {
         loop {
+            if self.opts.is_past_deadline() {
+                break None;
+            }
             let rev = self.revwalk.next();
             match rev {
                 None => break None,
                 Some(rev) => {
                     let oid = rev.unwrap();
-                    let commit = self.repo.find_commit(oid).unwrap();
+                    let replacement = self.replacements.get(&oid).cloned();
+                    let commit = self.repo.find_commit(replacement.unwrap_or(oid)).unwrap();
 
                     if let Some(ref since) = self.opts.since {
                         let commit_time = convert_time(&commit.author().when());
@@ -85,14 +488,27 @@ impl<'repo> Iterator for AnalyzedRevwalk<'repo> {
                         }
                     }
 
+                    if !self.config.repo.paths.is_empty()
+                        && !touches_paths(self.repo, &commit, &self.config.repo.paths)
+                    {
+                        continue;
+                    }
+
                     if !self.opts.ignore_unknown_authors
                         || self
                             .config
                             .config
                             .is_known(&Identity::from(commit.author()))
                     {
-                        let analyzer =
-                            CommitAnalyzer::new(self.repo, commit, self.config, &self.logger);
+                        let mut analyzer =
+                            CommitAnalyzer::new(self.repo, commit, self.config, &self.logger)
+                                .with_progress(self.progress.clone());
+                        if replacement.is_some() {
+                            analyzer = analyzer.replacing(oid);
+                        }
+                        if let Some(ref landings) = self.landings {
+                            analyzer = analyzer.with_landings(landings.clone());
+                        }
                         break Some(Ok(analyzer));
                     }
                 }
@@ -101,11 +517,31 @@ impl<'repo> Iterator for AnalyzedRevwalk<'repo> {
     }
 }
 
+/// Whether `commit` changes anything under `paths` relative to its first
+/// parent.
+fn touches_paths(repo: &Repository, commit: &Commit, paths: &[String]) -> bool {
+    let mut diff_opts = DiffOptions::new();
+    for path in paths {
+        diff_opts.pathspec(path);
+    }
+    let parent_tree = commit.parent(0).ok().and_then(|p| p.tree().ok());
+    commit
+        .tree()
+        .and_then(|tree| {
+            repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), Some(&mut diff_opts))
+        })
+        .map(|diff| diff.deltas().len() > 0)
+        .unwrap_or(true)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::landing::LandedVia;
     use crate::repo_info::RepoInfo;
     use crate::test::build_test_logger;
+    use futures::{Future, Stream};
+    use git2::Signature;
 
     #[test]
     fn test_analyze() -> Result<()> {
@@ -120,9 +556,22 @@ mod tests {
         let opts = AnalyzeOpts {
             ignore_unknown_authors: false,
             since: None,
+            resume: false,
+            deadline: None,
+            default_branch_only: false,
+            branch: None,
+            first_parent: false,
+            analyzed_heads: vec![],
         };
         let analyzer = RepoAnalyzer::new(repo, config, &build_test_logger());
-        assert!(analyzer.analyze(opts)?.count() >= 4);
+        assert!(analyzer.analyze(opts.clone())?.count() >= 4);
+
+        let streamed: Vec<AnalyzedCommit> = analyzer.stream(opts.clone(), 1).collect().wait()?;
+        let analyzed = analyzer
+            .analyze(opts)?
+            .map(|c| c.and_then(|c| c.analyze()))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(streamed, analyzed);
         Ok(())
     }
 
@@ -139,9 +588,220 @@ mod tests {
         let opts = AnalyzeOpts {
             ignore_unknown_authors: true,
             since: None,
+            resume: false,
+            deadline: None,
+            default_branch_only: false,
+            branch: None,
+            first_parent: false,
+            analyzed_heads: vec![],
         };
         let analyzer = RepoAnalyzer::new(repo, config, &build_test_logger());
         assert_eq!(analyzer.analyze(opts)?.count(), 0);
         Ok(())
     }
+
+    #[test]
+    fn test_default_branch_only() -> Result<()> {
+        let tmp_dir = tempdir::TempDir::new("default_branch_only")?;
+        let repo = Repository::init(tmp_dir.path())?;
+        repo.set_head("refs/heads/main")?;
+        {
+            let sig = Signature::now("Test", "test@example.com")?;
+            let tree = repo.find_tree(repo.treebuilder(None)?.write()?)?;
+            let first = repo.commit(Some("HEAD"), &sig, &sig, "first", &tree, &[])?;
+            let first = repo.find_commit(first)?;
+            repo.commit(
+                Some("refs/heads/feature"),
+                &sig,
+                &sig,
+                "wip",
+                &tree,
+                &[&first],
+            )?;
+        }
+        let config = RepoConfig {
+            repo: RepoInfo {
+                refs: vec![
+                    "refs/heads/main".to_owned(),
+                    "refs/heads/feature".to_owned(),
+                ],
+                ..RepoInfo::default()
+            },
+            ..RepoConfig::default()
+        };
+        let analyzer = RepoAnalyzer::new(repo, config, &build_test_logger());
+        assert_eq!(
+            analyzer.default_branch(),
+            Some("refs/heads/main".to_owned())
+        );
+        assert_eq!(analyzer.analyze(AnalyzeOpts::default())?.count(), 2);
+        let opts = AnalyzeOpts {
+            default_branch_only: true,
+            ..AnalyzeOpts::default()
+        };
+        assert_eq!(analyzer.analyze(opts)?.count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_branch_first_parent() -> Result<()> {
+        let tmp_dir = tempdir::TempDir::new("branch_first_parent")?;
+        let repo = Repository::init(tmp_dir.path())?;
+        repo.set_head("refs/heads/main")?;
+        let feature = {
+            let sig = Signature::now("Test", "test@example.com")?;
+            let tree = repo.find_tree(repo.treebuilder(None)?.write()?)?;
+            let first = repo.commit(Some("HEAD"), &sig, &sig, "first", &tree, &[])?;
+            let first = repo.find_commit(first)?;
+            let feature = repo.commit(
+                Some("refs/heads/release"),
+                &sig,
+                &sig,
+                "fix",
+                &tree,
+                &[&first],
+            )?;
+            let feature = repo.find_commit(feature)?;
+            repo.commit(
+                Some("HEAD"),
+                &sig,
+                &sig,
+                "Merge release",
+                &tree,
+                &[&first, &feature],
+            )?;
+            feature.id().to_string()
+        };
+        let config = RepoConfig {
+            repo: RepoInfo {
+                refs: vec!["refs/heads/main".to_owned()],
+                ..RepoInfo::default()
+            },
+            ..RepoConfig::default()
+        };
+        let analyzer = RepoAnalyzer::new(repo, config, &build_test_logger());
+        let analyze = |opts: AnalyzeOpts| -> Result<Vec<AnalyzedCommit>> {
+            analyzer
+                .analyze(opts)?
+                .map(|c| c?.analyze())
+                .collect::<Result<Vec<_>>>()
+        };
+        let commits = analyze(AnalyzeOpts::default())?;
+        assert_eq!(commits.len(), 3);
+        let fix = commits.iter().find(|c| c.id == feature).unwrap();
+        assert_eq!(fix.target_branch, Some("main".to_owned()));
+        assert_eq!(fix.landed_via, Some(LandedVia::Merge));
+
+        let first_parent = analyze(AnalyzeOpts {
+            first_parent: true,
+            ..AnalyzeOpts::default()
+        })?;
+        assert_eq!(first_parent.len(), 2);
+        assert!(first_parent.iter().all(|c| c.id != feature));
+
+        let release = analyze(AnalyzeOpts {
+            branch: Some("release".to_owned()),
+            ..AnalyzeOpts::default()
+        })?;
+        assert_eq!(release.len(), 2);
+        assert!(release
+            .iter()
+            .all(|c| c.target_branch == Some("release".to_owned())
+                && c.landed_via == Some(LandedVia::Direct)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_analyzed_heads() -> Result<()> {
+        let tmp_dir = tempdir::TempDir::new("analyzed_heads")?;
+        let repo = Repository::init(tmp_dir.path())?;
+        let (first, second) = {
+            let sig = Signature::now("Test", "test@example.com")?;
+            let tree = repo.find_tree(repo.treebuilder(None)?.write()?)?;
+            let first = repo.commit(Some("HEAD"), &sig, &sig, "first", &tree, &[])?;
+            let second = repo.commit(
+                Some("HEAD"),
+                &sig,
+                &sig,
+                "second",
+                &tree,
+                &[&repo.find_commit(first)?],
+            )?;
+            (first, second)
+        };
+        let config = RepoConfig {
+            repo: RepoInfo {
+                refs: vec!["refs/heads/master".to_owned()],
+                ..RepoInfo::default()
+            },
+            ..RepoConfig::default()
+        };
+        let analyzer = RepoAnalyzer::new(repo, config, &build_test_logger());
+        let heads = analyzer.ref_heads(&AnalyzeOpts::default())?;
+        assert_eq!(heads["refs/heads/master"], second.to_string());
+        let since = |analyzed: &str| AnalyzeOpts {
+            analyzed_heads: vec![analyzed.to_owned()],
+            ..AnalyzeOpts::default()
+        };
+        let new_commits: Vec<_> = analyzer
+            .analyze(since(&first.to_string()))?
+            .map(|c| c.unwrap().analyze().unwrap().id)
+            .collect();
+        assert_eq!(new_commits, vec![second.to_string()]);
+        assert_eq!(analyzer.analyze(since(&second.to_string()))?.count(), 0);
+        // unknown commits, e.g. lost to a force push, are ignored
+        assert_eq!(
+            analyzer.analyze(since(&Oid::zero().to_string()))?.count(),
+            2
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_mainline() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/simple")?;
+        let analyzer = RepoAnalyzer::new(repo, RepoConfig::default(), &build_test_logger());
+        let mainline = analyzer.mainline()?;
+        assert_eq!(mainline.len(), 3);
+        assert!(!mainline.contains("1b70351ae0018e6bf7fd4c9e3262a93b475003c4"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_deadline() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/simple")?;
+        let analyzer = RepoAnalyzer::new(repo, RepoConfig::default(), &build_test_logger());
+        let all = analyzer.analyze(AnalyzeOpts::default())?.count();
+        let opts = AnalyzeOpts {
+            deadline: Some(Utc::now() + chrono::Duration::hours(1)),
+            ..AnalyzeOpts::default()
+        };
+        let first = analyzer.analyze(opts.clone())?.next().unwrap()?.analyze()?;
+        assert!(first.parents.is_empty());
+        assert_eq!(analyzer.analyze(opts)?.count(), all);
+
+        let opts = AnalyzeOpts {
+            deadline: Some(Utc::now()),
+            ..AnalyzeOpts::default()
+        };
+        assert_eq!(analyzer.analyze(opts)?.count(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_commits() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/simple")?;
+        let analyzer = RepoAnalyzer::new(repo, RepoConfig::default(), &build_test_logger());
+        let first = analyzer
+            .analyze(AnalyzeOpts::default())?
+            .next()
+            .unwrap()?
+            .analyze()?;
+        let ids = vec![first.id[..10].to_owned(), "0000000".to_owned()];
+        let analyzed: Vec<_> = analyzer.commits(&ids).collect();
+        assert_eq!(analyzed.len(), 2);
+        assert_eq!(analyzed[0].as_ref().unwrap().analyze()?.id, first.id);
+        assert!(analyzed[1].is_err());
+        Ok(())
+    }
 }
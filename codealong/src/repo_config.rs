@@ -5,7 +5,9 @@ use git2::Repository;
 
 use crate::config::Config;
 use crate::error::*;
-use crate::repo_info::RepoInfo;
+use crate::mailmap::Mailmap;
+use crate::partial_clone::discover;
+use crate::repo_info::{PartialRepoInfo, RepoInfo};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RepoConfig {
@@ -44,7 +46,7 @@ impl RepoConfig {
     ///
     /// If the config has no `name`, then default to the name of the directory.
     pub fn from_dir(path: &Path) -> Result<Self> {
-        Self::from_repository(&Repository::discover(path)?)
+        Self::from_repository(&discover(path)?)
     }
 
     pub fn from_repository(repo: &Repository) -> Result<Self> {
@@ -60,14 +62,45 @@ impl RepoConfig {
             Self::default()
         };
         // TODO: merge this
-        config.repo = RepoInfo::from_repository(&repo)?;
-        Ok(config)
+        config.repo = RepoInfo {
+            remote: config.repo.remote.take(),
+            canonical_url: config.repo.canonical_url.take(),
+            ..Default::default()
+        }
+        .resolve(repo)?;
+        config.with_mailmap(repo)
+    }
+
+    /// Adds the entries of the `.mailmap` of `repo`, which the configured
+    /// entries take precedence over.
+    pub fn with_mailmap(mut self, repo: &Repository) -> Result<Self> {
+        let mut mailmap = Mailmap::from_repository(repo)?;
+        mailmap.extend(std::mem::take(&mut self.config.mailmap));
+        self.config.mailmap = mailmap;
+        Ok(self)
+    }
+
+    /// Name of the repo after following any `repo_aliases`.
+    pub fn repo_name(&self) -> &str {
+        self.config.canonical_repo_name(&self.repo.name)
+    }
+
+    /// Repo info attached to events, under the aliased name of the repo.
+    pub fn partial_repo(&self) -> PartialRepoInfo {
+        PartialRepoInfo::new(self.repo_name(), self.repo.fork, self.repo.archived)
+            .with_metadata(self.repo.metadata.as_ref())
     }
 
     pub fn merge(&mut self, other: RepoConfig) {
         if let None = self.repo.github_name {
             self.repo.github_name = other.repo.github_name.clone();
         }
+        if self.repo.remote.is_none() {
+            self.repo.remote = other.repo.remote.clone();
+        }
+        if self.repo.canonical_url.is_none() {
+            self.repo.canonical_url = other.repo.canonical_url.clone();
+        }
         self.config.merge(other.config);
     }
 }
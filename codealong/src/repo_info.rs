@@ -3,6 +3,7 @@ use regex::Regex;
 use url::Url;
 
 use crate::error::*;
+use crate::repo_metadata::RepoMetadata;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RepoInfo {
@@ -12,39 +13,83 @@ pub struct RepoInfo {
     #[serde(default)]
     pub fork: bool,
 
+    /// Whether the repo is archived, i.e. read-only on its host. See
+    /// `archived_repos` in the config for how archived repos are analyzed.
+    #[serde(default)]
+    pub archived: bool,
+
     #[serde(default)]
     pub github_name: Option<String>,
 
+    /// Path of the project on a GitLab instance, e.g. `gitlab-org/gitlab`.
+    /// Detected from urls whose host contains `gitlab`, and otherwise set in
+    /// the workspace config for self-hosted instances on other domains.
+    #[serde(default)]
+    pub gitlab_name: Option<String>,
+
+    /// Full name of the repo on Bitbucket Cloud, e.g. `atlassian/python-bitbucket`.
+    /// Detected from urls whose host is `bitbucket.org`.
+    #[serde(default)]
+    pub bitbucket_name: Option<String>,
+
     // intentionally left as string since Url does not support ssh+git style urls
     #[serde(default)]
     pub clone_url: String,
 
     #[serde(default)]
     pub refs: Vec<String>,
+
+    /// Paths to scope analysis to. When set, the repo is cloned as a
+    /// blobless partial clone with a sparse checkout of these paths and only
+    /// commits touching them are analyzed.
+    #[serde(default)]
+    pub paths: Vec<String>,
+
+    /// Remote whose url identifies the repo in events. Defaults to
+    /// `upstream` if present, since `origin` is then usually a fork, and
+    /// otherwise `origin`.
+    #[serde(default)]
+    pub remote: Option<String>,
+
+    /// Url identifying the repo in events, overriding `remote`.
+    #[serde(default)]
+    pub canonical_url: Option<String>,
+
+    /// Whether the repo is a mirror kept up to date by other tooling, e.g.
+    /// backups, which is analyzed in place without being fetched.
+    #[serde(default)]
+    pub mirror: bool,
+
+    /// Days within which rewritten lines count as churn in this repo,
+    /// overriding `churn_cutoff` in the config. Globs setting their own
+    /// still take precedence.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub churn_cutoff: Option<u64>,
+
+    /// Metadata fetched from the host of the repo, a subset of which is
+    /// attached to events.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<RepoMetadata>,
 }
 
 impl RepoInfo {
     pub fn from_url(url: &str) -> Result<RepoInfo> {
-        let name = if let Ok(url) = Url::parse(&url) {
-            url.path().to_owned().split_off(1)
-        } else {
-            // We cannot use the url crate to parse ssh urls since they are not
-            // standards compliant, e.g. git@github.com:getoutreach/broccoli-babel.git
-            lazy_static! {
-                static ref SSH_URL_REGEX: Regex =
-                    Regex::new(r#"(.+@)?(.+):(?P<path>.+).git"#).unwrap();
-            }
-            SSH_URL_REGEX
-                .captures(url)
-                .and_then(|captures| captures.name("path"))
-                .ok_or::<Error>(ErrorKind::InvalidRepo(url.to_owned()).into())?
-                .as_str()
-                .to_owned()
-        };
+        let (host, name) =
+            parse_remote_url(url).ok_or::<Error>(ErrorKind::InvalidRepo(url.to_owned()).into())?;
 
         Ok(RepoInfo {
             name: name.clone(),
             github_name: if url.contains("github") {
+                Some(name.clone())
+            } else {
+                None
+            },
+            gitlab_name: if host.contains("gitlab") {
+                Some(name.clone())
+            } else {
+                None
+            },
+            bitbucket_name: if host == "bitbucket.org" {
                 Some(name)
             } else {
                 None
@@ -55,52 +100,72 @@ impl RepoInfo {
     }
 
     pub fn from_repository(repo: &Repository) -> Result<RepoInfo> {
-        let mut repo_info = Self::default();
-        if let Ok(remote) = repo.find_remote("origin") {
-            let url = remote.url().ok_or::<Error>(
-                ErrorKind::InvalidRepo(repo.path().to_string_lossy().to_string()).into(),
-            )?;
-            repo_info.clone_url = url.to_owned();
-            lazy_static! {
-                static ref GITHUB_REGEX: Regex = Regex::new(
-                    r#"(git@github.com:(?P<a>.+/.+).git)|(https://github.com/(?P<b>.+/.+)(?:.git)?)"#
-                )
-                .unwrap();
+        RepoInfo::default().resolve(repo)
+    }
+
+    /// Fills in the identity of the repo from its canonical url, or else the
+    /// url of its canonical remote.
+    pub fn resolve(mut self, repo: &Repository) -> Result<RepoInfo> {
+        let url = match self.canonical_url {
+            Some(ref url) => Some(url.to_owned()),
+            None => {
+                let remote = match self.remote {
+                    Some(ref remote) => Some(remote.to_owned()),
+                    None => canonical_remote(repo)?,
+                };
+                match remote {
+                    Some(remote) => Some(
+                        repo.find_remote(&remote)?
+                            .url()
+                            .ok_or::<Error>(
+                                ErrorKind::InvalidRepo(repo.path().to_string_lossy().to_string())
+                                    .into(),
+                            )?
+                            .to_owned(),
+                    ),
+                    None => None,
+                }
             }
-            GITHUB_REGEX.captures(url).map(|captures| {
-                repo_info.github_name.replace(
-                    captures
-                        .name("a")
-                        .or_else(|| captures.name("b"))
-                        .unwrap()
-                        .as_str()
-                        .to_owned(),
-                );
-            });
+        };
+
+        if let Some(url) = url {
+            if let Some((host, path)) = parse_remote_url(&url) {
+                // Github Enterprise Server hosts are told apart by name,
+                // the same as `from_url` does
+                if host.contains("github") {
+                    self.github_name = Some(path);
+                } else if host.contains("gitlab") && self.gitlab_name.is_none() {
+                    self.gitlab_name = Some(path);
+                } else if host == "bitbucket.org" {
+                    self.bitbucket_name = Some(path);
+                }
+            }
+            self.clone_url = url;
         }
 
-        if let Some(ref github_name) = repo_info.github_name {
-            repo_info.name = github_name.to_owned();
+        if let Some(ref github_name) = self.github_name {
+            self.name = github_name.to_owned();
+        } else if let Some(ref gitlab_name) = self.gitlab_name {
+            self.name = gitlab_name.to_owned();
+        } else if let Some(ref bitbucket_name) = self.bitbucket_name {
+            self.name = bitbucket_name.to_owned();
         } else {
             // if we don't have a remote, we just use the name of the containing
-            // directory
-            repo_info.name = repo
-                .path()
-                .parent()
-                .unwrap()
-                .file_name()
-                .unwrap()
-                .to_string_lossy()
-                .to_string();
+            // directory, which for bare repos is the repo itself
+            let dir = if repo.is_bare() {
+                repo.path()
+            } else {
+                repo.path().parent().unwrap()
+            };
+            let name = dir.file_name().unwrap().to_string_lossy();
+            self.name = name.trim_end_matches(".git").to_owned();
         }
-        Ok(repo_info)
+        Ok(self)
     }
 
     pub fn partial(&self) -> PartialRepoInfo {
-        PartialRepoInfo {
-            name: self.name.clone(),
-            fork: self.fork,
-        }
+        PartialRepoInfo::new(&self.name, self.fork, self.archived)
+            .with_metadata(self.metadata.as_ref())
     }
 }
 
@@ -109,18 +174,115 @@ impl Default for RepoInfo {
         RepoInfo {
             name: "".to_owned(),
             fork: false,
+            archived: false,
             github_name: None,
+            gitlab_name: None,
+            bitbucket_name: None,
             clone_url: "".to_owned(),
             refs: vec!["refs/remotes/origin/master".to_owned()],
+            paths: vec![],
+            remote: None,
+            canonical_url: None,
+            mirror: false,
+            churn_cutoff: None,
+            metadata: None,
         }
     }
 }
 
+/// Picks the remote identifying the repo when none is configured: `upstream`,
+/// then `origin`, then the only remote.
+fn canonical_remote(repo: &Repository) -> Result<Option<String>> {
+    let remotes = repo.remotes()?;
+    let names: Vec<&str> = remotes.iter().flatten().collect();
+    Ok(["upstream", "origin"]
+        .iter()
+        .find(|name| names.contains(name))
+        .cloned()
+        .or_else(|| {
+            if names.len() == 1 {
+                Some(names[0])
+            } else {
+                None
+            }
+        })
+        .map(|name| name.to_owned()))
+}
+
+/// Splits a remote url into its lowercased host and its path, without any
+/// `.git` suffix, so that the ssh and https forms of a url compare equal.
+///
+/// Handles both standard urls, e.g. `https://github.com/foo/bar.git` or
+/// `ssh://git@github.com/foo/bar`, and scp-like urls, e.g.
+/// `git@github.com:foo/bar.git`.
+pub fn parse_remote_url(url: &str) -> Option<(String, String)> {
+    lazy_static! {
+        // We cannot use the url crate to parse scp-like urls since they are
+        // not standards compliant
+        static ref SCP_URL_REGEX: Regex = Regex::new(r#"^(?:[^@/]+@)?([^:/]+):(.+)$"#).unwrap();
+    }
+    let (host, path) = match Url::parse(url) {
+        Ok(ref url) if url.host_str().is_some() => {
+            (url.host_str().unwrap().to_owned(), url.path().to_owned())
+        }
+        _ => {
+            let captures = SCP_URL_REGEX.captures(url)?;
+            (captures[1].to_owned(), captures[2].to_owned())
+        }
+    };
+    let path = path.trim_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    if path.is_empty() {
+        return None;
+    }
+    Some((host.to_lowercase(), path.to_owned()))
+}
+
+/// Normalizes a remote url to `host/path`, e.g. `github.com/foo/bar`.
+pub fn normalize_url(url: &str) -> Option<String> {
+    parse_remote_url(url).map(|(host, path)| format!("{}/{}", host, path))
+}
+
 /// Subset of RepoInfo that is included with each event
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PartialRepoInfo {
     name: String,
     fork: bool,
+    #[serde(default)]
+    language: Option<String>,
+    #[serde(default)]
+    topics: Vec<String>,
+    #[serde(default)]
+    visibility: Option<String>,
+    #[serde(default)]
+    archived: bool,
+}
+
+impl PartialRepoInfo {
+    pub(crate) fn new(name: &str, fork: bool, archived: bool) -> PartialRepoInfo {
+        PartialRepoInfo {
+            name: name.to_owned(),
+            fork,
+            language: None,
+            topics: vec![],
+            visibility: None,
+            archived,
+        }
+    }
+
+    pub(crate) fn with_metadata(mut self, metadata: Option<&RepoMetadata>) -> PartialRepoInfo {
+        if let Some(metadata) = metadata {
+            self.language = metadata.language.clone();
+            self.topics = metadata.topics.clone();
+            self.visibility = metadata.visibility.clone();
+            self.archived |= metadata.archived;
+        }
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
 }
 
 #[cfg(test)]
@@ -137,6 +299,76 @@ mod tests {
             RepoInfo::from_url("git@github.com:getoutreach/broccoli-babel.git")?.name,
             "getoutreach/broccoli-babel"
         );
+        let info = RepoInfo::from_url("git@gitlab.example.com:platform/infra/terraform.git")?;
+        assert_eq!(info.name, "platform/infra/terraform");
+        assert_eq!(
+            info.gitlab_name,
+            Some("platform/infra/terraform".to_owned())
+        );
+        assert_eq!(info.github_name, None);
+        let info = RepoInfo::from_url("https://jdoe@bitbucket.org/acme/billing.git")?;
+        assert_eq!(info.name, "acme/billing");
+        assert_eq!(info.bitbucket_name, Some("acme/billing".to_owned()));
+        assert_eq!(info.gitlab_name, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_url() {
+        for url in &[
+            "https://github.com/foo/bar",
+            "https://github.com/foo/bar.git",
+            "ssh://git@GitHub.com/foo/bar.git",
+            "git@github.com:foo/bar.git",
+            "github.com:foo/bar",
+        ] {
+            assert_eq!(normalize_url(url), Some("github.com/foo/bar".to_owned()));
+        }
+        assert_eq!(normalize_url("/tmp/foo"), None);
+    }
+
+    #[test]
+    fn test_resolve_canonical_remote() -> Result<()> {
+        let tmp_dir = tempdir::TempDir::new("repo_info")?;
+        let repo = Repository::init(tmp_dir.path())?;
+        repo.remote("origin", "git@github.com:me/bar.git")?;
+        assert_eq!(RepoInfo::from_repository(&repo)?.name, "me/bar");
+
+        repo.remote("upstream", "https://github.com/foo/bar.git")?;
+        let info = RepoInfo::from_repository(&repo)?;
+        assert_eq!(info.name, "foo/bar");
+        assert_eq!(info.clone_url, "https://github.com/foo/bar.git");
+
+        let info = RepoInfo {
+            remote: Some("origin".to_owned()),
+            ..Default::default()
+        }
+        .resolve(&repo)?;
+        assert_eq!(info.name, "me/bar");
+
+        let info = RepoInfo {
+            canonical_url: Some("https://github.com/baz/bar".to_owned()),
+            ..Default::default()
+        }
+        .resolve(&repo)?;
+        assert_eq!(info.name, "baz/bar");
         Ok(())
     }
+
+    #[test]
+    fn test_partial_with_metadata() {
+        let mut info = RepoInfo::from_url("https://github.com/foo/bar.git").unwrap();
+        assert_eq!(info.partial().topics, Vec::<String>::new());
+        info.metadata = Some(RepoMetadata {
+            topics: vec!["cli".to_owned()],
+            visibility: Some("public".to_owned()),
+            archived: true,
+            ..RepoMetadata::default()
+        });
+        let partial = info.partial();
+        assert_eq!(partial.name(), "foo/bar");
+        assert_eq!(partial.topics, vec!["cli".to_owned()]);
+        assert_eq!(partial.visibility, Some("public".to_owned()));
+        assert!(partial.archived);
+    }
 }
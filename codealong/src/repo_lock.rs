@@ -0,0 +1,275 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind as IoErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use chrono::prelude::*;
+use chrono::{DateTime, Duration};
+
+use crate::error::*;
+
+/// Distinguishes the files a process writes aside before moving them into
+/// place.
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Age after which a lock is considered abandoned even if its holder cannot
+/// be shown to have exited, e.g. because it ran on another host.
+pub const STALE_LOCK_HOURS: i64 = 24;
+
+/// Who holds a repo lock, recorded in the lock file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockHolder {
+    pub pid: u32,
+    pub host: Option<String>,
+    /// Id of the analysis run holding the lock.
+    pub run: Option<String>,
+    pub acquired_at: DateTime<Utc>,
+}
+
+impl LockHolder {
+    /// The current process.
+    pub fn current(host: Option<String>, run: Option<String>) -> LockHolder {
+        LockHolder {
+            pid: std::process::id(),
+            host,
+            run,
+            acquired_at: Utc::now(),
+        }
+    }
+
+    /// Whether the holder has exited, or held the lock for longer than
+    /// `stale_after`. Only holders known to be on the same host as `current`
+    /// can be checked for having exited, and only where `/proc` is available,
+    /// since the pid of a holder elsewhere may belong to another process here.
+    pub fn is_stale(&self, current: &LockHolder, stale_after: Duration) -> bool {
+        if current.acquired_at - self.acquired_at > stale_after {
+            return true;
+        }
+        let same_host = match (&self.host, &current.host) {
+            (Some(host), Some(current_host)) => host == current_host,
+            _ => false,
+        };
+        let proc_dir = Path::new("/proc");
+        same_host && proc_dir.is_dir() && !proc_dir.join(self.pid.to_string()).exists()
+    }
+}
+
+/// An advisory lock on a repo, so that overlapping invocations on the same
+/// workspace, e.g. a scheduled run and a manual one, do not both write its
+/// checkpoint and state. The lock is released when dropped.
+#[derive(Debug)]
+pub struct RepoLock {
+    path: PathBuf,
+}
+
+impl RepoLock {
+    /// Takes the lock at `path` for `holder`, replacing a stale lock. Returns
+    /// the current holder instead if the lock is held by someone else.
+    ///
+    /// The lock is written aside and linked into place, which fails if the
+    /// lock exists, so that it is never seen half written. A stale lock is
+    /// first moved aside, which only one of the processes finding it stale
+    /// can do, and put back if it turns out to have been replaced by a live
+    /// one in the meantime.
+    pub fn try_acquire(
+        path: &Path,
+        holder: &LockHolder,
+        stale_after: Duration,
+    ) -> Result<std::result::Result<RepoLock, LockHolder>> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp_path = aside(path, "tmp");
+        {
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&tmp_path)?;
+            serde_json::to_writer(&mut file, holder)?;
+            file.write_all(b"\n")?;
+        }
+        let res = RepoLock::link(path, &tmp_path, holder, stale_after);
+        let _ = fs::remove_file(&tmp_path);
+        res
+    }
+
+    fn link(
+        path: &Path,
+        tmp_path: &Path,
+        holder: &LockHolder,
+        stale_after: Duration,
+    ) -> Result<std::result::Result<RepoLock, LockHolder>> {
+        // bounded in case other processes keep replacing the lock
+        for _ in 0..3 {
+            match fs::hard_link(tmp_path, path) {
+                Ok(()) => {
+                    return Ok(Ok(RepoLock {
+                        path: path.to_owned(),
+                    }));
+                }
+                Err(ref e) if e.kind() == IoErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e.into()),
+            }
+            let existing = RepoLock::holder(path);
+            match existing {
+                Some(ref existing) if !existing.is_stale(holder, stale_after) => {
+                    return Ok(Err(existing.clone()));
+                }
+                // unreadable locks were left by a crash before locks were
+                // written aside
+                _ => {}
+            }
+            let stale_path = aside(path, "stale");
+            match fs::rename(path, &stale_path) {
+                Ok(()) => {}
+                // moved aside by another process
+                Err(ref e) if e.kind() == IoErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            }
+            let moved = RepoLock::holder(&stale_path);
+            if moved != existing {
+                // replaced by a live lock after it was found stale
+                let res = fs::hard_link(&stale_path, path);
+                fs::remove_file(&stale_path)?;
+                match (res, moved) {
+                    (Ok(()), Some(moved)) => return Ok(Err(moved)),
+                    (Ok(()), None) => {}
+                    (Err(ref e), _) if e.kind() == IoErrorKind::AlreadyExists => {}
+                    (Err(e), _) => return Err(e.into()),
+                }
+                continue;
+            }
+            fs::remove_file(&stale_path)?;
+        }
+        Ok(Err(RepoLock::holder(path).unwrap_or_else(|| holder.clone())))
+    }
+
+    /// Takes the lock at `path` for `holder` like `try_acquire`, waiting for
+    /// up to `timeout` while someone else holds it.
+    pub fn acquire(
+        path: &Path,
+        holder: &LockHolder,
+        stale_after: Duration,
+        timeout: std::time::Duration,
+    ) -> Result<RepoLock> {
+        let started = std::time::Instant::now();
+        loop {
+            match RepoLock::try_acquire(path, holder, stale_after)? {
+                Ok(lock) => return Ok(lock),
+                Err(existing) if started.elapsed() >= timeout => {
+                    return Err(ErrorKind::Locked(path.display().to_string(), existing.pid).into());
+                }
+                Err(_) => thread::sleep(std::time::Duration::from_millis(100)),
+            }
+        }
+    }
+
+    /// The holder of the lock at `path`, if it is held.
+    pub fn holder(path: &Path) -> Option<LockHolder> {
+        File::open(path)
+            .ok()
+            .and_then(|file| serde_json::from_reader(file).ok())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// A path next to `path` unique to this process and call, with `extension`.
+fn aside(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(format!(
+        ".{}.{}.{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::SeqCst),
+        extension
+    ));
+    path.with_file_name(name)
+}
+
+impl Drop for RepoLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_try_acquire() -> Result<()> {
+        let tmp_dir = tempdir::TempDir::new("repo_lock")?;
+        let path = tmp_dir.path().join("locks/acme/app.lock");
+        let stale_after = Duration::hours(STALE_LOCK_HOURS);
+        let holder = LockHolder::current(Some("host".to_owned()), Some("run-1".to_owned()));
+
+        let lock = RepoLock::try_acquire(&path, &holder, stale_after)?.unwrap();
+        assert_eq!(RepoLock::holder(&path), Some(holder.clone()));
+        let other = LockHolder::current(Some("other".to_owned()), Some("run-2".to_owned()));
+        assert_eq!(
+            RepoLock::try_acquire(&path, &other, stale_after)?.unwrap_err(),
+            holder
+        );
+        drop(lock);
+        assert!(!path.exists());
+
+        // abandoned by a run which is long gone
+        let abandoned = LockHolder {
+            acquired_at: holder.acquired_at - Duration::hours(STALE_LOCK_HOURS + 1),
+            ..other.clone()
+        };
+        let lock = RepoLock::try_acquire(&path, &abandoned, stale_after)?.unwrap();
+        std::mem::forget(lock);
+        let lock = RepoLock::try_acquire(&path, &holder, stale_after)?.unwrap();
+        assert_eq!(RepoLock::holder(lock.path()), Some(holder));
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_stale() {
+        let current = LockHolder::current(Some("host".to_owned()), None);
+        assert!(!current.is_stale(&current, Duration::hours(1)));
+        let exited = LockHolder {
+            pid: u32::MAX,
+            ..current.clone()
+        };
+        assert_eq!(
+            exited.is_stale(&current, Duration::hours(1)),
+            Path::new("/proc").is_dir()
+        );
+        let elsewhere = LockHolder {
+            host: Some("other".to_owned()),
+            ..exited.clone()
+        };
+        assert!(!elsewhere.is_stale(&current, Duration::hours(1)));
+        // hosts which are unknown may differ
+        let unknown = LockHolder {
+            host: None,
+            ..exited
+        };
+        let unknown_current = LockHolder {
+            host: None,
+            ..current
+        };
+        assert!(!unknown.is_stale(&unknown_current, Duration::hours(1)));
+    }
+
+    #[test]
+    fn test_acquire() -> Result<()> {
+        let tmp_dir = tempdir::TempDir::new("repo_lock")?;
+        let path = tmp_dir.path().join("state.lock");
+        let stale_after = Duration::hours(STALE_LOCK_HOURS);
+        let holder = LockHolder::current(Some("host".to_owned()), None);
+        let timeout = std::time::Duration::from_millis(200);
+        let lock = RepoLock::acquire(&path, &holder, stale_after, timeout)?;
+        assert!(RepoLock::acquire(&path, &holder, stale_after, timeout).is_err());
+        drop(lock);
+        RepoLock::acquire(&path, &holder, stale_after, timeout)?;
+        // nothing is left aside
+        assert_eq!(fs::read_dir(tmp_dir.path())?.count(), 0);
+        Ok(())
+    }
+}
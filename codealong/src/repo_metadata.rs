@@ -0,0 +1,66 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
+
+use chrono::prelude::*;
+use chrono::DateTime;
+
+use codealong_model::Event;
+
+/// Metadata about a repo kept by its host rather than in git, e.g. the
+/// topics and visibility of a Github repo.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct RepoMetadata {
+    pub stars: u64,
+    /// Main language, as detected by the host.
+    pub language: Option<String>,
+    /// Bytes of code per language.
+    pub languages: BTreeMap<String, u64>,
+    pub topics: Vec<String>,
+    /// `public`, `private` or `internal`.
+    pub visibility: Option<String>,
+    pub default_branch: Option<String>,
+    pub archived: bool,
+}
+
+/// The metadata of a repo at the time it was fetched.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RepoMetadataSnapshot {
+    pub id: String,
+    pub repo: String,
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub metadata: RepoMetadata,
+}
+
+impl RepoMetadataSnapshot {
+    pub fn new(
+        repo: &str,
+        metadata: RepoMetadata,
+        timestamp: DateTime<Utc>,
+    ) -> RepoMetadataSnapshot {
+        RepoMetadataSnapshot {
+            id: format!("{}-{}", repo, timestamp.format("%Y-%m-%d")),
+            repo: repo.to_owned(),
+            timestamp,
+            metadata,
+        }
+    }
+}
+
+impl Event for RepoMetadataSnapshot {
+    fn timestamp(&self) -> &DateTime<Utc> {
+        &self.timestamp
+    }
+
+    fn event_type(&self) -> &str {
+        "repo_metadata"
+    }
+
+    fn id(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.id)
+    }
+
+    fn tags(&self) -> HashSet<String> {
+        self.metadata.topics.iter().cloned().collect()
+    }
+}
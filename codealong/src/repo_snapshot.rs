@@ -0,0 +1,141 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
+
+use chrono::prelude::*;
+use chrono::DateTime;
+
+use crate::analyzed_commit::AnalyzedCommit;
+use crate::period::Period;
+use codealong_model::{metric_versions, Event, MetricVersions, COMPLIANCE_RATE, SIGNING_RATE};
+
+/// Activity within a single repo over a single period.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RepoSnapshot {
+    pub id: String,
+    pub repo: String,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub commits: u64,
+    /// Commits which were checked against the required trailers.
+    pub checked_commits: u64,
+    pub compliant_commits: u64,
+    pub compliance_rate: Option<f64>,
+    pub signed_commits: u64,
+    pub signing_rate: f64,
+    #[serde(default)]
+    pub metric_versions: MetricVersions,
+}
+
+impl RepoSnapshot {
+    fn new(repo: &str, period_start: DateTime<Utc>, period_end: DateTime<Utc>) -> RepoSnapshot {
+        RepoSnapshot {
+            id: format!("{}-{}", repo, period_start.format("%Y-%m-%d")),
+            repo: repo.to_owned(),
+            period_start,
+            period_end,
+            commits: 0,
+            checked_commits: 0,
+            compliant_commits: 0,
+            compliance_rate: None,
+            signed_commits: 0,
+            signing_rate: 0.0,
+            metric_versions: metric_versions(&[COMPLIANCE_RATE, SIGNING_RATE]),
+        }
+    }
+
+    fn add_commit(&mut self, commit: &AnalyzedCommit) {
+        self.commits += 1;
+        if commit.signature.signed {
+            self.signed_commits += 1;
+        }
+        self.signing_rate = self.signed_commits as f64 / self.commits as f64;
+        if let Some(ref compliance) = commit.compliance {
+            self.checked_commits += 1;
+            if compliance.compliant {
+                self.compliant_commits += 1;
+            }
+            self.compliance_rate =
+                Some(self.compliant_commits as f64 / self.checked_commits as f64);
+        }
+    }
+}
+
+impl Event for RepoSnapshot {
+    fn timestamp(&self) -> &DateTime<Utc> {
+        &self.period_start
+    }
+
+    fn event_type(&self) -> &str {
+        "repo_snapshot"
+    }
+
+    fn id(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.id)
+    }
+
+    fn tags(&self) -> HashSet<String> {
+        HashSet::new()
+    }
+}
+
+/// Accumulates analyzed commits of a single repo into snapshots.
+pub struct RepoSnapshots {
+    repo: String,
+    period: Period,
+    snapshots: BTreeMap<DateTime<Utc>, RepoSnapshot>,
+}
+
+impl RepoSnapshots {
+    pub fn new(repo: &str, period: Period) -> RepoSnapshots {
+        RepoSnapshots {
+            repo: repo.to_owned(),
+            period,
+            snapshots: BTreeMap::new(),
+        }
+    }
+
+    pub fn add_commit(&mut self, commit: &AnalyzedCommit) {
+        let (period_start, period_end) = self.period.bounds(&commit.authored_at);
+        let repo = &self.repo;
+        self.snapshots
+            .entry(period_start)
+            .or_insert_with(|| RepoSnapshot::new(repo, period_start, period_end))
+            .add_commit(commit);
+    }
+
+    pub fn finish(self) -> Vec<RepoSnapshot> {
+        self.snapshots.into_values().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compliance::Compliance;
+    use git2::{Oid, Repository};
+
+    #[test]
+    fn test_rates() {
+        let repo = Repository::open("./fixtures/repos/simple").unwrap();
+        let commit = repo
+            .find_commit(Oid::from_str("86d242301830075e93ff039a4d1e88673a4a3020").unwrap())
+            .unwrap();
+        let mut snapshots = RepoSnapshots::new("simple", Period::Week);
+        for compliant in &[true, false, true, true] {
+            let mut analyzed = AnalyzedCommit::new(&commit);
+            analyzed.signature.signed = *compliant;
+            analyzed.compliance = Some(Compliance {
+                compliant: *compliant,
+                missing: vec![],
+            });
+            snapshots.add_commit(&analyzed);
+        }
+        snapshots.add_commit(&AnalyzedCommit::new(&commit));
+        let snapshots = snapshots.finish();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].commits, 5);
+        assert_eq!(snapshots[0].checked_commits, 4);
+        assert_eq!(snapshots[0].compliance_rate, Some(0.75));
+        assert_eq!(snapshots[0].signing_rate, 0.6);
+    }
+}
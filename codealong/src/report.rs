@@ -0,0 +1,349 @@
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use chrono::prelude::*;
+use chrono::DateTime;
+
+use crate::analyzed_commit::AnalyzedCommit;
+use codealong_metrics::WorkStats;
+
+/// The commits of an author, team or language over the period of a report.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReportRow {
+    pub name: String,
+    pub commits: u64,
+    /// Number of distinct authors of the commits.
+    pub authors: u64,
+    pub added: u64,
+    pub deleted: u64,
+    pub stats: WorkStats,
+    #[serde(skip)]
+    author_ids: BTreeSet<String>,
+}
+
+/// Totals of analyzed commits by author, team and language between `since`
+/// and `until`, rendered as a static HTML page or a terminal table for those
+/// who do not run Kibana.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct Report {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub commits: u64,
+    pub authors: Vec<ReportRow>,
+    pub teams: Vec<ReportRow>,
+    pub languages: Vec<ReportRow>,
+}
+
+impl ReportRow {
+    fn new(name: &str) -> ReportRow {
+        ReportRow {
+            name: name.to_owned(),
+            commits: 0,
+            authors: 0,
+            added: 0,
+            deleted: 0,
+            stats: WorkStats::empty(),
+            author_ids: BTreeSet::new(),
+        }
+    }
+
+    fn add(&mut self, author_id: &str, added: u64, deleted: u64, stats: WorkStats) {
+        self.commits += 1;
+        self.added += added;
+        self.deleted += deleted;
+        self.stats += stats;
+        self.author_ids.insert(author_id.to_owned());
+        self.authors = self.author_ids.len() as u64;
+    }
+}
+
+impl Report {
+    /// A report of the commits authored from `since`, inclusive, until
+    /// `until`, exclusive.
+    pub fn new(since: Option<DateTime<Utc>>, until: Option<DateTime<Utc>>) -> Report {
+        Report {
+            since,
+            until,
+            ..Default::default()
+        }
+    }
+
+    pub fn add_commit(&mut self, commit: &AnalyzedCommit) {
+        // merges only repeat the changes they bring in
+        if commit.parents.len() > 1
+            || self.since.is_some_and(|since| commit.authored_at < since)
+            || self.until.is_some_and(|until| commit.authored_at >= until)
+        {
+            return;
+        }
+        self.commits += 1;
+        let author_id = commit
+            .normalized_author
+            .as_ref()
+            .map(|author| author.id.clone())
+            .or_else(|| commit.author.email.clone())
+            .unwrap_or_default();
+        let author_name = commit
+            .normalized_author
+            .as_ref()
+            .and_then(|author| author.name.clone())
+            .or_else(|| commit.author.name.clone())
+            .unwrap_or_else(|| author_id.clone());
+        let line_stats = &commit.diff.line_stats;
+        let (added, deleted, stats) = (line_stats.added, line_stats.deleted, commit.diff.stats);
+        row(&mut self.authors, &author_name).add(&author_id, added, deleted, stats);
+        for team in commit
+            .normalized_author
+            .iter()
+            .flat_map(|author| &author.teams)
+        {
+            row(&mut self.teams, team).add(&author_id, added, deleted, stats);
+        }
+        for (language, language_stats) in &commit.diff.language_stats {
+            row(&mut self.languages, language).add(
+                &author_id,
+                language_stats.added,
+                language_stats.deleted,
+                language_stats.stats,
+            );
+        }
+    }
+
+    /// Rows ordered by the number of commits, most first.
+    pub fn finish(mut self) -> Report {
+        for rows in &mut [&mut self.authors, &mut self.teams, &mut self.languages] {
+            rows.sort_by(|a, b| b.commits.cmp(&a.commits).then(a.name.cmp(&b.name)));
+        }
+        self
+    }
+
+    /// Renders the report as tables drawn with box-drawing characters.
+    pub fn to_table(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "{}, {} commits\n", self.period(), self.commits).unwrap();
+        for (title, rows) in self.sections() {
+            writeln!(out, "{}", title).unwrap();
+            let cells: Vec<Vec<String>> = rows.iter().map(cells).collect();
+            let mut widths: Vec<usize> = COLUMNS.iter().map(|c| c.chars().count()).collect();
+            for row in &cells {
+                for (width, cell) in widths.iter_mut().zip(row) {
+                    *width = (*width).max(cell.chars().count());
+                }
+            }
+            let rule = |left: &str, middle: &str, right: &str| {
+                let lines: Vec<String> = widths.iter().map(|w| "─".repeat(w + 2)).collect();
+                format!("{}{}{}", left, lines.join(middle), right)
+            };
+            writeln!(out, "{}", rule("┌", "┬", "┐")).unwrap();
+            let header: Vec<String> = COLUMNS.iter().map(|c| c.to_string()).collect();
+            writeln!(out, "{}", table_line(&header, &widths)).unwrap();
+            writeln!(out, "{}", rule("├", "┼", "┤")).unwrap();
+            for row in &cells {
+                writeln!(out, "{}", table_line(row, &widths)).unwrap();
+            }
+            writeln!(out, "{}\n", rule("└", "┴", "┘")).unwrap();
+        }
+        out
+    }
+
+    /// Renders the report as a self-contained HTML page.
+    pub fn to_html(&self) -> String {
+        let mut out = String::new();
+        writeln!(
+            out,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>Codealong report</title>\n<style>{}</style>\n</head>\n<body>",
+            STYLE
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "<h1>Codealong report</h1>\n<p>{}, {} commits</p>",
+            escape(&self.period()),
+            self.commits
+        )
+        .unwrap();
+        for (title, rows) in self.sections() {
+            writeln!(out, "<h2>{}</h2>\n<table>\n<tr>", title).unwrap();
+            for column in COLUMNS {
+                writeln!(out, "<th>{}</th>", column).unwrap();
+            }
+            writeln!(out, "</tr>").unwrap();
+            for row in rows {
+                let row = cells(row);
+                writeln!(out, "<tr><td>{}</td>", escape(&row[0])).unwrap();
+                for cell in &row[1..] {
+                    writeln!(out, "<td class=\"number\">{}</td>", cell).unwrap();
+                }
+                writeln!(out, "</tr>").unwrap();
+            }
+            writeln!(out, "</table>").unwrap();
+        }
+        writeln!(out, "</body>\n</html>").unwrap();
+        out
+    }
+
+    fn sections(&self) -> Vec<(&'static str, &[ReportRow])> {
+        vec![
+            ("Authors", &self.authors),
+            ("Teams", &self.teams),
+            ("Languages", &self.languages),
+        ]
+        .into_iter()
+        .filter(|(_, rows)| !rows.is_empty())
+        .map(|(title, rows)| (title, rows.as_slice()))
+        .collect()
+    }
+
+    fn period(&self) -> String {
+        let date = |at: &Option<DateTime<Utc>>| at.map(|at| at.format("%Y-%m-%d").to_string());
+        match (date(&self.since), date(&self.until)) {
+            (Some(since), Some(until)) => format!("From {} until {}", since, until),
+            (Some(since), None) => format!("Since {}", since),
+            (None, Some(until)) => format!("Until {}", until),
+            (None, None) => "All time".to_owned(),
+        }
+    }
+}
+
+const COLUMNS: &[&str] = &[
+    "Name",
+    "Commits",
+    "Authors",
+    "Added",
+    "Deleted",
+    "New work",
+    "Churn",
+    "Legacy refactor",
+    "Help others",
+];
+
+const STYLE: &str = "body{font-family:sans-serif;margin:2em}\
+                     table{border-collapse:collapse;margin-bottom:2em}\
+                     th,td{border:1px solid #ccc;padding:4px 8px}\
+                     th{background:#f4f4f4}td.number{text-align:right}";
+
+fn row<'a>(rows: &'a mut Vec<ReportRow>, name: &str) -> &'a mut ReportRow {
+    let index = match rows.iter().position(|row| row.name == name) {
+        Some(index) => index,
+        None => {
+            rows.push(ReportRow::new(name));
+            rows.len() - 1
+        }
+    };
+    &mut rows[index]
+}
+
+fn cells(row: &ReportRow) -> Vec<String> {
+    let mut cells = vec![row.name.clone()];
+    cells.extend(
+        [
+            row.commits,
+            row.authors,
+            row.added,
+            row.deleted,
+            row.stats.new_work,
+            row.stats.churn,
+            row.stats.legacy_refactor,
+            row.stats.help_others,
+        ]
+        .iter()
+        .map(|value| value.to_string()),
+    );
+    cells
+}
+
+/// A line of a table with the name left aligned and the numbers right
+/// aligned.
+fn table_line(cells: &[String], widths: &[usize]) -> String {
+    let cells: Vec<String> = cells
+        .iter()
+        .zip(widths)
+        .enumerate()
+        .map(|(i, (cell, width))| {
+            let padding = " ".repeat(width - cell.chars().count());
+            if i == 0 {
+                format!(" {}{} ", cell, padding)
+            } else {
+                format!(" {}{} ", padding, cell)
+            }
+        })
+        .collect();
+    format!("│{}│", cells.join("│"))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codealong_metrics::LanguageStats;
+    use codealong_model::Person;
+    use git2::{Oid, Repository};
+
+    #[test]
+    fn test_report() {
+        let repo = Repository::open("./fixtures/repos/simple").unwrap();
+        let commit = repo
+            .find_commit(Oid::from_str("86d242301830075e93ff039a4d1e88673a4a3020").unwrap())
+            .unwrap();
+        let person = |id: &str, teams: &[&str]| Person {
+            id: id.to_owned(),
+            name: Some(id.to_uppercase()),
+            email: None,
+            github_login: None,
+            teams: teams.iter().map(|team| team.to_string()).collect(),
+        };
+        let mut report = Report::new(
+            Some(Utc.ymd(2019, 1, 1).and_hms(0, 0, 0)),
+            Some(Utc.ymd(2019, 2, 1).and_hms(0, 0, 0)),
+        );
+        for (author, day) in &[("bob", 3), ("alice", 4), ("alice", 5), ("alice", 40)] {
+            let mut analyzed = AnalyzedCommit::new(&commit);
+            analyzed.parents = vec![];
+            analyzed.authored_at =
+                Utc.ymd(2019, 1, 1).and_hms(0, 0, 0) + chrono::Duration::days(*day);
+            analyzed.normalized_author = Some(person(author, &["core"]));
+            analyzed.diff.line_stats.added = 2;
+            analyzed.diff.stats.new_work = 2;
+            analyzed.diff.language_stats = vec![(
+                "Rust".to_owned(),
+                LanguageStats {
+                    files: 1,
+                    added: 2,
+                    deleted: 0,
+                    stats: WorkStats::new_work(),
+                },
+            )]
+            .into_iter()
+            .collect();
+            report.add_commit(&analyzed);
+        }
+        let report = report.finish();
+        assert_eq!(report.commits, 3);
+        let authors: Vec<(&str, u64)> = report
+            .authors
+            .iter()
+            .map(|row| (row.name.as_str(), row.commits))
+            .collect();
+        assert_eq!(authors, vec![("ALICE", 2), ("BOB", 1)]);
+        assert_eq!(report.authors[0].added, 4);
+        assert_eq!(report.authors[0].stats.new_work, 4);
+        assert_eq!(report.teams[0].name, "core");
+        assert_eq!(report.teams[0].authors, 2);
+        assert_eq!(report.languages[0].name, "Rust");
+        assert_eq!(report.languages[0].added, 6);
+
+        let table = report.to_table();
+        assert!(table.starts_with("From 2019-01-01 until 2019-02-01, 3 commits"));
+        assert!(table.contains("│ ALICE │       2 │       1 │"));
+        let html = report.to_html();
+        assert!(html.contains("<h2>Languages</h2>"));
+        assert!(html.contains("<tr><td>BOB</td>"));
+    }
+}
@@ -0,0 +1,434 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+use std::path::Path;
+
+use chrono::prelude::*;
+use chrono::DateTime;
+use git2::{BlameOptions, Commit, DiffOptions, Oid, Repository};
+use glob::{MatchOptions, Pattern};
+
+use crate::config::Config;
+use crate::error::Result;
+use crate::identity::Identity;
+use crate::utils::convert_time;
+use codealong_model::Person;
+
+/// Where Github looks for the CODEOWNERS file, in order.
+const CODEOWNERS_PATHS: &[&str] = &[".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+/// Days after which a line counts half as much towards knowing it, so that
+/// the authors of recent changes are preferred over those of old ones.
+const HALF_LIFE_DAYS: f64 = 90.0;
+
+/// Owners of the files of a repo by the gitignore style patterns of its
+/// CODEOWNERS file, the last matching pattern taking precedence.
+#[derive(Debug, Clone, Default)]
+pub struct Codeowners {
+    rules: Vec<(Vec<Pattern>, Vec<String>)>,
+}
+
+impl Codeowners {
+    pub fn parse(content: &str) -> Codeowners {
+        let mut rules = vec![];
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let mut parts = line.split_whitespace();
+            let pattern = match parts.next() {
+                Some(pattern) => pattern,
+                None => continue,
+            };
+            let owners = parts.map(str::to_owned).collect();
+            rules.push((patterns(pattern), owners));
+        }
+        Codeowners { rules }
+    }
+
+    /// The CODEOWNERS file of the tree of `commit`, if it has one.
+    pub fn from_commit(repo: &Repository, commit: &Commit) -> Result<Option<Codeowners>> {
+        let tree = commit.tree()?;
+        for path in CODEOWNERS_PATHS {
+            if let Ok(entry) = tree.get_path(Path::new(path)) {
+                let blob = entry.to_object(repo)?.peel_to_blob()?;
+                return Ok(Some(Codeowners::parse(&String::from_utf8_lossy(
+                    blob.content(),
+                ))));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The owners of `path`: Github logins and teams prefixed with `@`, and
+    /// emails. Empty if the matching pattern lists none, which unsets the
+    /// owners of the files it matches.
+    pub fn owners(&self, path: &str) -> &[String] {
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..MatchOptions::new()
+        };
+        self.rules
+            .iter()
+            .rev()
+            .find(|(patterns, _)| patterns.iter().any(|p| p.matches_with(path, &options)))
+            .map_or(&[], |(_, owners)| &owners[..])
+    }
+}
+
+/// Globs matching the same paths as a CODEOWNERS pattern: patterns without
+/// a slash but at the end match at any depth, and patterns match the
+/// contents of the directories they match.
+fn patterns(pattern: &str) -> Vec<Pattern> {
+    let anchored = pattern.trim_end_matches('/').contains('/');
+    let pattern = pattern.trim_start_matches('/');
+    let pattern = if anchored || pattern.starts_with("**") {
+        pattern.to_owned()
+    } else {
+        format!("**/{}", pattern)
+    };
+    let directory = format!("{}/**", pattern.trim_end_matches('/'));
+    let mut globs = vec![directory];
+    if !pattern.ends_with('/') {
+        globs.push(pattern);
+    }
+    globs
+        .iter()
+        .filter_map(|glob| Pattern::new(glob).ok())
+        .collect()
+}
+
+/// Someone who could review a change, with why.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReviewerCandidate {
+    /// Id of the person, or the handle of a team owning changed files.
+    pub id: String,
+    pub person: Option<Person>,
+    /// Higher is better: the share of the touched lines last changed by the
+    /// candidate, weighted by recency, plus the share of the changed files
+    /// they own, divided by one more than their open reviews.
+    pub score: f64,
+    /// Touched lines last changed by the candidate.
+    pub authored_lines: u64,
+    /// Changed files the candidate owns per CODEOWNERS.
+    pub owned_paths: Vec<String>,
+    /// Reviews the candidate has pending.
+    pub load: u64,
+}
+
+/// Ranked reviewers for the commits of a head which are not on its base.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReviewerSuggestions {
+    pub merge_base: String,
+    pub head: String,
+    /// Best first, leaving out the authors of the change.
+    pub candidates: Vec<ReviewerCandidate>,
+}
+
+#[derive(Default)]
+struct Tally {
+    person: Option<Person>,
+    weight: f64,
+    lines: u64,
+    owned_paths: Vec<String>,
+}
+
+impl ReviewerSuggestions {
+    /// Suggests reviewers for the changes between the merge base of `base`
+    /// and `head`, and `head`, from the authors of the lines they touch as
+    /// of the merge base and the CODEOWNERS file of `head`. `load` is the
+    /// number of pending reviews by person id or handle, and `at` the time
+    /// recency is measured from.
+    pub fn build(
+        repo: &Repository,
+        base: &Commit,
+        head: &Commit,
+        config: &Config,
+        load: &HashMap<String, u64>,
+        at: &DateTime<Utc>,
+    ) -> Result<ReviewerSuggestions> {
+        let merge_base = repo.find_commit(repo.merge_base(base.id(), head.id())?)?;
+        let mut revwalk = repo.revwalk()?;
+        revwalk.push(head.id())?;
+        revwalk.hide(merge_base.id())?;
+        let mut change_authors = HashSet::new();
+        for oid in revwalk {
+            let commit = repo.find_commit(oid?)?;
+            let author = Identity::from(commit.author());
+            change_authors.insert(config.person_for_identity(&author).id);
+        }
+
+        let mut diff_opts = DiffOptions::new();
+        diff_opts.context_lines(0);
+        let diff = repo.diff_tree_to_tree(
+            Some(&merge_base.tree()?),
+            Some(&head.tree()?),
+            Some(&mut diff_opts),
+        )?;
+        // touched line numbers of the base version of each changed file
+        let mut touched: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        let mut changed_paths = vec![];
+        diff.foreach(
+            &mut |delta, _| {
+                if let Some(path) = delta.new_file().path().or_else(|| delta.old_file().path()) {
+                    changed_paths.push(path.to_string_lossy().into_owned());
+                }
+                true
+            },
+            None,
+            Some(&mut |delta, hunk| {
+                if let Some(path) = delta.old_file().path() {
+                    let start = hunk.old_start() as usize;
+                    // pure additions touch the line they follow
+                    let lines = (hunk.old_lines() as usize).max(1);
+                    touched
+                        .entry(path.to_string_lossy().into_owned())
+                        .or_default()
+                        .extend((start..start + lines).filter(|&line| line > 0));
+                }
+                true
+            }),
+            None,
+        )?;
+
+        let mut tallies: BTreeMap<String, Tally> = BTreeMap::new();
+        let mut emails: HashMap<String, String> = HashMap::new();
+        for (path, lines) in &touched {
+            let mut blame_opts = BlameOptions::new();
+            blame_opts.newest_commit(merge_base.id());
+            let blame = match repo.blame_file(Path::new(path), Some(&mut blame_opts)) {
+                Ok(blame) => blame,
+                // e.g. added files
+                Err(_) => continue,
+            };
+            let mut authors: HashMap<Oid, (Person, f64)> = HashMap::new();
+            for &line in lines {
+                let hunk = match blame.get_line(line) {
+                    Some(hunk) => hunk,
+                    None => continue,
+                };
+                let (person, weight) = authors
+                    .entry(hunk.final_commit_id())
+                    .or_insert_with(|| {
+                        let signature = hunk.final_signature();
+                        let authored_at = convert_time(&signature.when());
+                        let age = (*at - authored_at).num_days().max(0) as f64;
+                        (
+                            config.person_for_identity_at(&Identity::from(signature), &authored_at),
+                            0.5f64.powf(age / HALF_LIFE_DAYS),
+                        )
+                    })
+                    .clone();
+                if let Some(ref email) = person.email {
+                    emails.insert(email.to_lowercase(), person.id.clone());
+                }
+                let tally = tallies.entry(person.id.clone()).or_default();
+                tally.person = Some(person);
+                tally.weight += weight;
+                tally.lines += 1;
+            }
+        }
+
+        if let Some(codeowners) = Codeowners::from_commit(repo, head)? {
+            for path in &changed_paths {
+                for owner in codeowners.owners(path) {
+                    let (id, person) = match owner.strip_prefix('@') {
+                        Some(team) if team.contains('/') => (owner.clone(), None),
+                        Some(login) => {
+                            let person = config.person_for_github_login(login);
+                            (person.id.clone(), Some(person))
+                        }
+                        None => match emails.get(&owner.to_lowercase()) {
+                            Some(id) => (id.clone(), None),
+                            None => {
+                                let person = config.person_for_identity(&Identity {
+                                    name: None,
+                                    email: Some(owner.clone()),
+                                });
+                                (person.id.clone(), Some(person))
+                            }
+                        },
+                    };
+                    let tally = tallies.entry(id).or_default();
+                    if tally.person.is_none() {
+                        tally.person = person;
+                    }
+                    if !tally.owned_paths.contains(path) {
+                        tally.owned_paths.push(path.clone());
+                    }
+                }
+            }
+        }
+
+        let total_weight: f64 = tallies.values().map(|tally| tally.weight).sum();
+        let mut candidates: Vec<ReviewerCandidate> = tallies
+            .into_iter()
+            .filter(|(id, _)| !change_authors.contains(id))
+            .map(|(id, tally)| {
+                let load = load.get(&id).cloned().unwrap_or(0);
+                let authorship = if total_weight > 0.0 {
+                    tally.weight / total_weight
+                } else {
+                    0.0
+                };
+                let ownership = tally.owned_paths.len() as f64 / changed_paths.len().max(1) as f64;
+                ReviewerCandidate {
+                    score: (authorship + ownership) / (1 + load) as f64,
+                    id,
+                    person: tally.person,
+                    authored_lines: tally.lines,
+                    owned_paths: tally.owned_paths,
+                    load,
+                }
+            })
+            .collect();
+        candidates.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.id.cmp(&b.id))
+        });
+
+        Ok(ReviewerSuggestions {
+            merge_base: merge_base.id().to_string(),
+            head: head.id().to_string(),
+            candidates,
+        })
+    }
+
+    /// One candidate per line, best first.
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for candidate in &self.candidates {
+            let name = candidate
+                .person
+                .as_ref()
+                .and_then(|person| person.github_login.as_ref().or(person.name.as_ref()))
+                .unwrap_or(&candidate.id);
+            let mut reasons = vec![];
+            if candidate.authored_lines > 0 {
+                reasons.push(format!("{} lines touched", candidate.authored_lines));
+            }
+            if !candidate.owned_paths.is_empty() {
+                reasons.push(format!("owns {} files", candidate.owned_paths.len()));
+            }
+            if candidate.load > 0 {
+                reasons.push(format!("{} pending reviews", candidate.load));
+            }
+            writeln!(
+                out,
+                "{:.2}\t{}\t{}",
+                candidate.score,
+                name,
+                reasons.join(", ")
+            )
+            .unwrap();
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{Signature, Time};
+
+    fn commit(repo: &Repository, dir: &Path, author: &str, files: &[(&str, &str)]) -> Result<Oid> {
+        for (path, content) in files {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap())?;
+            std::fs::write(&path, content)?;
+        }
+        let mut index = repo.index()?;
+        index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
+        index.write()?;
+        let tree = repo.find_tree(index.write_tree()?)?;
+        let sig = Signature::new(
+            author,
+            &format!("{}@example.com", author.to_lowercase()),
+            &Time::new(1_500_000_000, 0),
+        )?;
+        let parents = match repo.head() {
+            Ok(head) => vec![head.peel_to_commit()?],
+            Err(_) => vec![],
+        };
+        let parents: Vec<&Commit> = parents.iter().collect();
+        Ok(repo.commit(Some("HEAD"), &sig, &sig, "Change", &tree, &parents)?)
+    }
+
+    #[test]
+    fn test_codeowners() {
+        let codeowners = Codeowners::parse(
+            "# comment\n* @everyone\n*.rs @rustacean\n/docs/ docs@example.com\nsrc/vendor/ @org/vendoring\nsrc/vendor/unowned.rs\n",
+        );
+        assert_eq!(codeowners.owners("README.md"), &["@everyone".to_owned()]);
+        assert_eq!(codeowners.owners("src/lib.rs"), &["@rustacean".to_owned()]);
+        assert_eq!(
+            codeowners.owners("docs/guide/index.md"),
+            &["docs@example.com".to_owned()]
+        );
+        assert_eq!(
+            codeowners.owners("src/vendor/a/b.c"),
+            &["@org/vendoring".to_owned()]
+        );
+        assert!(codeowners.owners("src/vendor/unowned.rs").is_empty());
+    }
+
+    #[test]
+    fn test_build() -> Result<()> {
+        let tmp_dir = tempdir::TempDir::new("reviewers")?;
+        let dir = tmp_dir.path();
+        let repo = Repository::init(dir)?;
+        commit(
+            &repo,
+            dir,
+            "Alice",
+            &[
+                ("src/lib.rs", "fn a() {}\nfn b() {}\n"),
+                ("CODEOWNERS", "*.md @bob\n/src/ @org/core\n"),
+            ],
+        )?;
+        let base = commit(
+            &repo,
+            dir,
+            "Carol",
+            &[("src/lib.rs", "fn a() {}\nfn c() {}\n")],
+        )?;
+        let head = commit(
+            &repo,
+            dir,
+            "Dave",
+            &[
+                ("src/lib.rs", "fn a() {}\nfn d() {}\n"),
+                ("README.md", "hi\n"),
+            ],
+        )?;
+
+        let config = Config::base();
+        let mut load = HashMap::new();
+        load.insert("bob".to_owned(), 1);
+        let suggestions = ReviewerSuggestions::build(
+            &repo,
+            &repo.find_commit(base)?,
+            &repo.find_commit(head)?,
+            &config,
+            &load,
+            &Utc::now(),
+        )?;
+        assert_eq!(suggestions.merge_base, base.to_string());
+        let ids: Vec<&str> = suggestions
+            .candidates
+            .iter()
+            .map(|candidate| &*candidate.id)
+            .collect();
+        assert_eq!(ids, vec!["Carol <carol@example.com>", "@org/core", "bob"]);
+        let carol = &suggestions.candidates[0];
+        assert_eq!(carol.authored_lines, 1);
+        assert!((carol.score - 1.0).abs() < 1e-9);
+        let bob = &suggestions.candidates[2];
+        assert_eq!(bob.owned_paths, vec!["README.md".to_owned()]);
+        assert_eq!(bob.load, 1);
+        assert!((bob.score - 0.25).abs() < 1e-9);
+        assert!(suggestions
+            .to_text()
+            .starts_with("1.00\tCarol\t1 lines touched\n"));
+        Ok(())
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::HashSet;
+
+use chrono::prelude::*;
+use chrono::DateTime;
+use git2::{Oid, Repository};
+
+use crate::analyzed_diff::AnalyzedDiff;
+use crate::ci_report::History;
+use crate::config::Config;
+use crate::error::Result;
+use codealong_metrics::WorkStats;
+
+/// Tag of the files holding tests.
+const TEST_TAG: &str = "test";
+
+/// Lines changed at which a change counts as half as risky as it gets by
+/// size alone.
+const HALF_RISK_LINES: f64 = 400.0;
+
+/// Weights of the hotspot, unfamiliarity, size and untested signals in the
+/// score.
+const WEIGHTS: [f64; 4] = [0.35, 0.25, 0.2, 0.2];
+
+/// How risky a change is, from the signals combined into its score.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskLevel {
+    Low,
+    Medium,
+    High,
+}
+
+/// Risk of a commit or pull request, between 0 and 1, combining how much of
+/// it touches hotspots, how unfamiliar its author is with the lines it
+/// changes, its size, and how little of it is tests.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Risk {
+    pub score: f64,
+    pub level: RiskLevel,
+    /// Share of the files changed which are hotspots.
+    pub hotspot: f64,
+    /// Share of the lines modified which were last changed by someone else,
+    /// or too long ago to be churn.
+    pub unfamiliarity: f64,
+    pub size: f64,
+    /// 1 when no tests changed along with the code, down to 0 when at least
+    /// a third of the lines changed are in tests.
+    pub untested: f64,
+}
+
+/// Files among the top tenth of the files of a repo by churn.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Hotspots {
+    paths: HashSet<String>,
+}
+
+impl Hotspots {
+    /// Finds the hotspots from the commits reachable from `head` authored
+    /// since `since`.
+    pub fn walk(
+        repo: &Repository,
+        head: Oid,
+        config: &Config,
+        since: &DateTime<Utc>,
+    ) -> Result<Hotspots> {
+        let history = History::walk(repo, head, config, since)?;
+        let threshold = history.hotspot_threshold();
+        Ok(Hotspots {
+            paths: history
+                .churn
+                .into_iter()
+                .filter(|(_, churn)| *churn >= threshold)
+                .map(|(path, _)| path)
+                .collect(),
+        })
+    }
+
+    pub fn contains(&self, path: &str) -> bool {
+        self.paths.contains(path)
+    }
+}
+
+impl Risk {
+    pub fn assess(diff: &AnalyzedDiff, hotspots: &Hotspots) -> Risk {
+        let hotspot = if diff.files.is_empty() {
+            0.0
+        } else {
+            diff.files
+                .iter()
+                .filter(|file| hotspots.contains(&file.path))
+                .count() as f64
+                / diff.files.len() as f64
+        };
+        let modified = diff.stats.churn + diff.stats.help_others + diff.stats.legacy_refactor;
+        let unfamiliarity = if modified > 0 {
+            (diff.stats.help_others + diff.stats.legacy_refactor) as f64 / modified as f64
+        } else {
+            0.0
+        };
+        let changed = lines(&diff.stats);
+        let size = changed as f64 / (changed as f64 + HALF_RISK_LINES);
+        let test_lines: u64 = diff
+            .files
+            .iter()
+            .filter(|file| file.tags.iter().any(|tag| tag == TEST_TAG))
+            .map(|file| lines(&file.stats))
+            .sum();
+        let untested = if changed > 0 {
+            (1.0 - 3.0 * test_lines as f64 / changed as f64).max(0.0)
+        } else {
+            0.0
+        };
+        let score = [hotspot, unfamiliarity, size, untested]
+            .iter()
+            .zip(WEIGHTS.iter())
+            .map(|(signal, weight)| signal * weight)
+            .sum::<f64>();
+        Risk {
+            score,
+            level: RiskLevel::from_score(score),
+            hotspot,
+            unfamiliarity,
+            size,
+            untested,
+        }
+    }
+}
+
+impl RiskLevel {
+    pub fn from_score(score: f64) -> RiskLevel {
+        if score >= 0.6 {
+            RiskLevel::High
+        } else if score >= 0.35 {
+            RiskLevel::Medium
+        } else {
+            RiskLevel::Low
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RiskLevel::Low => "low",
+            RiskLevel::Medium => "medium",
+            RiskLevel::High => "high",
+        }
+    }
+}
+
+/// Lines changed according to work stats.
+fn lines(stats: &WorkStats) -> u64 {
+    stats.new_work + stats.legacy_refactor + stats.churn + stats.help_others + stats.other
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzed_diff::FileStats;
+
+    fn file(path: &str, tags: &[&str], stats: WorkStats) -> FileStats {
+        FileStats {
+            path: path.to_owned(),
+            old_path: None,
+            renamed: false,
+            copied: false,
+            canonical_path: None,
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            weight: 1.0,
+            language: None,
+            stats,
+            complexity: None,
+            skipped_reason: None,
+        }
+    }
+
+    #[test]
+    fn test_assess() {
+        let hotspots = Hotspots {
+            paths: vec!["src/lib.rs".to_owned()].into_iter().collect(),
+        };
+        let lib = WorkStats {
+            help_others: 300,
+            churn: 100,
+            ..Default::default()
+        };
+        let other = WorkStats {
+            new_work: 400,
+            ..Default::default()
+        };
+        let diff = AnalyzedDiff {
+            stats: lib + other,
+            files: vec![file("src/lib.rs", &[], lib), file("src/new.rs", &[], other)],
+            ..Default::default()
+        };
+        let risk = Risk::assess(&diff, &hotspots);
+        assert_eq!(risk.hotspot, 0.5);
+        assert_eq!(risk.unfamiliarity, 0.75);
+        assert_eq!(risk.size, 800.0 / 1200.0);
+        assert_eq!(risk.untested, 1.0);
+        assert_eq!(risk.level, RiskLevel::High);
+
+        let tests = WorkStats {
+            new_work: 400,
+            ..Default::default()
+        };
+        let diff = AnalyzedDiff {
+            stats: other + tests,
+            files: vec![
+                file("src/new.rs", &[], other),
+                file("tests/new.rs", &["test"], tests),
+            ],
+            ..Default::default()
+        };
+        let risk = Risk::assess(&diff, &hotspots);
+        assert_eq!(risk.hotspot, 0.0);
+        assert_eq!(risk.unfamiliarity, 0.0);
+        assert_eq!(risk.untested, 0.0);
+        assert_eq!(risk.level, RiskLevel::Low);
+    }
+
+    #[test]
+    fn test_empty() {
+        let risk = Risk::assess(&AnalyzedDiff::empty(), &Hotspots::default());
+        assert_eq!(risk.score, 0.0);
+        assert_eq!(risk.level, RiskLevel::Low);
+    }
+}
@@ -0,0 +1,373 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use chrono::{DateTime, Datelike, Duration, Utc, Weekday};
+use git2::{IndexAddOption, Oid, Repository, Signature, Time};
+use rand::prng::XorShiftRng;
+use rand::{Rng, SeedableRng};
+
+use crate::error::Result;
+use crate::workspace::Workspace;
+use crate::workspace_config::WorkspaceConfig;
+
+/// Fictional people committing to the sample repos: name, email, Github
+/// login and team.
+const AUTHORS: &[(&str, &str, &str, &str)] = &[
+    ("Ada Lovelace", "ada@example.com", "ada", "platform"),
+    ("Grace Hopper", "grace@example.com", "grace", "platform"),
+    ("Alan Turing", "alan@example.com", "alan", "product"),
+    (
+        "Katherine Johnson",
+        "katherine@example.com",
+        "katherine",
+        "product",
+    ),
+];
+
+/// The sample repos: name, source files and lockfile.
+const REPOS: &[(&str, &[&str], &str)] = &[
+    (
+        "billing",
+        &["src/invoice.rs", "src/tax.rs", "src/ledger.rs"],
+        "Cargo.lock",
+    ),
+    (
+        "storefront",
+        &["src/cart.js", "src/checkout.js", "src/catalog.js"],
+        "package-lock.json",
+    ),
+    (
+        "pipelines",
+        &["etl/extract.py", "etl/transform.py", "etl/load.py"],
+        "poetry.lock",
+    ),
+];
+
+/// Topics of the synthetic commit messages.
+const TOPICS: &[&str] = &[
+    "rounding",
+    "currency codes",
+    "retries",
+    "pagination",
+    "caching",
+    "timeouts",
+    "validation",
+    "logging",
+];
+
+/// Weeks of history generated.
+const WEEKS: i64 = 12;
+
+/// Generates a workspace in `dir` of a few repos with `WEEKS` weeks of
+/// synthetic history up to `end`, by fictional authors on two teams, along
+/// with its config, so that codealong can be evaluated without cloning
+/// anything or configuring tokens. The repos are mirrors, analyzed in place,
+/// and the history is the same for a given `end`.
+pub fn generate_sample_workspace(dir: &Path, end: &DateTime<Utc>) -> Result<Workspace> {
+    fs::create_dir_all(dir)?;
+    let mut rng = XorShiftRng::from_seed([42; 16]);
+    let start = end.date().and_hms(0, 0, 0) - Duration::weeks(WEEKS);
+    for (name, files, lockfile) in REPOS {
+        let repo_dir = dir.join(name);
+        if repo_dir.exists() {
+            fs::remove_dir_all(&repo_dir)?;
+        }
+        SampleRepo::init(&repo_dir, files, lockfile, &start)?.simulate(&mut rng, &start, end)?;
+    }
+    fs::write(
+        dir.join(WorkspaceConfig::DEFAULT_PATH),
+        sample_workspace_config(),
+    )?;
+    Ok(Workspace::new(
+        dir.to_owned(),
+        WorkspaceConfig::from_dir(dir)?,
+    ))
+}
+
+fn sample_workspace_config() -> String {
+    let mut config = String::from("authors:\n");
+    for (name, email, login, team) in AUTHORS {
+        writeln!(
+            config,
+            "  \"{} <{}>\":\n    github_logins: [\"{}\"]\n    teams: [\"{}\"]",
+            name, email, login, team
+        )
+        .unwrap();
+    }
+    config.push_str(
+        "teams:\n  platform:\n    parent: engineering\n  product:\n    parent: engineering\n",
+    );
+    config.push_str("repos:\n");
+    for (name, _, _) in REPOS {
+        writeln!(
+            config,
+            "  - name: \"{}\"\n    clone_url: \"\"\n    refs: [\"refs/heads/main\"]\n    mirror: true",
+            name
+        )
+        .unwrap();
+    }
+    config
+}
+
+/// A repo being generated, with the lines of its files.
+struct SampleRepo<'a> {
+    repo: Repository,
+    files: BTreeMap<&'a str, Vec<String>>,
+    sources: &'a [&'a str],
+    lockfile: &'a str,
+    next_line: u64,
+    pull_requests: u64,
+    dependency_version: u64,
+}
+
+impl<'a> SampleRepo<'a> {
+    fn init(
+        dir: &Path,
+        sources: &'a [&'a str],
+        lockfile: &'a str,
+        at: &DateTime<Utc>,
+    ) -> Result<SampleRepo<'a>> {
+        let repo = Repository::init(dir)?;
+        repo.set_head("refs/heads/main")?;
+        let mut sample = SampleRepo {
+            repo,
+            files: BTreeMap::new(),
+            sources,
+            lockfile,
+            next_line: 0,
+            pull_requests: 0,
+            dependency_version: 1,
+        };
+        for source in sources {
+            let lines = (0..10).map(|_| sample.line(source)).collect();
+            sample.files.insert(source, lines);
+        }
+        sample.files.insert(lockfile, sample.lockfile_lines());
+        let (name, email, _, _) = AUTHORS[0];
+        sample.commit(&Signature::new(name, email, &time(at))?, "Initial commit")?;
+        Ok(sample)
+    }
+
+    /// Commits of every author on each working day between `start` and
+    /// `end`, some of them landed through pull requests, along with weekly
+    /// dependency updates by a bot.
+    fn simulate(
+        &mut self,
+        rng: &mut XorShiftRng,
+        start: &DateTime<Utc>,
+        end: &DateTime<Utc>,
+    ) -> Result<()> {
+        let mut day = *start + Duration::days(1);
+        // only whole days, so that no commit is in the future of `end`
+        while day + Duration::days(1) <= *end {
+            if day.weekday() == Weekday::Mon {
+                let at = day + Duration::hours(6);
+                let message = format!(
+                    "Bump dependencies from 1.{} to 1.{}",
+                    self.dependency_version,
+                    self.dependency_version + 1
+                );
+                self.dependency_version += 1;
+                self.files.insert(self.lockfile, self.lockfile_lines());
+                let bot = Signature::new(
+                    "dependabot[bot]",
+                    "49699333+dependabot[bot]@users.noreply.github.com",
+                    &time(&at),
+                )?;
+                self.commit(&bot, &message)?;
+            }
+            if day.weekday() != Weekday::Sat && day.weekday() != Weekday::Sun {
+                for (name, email, login, _) in AUTHORS {
+                    if !rng.gen_bool(0.4) {
+                        continue;
+                    }
+                    let at = day + Duration::hours(9 + rng.gen_range(0, 8));
+                    let message = self.change(rng);
+                    let author = Signature::new(name, email, &time(&at))?;
+                    if rng.gen_bool(0.3) {
+                        self.pull_request(&author, login, &message, &at)?;
+                    } else {
+                        self.commit(&author, &message)?;
+                    }
+                }
+            }
+            day = day + Duration::days(1);
+        }
+        Ok(())
+    }
+
+    /// Changes a source file at random, returning the message describing
+    /// the change.
+    fn change(&mut self, rng: &mut XorShiftRng) -> String {
+        let source = self.sources[rng.gen_range(0, self.sources.len())];
+        let topic = TOPICS[rng.gen_range(0, TOPICS.len())];
+        let len = self.files[source].len();
+        match rng.gen_range(0, 10) {
+            0..=5 => {
+                let count = rng.gen_range(3, 15);
+                let lines: Vec<String> = (0..count).map(|_| self.line(source)).collect();
+                self.files.get_mut(source).unwrap().extend(lines);
+                format!("feat: support {} in {}", topic, source)
+            }
+            6..=8 => {
+                for _ in 0..rng.gen_range(1, 5) {
+                    let index = rng.gen_range(0, len);
+                    let line = self.line(source);
+                    self.files.get_mut(source).unwrap()[index] = line;
+                }
+                format!("fix: correct {} in {}", topic, source)
+            }
+            _ => {
+                let lines = self.files.get_mut(source).unwrap();
+                let keep = len.saturating_sub(rng.gen_range(1, 4)).max(5);
+                lines.truncate(keep);
+                format!("refactor: simplify {} in {}", topic, source)
+            }
+        }
+    }
+
+    /// Commits the changes on a branch merged through a pull request.
+    fn pull_request(
+        &mut self,
+        author: &Signature,
+        login: &str,
+        message: &str,
+        at: &DateTime<Utc>,
+    ) -> Result<()> {
+        let head = self.repo.head()?.peel_to_commit()?;
+        let change = self.write_commit(author, message, &[&head])?;
+        let change = self.repo.find_commit(change)?;
+        self.pull_requests += 1;
+        let merged_at = *at + Duration::hours(3);
+        let merger = Signature::new("GitHub", "noreply@github.com", &time(&merged_at))?;
+        let merge_message = format!(
+            "Merge pull request #{} from {}/change-{}\n\n{}",
+            self.pull_requests, login, self.pull_requests, message
+        );
+        let tree = change.tree()?;
+        self.repo.commit(
+            Some("HEAD"),
+            &merger,
+            &merger,
+            &merge_message,
+            &tree,
+            &[&head, &change],
+        )?;
+        Ok(())
+    }
+
+    fn commit(&mut self, author: &Signature, message: &str) -> Result<Oid> {
+        let parents = match self.repo.head() {
+            Ok(head) => vec![head.peel_to_commit()?],
+            Err(_) => vec![],
+        };
+        let parents: Vec<&git2::Commit> = parents.iter().collect();
+        let oid = self.write_commit(author, message, &parents)?;
+        self.repo
+            .reference("refs/heads/main", oid, true, "sample history")?;
+        Ok(oid)
+    }
+
+    /// Writes the files and commits them without moving any branch.
+    fn write_commit(
+        &self,
+        author: &Signature,
+        message: &str,
+        parents: &[&git2::Commit],
+    ) -> Result<Oid> {
+        let workdir = self.repo.workdir().unwrap();
+        for (path, lines) in &self.files {
+            let path = workdir.join(path);
+            fs::create_dir_all(path.parent().unwrap())?;
+            fs::write(path, lines.join("\n") + "\n")?;
+        }
+        let mut index = self.repo.index()?;
+        index.add_all(["*"].iter(), IndexAddOption::DEFAULT, None)?;
+        index.write()?;
+        let tree = self.repo.find_tree(index.write_tree()?)?;
+        Ok(self
+            .repo
+            .commit(None, author, author, message, &tree, parents)?)
+    }
+
+    /// A new line of code in the language of `source`.
+    fn line(&mut self, source: &str) -> String {
+        self.next_line += 1;
+        let n = self.next_line;
+        if source.ends_with(".rs") {
+            format!("pub fn step_{}(x: u64) -> u64 {{ x + {} }}", n, n)
+        } else if source.ends_with(".js") {
+            format!("export function step{}(x) {{ return x + {}; }}", n, n)
+        } else {
+            format!("def step_{}(x): return x + {}", n, n)
+        }
+    }
+
+    fn lockfile_lines(&self) -> Vec<String> {
+        vec![
+            "# generated by the package manager".to_owned(),
+            format!("version = \"1.{}.0\"", self.dependency_version),
+        ]
+    }
+}
+
+fn time(at: &DateTime<Utc>) -> Time {
+    Time::new(at.timestamp(), 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_generate_sample_workspace() -> Result<()> {
+        let tmp_dir = tempdir::TempDir::new("sample_workspace")?;
+        let end = Utc.ymd(2020, 3, 2).and_hms(0, 0, 0);
+        let workspace = generate_sample_workspace(tmp_dir.path(), &end)?;
+        let repos = workspace.repos();
+        assert_eq!(repos.len(), 3);
+        assert_eq!(
+            workspace.config().teams["platform"].parent.as_deref(),
+            Some("engineering")
+        );
+        let person = workspace.config().person_for_identity_at(
+            &crate::identity::Identity {
+                name: Some("Ada Lovelace".to_owned()),
+                email: Some("ada@example.com".to_owned()),
+            },
+            &end,
+        );
+        assert_eq!(person.teams, vec!["platform", "engineering"]);
+
+        let repository = repos[0].repository()?;
+        let mut revwalk = repository.revwalk()?;
+        revwalk.push_head()?;
+        let commits: Vec<git2::Commit> = revwalk
+            .map(|oid| repository.find_commit(oid.unwrap()).unwrap())
+            .collect();
+        assert!(commits.len() > 50, "{} commits", commits.len());
+        assert!(commits.iter().any(|c| c.parent_count() == 2));
+        assert!(commits
+            .iter()
+            .any(|c| c.author().name() == Some("dependabot[bot]")));
+        assert!(commits
+            .iter()
+            .all(|c| c.time().seconds() <= end.timestamp()));
+
+        // the history only depends on the end
+        let again = tempdir::TempDir::new("sample_workspace")?;
+        let workspace = generate_sample_workspace(again.path(), &end)?;
+        let head = |workspace: &Workspace| -> Result<Oid> {
+            Ok(workspace.repos()[0]
+                .repository()?
+                .head()?
+                .peel_to_commit()?
+                .id())
+        };
+        assert_eq!(head(&workspace)?, repository.head()?.peel_to_commit()?.id());
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+
+use git2::{ErrorCode, Oid, Repository};
+
+use crate::error::*;
+
+const PGP_SIGNATURE_HEADER: &str = "-----BEGIN PGP SIGNATURE-----";
+
+/// Signature information for a commit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct CommitSignature {
+    pub signed: bool,
+    /// Whether the signature was verified against the configured keyring.
+    /// Absent when no keyring is configured or the signature is not a PGP
+    /// signature.
+    pub signature_valid: Option<bool>,
+    /// User id of the key which made a valid signature.
+    pub signer: Option<String>,
+}
+
+/// Extracts and, when a keyring is provided, verifies the signature of a
+/// commit. Verification defers to a `gpgv` binary on the current path.
+pub fn verify_commit(
+    repo: &Repository,
+    commit_id: &Oid,
+    keyring: Option<&Path>,
+) -> Result<CommitSignature> {
+    let (signature, content) = match repo.extract_signature(commit_id, None) {
+        Ok(res) => res,
+        Err(ref e) if e.code() == ErrorCode::NotFound => return Ok(CommitSignature::default()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut res = CommitSignature {
+        signed: true,
+        ..CommitSignature::default()
+    };
+    let keyring = match keyring {
+        Some(keyring) if signature.starts_with(PGP_SIGNATURE_HEADER.as_bytes()) => keyring,
+        _ => return Ok(res),
+    };
+
+    let dir = tempdir::TempDir::new("codealong-signature")?;
+    let signature_path = dir.path().join("commit.sig");
+    let content_path = dir.path().join("commit");
+    File::create(&signature_path)?.write_all(&signature)?;
+    File::create(&content_path)?.write_all(&content)?;
+    let output = Command::new("gpgv")
+        .arg("--status-fd")
+        .arg("1")
+        .arg("--keyring")
+        .arg(keyring)
+        .arg(&signature_path)
+        .arg(&content_path)
+        .output()?;
+    let (valid, signer) = parse_gpg_status(&String::from_utf8_lossy(&output.stdout));
+    res.signature_valid = Some(valid && output.status.success());
+    res.signer = signer;
+    Ok(res)
+}
+
+/// Parses the machine readable `--status-fd` output of gpg, returning whether
+/// there was a good signature and the user id it was made by.
+fn parse_gpg_status(status: &str) -> (bool, Option<String>) {
+    let mut valid = false;
+    let mut signer = None;
+    for line in status.lines() {
+        let mut parts = line.splitn(4, ' ');
+        if parts.next() != Some("[GNUPG:]") {
+            continue;
+        }
+        match parts.next() {
+            Some("GOODSIG") => {
+                valid = true;
+                signer = parts.nth(1).map(|s| s.to_owned());
+            }
+            Some("BADSIG") | Some("ERRSIG") | Some("EXPKEYSIG") | Some("REVKEYSIG") => {
+                return (false, None);
+            }
+            _ => {}
+        }
+    }
+    (valid, signer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unsigned_commit() -> Result<()> {
+        let repo = Repository::open("./fixtures/repos/simple")?;
+        let id = Oid::from_str("86d242301830075e93ff039a4d1e88673a4a3020")?;
+        assert_eq!(
+            verify_commit(&repo, &id, Some(Path::new("keyring.gpg")))?,
+            CommitSignature::default()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_gpg_status() {
+        let status = "[GNUPG:] NEWSIG\n[GNUPG:] GOODSIG 4AEE18F83AFDEB23 Jane Doe <jane@example.com>\n[GNUPG:] VALIDSIG 5DE3E0509C47EA3CF04A42D34AEE18F83AFDEB23 2019-12-16\n";
+        assert_eq!(
+            parse_gpg_status(status),
+            (true, Some("Jane Doe <jane@example.com>".to_owned()))
+        );
+        let status = "[GNUPG:] NEWSIG\n[GNUPG:] ERRSIG 4AEE18F83AFDEB23 1 8 00 1576454400 9\n[GNUPG:] NO_PUBKEY 4AEE18F83AFDEB23\n";
+        assert_eq!(parse_gpg_status(status), (false, None));
+    }
+}
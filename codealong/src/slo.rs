@@ -0,0 +1,305 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+use chrono::prelude::*;
+use chrono::DateTime;
+
+use linked_hash_map::LinkedHashMap;
+
+use codealong_model::{
+    Event, MetricDef, Person, ReviewStats, BUSINESS_TIME_TO_FIRST_REVIEW, BUSINESS_TIME_TO_RESOLVE,
+    LINES_CHANGED, TIME_TO_FIRST_REVIEW, TIME_TO_RESOLVE,
+};
+
+use crate::analyzed_diff::AnalyzedDiff;
+
+/// Metrics which service level objectives can be defined against.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SloMetric {
+    /// Lines added plus lines deleted.
+    LinesChanged,
+    /// Wall-clock hours from opening to merging a pull request.
+    HoursToResolve,
+    /// Working hours from opening to merging a pull request, according to
+    /// the configured business calendar.
+    BusinessHoursToResolve,
+    /// Wall-clock hours from opening a pull request to its first review by
+    /// someone other than its author.
+    HoursToFirstReview,
+    /// Working hours from opening a pull request to its first review,
+    /// according to the configured business calendar.
+    BusinessHoursToFirstReview,
+}
+
+impl SloMetric {
+    /// Definition of the metric the SLO is checked against.
+    pub fn def(&self) -> MetricDef {
+        match self {
+            SloMetric::LinesChanged => LINES_CHANGED,
+            SloMetric::HoursToResolve => TIME_TO_RESOLVE,
+            SloMetric::BusinessHoursToResolve => BUSINESS_TIME_TO_RESOLVE,
+            SloMetric::HoursToFirstReview => TIME_TO_FIRST_REVIEW,
+            SloMetric::BusinessHoursToFirstReview => BUSINESS_TIME_TO_FIRST_REVIEW,
+        }
+    }
+}
+
+/// A service level objective, e.g. "pull requests are merged within 16
+/// business hours".
+///
+/// Example configuration:
+///
+/// ```yaml
+/// slos:
+///   small_pull_requests:
+///     metric: lines_changed
+///     max: 400
+///   fast_merges:
+///     metric: business_hours_to_resolve
+///     max: 16
+///   first_review:
+///     metric: business_hours_to_first_review
+///     max: 8
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Slo {
+    pub metric: SloMetric,
+    pub max: f64,
+}
+
+impl Slo {
+    pub fn is_breached(&self, value: f64) -> bool {
+        value > self.max
+    }
+}
+
+/// Emitted whenever a subject, e.g. a pull request, violates a configured
+/// SLO so that alerting can notify the owner.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SloBreach {
+    pub id: String,
+    pub slo: String,
+    pub metric: SloMetric,
+    #[serde(default)]
+    pub metric_version: u32,
+    pub threshold: f64,
+    pub value: f64,
+    /// Identifier of the event which breached the SLO.
+    pub subject: String,
+    pub url: Option<String>,
+    pub owner: Person,
+    pub repo: Option<String>,
+    pub breached_at: DateTime<Utc>,
+}
+
+impl SloBreach {
+    /// Checks `value` against `slo`, returning a breach if it is violated.
+    pub fn check(
+        name: &str,
+        slo: &Slo,
+        value: f64,
+        subject: &str,
+        owner: &Person,
+        breached_at: &DateTime<Utc>,
+    ) -> Option<SloBreach> {
+        if !slo.is_breached(value) {
+            return None;
+        }
+        Some(SloBreach {
+            id: format!("{}-{}", subject, name),
+            slo: name.to_owned(),
+            metric: slo.metric,
+            metric_version: slo.metric.def().version,
+            threshold: slo.max,
+            value,
+            subject: subject.to_owned(),
+            url: None,
+            owner: owner.clone(),
+            repo: None,
+            breached_at: *breached_at,
+        })
+    }
+}
+
+/// A pull request, of whichever forge, checked against the configured SLOs.
+pub trait SloSubject: Event {
+    fn diff(&self) -> Option<&AnalyzedDiff>;
+
+    fn time_to_resolve(&self) -> Option<i64>;
+
+    fn business_time_to_resolve(&self) -> Option<i64>;
+
+    fn review_stats(&self) -> &ReviewStats;
+
+    /// Whom breaches are attributed to.
+    fn owner(&self) -> &Person;
+
+    fn url(&self) -> Option<String>;
+
+    fn repo(&self) -> Option<String>;
+
+    /// The value of `metric` for this subject, if applicable. Pull requests
+    /// not reviewed yet have no time to first review.
+    fn metric(&self, metric: SloMetric) -> Option<f64> {
+        let hours = |seconds: i64| seconds as f64 / 3600.0;
+        match metric {
+            SloMetric::LinesChanged => self
+                .diff()
+                .map(|diff| (diff.line_stats.added + diff.line_stats.deleted) as f64),
+            SloMetric::HoursToResolve => self.time_to_resolve().map(hours),
+            SloMetric::BusinessHoursToResolve => self.business_time_to_resolve().map(hours),
+            SloMetric::HoursToFirstReview => self.review_stats().time_to_first_review.map(hours),
+            SloMetric::BusinessHoursToFirstReview => {
+                self.review_stats().business_time_to_first_review.map(hours)
+            }
+        }
+    }
+
+    /// Checks this subject against each of `slos`.
+    fn slo_breaches(&self, slos: &LinkedHashMap<String, Slo>) -> Vec<SloBreach> {
+        slos.iter()
+            .filter_map(|(name, slo)| {
+                let value = self.metric(slo.metric)?;
+                let mut breach =
+                    SloBreach::check(name, slo, value, &self.id(), self.owner(), self.timestamp())?;
+                breach.url = self.url();
+                breach.repo = self.repo();
+                Some(breach)
+            })
+            .collect()
+    }
+}
+
+impl Event for SloBreach {
+    fn timestamp(&self) -> &DateTime<Utc> {
+        &self.breached_at
+    }
+
+    fn event_type(&self) -> &str {
+        "slo_breach"
+    }
+
+    fn id(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.id)
+    }
+
+    fn tags(&self) -> HashSet<String> {
+        HashSet::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::Identity;
+
+    #[test]
+    fn test_check() {
+        let slo = Slo {
+            metric: SloMetric::LinesChanged,
+            max: 400.0,
+        };
+        let owner = Identity::parse("Alice <alice@example.com>").to_person();
+        let now = Utc.ymd(2019, 12, 18).and_hms(9, 0, 0);
+        assert!(SloBreach::check("small", &slo, 400.0, "1", &owner, &now).is_none());
+        let breach = SloBreach::check("small", &slo, 512.0, "1", &owner, &now).unwrap();
+        assert_eq!(breach.id, "1-small");
+        assert_eq!(breach.threshold, 400.0);
+        assert_eq!(breach.value, 512.0);
+    }
+
+    struct PullRequest {
+        timestamp: DateTime<Utc>,
+        owner: Person,
+        review_stats: ReviewStats,
+    }
+
+    impl Event for PullRequest {
+        fn timestamp(&self) -> &DateTime<Utc> {
+            &self.timestamp
+        }
+
+        fn event_type(&self) -> &str {
+            "pull_request"
+        }
+
+        fn id(&self) -> Cow<'_, str> {
+            Cow::Borrowed("7")
+        }
+
+        fn tags(&self) -> HashSet<String> {
+            HashSet::new()
+        }
+    }
+
+    impl SloSubject for PullRequest {
+        fn diff(&self) -> Option<&AnalyzedDiff> {
+            None
+        }
+
+        fn time_to_resolve(&self) -> Option<i64> {
+            Some(3600)
+        }
+
+        fn business_time_to_resolve(&self) -> Option<i64> {
+            None
+        }
+
+        fn review_stats(&self) -> &ReviewStats {
+            &self.review_stats
+        }
+
+        fn owner(&self) -> &Person {
+            &self.owner
+        }
+
+        fn url(&self) -> Option<String> {
+            None
+        }
+
+        fn repo(&self) -> Option<String> {
+            Some("acme/uploader".to_owned())
+        }
+    }
+
+    #[test]
+    fn test_slo_breaches() {
+        let mut pr = PullRequest {
+            timestamp: Utc.ymd(2019, 12, 18).and_hms(9, 0, 0),
+            owner: Identity::parse("Alice <alice@example.com>").to_person(),
+            review_stats: ReviewStats::default(),
+        };
+        let mut slos = LinkedHashMap::new();
+        slos.insert(
+            "first_review".to_owned(),
+            Slo {
+                metric: SloMetric::HoursToFirstReview,
+                max: 8.0,
+            },
+        );
+        slos.insert(
+            "small".to_owned(),
+            Slo {
+                metric: SloMetric::LinesChanged,
+                max: 400.0,
+            },
+        );
+        slos.insert(
+            "fast_merges".to_owned(),
+            Slo {
+                metric: SloMetric::HoursToResolve,
+                max: 16.0,
+            },
+        );
+        // neither reviewed nor diffed
+        assert!(pr.slo_breaches(&slos).is_empty());
+
+        pr.review_stats.time_to_first_review = Some(9 * 3600);
+        let breaches = pr.slo_breaches(&slos);
+        assert_eq!(breaches.len(), 1);
+        assert_eq!(breaches[0].id, "7-first_review");
+        assert_eq!(breaches[0].value, 9.0);
+        assert_eq!(breaches[0].repo, Some("acme/uploader".to_owned()));
+    }
+}
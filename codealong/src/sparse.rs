@@ -0,0 +1,81 @@
+use std::path::Path;
+
+use crate::error::*;
+use crate::partial_clone::{fetch_objects, git, make_compatible, missing_objects};
+
+// libgit2 does not support partial clones, so path-scoped repos are cloned
+// and fetched with a git binary on the current path
+
+/// Clones `url` as a blobless partial clone with a sparse checkout of
+/// `paths`, so that clone size scales with the scoped paths rather than the
+/// whole repo.
+pub fn clone_sparse(url: &str, into: &Path, paths: &[String]) -> Result<()> {
+    let parent = into.parent().unwrap_or_else(|| Path::new("."));
+    std::fs::create_dir_all(parent)?;
+    git(
+        parent,
+        &[
+            "clone",
+            "--quiet",
+            "--filter=blob:none",
+            "--no-checkout",
+            "--sparse",
+            url,
+            &into.to_string_lossy(),
+        ],
+        None,
+    )?;
+    let mut args = vec!["sparse-checkout", "set", "--"];
+    args.extend(paths.iter().map(|p| &**p));
+    git(into, &args, None)?;
+    git(into, &["checkout", "--quiet"], None)?;
+    make_compatible(into)?;
+    Ok(())
+}
+
+/// Fetches new commits and trees from origin without blobs.
+pub fn fetch_sparse(dir: &Path) -> Result<()> {
+    git(
+        dir,
+        &["fetch", "--quiet", "--filter=blob:none", "origin"],
+        None,
+    )?;
+    Ok(())
+}
+
+/// Fetches, in a single batch, the blobs under `paths` which are reachable
+/// from `revs` but missing from the partial clone. Returns the number of
+/// blobs fetched.
+pub fn prefetch_blobs(dir: &Path, revs: &[String], paths: &[String]) -> Result<usize> {
+    let missing = missing_objects(dir, revs, paths)?;
+    let missing: Vec<&str> = missing.iter().map(|s| &**s).collect();
+    fetch_objects(dir, "origin", &missing)?;
+    Ok(missing.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::partial_clone::tests::build_origin;
+
+    #[test]
+    fn test_clone_sparse() -> Result<()> {
+        let tmp_dir = tempdir::TempDir::new("sparse")?;
+        let origin = tmp_dir.path().join("origin");
+        build_origin(&origin)?;
+        let into = tmp_dir.path().join("clone");
+        let paths = vec!["a".to_owned()];
+        clone_sparse(
+            &format!("file://{}", origin.to_string_lossy()),
+            &into,
+            &paths,
+        )?;
+        assert!(into.join("a/x").exists());
+        assert!(!into.join("b/y").exists());
+        let revs = vec!["HEAD".to_owned()];
+        assert_eq!(prefetch_blobs(&into, &revs, &paths)?, 1);
+        assert_eq!(prefetch_blobs(&into, &revs, &paths)?, 0);
+        fetch_sparse(&into)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use chrono::prelude::*;
+use chrono::DateTime;
+use linked_hash_map::LinkedHashMap;
+
+use crate::error::*;
+
+const TEAM_COLUMN: &str = "team";
+const MONTH_COLUMN: &str = "month";
+
+/// Qualitative data, e.g. team health survey scores, keyed by team and month
+/// so that it can be joined onto rollups.
+///
+/// Expects a CSV file with `team` and `month` (`YYYY-MM`) columns. Every other
+/// column is treated as a numeric score:
+///
+/// ```text
+/// team,month,happiness,psychological_safety
+/// payments,2019-11,3.8,4.1
+/// payments,2019-12,3.2,4.0
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SurveyScores {
+    scores: HashMap<(String, String), LinkedHashMap<String, f64>>,
+}
+
+impl SurveyScores {
+    pub fn from_path(path: &Path) -> Result<SurveyScores> {
+        SurveyScores::from_reader(File::open(path)?)
+    }
+
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<SurveyScores> {
+        let mut contents = String::new();
+        reader.read_to_string(&mut contents)?;
+        let mut lines = contents.lines().filter(|l| !l.trim().is_empty());
+        let headers = lines.next().map(parse_csv_line).unwrap_or_default();
+        let column = |name: &str| {
+            headers.iter().position(|h| h == name).ok_or_else(|| {
+                Error::from(ErrorKind::InvalidSurvey(format!("missing {} column", name)))
+            })
+        };
+        let team_column = column(TEAM_COLUMN)?;
+        let month_column = column(MONTH_COLUMN)?;
+
+        let mut scores = HashMap::new();
+        for line in lines {
+            let record = parse_csv_line(line);
+            let (team, month) = match (record.get(team_column), record.get(month_column)) {
+                (Some(team), Some(month)) => (team.to_owned(), month.to_owned()),
+                _ => continue,
+            };
+            let mut row = LinkedHashMap::new();
+            for (i, value) in record.iter().enumerate() {
+                if i == team_column || i == month_column {
+                    continue;
+                }
+                if let (Some(header), Ok(value)) = (headers.get(i), value.parse::<f64>()) {
+                    row.insert(header.to_owned(), value);
+                }
+            }
+            scores.insert((team, month), row);
+        }
+        Ok(SurveyScores { scores })
+    }
+
+    /// The scores of `team` for the month containing `timestamp`.
+    pub fn get(
+        &self,
+        team: &str,
+        timestamp: &DateTime<Utc>,
+    ) -> Option<&LinkedHashMap<String, f64>> {
+        self.scores
+            .get(&(team.to_owned(), timestamp.format("%Y-%m").to_string()))
+    }
+}
+
+/// Splits a line of CSV into trimmed fields, honoring double-quoted fields
+/// which may contain commas and escaped (doubled) quotes.
+fn parse_csv_line(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                fields.push(field.trim().to_owned());
+                field.clear();
+            }
+            _ => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_owned());
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_reader() -> Result<()> {
+        let csv = "team,month,happiness,notes\npayments,2019-12,3.5,\"busy, but ok\"\n";
+        let scores = SurveyScores::from_reader(csv.as_bytes())?;
+        let row = scores
+            .get("payments", &Utc.ymd(2019, 12, 16).and_hms(0, 0, 0))
+            .unwrap();
+        assert_eq!(row.get("happiness"), Some(&3.5));
+        assert_eq!(row.get("notes"), None);
+        assert!(scores
+            .get("payments", &Utc.ymd(2019, 11, 16).and_hms(0, 0, 0))
+            .is_none());
+        assert!(SurveyScores::from_reader("month,score\n".as_bytes()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_csv_line() {
+        assert_eq!(
+            parse_csv_line(r#"a, "b, c" ,"say ""hi""","#),
+            vec!["a", "b, c", r#"say "hi""#, ""]
+        );
+    }
+}
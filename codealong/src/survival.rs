@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use git2::{ObjectType, Oid, Repository, TreeWalkMode, TreeWalkResult};
+
+use crate::analyzed_commit::AnalyzedCommit;
+use crate::config::Config;
+use crate::error::*;
+use crate::git_blame::GitBlame;
+
+/// The lines of each commit which still exist at a head, found by blaming
+/// every analyzed file of the head, so that durable work can be told apart
+/// from work which was thrown away.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Survival {
+    lines: HashMap<Oid, u64>,
+}
+
+impl Survival {
+    /// Blames the files of `head` other than those which are excluded,
+    /// ignored, binary or too large to be analyzed.
+    pub fn at(repo: &Repository, head: Oid, config: &Config) -> Result<Survival> {
+        let tree = repo.find_commit(head)?.tree()?;
+        let path_filter = config.path_filter();
+        let odb = repo.odb()?;
+        let mut paths = vec![];
+        tree.walk(TreeWalkMode::PreOrder, |root, entry| {
+            if entry.kind() != Some(ObjectType::Blob) {
+                return TreeWalkResult::Ok;
+            }
+            let path = match entry.name() {
+                Some(name) => format!("{}{}", root, name),
+                None => return TreeWalkResult::Ok,
+            };
+            let ignored = config
+                .config_for_file(&path)
+                .is_some_and(|file_config| file_config.ignore());
+            let analyzed = path_filter.includes(&path)
+                && !ignored
+                && odb
+                    .read_header(entry.id())
+                    .is_ok_and(|(size, _)| size as u64 <= config.large_files.max_size)
+                && repo
+                    .find_blob(entry.id())
+                    .is_ok_and(|blob| !blob.is_binary());
+            if analyzed {
+                paths.push(path);
+            }
+            TreeWalkResult::Ok
+        })?;
+        let mut survival = Survival::default();
+        for path in paths {
+            for (oid, count) in GitBlame::line_counts(repo, &head, Path::new(&path), config)? {
+                *survival.lines.entry(oid).or_insert(0) += count;
+            }
+        }
+        Ok(survival)
+    }
+
+    /// Number of the lines last changed by the commit `oid` which still
+    /// exist.
+    pub fn surviving(&self, oid: &Oid) -> u64 {
+        self.lines.get(oid).cloned().unwrap_or(0)
+    }
+
+    /// Records on `commit` the share of the lines it added which still
+    /// exist, if it added any.
+    pub fn apply(&self, commit: &mut AnalyzedCommit) {
+        let added = commit.diff.line_stats.added;
+        if added == 0 {
+            return;
+        }
+        if let Ok(oid) = Oid::from_str(&commit.id) {
+            // blame may credit a commit with lines its diff, which ignores
+            // whitespace, does not count as added
+            commit.survival_rate = Some((self.surviving(&oid) as f64 / added as f64).min(1.0));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commit_analyzer::CommitAnalyzer;
+    use crate::repo_config::RepoConfig;
+    use crate::test::build_test_logger;
+    use git2::{Commit, Signature};
+
+    fn commit(repo: &Repository, dir: &Path, path: &str, content: &str) -> Result<Oid> {
+        std::fs::write(dir.join(path), content)?;
+        let mut index = repo.index()?;
+        index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
+        index.write()?;
+        let tree = repo.find_tree(index.write_tree()?)?;
+        let sig = Signature::now("Test", "test@example.com")?;
+        let parents = match repo.head() {
+            Ok(head) => vec![head.peel_to_commit()?],
+            Err(_) => vec![],
+        };
+        let parents: Vec<&Commit> = parents.iter().collect();
+        Ok(repo.commit(Some("HEAD"), &sig, &sig, "Change", &tree, &parents)?)
+    }
+
+    #[test]
+    fn test_survival() -> Result<()> {
+        let tmp_dir = tempdir::TempDir::new("survival")?;
+        let dir = tmp_dir.path();
+        let repo = Repository::init(dir)?;
+        let first = commit(&repo, dir, "lib.rs", "a\nb\nc\nd\n")?;
+        let second = commit(&repo, dir, "lib.rs", "a\nB\nC\nd\ne\n")?;
+        let head = commit(&repo, dir, "lib.rs", "a\nB\nd\ne\nf\n")?;
+
+        let config = RepoConfig::default();
+        let survival = Survival::at(&repo, head, &config.config)?;
+        assert_eq!(survival.surviving(&first), 2);
+        assert_eq!(survival.surviving(&second), 2);
+        assert_eq!(survival.surviving(&head), 1);
+
+        let mut analyzed = CommitAnalyzer::new(
+            &repo,
+            repo.find_commit(second)?,
+            &config,
+            &build_test_logger(),
+        )
+        .analyze()?;
+        survival.apply(&mut analyzed);
+        assert_eq!(analyzed.survival_rate, Some(2.0 / 3.0));
+        Ok(())
+    }
+}
@@ -0,0 +1,147 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashSet};
+
+use chrono::prelude::*;
+use chrono::DateTime;
+use linked_hash_map::LinkedHashMap;
+
+use crate::person_snapshot::PersonSnapshot;
+use crate::survey::SurveyScores;
+use codealong_metrics::WorkStats;
+use codealong_model::{metric_versions, Event, MetricVersions, TIME_TO_RESOLVE, WORK_STATS};
+
+/// Activity of all members of a team over a single period.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TeamSnapshot {
+    pub id: String,
+    pub team: String,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub members: Vec<String>,
+    pub commits: u64,
+    pub stats: WorkStats,
+    pub pull_requests_merged: u64,
+    /// Mean seconds from opening to merging for pull requests merged during
+    /// the period.
+    pub time_to_resolve: Option<f64>,
+    /// Qualitative scores joined from survey data, if any.
+    pub survey: Option<LinkedHashMap<String, f64>>,
+    #[serde(default)]
+    pub metric_versions: MetricVersions,
+}
+
+impl TeamSnapshot {
+    fn new(team: &str, period_start: DateTime<Utc>, period_end: DateTime<Utc>) -> TeamSnapshot {
+        TeamSnapshot {
+            id: format!("{}-{}", team, period_start.format("%Y-%m-%d")),
+            team: team.to_owned(),
+            period_start,
+            period_end,
+            members: vec![],
+            commits: 0,
+            stats: WorkStats::empty(),
+            pull_requests_merged: 0,
+            time_to_resolve: None,
+            survey: None,
+            metric_versions: metric_versions(&[WORK_STATS, TIME_TO_RESOLVE]),
+        }
+    }
+
+    fn add(&mut self, snapshot: &PersonSnapshot) {
+        let merged = self.pull_requests_merged + snapshot.pull_requests_merged;
+        if merged > 0 {
+            let total = self.time_to_resolve.unwrap_or(0.0) * self.pull_requests_merged as f64
+                + snapshot.time_to_resolve.unwrap_or(0.0) * snapshot.pull_requests_merged as f64;
+            self.time_to_resolve = Some(total / merged as f64);
+        }
+        self.pull_requests_merged = merged;
+        self.commits += snapshot.commits;
+        self.stats += snapshot.stats;
+        if !self.members.contains(&snapshot.person.id) {
+            self.members.push(snapshot.person.id.clone());
+        }
+    }
+
+    /// Attaches the survey scores of this team for the month the period
+    /// starts in.
+    pub fn join_survey(&mut self, survey: &SurveyScores) {
+        self.survey = survey.get(&self.team, &self.period_start).cloned();
+    }
+}
+
+impl Event for TeamSnapshot {
+    fn timestamp(&self) -> &DateTime<Utc> {
+        &self.period_start
+    }
+
+    fn event_type(&self) -> &str {
+        "team_snapshot"
+    }
+
+    fn id(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.id)
+    }
+
+    fn tags(&self) -> HashSet<String> {
+        HashSet::new()
+    }
+}
+
+/// Rolls org-wide person snapshots up into snapshots for each of their teams.
+/// Each snapshot counts towards the teams the person was on during its
+/// period, see `rollup_org`.
+pub fn rollup_teams(snapshots: &[PersonSnapshot]) -> Vec<TeamSnapshot> {
+    let mut res: BTreeMap<(String, DateTime<Utc>), TeamSnapshot> = BTreeMap::new();
+    for snapshot in snapshots {
+        for team in &snapshot.person.teams {
+            res.entry((team.clone(), snapshot.period_start))
+                .or_insert_with(|| {
+                    TeamSnapshot::new(team, snapshot.period_start, snapshot.period_end)
+                })
+                .add(snapshot);
+        }
+    }
+    res.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::Identity;
+    use crate::org_rollup::rollup_org;
+    use crate::period::Period;
+    use crate::person_snapshot::PersonSnapshots;
+
+    #[test]
+    fn test_rollup_teams() -> crate::error::Result<()> {
+        let mut alice = Identity::parse("Alice <alice@example.com>").to_person();
+        alice.teams = vec!["payments".to_owned()];
+        let mut bob = Identity::parse("Bob <bob@example.com>").to_person();
+        bob.teams = vec!["payments".to_owned(), "platform".to_owned()];
+        let monday = Utc.ymd(2019, 12, 16).and_hms(9, 0, 0);
+
+        let mut snapshots = PersonSnapshots::new(Period::Week);
+        snapshots.add_pull_request(&alice, &monday, "org/api", "a", 1, Some(3600));
+        snapshots.add_pull_request(&bob, &monday, "org/api", "b", 2, Some(7200));
+        let mut teams = rollup_teams(&rollup_org(&snapshots.finish()));
+
+        assert_eq!(teams.len(), 2);
+        assert_eq!(teams[0].id, "payments-2019-12-16");
+        assert_eq!(teams[0].members.len(), 2);
+        assert_eq!(teams[0].time_to_resolve, Some(5400.0));
+        assert_eq!(teams[1].team, "platform");
+        assert_eq!(teams[1].pull_requests_merged, 1);
+
+        let survey =
+            SurveyScores::from_reader("team,month,happiness\npayments,2019-12,4\n".as_bytes())?;
+        for team in &mut teams {
+            team.join_survey(&survey);
+        }
+        assert_eq!(
+            teams[0].survey.as_ref().unwrap().get("happiness"),
+            Some(&4.0)
+        );
+        assert_eq!(teams[1].survey, None);
+        Ok(())
+    }
+}
@@ -0,0 +1,242 @@
+use std::borrow::Cow;
+
+use encoding_rs::Encoding;
+use git2::Commit;
+use unicode_normalization::char::is_combining_mark;
+
+/// Appended to text which was truncated.
+const ELLIPSIS: char = '…';
+
+const ZERO_WIDTH_JOINER: char = '\u{200d}';
+
+/// Decodes `bytes` from the encoding labelled `encoding`, e.g. `ISO-8859-1`
+/// or `Shift_JIS`, falling back to UTF-8. Invalid sequences are replaced
+/// rather than failing, so that one badly encoded commit does not lose its
+/// whole message.
+pub fn decode(bytes: &[u8], encoding: Option<&str>) -> String {
+    let encoding = encoding
+        .and_then(|label| Encoding::for_label(label.trim().as_bytes()))
+        .unwrap_or(encoding_rs::UTF_8);
+    encoding.decode_without_bom_handling(bytes).0.into_owned()
+}
+
+/// The message of `commit`, honoring the `encoding` header written when
+/// `i18n.commitEncoding` is set.
+pub fn commit_message(commit: &Commit) -> String {
+    decode(commit.message_bytes(), commit.message_encoding())
+}
+
+/// The first paragraph of the message of `commit`, with its lines joined,
+/// like `git log --format=%s`.
+pub fn commit_summary(commit: &Commit) -> Option<String> {
+    let message = commit_message(commit);
+    let summary = message
+        .trim_start()
+        .lines()
+        .map(|line| line.trim())
+        .take_while(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    if summary.is_empty() {
+        None
+    } else {
+        Some(summary)
+    }
+}
+
+/// Truncates `text` to at most `max` user-perceived characters, including
+/// the ellipsis marking the truncation, without splitting a character from
+/// its combining marks, an emoji from its modifiers, or a joined emoji
+/// sequence.
+pub fn truncate_graphemes(text: &str, max: usize) -> Cow<'_, str> {
+    let boundaries = grapheme_boundaries(text);
+    if boundaries.len() <= max {
+        return Cow::Borrowed(text);
+    }
+    if max == 0 {
+        return Cow::Borrowed("");
+    }
+    let mut truncated = text[..boundaries[max - 1]].trim_end().to_owned();
+    truncated.push(ELLIPSIS);
+    Cow::Owned(truncated)
+}
+
+/// Byte offsets at which each grapheme cluster of `text` starts. Clusters
+/// are approximated from the combining marks, joiners and modifiers that
+/// extend the preceding character, which covers the scripts and emoji seen
+/// in names and messages without the full Unicode segmentation tables.
+fn grapheme_boundaries(text: &str) -> Vec<usize> {
+    let mut boundaries = vec![];
+    let mut previous: Option<char> = None;
+    let mut regional_indicators = 0;
+    for (index, c) in text.char_indices() {
+        let extends = match previous {
+            None => false,
+            Some(previous) => {
+                is_extending(c)
+                    || previous == ZERO_WIDTH_JOINER
+                    || (previous == '\r' && c == '\n')
+                    || (is_regional_indicator(c) && regional_indicators % 2 == 1)
+            }
+        };
+        regional_indicators = if is_regional_indicator(c) {
+            regional_indicators + 1
+        } else {
+            0
+        };
+        if !extends {
+            boundaries.push(index);
+        }
+        previous = Some(c);
+    }
+    boundaries
+}
+
+fn is_extending(c: char) -> bool {
+    is_combining_mark(c)
+        || c == ZERO_WIDTH_JOINER
+        // variation selectors
+        || ('\u{fe00}'..='\u{fe0f}').contains(&c)
+        || ('\u{e0100}'..='\u{e01ef}').contains(&c)
+        // emoji skin tone modifiers
+        || ('\u{1f3fb}'..='\u{1f3ff}').contains(&c)
+        // emoji tag sequences, e.g. subdivision flags
+        || ('\u{e0020}'..='\u{e007f}').contains(&c)
+        // conjoining Hangul vowels and final consonants
+        || ('\u{1160}'..='\u{11ff}').contains(&c)
+        || ('\u{d7b0}'..='\u{d7ff}').contains(&c)
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    ('\u{1f1e6}'..='\u{1f1ff}').contains(&c)
+}
+
+/// Decodes the RFC 2047 encoded words of a mail header, e.g.
+/// `=?UTF-8?B?5bGx55Sw?=`, which is how non-ASCII names and subjects are
+/// sent in patch emails.
+pub fn decode_encoded_words(header: &str) -> String {
+    let mut decoded = String::new();
+    let mut rest = header;
+    // whitespace between adjacent encoded words is not part of the text
+    let mut pending_whitespace = String::new();
+    let mut previous_encoded = false;
+    while !rest.is_empty() {
+        let whitespace_len = rest.len() - rest.trim_start().len();
+        if whitespace_len > 0 {
+            pending_whitespace.push_str(&rest[..whitespace_len]);
+            rest = &rest[whitespace_len..];
+            continue;
+        }
+        let word_len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let word = &rest[..word_len];
+        rest = &rest[word_len..];
+        match decode_encoded_word(word) {
+            Some(text) => {
+                if !previous_encoded {
+                    decoded.push_str(&pending_whitespace);
+                }
+                decoded.push_str(&text);
+                previous_encoded = true;
+            }
+            None => {
+                decoded.push_str(&pending_whitespace);
+                decoded.push_str(word);
+                previous_encoded = false;
+            }
+        }
+        pending_whitespace.clear();
+    }
+    decoded.push_str(&pending_whitespace);
+    decoded
+}
+
+fn decode_encoded_word(word: &str) -> Option<String> {
+    if !word.starts_with("=?") || !word.ends_with("?=") || word.len() < 4 {
+        return None;
+    }
+    let mut parts = word[2..word.len() - 2].splitn(3, '?');
+    // RFC 2231 allows a language after the charset, e.g. `UTF-8*ja`
+    let charset = parts.next()?.split('*').next()?;
+    let encoding = parts.next()?;
+    let text = parts.next()?;
+    let bytes = match encoding {
+        "B" | "b" => base64::decode(text).ok()?,
+        "Q" | "q" => decode_q(text)?,
+        _ => return None,
+    };
+    Some(decode(&bytes, Some(charset)))
+}
+
+/// The "Q" encoding, a variant of quoted-printable where `_` is a space.
+fn decode_q(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = vec![];
+    let mut chars = text.bytes();
+    while let Some(b) = chars.next() {
+        match b {
+            b'_' => bytes.push(b' '),
+            b'=' => {
+                let hex = [chars.next()?, chars.next()?];
+                bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+            }
+            b => bytes.push(b),
+        }
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode(b"caf\xe9", Some("ISO-8859-1")), "café");
+        assert_eq!(
+            decode(b"\x93\xfa\x96\x7b\x8c\xea", Some("Shift_JIS")),
+            "日本語"
+        );
+        assert_eq!(decode("日本語".as_bytes(), None), "日本語");
+        assert_eq!(decode(b"caf\xe9", None), "caf\u{fffd}");
+        assert_eq!(decode("שלום".as_bytes(), Some("unknown")), "שלום");
+    }
+
+    #[test]
+    fn test_truncate_graphemes() {
+        assert_eq!(truncate_graphemes("short", 10), "short");
+        assert_eq!(truncate_graphemes("Fix the build", 8), "Fix the…");
+        assert_eq!(truncate_graphemes("日本語のコミット", 4), "日本語…");
+        // combining marks stay with their base character
+        assert_eq!(
+            truncate_graphemes("e\u{301}e\u{301}e\u{301}", 2),
+            "e\u{301}…"
+        );
+        // as do joined emoji and flags
+        let family = "👩\u{200d}👩\u{200d}👧";
+        assert_eq!(
+            truncate_graphemes(&format!("{}{}🇯🇵🇰🇷", family, family), 3),
+            format!("{}{}…", family, family)
+        );
+        assert_eq!(truncate_graphemes("🇯🇵🇰🇷🇫🇷", 2), "🇯🇵…");
+        assert_eq!(truncate_graphemes("אבגדה", 3), "אב…");
+        // hangul jamo compose into one syllable
+        assert_eq!(
+            truncate_graphemes("\u{1100}\u{1161}\u{11a8}xyz", 2),
+            "\u{1100}\u{1161}\u{11a8}…"
+        );
+        assert_eq!(truncate_graphemes("anything", 0), "");
+    }
+
+    #[test]
+    fn test_decode_encoded_words() {
+        assert_eq!(
+            decode_encoded_words("=?UTF-8?B?5bGx55Sw5aSq6YOO?= <taro@example.jp>"),
+            "山田太郎 <taro@example.jp>"
+        );
+        assert_eq!(
+            decode_encoded_words("[PATCH] =?ISO-8859-1?Q?Caf=E9_menu?= =?ISO-8859-1?Q?_fix?="),
+            "[PATCH] Café menu fix"
+        );
+        assert_eq!(decode_encoded_words("Plain subject"), "Plain subject");
+        assert_eq!(decode_encoded_words("=?bogus"), "=?bogus");
+    }
+}
@@ -1,8 +1,9 @@
 use chrono::prelude::*;
 use chrono::DateTime;
-use git2::Time;
+use git2::{Commit, DiffOptions, Repository, Time};
 
 use crate::error::*;
+use crate::offline::ensure_online;
 use std::env;
 
 type CargoResult<T> = Result<T>;
@@ -41,6 +42,7 @@ pub fn with_authentication<T, F>(url: &str, cfg: &git2::Config, mut f: F) -> Car
 where
     F: FnMut(&mut git2::Credentials) -> CargoResult<T>,
 {
+    ensure_online(&format!("fetching {}", url))?;
     let mut cred_helper = git2::CredentialHelper::new(url);
     cred_helper.config(cfg);
 
@@ -220,7 +222,32 @@ where
     Ok(res)
 }
 
+/// Lowercase hexadecimal encoding of `bytes`, e.g. of a digest.
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
 pub fn convert_time(time: &Time) -> DateTime<Utc> {
     let tz = FixedOffset::east(time.offset_minutes() * 60);
     tz.timestamp(time.seconds(), 0).with_timezone(&Utc)
 }
+
+/// Paths changed by `commit` relative to its first parent.
+pub fn changed_paths(repo: &Repository, commit: &Commit) -> Result<Vec<String>> {
+    let parent_tree = commit.parent(0).ok().and_then(|p| p.tree().ok());
+    let diff = repo.diff_tree_to_tree(
+        parent_tree.as_ref(),
+        Some(&commit.tree()?),
+        Some(&mut DiffOptions::new()),
+    )?;
+    Ok(diff
+        .deltas()
+        .flat_map(|delta| vec![delta.old_file().path(), delta.new_file().path()])
+        .filter_map(|path| path.and_then(|path| path.to_str()).map(str::to_owned))
+        .fold(vec![], |mut paths, path| {
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+            paths
+        }))
+}
@@ -0,0 +1,145 @@
+use crate::identity::Identity;
+
+/// Files of package managers by name, along with the tool generating them
+/// and whether they are lockfiles rather than manifests.
+const DEPENDENCY_FILES: &[(&str, &str, bool)] = &[
+    ("Cargo.lock", "cargo", true),
+    ("Cargo.toml", "cargo", false),
+    ("package-lock.json", "npm", true),
+    ("npm-shrinkwrap.json", "npm", true),
+    ("package.json", "npm", false),
+    ("yarn.lock", "yarn", true),
+    ("pnpm-lock.yaml", "pnpm", true),
+    ("go.sum", "go", true),
+    ("go.mod", "go", false),
+    ("Gemfile.lock", "bundler", true),
+    ("Gemfile", "bundler", false),
+    ("poetry.lock", "poetry", true),
+    ("pyproject.toml", "poetry", false),
+    ("Pipfile.lock", "pipenv", true),
+    ("Pipfile", "pipenv", false),
+    ("composer.lock", "composer", true),
+    ("composer.json", "composer", false),
+    ("mix.lock", "mix", true),
+    ("pubspec.lock", "pub", true),
+    ("Podfile.lock", "cocoapods", true),
+    ("packages.lock.json", "nuget", true),
+    ("gradle.lockfile", "gradle", true),
+    ("flake.lock", "nix", true),
+];
+
+/// Bots updating dependencies, by a part of the name or email of the
+/// identities they commit as.
+const DEPENDENCY_BOTS: &[(&str, &str)] = &[
+    ("dependabot", "dependabot"),
+    ("renovate", "renovate"),
+    ("greenkeeper", "greenkeeper"),
+    ("snyk-bot", "snyk"),
+    ("pyup-bot", "pyup"),
+];
+
+/// The kind of work a commit is, so that e.g. dependency updates can be
+/// filtered out of throughput metrics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkType {
+    /// Changes only lockfiles and manifests of package managers, at least
+    /// one of them a lockfile.
+    DependencyUpdate,
+    BugFix,
+    Refactor,
+    #[default]
+    Other,
+}
+
+/// A commit changing only the lockfiles and manifests of package managers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DependencyUpdate {
+    /// The bot which made the update, or else the package manager of the
+    /// lockfiles changed if there is only one.
+    pub tool: Option<String>,
+}
+
+/// Detects whether a commit by `author` changing `paths` only updates
+/// dependencies.
+pub fn detect_dependency_update(paths: &[String], author: &Identity) -> Option<DependencyUpdate> {
+    let files: Vec<&(&str, &str, bool)> = paths
+        .iter()
+        .map(|path| {
+            let name = path.rsplit('/').next().unwrap_or(path);
+            DEPENDENCY_FILES.iter().find(|(file, _, _)| *file == name)
+        })
+        .collect::<Option<_>>()?;
+    let mut package_managers: Vec<&str> = files
+        .iter()
+        .filter(|(_, _, lockfile)| *lockfile)
+        .map(|(_, tool, _)| *tool)
+        .collect();
+    if package_managers.is_empty() {
+        return None;
+    }
+    package_managers.sort_unstable();
+    package_managers.dedup();
+    let identity = format!(
+        "{} {}",
+        author.name.as_deref().unwrap_or_default(),
+        author.email.as_deref().unwrap_or_default()
+    )
+    .to_lowercase();
+    let bot = DEPENDENCY_BOTS
+        .iter()
+        .find(|(part, _)| identity.contains(part))
+        .map(|(_, bot)| *bot);
+    let tool = match (bot, package_managers.as_slice()) {
+        (Some(bot), _) => Some(bot.to_owned()),
+        (None, [package_manager]) => Some(package_manager.to_string()),
+        (None, _) => None,
+    };
+    Some(DependencyUpdate { tool })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(paths: &[&str]) -> Vec<String> {
+        paths.iter().map(|path| path.to_string()).collect()
+    }
+
+    #[test]
+    fn test_detect_dependency_update() {
+        let dev = Identity {
+            name: Some("Alice".to_owned()),
+            email: Some("alice@example.com".to_owned()),
+        };
+        let dependabot = Identity {
+            name: Some("dependabot[bot]".to_owned()),
+            email: Some("49699333+dependabot[bot]@users.noreply.github.com".to_owned()),
+        };
+        assert_eq!(
+            detect_dependency_update(&paths(&["Cargo.toml", "Cargo.lock"]), &dev),
+            Some(DependencyUpdate {
+                tool: Some("cargo".to_owned())
+            })
+        );
+        assert_eq!(
+            detect_dependency_update(&paths(&["web/package.json", "web/yarn.lock"]), &dependabot),
+            Some(DependencyUpdate {
+                tool: Some("dependabot".to_owned())
+            })
+        );
+        assert_eq!(
+            detect_dependency_update(&paths(&["go.sum", "web/package-lock.json"]), &dev),
+            Some(DependencyUpdate { tool: None })
+        );
+        assert_eq!(
+            detect_dependency_update(&paths(&["package.json"]), &dev),
+            None
+        );
+        assert_eq!(
+            detect_dependency_update(&paths(&["Cargo.lock", "src/main.rs"]), &dependabot),
+            None
+        );
+        assert_eq!(detect_dependency_update(&[], &dev), None);
+    }
+}
@@ -74,6 +74,20 @@ impl Workspace {
         Ok(self.config.add(repo_info, path))
     }
 
+    /// Adds the discovered repos which are not configured yet, returning how
+    /// many were added.
+    pub fn add_discovered(&mut self, entries: Vec<RepoEntry>) -> usize {
+        entries
+            .into_iter()
+            .filter(|entry| self.config.add_discovered(entry.clone()))
+            .count()
+    }
+
+    /// Github organizations whose repos are discovered on each run.
+    pub fn github_orgs(&self) -> &[String] {
+        &self.config.github_orgs
+    }
+
     pub fn get_repo(self, name: &str) -> Option<Repo> {
         self.config.get_entry(name).map(|entry| self.repo(entry))
     }
@@ -82,6 +96,10 @@ impl Workspace {
         self.config.config.merge(config);
     }
 
+    pub fn config(&self) -> &Config {
+        &self.config.config
+    }
+
     pub fn dir(&self) -> &Path {
         &self.dir
     }
@@ -89,6 +107,30 @@ impl Workspace {
     pub fn path(&self) -> &Path {
         &self.dir
     }
+
+    /// Where the `RepoLock` of `repo` is taken.
+    pub fn lock_path(&self, repo: &Repo) -> PathBuf {
+        self.dir
+            .join("locks")
+            .join(format!("{}.lock", repo.repo_info().name))
+    }
+
+    /// Where the `AnalysisState` of the workspace is saved.
+    pub fn state_path(&self) -> PathBuf {
+        self.dir.join("state.json")
+    }
+
+    /// Where the lock is taken while the `AnalysisState` of the workspace is
+    /// updated.
+    pub fn state_lock_path(&self) -> PathBuf {
+        self.dir.join("locks").join("state.lock")
+    }
+
+    /// Where the events which could not be indexed are spooled by runs not
+    /// given a spool directory of their own, to be replayed by the next run.
+    pub fn spool_dir(&self) -> PathBuf {
+        self.dir.join("spool")
+    }
 }
 
 #[cfg(test)]
@@ -103,4 +145,26 @@ mod tests {
         );
         Ok(())
     }
+
+    #[test]
+    fn test_add_discovered() -> Result<()> {
+        let mut workspace =
+            Workspace::from_dir(Path::new("./fixtures/workspaces/serde-rs").to_owned())?;
+        let configured = workspace.repos().len();
+        let existing = workspace.config.repos[0].clone();
+        let discovered = RepoEntry {
+            repo_info: RepoInfo {
+                name: "serde-rs/new-repo".to_owned(),
+                ..Default::default()
+            },
+            ignore: false,
+            path: Some("serde-rs/new-repo.git".to_owned()),
+        };
+        let mut renamed = existing.clone();
+        renamed.ignore = true;
+        assert_eq!(workspace.add_discovered(vec![renamed, discovered]), 1);
+        assert_eq!(workspace.repos().len(), configured + 1);
+        assert_eq!(workspace.config.repos[0], existing);
+        Ok(())
+    }
 }
@@ -12,6 +12,12 @@ pub struct WorkspaceConfig {
 
     #[serde(default)]
     pub repos: Vec<RepoEntry>,
+
+    /// Github organizations whose repos are discovered on each run, in
+    /// addition to `repos`, so that new repos are picked up without editing
+    /// the config.
+    #[serde(default)]
+    pub github_orgs: Vec<String>,
 }
 
 impl WorkspaceConfig {
@@ -52,7 +58,7 @@ impl WorkspaceConfig {
         match self
             .repos
             .iter_mut()
-            .find(|ref entry| entry.repo_info.name == entry.repo_info.name)
+            .find(|existing| existing.repo_info.name == entry.repo_info.name)
         {
             Some(e) => {
                 *e = entry;
@@ -63,6 +69,17 @@ impl WorkspaceConfig {
         }
     }
 
+    /// Adds a discovered repo unless a repo with the same name is already
+    /// configured, so that entries of the config, e.g. ignored repos, win.
+    /// Returns whether the repo was added.
+    pub fn add_discovered(&mut self, entry: RepoEntry) -> bool {
+        if self.get_entry(&entry.repo_info.name).is_some() {
+            return false;
+        }
+        self.repos.push(entry);
+        true
+    }
+
     pub fn get_entry(&self, name: &str) -> Option<&RepoEntry> {
         self.repos
             .iter()
@@ -71,6 +88,7 @@ impl WorkspaceConfig {
 
     pub fn merge(&mut self, other: WorkspaceConfig) {
         self.repos.extend(other.repos);
+        self.github_orgs.extend(other.github_orgs);
         self.config.merge(other.config);
     }
 }
@@ -80,6 +98,7 @@ impl Default for WorkspaceConfig {
         WorkspaceConfig {
             config: Config::default(),
             repos: vec![],
+            github_orgs: vec![],
         }
     }
 }